@@ -0,0 +1,24 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(set = 0, binding = 0) uniform sampler2D bloom;
+layout(set = 0, binding = 1, rgba16f) uniform image2D scene_image;
+
+layout(push_constant) uniform BloomSettings {
+    float threshold;
+    float intensity;
+} settings;
+
+void main() {
+    ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+    ivec2 size = imageSize(scene_image);
+    if (pixel.x >= size.x || pixel.y >= size.y) {
+        return;
+    }
+
+    vec2 uv = (vec2(pixel) + 0.5) / vec2(size);
+    vec4 scene = imageLoad(scene_image, pixel);
+    vec3 glow = textureLod(bloom, uv, 0.0).rgb * settings.intensity;
+    imageStore(scene_image, pixel, vec4(scene.rgb + glow, scene.a));
+}
@@ -0,0 +1,25 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(set = 0, binding = 0) uniform sampler2D source;
+layout(set = 0, binding = 1, rgba16f) uniform writeonly image2D destination;
+
+void main() {
+    ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+    ivec2 size = imageSize(destination);
+    if (pixel.x >= size.x || pixel.y >= size.y) {
+        return;
+    }
+
+    // Dual filter down-sample: the center plus four diagonal bilinear taps
+    vec2 uv = (vec2(pixel) + 0.5) / vec2(size);
+    vec2 half_texel = 0.5 / vec2(textureSize(source, 0));
+    vec3 color = textureLod(source, uv, 0.0).rgb * 4.0;
+    color += textureLod(source, uv - half_texel, 0.0).rgb;
+    color += textureLod(source, uv + half_texel, 0.0).rgb;
+    color += textureLod(source, uv + vec2(half_texel.x, -half_texel.y), 0.0).rgb;
+    color += textureLod(source, uv + vec2(-half_texel.x, half_texel.y), 0.0).rgb;
+
+    imageStore(destination, pixel, vec4(color / 8.0, 1.0));
+}
@@ -0,0 +1,34 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(set = 0, binding = 0, rgba16f) uniform readonly image2D scene_image;
+layout(set = 0, binding = 1, rgba16f) uniform writeonly image2D bright_image;
+
+layout(push_constant) uniform BloomSettings {
+    float threshold;
+    float intensity;
+} settings;
+
+void main() {
+    ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+    ivec2 size = imageSize(bright_image);
+    if (pixel.x >= size.x || pixel.y >= size.y) {
+        return;
+    }
+
+    // Average the 2x2 block of the full resolution scene under this half resolution pixel
+    ivec2 scene_max = imageSize(scene_image) - 1;
+    vec3 color = vec3(0.0);
+    for (int x = 0; x < 2; x++) {
+        for (int y = 0; y < 2; y++) {
+            color += imageLoad(scene_image, min(pixel * 2 + ivec2(x, y), scene_max)).rgb;
+        }
+    }
+    color *= 0.25;
+
+    // Keep only the part of the color above the threshold
+    float luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+    float contribution = max(luminance - settings.threshold, 0.0) / max(luminance, 0.0001);
+    imageStore(bright_image, pixel, vec4(color * contribution, 1.0));
+}
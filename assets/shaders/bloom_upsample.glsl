@@ -0,0 +1,30 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(set = 0, binding = 0) uniform sampler2D source;
+layout(set = 0, binding = 1, rgba16f) uniform image2D destination;
+
+void main() {
+    ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+    ivec2 size = imageSize(destination);
+    if (pixel.x >= size.x || pixel.y >= size.y) {
+        return;
+    }
+
+    // Dual filter up-sample: a ring of eight taps from the smaller level
+    vec2 uv = (vec2(pixel) + 0.5) / vec2(size);
+    vec2 half_texel = 0.5 / vec2(textureSize(source, 0));
+    vec3 color = textureLod(source, uv + vec2(-half_texel.x * 2.0, 0.0), 0.0).rgb;
+    color += textureLod(source, uv + vec2(-half_texel.x, half_texel.y), 0.0).rgb * 2.0;
+    color += textureLod(source, uv + vec2(0.0, half_texel.y * 2.0), 0.0).rgb;
+    color += textureLod(source, uv + vec2(half_texel.x, half_texel.y), 0.0).rgb * 2.0;
+    color += textureLod(source, uv + vec2(half_texel.x * 2.0, 0.0), 0.0).rgb;
+    color += textureLod(source, uv + vec2(half_texel.x, -half_texel.y), 0.0).rgb * 2.0;
+    color += textureLod(source, uv + vec2(0.0, -half_texel.y * 2.0), 0.0).rgb;
+    color += textureLod(source, uv + vec2(-half_texel.x, -half_texel.y), 0.0).rgb * 2.0;
+
+    // Accumulate on top of what the down-sample left at this level
+    vec3 existing = imageLoad(destination, pixel).rgb;
+    imageStore(destination, pixel, vec4(existing + color / 12.0, 1.0));
+}
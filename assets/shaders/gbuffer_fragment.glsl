@@ -0,0 +1,17 @@
+#version 450
+
+layout(location = 0) in vec3 in_normal;
+layout(location = 1) in vec2 in_tex_coords;
+layout(location = 2) in vec3 in_world_pos;
+
+layout(location = 0) out vec4 out_position;
+layout(location = 1) out vec4 out_normal;
+layout(location = 2) out vec4 out_albedo;
+
+void main() {
+    out_position = vec4(in_world_pos, 1.0);
+    out_normal = vec4(normalize(in_normal), 0.0);
+
+    // Grass color until materials are passed in
+    out_albedo = vec4(0.2, 0.6, 0.2, 1.0);
+}
@@ -0,0 +1,50 @@
+#version 450
+
+layout(local_size_x = 64) in;
+
+struct Object {
+    vec4 bounding_sphere;
+    mat4 model;
+};
+
+layout(set = 0, binding = 0) uniform CullUniforms {
+    mat4 view;
+    mat4 proj;
+    // Inward facing, xyz normal and w distance
+    vec4 frustum_planes[6];
+    uint object_count;
+} ubo;
+
+layout(std430, set = 0, binding = 1) readonly buffer Objects {
+    Object objects[];
+};
+
+layout(std430, set = 0, binding = 2) writeonly buffer Visible {
+    uint visible[];
+};
+
+// VkDrawIndexedIndirectCommand; instance_count doubles as the visible counter
+layout(std430, set = 0, binding = 3) buffer DrawCommand {
+    uint index_count;
+    uint instance_count;
+    uint first_index;
+    int vertex_offset;
+    uint first_instance;
+} draw_command;
+
+void main() {
+    uint index = gl_GlobalInvocationID.x;
+    if (index >= ubo.object_count) {
+        return;
+    }
+
+    vec4 sphere = objects[index].bounding_sphere;
+    for (int i = 0; i < 6; i++) {
+        if (dot(ubo.frustum_planes[i].xyz, sphere.xyz) + ubo.frustum_planes[i].w < -sphere.w) {
+            return;
+        }
+    }
+
+    uint slot = atomicAdd(draw_command.instance_count, 1);
+    visible[slot] = index;
+}
@@ -0,0 +1,31 @@
+#version 450
+
+layout(input_attachment_index = 0, set = 0, binding = 0) uniform subpassInput g_position;
+layout(input_attachment_index = 1, set = 0, binding = 1) uniform subpassInput g_normal;
+layout(input_attachment_index = 2, set = 0, binding = 2) uniform subpassInput g_albedo;
+layout(set = 0, binding = 3) uniform sampler2D ambient_occlusion;
+// rgb is in-scattered light, alpha how much of the surface shows through
+layout(set = 0, binding = 4) uniform sampler2D volumetric_fog;
+
+// Blended over the sky as rgb + sky * a, so alpha is how much of the sky shows through
+layout(location = 0) out vec4 out_color;
+
+void main() {
+    vec3 normal = subpassLoad(g_normal).xyz;
+    vec3 albedo = subpassLoad(g_albedo).rgb;
+
+    vec4 fog = texelFetch(volumetric_fog, ivec2(gl_FragCoord.xy), 0);
+
+    // Nothing was drawn here in the geometry pass, the sky shows through the fog
+    if (subpassLoad(g_position).w == 0.0) {
+        out_color = fog;
+        return;
+    }
+
+    vec3 light_dir = normalize(vec3(1.0, 1.0, 1.0));
+    float diff = max(dot(normal, light_dir), 0.0);
+    // SSAO only darkens the ambient term
+    float occlusion = texelFetch(ambient_occlusion, ivec2(gl_FragCoord.xy), 0).r;
+    vec3 color = (vec3(0.2) * occlusion + diff * vec3(0.8)) * albedo;
+    out_color = vec4(color * fog.a + fog.rgb, 0.0);
+}
@@ -0,0 +1,69 @@
+#version 460
+#extension GL_EXT_ray_tracing : require
+
+layout(set = 0, binding = 0) uniform accelerationStructureEXT scene;
+layout(set = 0, binding = 1) uniform sampler2D g_position;
+layout(set = 0, binding = 2) uniform sampler2D g_normal;
+layout(set = 0, binding = 3, r32f) uniform writeonly image2D occlusion_image;
+
+layout(push_constant) uniform RayTracingConstants {
+    uint frame;
+} constants;
+
+// 1.0 when the ray escapes, 0.0 when it hits something
+layout(location = 0) rayPayloadEXT float visibility;
+
+const uint RAY_COUNT = 8;
+const float RAY_LENGTH = 1.5;
+const float RAY_OFFSET = 0.01;
+const float PI = 3.14159265;
+
+// PCG hash, seeded per pixel and frame so the blur pass averages out the noise
+uint hash(uint value) {
+    uint state = value * 747796405u + 2891336453u;
+    uint word = ((state >> ((state >> 28u) + 4u)) ^ state) * 277803737u;
+    return (word >> 22u) ^ word;
+}
+
+float random(inout uint seed) {
+    seed = hash(seed);
+    return float(seed) / 4294967295.0;
+}
+
+void main() {
+    ivec2 pixel = ivec2(gl_LaunchIDEXT.xy);
+    vec4 world_position = texelFetch(g_position, pixel, 0);
+    // Nothing was drawn here, leave it unoccluded
+    if (world_position.w == 0.0) {
+        imageStore(occlusion_image, pixel, vec4(1.0));
+        return;
+    }
+
+    vec3 normal = normalize(texelFetch(g_normal, pixel, 0).xyz);
+    vec3 helper = abs(normal.y) < 0.99 ? vec3(0.0, 1.0, 0.0) : vec3(1.0, 0.0, 0.0);
+    vec3 tangent = normalize(cross(helper, normal));
+    vec3 bitangent = cross(normal, tangent);
+    vec3 origin = world_position.xyz + normal * RAY_OFFSET;
+
+    uint seed = hash(uint(pixel.x) + uint(pixel.y) * gl_LaunchSizeEXT.x) ^ hash(constants.frame);
+    float unoccluded = 0.0;
+    for (uint i = 0; i < RAY_COUNT; i++) {
+        // Cosine weighted direction in the hemisphere around the normal
+        float u = random(seed);
+        float phi = 2.0 * PI * random(seed);
+        vec3 local = vec3(cos(phi) * sqrt(u), sin(phi) * sqrt(u), sqrt(1.0 - u));
+        vec3 direction = tangent * local.x + bitangent * local.y + normal * local.z;
+
+        visibility = 0.0;
+        traceRayEXT(
+            scene,
+            gl_RayFlagsOpaqueEXT | gl_RayFlagsTerminateOnFirstHitEXT,
+            0xFF, 0, 0, 0,
+            origin, 0.0, direction, RAY_LENGTH,
+            0
+        );
+        unoccluded += visibility;
+    }
+
+    imageStore(occlusion_image, pixel, vec4(unoccluded / float(RAY_COUNT)));
+}
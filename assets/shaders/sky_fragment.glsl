@@ -0,0 +1,65 @@
+#version 450
+
+// Preetham et al. 1999 analytic daylight model. The distribution coefficients and the zenith
+// color are computed on the CPU, see SkyUniforms in vulkan_renderer.rs.
+layout(set = 0, binding = 0) uniform SkyUniforms {
+    mat4 inverse_view_projection;
+    vec4 camera_position;
+    // Points towards the sun
+    vec4 sun_direction;
+    // Perez coefficients A-E for Y, x and y in xyz
+    vec4 perez_a;
+    vec4 perez_b;
+    vec4 perez_c;
+    vec4 perez_d;
+    vec4 perez_e;
+    // Zenith Yxy divided by the distribution at the zenith, scaled to scene units
+    vec4 zenith;
+    // rgb ground albedo, w how much of the day sky shows (0 at night)
+    vec4 ground_albedo;
+} sky;
+
+layout(location = 0) in vec2 ndc;
+
+layout(location = 0) out vec4 out_color;
+
+const vec3 NIGHT_COLOR = vec3(0.002, 0.003, 0.008);
+// cos of the sun disc's angular radius, drawn several times wider than the real sun
+const float SUN_DISC_COS = 0.9995;
+const float SUN_DISC_INTENSITY = 20.0;
+
+vec3 perez(float cos_theta, float gamma, float cos_gamma) {
+    return (1.0 + sky.perez_a.xyz * exp(sky.perez_b.xyz / max(cos_theta, 0.01)))
+        * (1.0 + sky.perez_c.xyz * exp(sky.perez_d.xyz * gamma) + sky.perez_e.xyz * cos_gamma * cos_gamma);
+}
+
+vec3 yxy_to_rgb(vec3 yxy) {
+    float Y = yxy.x;
+    vec3 xyz = vec3(yxy.y * Y / yxy.z, Y, (1.0 - yxy.y - yxy.z) * Y / yxy.z);
+    return max(mat3(
+        3.2406, -0.9689, 0.0557,
+        -1.5372, 1.8758, -0.2040,
+        -0.4986, 0.0415, 1.0570
+    ) * xyz, vec3(0.0));
+}
+
+vec3 sky_color(vec3 direction) {
+    // Below the horizon, show the horizon light bounced off the ground
+    float cos_theta = max(direction.y, 0.0);
+    float cos_gamma = clamp(dot(direction, sky.sun_direction.xyz), -1.0, 1.0);
+    float gamma = acos(cos_gamma);
+    vec3 color = yxy_to_rgb(sky.zenith.xyz * perez(cos_theta, gamma, cos_gamma));
+    if (direction.y < 0.0) {
+        color *= sky.ground_albedo.rgb;
+    } else if (cos_gamma > SUN_DISC_COS) {
+        color += SUN_DISC_INTENSITY * color;
+    }
+    return mix(NIGHT_COLOR, color, sky.ground_albedo.w);
+}
+
+void main() {
+    // Unproject a point on the near plane (reverse-Z, so depth 1) to get the view ray
+    vec4 near_point = sky.inverse_view_projection * vec4(ndc, 1.0, 1.0);
+    vec3 direction = normalize(near_point.xyz / near_point.w - sky.camera_position.xyz);
+    out_color = vec4(sky_color(direction), 1.0);
+}
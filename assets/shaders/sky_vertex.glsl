@@ -0,0 +1,11 @@
+#version 450
+
+// Bevy's y-up clip space position of the pixel, for unprojecting view rays
+layout(location = 0) out vec2 ndc;
+
+// Fullscreen triangle like the lighting pass
+void main() {
+    vec2 uv = vec2((gl_VertexIndex << 1) & 2, gl_VertexIndex & 2);
+    gl_Position = vec4(uv * 2.0 - 1.0, 0.0, 1.0);
+    ndc = vec2(gl_Position.x, -gl_Position.y);
+}
@@ -0,0 +1,25 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(set = 0, binding = 0, r32f) uniform readonly image2D occlusion_image;
+layout(set = 0, binding = 1, r32f) uniform writeonly image2D blurred_image;
+
+void main() {
+    ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+    ivec2 size = imageSize(blurred_image);
+    if (pixel.x >= size.x || pixel.y >= size.y) {
+        return;
+    }
+
+    // 4x4 box blur matching the noise tile size
+    float result = 0.0;
+    for (int x = -2; x < 2; x++) {
+        for (int y = -2; y < 2; y++) {
+            ivec2 sample_pixel = clamp(pixel + ivec2(x, y), ivec2(0), size - 1);
+            result += imageLoad(occlusion_image, sample_pixel).r;
+        }
+    }
+
+    imageStore(blurred_image, pixel, vec4(result / 16.0));
+}
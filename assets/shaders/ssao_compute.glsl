@@ -0,0 +1,59 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(set = 0, binding = 0) uniform sampler2D g_position;
+layout(set = 0, binding = 1) uniform sampler2D g_normal;
+layout(set = 0, binding = 2) uniform SsaoUniforms {
+    mat4 view;
+    mat4 projection;
+    vec4 kernel[64];
+    vec4 noise[16];
+} ssao;
+layout(set = 0, binding = 3, r32f) uniform writeonly image2D occlusion_image;
+
+const float RADIUS = 0.5;
+const float BIAS = 0.025;
+
+void main() {
+    ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+    ivec2 size = imageSize(occlusion_image);
+    if (pixel.x >= size.x || pixel.y >= size.y) {
+        return;
+    }
+
+    vec4 world_position = texelFetch(g_position, pixel, 0);
+    // Nothing was drawn here, leave it unoccluded
+    if (world_position.w == 0.0) {
+        imageStore(occlusion_image, pixel, vec4(1.0));
+        return;
+    }
+
+    vec3 position = (ssao.view * vec4(world_position.xyz, 1.0)).xyz;
+    vec3 normal = normalize(mat3(ssao.view) * texelFetch(g_normal, pixel, 0).xyz);
+
+    // Tile the 4x4 noise across the screen to rotate the kernel per pixel
+    ivec2 noise_pixel = pixel % 4;
+    vec3 random_vec = ssao.noise[noise_pixel.y * 4 + noise_pixel.x].xyz;
+    vec3 tangent = normalize(random_vec - normal * dot(random_vec, normal));
+    vec3 bitangent = cross(normal, tangent);
+    mat3 tbn = mat3(tangent, bitangent, normal);
+
+    float occlusion = 0.0;
+    for (int i = 0; i < 64; i++) {
+        vec3 sample_position = position + tbn * ssao.kernel[i].xyz * RADIUS;
+
+        vec4 offset = ssao.projection * vec4(sample_position, 1.0);
+        offset.xy /= offset.w;
+        // Bevy's projection is y-up, Vulkan images are y-down
+        vec2 uv = vec2(offset.x, -offset.y) * 0.5 + 0.5;
+        ivec2 sample_pixel = clamp(ivec2(uv * vec2(size)), ivec2(0), size - 1);
+
+        vec4 sample_world = texelFetch(g_position, sample_pixel, 0);
+        float sample_depth = (ssao.view * vec4(sample_world.xyz, 1.0)).z;
+        float range_check = smoothstep(0.0, 1.0, RADIUS / abs(position.z - sample_depth));
+        occlusion += (sample_depth >= sample_position.z + BIAS ? 1.0 : 0.0) * range_check;
+    }
+
+    imageStore(occlusion_image, pixel, vec4(1.0 - occlusion / 64.0));
+}
@@ -0,0 +1,73 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(set = 0, binding = 0, rgba16f) uniform readonly image2D scene_image;
+layout(set = 0, binding = 1, rgba8) uniform writeonly image2D output_image;
+layout(set = 0, binding = 2) uniform sampler3D lut_sampler3D;
+
+layout(push_constant) uniform ToneMapSettings {
+    float exposure;
+    // 0 = ACES, 1 = Reinhard, 2 = Uncharted 2
+    uint mode;
+    // 0 = identity, 1 = fully graded
+    float lut_blend;
+    // 0 = untouched, 1 = greyscale
+    float desaturation;
+    // Darkening at the corners, 0 = none
+    float vignette;
+} settings;
+
+vec3 aces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+vec3 reinhard(vec3 x) {
+    return x / (1.0 + x);
+}
+
+vec3 uncharted2_curve(vec3 x) {
+    const float A = 0.15;
+    const float B = 0.50;
+    const float C = 0.10;
+    const float D = 0.20;
+    const float E = 0.02;
+    const float F = 0.30;
+    return ((x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F)) - E / F;
+}
+
+vec3 uncharted2(vec3 x) {
+    const float EXPOSURE_BIAS = 2.0;
+    const vec3 WHITE_POINT = vec3(11.2);
+    return uncharted2_curve(x * EXPOSURE_BIAS) / uncharted2_curve(WHITE_POINT);
+}
+
+void main() {
+    ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+    ivec2 size = imageSize(output_image);
+    if (pixel.x >= size.x || pixel.y >= size.y) {
+        return;
+    }
+
+    vec3 color = imageLoad(scene_image, pixel).rgb * settings.exposure;
+    switch (settings.mode) {
+        case 1u: color = reinhard(color); break;
+        case 2u: color = uncharted2(color); break;
+        default: color = aces(color); break;
+    }
+
+    // The output and swapchain are UNORM, so gamma is applied here
+    color = pow(clamp(color, 0.0, 1.0), vec3(1.0 / 2.2));
+
+    // Color grade in display space; offset so 0 and 1 land on the first and last LUT texel centers
+    float lut_size = float(textureSize(lut_sampler3D, 0).x);
+    vec3 lut_coord = color * ((lut_size - 1.0) / lut_size) + 0.5 / lut_size;
+    vec3 graded = texture(lut_sampler3D, lut_coord).rgb;
+    color = mix(color, graded, settings.lut_blend);
+
+    color = mix(color, vec3(dot(color, vec3(0.2126, 0.7152, 0.0722))), settings.desaturation);
+    // Falls off from the middle, reaching full strength in the corners
+    vec2 from_center = (vec2(pixel) + 0.5) / vec2(size) * 2.0 - 1.0;
+    color *= 1.0 - settings.vignette * smoothstep(0.5, 1.4, length(from_center));
+    imageStore(output_image, pixel, vec4(color, 1.0));
+}
@@ -0,0 +1,65 @@
+#version 450
+
+layout(local_size_x = 8, local_size_y = 8) in;
+
+layout(set = 0, binding = 0) uniform sampler2D g_position;
+layout(set = 0, binding = 1) uniform FogUniforms {
+    mat4 inverse_view_projection;
+    vec4 camera_position;
+    // Points towards the light
+    vec4 light_direction;
+    vec4 light_color;
+    // density, scatter, absorption, anisotropy
+    vec4 params;
+} fog;
+layout(set = 0, binding = 2) uniform sampler3D noise;
+layout(set = 0, binding = 3, rgba16f) uniform writeonly image2D fog_image;
+
+const int STEPS = 32;
+// Rays that hit nothing stop here
+const float MAX_DISTANCE = 100.0;
+// World units per tile of the noise volume
+const float NOISE_SCALE = 1.0 / 24.0;
+const float PI = 3.14159265;
+
+float henyey_greenstein(float cos_theta, float g) {
+    float g2 = g * g;
+    return (1.0 - g2) / (4.0 * PI * pow(1.0 + g2 - 2.0 * g * cos_theta, 1.5));
+}
+
+void main() {
+    ivec2 pixel = ivec2(gl_GlobalInvocationID.xy);
+    ivec2 size = imageSize(fog_image);
+    if (pixel.x >= size.x || pixel.y >= size.y) {
+        return;
+    }
+
+    // Unproject a point on the near plane (reverse-Z, so depth 1) to get the view ray.
+    // Bevy's projection is y-up, Vulkan images are y-down.
+    vec2 uv = (vec2(pixel) + 0.5) / vec2(size);
+    vec4 near_point = fog.inverse_view_projection * vec4(uv.x * 2.0 - 1.0, 1.0 - uv.y * 2.0, 1.0, 1.0);
+    vec3 origin = fog.camera_position.xyz;
+    vec3 direction = normalize(near_point.xyz / near_point.w - origin);
+
+    vec4 surface = texelFetch(g_position, pixel, 0);
+    float ray_length = surface.w == 0.0 ? MAX_DISTANCE : min(distance(surface.xyz, origin), MAX_DISTANCE);
+    float step_length = ray_length / float(STEPS);
+
+    float density = fog.params.x;
+    float scatter = fog.params.y;
+    float absorption = fog.params.z;
+    float phase = henyey_greenstein(dot(direction, fog.light_direction.xyz), fog.params.w);
+
+    vec3 in_scattered = vec3(0.0);
+    float transmittance = 1.0;
+    for (int i = 0; i < STEPS; i++) {
+        vec3 position = origin + direction * (float(i) + 0.5) * step_length;
+        float local_density = density * texture(noise, position * NOISE_SCALE).r;
+        float extinction = local_density * (scatter + absorption);
+
+        in_scattered += transmittance * local_density * scatter * phase * fog.light_color.rgb * step_length;
+        transmittance *= exp(-extinction * step_length);
+    }
+
+    imageStore(fog_image, pixel, vec4(in_scattered, transmittance));
+}
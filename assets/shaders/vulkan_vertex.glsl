@@ -8,15 +8,32 @@ layout(location = 0) out vec3 out_normal;
 layout(location = 1) out vec2 out_tex_coords;
 layout(location = 2) out vec3 out_world_pos;
 
-layout(set = 0, binding = 0) uniform UniformBufferObject {
+struct Object {
+    vec4 bounding_sphere;
     mat4 model;
+};
+
+// Shared with gpu_cull.glsl, which also reads the frustum planes
+layout(set = 0, binding = 0) uniform CullUniforms {
     mat4 view;
     mat4 proj;
+    vec4 frustum_planes[6];
+    uint object_count;
 } ubo;
 
+layout(std430, set = 0, binding = 1) readonly buffer Objects {
+    Object objects[];
+};
+
+// Each instance is one object that survived culling
+layout(std430, set = 0, binding = 2) readonly buffer Visible {
+    uint visible[];
+};
+
 void main() {
-    out_world_pos = vec3(ubo.model * vec4(position, 1.0));
-    out_normal = mat3(ubo.model) * normal;
+    mat4 model = objects[visible[gl_InstanceIndex]].model;
+    out_world_pos = vec3(model * vec4(position, 1.0));
+    out_normal = mat3(model) * normal;
     out_tex_coords = tex_coords;
-    gl_Position = ubo.proj * ubo.view * ubo.model * vec4(position, 1.0);
-} 
\ No newline at end of file
+    gl_Position = ubo.proj * ubo.view * model * vec4(position, 1.0);
+}
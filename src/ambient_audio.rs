@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+use bevy::audio::Volume;
+use std::path::Path;
+use crate::player::Player;
+
+pub struct AmbientAudioPlugin;
+
+impl Plugin for AmbientAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_ambient_sound_zones)
+            .add_systems(Update, (start_ambient_sounds, ambient_audio_system).chain());
+    }
+}
+
+// A looping sound that gets louder as the player approaches. Each zone plays through its own
+// sink, so overlapping zones add up in the mixer.
+#[derive(Component)]
+pub struct AmbientSoundZone {
+    pub audio_handle: Handle<AudioSource>,
+    pub max_volume: f32,
+    // Silent beyond this distance
+    pub falloff_radius: f32,
+    // Full volume within this distance
+    pub inner_radius: f32,
+}
+
+impl AmbientSoundZone {
+    // Linear falloff from max_volume at inner_radius down to silence at falloff_radius
+    pub fn volume_at(&self, distance: f32) -> f32 {
+        if distance <= self.inner_radius {
+            return self.max_volume;
+        }
+        let range = (self.falloff_radius - self.inner_radius).max(f32::EPSILON);
+        self.max_volume * (1.0 - (distance - self.inner_radius) / range).clamp(0.0, 1.0)
+    }
+}
+
+// Sound file (relative to the assets folder), position, max volume, inner and falloff radius
+const AMBIENT_ZONES: [(&str, Vec3, f32, f32, f32); 3] = [
+    // Off the eastern edge of the main island
+    ("audio/waterfall.ogg", Vec3::new(19.0, 0.0, -12.0), 0.8, 4.0, 20.0),
+    // Under the western floating platform
+    ("audio/cave_wind.ogg", Vec3::new(-25.0, 4.0, 0.0), 0.6, 3.0, 15.0),
+    // Between the trees near the spawn
+    ("audio/fire_pit.ogg", Vec3::new(-6.0, 0.5, -6.0), 0.7, 1.5, 10.0),
+];
+
+fn spawn_ambient_sound_zones(mut commands: Commands, asset_server: Res<AssetServer>) {
+    for (path, position, max_volume, inner_radius, falloff_radius) in AMBIENT_ZONES {
+        // Missing sounds would only log load errors every run
+        if !Path::new("assets").join(path).exists() {
+            println!("No ambient sound at {}, skipping its zone", path);
+            continue;
+        }
+        commands.spawn((
+            AmbientSoundZone {
+                audio_handle: asset_server.load(path),
+                max_volume,
+                falloff_radius,
+                inner_radius,
+            },
+            TransformBundle::from_transform(Transform::from_translation(position)),
+        ));
+        println!("Ambient sound zone {} at {:?}", path, position);
+    }
+}
+
+// Starts each new zone's loop silently; ambient_audio_system brings it up to the right volume
+fn start_ambient_sounds(
+    mut commands: Commands,
+    zone_query: Query<(Entity, &AmbientSoundZone), Added<AmbientSoundZone>>,
+) {
+    for (entity, zone) in zone_query.iter() {
+        commands.entity(entity).insert(AudioBundle {
+            source: zone.audio_handle.clone(),
+            settings: PlaybackSettings::LOOP.with_volume(Volume::new_relative(0.0)),
+        });
+    }
+}
+
+fn ambient_audio_system(
+    player_query: Query<&GlobalTransform, With<Player>>,
+    zone_query: Query<(&AmbientSoundZone, &GlobalTransform, &AudioSink)>,
+) {
+    if player_query.is_empty() {
+        return;
+    }
+    // Split-screen shares the speakers, so each zone plays as loud as the closest player hears it
+    for (zone, zone_transform, sink) in zone_query.iter() {
+        let distance = player_query
+            .iter()
+            .map(|player_transform| player_transform.translation().distance(zone_transform.translation()))
+            .fold(f32::INFINITY, f32::min);
+        sink.set_volume(zone.volume_at(distance));
+    }
+}
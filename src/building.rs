@@ -0,0 +1,273 @@
+use bevy::pbr::NotShadowCaster;
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use bevy::window::PrimaryWindow;
+use bevy_rapier3d::prelude::*;
+use std::f32::consts::FRAC_PI_2;
+use crate::camera::ThirdPersonCamera;
+use crate::input_map::{Action, InputMap};
+use crate::occlusion::OcclusionFadeable;
+use crate::player::PlayerIndex;
+use crate::terrain::SurfaceType;
+
+pub struct BuildingPlugin;
+
+impl Plugin for BuildingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<BuildMode>()
+            .add_systems(Startup, spawn_ghost)
+            .add_systems(Update, (toggle_build_mode, update_ghost, place_building).chain());
+    }
+}
+
+// Ghost tints for a spot that's free and one that's blocked
+const GHOST_VALID_COLOR: Color = Color::rgba(0.3, 0.9, 0.4, 0.4);
+const GHOST_BLOCKED_COLOR: Color = Color::rgba(0.9, 0.25, 0.2, 0.4);
+// How far from the camera the cursor can place things
+const PLACEMENT_RANGE: f32 = 60.0;
+// The overlap check shrinks the building by this much, so touching a neighbour isn't overlapping it
+const OVERLAP_MARGIN: f32 = 0.05;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuildingType {
+    Wall,
+    Tower,
+}
+
+impl BuildingType {
+    const ALL: [BuildingType; 2] = [BuildingType::Wall, BuildingType::Tower];
+
+    pub fn half_extents(self) -> Vec3 {
+        match self {
+            BuildingType::Wall => Vec3::new(2.0, 1.5, 0.25),
+            BuildingType::Tower => Vec3::new(1.0, 3.0, 1.0),
+        }
+    }
+
+    pub fn max_health(self) -> f32 {
+        match self {
+            BuildingType::Wall => 200.0,
+            BuildingType::Tower => 400.0,
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            BuildingType::Wall => Color::rgb(0.55, 0.5, 0.45),
+            BuildingType::Tower => Color::rgb(0.45, 0.45, 0.5),
+        }
+    }
+
+    fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|kind| *kind == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+// A placed building. Nothing damages buildings yet, so health only starts at the kind's maximum.
+#[derive(Component, Debug)]
+#[allow(dead_code)]
+pub struct Building {
+    pub health: f32,
+    pub kind: BuildingType,
+}
+
+// Build mode, what it's placing and how it snaps. While it's on, left click places rather than
+// firing.
+#[derive(Resource)]
+pub struct BuildMode {
+    pub build_mode: bool,
+    pub kind: BuildingType,
+    // Buildings snap to a grid of this spacing across the ground
+    pub grid_size: f32,
+    // Quarter turns about world up
+    pub rotation_steps: u8,
+    // Where the ghost is now and whether it can go there, None while the cursor is off the ground
+    placement: Option<(Vec3, bool)>,
+}
+
+impl Default for BuildMode {
+    fn default() -> Self {
+        Self {
+            build_mode: false,
+            kind: BuildingType::Wall,
+            grid_size: 1.0,
+            rotation_steps: 0,
+            placement: None,
+        }
+    }
+}
+
+impl BuildMode {
+    fn rotation(&self) -> Quat {
+        Quat::from_rotation_y(self.rotation_steps as f32 * FRAC_PI_2)
+    }
+}
+
+// The translucent preview of what would be placed
+#[derive(Component)]
+struct BuildingGhost;
+
+fn spawn_ghost(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    commands.spawn((
+        BuildingGhost,
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Cube { size: 1.0 })),
+            material: materials.add(StandardMaterial {
+                base_color: GHOST_VALID_COLOR,
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            }),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        NotShadowCaster,
+    ));
+}
+
+// B enters build mode, and pressed again picks the next kind of building. R turns it a quarter,
+// and the aim button (right click) leaves.
+fn toggle_build_mode(input_map: Res<InputMap>, mut build_mode: ResMut<BuildMode>) {
+    if input_map.just_pressed(Action::ToggleBuildMode) {
+        if build_mode.build_mode {
+            build_mode.kind = build_mode.kind.next();
+        }
+        build_mode.build_mode = true;
+        println!("Build mode: placing {:?}", build_mode.kind);
+    }
+    if !build_mode.build_mode {
+        return;
+    }
+    if input_map.just_pressed(Action::RotateBuilding) {
+        build_mode.rotation_steps = (build_mode.rotation_steps + 1) % 4;
+    }
+    if input_map.just_pressed(Action::Aim) {
+        build_mode.build_mode = false;
+        build_mode.placement = None;
+        println!("Left build mode");
+    }
+}
+
+// Rounds the horizontal position to the nearest grid point
+fn snap_to_grid(position: Vec3, grid_size: f32) -> Vec3 {
+    if grid_size <= 0.0 {
+        return position;
+    }
+    Vec3::new(
+        (position.x / grid_size).round() * grid_size,
+        position.y,
+        (position.z / grid_size).round() * grid_size,
+    )
+}
+
+// Casts from the first player's camera through the cursor, or the middle of the view while the
+// cursor is grabbed, then snaps the hit to the grid and drops it back onto the ground there
+fn update_ghost(
+    mut build_mode: ResMut<BuildMode>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    camera_query: Query<(&PlayerIndex, &Camera, &GlobalTransform), With<ThirdPersonCamera>>,
+    mut ghost_query: Query<(&mut Transform, &mut Visibility, &Handle<StandardMaterial>), With<BuildingGhost>>,
+    rapier_context: Res<RapierContext>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let Ok((mut ghost_transform, mut ghost_visibility, ghost_material)) = ghost_query.get_single_mut() else {
+        return;
+    };
+    build_mode.placement = None;
+    let ray = build_mode.build_mode.then(|| {
+        let (_, camera, camera_transform) = camera_query.iter().find(|(index, _, _)| index.0 == 0)?;
+        let cursor = window_query
+            .get_single()
+            .ok()
+            .and_then(|window| window.cursor_position())
+            .or_else(|| camera.logical_viewport_size().map(|size| size * 0.5))?;
+        camera.viewport_to_world(camera_transform, cursor)
+    }).flatten();
+    let Some(ray) = ray else {
+        *ghost_visibility = Visibility::Hidden;
+        return;
+    };
+
+    let filter = QueryFilter::exclude_dynamic().exclude_sensors();
+    let Some((_, toi)) = rapier_context.cast_ray(ray.origin, ray.direction, PLACEMENT_RANGE, true, filter) else {
+        *ghost_visibility = Visibility::Hidden;
+        return;
+    };
+    // Look for the ground again straight down at the snapped point, so the base sits on the
+    // surface there rather than at the height the cursor happened to hit
+    let snapped = snap_to_grid(ray.get_point(toi), build_mode.grid_size);
+    let probe_height = build_mode.kind.half_extents().y * 2.0 + 1.0;
+    let Some((ground, ground_toi)) = rapier_context.cast_ray(snapped + Vec3::Y * probe_height, Vec3::NEG_Y, probe_height * 2.0, true, filter) else {
+        *ghost_visibility = Visibility::Hidden;
+        return;
+    };
+    let base = snapped + Vec3::Y * (probe_height - ground_toi);
+
+    // Blocked by anything the building would overlap other than the ground it stands on
+    let half_extents = build_mode.kind.half_extents();
+    let rotation = build_mode.rotation();
+    let center = base + Vec3::Y * half_extents.y;
+    let test_extents = (half_extents - Vec3::splat(OVERLAP_MARGIN)).max(Vec3::splat(OVERLAP_MARGIN));
+    let test_shape = Collider::cuboid(test_extents.x, test_extents.y, test_extents.z);
+    let overlap_filter = QueryFilter::default().exclude_sensors().exclude_collider(ground);
+    let valid = rapier_context
+        .intersection_with_shape(center + Vec3::Y * OVERLAP_MARGIN, rotation, &test_shape, overlap_filter)
+        .is_none();
+    build_mode.placement = Some((center, valid));
+
+    *ghost_transform = Transform {
+        translation: center,
+        rotation,
+        scale: half_extents * 2.0,
+    };
+    *ghost_visibility = Visibility::Visible;
+    if let Some(material) = materials.get_mut(ghost_material) {
+        material.base_color = if valid { GHOST_VALID_COLOR } else { GHOST_BLOCKED_COLOR };
+    }
+}
+
+fn place_building(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    input_map: Res<InputMap>,
+    build_mode: Res<BuildMode>,
+) {
+    if !build_mode.build_mode || !input_map.just_pressed(Action::Fire) {
+        return;
+    }
+    let Some((center, valid)) = build_mode.placement else {
+        return;
+    };
+    if !valid {
+        println!("Can't build there, something is in the way");
+        return;
+    }
+    let kind = build_mode.kind;
+    let half_extents = kind.half_extents();
+    commands.spawn((
+        Building {
+            health: kind.max_health(),
+            kind,
+        },
+        RigidBody::Fixed,
+        Collider::cuboid(half_extents.x, half_extents.y, half_extents.z),
+        SurfaceType::Stone,
+        OcclusionFadeable::default(),
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Box::new(half_extents.x * 2.0, half_extents.y * 2.0, half_extents.z * 2.0))),
+            material: materials.add(StandardMaterial {
+                base_color: kind.color(),
+                ..default()
+            }),
+            transform: Transform::from_translation(center).with_rotation(build_mode.rotation()),
+            ..default()
+        },
+    ));
+    println!("Placed {:?} at {:?}", kind, center);
+}
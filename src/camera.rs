@@ -1,141 +1,2130 @@
 use bevy::prelude::*;
-use bevy::input::mouse::{MouseMotion, MouseWheel};
-use crate::player::Player;
+use bevy::render::camera::Viewport;
+use bevy::window::{CursorGrabMode, PrimaryWindow, WindowFocused, WindowResized};
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI, TAU};
+use std::fs;
+use std::path::Path;
+use crate::camera_path::camera_following;
+use crate::debug::{debug_overlay_enabled, DebugLogTimers, Noclip, DEBUG_LOG_TARGET};
+use crate::health::PlayerRespawned;
+use crate::input_map::{Action, InputMap};
+use crate::lock_on::LockOnTarget;
+use crate::player::{Player, PlayerIndex, PlayerLanded, PlayerModel, PlayerSettings};
+use crate::spectator::SpectatorMode;
+
+pub mod math;
 
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, setup_camera)
-            .add_systems(Update, camera_follow)
-            .add_systems(Update, camera_rotation)
-            .add_systems(Update, camera_zoom)
-            .add_systems(Update, debug_camera_state);
+        app.insert_resource(CameraSettings::load(CAMERA_SETTINGS_PATH))
+            .add_event::<CameraShakeEvent>()
+            .add_event::<CameraSnapEvent>()
+            .add_event::<CameraTargetChanged>()
+            .add_systems(Startup, setup_camera)
+            .add_systems(Update, toggle_camera_mode.before(camera_follow))
+            .add_systems(Update, (receive_camera_shake, landing_shake).before(camera_follow))
+            .add_systems(Update, camera_follow.run_if(camera_following).run_if(not_in_photo_mode))
+            .add_systems(Update, (announce_camera_target_changes, log_camera_target_changes).chain().after(camera_follow))
+            .add_systems(Update, update_player_model_visibility.after(camera_follow))
+            .init_resource::<AimTarget>()
+            .add_systems(Update, aim_input.before(read_camera_input).after(toggle_camera_mode))
+            .add_systems(Update, read_camera_input.before(camera_rotation).before(camera_zoom))
+            .add_systems(Update, camera_rotation.run_if(not_in_photo_mode))
+            .add_systems(Update, (recenter_on_respawn, recenter_camera).chain().after(camera_rotation).before(auto_align_camera))
+            .add_systems(Update, auto_align_camera.after(camera_rotation).before(camera_follow))
+            .add_systems(Update, cursor_grab.after(toggle_camera_mode))
+            .add_systems(Update, camera_zoom.run_if(not_in_photo_mode))
+            .add_systems(Update, shoulder_input.before(camera_follow))
+            .add_systems(Update, update_split_screen_viewports)
+            .add_systems(Update, apply_camera_settings.after(camera_zoom))
+            .add_systems(Update, update_camera_fov.after(apply_camera_settings).after(aim_input).run_if(not_in_photo_mode))
+            .add_systems(Update, update_aim_target.after(camera_follow))
+            .add_systems(Update, debug_camera_state.run_if(debug_overlay_enabled));
+
+        #[cfg(feature = "dev-tools")]
+        app.add_systems(Update, cycle_camera_target.before(camera_follow));
+    }
+}
+
+const CAMERA_SETTINGS_PATH: &str = "assets/settings/camera.ron";
+
+// Tuning shared by every camera, read each frame so edits apply straight away. Fields missing
+// from the file keep their defaults.
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    // Multipliers on the mouse look speed
+    pub horizontal_sensitivity: f32,
+    pub vertical_sensitivity: f32,
+    pub invert_x: bool,
+    pub invert_y: bool,
+    // Right stick turn rate at full deflection, in degrees per second
+    pub gamepad_sensitivity: f32,
+    // Stick deflection ignored around the centre
+    pub gamepad_deadzone: f32,
+    pub zoom_speed: f32,
+    pub zoom_mode: ZoomMode,
+    // Field of view limits in degrees for Fov zoom; Hybrid zooms from fov down to min_fov
+    pub min_fov: f32,
+    pub max_fov: f32,
+    // Roughly how many seconds the distance takes to ease to where zooming asked for, and the
+    // third person camera to catch up with its follow position. Both are critically damped
+    // springs, so they never overshoot and look the same at any frame rate.
+    pub zoom_time: f32,
+    pub follow_time: f32,
+    // Older files tuned the same things as a rate and a spring stiffness. If present they're
+    // converted and take over from the times above; the old spring_damping is no longer read,
+    // since the spring is always critically damped.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zoom_smoothness: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spring_stiffness: Option<f32>,
+    pub start_distance: f32,
+    pub min_distance: f32,
+    pub max_distance: f32,
+    // Height of the third person look target above the player
+    pub height: f32,
+    // Vertical field of view in degrees
+    pub fov: f32,
+    // Keep the cursor grabbed and hidden whenever the window has focus, not just while rotating.
+    // The mouse then orbits the third person camera without a button held.
+    pub always_grab_cursor: bool,
+    // Over the shoulder offset of the third person camera and its look target, in the camera's
+    // yaw frame: x towards the right shoulder, y up, z back. Zero keeps the player centred.
+    pub shoulder_offset: Vec3,
+    // Letting go of free-look keeps the view it ended on as the movement frame, instead of
+    // easing back to the old one
+    pub freelook_commit: bool,
+    // Swing the third person camera round behind the way the player is moving, once they've been
+    // moving a moment and haven't touched the camera for a second. In radians per second.
+    pub auto_align: bool,
+    pub auto_align_speed: f32,
+    // Seconds the camera takes to move between first and third person
+    pub transition_duration: f32,
+    // How far ahead of a sprinting player the third person camera looks, in metres, and roughly
+    // how many seconds the lead takes to catch up with a change of direction
+    pub lead_distance: f32,
+    pub lead_smoothness: f32,
+    pub follow_mode: FollowMode,
+    // Top-down view: metres above the player, the limits scrolling keeps it between, and degrees
+    // it leans back from looking straight down
+    pub top_down_height: f32,
+    pub top_down_min_height: f32,
+    pub top_down_max_height: f32,
+    pub top_down_tilt: f32,
+    // Aiming: the field of view it narrows to, or in Dolly zoom the distance it pulls in to, the
+    // shoulder offset it swings over to, how much it slows the look speed, and seconds to blend
+    pub aim_fov: f32,
+    pub aim_distance: f32,
+    pub aim_shoulder_offset: Vec3,
+    pub aim_sensitivity_multiplier: f32,
+    pub aim_blend_time: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            horizontal_sensitivity: 1.0,
+            vertical_sensitivity: 1.0,
+            invert_x: false,
+            invert_y: false,
+            gamepad_sensitivity: 180.0,
+            gamepad_deadzone: 0.15,
+            zoom_speed: 1.0,
+            zoom_mode: ZoomMode::Dolly,
+            min_fov: 20.0,
+            max_fov: 70.0,
+            zoom_time: 0.1,
+            follow_time: 0.25,
+            zoom_smoothness: None,
+            spring_stiffness: None,
+            start_distance: 8.0,
+            min_distance: 3.0,
+            max_distance: 15.0,
+            height: 3.0,
+            fov: 45.0,
+            always_grab_cursor: false,
+            shoulder_offset: Vec3::ZERO,
+            freelook_commit: false,
+            auto_align: true,
+            auto_align_speed: 1.5,
+            transition_duration: 0.35,
+            lead_distance: 1.5,
+            lead_smoothness: 0.4,
+            follow_mode: FollowMode::Tight,
+            top_down_height: 18.0,
+            top_down_min_height: 8.0,
+            top_down_max_height: 35.0,
+            top_down_tilt: 0.0,
+            aim_fov: 30.0,
+            aim_distance: 3.5,
+            aim_shoulder_offset: Vec3::new(0.8, 0.3, 0.0),
+            aim_sensitivity_multiplier: 0.5,
+            aim_blend_time: 0.15,
+        }
+    }
+}
+
+impl CameraSettings {
+    // Reads the settings from disk, falling back to the defaults if the file is missing or invalid
+    pub fn load(path: &str) -> Self {
+        match fs::read_to_string(path) {
+            Ok(contents) => match ron::from_str::<CameraSettings>(&contents) {
+                Ok(settings) => {
+                    println!("Loaded camera settings from {}", path);
+                    settings
+                }
+                Err(err) => {
+                    println!("WARNING: Failed to parse {}: {}, using default camera settings", path, err);
+                    CameraSettings::default()
+                }
+            },
+            Err(_) => {
+                println!("No camera settings at {}, using defaults", path);
+                CameraSettings::default()
+            }
+        }
+    }
+
+    // Writes the settings to `path` in the format load reads, e.g. CAMERA_SETTINGS_PATH after an
+    // options screen changes them
+    #[allow(dead_code)]
+    pub fn save(&self, path: &str) {
+        let contents = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => contents,
+            Err(err) => {
+                println!("WARNING: Failed to serialize camera settings: {}", err);
+                return;
+            }
+        };
+        if let Some(parent) = Path::new(path).parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        match fs::write(path, contents) {
+            Ok(()) => println!("Saved camera settings to {}", path),
+            Err(err) => println!("WARNING: Failed to write {}: {}", path, err),
+        }
+    }
+
+    // A zoom_smoothness of 15 used to be within 1% after 0.3s, which a zoom_time of 0.1 matches
+    fn zoom_smooth_time(&self) -> f32 {
+        self.zoom_smoothness.map_or(self.zoom_time, |smoothness| 1.5 / smoothness.max(f32::EPSILON))
+    }
+
+    // A spring of stiffness k has a natural frequency of sqrt(k), which smooth_damp gets from a
+    // smooth time of 2 / sqrt(k). The old default of 64 comes out at 0.25s.
+    fn follow_smooth_time(&self) -> f32 {
+        self.spring_stiffness.map_or(self.follow_time, |stiffness| 2.0 / stiffness.max(f32::EPSILON).sqrt())
+    }
+
+    pub fn invert(&self) -> Vec2 {
+        Vec2::new(
+            if self.invert_x { -1.0 } else { 1.0 },
+            if self.invert_y { -1.0 } else { 1.0 },
+        )
     }
 }
 
+// How the third person camera keeps up with the player
+#[derive(Clone, Copy, Debug, PartialEq, Default, Serialize, Deserialize)]
+pub enum FollowMode {
+    // Springs towards the player every frame
+    #[default]
+    Tight,
+    // Holds still until the player leaves a box around the screen centre, in metres at the look
+    // target across and up, then moves just enough to keep them on its edge. Orbit and zoom still
+    // move it as usual.
+    DeadZone { half_extents: Vec2 },
+}
+
+// What third person zoom input changes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ZoomMode {
+    // Moves the camera in and out
+    #[default]
+    Dolly,
+    // Narrows the field of view with the camera left where it is, so tight spots don't pull it in
+    Fov,
+    // Dollies in to min_distance, then narrows the field of view the rest of the way
+    Hybrid,
+}
+
+// One frame of look input for a camera. Mouse motion is a delta already covering the frame, while
+// the stick gives a rate that still has to be multiplied by the frame time.
+#[derive(Component, Default)]
+pub struct CameraInput {
+    // Pixels moved this frame, scaled by sensitivity; x right, y down
+    pub mouse_delta: Vec2,
+    // Radians per second from the right stick; x right, y down like the mouse
+    pub stick_rate: Vec2,
+    // Mouse wheel notches this frame, positive towards the player
+    pub zoom_steps: f32,
+    // Shaped right stick vertical deflection, -1..1 with up positive; zooms in third person
+    pub stick_zoom: f32,
+}
+
+// Per camera state; the tuning lives in CameraSettings
 #[derive(Component)]
 pub struct ThirdPersonCamera {
     pub target: Entity,
+    // The zoom distance camera_follow uses, eased towards target_distance
     pub distance: f32,
-    pub height: f32,
-    pub smoothness: f32,
-    pub rotation_speed: f32,
+    // Where zoom input has put the camera, within the settings' limits
+    pub target_distance: f32,
+    // Vertical field of view in degrees, narrowed by Fov and Hybrid zoom
+    pub fov: f32,
     pub current_rotation: f32,
-    pub min_distance: f32,
-    pub max_distance: f32,
-    pub zoom_speed: f32,
+    // Third person follow speed, carried between frames by the spring
+    pub camera_velocity: Vec3,
+    // Rate the distance is changing at while it eases to target_distance
+    pub distance_velocity: f32,
+    pub mode: CameraMode,
+    // Shoulder the camera looks over, 1 right or -1 left, unless it's been centred
+    pub shoulder_side: f32,
+    pub shoulder_centered: bool,
+    // The side and how much of the shoulder offset is applied, eased so swaps slide across
+    pub shoulder_x: f32,
+    pub shoulder_amount: f32,
+    // LockOnTarget the camera is framing, if any, and where it last was
+    pub lock_on: Option<Entity>,
+    pub lock_point: Vec3,
+    // 1 while locked on, eased back to 0 after letting go
+    pub lock_blend: f32,
+    // Free-look view on top of current_rotation, which stays the movement frame while looking
+    // around; both ease back to 0 once free-look is let go
+    pub freelook_yaw: f32,
+    pub freelook_pitch: f32,
+    // Seconds since the last mouse or stick camera input, and spent moving; auto-align waits on both
+    pub since_manual_input: f32,
+    pub moving_time: f32,
+    // First person look angles; third person keeps using current_rotation/distance
+    pub yaw: f32,
+    pub pitch: f32,
+    // Move between views in progress; mode only switches once it's done
+    pub camera_transition: Option<CameraTransition>,
+    // Horizontal look-ahead added to the follow target, and its smoothing velocity
+    pub lead: Vec3,
+    pub lead_velocity: Vec3,
+    // Height added by the bob while sliding on ice, and how far through its cycle it is
+    pub slide_bob: f32,
+    pub slide_bob_phase: f32,
+    // The point a DeadZone camera is centred on, which only follows the look target out of the box
+    pub dead_zone_focus: Option<Vec3>,
+    // Where the camera was following from when the target last changed, and seconds since
+    pub target_blend: Option<(Vec3, f32)>,
+    // The movement frame while a fixed camera holds the view: the current_rotation that would
+    // look the same way it does
+    pub fixed_rotation: f32,
+    // The position actually followed last frame, blended while switching targets
+    pub followed_position: Vec3,
+    // Top-down height above the target, and its yaw easing towards the 45 degree step it was
+    // last turned to. At yaw 0 the top of the screen is -Z.
+    pub top_down_height: f32,
+    pub top_down_yaw: f32,
+    pub top_down_target_yaw: f32,
+    // Aim held in third or first person, how far the view has blended into it (0 to 1), and the
+    // third person orbit tilt it's looked up or down by, eased back to 0 once it's let go
+    pub aiming: bool,
+    pub aim_blend: f32,
+    pub aim_pitch: f32,
+    // Swinging round behind the player after recenter(), and how fast it's turning
+    pub recentering: bool,
+    pub recenter_velocity: f32,
+}
+
+// Something the camera can be pointed at instead of the player
+#[derive(Component, Clone, Debug)]
+#[cfg_attr(not(feature = "dev-tools"), allow(dead_code))]
+pub struct CameraTargetable {
+    pub name: String,
+}
+
+#[derive(Event)]
+pub struct CameraTargetChanged {
+    pub camera: Entity,
+    pub previous: Entity,
+    pub current: Entity,
+}
+
+// Where one player's crosshair points: the ray through the middle of their view
+#[derive(Clone, Copy, Debug)]
+pub struct Aim {
+    pub aiming: bool,
+    pub ray: Ray,
+}
+
+// Every local player's aim, in player order, for shooting and interaction to aim along
+#[derive(Resource, Default)]
+pub struct AimTarget(Vec<Aim>);
+
+impl AimTarget {
+    pub fn for_player(&self, index: usize) -> Option<&Aim> {
+        self.0.get(index)
+    }
+}
+
+// Positions are relative to the target, so the move keeps up with a player who's walking
+#[derive(Clone, Copy, Debug)]
+pub struct CameraTransition {
+    pub from_pos: Vec3,
+    pub to_pos: Vec3,
+    pub from_rot: Quat,
+    pub to_rot: Quat,
+    // 0 to 1
+    pub progress: f32,
+    pub duration: f32,
+    pub to_mode: CameraMode,
+}
+
+impl CameraTransition {
+    // Offset from the target and rotation at the current progress. The path is a cubic Bezier
+    // that leaves and arrives along each view's own axis, so it dollies rather than cutting across.
+    fn sample(&self) -> (Vec3, Quat) {
+        let t = self.progress * self.progress * (3.0 - 2.0 * self.progress);
+        let span = self.to_pos - self.from_pos;
+        let reach = span.length() / 3.0;
+        let from_forward = self.from_rot * Vec3::NEG_Z;
+        let to_forward = self.to_rot * Vec3::NEG_Z;
+        let control_1 = self.from_pos + from_forward * reach * from_forward.dot(span).signum();
+        let control_2 = self.to_pos - to_forward * reach * to_forward.dot(span).signum();
+        let u = 1.0 - t;
+        let position = self.from_pos * (u * u * u)
+            + control_1 * (3.0 * u * u * t)
+            + control_2 * (3.0 * u * t * t)
+            + self.to_pos * (t * t * t);
+        (position, self.from_rot.slerp(self.to_rot, t))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum CameraMode {
+    #[default]
+    ThirdPerson,
+    FirstPerson,
+    // High above the player looking down, with WASD moving across the screen
+    TopDown,
+    // Detached and flown around freely while the game is paused, see photo_mode
+    Photo,
+    // Held on a pre-framed shot while the player is in a FixedCameraZone, see fixed_camera
+    Fixed,
+}
+
+// Run condition for the systems that drive a camera from the player, which photo mode takes over from
+pub fn not_in_photo_mode(camera_query: Query<&ThirdPersonCamera>) -> bool {
+    !camera_query.iter().any(|camera| camera.mode == CameraMode::Photo)
+}
+
+impl ThirdPersonCamera {
+    // Follows another entity, easing across from wherever the camera was following over
+    // TARGET_BLEND_TIME rather than cutting to it
+    pub fn set_camera_target(&mut self, entity: Entity) {
+        if self.target == entity {
+            return;
+        }
+        self.target = entity;
+        self.target_blend = Some((self.followed_position, 0.0));
+        self.dead_zone_focus = None;
+    }
+
+    // Angle player_movement rotates WASD input by. Free-look is left out, so looking around
+    // doesn't change which way the player walks or faces.
+    pub fn movement_rotation(&self) -> f32 {
+        match self.mode {
+            CameraMode::ThirdPerson | CameraMode::Photo => self.current_rotation,
+            CameraMode::FirstPerson => -self.yaw,
+            CameraMode::Fixed => self.fixed_rotation,
+            CameraMode::TopDown => self.top_down_yaw,
+        }
+    }
+
+    // Swings a third person camera round behind the way the player faces and back to the default
+    // zoom, see recenter_camera. Turning or zooming the camera meanwhile cancels it.
+    pub fn recenter(&mut self) {
+        self.recentering = true;
+        self.recenter_velocity = 0.0;
+    }
+}
+
+// Where a top-down camera sits relative to what it follows, and which way it faces: straight
+// down with the top of the screen along yaw, or leant back by tilt_degrees. Built from the angles,
+// since look_at has no usable up vector looking straight down.
+fn top_down_pose(yaw: f32, height: f32, tilt_degrees: f32) -> (Vec3, Quat) {
+    let tilt = tilt_degrees.to_radians().clamp(0.0, TOP_DOWN_MAX_TILT);
+    let yaw_rotation = Quat::from_rotation_y(yaw);
+    let offset = Vec3::Y * height + yaw_rotation * Vec3::Z * height * tilt.tan();
+    (offset, yaw_rotation * Quat::from_rotation_x(tilt - FRAC_PI_2))
+}
+
+// Seconds a switch between camera targets takes to ease across
+const TARGET_BLEND_TIME: f32 = 0.6;
+// Eyes sit just below the top of the capsule (half height 1.5)
+const FIRST_PERSON_EYE_HEIGHT: f32 = 1.3;
+// Radians of look per pixel of mouse motion at sensitivity 1
+const FIRST_PERSON_SENSITIVITY: f32 = 0.002;
+// Radians of third person orbit per pixel of mouse motion at sensitivity 1
+const THIRD_PERSON_SENSITIVITY: f32 = 0.0003;
+// Third person zoom speed with the right stick held fully up or down, at zoom speed 1
+const GAMEPAD_ZOOM_RATE: f32 = 8.0;
+// Distance per scroll wheel notch at zoom speed 1
+const ZOOM_STEP: f32 = 0.1;
+// Degrees of field of view per unit of zoom distance, for Fov and Hybrid zoom
+const FOV_ZOOM_SCALE: f32 = 5.0;
+const FIRST_PERSON_MAX_PITCH: f32 = 1.5;
+// Top-down height per unit of zoom distance, and roughly how long a 45 degree turn takes
+const TOP_DOWN_ZOOM_SCALE: f32 = 3.0;
+const TOP_DOWN_TURN_TIME: f32 = 0.15;
+// Leaning back further than this is just a third person camera
+const TOP_DOWN_MAX_TILT: f32 = 1.0;
+
+// Radius of the sphere swept from the player to the camera, and the gap kept in front of a hit
+const CAMERA_COLLISION_RADIUS: f32 = 0.2;
+const CAMERA_COLLISION_SKIN: f32 = 0.1;
+// Above this player speed the follow time shortens in proportion, down to a MAX_FOLLOW_SPEEDUP
+// times faster, so the camera keeps up with dashes
+const FAST_FOLLOW_SPEED: f32 = 12.0;
+const MAX_FOLLOW_SPEEDUP: f32 = 2.0;
+// Smooth time for easing back out after an obstruction clears, settling within about 0.3s
+const CAMERA_RETURN_TIME: f32 = 0.1;
+// Time constant for sliding between shoulders or to the centre
+const SHOULDER_SWAP_TIME: f32 = 0.08;
+// Time constant for swinging into and out of lock-on framing, settling within about 0.4s
+const LOCK_ON_BLEND_TIME: f32 = 0.13;
+// How far from the player towards a lock-on target the camera looks
+const LOCK_ON_LOOK_WEIGHT: f32 = 0.35;
+// Free-look eases back to the movement frame with this time constant, settling in about 0.5s
+const FREELOOK_RETURN_TIME: f32 = 0.12;
+// How far free-look can tilt the orbit up or down, in radians
+const FREELOOK_MAX_PITCH: f32 = 1.0;
+// Recentering springs round behind the player in about this many seconds, and counts as done
+// within this many radians
+const RECENTER_TIME: f32 = 0.3;
+const RECENTER_DONE_ANGLE: f32 = 0.01;
+// Ground speed, relative to whatever the player stands on, that gets the full lead_distance. The
+// default sprint speed; walking gets two thirds.
+const LEAD_FULL_SPEED: f32 = 12.0;
+// Height of the bob while sliding on ice, above this ground speed. Its phase advances a radian
+// per metre slid.
+const SLIDE_BOB_HEIGHT: f32 = 0.05;
+const SLIDE_BOB_MIN_SPEED: f32 = 1.0;
+// Airborne players get this much more room upwards in DeadZone follow, so hops don't bob the camera
+const DEAD_ZONE_JUMP_SCALE: f32 = 3.0;
+// Auto-align waits this long after manual camera input, and for the player to have been moving
+// this long above AUTO_ALIGN_MIN_SPEED
+const AUTO_ALIGN_INPUT_COOLDOWN: f32 = 1.0;
+const AUTO_ALIGN_MOVE_TIME: f32 = 0.5;
+const AUTO_ALIGN_MIN_SPEED: f32 = 1.0;
+// Moving more than this far round towards the camera (backing up) leaves it alone; aligning would
+// turn the movement frame with it and spin the camera round forever
+const AUTO_ALIGN_MAX_ANGLE: f32 = 2.4;
+
+const MAX_SHAKE_OFFSET: f32 = 0.5;
+// Roll in radians at full trauma
+const MAX_SHAKE_ROLL: f32 = 0.05;
+// Trauma lost per second
+const SHAKE_DECAY: f32 = 1.5;
+// Noise cycles per second; higher is more jittery
+const SHAKE_FREQUENCY: f32 = 18.0;
+// Landings faster than this shake the camera, reaching LANDING_MAX_TRAUMA at LANDING_FULL_SHAKE_SPEED
+const LANDING_SHAKE_SPEED: f32 = 6.0;
+const LANDING_FULL_SHAKE_SPEED: f32 = 20.0;
+const LANDING_MAX_TRAUMA: f32 = 0.6;
+
+// Trauma in 0..1 decays linearly; the shake itself scales with trauma squared, so small knocks
+// stay subtle and nothing moves at all once it reaches zero
+#[derive(Component, Default)]
+pub struct CameraShake {
+    pub trauma: f32,
+    offset: Vec3,
+    // Seconds since this shake started, which the noise is sampled by. Restarting it per shake
+    // keeps replays identical however long the game ran before recording.
+    time: f32,
 }
 
-fn setup_camera(mut commands: Commands) {
+impl CameraShake {
+    pub fn add_trauma(&mut self, amount: f32) {
+        self.trauma = (self.trauma + amount).clamp(0.0, 1.0);
+    }
+
+    fn decay(&mut self, delta_seconds: f32) {
+        self.trauma = (self.trauma - SHAKE_DECAY * delta_seconds).max(0.0);
+        self.time = if self.trauma > 0.0 { self.time + delta_seconds } else { 0.0 };
+    }
+}
+
+// Smooth 1D gradient noise in about -1..1, zero at whole numbers
+fn perlin_noise(x: f32) -> f32 {
+    let gradient = |cell: i32| {
+        // Integer hash to a gradient in -1..1
+        let mut h = (cell as u32).wrapping_mul(0x27d4_eb2d);
+        h ^= h >> 15;
+        h = h.wrapping_mul(0x85eb_ca6b);
+        h ^= h >> 13;
+        (h & 0xffff) as f32 / 32767.5 - 1.0
+    };
+    let cell = x.floor();
+    let t = x - cell;
+    let fade = t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    let left = gradient(cell as i32) * t;
+    let right = gradient(cell as i32 + 1) * (t - 1.0);
+    // Gradient noise in 1D peaks at half the gradient
+    (left + (right - left) * fade) * 2.0
+}
+
+#[derive(Event)]
+pub struct CameraShakeEvent {
+    pub trauma: f32,
+}
+
+// How far the camera may currently sit from its target: pulled in at once when something is in
+// the way, eased back out to the zoom distance once it clears
+#[derive(Component)]
+pub struct CameraCollision {
+    distance: f32,
+    // Rate it's easing back out at
+    velocity: f32,
+}
+
+// Jumps the cameras following `target` straight to their follow position instead of easing
+// there, e.g. after a teleport
+#[derive(Event)]
+pub struct CameraSnapEvent {
+    pub target: Entity,
+}
+
+// One camera per local player, each drawing to its own part of the window
+fn setup_camera(mut commands: Commands, settings: Res<PlayerSettings>, camera_settings: Res<CameraSettings>) {
     println!("=== SETTING UP CAMERA ===");
+    for index in 0..settings.local_players {
+        spawn_camera(&mut commands, index, &camera_settings);
+    }
+    println!("Camera spawned with placeholder target");
+}
+
+pub fn spawn_camera(commands: &mut Commands, index: usize, settings: &CameraSettings) {
     commands.spawn((
+        PlayerIndex(index),
         Camera3dBundle {
+            camera: Camera {
+                order: index as isize,
+                ..default()
+            },
+            projection: Projection::Perspective(PerspectiveProjection {
+                fov: settings.fov.to_radians(),
+                ..default()
+            }),
             transform: Transform::from_xyz(0.0, 5.0, 10.0)
                 .looking_at(Vec3::ZERO, Vec3::Y),
             ..default()
         },
         ThirdPersonCamera {
             target: Entity::PLACEHOLDER,
-            distance: 8.0,
-            height: 3.0,
-            smoothness: 5.0,
-            rotation_speed: 2.0,
+            distance: settings.start_distance,
+            target_distance: settings.start_distance,
+            fov: settings.fov,
             current_rotation: 0.0,
-            min_distance: 3.0,
-            max_distance: 15.0,
-            zoom_speed: 1.0,
+            camera_velocity: Vec3::ZERO,
+            distance_velocity: 0.0,
+            mode: CameraMode::ThirdPerson,
+            shoulder_side: 1.0,
+            shoulder_centered: false,
+            shoulder_x: 1.0,
+            shoulder_amount: 1.0,
+            lock_on: None,
+            lock_point: Vec3::ZERO,
+            lock_blend: 0.0,
+            freelook_yaw: 0.0,
+            freelook_pitch: 0.0,
+            since_manual_input: 0.0,
+            moving_time: 0.0,
+            yaw: 0.0,
+            pitch: 0.0,
+            camera_transition: None,
+            lead: Vec3::ZERO,
+            lead_velocity: Vec3::ZERO,
+            slide_bob: 0.0,
+            slide_bob_phase: 0.0,
+            dead_zone_focus: None,
+            target_blend: None,
+            followed_position: Vec3::ZERO,
+            top_down_height: settings.top_down_height,
+            top_down_yaw: 0.0,
+            top_down_target_yaw: 0.0,
+            aiming: false,
+            aim_blend: 0.0,
+            aim_pitch: 0.0,
+            recentering: false,
+            recenter_velocity: 0.0,
+            fixed_rotation: 0.0,
+        },
+        CameraShake::default(),
+        CameraInput::default(),
+        CameraCollision {
+            distance: settings.start_distance,
+            velocity: 0.0,
         },
     ));
-    println!("Camera spawned with placeholder target");
 }
 
-fn camera_follow(
-    mut camera_query: Query<(&mut Transform, &mut ThirdPersonCamera)>,
-    player_query: Query<&Transform, (With<Player>, Without<ThirdPersonCamera>)>,
+// Splits the window into side by side columns, recomputed whenever the window is resized
+fn update_split_screen_viewports(
+    mut resize_events: EventReader<WindowResized>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut camera_query: Query<(&PlayerIndex, &mut Camera), With<ThirdPersonCamera>>,
+    added_query: Query<(), Added<ThirdPersonCamera>>,
+    settings: Res<PlayerSettings>,
+) {
+    let resized = resize_events.read().count() > 0;
+    if settings.local_players < 2 || (!resized && added_query.is_empty()) {
+        return;
+    }
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    let columns = settings.local_players as u32;
+    let width = window.physical_width() / columns;
+    let height = window.physical_height();
+    for (index, mut camera) in camera_query.iter_mut() {
+        camera.viewport = Some(Viewport {
+            physical_position: UVec2::new(width * index.0 as u32, 0),
+            physical_size: UVec2::new(width.max(1), height.max(1)),
+            ..default()
+        });
+    }
+}
+
+pub fn camera_follow(
+    mut camera_query: Query<(&mut Transform, &mut ThirdPersonCamera, &mut CameraCollision, Option<&mut CameraShake>)>,
+    target_query: Query<(&Transform, Option<&Player>, Option<&Velocity>), Without<ThirdPersonCamera>>,
+    lock_target_query: Query<&GlobalTransform, With<LockOnTarget>>,
+    rapier_context: Res<RapierContext>,
+    settings: Res<CameraSettings>,
+    mut snap_events: EventReader<CameraSnapEvent>,
     time: Res<Time>,
 ) {
-    if let Ok((mut camera_transform, mut camera)) = camera_query.get_single_mut() {
-        if let Ok(player_transform) = player_query.get(camera.target) {
-            let target_pos = player_transform.translation;
-            let target_pos_with_height = target_pos + Vec3::Y * camera.height;
+    let snap_targets: Vec<Entity> = snap_events.read().map(|event| event.target).collect();
+    for (mut camera_transform, mut camera, mut collision, mut shake) in camera_query.iter_mut() {
+        // A fixed camera zone is holding the view
+        if camera.mode == CameraMode::Fixed {
+            continue;
+        }
+        if let Ok((target_transform, player, player_velocity)) = target_query.get(camera.target) {
+            // Just after a switch, follow a point sliding from the old target over to the new one
+            let followed_position = match camera.target_blend {
+                Some((from, elapsed)) => {
+                    let elapsed = elapsed + time.delta_seconds();
+                    let t = (elapsed / TARGET_BLEND_TIME).min(1.0);
+                    camera.target_blend = if t < 1.0 { Some((from, elapsed)) } else { None };
+                    from.lerp(target_transform.translation, t * t * (3.0 - 2.0 * t))
+                }
+                None => target_transform.translation,
+            };
+            camera.followed_position = followed_position;
+
+            if let Some(mut transition) = camera.camera_transition {
+                transition.progress = if transition.duration > 0.0 {
+                    (transition.progress + time.delta_seconds() / transition.duration).min(1.0)
+                } else {
+                    1.0
+                };
+                let (offset, rotation) = transition.sample();
+                camera_transform.translation = followed_position + offset;
+                camera_transform.rotation = rotation;
+                if transition.progress >= 1.0 {
+                    camera.mode = transition.to_mode;
+                    camera.camera_transition = None;
+                    println!("Camera mode: {:?}", camera.mode);
+                } else {
+                    camera.camera_transition = Some(transition);
+                }
+                continue;
+            }
+
+            if camera.mode == CameraMode::FirstPerson {
+                camera.camera_velocity = Vec3::ZERO;
+                camera.lead = Vec3::ZERO;
+                camera.lead_velocity = Vec3::ZERO;
+                camera.dead_zone_focus = None;
+                camera.slide_bob = 0.0;
+                camera_transform.translation = followed_position + Vec3::Y * FIRST_PERSON_EYE_HEIGHT;
+                camera_transform.rotation = math::yaw_pitch_rotation(camera.yaw, camera.pitch);
+                if let Some(shake) = shake.as_mut() {
+                    apply_shake(&mut camera_transform, shake, time.delta_seconds());
+                }
+                continue;
+            }
+
+            if camera.mode == CameraMode::TopDown {
+                camera.lead = Vec3::ZERO;
+                camera.lead_velocity = Vec3::ZERO;
+                camera.dead_zone_focus = None;
+                camera.slide_bob = 0.0;
+                let turn_ease = 1.0 - (-time.delta_seconds() / TOP_DOWN_TURN_TIME).exp();
+                let turn = math::wrap_angle(camera.top_down_target_yaw - camera.top_down_yaw);
+                camera.top_down_yaw = math::wrap_angle(camera.top_down_yaw + turn * turn_ease);
+                let (offset, rotation) = top_down_pose(camera.top_down_yaw, camera.top_down_height, settings.top_down_tilt);
+                let desired_pos = followed_position + offset;
+                let previous_offset = shake.as_ref().map_or(Vec3::ZERO, |shake| shake.offset);
+                camera_transform.translation = if snap_targets.contains(&camera.target) {
+                    camera.camera_velocity = Vec3::ZERO;
+                    desired_pos
+                } else {
+                    let mut camera_velocity = camera.camera_velocity;
+                    let new_pos = math::smooth_damp(camera_transform.translation - previous_offset, desired_pos, &mut camera_velocity, settings.follow_smooth_time(), time.delta_seconds());
+                    camera.camera_velocity = camera_velocity;
+                    new_pos
+                };
+                camera_transform.rotation = rotation;
+                if let Some(shake) = shake.as_mut() {
+                    apply_shake(&mut camera_transform, shake, time.delta_seconds());
+                }
+                continue;
+            }
+
+            let mut distance_velocity = camera.distance_velocity;
+            camera.distance = math::smooth_damp(camera.distance, camera.target_distance, &mut distance_velocity, settings.zoom_smooth_time(), time.delta_seconds());
+            camera.distance_velocity = distance_velocity;
+
+            let shoulder_blend = 1.0 - (-time.delta_seconds() / SHOULDER_SWAP_TIME).exp();
+            let shoulder_amount = if camera.shoulder_centered { 0.0 } else { 1.0 };
+            camera.shoulder_x += (camera.shoulder_side - camera.shoulder_x) * shoulder_blend;
+            camera.shoulder_amount += (shoulder_amount - camera.shoulder_amount) * shoulder_blend;
+
+            // Dolly zoom aims by pulling in, the other zoom modes by narrowing the view
+            let aim = math::smoothstep(camera.aim_blend);
+            let follow_distance = if settings.zoom_mode == ZoomMode::Dolly {
+                camera.distance + (settings.aim_distance.min(camera.distance) - camera.distance) * aim
+            } else {
+                camera.distance
+            };
+
+            let target_pos = followed_position;
+            let target_pos_with_height = target_pos + Vec3::Y * settings.height;
+
+            // Locked on, the camera swings round behind the player on the line from the target
+            let lock_target = camera.lock_on.and_then(|target| lock_target_query.get(target).ok());
+            if let Some(lock_transform) = lock_target {
+                camera.lock_point = lock_transform.translation();
+            }
+            let lock_ease = 1.0 - (-time.delta_seconds() / LOCK_ON_BLEND_TIME).exp();
+            let locked = if lock_target.is_some() { 1.0 } else { 0.0 };
+            camera.lock_blend += (locked - camera.lock_blend) * lock_ease;
+            if lock_target.is_some() {
+                if let Some(away) = ((target_pos - camera.lock_point) * Vec3::new(1.0, 0.0, 1.0)).try_normalize() {
+                    let lock_rotation = away.x.atan2(away.z);
+                    let turn = (lock_rotation - camera.current_rotation + PI).rem_euclid(TAU) - PI;
+                    camera.current_rotation = math::wrap_angle(camera.current_rotation + turn * lock_ease);
+                }
+            }
             
-            // Calculate camera position based on rotation
-            let rotation_rad = camera.current_rotation;
-            let camera_offset = Vec3::new(
-                rotation_rad.sin() * camera.distance,
-                0.0,
-                rotation_rad.cos() * camera.distance,
-            );
-            let desired_pos = target_pos_with_height + camera_offset;
+            // Look ahead of where the target is moving, relative to what a player is riding, and
+            // fade it out while locked on. Smoothing the lead vector itself rather than the velocity
+            // keeps a quick reversal from flicking the frame across.
+            let snapped = snap_targets.contains(&camera.target);
+            let ground_velocity = player_velocity.map_or(Vec3::ZERO, |velocity| velocity.linvel - player.map_or(Vec3::ZERO, |player| player.platform_velocity)) * Vec3::new(1.0, 0.0, 1.0);
+            let lead_scale = (ground_velocity.length() / LEAD_FULL_SPEED).min(1.0) * (1.0 - camera.lock_blend);
+            let desired_lead = ground_velocity.normalize_or_zero() * settings.lead_distance * lead_scale;
+            if snapped {
+                camera.lead = desired_lead;
+                camera.lead_velocity = Vec3::ZERO;
+            } else {
+                let mut lead_velocity = camera.lead_velocity;
+                camera.lead = math::smooth_damp(camera.lead, desired_lead, &mut lead_velocity, settings.lead_smoothness, time.delta_seconds());
+                camera.lead_velocity = lead_velocity;
+            }
+
+            // Calculate camera position based on rotation, with free-look swung on top
+            let rotation_rad = camera.current_rotation + camera.freelook_yaw;
+            let camera_direction = math::orbit_direction(rotation_rad, camera.freelook_pitch + camera.aim_pitch);
+
+            // Shift the look target over the shoulder and ahead by the lead, stopping short of any
+            // wall in the way. The camera's own collision cast then starts from the shifted target.
+            // Aiming swings over to the aim offset, even from a centred view
+            let shoulder_offset = (settings.shoulder_offset * camera.shoulder_amount).lerp(settings.aim_shoulder_offset, aim);
+            let shoulder = Quat::from_rotation_y(rotation_rad) * shoulder_offset * Vec3::new(camera.shoulder_x, 1.0, 1.0);
+            let target_offset = shoulder + camera.lead;
+            let look_target = match target_offset.try_normalize() {
+                Some(offset_direction) => {
+                    let clear = camera_clear_distance(&rapier_context, camera.target, target_pos_with_height, offset_direction, target_offset.length());
+                    target_pos_with_height + offset_direction * clear
+                }
+                None => target_pos_with_height,
+            };
+
+            // DeadZone centres on a focus point that only moves by however far the look target has
+            // left the box, measured across and up the view. Depth is followed fully so zoom is the same.
+            let look_target = match settings.follow_mode {
+                FollowMode::DeadZone { half_extents } if !snapped => {
+                    let focus = camera.dead_zone_focus.unwrap_or(look_target);
+                    let view_rotation = math::look_rotation(camera_direction, Vec3::ZERO).unwrap_or_default();
+                    let local = view_rotation.inverse() * (look_target - focus);
+                    let mut extents = half_extents;
+                    if !player.is_none_or(|player| player.touching_ground()) && local.y > 0.0 {
+                        extents.y *= DEAD_ZONE_JUMP_SCALE;
+                    }
+                    let focus = focus + view_rotation * math::dead_zone_excess(local, extents);
+                    camera.dead_zone_focus = Some(focus);
+                    focus
+                }
+                FollowMode::DeadZone { .. } => {
+                    camera.dead_zone_focus = Some(look_target);
+                    look_target
+                }
+                FollowMode::Tight => {
+                    camera.dead_zone_focus = None;
+                    look_target
+                }
+            };
+            let clear_distance = camera_clear_distance(&rapier_context, camera.target, look_target, camera_direction, follow_distance);
+            let obstructed = clear_distance < collision.distance;
+            collision.distance = if obstructed {
+                collision.velocity = 0.0;
+                clear_distance
+            } else {
+                let mut velocity = collision.velocity;
+                let eased = math::smooth_damp(collision.distance, clear_distance, &mut velocity, CAMERA_RETURN_TIME, time.delta_seconds());
+                collision.velocity = velocity;
+                eased.min(clear_distance)
+            };
+            let desired_pos = look_target + camera_direction * collision.distance;
             
-            // Smoothly interpolate camera position
-            let current_pos = camera_transform.translation;
-            let new_pos = current_pos.lerp(desired_pos, camera.smoothness * time.delta_seconds());
+            // Spring towards the follow position (ignoring last frame's shake). While held in by
+            // an obstruction it sits exactly where the collision allows, so it never lags into it.
+            // DeadZone has already done its holding back, so it goes straight there too.
+            let previous_offset = shake.as_ref().map_or(Vec3::ZERO, |shake| shake.offset);
+            let current_pos = camera_transform.translation - previous_offset - Vec3::Y * camera.slide_bob;
+            let held_in = collision.distance < follow_distance - f32::EPSILON;
+            let dead_zone = camera.dead_zone_focus.is_some();
+            let new_pos = if snapped || held_in || dead_zone {
+                camera.camera_velocity = Vec3::ZERO;
+                desired_pos
+            } else {
+                // Quicker at high speed so it doesn't trail behind
+                let player_speed = player_velocity.map_or(0.0, |velocity| velocity.linvel.length());
+                let speedup = (player_speed / FAST_FOLLOW_SPEED).clamp(1.0, MAX_FOLLOW_SPEEDUP);
+                let mut camera_velocity = camera.camera_velocity;
+                let new_pos = math::smooth_damp(current_pos, desired_pos, &mut camera_velocity, settings.follow_smooth_time() / speedup, time.delta_seconds());
+                camera.camera_velocity = camera_velocity;
+                new_pos
+            };
             
             camera_transform.translation = new_pos;
-            camera_transform.look_at(target_pos_with_height, Vec3::Y);
+            // Framing both: look part way towards the lock-on target
+            let lock_look = look_target.lerp(camera.lock_point, LOCK_ON_LOOK_WEIGHT);
+            if let Some(rotation) = math::look_rotation(new_pos, look_target.lerp(lock_look, camera.lock_blend)) {
+                camera_transform.rotation = rotation;
+            }
+
+            // A gentle bob while sliding over ice, kept out of the follow like the shake is
+            let slide_speed = ground_velocity.length();
+            camera.slide_bob = if player.is_some_and(|player| player.on_ice()) && slide_speed > SLIDE_BOB_MIN_SPEED {
+                camera.slide_bob_phase = (camera.slide_bob_phase + slide_speed * time.delta_seconds()) % TAU;
+                camera.slide_bob_phase.sin() * SLIDE_BOB_HEIGHT
+            } else {
+                0.0
+            };
+            camera_transform.translation.y += camera.slide_bob;
+
+            // Apply screen shake on top of the follow position
+            if let Some(shake) = shake.as_mut() {
+                apply_shake(&mut camera_transform, shake, time.delta_seconds());
+            }
         } else {
-            println!("Camera: Player not found, target entity: {:?}", camera.target);
+            println!("Camera: target not found, target entity: {:?}", camera.target);
+        }
+    }
+}
+
+// How far a small sphere gets from the target towards the camera before hitting level geometry
+fn camera_clear_distance(rapier_context: &RapierContext, target: Entity, origin: Vec3, direction: Vec3, distance: f32) -> f32 {
+    let filter = QueryFilter::exclude_dynamic()
+        .exclude_sensors()
+        .exclude_rigid_body(target);
+    let probe = Collider::ball(CAMERA_COLLISION_RADIUS);
+    match rapier_context.cast_shape(origin, Quat::IDENTITY, direction, &probe, distance, true, filter) {
+        Some((_, hit)) => (hit.toi - CAMERA_COLLISION_SKIN).max(0.0),
+        None => distance,
+    }
+}
+
+// Applied after the follow position and look direction are settled. Each channel reads the noise
+// at its own offset so they move independently.
+fn apply_shake(camera_transform: &mut Transform, shake: &mut CameraShake, delta_seconds: f32) {
+    let strength = shake.trauma * shake.trauma;
+    let sample = |channel: f32| perlin_noise(shake.time * SHAKE_FREQUENCY + channel * 31.7);
+    shake.offset = (camera_transform.right() * sample(0.0) + camera_transform.up() * sample(1.0))
+        * strength
+        * MAX_SHAKE_OFFSET;
+    camera_transform.translation += shake.offset;
+    camera_transform.rotate_local_z(sample(2.0) * strength * MAX_SHAKE_ROLL);
+    shake.decay(delta_seconds);
+}
+
+fn toggle_camera_mode(
+    input_map: Res<InputMap>,
+    settings: Res<CameraSettings>,
+    mut camera_query: Query<(&PlayerIndex, &mut ThirdPersonCamera, &mut CameraShake, &CameraCollision, &Transform)>,
+    player_query: Query<&Transform, (With<Player>, Without<ThirdPersonCamera>)>,
+) {
+    for (index, mut camera, mut shake, collision, camera_transform) in camera_query.iter_mut() {
+        // A press mid-move is ignored rather than reversing it
+        if !input_map.for_player(index.0).just_pressed(Action::ToggleView) || camera.camera_transition.is_some() || matches!(camera.mode, CameraMode::Photo | CameraMode::Fixed) {
+            continue;
         }
-    } else {
-        println!("Camera: No camera found");
+        let Ok(player_transform) = player_query.get(camera.target) else {
+            continue;
+        };
+        toggle_player_camera_mode(&mut camera, &mut shake, collision, camera_transform, player_transform, &settings);
+    }
+}
+
+// Starts the move to the next view, third person to first person to top-down and round again,
+// from wherever the camera is now to where that view would put it
+fn toggle_player_camera_mode(
+    camera: &mut ThirdPersonCamera,
+    shake: &mut CameraShake,
+    collision: &CameraCollision,
+    camera_transform: &Transform,
+    player_transform: &Transform,
+    settings: &CameraSettings,
+) {
+    let to_mode = match camera.mode {
+        CameraMode::ThirdPerson => CameraMode::FirstPerson,
+        CameraMode::FirstPerson => CameraMode::TopDown,
+        CameraMode::TopDown | CameraMode::Photo | CameraMode::Fixed => CameraMode::ThirdPerson,
+    };
+    let (to_pos, to_rot) = match to_mode {
+        CameraMode::FirstPerson => {
+            // Start looking the same way the third person camera was
+            let forward = camera_transform.forward();
+            camera.yaw = (-forward.x).atan2(-forward.z);
+            camera.pitch = 0.0;
+            (Vec3::Y * FIRST_PERSON_EYE_HEIGHT, math::yaw_pitch_rotation(camera.yaw, camera.pitch))
+        }
+        CameraMode::TopDown => {
+            // Turned whichever way it was last left, finished turning
+            camera.top_down_yaw = camera.top_down_target_yaw;
+            top_down_pose(camera.top_down_yaw, camera.top_down_height, settings.top_down_tilt)
+        }
+        _ => {
+            // Coming down from above, keep walking the way the top of the screen was
+            if camera.mode == CameraMode::TopDown {
+                camera.current_rotation = camera.top_down_target_yaw;
+                camera.freelook_yaw = 0.0;
+                camera.freelook_pitch = 0.0;
+            }
+            // Behind the player where the follow would settle, short of anything it last collided with
+            let look_target = Vec3::Y * settings.height;
+            let direction = math::orbit_direction(camera.current_rotation + camera.freelook_yaw, camera.freelook_pitch);
+            let to_pos = look_target + direction * collision.distance.min(camera.distance);
+            (to_pos, math::look_rotation(to_pos, look_target).unwrap_or(camera_transform.rotation))
+        }
+    };
+    // The third person follow subtracts last frame's shake offset, which no longer applies
+    let shake_offset = std::mem::take(&mut shake.offset);
+    camera.camera_velocity = Vec3::ZERO;
+    camera.camera_transition = Some(CameraTransition {
+        from_pos: camera_transform.translation - shake_offset - player_transform.translation,
+        to_pos,
+        from_rot: camera_transform.rotation,
+        to_rot,
+        progress: 0.0,
+        duration: settings.transition_duration,
+        to_mode,
+    });
+    println!("Camera mode: {:?} -> {:?}", camera.mode, to_mode);
+}
+
+// Only this player's model, and only once the camera is all the way in first person, so it
+// doesn't vanish while the camera is still pulling in or stay hidden while it pulls out.
+// Visibility isn't per view, so a split-screen partner loses sight of it too.
+fn update_player_model_visibility(
+    camera_query: Query<&ThirdPersonCamera, Changed<ThirdPersonCamera>>,
+    mut model_query: Query<(&Parent, &mut Visibility), With<PlayerModel>>,
+) {
+    for camera in camera_query.iter() {
+        let hidden = camera.mode == CameraMode::FirstPerson && camera.camera_transition.is_none();
+        let wanted = if hidden { Visibility::Hidden } else { Visibility::Inherited };
+        for (parent, mut visibility) in model_query.iter_mut() {
+            if parent.get() == camera.target && *visibility != wanted {
+                *visibility = wanted;
+            }
+        }
+    }
+}
+
+// The cursor belongs to the keyboard and mouse player: grabbed and hidden while they aim, drag to
+// rotate, free-look or look around in first person, photo mode or the spectator camera, and always
+// released when the window loses focus so it can't get stuck hidden. It's grabbed again on refocus if still wanted. Bevy falls back to
+// Confined where Locked isn't supported.
+fn cursor_grab(
+    mut focus_events: EventReader<WindowFocused>,
+    mut unfocused: Local<bool>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+    camera_query: Query<(&PlayerIndex, &ThirdPersonCamera)>,
+    spectator: Res<SpectatorMode>,
+    input_map: Res<InputMap>,
+    settings: Res<CameraSettings>,
+) {
+    for event in focus_events.read() {
+        *unfocused = !event.focused;
+    }
+    let Ok(mut window) = window_query.get_single_mut() else {
+        return;
+    };
+    let mouse_look = camera_query
+        .iter()
+        .any(|(index, camera)| index.0 == 0 && matches!(camera.mode, CameraMode::FirstPerson | CameraMode::Photo))
+        || spectator.is_active();
+    let input = input_map.for_player(0);
+    let rotating = input.pressed(Action::RotateCamera) || input.pressed(Action::FreeLook) || input.pressed(Action::Aim);
+    let grab = !*unfocused && (settings.always_grab_cursor || mouse_look || rotating);
+
+    let grab_mode = if grab { CursorGrabMode::Locked } else { CursorGrabMode::None };
+    if window.cursor.grab_mode != grab_mode {
+        window.cursor.grab_mode = grab_mode;
+    }
+    if window.cursor.visible == grab {
+        window.cursor.visible = !grab;
+    }
+}
+
+fn receive_camera_shake(
+    mut shake_events: EventReader<CameraShakeEvent>,
+    mut shake_query: Query<&mut CameraShake>,
+) {
+    for event in shake_events.read() {
+        for mut shake in shake_query.iter_mut() {
+            shake.add_trauma(event.trauma);
+        }
+    }
+}
+
+// Hard landings jolt the landing player's camera, more the faster they hit
+fn landing_shake(
+    mut landed_events: EventReader<PlayerLanded>,
+    mut shake_query: Query<(&ThirdPersonCamera, &mut CameraShake)>,
+) {
+    for event in landed_events.read() {
+        let excess = (event.impact_speed - LANDING_SHAKE_SPEED) / (LANDING_FULL_SHAKE_SPEED - LANDING_SHAKE_SPEED);
+        if excess <= 0.0 {
+            continue;
+        }
+        for (camera, mut shake) in shake_query.iter_mut() {
+            if camera.target == event.player {
+                shake.add_trauma(excess.min(1.0) * LANDING_MAX_TRAUMA);
+            }
+        }
+    }
+}
+
+// Radial dead zone, then a squared response so small deflections give fine control
+fn shape_stick(stick: Vec2, deadzone: f32) -> Vec2 {
+    let magnitude = stick.length();
+    if magnitude <= deadzone {
+        return Vec2::ZERO;
+    }
+    let scaled = ((magnitude - deadzone) / (1.0 - deadzone).max(f32::EPSILON)).min(1.0);
+    stick / magnitude * scaled * scaled
+}
+
+fn read_camera_input(
+    mut camera_query: Query<(&PlayerIndex, &ThirdPersonCamera, &mut CameraInput)>,
+    input_map: Res<InputMap>,
+    settings: Res<CameraSettings>,
+) {
+    for (index, camera, mut camera_input) in camera_query.iter_mut() {
+        let input = input_map.for_player(index.0);
+        // Aiming slows the look down for finer control
+        let invert = settings.invert() * if camera.aiming { settings.aim_sensitivity_multiplier } else { 1.0 };
+        let stick = shape_stick(input.look_stick(), settings.gamepad_deadzone);
+        *camera_input = CameraInput {
+            mouse_delta: input.look_delta()
+                * Vec2::new(settings.horizontal_sensitivity, settings.vertical_sensitivity)
+                * invert,
+            // Stick up means look up, the opposite of the mouse's y
+            stick_rate: Vec2::new(stick.x, -stick.y) * settings.gamepad_sensitivity.to_radians() * invert,
+            zoom_steps: input.axis(Action::ZoomIn) - input.axis(Action::ZoomOut),
+            stick_zoom: stick.y,
+        };
     }
 }
 
 fn camera_rotation(
-    mut camera_query: Query<&mut ThirdPersonCamera>,
-    mouse_input: Res<Input<MouseButton>>,
-    mut mouse_motion: EventReader<MouseMotion>,
+    mut camera_query: Query<(&PlayerIndex, &mut ThirdPersonCamera, &CameraInput)>,
+    input_map: Res<InputMap>,
+    settings: Res<CameraSettings>,
     time: Res<Time>,
 ) {
-    if let Ok(mut camera) = camera_query.get_single_mut() {
-        // Handle mouse rotation when right mouse button is held
-        if mouse_input.pressed(MouseButton::Right) {
-            for ev in mouse_motion.read() {
-                let rotation_delta = ev.delta.x * camera.rotation_speed * time.delta_seconds() * 0.01;
-                camera.current_rotation -= rotation_delta;
-                println!("Camera rotation: {} (delta: {})", camera.current_rotation, rotation_delta);
+    for (index, mut camera, camera_input) in camera_query.iter_mut() {
+        // A fixed camera can't be turned
+        if camera.mode == CameraMode::Fixed {
+            continue;
+        }
+        let stick_turn = camera_input.stick_rate * time.delta_seconds();
+
+        // Top-down turns in 45 degree steps, eased by the follow
+        if camera.mode == CameraMode::TopDown {
+            let input = input_map.for_player(index.0);
+            let steps = input.just_pressed(Action::RotateViewLeft) as i32 - input.just_pressed(Action::RotateViewRight) as i32;
+            if steps != 0 {
+                camera.top_down_target_yaw = math::wrap_angle(camera.top_down_target_yaw + steps as f32 * FRAC_PI_4);
+                println!("Top-down rotation: {:.0} degrees", camera.top_down_target_yaw.to_degrees());
             }
+            continue;
+        }
+
+        // First person looks around with the mouse directly, the cursor is grabbed
+        if camera.mode == CameraMode::FirstPerson {
+            let look = camera_input.mouse_delta * FIRST_PERSON_SENSITIVITY + stick_turn;
+            camera.yaw = math::wrap_angle(camera.yaw - look.x);
+            camera.pitch = math::clamp_pitch(camera.pitch - look.y, FIRST_PERSON_MAX_PITCH);
+            continue;
+        }
+
+        // Free-look swings the view around while the movement frame stays put
+        let input = input_map.for_player(index.0);
+        if input.pressed(Action::FreeLook) && camera.lock_on.is_none() {
+            camera.since_manual_input = 0.0;
+            camera.recentering = false;
+            let look = camera_input.mouse_delta * THIRD_PERSON_SENSITIVITY + stick_turn;
+            camera.freelook_yaw -= look.x;
+            camera.freelook_pitch = math::clamp_pitch(camera.freelook_pitch + look.y, FREELOOK_MAX_PITCH);
+            continue;
+        }
+        if settings.freelook_commit && camera.freelook_yaw != 0.0 {
+            camera.current_rotation = math::wrap_angle(camera.current_rotation + camera.freelook_yaw);
+            camera.freelook_yaw = 0.0;
+            println!("Camera rotation: {} (free-look committed)", camera.current_rotation);
+        }
+        let freelook_ease = (-time.delta_seconds() / FREELOOK_RETURN_TIME).exp();
+        camera.freelook_yaw *= freelook_ease;
+        camera.freelook_pitch *= freelook_ease;
+
+        // Aiming looks up and down too, settling back level once it's let go
+        if camera.aiming && camera.lock_on.is_none() {
+            let look = camera_input.mouse_delta * THIRD_PERSON_SENSITIVITY + stick_turn;
+            camera.aim_pitch = math::clamp_pitch(camera.aim_pitch + look.y, FREELOOK_MAX_PITCH);
+        } else {
+            camera.aim_pitch *= freelook_ease;
+        }
+
+        // Lock-on decides where a third person camera points
+        if camera.lock_on.is_some() {
+            continue;
+        }
+
+        // The mouse orbits while aiming, the rotate binding is held or the cursor is always grabbed,
+        // the stick whenever it's pushed
+        let mut rotation_delta = stick_turn.x;
+        if camera.aiming || input.pressed(Action::RotateCamera) || settings.always_grab_cursor {
+            rotation_delta += camera_input.mouse_delta.x * THIRD_PERSON_SENSITIVITY;
+        }
+        if rotation_delta != 0.0 {
+            camera.since_manual_input = 0.0;
+            camera.recentering = false;
+            camera.current_rotation = math::wrap_angle(camera.current_rotation - rotation_delta);
         }
     }
 }
 
-fn camera_zoom(
+// Eases a third person camera round behind the player's movement. Any manual input restarts the
+// cooldown, so it never fights the user; lock-on and free-look point the camera themselves.
+fn auto_align_camera(
     mut camera_query: Query<&mut ThirdPersonCamera>,
-    mut scroll_evr: EventReader<MouseWheel>,
+    player_query: Query<(&Player, &Velocity)>,
+    settings: Res<CameraSettings>,
+    time: Res<Time>,
+) {
+    let delta_seconds = time.delta_seconds();
+    for mut camera in camera_query.iter_mut() {
+        camera.since_manual_input += delta_seconds;
+        let Ok((player, velocity)) = player_query.get(camera.target) else {
+            continue;
+        };
+        // Relative to whatever the player is riding, so a moving platform alone doesn't count
+        let movement = (velocity.linvel - player.platform_velocity) * Vec3::new(1.0, 0.0, 1.0);
+        if movement.length() > AUTO_ALIGN_MIN_SPEED {
+            camera.moving_time += delta_seconds;
+        } else {
+            camera.moving_time = 0.0;
+        }
+
+        let engaged = settings.auto_align
+            && camera.mode == CameraMode::ThirdPerson
+            && camera.lock_on.is_none()
+            && !camera.aiming
+            && !camera.recentering
+            && camera.freelook_yaw.abs() < 0.01
+            && camera.since_manual_input > AUTO_ALIGN_INPUT_COOLDOWN
+            && camera.moving_time > AUTO_ALIGN_MOVE_TIME;
+        if engaged {
+            camera.current_rotation = auto_align_step(camera.current_rotation, movement, settings.auto_align_speed * delta_seconds);
+        }
+    }
+}
+
+// Turns rotation up to max_step towards sitting behind horizontal movement, i.e. the camera
+// direction opposite to it
+fn auto_align_step(rotation: f32, movement: Vec3, max_step: f32) -> f32 {
+    let behind = (-movement.x).atan2(-movement.z);
+    let turn = (behind - rotation + PI).rem_euclid(TAU) - PI;
+    if turn.abs() > AUTO_ALIGN_MAX_ANGLE {
+        return rotation;
+    }
+    math::wrap_angle(rotation + turn.clamp(-max_step, max_step))
+}
+
+// Middle click starts a third person camera recentering, and while it is, springs the orbit round
+// to sit opposite the way the player faces. That comes from the player's rotation rather than how
+// it's moving, so it works standing still. The zoom goes back to start_distance and the free-look
+// and aim tilts ease back level by themselves.
+fn recenter_camera(
+    mut camera_query: Query<(&PlayerIndex, &mut ThirdPersonCamera)>,
+    player_query: Query<&Transform, With<Player>>,
+    input_map: Res<InputMap>,
+    settings: Res<CameraSettings>,
+    time: Res<Time>,
+) {
+    for (index, mut camera) in camera_query.iter_mut() {
+        if camera.mode == CameraMode::ThirdPerson && input_map.for_player(index.0).just_pressed(Action::RecenterCamera) {
+            camera.recenter();
+            println!("Camera recentering");
+        }
+        if !camera.recentering {
+            continue;
+        }
+        // Lock-on decides where the camera points, and the other views don't orbit
+        let player_transform = player_query.get(camera.target).ok();
+        let Some(player_transform) = player_transform.filter(|_| camera.mode == CameraMode::ThirdPerson && camera.lock_on.is_none()) else {
+            camera.recentering = false;
+            continue;
+        };
+
+        // The player faces +Z turned by its yaw, so behind it is half a turn round from that
+        let (yaw, _, _) = player_transform.rotation.to_euler(EulerRot::YXZ);
+        let behind = math::wrap_angle(yaw + PI);
+        let mut velocity = camera.recenter_velocity;
+        let offset = math::smooth_damp(math::wrap_angle(camera.current_rotation - behind), 0.0, &mut velocity, RECENTER_TIME, time.delta_seconds());
+        camera.recenter_velocity = velocity;
+        camera.current_rotation = math::wrap_angle(behind + offset);
+        camera.target_distance = settings.start_distance.clamp(settings.min_distance, settings.max_distance);
+        camera.fov = settings.fov;
+        if offset.abs() < RECENTER_DONE_ANGLE {
+            camera.current_rotation = behind;
+            camera.recentering = false;
+            println!("Camera recentered: rotation {}", camera.current_rotation);
+        }
+    }
+}
+
+// A respawned player gets their camera back behind them
+fn recenter_on_respawn(mut respawned_events: EventReader<PlayerRespawned>, mut camera_query: Query<&mut ThirdPersonCamera>) {
+    for event in respawned_events.read() {
+        for mut camera in camera_query.iter_mut().filter(|camera| camera.target == event.player) {
+            camera.recenter();
+        }
+    }
+}
+
+// Mirrors the over the shoulder view to the other side, or centres it and back
+fn shoulder_input(
+    mut camera_query: Query<(&PlayerIndex, &mut ThirdPersonCamera)>,
+    input_map: Res<InputMap>,
+) {
+    for (index, mut camera) in camera_query.iter_mut() {
+        let input = input_map.for_player(index.0);
+        if input.just_pressed(Action::SwapShoulder) {
+            // Swapping while centred comes out on the other shoulder
+            camera.shoulder_side = -camera.shoulder_side;
+            camera.shoulder_centered = false;
+            println!("Camera shoulder: {}", if camera.shoulder_side > 0.0 { "right" } else { "left" });
+        }
+        if input.just_pressed(Action::CenterCamera) {
+            camera.shoulder_centered = !camera.shoulder_centered;
+            println!("Camera centred: {}", camera.shoulder_centered);
+        }
+    }
+}
+
+fn camera_zoom(
+    mut camera_query: Query<(&PlayerIndex, &mut ThirdPersonCamera, &CameraInput)>,
+    input_map: Res<InputMap>,
+    settings: Res<CameraSettings>,
+    noclip_query: Query<(), (With<Player>, With<Noclip>)>,
+    time: Res<Time>,
+) {
+    for (index, mut camera, camera_input) in camera_query.iter_mut() {
+        // The scroll wheel sets the fly speed while noclipping
+        if noclip_query.contains(camera.target) {
+            continue;
+        }
+        let zoom_delta = (camera_input.zoom_steps * ZOOM_STEP
+            + camera_input.stick_zoom * GAMEPAD_ZOOM_RATE * time.delta_seconds())
+            * settings.zoom_speed;
+        // Top-down zooms by changing height
+        if camera.mode == CameraMode::TopDown {
+            let input = input_map.for_player(index.0);
+            if input.just_pressed(Action::ResetZoom) || input.just_pressed(Action::RecenterCamera) {
+                camera.top_down_height = settings.top_down_height;
+            } else if zoom_delta != 0.0 {
+                camera.top_down_height = (camera.top_down_height - zoom_delta * TOP_DOWN_ZOOM_SCALE)
+                    .clamp(settings.top_down_min_height, settings.top_down_max_height);
+            }
+            continue;
+        }
+        if camera.mode != CameraMode::ThirdPerson {
+            continue;
+        }
+        if input_map.for_player(index.0).just_pressed(Action::ResetZoom) {
+            camera.target_distance = settings.start_distance.clamp(settings.min_distance, settings.max_distance);
+            camera.fov = settings.fov;
+            println!("Camera zoom reset: distance {}, fov {}", camera.target_distance, camera.fov);
+        } else {
+            // Third person has no pitch, so the stick's vertical axis zooms instead: up pulls in
+            if zoom_delta != 0.0 {
+                camera.recentering = false;
+                let (old_distance, old_fov) = (camera.target_distance, camera.fov);
+                (camera.target_distance, camera.fov) = zoom(&settings, camera.target_distance, camera.fov, zoom_delta);
+                println!(
+                    "Camera zoom: distance {} -> {}, fov {} -> {} (delta: {})",
+                    old_distance, camera.target_distance, old_fov, camera.fov, zoom_delta,
+                );
+            }
+        }
+    }
+}
+
+// Aim is held in third and first person, not while moving between them or in the other views.
+// The blend eases in and out over aim_blend_time.
+fn aim_input(
+    mut camera_query: Query<(&PlayerIndex, &mut ThirdPersonCamera)>,
+    input_map: Res<InputMap>,
+    settings: Res<CameraSettings>,
+    time: Res<Time>,
+) {
+    for (index, mut camera) in camera_query.iter_mut() {
+        let aiming = input_map.for_player(index.0).pressed(Action::Aim)
+            && matches!(camera.mode, CameraMode::ThirdPerson | CameraMode::FirstPerson)
+            && camera.camera_transition.is_none();
+        if aiming != camera.aiming {
+            camera.aiming = aiming;
+            println!("Camera {}: {}", index.0 + 1, if aiming { "aiming" } else { "stopped aiming" });
+        }
+        let step = if settings.aim_blend_time > 0.0 { time.delta_seconds() / settings.aim_blend_time } else { 1.0 };
+        let target = if aiming { 1.0 } else { 0.0 };
+        camera.aim_blend += (target - camera.aim_blend).clamp(-step, step);
+    }
+}
+
+// The zoomed field of view, narrowed towards aim_fov while aiming in first person or in the zoom
+// modes that aim by narrowing. Written before the follow so the renderers see it this frame.
+fn update_camera_fov(
+    mut camera_query: Query<(&ThirdPersonCamera, &mut Projection)>,
+    settings: Res<CameraSettings>,
+) {
+    for (camera, mut projection) in camera_query.iter_mut() {
+        let narrows = camera.mode == CameraMode::FirstPerson || settings.zoom_mode != ZoomMode::Dolly;
+        let aim = if narrows { math::smoothstep(camera.aim_blend) } else { 0.0 };
+        let fov = camera.fov + (settings.aim_fov.min(camera.fov) - camera.fov) * aim;
+        if let Projection::Perspective(perspective) = projection.as_mut() {
+            let fov = fov.to_radians();
+            if perspective.fov != fov {
+                perspective.fov = fov;
+            }
+        }
+    }
+}
+
+// Follows the middle of each player's view, which is where a crosshair would be
+fn update_aim_target(
+    camera_query: Query<(&PlayerIndex, &ThirdPersonCamera, &Transform)>,
+    mut aim_target: ResMut<AimTarget>,
+) {
+    let players = camera_query.iter().map(|(index, ..)| index.0 + 1).max().unwrap_or(0);
+    let idle = Aim {
+        aiming: false,
+        ray: Ray {
+            origin: Vec3::ZERO,
+            direction: Vec3::NEG_Z,
+        },
+    };
+    aim_target.0.resize(players, idle);
+    for (index, camera, transform) in camera_query.iter() {
+        aim_target.0[index.0] = Aim {
+            aiming: camera.aiming,
+            ray: Ray {
+                origin: transform.translation,
+                direction: transform.forward(),
+            },
+        };
+    }
+}
+
+// Applies zoom_delta (in distance, positive zooms in) to a camera's target distance and field of
+// view, returning both
+fn zoom(settings: &CameraSettings, distance: f32, fov: f32, zoom_delta: f32) -> (f32, f32) {
+    let clamp_distance = |distance: f32| distance.clamp(settings.min_distance, settings.max_distance);
+    match settings.zoom_mode {
+        ZoomMode::Dolly => (clamp_distance(distance - zoom_delta), fov),
+        ZoomMode::Fov => {
+            let fov = (fov - zoom_delta * FOV_ZOOM_SCALE).clamp(settings.min_fov, settings.max_fov);
+            (distance, fov)
+        }
+        ZoomMode::Hybrid => {
+            let min_fov = settings.min_fov.min(settings.fov);
+            if zoom_delta > 0.0 {
+                // In to min_distance first, whatever is left narrows the view
+                let dolly = zoom_delta.min((distance - settings.min_distance).max(0.0));
+                let fov = (fov - (zoom_delta - dolly) * FOV_ZOOM_SCALE).clamp(min_fov, settings.fov);
+                (clamp_distance(distance - dolly), fov)
+            } else {
+                // Back out to the normal view first, then dolly out with what's left
+                let widen = (-zoom_delta * FOV_ZOOM_SCALE).min((settings.fov - fov).max(0.0));
+                let dolly = -zoom_delta - widen / FOV_ZOOM_SCALE;
+                (clamp_distance(distance + dolly), (fov + widen).clamp(min_fov, settings.fov))
+            }
+        }
+    }
+}
+
+// Steps the first player's camera through everything targetable, in name order
+#[cfg(feature = "dev-tools")]
+fn cycle_camera_target(
+    input_map: Res<InputMap>,
+    mut camera_query: Query<(&PlayerIndex, &mut ThirdPersonCamera)>,
+    targetable_query: Query<(Entity, &CameraTargetable)>,
+) {
+    if !input_map.just_pressed(Action::CycleCameraTarget) {
+        return;
+    }
+    let Some((_, mut camera)) = camera_query.iter_mut().find(|(index, _)| index.0 == 0) else {
+        return;
+    };
+    let mut targets: Vec<(Entity, &CameraTargetable)> = targetable_query.iter().collect();
+    if targets.is_empty() {
+        return;
+    }
+    targets.sort_by(|(_, a), (_, b)| a.name.cmp(&b.name));
+    let next = targets
+        .iter()
+        .position(|(entity, _)| *entity == camera.target)
+        .map_or(0, |current| (current + 1) % targets.len());
+    let (entity, targetable) = targets[next];
+    camera.set_camera_target(entity);
+    println!("Camera target: {}", targetable.name);
+}
+
+// Sends CameraTargetChanged whenever a camera starts following something else, however it was
+// switched. The first target a camera gets isn't a change.
+fn announce_camera_target_changes(
+    camera_query: Query<(Entity, &ThirdPersonCamera), Changed<ThirdPersonCamera>>,
+    mut last_targets: Local<HashMap<Entity, Entity>>,
+    mut changed_events: EventWriter<CameraTargetChanged>,
+) {
+    for (entity, camera) in camera_query.iter() {
+        let previous = last_targets.insert(entity, camera.target).unwrap_or(Entity::PLACEHOLDER);
+        if previous != camera.target && previous != Entity::PLACEHOLDER {
+            changed_events.send(CameraTargetChanged {
+                camera: entity,
+                previous,
+                current: camera.target,
+            });
+        }
+    }
+}
+
+fn log_camera_target_changes(mut changed_events: EventReader<CameraTargetChanged>) {
+    for event in changed_events.read() {
+        println!("Camera {:?} target changed from {:?} to {:?}", event.camera, event.previous, event.current);
+    }
+}
+
+// Settings edits reach cameras that already exist: zoom limits and field of view
+fn apply_camera_settings(
+    settings: Res<CameraSettings>,
+    mut camera_query: Query<(&mut ThirdPersonCamera, &mut Projection)>,
 ) {
-    if let Ok(mut camera) = camera_query.get_single_mut() {
-        for ev in scroll_evr.read() {
-            let zoom_delta = ev.y * camera.zoom_speed * 0.1;
-            let old_distance = camera.distance;
-            camera.distance = (camera.distance - zoom_delta)
-                .clamp(camera.min_distance, camera.max_distance);
-            println!("Camera zoom: {} -> {} (delta: {})", old_distance, camera.distance, zoom_delta);
+    if !settings.is_changed() || settings.is_added() {
+        return;
+    }
+    for (mut camera, mut projection) in camera_query.iter_mut() {
+        camera.target_distance = camera.target_distance.clamp(settings.min_distance, settings.max_distance);
+        // Any field of view zoom starts over from the new setting
+        camera.fov = settings.fov;
+        if let Projection::Perspective(perspective) = projection.as_mut() {
+            perspective.fov = settings.fov.to_radians();
         }
     }
 }
 
 fn debug_camera_state(
     camera_query: Query<&ThirdPersonCamera>,
+    settings: Res<CameraSettings>,
+    mut timers: ResMut<DebugLogTimers>,
     time: Res<Time>,
 ) {
-    static mut LAST_LOG_TIME: f32 = 0.0;
-    
-    unsafe {
-        if time.elapsed_seconds() - LAST_LOG_TIME > 3.0 {
-            if let Ok(camera) = camera_query.get_single() {
-                println!("=== CAMERA DEBUG ===");
-                println!("Target entity: {:?}", camera.target);
-                println!("Distance: {}", camera.distance);
-                println!("Height: {}", camera.height);
-                println!("Current rotation: {}", camera.current_rotation);
-                println!("Rotation speed: {}", camera.rotation_speed);
-                println!("===================");
-            } else {
-                println!("ERROR: No camera found in debug system!");
+    if !timers.camera.tick(time.delta()).just_finished() {
+        return;
+    }
+    if camera_query.is_empty() {
+        warn!(target: DEBUG_LOG_TARGET, "No camera found in debug system");
+    }
+    for camera in camera_query.iter() {
+        debug!(
+            target: DEBUG_LOG_TARGET,
+            "Camera: target {:?}, distance {}, height {}, rotation {}, sensitivity {}",
+            camera.target, camera.distance, settings.height, camera.current_rotation, settings.horizontal_sensitivity,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use crate::debug::DebugOverlay;
+    use crate::player::{update_camera_target, Players};
+    use crate::input_map::InputSnapshot;
+    use crate::test_app::{physics_app, TempDir, FRAME};
+    use std::time::Duration;
+
+    // A headless app with one third person camera, its systems added by each test
+    fn camera_app(settings: CameraSettings) -> App {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .insert_resource(InputMap::default())
+            .insert_resource(settings.clone());
+        app.world.run_system_once(move |mut commands: Commands| spawn_camera(&mut commands, 0, &settings));
+        app
+    }
+
+    fn camera(app: &mut App) -> &ThirdPersonCamera {
+        app.world.query::<&ThirdPersonCamera>().single(&app.world)
+    }
+
+    // Feeds the same total mouse movement over frames of the given length
+    fn orbit_after(total_pixels: f32, frames: u32, dt: f32) -> f32 {
+        let mut app = camera_app(CameraSettings {
+            always_grab_cursor: true,
+            ..default()
+        });
+        app.add_systems(Update, camera_rotation);
+        for _ in 0..frames {
+            app.world.resource_mut::<Time>().advance_by(Duration::from_secs_f32(dt));
+            let mut input = app.world.query::<&mut CameraInput>().single_mut(&mut app.world);
+            input.mouse_delta = Vec2::new(total_pixels / frames as f32, 0.0);
+            app.update();
+        }
+        camera(&mut app).current_rotation
+    }
+
+    #[test]
+    fn mouse_yaw_ignores_how_the_movement_is_split_into_frames() {
+        let one_frame = orbit_after(120.0, 1, 1.0 / 20.0);
+        for (frames, dt) in [(4, 1.0 / 30.0), (16, 1.0 / 144.0), (60, 1.0 / 240.0)] {
+            let split = orbit_after(120.0, frames, dt);
+            assert!((split - one_frame).abs() < 1e-4, "{frames} frames: {split} vs {one_frame}");
+        }
+    }
+
+    #[test]
+    fn mouse_yaw_matches_across_the_wrap() {
+        // Far enough to wrap past +-pi at least once
+        let total = 3.5 * PI / THIRD_PERSON_SENSITIVITY;
+        let one_frame = orbit_after(total, 1, 1.0 / 60.0);
+        let split = orbit_after(total, 90, 1.0 / 60.0);
+        assert!((-PI..=PI).contains(&split));
+        assert!(math::wrap_angle(split - one_frame).abs() < 1e-3, "{split} vs {one_frame}");
+    }
+
+    #[test]
+    fn camera_debug_log_is_throttled() {
+        #[derive(Resource, Default)]
+        struct LoggedFrames(u32);
+
+        let mut app = camera_app(CameraSettings::default());
+        app.init_resource::<DebugLogTimers>()
+            .insert_resource(DebugOverlay { enabled: true })
+            .init_resource::<LoggedFrames>()
+            .add_systems(Update, (
+                debug_camera_state.run_if(debug_overlay_enabled),
+                (|timers: Res<DebugLogTimers>, mut logged: ResMut<LoggedFrames>| {
+                    logged.0 += timers.camera.just_finished() as u32;
+                }).after(debug_camera_state),
+            ));
+        for _ in 0..600 {
+            app.world.resource_mut::<Time>().advance_by(Duration::from_secs_f32(1.0 / 60.0));
+            app.update();
+        }
+        // Every 3 seconds over 10
+        assert_eq!(app.world.resource::<LoggedFrames>().0, 3);
+    }
+
+    #[test]
+    fn camera_settings_survive_a_round_trip_through_ron() {
+        let settings = CameraSettings {
+            horizontal_sensitivity: 0.4,
+            invert_y: true,
+            zoom_mode: ZoomMode::Fov,
+            spring_stiffness: Some(90.0),
+            shoulder_offset: Vec3::new(0.6, 0.2, 0.0),
+            follow_mode: FollowMode::DeadZone { half_extents: Vec2::new(0.5, 0.8) },
+            ..default()
+        };
+        let text = ron::to_string(&settings).unwrap();
+        assert_eq!(ron::from_str::<CameraSettings>(&text).unwrap(), settings);
+    }
+
+    #[test]
+    fn saved_camera_settings_load_back_the_same() {
+        let dir = TempDir::new("camera-settings");
+        let path = dir.path().join("settings").join("camera.ron");
+        let path = path.to_str().unwrap();
+        let settings = CameraSettings {
+            vertical_sensitivity: 0.7,
+            invert_x: true,
+            zoom_mode: ZoomMode::Hybrid,
+            min_distance: 2.5,
+            ..default()
+        };
+        settings.save(path);
+        assert_eq!(CameraSettings::load(path), settings);
+    }
+
+    #[test]
+    fn missing_camera_settings_fields_keep_their_defaults() {
+        let settings: CameraSettings = ron::from_str("(fov: 60.0, invert_x: true)").unwrap();
+        assert_eq!(settings, CameraSettings {
+            fov: 60.0,
+            invert_x: true,
+            ..default()
+        });
+        assert_eq!(ron::from_str::<CameraSettings>("()").unwrap(), CameraSettings::default());
+    }
+
+    #[test]
+    fn shipped_camera_settings_match_the_defaults() {
+        assert_eq!(CameraSettings::load(CAMERA_SETTINGS_PATH), CameraSettings::default());
+        assert_eq!(CameraSettings::load("assets/settings/missing.ron"), CameraSettings::default());
+    }
+
+    fn shake(trauma: f32) -> CameraShake {
+        let mut shake = CameraShake::default();
+        shake.add_trauma(trauma);
+        shake
+    }
+
+    #[test]
+    fn trauma_decays_linearly_at_any_frame_rate() {
+        // Half a second in, whatever the frame rate
+        for fps in [30, 60, 144] {
+            let mut shake = shake(0.9);
+            for _ in 0..fps / 2 {
+                shake.decay(1.0 / fps as f32);
+            }
+            assert!((shake.trauma - (0.9 - SHAKE_DECAY * 0.5)).abs() < 1e-4, "{} at {fps} fps", shake.trauma);
+        }
+    }
+
+    #[test]
+    fn trauma_runs_out_and_stays_at_zero() {
+        let mut shake = shake(0.6);
+        shake.decay(0.6 / SHAKE_DECAY - 0.01);
+        assert!(shake.trauma > 0.0);
+        shake.decay(0.02);
+        assert_eq!(shake.trauma, 0.0);
+        assert_eq!(shake.time, 0.0);
+        shake.decay(1.0);
+        assert_eq!(shake.trauma, 0.0);
+    }
+
+    #[test]
+    fn trauma_is_capped_at_one() {
+        let mut shake = shake(0.7);
+        shake.add_trauma(0.7);
+        assert_eq!(shake.trauma, 1.0);
+    }
+
+    #[test]
+    fn no_trauma_leaves_the_camera_alone() {
+        let start = Transform::from_xyz(1.0, 4.0, 8.0).looking_at(Vec3::ZERO, Vec3::Y);
+        let mut transform = start;
+        let mut shake = CameraShake::default();
+        apply_shake(&mut transform, &mut shake, 1.0 / 60.0);
+        assert_eq!(transform, start);
+        assert_eq!(shake.offset, Vec3::ZERO);
+    }
+
+    #[test]
+    fn shake_noise_is_deterministic_and_bounded() {
+        for i in 0..2000 {
+            let x = i as f32 * 0.037 - 20.0;
+            let sample = perlin_noise(x);
+            assert_eq!(sample, perlin_noise(x));
+            assert!((-1.0..=1.0).contains(&sample), "{sample} at {x}");
+        }
+        assert_eq!(perlin_noise(3.0), 0.0);
+        assert_eq!(perlin_noise(-7.0), 0.0);
+    }
+
+    #[test]
+    fn stick_inside_the_dead_zone_does_nothing() {
+        assert_eq!(shape_stick(Vec2::ZERO, 0.15), Vec2::ZERO);
+        assert_eq!(shape_stick(Vec2::new(0.1, -0.1), 0.15), Vec2::ZERO);
+        assert_eq!(shape_stick(Vec2::new(0.15, 0.0), 0.15), Vec2::ZERO);
+    }
+
+    #[test]
+    fn stick_response_is_squared_past_the_dead_zone_and_keeps_direction() {
+        // Halfway from the dead zone to the edge gives a quarter
+        let halfway = shape_stick(Vec2::new(0.0, 0.575), 0.15);
+        assert!((halfway.y - 0.25).abs() < 1e-5 && halfway.x == 0.0);
+        let full = shape_stick(Vec2::new(-0.6, 0.8), 0.15);
+        assert!((full - Vec2::new(-0.6, 0.8)).length() < 1e-5);
+        // Corners past the unit circle don't go faster than full deflection
+        assert!(shape_stick(Vec2::new(1.0, 1.0), 0.15).length() <= 1.0 + 1e-5);
+    }
+
+    #[test]
+    fn stick_shaping_is_deterministic_and_monotonic() {
+        let mut previous = 0.0;
+        for i in 0..=100 {
+            let stick = Vec2::new(0.6, 0.8) * (i as f32 / 100.0);
+            let shaped = shape_stick(stick, 0.15);
+            assert_eq!(shaped, shape_stick(stick, 0.15));
+            assert!(shaped.length() >= previous);
+            previous = shaped.length();
+        }
+        // No dead zone at all still works
+        assert_eq!(shape_stick(Vec2::new(0.5, 0.0), 0.0), Vec2::new(0.25, 0.0));
+    }
+
+    fn zoom_settings(zoom_mode: ZoomMode) -> CameraSettings {
+        CameraSettings {
+            zoom_mode,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn dolly_zoom_clamps_the_distance_and_keeps_the_fov() {
+        let settings = zoom_settings(ZoomMode::Dolly);
+        assert_eq!(zoom(&settings, 8.0, 45.0, 2.0), (6.0, 45.0));
+        assert_eq!(zoom(&settings, 4.0, 45.0, 5.0), (settings.min_distance, 45.0));
+        assert_eq!(zoom(&settings, 14.0, 45.0, -5.0), (settings.max_distance, 45.0));
+    }
+
+    #[test]
+    fn fov_zoom_clamps_the_fov_and_keeps_the_distance() {
+        let settings = zoom_settings(ZoomMode::Fov);
+        assert_eq!(zoom(&settings, 8.0, 45.0, 1.0), (8.0, 45.0 - FOV_ZOOM_SCALE));
+        assert_eq!(zoom(&settings, 8.0, 25.0, 10.0), (8.0, settings.min_fov));
+        assert_eq!(zoom(&settings, 8.0, 65.0, -10.0), (8.0, settings.max_fov));
+    }
+
+    #[test]
+    fn hybrid_zoom_dollies_to_min_distance_before_narrowing() {
+        let settings = zoom_settings(ZoomMode::Hybrid);
+        // One metre of dolly left, the other metre narrows the view
+        let (distance, fov) = zoom(&settings, settings.min_distance + 1.0, settings.fov, 2.0);
+        assert_eq!(distance, settings.min_distance);
+        assert!((fov - (settings.fov - FOV_ZOOM_SCALE)).abs() < 1e-5);
+        // Out again widens back to the normal view before dollying, ending where it started
+        let (distance, fov) = zoom(&settings, distance, fov, -2.0);
+        assert!((distance - (settings.min_distance + 1.0)).abs() < 1e-5);
+        assert!((fov - settings.fov).abs() < 1e-5);
+    }
+
+    #[test]
+    fn hybrid_zoom_stays_between_the_limits() {
+        let settings = zoom_settings(ZoomMode::Hybrid);
+        assert_eq!(zoom(&settings, settings.min_distance, settings.min_fov, 3.0), (settings.min_distance, settings.min_fov));
+        // Hybrid only narrows, so it never widens past the normal view
+        assert_eq!(zoom(&settings, settings.max_distance, settings.fov, -3.0), (settings.max_distance, settings.fov));
+    }
+
+    #[test]
+    fn reset_zoom_restores_the_start_distance_and_fov() {
+        let mut app = camera_app(zoom_settings(ZoomMode::Hybrid));
+        app.add_systems(Update, camera_zoom);
+        {
+            let mut camera = app.world.query::<&mut ThirdPersonCamera>().single_mut(&mut app.world);
+            camera.target_distance = 3.0;
+            camera.fov = 30.0;
+        }
+        press(&mut app, Action::ResetZoom);
+        let settings = CameraSettings::default();
+        assert_eq!(camera(&mut app).target_distance, settings.start_distance);
+        assert_eq!(camera(&mut app).fov, settings.fov);
+    }
+
+    #[test]
+    fn auto_align_turns_behind_each_cardinal_direction() {
+        // Movement, and the rotation that sits the camera behind it
+        let cardinals = [(Vec3::NEG_Z, 0.0), (Vec3::X, -FRAC_PI_2), (Vec3::NEG_X, FRAC_PI_2), (Vec3::Z, PI)];
+        for (movement, behind) in cardinals {
+            for start in [behind - 1.0, behind + 1.0] {
+                let start = math::wrap_angle(start);
+                let first = auto_align_step(start, movement * 5.0, 0.05);
+                // The short way round, by no more than the step
+                assert!((math::wrap_angle(first - start).abs() - 0.05).abs() < 1e-5);
+                assert!(math::wrap_angle(behind - first).abs() < 1.0);
+                let mut rotation = first;
+                for _ in 0..40 {
+                    rotation = auto_align_step(rotation, movement * 5.0, 0.05);
+                }
+                assert!(math::wrap_angle(rotation - behind).abs() < 1e-4, "{rotation} for {movement:?}");
             }
-            LAST_LOG_TIME = time.elapsed_seconds();
         }
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn auto_align_leaves_running_at_the_camera_alone() {
+        assert_eq!(auto_align_step(0.0, Vec3::Z * 5.0, 0.05), 0.0);
+        assert_eq!(auto_align_step(0.3, Vec3::new(0.5, 0.0, 5.0), 0.05), 0.3);
+    }
+
+    #[test]
+    fn manual_input_holds_off_auto_align_for_the_cooldown() {
+        let mut app = camera_app(CameraSettings {
+            always_grab_cursor: true,
+            ..default()
+        });
+        app.add_systems(Update, (camera_rotation, auto_align_camera.after(camera_rotation)));
+        // Running off to the right, so the camera wants to swing round to -pi/2
+        let player = app.world.spawn((Player::default(), Velocity::linear(Vec3::X * 6.0))).id();
+        app.world.query::<&mut ThirdPersonCamera>().single_mut(&mut app.world).target = player;
+        let step = |app: &mut App, mouse: f32| {
+            app.world.query::<&mut CameraInput>().single_mut(&mut app.world).mouse_delta = Vec2::new(mouse, 0.0);
+            app.world.resource_mut::<Time>().advance_by(Duration::from_secs_f32(0.125));
+            app.update();
+            camera(app).current_rotation
+        };
+
+        // Nothing for the first second, as if the spawn was the last input, then the first nudge
+        for _ in 0..8 {
+            assert_eq!(step(&mut app, 0.0), 0.0);
+        }
+        assert!(step(&mut app, 0.0) < 0.0);
+
+        // A touch of the mouse stops it dead, and it stays put until a second has passed
+        let after_input = step(&mut app, -1.0);
+        for _ in 0..7 {
+            assert_eq!(step(&mut app, 0.0), after_input);
+        }
+        assert!(step(&mut app, 0.0) < after_input);
+    }
+
+    // A physics app with a third person camera following a bare target at the origin
+    fn follow_app(settings: CameraSettings) -> (App, Entity) {
+        let mut app = physics_app();
+        app.insert_resource(InputMap::default())
+            .insert_resource(settings.clone())
+            .add_event::<CameraSnapEvent>()
+            .add_systems(Update, camera_follow);
+        let target = app.world.spawn(TransformBundle::default()).id();
+        app.world.run_system_once(move |mut commands: Commands| spawn_camera(&mut commands, 0, &settings));
+        app.world.query::<&mut ThirdPersonCamera>().single_mut(&mut app.world).target = target;
+        (app, target)
+    }
+
+    fn collision_distance(app: &mut App) -> f32 {
+        app.world.query::<&CameraCollision>().single(&app.world).distance
+    }
+
+    fn camera_position(app: &mut App) -> Vec3 {
+        app.world.query_filtered::<&Transform, With<ThirdPersonCamera>>().single(&app.world).translation
+    }
+
+    #[test]
+    fn a_wall_between_camera_and_player_pulls_the_camera_in_at_once() {
+        let (mut app, _) = follow_app(CameraSettings::default());
+        for _ in 0..60 {
+            app.update();
+        }
+        // With no rotation the camera sits out along +z
+        let full = collision_distance(&mut app);
+        assert!(camera_position(&mut app).z > 5.0);
+
+        let wall = app.world.spawn((RigidBody::Fixed, Collider::cuboid(5.0, 5.0, 0.1), TransformBundle::from(Transform::from_xyz(0.0, 3.0, 3.0)))).id();
+        // One frame for Rapier to take the wall in, then the camera's straight in front of it
+        app.update();
+        app.update();
+        let blocked = collision_distance(&mut app);
+        assert!(blocked < full - 3.0);
+        assert!(camera_position(&mut app).z < 2.9, "camera at {:?}", camera_position(&mut app));
+
+        // Out again once the wall's gone, eased rather than popping
+        app.world.despawn(wall);
+        app.update();
+        let mut previous = collision_distance(&mut app);
+        let mut frames_to_return = 0;
+        for frame in 1..=60 {
+            app.update();
+            let distance = collision_distance(&mut app);
+            assert!(distance >= previous - 1e-4 && distance - previous < 1.0, "jumped from {previous} to {distance}");
+            previous = distance;
+            if frames_to_return == 0 && (full - distance).abs() < 0.05 {
+                frames_to_return = frame;
+            }
+        }
+        let seconds = frames_to_return as f32 * FRAME;
+        assert!((0.15..=0.6).contains(&seconds), "back out after {seconds} s");
+    }
+
+    #[test]
+    fn one_wheel_line_eases_to_the_new_distance_without_overshooting() {
+        let (mut app, _) = follow_app(CameraSettings::default());
+        app.add_systems(Update, camera_zoom.before(camera_follow));
+        for _ in 0..10 {
+            app.update();
+        }
+        let start = camera(&mut app).distance;
+        app.world.query::<&mut CameraInput>().single_mut(&mut app.world).zoom_steps = 1.0;
+        app.update();
+        app.world.query::<&mut CameraInput>().single_mut(&mut app.world).zoom_steps = 0.0;
+        let target = camera(&mut app).target_distance;
+        assert!((start - target - ZOOM_STEP).abs() < 1e-5);
+
+        let mut previous = start;
+        let mut frames = 1;
+        while frames < 60 && (camera(&mut app).distance - target).abs() > (start - target) * 0.01 {
+            app.update();
+            frames += 1;
+            let distance = camera(&mut app).distance;
+            assert!(distance <= previous && distance >= target, "{distance} past {target}");
+            previous = distance;
+        }
+        // Within 1% of the step about 0.3 seconds on
+        let seconds = frames as f32 * FRAME;
+        assert!((0.2..=0.4).contains(&seconds), "settled after {seconds} s");
+    }
+
+    fn press(app: &mut App, action: Action) {
+        app.world.resource_mut::<InputMap>().apply_snapshot(&InputSnapshot {
+            just_pressed: vec![action],
+            ..default()
+        });
+        app.update();
+        app.world.resource_mut::<InputMap>().apply_snapshot(&InputSnapshot::default());
+    }
+
+    // Where the followed target shows up across the view, negative to the left of centre
+    fn target_screen_x(app: &mut App, target: Entity) -> f32 {
+        let target = app.world.get::<Transform>(target).unwrap().translation;
+        let view = app.world.query_filtered::<&Transform, With<ThirdPersonCamera>>().single(&app.world).compute_matrix().inverse();
+        view.transform_point3(target).x
+    }
+
+    #[test]
+    fn mirroring_the_shoulder_flips_which_side_the_player_is_on() {
+        let (mut app, target) = follow_app(CameraSettings {
+            shoulder_offset: Vec3::new(1.0, 0.0, 0.0),
+            ..default()
+        });
+        app.add_systems(Update, shoulder_input.before(camera_follow));
+        for _ in 0..60 {
+            app.update();
+        }
+        // Looking over the right shoulder puts the player left of centre
+        let right_shoulder = target_screen_x(&mut app, target);
+        assert!(right_shoulder < -0.5, "{right_shoulder}");
+
+        press(&mut app, Action::SwapShoulder);
+        for _ in 0..60 {
+            app.update();
+        }
+        let left_shoulder = target_screen_x(&mut app, target);
+        assert!((left_shoulder + right_shoulder).abs() < 0.05, "{left_shoulder} vs {right_shoulder}");
+
+        press(&mut app, Action::CenterCamera);
+        for _ in 0..60 {
+            app.update();
+        }
+        assert!(target_screen_x(&mut app, target).abs() < 0.05);
+    }
+
+    // Settled lead, and where the target then sits across the view, while it moves sideways
+    fn framing_at(speed: f32) -> (Vec3, f32) {
+        let (mut app, target) = follow_app(CameraSettings::default());
+        app.world.entity_mut(target).insert((Player::default(), Velocity::linear(Vec3::X * speed)));
+        for _ in 0..120 {
+            app.update();
+        }
+        (camera(&mut app).lead, target_screen_x(&mut app, target))
+    }
+
+    #[test]
+    fn sprinting_leads_further_than_walking() {
+        let player = Player::default();
+        let settings = CameraSettings::default();
+        let (idle_lead, idle_x) = framing_at(0.0);
+        let (walk_lead, walk_x) = framing_at(player.speed);
+        let (sprint_lead, sprint_x) = framing_at(player.speed * player.sprint_multiplier);
+        assert_eq!(idle_lead, Vec3::ZERO);
+        assert!(idle_x.abs() < 1e-3);
+        // Both lead the way the player's going, in proportion to speed up to lead_distance
+        assert!((walk_lead - Vec3::X * settings.lead_distance * player.speed / LEAD_FULL_SPEED).length() < 0.01, "{walk_lead:?}");
+        assert!((sprint_lead - Vec3::X * settings.lead_distance).length() < 0.01, "{sprint_lead:?}");
+        // So the player sits further back towards the left of the view
+        assert!(sprint_x < walk_x && walk_x < idle_x);
+        assert!((sprint_x / walk_x - player.sprint_multiplier).abs() < 0.05, "{sprint_x} vs {walk_x}");
+    }
+
+    #[test]
+    fn small_moves_inside_the_dead_zone_leave_the_camera_still() {
+        let (mut app, target) = follow_app(CameraSettings {
+            follow_mode: FollowMode::DeadZone { half_extents: Vec2::new(1.0, 0.8) },
+            lead_distance: 0.0,
+            ..default()
+        });
+        for _ in 0..60 {
+            app.update();
+        }
+        let still = camera_position(&mut app);
+        for frame in 0..120 {
+            let t = frame as f32 * FRAME;
+            app.world.get_mut::<Transform>(target).unwrap().translation = Vec3::new(0.6 * (t * 9.0).sin(), 0.5 * (t * 7.0).cos(), 0.0);
+            app.update();
+            assert!(camera_position(&mut app).distance(still) < 1e-4, "moved to {:?} on frame {frame}", camera_position(&mut app));
+        }
+
+        // Leaving the box drags the camera along by however far past its edge it went
+        app.world.get_mut::<Transform>(target).unwrap().translation = Vec3::new(3.0, 0.0, 0.0);
+        app.update();
+        assert!((camera_position(&mut app).x - still.x - 2.0).abs() < 0.01, "{:?}", camera_position(&mut app));
+    }
+
+    #[test]
+    fn a_despawned_camera_target_falls_back_to_the_player() {
+        let (mut app, player) = follow_app(CameraSettings::default());
+        app.world.entity_mut(player).insert(PlayerIndex(0));
+        app.insert_resource(Players(vec![player]))
+            .add_event::<CameraTargetChanged>()
+            .add_systems(Update, (
+                update_camera_target.before(camera_follow),
+                announce_camera_target_changes.after(camera_follow),
+            ));
+        let tree = app.world.spawn(TransformBundle::from(Transform::from_xyz(5.0, 0.0, -5.0))).id();
+        app.update();
+        app.world.query::<&mut ThirdPersonCamera>().single_mut(&mut app.world).set_camera_target(tree);
+        for _ in 0..10 {
+            app.update();
+        }
+        assert_eq!(camera(&mut app).target, tree);
+
+        app.world.despawn(tree);
+        app.update();
+        assert_eq!(camera(&mut app).target, player);
+        let events = app.world.resource::<Events<CameraTargetChanged>>();
+        let changes: Vec<(Entity, Entity)> = events.get_reader().read(events).map(|event| (event.previous, event.current)).collect();
+        assert_eq!(changes, vec![(player, tree), (tree, player)]);
+        // Eased back across from where it was rather than cut to
+        assert!(camera(&mut app).target_blend.is_some());
+    }
+}
@@ -0,0 +1,217 @@
+use bevy::prelude::*;
+use std::f32::consts::{PI, TAU};
+use std::ops::{Add, Mul, Sub};
+
+// Pitch stays strictly inside ±89°. At ±90° the view direction lines up with world up, so there is
+// no longer a single yaw that produces it and a look_at flips or goes NaN.
+pub const MAX_PITCH: f32 = 89.0 * std::f32::consts::PI / 180.0;
+
+// Clamps to ±limit, never past MAX_PITCH. Garbage in gives a level view rather than NaN.
+pub fn clamp_pitch(pitch: f32, limit: f32) -> f32 {
+    if !pitch.is_finite() {
+        return 0.0;
+    }
+    let limit = limit.min(MAX_PITCH);
+    pitch.clamp(-limit, limit)
+}
+
+// The same angle in (-PI, PI], so yaws that are turned round and round don't grow until they lose
+// precision
+pub fn wrap_angle(angle: f32) -> f32 {
+    PI - (PI - angle).rem_euclid(TAU)
+}
+
+// Unit vector from the orbit centre out to the camera: yaw 0 sits on +Z, positive pitch above
+pub fn orbit_direction(yaw: f32, pitch: f32) -> Vec3 {
+    let pitch = clamp_pitch(pitch, MAX_PITCH);
+    Vec3::new(yaw.sin() * pitch.cos(), pitch.sin(), yaw.cos() * pitch.cos())
+}
+
+// A camera turned by yaw about world up, then tilted by pitch about its own right axis. Looks down
+// -Z at zero, like Bevy cameras, and never rolls.
+pub fn yaw_pitch_rotation(yaw: f32, pitch: f32) -> Quat {
+    Quat::from_rotation_y(yaw) * Quat::from_rotation_x(clamp_pitch(pitch, MAX_PITCH))
+}
+
+// Stand-in for look_at with world up that holds together with the target straight above or below:
+// the pitch is clamped instead of the up vector degenerating. None when the points coincide.
+pub fn look_rotation(from: Vec3, to: Vec3) -> Option<Quat> {
+    let direction = (to - from).try_normalize()?;
+    let yaw = (-direction.x).atan2(-direction.z);
+    let pitch = direction.y.clamp(-1.0, 1.0).asin();
+    Some(yaw_pitch_rotation(yaw, pitch))
+}
+
+// Critically damped spring towards target, settling in roughly smooth_time seconds without
+// overshooting. velocity carries between calls; the polynomial stands in for exp(-omega * dt), so
+// the path it takes barely depends on the frame rate. Works on f32 as well as vectors.
+pub fn smooth_damp<T>(current: T, target: T, velocity: &mut T, smooth_time: f32, delta_seconds: f32) -> T
+where
+    T: Copy + Default + Add<Output = T> + Sub<Output = T> + Mul<f32, Output = T>,
+{
+    if smooth_time <= 0.0 {
+        *velocity = T::default();
+        return target;
+    }
+    let omega = 2.0 / smooth_time;
+    let x = omega * delta_seconds;
+    let decay = 1.0 / (1.0 + x + 0.48 * x * x + 0.235 * x * x * x);
+    let change = current - target;
+    let temp = (*velocity + change * omega) * delta_seconds;
+    *velocity = (*velocity - temp * omega) * decay;
+    target + (change + temp) * decay
+}
+
+// How far a point in a camera's local frame (x right, y up, z back) sits outside a box of
+// half_extents around the screen centre. Depth isn't boxed, so z passes straight through.
+pub fn dead_zone_excess(local: Vec3, half_extents: Vec2) -> Vec3 {
+    let excess = |offset: f32, half_extent: f32| offset.signum() * (offset.abs() - half_extent.max(0.0)).max(0.0);
+    Vec3::new(excess(local.x, half_extents.x), excess(local.y, half_extents.y), local.z)
+}
+
+// Turns +Z to face a horizontal direction. Quat::from_rotation_arc(Vec3::Z, ..) has no unique axis
+// when the direction is exactly -Z and can snap, this is a plain rotation about Y. None for a
+// direction with no horizontal part.
+pub fn facing_rotation(direction: Vec3) -> Option<Quat> {
+    let flat = Vec3::new(direction.x, 0.0, direction.z);
+    if !flat.is_finite() || flat.length_squared() < 1e-8 {
+        return None;
+    }
+    Some(Quat::from_rotation_y(flat.x.atan2(flat.z)))
+}
+
+// Eases 0..1 in and out, flat at both ends
+pub fn smoothstep(t: f32) -> f32 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::FRAC_PI_2;
+
+    fn assert_vec_near(actual: Vec3, expected: Vec3) {
+        assert!(actual.abs_diff_eq(expected, 1e-5), "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn clamp_pitch_stops_short_of_straight_up_and_down() {
+        assert_eq!(clamp_pitch(FRAC_PI_2, FRAC_PI_2), MAX_PITCH);
+        assert_eq!(clamp_pitch(-FRAC_PI_2, FRAC_PI_2), -MAX_PITCH);
+        assert_eq!(clamp_pitch(FRAC_PI_2, 0.5), 0.5);
+        assert_eq!(clamp_pitch(f32::NAN, FRAC_PI_2), 0.0);
+        assert_eq!(clamp_pitch(f32::INFINITY, FRAC_PI_2), 0.0);
+    }
+
+    #[test]
+    fn clamped_pitch_still_gives_a_usable_view() {
+        let rotation = yaw_pitch_rotation(0.3, FRAC_PI_2);
+        assert!(rotation.is_finite());
+        let forward = rotation * Vec3::NEG_Z;
+        assert!(forward.y < 1.0);
+        assert!(Vec3::new(forward.x, 0.0, forward.z).length() > 0.01);
+    }
+
+    #[test]
+    fn facing_rotation_turns_exactly_around_for_negative_z() {
+        let rotation = facing_rotation(Vec3::NEG_Z).unwrap();
+        assert_vec_near(rotation * Vec3::Z, Vec3::NEG_Z);
+        // A plain turn about Y, so up stays up
+        assert_vec_near(rotation * Vec3::Y, Vec3::Y);
+    }
+
+    #[test]
+    fn facing_rotation_ignores_height_and_rejects_vertical() {
+        let rotation = facing_rotation(Vec3::new(1.0, 5.0, 0.0)).unwrap();
+        assert_vec_near(rotation * Vec3::Z, Vec3::X);
+        assert!(facing_rotation(Vec3::Y).is_none());
+        assert!(facing_rotation(Vec3::ZERO).is_none());
+        assert!(facing_rotation(Vec3::new(f32::NAN, 0.0, 1.0)).is_none());
+    }
+
+    #[test]
+    fn look_rotation_with_zero_input() {
+        assert!(look_rotation(Vec3::ZERO, Vec3::ZERO).is_none());
+        assert!(look_rotation(Vec3::ONE, Vec3::ONE).is_none());
+        // Looking from the origin works like from anywhere else
+        let rotation = look_rotation(Vec3::ZERO, Vec3::new(0.0, 0.0, -4.0)).unwrap();
+        assert_vec_near(rotation * Vec3::NEG_Z, Vec3::NEG_Z);
+    }
+
+    #[test]
+    fn look_rotation_straight_up_stays_finite() {
+        let rotation = look_rotation(Vec3::ZERO, Vec3::Y * 10.0).unwrap();
+        assert!(rotation.is_finite());
+        let forward = rotation * Vec3::NEG_Z;
+        assert!((forward.y - MAX_PITCH.sin()).abs() < 1e-5);
+    }
+
+    #[test]
+    fn orbit_direction_with_zero_angles_sits_on_positive_z() {
+        assert_vec_near(orbit_direction(0.0, 0.0), Vec3::Z);
+        assert_vec_near(orbit_direction(FRAC_PI_2, 0.0), Vec3::X);
+    }
+
+    #[test]
+    fn orbit_direction_is_unit_length_even_past_the_pole() {
+        for pitch in [-10.0, -FRAC_PI_2, 0.0, FRAC_PI_2, 10.0, f32::NAN] {
+            let direction = orbit_direction(1.0, pitch);
+            assert!(direction.is_finite());
+            assert!((direction.length() - 1.0).abs() < 1e-5);
+            assert!(direction.y <= MAX_PITCH.sin() + 1e-6);
+        }
+    }
+
+    // Runs smooth_damp from 0 towards 1 for `seconds` at a fixed frame time, returning the value
+    // after each frame
+    fn damp_path(smooth_time: f32, delta_seconds: f32, seconds: f32) -> Vec<f32> {
+        let frames = (seconds / delta_seconds).round() as usize;
+        let mut value = 0.0;
+        let mut velocity = 0.0;
+        (0..frames)
+            .map(|_| {
+                value = smooth_damp(value, 1.0, &mut velocity, smooth_time, delta_seconds);
+                value
+            })
+            .collect()
+    }
+
+    #[test]
+    fn smooth_damp_never_overshoots() {
+        for delta_seconds in [1.0 / 20.0, 1.0 / 240.0] {
+            let path = damp_path(0.3, delta_seconds, 3.0);
+            assert!(path.iter().all(|value| *value <= 1.0), "overshot at dt {}", delta_seconds);
+            assert!(path.windows(2).all(|pair| pair[1] >= pair[0]), "turned back at dt {}", delta_seconds);
+        }
+    }
+
+    #[test]
+    fn smooth_damp_converges_the_same_at_any_frame_rate() {
+        let slow = damp_path(0.3, 1.0 / 20.0, 1.0);
+        let fast = damp_path(0.3, 1.0 / 240.0, 1.0);
+        // Compare at the same moments: every 12th fast frame lines up with a slow one
+        for (index, slow_value) in slow.iter().enumerate() {
+            let fast_value = fast[(index + 1) * 12 - 1];
+            assert!((slow_value - fast_value).abs() < 0.02, "{} vs {} at frame {}", slow_value, fast_value, index);
+        }
+        assert!(1.0 - slow.last().unwrap() < 0.01);
+        assert!(1.0 - fast.last().unwrap() < 0.01);
+    }
+
+    #[test]
+    fn smooth_damp_with_no_smooth_time_snaps() {
+        let mut velocity = 3.0;
+        assert_eq!(smooth_damp(0.0, 2.0, &mut velocity, 0.0, 1.0 / 60.0), 2.0);
+        assert_eq!(velocity, 0.0);
+    }
+
+    #[test]
+    fn dead_zone_excess_is_zero_inside_the_box_and_passes_depth_through() {
+        let half_extents = Vec2::new(1.0, 0.5);
+        assert_eq!(dead_zone_excess(Vec3::new(0.9, -0.4, 3.0), half_extents), Vec3::new(0.0, 0.0, 3.0));
+        assert_eq!(dead_zone_excess(Vec3::new(-1.0, 0.5, 0.0), half_extents), Vec3::ZERO);
+        let outside = dead_zone_excess(Vec3::new(-1.5, 2.0, -1.0), half_extents);
+        assert!((outside - Vec3::new(-0.5, 1.5, -1.0)).length() < 1e-6);
+    }
+}
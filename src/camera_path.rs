@@ -0,0 +1,281 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use crate::camera::{camera_follow, math, CameraSnapEvent, ThirdPersonCamera};
+use crate::input_map::{update_action_state, Action, InputMap, InputSnapshot};
+use crate::player::PlayerIndex;
+
+pub struct CameraPathPlugin;
+
+impl Plugin for CameraPathPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CameraPaths::load(CAMERA_PATHS_DIR))
+            .init_resource::<CameraPathPlayer>()
+            .add_systems(PreUpdate, lock_input_during_path.after(update_action_state))
+            .add_systems(Update, (play_intro_path, drive_camera_path.after(camera_follow)).chain());
+    }
+}
+
+const CAMERA_PATHS_DIR: &str = "assets/camera_paths";
+const INTRO_PATH: &str = "intro";
+// How long the intro takes to hand back to the follow camera
+const INTRO_BLEND_TIME: f32 = 1.5;
+
+// How a segment speeds up and slows down between its two keyframes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Ease {
+    Linear,
+    In,
+    Out,
+    #[default]
+    InOut,
+}
+
+impl Ease {
+    fn apply(self, t: f32) -> f32 {
+        match self {
+            Ease::Linear => t,
+            Ease::In => t * t,
+            Ease::Out => 1.0 - (1.0 - t) * (1.0 - t),
+            Ease::InOut => t * t * (3.0 - 2.0 * t),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum KeyframeLook {
+    At(Vec3),
+    Rotation(Quat),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CameraKeyframe {
+    // Seconds from the start of the path
+    pub time: f32,
+    pub position: Vec3,
+    pub look: KeyframeLook,
+    // Easing of the segment from this keyframe to the next
+    #[serde(default)]
+    pub ease: Ease,
+}
+
+impl CameraKeyframe {
+    fn rotation(&self) -> Quat {
+        match self.look {
+            KeyframeLook::At(target) => math::look_rotation(self.position, target).unwrap_or_default(),
+            KeyframeLook::Rotation(rotation) => rotation,
+        }
+    }
+}
+
+// A scripted camera move: Catmull-Rom through the keyframe positions, slerping between orientations
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CameraPath {
+    pub keyframes: Vec<CameraKeyframe>,
+    // Ignore player input while it plays
+    #[serde(default)]
+    pub lock_input: bool,
+}
+
+impl CameraPath {
+    pub fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.time)
+    }
+
+    pub fn sample(&self, time: f32) -> Transform {
+        let keyframes = &self.keyframes;
+        let Some(first) = keyframes.first() else {
+            return Transform::IDENTITY;
+        };
+        // The segment containing `time`, clamped to the ends of the path
+        let segment = keyframes
+            .windows(2)
+            .position(|pair| time < pair[1].time)
+            .unwrap_or(keyframes.len().saturating_sub(2));
+        let Some(end) = keyframes.get(segment + 1) else {
+            return Transform::from_translation(first.position).with_rotation(first.rotation());
+        };
+        let start = &keyframes[segment];
+        let length = (end.time - start.time).max(f32::EPSILON);
+        let t = start.ease.apply(((time - start.time) / length).clamp(0.0, 1.0));
+
+        // The ends of the path reuse their own keyframe as the missing neighbour
+        let before = keyframes[segment.saturating_sub(1)].position;
+        let after = keyframes.get(segment + 2).map_or(end.position, |keyframe| keyframe.position);
+        let position = catmull_rom(before, start.position, end.position, after, t);
+        Transform::from_translation(position).with_rotation(start.rotation().slerp(end.rotation(), t))
+    }
+}
+
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2.0 * p1 + (p2 - p0) * t + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2 + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+// Every path in assets/camera_paths, by file name without the extension
+#[derive(Resource, Default)]
+pub struct CameraPaths(pub HashMap<String, CameraPath>);
+
+impl CameraPaths {
+    fn load(dir: &str) -> Self {
+        let mut paths = HashMap::new();
+        let Ok(entries) = fs::read_dir(dir) else {
+            println!("No camera paths at {}", dir);
+            return Self(paths);
+        };
+        for entry in entries.flatten() {
+            let file = entry.path();
+            if file.extension().and_then(|extension| extension.to_str()) != Some("ron") {
+                continue;
+            }
+            let Some(name) = file.file_stem().and_then(|stem| stem.to_str()).map(str::to_string) else {
+                continue;
+            };
+            match fs::read_to_string(&file).map_err(|err| err.to_string()).and_then(|contents| {
+                ron::from_str::<CameraPath>(&contents).map_err(|err| err.to_string())
+            }) {
+                Ok(mut path) if path.keyframes.len() >= 2 => {
+                    path.keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+                    println!("Loaded camera path {} from {}", name, file.display());
+                    paths.insert(name, path);
+                }
+                Ok(_) => println!("WARNING: Camera path {} needs at least two keyframes", file.display()),
+                Err(err) => println!("WARNING: Failed to load camera path {}: {}", file.display(), err),
+            }
+        }
+        Self(paths)
+    }
+}
+
+// What the camera does once a path runs out
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CameraPathEnd {
+    // Eases back to the third person view over this many seconds
+    BlendBack(f32),
+    // Stays on the last keyframe until skipped. Nothing holds yet; the intro blends back.
+    #[allow(dead_code)]
+    Hold,
+}
+
+struct Playback {
+    path: CameraPath,
+    then: CameraPathEnd,
+    elapsed: f32,
+    skipped: bool,
+    // Pose the camera left the path at, and how far through the blend back it is
+    blend: Option<(Transform, f32, f32)>,
+}
+
+// Drives the first player's camera along a path instead of following the player
+#[derive(Resource, Default)]
+pub struct CameraPathPlayer {
+    playback: Option<Playback>,
+}
+
+impl CameraPathPlayer {
+    pub fn play_camera_path(&mut self, path: CameraPath, then: CameraPathEnd) {
+        println!("Playing camera path ({:.1}s)", path.duration());
+        self.playback = Some(Playback {
+            path,
+            then,
+            elapsed: 0.0,
+            skipped: false,
+            blend: None,
+        });
+    }
+
+    // Leaves the path where it is and blends back, or cuts straight back from a held path
+    pub fn skip(&mut self) {
+        if let Some(playback) = &mut self.playback {
+            playback.skipped = true;
+        }
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    fn locks_input(&self) -> bool {
+        self.playback.as_ref().is_some_and(|playback| playback.path.lock_input)
+    }
+}
+
+// Run condition for the third person follow, which is off while a path has the camera. It comes
+// back on for the blend so there's a view to blend to.
+pub fn camera_following(player: Res<CameraPathPlayer>) -> bool {
+    player.playback.as_ref().is_none_or(|playback| playback.blend.is_some())
+}
+
+// Same as replaying a recording: this frame's input is swapped for an empty one, except the key
+// that skips the path
+fn lock_input_during_path(player: Res<CameraPathPlayer>, mut input_map: ResMut<InputMap>) {
+    if !player.locks_input() {
+        return;
+    }
+    let skip = input_map.just_pressed(Action::PlayCameraPath);
+    input_map.apply_snapshot(&InputSnapshot {
+        just_pressed: if skip { vec![Action::PlayCameraPath] } else { Vec::new() },
+        ..default()
+    });
+}
+
+fn play_intro_path(input_map: Res<InputMap>, paths: Res<CameraPaths>, mut player: ResMut<CameraPathPlayer>) {
+    if !input_map.just_pressed(Action::PlayCameraPath) {
+        return;
+    }
+    if player.is_playing() {
+        player.skip();
+        return;
+    }
+    match paths.0.get(INTRO_PATH) {
+        Some(path) => player.play_camera_path(path.clone(), CameraPathEnd::BlendBack(INTRO_BLEND_TIME)),
+        None => println!("WARNING: No {} camera path in {}", INTRO_PATH, CAMERA_PATHS_DIR),
+    }
+}
+
+fn drive_camera_path(
+    mut player: ResMut<CameraPathPlayer>,
+    mut camera_query: Query<(&PlayerIndex, &ThirdPersonCamera, &mut Transform)>,
+    mut snap_events: EventWriter<CameraSnapEvent>,
+    time: Res<Time>,
+) {
+    let Some(playback) = &mut player.playback else {
+        return;
+    };
+    let Some((_, camera, mut transform)) = camera_query.iter_mut().find(|(index, _, _)| index.0 == 0) else {
+        return;
+    };
+
+    match &mut playback.blend {
+        None => {
+            playback.elapsed += time.delta_seconds();
+            if !playback.skipped {
+                *transform = playback.path.sample(playback.elapsed);
+            }
+            let finished = playback.elapsed >= playback.path.duration();
+            let blend_time = match playback.then {
+                CameraPathEnd::BlendBack(blend_time) if finished || playback.skipped => blend_time,
+                CameraPathEnd::Hold if playback.skipped => 0.0,
+                _ => return,
+            };
+            playback.blend = Some((*transform, 0.0, blend_time));
+        }
+        Some((from, elapsed, blend_time)) => {
+            // The follow camera has just placed itself where it wants to be; ease from the path to there
+            *elapsed += time.delta_seconds();
+            let t = if *blend_time > 0.0 { (*elapsed / *blend_time).min(1.0) } else { 1.0 };
+            if t >= 1.0 {
+                println!("Camera path finished");
+                player.playback = None;
+                return;
+            }
+            let t = Ease::InOut.apply(t);
+            transform.translation = from.translation.lerp(transform.translation, t);
+            transform.rotation = from.rotation.slerp(transform.rotation, t);
+        }
+    }
+    // Keeps the follow spring from chasing the blended pose, so it hands over at rest
+    snap_events.send(CameraSnapEvent { target: camera.target });
+}
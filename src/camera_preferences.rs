@@ -0,0 +1,372 @@
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use bevy::time::Real;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::camera::{CameraMode, CameraSettings, FollowMode, ThirdPersonCamera};
+use crate::player::PlayerIndex;
+
+pub struct CameraPreferencesPlugin;
+
+impl Plugin for CameraPreferencesPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CameraPreferenceFile::new(preferences_path()))
+            // Before setup_camera spawns the cameras from the settings
+            .add_systems(PreStartup, load_camera_preferences)
+            .add_systems(Update, (apply_preferences_to_new_cameras, save_camera_preferences).chain())
+            .add_systems(Last, save_camera_preferences_on_exit);
+    }
+}
+
+const APP_CONFIG_DIR: &str = "vulkan-ex";
+const PREFERENCES_FILE: &str = "camera.ron";
+// Used when there's no home directory to put the file in
+const FALLBACK_DIR: &str = "save";
+// Real seconds after the last change before the file is written, so scrolling the zoom doesn't
+// write it every frame
+const SAVE_DELAY: f32 = 2.0;
+
+// The per user platform config directory, like the dirs crate's config_dir
+fn config_dir() -> Option<PathBuf> {
+    let non_empty = |name: &str| env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    if cfg!(target_os = "windows") {
+        non_empty("APPDATA")
+    } else if cfg!(target_os = "macos") {
+        non_empty("HOME").map(|home| home.join("Library/Application Support"))
+    } else {
+        non_empty("XDG_CONFIG_HOME").or_else(|| non_empty("HOME").map(|home| home.join(".config")))
+    }
+}
+
+// Photo mode and fixed camera zones only borrow the camera, so they're never what's saved
+fn is_saved_mode(mode: CameraMode) -> bool {
+    matches!(mode, CameraMode::ThirdPerson | CameraMode::FirstPerson | CameraMode::TopDown)
+}
+
+fn preferences_path() -> PathBuf {
+    match config_dir() {
+        Some(dir) => dir.join(APP_CONFIG_DIR).join(PREFERENCES_FILE),
+        None => PathBuf::from(FALLBACK_DIR).join(PREFERENCES_FILE),
+    }
+}
+
+// The camera options a player picks for themselves, kept between runs on top of the tuning in
+// assets/settings/camera.ron. Anything the file leaves out keeps that tuning's value, so files from
+// before a field was added still load.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraPreferences {
+    pub horizontal_sensitivity: Option<f32>,
+    pub vertical_sensitivity: Option<f32>,
+    pub gamepad_sensitivity: Option<f32>,
+    pub invert_x: Option<bool>,
+    pub invert_y: Option<bool>,
+    // Where the first player last left the third person zoom
+    pub zoom_distance: Option<f32>,
+    pub follow_mode: Option<FollowMode>,
+    // Vertical field of view in degrees
+    pub fov: Option<f32>,
+    // 1 for the right shoulder, -1 for the left
+    pub shoulder_side: Option<f32>,
+    // Third person, first person or top-down
+    pub camera_mode: Option<CameraMode>,
+}
+
+impl CameraPreferences {
+    // Everything as it is now; the zoom and shoulder come from the first player's camera
+    fn capture(settings: &CameraSettings, camera: Option<&ThirdPersonCamera>) -> Self {
+        Self {
+            horizontal_sensitivity: Some(settings.horizontal_sensitivity),
+            vertical_sensitivity: Some(settings.vertical_sensitivity),
+            gamepad_sensitivity: Some(settings.gamepad_sensitivity),
+            invert_x: Some(settings.invert_x),
+            invert_y: Some(settings.invert_y),
+            zoom_distance: camera.map(|camera| camera.target_distance),
+            follow_mode: Some(settings.follow_mode),
+            fov: Some(settings.fov),
+            shoulder_side: camera.map(|camera| camera.shoulder_side),
+            camera_mode: camera.map(|camera| camera.mode),
+        }
+    }
+
+    fn apply_to_settings(&self, settings: &mut CameraSettings) {
+        if let Some(sensitivity) = self.horizontal_sensitivity {
+            settings.horizontal_sensitivity = sensitivity;
+        }
+        if let Some(sensitivity) = self.vertical_sensitivity {
+            settings.vertical_sensitivity = sensitivity;
+        }
+        if let Some(sensitivity) = self.gamepad_sensitivity {
+            settings.gamepad_sensitivity = sensitivity;
+        }
+        if let Some(invert) = self.invert_x {
+            settings.invert_x = invert;
+        }
+        if let Some(invert) = self.invert_y {
+            settings.invert_y = invert;
+        }
+        if let Some(follow_mode) = self.follow_mode {
+            settings.follow_mode = follow_mode;
+        }
+        if let Some(fov) = self.fov {
+            settings.fov = fov.clamp(settings.min_fov, settings.max_fov);
+        }
+    }
+
+    fn apply_to_camera(&self, settings: &CameraSettings, camera: &mut ThirdPersonCamera) {
+        if let Some(distance) = self.zoom_distance {
+            camera.target_distance = distance.clamp(settings.min_distance, settings.max_distance);
+            camera.distance = camera.target_distance;
+        }
+        if let Some(side) = self.shoulder_side.filter(|side| *side != 0.0) {
+            camera.shoulder_side = side.signum();
+            camera.shoulder_x = camera.shoulder_side;
+        }
+        if let Some(mode) = self.camera_mode.filter(|mode| is_saved_mode(*mode)) {
+            camera.mode = mode;
+        }
+    }
+
+    // Reads the preferences, or none if there's no file yet. A file that can't be parsed is moved
+    // aside to camera.ron.corrupt rather than being overwritten by the next save. Fields are read
+    // as implicit Some either way, so a hand written file doesn't need the header saves put on it.
+    fn load(path: &Path) -> Self {
+        let Ok(contents) = fs::read_to_string(path) else {
+            println!("No camera preferences at {}, using the camera settings", path.display());
+            return Self::default();
+        };
+        let options = ron::Options::default().with_default_extension(ron::extensions::Extensions::IMPLICIT_SOME);
+        match options.from_str::<CameraPreferences>(&contents) {
+            Ok(preferences) => {
+                println!("Loaded camera preferences from {}", path.display());
+                preferences
+            }
+            Err(err) => {
+                let aside = path.with_extension("ron.corrupt");
+                println!("WARNING: Failed to parse {}: {}, moving it to {}", path.display(), err, aside.display());
+                if let Err(err) = fs::rename(path, &aside) {
+                    println!("WARNING: Failed to move {} aside: {}", path.display(), err);
+                }
+                Self::default()
+            }
+        }
+    }
+
+    fn save(&self, path: &Path) -> bool {
+        let config = ron::ser::PrettyConfig::default().extensions(ron::extensions::Extensions::IMPLICIT_SOME);
+        let contents = match ron::ser::to_string_pretty(self, config) {
+            Ok(contents) => contents,
+            Err(err) => {
+                println!("WARNING: Failed to serialize camera preferences: {}", err);
+                return false;
+            }
+        };
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        match fs::write(path, contents) {
+            Ok(()) => {
+                println!("Saved camera preferences to {}", path.display());
+                true
+            }
+            Err(err) => {
+                println!("WARNING: Failed to write {}: {}", path.display(), err);
+                false
+            }
+        }
+    }
+}
+
+#[derive(Resource)]
+struct CameraPreferenceFile {
+    path: PathBuf,
+    // As read at startup, for the zoom and shoulder of cameras as they're spawned
+    loaded: CameraPreferences,
+    // As last read or written, None until the cameras have had the loaded ones put on them
+    saved: Option<CameraPreferences>,
+    // A change not written yet, and real seconds since it last changed
+    pending: Option<(CameraPreferences, f32)>,
+}
+
+impl CameraPreferenceFile {
+    fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            loaded: CameraPreferences::default(),
+            saved: None,
+            pending: None,
+        }
+    }
+
+    // Counts down the wait on a change, restarting it whenever the preferences change again.
+    // True once they've held still for SAVE_DELAY and should be written.
+    fn update(&mut self, current: CameraPreferences, delta_seconds: f32) -> bool {
+        let Some(saved) = &self.saved else {
+            self.saved = Some(current);
+            return false;
+        };
+        if *saved == current {
+            self.pending = None;
+            return false;
+        }
+        match &mut self.pending {
+            Some((pending, elapsed)) if *pending == current => {
+                *elapsed += delta_seconds;
+                *elapsed >= SAVE_DELAY
+            }
+            _ => {
+                self.pending = Some((current, 0.0));
+                false
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        if let Some((preferences, _)) = self.pending.take() {
+            if preferences.save(&self.path) {
+                self.saved = Some(preferences);
+            }
+        }
+    }
+}
+
+fn load_camera_preferences(mut file: ResMut<CameraPreferenceFile>, mut settings: ResMut<CameraSettings>) {
+    file.loaded = CameraPreferences::load(&file.path);
+    file.loaded.apply_to_settings(&mut settings);
+}
+
+fn apply_preferences_to_new_cameras(
+    file: Res<CameraPreferenceFile>,
+    settings: Res<CameraSettings>,
+    mut camera_query: Query<&mut ThirdPersonCamera, Added<ThirdPersonCamera>>,
+) {
+    for mut camera in camera_query.iter_mut() {
+        file.loaded.apply_to_camera(&settings, &mut camera);
+    }
+}
+
+// The first player's camera, while it's settled in a mode worth saving
+fn first_camera<'a>(camera_query: &'a Query<(&PlayerIndex, &ThirdPersonCamera)>) -> Option<&'a ThirdPersonCamera> {
+    camera_query
+        .iter()
+        .find(|(index, _)| index.0 == 0)
+        .map(|(_, camera)| camera)
+        .filter(|camera| is_saved_mode(camera.mode) && camera.camera_transition.is_none())
+}
+
+// Real time, so a change made while photo mode has the game paused still gets written
+fn save_camera_preferences(
+    mut file: ResMut<CameraPreferenceFile>,
+    settings: Res<CameraSettings>,
+    camera_query: Query<(&PlayerIndex, &ThirdPersonCamera)>,
+    time: Res<Time<Real>>,
+) {
+    let Some(camera) = first_camera(&camera_query) else {
+        return;
+    };
+    let current = CameraPreferences::capture(&settings, Some(camera));
+    if file.update(current, time.delta_seconds()) {
+        file.flush();
+    }
+}
+
+// Writes any change still waiting out its delay
+fn save_camera_preferences_on_exit(
+    mut exit_events: EventReader<AppExit>,
+    mut file: ResMut<CameraPreferenceFile>,
+    settings: Res<CameraSettings>,
+    camera_query: Query<(&PlayerIndex, &ThirdPersonCamera)>,
+) {
+    if exit_events.read().next().is_none() {
+        return;
+    }
+    let Some(camera) = first_camera(&camera_query) else {
+        return;
+    };
+    let current = CameraPreferences::capture(&settings, Some(camera));
+    if file.saved.as_ref().is_some_and(|saved| *saved != current) {
+        file.pending = Some((current, SAVE_DELAY));
+        file.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_app::TempDir;
+
+    #[test]
+    fn a_file_missing_fields_keeps_the_settings_for_them() {
+        let dir = TempDir::new("merge");
+        let path = dir.path().join(PREFERENCES_FILE);
+        fs::write(&path, "(fov: 70.0, invert_y: true)").unwrap();
+
+        let preferences = CameraPreferences::load(&path);
+        let defaults = CameraSettings::default();
+        let mut settings = defaults.clone();
+        preferences.apply_to_settings(&mut settings);
+        assert_eq!(settings.fov, 70.0);
+        assert!(settings.invert_y);
+        assert_eq!(settings.invert_x, defaults.invert_x);
+        assert_eq!(settings.horizontal_sensitivity, defaults.horizontal_sensitivity);
+        assert_eq!(settings.vertical_sensitivity, defaults.vertical_sensitivity);
+        assert_eq!(settings.follow_mode, defaults.follow_mode);
+    }
+
+    #[test]
+    fn a_corrupt_file_is_moved_aside_for_the_defaults() {
+        let dir = TempDir::new("corrupt");
+        let path = dir.path().join(PREFERENCES_FILE);
+        fs::write(&path, "(fov: seventy").unwrap();
+
+        assert_eq!(CameraPreferences::load(&path), CameraPreferences::default());
+        assert!(!path.exists());
+        assert_eq!(fs::read_to_string(path.with_extension("ron.corrupt")).unwrap(), "(fov: seventy");
+    }
+
+    #[test]
+    fn saves_once_the_preferences_hold_still_for_the_delay() {
+        let dir = TempDir::new("debounce");
+        let path = dir.path().join("nested").join(PREFERENCES_FILE);
+        let mut file = CameraPreferenceFile::new(path.clone());
+        let settings = CameraSettings::default();
+        let mut current = CameraPreferences::capture(&settings, None);
+
+        // The first look is what's already there, never a change
+        assert!(!file.update(current.clone(), 0.5));
+
+        // Each change restarts the wait, like scrolling the zoom over several frames
+        for fov in [65.0, 70.0, 75.0] {
+            current.fov = Some(fov);
+            assert!(!file.update(current.clone(), 0.5));
+            assert!(!file.update(current.clone(), SAVE_DELAY - 0.5));
+        }
+        assert!(!path.exists());
+
+        assert!(file.update(current.clone(), 0.5));
+        file.flush();
+        assert_eq!(CameraPreferences::load(&path), current);
+
+        // Written, so nothing further until it changes again
+        assert!(!file.update(current.clone(), SAVE_DELAY));
+        assert!(file.pending.is_none());
+    }
+
+    #[test]
+    fn changing_back_before_the_delay_writes_nothing() {
+        let dir = TempDir::new("revert");
+        let path = dir.path().join(PREFERENCES_FILE);
+        let mut file = CameraPreferenceFile::new(path.clone());
+        let original = CameraPreferences::capture(&CameraSettings::default(), None);
+        file.update(original.clone(), 0.0);
+
+        let mut changed = original.clone();
+        changed.invert_x = Some(!CameraSettings::default().invert_x);
+        assert!(!file.update(changed, 1.0));
+        assert!(!file.update(original, 1.0));
+        assert!(file.pending.is_none());
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,208 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use crate::input_map::{Action, InputMap};
+use crate::player::{Player, PlayerIndex, PLAYER_COLLISION_GROUP};
+
+pub struct CarryPlugin;
+
+impl Plugin for CarryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ObjectPickedUp>()
+            .add_event::<ObjectDropped>()
+            .add_event::<ObjectThrown>()
+            .add_systems(Update, (
+                carry_input,
+                update_carried_object.after(carry_input),
+                log_carry_events.after(update_carried_object),
+            ));
+    }
+}
+
+pub const CARRY_SPEED_MULTIPLIER: f32 = 0.6;
+const PICKUP_RANGE: f32 = 2.5;
+const HOLD_DISTANCE: f32 = 1.5;
+const HOLD_HEIGHT: f32 = 0.5;
+const THROW_IMPULSE: f32 = 8.0;
+
+#[derive(Component)]
+pub struct Carryable;
+
+// On the player while something is held
+#[derive(Component)]
+pub struct Carrying(HeldObject);
+
+#[derive(Event)]
+pub struct ObjectPickedUp {
+    pub player: Entity,
+    pub entity: Entity,
+}
+
+#[derive(Event)]
+pub struct ObjectDropped {
+    pub player: Entity,
+    pub entity: Entity,
+}
+
+#[derive(Event)]
+pub struct ObjectThrown {
+    pub player: Entity,
+    pub entity: Entity,
+    pub impulse: Vec3,
+}
+
+struct HeldObject {
+    entity: Entity,
+    original_body: RigidBody,
+    original_damping: Option<Damping>,
+    original_groups: Option<CollisionGroups>,
+}
+
+fn facing_direction(transform: &Transform) -> Vec3 {
+    // The player faces its local +Z (see the facing slerp in player_movement)
+    let facing = transform.rotation * Vec3::Z;
+    Vec3::new(facing.x, 0.0, facing.z).normalize_or_zero()
+}
+
+fn hold_point(transform: &Transform) -> Vec3 {
+    transform.translation + facing_direction(transform) * HOLD_DISTANCE + Vec3::Y * HOLD_HEIGHT
+}
+
+fn release(commands: &mut Commands, held: &HeldObject, player_entity: Entity) {
+    let mut entity_commands = commands.entity(held.entity);
+    entity_commands.insert(held.original_body);
+    match held.original_damping {
+        Some(damping) => entity_commands.insert(damping),
+        None => entity_commands.remove::<Damping>(),
+    };
+    match held.original_groups {
+        Some(groups) => entity_commands.insert(groups),
+        None => entity_commands.remove::<CollisionGroups>(),
+    };
+    commands.entity(player_entity).remove::<Carrying>();
+}
+
+fn carry_input(
+    mut commands: Commands,
+    input_map: Res<InputMap>,
+    rapier_context: Res<RapierContext>,
+    player_query: Query<(Entity, &PlayerIndex, &Transform, Option<&Carrying>), With<Player>>,
+    carryable_query: Query<(&RigidBody, Option<&Damping>, Option<&CollisionGroups>), With<Carryable>>,
+    mut velocity_query: Query<&mut Velocity>,
+    mut picked_up_events: EventWriter<ObjectPickedUp>,
+    mut dropped_events: EventWriter<ObjectDropped>,
+    mut thrown_events: EventWriter<ObjectThrown>,
+) {
+    // Nobody can take what another player is holding, including something picked up earlier this frame
+    let mut held_entities: Vec<Entity> = player_query
+        .iter()
+        .filter_map(|(_, _, _, carrying)| carrying.map(|Carrying(held)| held.entity))
+        .collect();
+
+    for (player_entity, index, player_transform, carrying) in player_query.iter() {
+        let input = input_map.for_player(index.0);
+
+        if let Some(Carrying(held)) = carrying {
+            if input.just_pressed(Action::Interact) {
+                release(&mut commands, held, player_entity);
+                if let Ok(mut velocity) = velocity_query.get_mut(held.entity) {
+                    *velocity = Velocity::zero();
+                }
+                dropped_events.send(ObjectDropped { player: player_entity, entity: held.entity });
+            } else if input.just_pressed(Action::Fire) {
+                let impulse = (facing_direction(player_transform) + Vec3::Y * 0.2) * THROW_IMPULSE;
+                release(&mut commands, held, player_entity);
+                commands.entity(held.entity).insert(ExternalImpulse {
+                    impulse,
+                    ..default()
+                });
+                thrown_events.send(ObjectThrown { player: player_entity, entity: held.entity, impulse });
+            }
+            continue;
+        }
+
+        if !input.just_pressed(Action::Interact) {
+            continue;
+        }
+
+        let ray_origin = player_transform.translation + Vec3::Y * HOLD_HEIGHT;
+        let ray_dir = facing_direction(player_transform);
+        let filter = QueryFilter::default().exclude_rigid_body(player_entity).exclude_sensors();
+        let Some((hit_entity, _toi)) = rapier_context.cast_ray(ray_origin, ray_dir, PICKUP_RANGE, true, filter) else {
+            continue;
+        };
+        if held_entities.contains(&hit_entity) {
+            continue;
+        }
+
+        if let Ok((body, damping, groups)) = carryable_query.get(hit_entity) {
+            commands.entity(hit_entity).insert((
+                RigidBody::KinematicPositionBased,
+                Damping {
+                    linear_damping: 5.0,
+                    angular_damping: 5.0,
+                },
+                CollisionGroups::new(Group::ALL, Group::ALL ^ PLAYER_COLLISION_GROUP),
+            ));
+            commands.entity(player_entity).insert(Carrying(HeldObject {
+                entity: hit_entity,
+                original_body: *body,
+                original_damping: damping.copied(),
+                original_groups: groups.copied(),
+            }));
+            held_entities.push(hit_entity);
+            picked_up_events.send(ObjectPickedUp { player: player_entity, entity: hit_entity });
+        }
+    }
+}
+
+pub fn update_carried_object(
+    mut commands: Commands,
+    rapier_context: Res<RapierContext>,
+    player_query: Query<(Entity, &Transform, &Carrying), With<Player>>,
+    mut transform_query: Query<&mut Transform, Without<Player>>,
+    mut dropped_events: EventWriter<ObjectDropped>,
+) {
+    for (player_entity, player_transform, Carrying(held)) in player_query.iter() {
+        let Ok(mut held_transform) = transform_query.get_mut(held.entity) else {
+            // Held object was despawned
+            commands.entity(player_entity).remove::<Carrying>();
+            continue;
+        };
+
+        // Drop the object if something gets between the player and the hold point
+        let target = hold_point(player_transform);
+        let ray_origin = player_transform.translation + Vec3::Y * HOLD_HEIGHT;
+        let to_target = target - ray_origin;
+        let distance = to_target.length();
+        let filter = QueryFilter::default()
+            .exclude_collider(player_entity)
+            .exclude_rigid_body(held.entity)
+            .exclude_sensors();
+        if let Some((_entity, toi)) = rapier_context.cast_ray(ray_origin, to_target / distance, distance, true, filter) {
+            if toi < distance {
+                release(&mut commands, held, player_entity);
+                dropped_events.send(ObjectDropped { player: player_entity, entity: held.entity });
+                continue;
+            }
+        }
+
+        held_transform.translation = target;
+        held_transform.rotation = player_transform.rotation;
+    }
+}
+
+fn log_carry_events(
+    mut picked_up_events: EventReader<ObjectPickedUp>,
+    mut dropped_events: EventReader<ObjectDropped>,
+    mut thrown_events: EventReader<ObjectThrown>,
+) {
+    for event in picked_up_events.read() {
+        println!("Player {:?} picked up object {:?}", event.player, event.entity);
+    }
+    for event in dropped_events.read() {
+        println!("Player {:?} dropped object {:?}", event.player, event.entity);
+    }
+    for event in thrown_events.read() {
+        println!("Player {:?} threw object {:?} with impulse {:?}", event.player, event.entity, event.impulse);
+    }
+}
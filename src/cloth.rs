@@ -0,0 +1,296 @@
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy_rapier3d::prelude::*;
+
+pub struct ClothPlugin;
+
+impl Plugin for ClothPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_wind_zones)
+            .add_systems(Update, cloth_simulation_system);
+    }
+}
+
+const GRAVITY: Vec3 = Vec3::new(0.0, -9.81, 0.0);
+// Springs are integrated explicitly, so the cloth steps at a fixed rate well above the frame rate
+const CLOTH_SUBSTEP: f32 = 1.0 / 120.0;
+const MAX_SUBSTEPS_PER_FRAME: u32 = 8;
+// How strongly wind hitting the cloth face on pushes it along its normal
+const WIND_PRESSURE: f32 = 1.5;
+const FLAG_WIDTH: f32 = 1.2;
+const FLAG_HEIGHT: f32 = 0.8;
+const POLE_HEIGHT: f32 = 3.0;
+const POLE_RADIUS: f32 = 0.05;
+
+// A grid of point masses held together by springs along its rows, columns and diagonals. The top
+// two corners are pinned to the entity, so the cloth hangs from whatever it's attached to.
+#[derive(Component, Clone, Copy)]
+pub struct ClothMesh {
+    pub width_segments: u32,
+    pub height_segments: u32,
+    pub stiffness: f32,
+    // Fraction of its velocity a vertex loses per second
+    pub damping: f32,
+}
+
+// Blows on any cloth whose origin is inside the box
+#[derive(Component)]
+pub struct WindZone {
+    pub half_extents: Vec3,
+    pub velocity: Vec3,
+}
+
+// Simulated vertices in the cloth entity's local space, in the same order as the mesh
+#[derive(Component)]
+struct ClothState {
+    positions: Vec<Vec3>,
+    previous: Vec<Vec3>,
+    pinned: [(usize, Vec3); 2],
+    springs: Vec<(usize, usize, f32)>,
+    accumulator: f32,
+}
+
+impl ClothState {
+    fn new(cloth: &ClothMesh, width: f32, height: f32) -> Self {
+        let columns = cloth.width_segments as usize + 1;
+        let rows = cloth.height_segments as usize + 1;
+        let positions = cloth_rest_positions(cloth, width, height);
+
+        let mut springs = Vec::new();
+        let mut connect = |a: usize, b: usize| {
+            springs.push((a, b, positions[a].distance(positions[b])));
+        };
+        for row in 0..rows {
+            for column in 0..columns {
+                let i = row * columns + column;
+                if column + 1 < columns {
+                    connect(i, i + 1);
+                }
+                if row + 1 < rows {
+                    connect(i, i + columns);
+                }
+                if column + 1 < columns && row + 1 < rows {
+                    connect(i, i + columns + 1);
+                    connect(i + 1, i + columns);
+                }
+            }
+        }
+
+        Self {
+            previous: positions.clone(),
+            pinned: [(0, positions[0]), (columns - 1, positions[columns - 1])],
+            positions,
+            springs,
+            accumulator: 0.0,
+        }
+    }
+
+    fn step(&mut self, cloth: &ClothMesh, gravity: Vec3, wind: Vec3, normals: &[Vec3], dt: f32) {
+        let mut accelerations = vec![gravity; self.positions.len()];
+        for &(a, b, rest_length) in &self.springs {
+            let delta = self.positions[b] - self.positions[a];
+            let length = delta.length();
+            if length <= f32::EPSILON {
+                continue;
+            }
+            let force = delta * (cloth.stiffness * (length - rest_length) / length);
+            accelerations[a] += force;
+            accelerations[b] -= force;
+        }
+
+        let retained = (1.0 - cloth.damping * dt).max(0.0);
+        for (i, acceleration) in accelerations.into_iter().enumerate() {
+            let position = self.positions[i];
+            let velocity = (position - self.previous[i]) / dt;
+            // Only the part of the wind hitting the face pushes it, which is what makes a flag ripple
+            let wind_force = normals[i] * normals[i].dot(wind - velocity) * WIND_PRESSURE;
+            self.previous[i] = position;
+            self.positions[i] = position + velocity * retained * dt + (acceleration + wind_force) * dt * dt;
+        }
+
+        for &(index, anchor) in &self.pinned {
+            self.positions[index] = anchor;
+            self.previous[index] = anchor;
+        }
+    }
+}
+
+fn cloth_rest_positions(cloth: &ClothMesh, width: f32, height: f32) -> Vec<Vec3> {
+    let columns = cloth.width_segments + 1;
+    let rows = cloth.height_segments + 1;
+    let mut positions = Vec::with_capacity((columns * rows) as usize);
+    for row in 0..rows {
+        for column in 0..columns {
+            positions.push(Vec3::new(
+                width * column as f32 / cloth.width_segments as f32,
+                -height * row as f32 / cloth.height_segments as f32,
+                0.0,
+            ));
+        }
+    }
+    positions
+}
+
+// Smooth normals from the faces around each vertex
+fn cloth_normals(cloth: &ClothMesh, positions: &[Vec3]) -> Vec<Vec3> {
+    let mut normals = vec![Vec3::ZERO; positions.len()];
+    for triangle in cloth_indices(cloth).chunks_exact(3) {
+        let [a, b, c] = [triangle[0] as usize, triangle[1] as usize, triangle[2] as usize];
+        let normal = (positions[b] - positions[a]).cross(positions[c] - positions[a]);
+        normals[a] += normal;
+        normals[b] += normal;
+        normals[c] += normal;
+    }
+    normals.into_iter().map(|normal| normal.try_normalize().unwrap_or(Vec3::Z)).collect()
+}
+
+fn cloth_indices(cloth: &ClothMesh) -> Vec<u32> {
+    let columns = cloth.width_segments + 1;
+    let mut indices = Vec::with_capacity((cloth.width_segments * cloth.height_segments * 6) as usize);
+    for row in 0..cloth.height_segments {
+        for column in 0..cloth.width_segments {
+            let i = row * columns + column;
+            let below = i + columns;
+            indices.extend_from_slice(&[i, below, i + 1, i + 1, below, below + 1]);
+        }
+    }
+    indices
+}
+
+fn cloth_grid_mesh(cloth: &ClothMesh, positions: &[Vec3]) -> Mesh {
+    let columns = cloth.width_segments + 1;
+    let rows = cloth.height_segments + 1;
+    let mut uvs = Vec::with_capacity(positions.len());
+    for row in 0..rows {
+        for column in 0..columns {
+            uvs.push([
+                column as f32 / cloth.width_segments as f32,
+                row as f32 / cloth.height_segments as f32,
+            ]);
+        }
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions.iter().map(|p| p.to_array()).collect::<Vec<_>>());
+    mesh.insert_attribute(
+        Mesh::ATTRIBUTE_NORMAL,
+        cloth_normals(cloth, positions).iter().map(|n| n.to_array()).collect::<Vec<_>>(),
+    );
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(cloth_indices(cloth))));
+    mesh
+}
+
+// A pole standing at `base` with a crossbar along +X and a flag hanging from the crossbar
+pub fn spawn_flag(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    base: Vec3,
+    color: Color,
+) {
+    let pole_material = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.35, 0.35, 0.38),
+        metallic: 0.6,
+        ..default()
+    });
+    commands.spawn((
+        RigidBody::Fixed,
+        Collider::cylinder(POLE_HEIGHT * 0.5, POLE_RADIUS),
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Cylinder {
+                radius: POLE_RADIUS,
+                height: POLE_HEIGHT,
+                ..default()
+            })),
+            material: pole_material.clone(),
+            transform: Transform::from_translation(base + Vec3::Y * POLE_HEIGHT * 0.5),
+            ..default()
+        },
+    ));
+    let top = base + Vec3::Y * (POLE_HEIGHT - POLE_RADIUS);
+    commands.spawn(PbrBundle {
+        mesh: meshes.add(Mesh::from(shape::Box::new(FLAG_WIDTH, POLE_RADIUS * 2.0, POLE_RADIUS * 2.0))),
+        material: pole_material,
+        transform: Transform::from_translation(top + Vec3::X * FLAG_WIDTH * 0.5),
+        ..default()
+    });
+
+    let cloth = ClothMesh {
+        width_segments: 12,
+        height_segments: 8,
+        stiffness: 600.0,
+        damping: 0.8,
+    };
+    let state = ClothState::new(&cloth, FLAG_WIDTH, FLAG_HEIGHT);
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(cloth_grid_mesh(&cloth, &state.positions)),
+            material: materials.add(StandardMaterial {
+                base_color: color,
+                perceptual_roughness: 0.9,
+                double_sided: true,
+                cull_mode: None,
+                ..default()
+            }),
+            transform: Transform::from_translation(top - Vec3::Y * POLE_RADIUS),
+            ..default()
+        },
+        cloth,
+        state,
+    ));
+}
+
+fn spawn_wind_zones(mut commands: Commands) {
+    // A steady breeze over the whole play area
+    commands.spawn((
+        WindZone {
+            half_extents: Vec3::new(45.0, 20.0, 45.0),
+            velocity: Vec3::new(4.0, 0.0, 2.5),
+        },
+        TransformBundle::from_transform(Transform::from_xyz(0.0, 10.0, 0.0)),
+    ));
+}
+
+fn cloth_simulation_system(
+    mut cloth_query: Query<(&ClothMesh, &mut ClothState, &GlobalTransform, &Handle<Mesh>)>,
+    wind_query: Query<(&WindZone, &GlobalTransform)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    time: Res<Time>,
+) {
+    for (cloth, mut state, transform, mesh_handle) in cloth_query.iter_mut() {
+        let origin = transform.translation();
+        let wind: Vec3 = wind_query
+            .iter()
+            .filter(|(zone, zone_transform)| {
+                ((origin - zone_transform.translation()).abs() - zone.half_extents).max_element() <= 0.0
+            })
+            .map(|(zone, _)| zone.velocity)
+            .sum();
+        // Gusts so the flags never settle into a steady pose
+        let phase = origin.x * 0.37 + origin.z * 0.23;
+        let gust = 1.0 + 0.4 * (time.elapsed_seconds() * 1.7 + phase).sin() * (time.elapsed_seconds() * 0.6).cos();
+
+        // Forces are applied in the cloth's own space, where the pins sit still
+        let to_local = transform.compute_transform().rotation.inverse();
+        let local_gravity = to_local * GRAVITY;
+        let local_wind = to_local * wind * gust;
+
+        state.accumulator = (state.accumulator + time.delta_seconds()).min(CLOTH_SUBSTEP * MAX_SUBSTEPS_PER_FRAME as f32);
+        let mut normals = cloth_normals(cloth, &state.positions);
+        while state.accumulator >= CLOTH_SUBSTEP {
+            state.accumulator -= CLOTH_SUBSTEP;
+            state.step(cloth, local_gravity, local_wind, &normals, CLOTH_SUBSTEP);
+            normals = cloth_normals(cloth, &state.positions);
+        }
+
+        if let Some(mesh) = meshes.get_mut(mesh_handle) {
+            mesh.insert_attribute(
+                Mesh::ATTRIBUTE_POSITION,
+                state.positions.iter().map(|p| p.to_array()).collect::<Vec<_>>(),
+            );
+            mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals.iter().map(|n| n.to_array()).collect::<Vec<_>>());
+        }
+    }
+}
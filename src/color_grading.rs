@@ -0,0 +1,165 @@
+use bevy::prelude::*;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::utils::BoxedFuture;
+use std::path::Path;
+use crate::vulkan_renderer::VulkanRenderer;
+
+pub struct ColorGradingPlugin;
+
+impl Plugin for ColorGradingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<LutAsset>()
+            .init_asset_loader::<CubeLutLoader>()
+            .add_systems(Startup, setup_color_grading)
+            .add_systems(Update, sync_color_grading);
+    }
+}
+
+// Relative to the assets folder; without it the identity LUT is used
+const LUT_PATH: &str = "luts/grading.cube";
+const IDENTITY_LUT_SIZE: u32 = 64;
+
+// A size x size x size RGBA8 lookup table, red varying fastest, then green, then blue
+#[derive(Asset, TypePath)]
+pub struct LutAsset {
+    pub size: u32,
+    pub data: Vec<[u8; 4]>,
+}
+
+impl LutAsset {
+    // Maps every color to itself
+    pub fn identity(size: u32) -> Self {
+        let max = (size - 1) as f32;
+        let mut data = Vec::with_capacity((size * size * size) as usize);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    data.push([
+                        (r as f32 / max * 255.0).round() as u8,
+                        (g as f32 / max * 255.0).round() as u8,
+                        (b as f32 / max * 255.0).round() as u8,
+                        255,
+                    ]);
+                }
+            }
+        }
+        Self { size, data }
+    }
+}
+
+#[derive(Resource)]
+pub struct ColorGradingConfig {
+    pub lut_handle: Handle<LutAsset>,
+    // 0 shows the tone mapped image untouched, 1 applies the LUT fully
+    pub blend: f32,
+}
+
+#[derive(Default)]
+pub struct CubeLutLoader;
+
+impl AssetLoader for CubeLutLoader {
+    type Asset = LutAsset;
+    type Settings = ();
+    type Error = String;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<LutAsset, String>> {
+        Box::pin(async move {
+            let mut contents = String::new();
+            reader.read_to_string(&mut contents).await
+                .map_err(|err| format!("Failed to read LUT: {}", err))?;
+            parse_cube(&contents)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["cube"]
+    }
+}
+
+// Parses the Adobe/Resolve .cube format: a LUT_3D_SIZE line followed by size^3 "r g b" rows
+fn parse_cube(contents: &str) -> Result<LutAsset, String> {
+    let mut size = None;
+    let mut data = Vec::new();
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(value) = line.strip_prefix("LUT_3D_SIZE") {
+            size = Some(value.trim().parse::<u32>().map_err(|err| format!("Bad LUT_3D_SIZE: {}", err))?);
+            continue;
+        }
+        // TITLE, DOMAIN_MIN and friends
+        if line.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            continue;
+        }
+        let channels: Vec<f32> = line
+            .split_whitespace()
+            .map(|value| value.parse::<f32>().map_err(|err| format!("Bad LUT entry {:?}: {}", line, err)))
+            .collect::<Result<_, _>>()?;
+        let [r, g, b] = channels[..] else {
+            return Err(format!("Expected three values per LUT entry, got {:?}", line));
+        };
+        let to_byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        data.push([to_byte(r), to_byte(g), to_byte(b), 255]);
+    }
+
+    let size = size.ok_or("Missing LUT_3D_SIZE")?;
+    let expected = (size * size * size) as usize;
+    if data.len() != expected {
+        return Err(format!("Expected {} LUT entries, found {}", expected, data.len()));
+    }
+    Ok(LutAsset { size, data })
+}
+
+fn setup_color_grading(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut luts: ResMut<Assets<LutAsset>>,
+) {
+    let lut_handle = if Path::new("assets").join(LUT_PATH).exists() {
+        println!("Loading color grading LUT from {}", LUT_PATH);
+        asset_server.load(LUT_PATH)
+    } else {
+        println!("No color grading LUT at {}, using identity", LUT_PATH);
+        luts.add(LutAsset::identity(IDENTITY_LUT_SIZE))
+    };
+    commands.insert_resource(ColorGradingConfig {
+        lut_handle,
+        blend: 1.0,
+    });
+}
+
+// Uploads the configured LUT once it's loaded (and again whenever it changes) and keeps the
+// renderer's blend in sync
+fn sync_color_grading(
+    config: Option<Res<ColorGradingConfig>>,
+    luts: Res<Assets<LutAsset>>,
+    mut lut_events: EventReader<AssetEvent<LutAsset>>,
+    mut vulkan_renderer: ResMut<VulkanRenderer>,
+    mut uploaded: Local<Option<AssetId<LutAsset>>>,
+) {
+    let Some(config) = config else {
+        return;
+    };
+    let id = config.lut_handle.id();
+    let modified = lut_events.read().any(|event| matches!(event, AssetEvent::Modified { id: event_id } if *event_id == id));
+    if !vulkan_renderer.pipeline_created {
+        return;
+    }
+
+    if *uploaded != Some(id) || modified {
+        if let Some(lut) = luts.get(id) {
+            vulkan_renderer.set_color_grading_lut(lut.size, &lut.data);
+            *uploaded = Some(id);
+        }
+    }
+    if vulkan_renderer.color_grading_blend != config.blend {
+        vulkan_renderer.color_grading_blend = config.blend;
+    }
+}
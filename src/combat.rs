@@ -0,0 +1,473 @@
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use bevy_rapier3d::prelude::*;
+use crate::building::BuildMode;
+use crate::camera::{AimTarget, CameraShakeEvent};
+use crate::carry::Carrying;
+use crate::dialogue::ActiveDialogue;
+use crate::explosion::{spawn_explosion, Explosion};
+use crate::input_map::{Action, InputMap};
+use crate::loot::LootTable;
+use crate::player::{Player, PlayerIndex};
+use crate::status_effects::StatusEffects;
+use crate::terrain::{height_at, TerrainConfig};
+
+pub struct CombatPlugin;
+
+impl Plugin for CombatPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<DamageEvent>()
+            .add_event::<HealthChanged>()
+            .add_event::<EnemyDeathEvent>()
+            .add_systems(Startup, spawn_enemies)
+            .add_systems(Update, (
+                despawn_muzzle_flashes,
+                weapon_fire_system.after(despawn_muzzle_flashes),
+                throw_grenade,
+                projectile_system.after(throw_grenade),
+                apply_damage.after(weapon_fire_system).after(projectile_system),
+                despawn_dead_enemies.after(apply_damage),
+                update_tracers,
+                tick_invulnerability,
+            ));
+    }
+}
+
+const TRACER_LIFETIME: f32 = 0.05;
+const GRENADE_SPEED: f32 = 14.0;
+const GRENADE_FORCE: f32 = 30.0;
+const GRENADE_LIGHT_DURATION: f32 = 0.15;
+const GRENADE_LIFETIME: f32 = 10.0;
+
+#[derive(Component)]
+pub struct Weapon {
+    pub damage: f32,
+    pub fire_rate: f32,
+    pub range: f32,
+    pub fire_cooldown: f32,
+}
+
+impl Default for Weapon {
+    fn default() -> Self {
+        Self {
+            damage: 25.0,
+            fire_rate: 5.0,
+            range: 50.0,
+            fire_cooldown: 0.0,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Health {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Health {
+    pub fn new(max: f32) -> Self {
+        Self { current: max, max }
+    }
+}
+
+// Entities with this component ignore damage for `duration` seconds after being hit
+#[derive(Component)]
+pub struct Invulnerability {
+    pub duration: f32,
+    pub remaining: f32,
+}
+
+impl Invulnerability {
+    pub fn new(duration: f32) -> Self {
+        Self { duration, remaining: 0.0 }
+    }
+}
+
+#[derive(Component)]
+pub struct Enemy;
+
+// Explodes where it hits, or with no aoe_radius just damages whatever it hit
+#[derive(Component)]
+pub struct Projectile {
+    pub velocity: Vec3,
+    pub gravity_scale: f32,
+    pub damage: f32,
+    pub aoe_radius: f32,
+    pub owner: Entity,
+    // Seconds left before it's despawned without hitting anything
+    pub lifetime: f32,
+}
+
+#[derive(Event)]
+pub struct DamageEvent {
+    pub entity: Entity,
+    pub amount: f32,
+}
+
+// Sent when an enemy's health reaches 0. It's despawned by despawn_dead_enemies, so systems that
+// need anything off it run before that.
+#[derive(Event)]
+pub struct EnemyDeathEvent {
+    pub entity: Entity,
+}
+
+#[derive(Event)]
+pub struct HealthChanged {
+    pub entity: Entity,
+    pub previous: f32,
+    pub current: f32,
+    pub max: f32,
+}
+
+#[derive(Component)]
+struct Tracer {
+    lifetime: f32,
+}
+
+#[derive(Component)]
+struct MuzzleFlash;
+
+fn spawn_enemies(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    config: Res<TerrainConfig>,
+) {
+    // Target dummies to shoot at, standing on the ground
+    let positions = [
+        Vec3::new(8.0, 1.0, 0.0),
+        Vec3::new(-8.0, 1.0, 2.0),
+        Vec3::new(0.0, 1.0, -9.0),
+    ];
+
+    for position in positions {
+        let position = position + Vec3::Y * height_at(&config, position.x, position.z);
+        commands.spawn((
+            Enemy,
+            Health::new(100.0),
+            StatusEffects::default(),
+            LootTable::enemy(),
+            RigidBody::Fixed,
+            Collider::capsule_y(0.5, 0.5),
+            PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Capsule {
+                    radius: 0.5,
+                    depth: 1.0,
+                    ..default()
+                })),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgb(0.6, 0.1, 0.6),
+                    ..default()
+                }),
+                transform: Transform::from_translation(position),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn weapon_fire_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    input_map: Res<InputMap>,
+    dialogue: Res<ActiveDialogue>,
+    build_mode: Res<BuildMode>,
+    rapier_context: Res<RapierContext>,
+    mut player_query: Query<(Entity, &PlayerIndex, &Transform, &mut Weapon, Has<Carrying>), With<Player>>,
+    aim_target: Res<AimTarget>,
+    enemy_query: Query<(), With<Enemy>>,
+    mut damage_events: EventWriter<DamageEvent>,
+    time: Res<Time>,
+) {
+    for (player_entity, index, player_transform, mut weapon, carrying) in player_query.iter_mut() {
+        weapon.fire_cooldown = (weapon.fire_cooldown - time.delta_seconds()).max(0.0);
+
+        // Left click throws instead while carrying something, picks responses while talking and
+        // places buildings in build mode
+        if carrying || dialogue.0.is_some() || build_mode.build_mode || !input_map.for_player(index.0).pressed(Action::Fire) || weapon.fire_cooldown > 0.0 {
+            continue;
+        }
+
+        // Shots go where the crosshair points
+        let Some(aim) = aim_target.for_player(index.0) else {
+            continue;
+        };
+
+        weapon.fire_cooldown = 1.0 / weapon.fire_rate;
+
+        let ray_origin = aim.ray.origin;
+        let ray_dir = aim.ray.direction;
+        let filter = QueryFilter::default().exclude_collider(player_entity).exclude_sensors();
+        let hit = rapier_context.cast_ray(ray_origin, ray_dir, weapon.range, true, filter);
+
+        let end_point = match hit {
+            Some((hit_entity, toi)) => {
+                if enemy_query.contains(hit_entity) {
+                    damage_events.send(DamageEvent {
+                        entity: hit_entity,
+                        amount: weapon.damage,
+                    });
+                }
+                ray_origin + ray_dir * toi
+            }
+            None => ray_origin + ray_dir * weapon.range,
+        };
+
+        // Barrel sits at chest height just in front of the player
+        let facing = player_transform.rotation * Vec3::Z;
+        let barrel = player_transform.translation + Vec3::Y * 0.5 + facing * 0.6;
+
+        // Tracer stretched from the barrel to the impact point
+        let tracer_length = barrel.distance(end_point);
+        if tracer_length > 0.01 {
+            commands.spawn((
+                Tracer { lifetime: TRACER_LIFETIME },
+                PbrBundle {
+                    mesh: meshes.add(Mesh::from(shape::Box::new(0.03, 0.03, tracer_length))),
+                    material: materials.add(StandardMaterial {
+                        base_color: Color::rgb(1.0, 0.9, 0.4),
+                        emissive: Color::rgb(1.0, 0.8, 0.3),
+                        unlit: true,
+                        ..default()
+                    }),
+                    transform: Transform::from_translation((barrel + end_point) / 2.0)
+                        .looking_at(end_point, Vec3::Y),
+                    ..default()
+                },
+            ));
+        }
+
+        // Muzzle flash lives for a single frame
+        commands.spawn((
+            MuzzleFlash,
+            PointLightBundle {
+                point_light: PointLight {
+                    color: Color::rgb(1.0, 0.8, 0.4),
+                    intensity: 2000.0,
+                    range: 5.0,
+                    ..default()
+                },
+                transform: Transform::from_translation(barrel),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn despawn_muzzle_flashes(
+    mut commands: Commands,
+    flash_query: Query<Entity, With<MuzzleFlash>>,
+) {
+    for entity in flash_query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+fn update_tracers(
+    mut commands: Commands,
+    mut tracer_query: Query<(Entity, &mut Tracer)>,
+    time: Res<Time>,
+) {
+    for (entity, mut tracer) in tracer_query.iter_mut() {
+        tracer.lifetime -= time.delta_seconds();
+        if tracer.lifetime <= 0.0 {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn throw_grenade(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    input_map: Res<InputMap>,
+    player_query: Query<(Entity, &PlayerIndex, &Transform), With<Player>>,
+    aim_target: Res<AimTarget>,
+) {
+    for (player_entity, index, player_transform) in player_query.iter() {
+        if !input_map.for_player(index.0).just_pressed(Action::ThrowGrenade) {
+            continue;
+        }
+        let Some(aim) = aim_target.for_player(index.0) else {
+            continue;
+        };
+
+        // Lob along the crosshair's direction with some extra loft
+        let forward = aim.ray.direction;
+        let direction = (Vec3::new(forward.x, 0.0, forward.z).normalize_or_zero() + Vec3::Y * 0.5).normalize();
+        let origin = player_transform.translation + Vec3::Y * 0.8;
+
+        commands.spawn((
+            Projectile {
+                velocity: direction * GRENADE_SPEED,
+                gravity_scale: 9.81,
+                damage: 60.0,
+                aoe_radius: 4.0,
+                owner: player_entity,
+                lifetime: GRENADE_LIFETIME,
+            },
+            PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::UVSphere {
+                    radius: 0.15,
+                    ..default()
+                })),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgb(0.2, 0.3, 0.2),
+                    ..default()
+                }),
+                transform: Transform::from_translation(origin),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn projectile_system(
+    mut commands: Commands,
+    mut projectile_query: Query<(Entity, &mut Projectile, &mut Transform)>,
+    rapier_context: Res<RapierContext>,
+    health_query: Query<(), With<Health>>,
+    mut shake_events: EventWriter<CameraShakeEvent>,
+    mut damage_events: EventWriter<DamageEvent>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    for (entity, mut projectile, mut transform) in projectile_query.iter_mut() {
+        projectile.lifetime -= dt;
+        if projectile.lifetime <= 0.0 {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+        let gravity = Vec3::NEG_Y * projectile.gravity_scale * dt;
+        projectile.velocity += gravity;
+        let movement = projectile.velocity * dt;
+        let distance = movement.length();
+        if distance <= f32::EPSILON {
+            continue;
+        }
+
+        let filter = QueryFilter::default().exclude_collider(projectile.owner).exclude_sensors();
+        let Some((hit_entity, toi)) = rapier_context.cast_ray(transform.translation, movement / distance, distance, true, filter) else {
+            transform.translation += movement;
+            continue;
+        };
+
+        if projectile.aoe_radius <= 0.0 {
+            let hit = rapier_context.collider_parent(hit_entity).unwrap_or(hit_entity);
+            if health_query.contains(hit) {
+                damage_events.send(DamageEvent {
+                    entity: hit,
+                    amount: projectile.damage,
+                });
+            }
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        // Detonate at the impact point
+        let impact = transform.translation + movement / distance * toi;
+        spawn_explosion(&mut commands, impact, Explosion {
+            radius: projectile.aoe_radius,
+            force: GRENADE_FORCE,
+            damage: projectile.damage,
+            light_duration: GRENADE_LIGHT_DURATION,
+        });
+        shake_events.send(CameraShakeEvent { trauma: 0.6 });
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn apply_damage(
+    mut damage_events: EventReader<DamageEvent>,
+    mut health_query: Query<(&mut Health, Option<&mut Invulnerability>, Has<Enemy>)>,
+    mut health_changed_events: EventWriter<HealthChanged>,
+    mut enemy_death_events: EventWriter<EnemyDeathEvent>,
+) {
+    for event in damage_events.read() {
+        if let Ok((mut health, invulnerability, is_enemy)) = health_query.get_mut(event.entity) {
+            if health.current <= 0.0 || event.amount <= 0.0 {
+                continue;
+            }
+            if let Some(mut invulnerability) = invulnerability {
+                if invulnerability.remaining > 0.0 {
+                    continue;
+                }
+                invulnerability.remaining = invulnerability.duration;
+            }
+
+            let previous = health.current;
+            health.current = (health.current - event.amount).max(0.0);
+            health_changed_events.send(HealthChanged {
+                entity: event.entity,
+                previous,
+                current: health.current,
+                max: health.max,
+            });
+            println!("{:?} took {} damage, health now {}/{}", event.entity, event.amount, health.current, health.max);
+            if is_enemy && health.current <= 0.0 {
+                enemy_death_events.send(EnemyDeathEvent { entity: event.entity });
+            }
+        }
+    }
+}
+
+pub fn despawn_dead_enemies(mut commands: Commands, mut enemy_death_events: EventReader<EnemyDeathEvent>) {
+    for event in enemy_death_events.read() {
+        println!("Enemy {:?} destroyed", event.entity);
+        commands.entity(event.entity).despawn_recursive();
+    }
+}
+
+fn tick_invulnerability(
+    mut invulnerability_query: Query<&mut Invulnerability>,
+    time: Res<Time>,
+) {
+    for mut invulnerability in invulnerability_query.iter_mut() {
+        if invulnerability.remaining > 0.0 {
+            invulnerability.remaining = (invulnerability.remaining - time.delta_seconds()).max(0.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn damage_app() -> App {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .add_event::<DamageEvent>()
+            .add_event::<HealthChanged>()
+            .add_event::<EnemyDeathEvent>()
+            .add_systems(Update, (tick_invulnerability, apply_damage.after(tick_invulnerability)));
+        app
+    }
+
+    fn hit(app: &mut App, entity: Entity, amount: f32, dt: f32) -> f32 {
+        app.world.resource_mut::<Time>().advance_by(Duration::from_secs_f32(dt));
+        app.world.send_event(DamageEvent { entity, amount });
+        app.update();
+        app.world.get::<Health>(entity).unwrap().current
+    }
+
+    #[test]
+    fn invulnerability_blocks_a_second_hit_inside_the_window() {
+        let mut app = damage_app();
+        let player = app.world.spawn((Health::new(100.0), Invulnerability::new(1.0))).id();
+        assert_eq!(hit(&mut app, player, 10.0, 0.0), 90.0);
+        assert_eq!(hit(&mut app, player, 10.0, 0.5), 90.0);
+        assert_eq!(hit(&mut app, player, 10.0, 0.4), 90.0);
+        // The window has run out
+        assert_eq!(hit(&mut app, player, 10.0, 0.2), 80.0);
+    }
+
+    #[test]
+    fn without_invulnerability_every_hit_lands() {
+        let mut app = damage_app();
+        let enemy = app.world.spawn(Health::new(100.0)).id();
+        assert_eq!(hit(&mut app, enemy, 10.0, 0.0), 90.0);
+        assert_eq!(hit(&mut app, enemy, 10.0, 0.0), 80.0);
+    }
+}
@@ -0,0 +1,172 @@
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use bevy::render::mesh::VertexAttributeValues;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::{ImageAddressMode, ImageSampler, ImageSamplerDescriptor};
+use bevy_rapier3d::prelude::*;
+use crate::player::Player;
+use crate::terrain::SurfaceType;
+
+pub struct ConveyorPlugin;
+
+impl Plugin for ConveyorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, (conveyor_system, scroll_conveyor_surfaces));
+    }
+}
+
+// How quickly a belt brings what's on it up to its speed, in m/s²
+const CONVEYOR_ACCELERATION: f32 = 20.0;
+// Only contacts this close to straight up count as riding the belt rather than bumping its side
+const MIN_RIDING_NORMAL_Y: f32 = 0.7;
+// The arrows on the belt repeat this often along it, in metres
+const ARROW_SPACING: f32 = 1.0;
+const ARROW_TEXTURE_SIZE: usize = 32;
+const BELT_COLOR: [u8; 4] = [40, 40, 45, 255];
+const ARROW_COLOR: [u8; 4] = [230, 180, 30, 255];
+
+// A fixed belt that carries whatever stands on it along `direction` at `speed` metres per second.
+// Players pick this up through their platform velocity instead, as their movement sets their
+// velocity every frame.
+#[derive(Component, Clone, Copy)]
+pub struct ConveyorBelt {
+    pub direction: Vec3,
+    pub speed: f32,
+}
+
+impl ConveyorBelt {
+    pub fn velocity(&self) -> Vec3 {
+        self.direction.normalize_or_zero() * self.speed
+    }
+}
+
+// The belt's arrows, scrolled along with it. The mesh is its own, so the UVs can be moved.
+#[derive(Component)]
+struct ConveyorSurface {
+    width: f32,
+    // Metres the arrows have moved, wrapped to ARROW_SPACING
+    offset: f32,
+}
+
+// Dark belt with a light arrow pointing along +u
+fn arrow_texture() -> Image {
+    let size = ARROW_TEXTURE_SIZE;
+    let mut data = Vec::with_capacity(size * size * 4);
+    for y in 0..size {
+        for x in 0..size {
+            let u = (x as f32 + 0.5) / size as f32;
+            let v = (y as f32 + 0.5) / size as f32;
+            let on_arrow = (u + (v - 0.5).abs()).fract() < 0.3;
+            data.extend_from_slice(if on_arrow { &ARROW_COLOR } else { &BELT_COLOR });
+        }
+    }
+    let mut image = Image::new(
+        Extent3d {
+            width: size as u32,
+            height: size as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    );
+    image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+        address_mode_u: ImageAddressMode::Repeat,
+        address_mode_v: ImageAddressMode::Repeat,
+        ..ImageSamplerDescriptor::linear()
+    });
+    image
+}
+
+// Projects the texture straight down onto the belt, with one arrow every ARROW_SPACING metres
+// along local x and one across its width, moved on by `offset` metres
+fn set_conveyor_uvs(mesh: &mut Mesh, width: f32, offset: f32) {
+    let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+        return;
+    };
+    let uvs: Vec<[f32; 2]> = positions
+        .iter()
+        .map(|position| [(position[0] - offset) / ARROW_SPACING, position[2] / width + 0.5])
+        .collect();
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+}
+
+// A belt `length` by `width` whose top sits at `position`, running along `belt.direction`
+pub fn spawn_conveyor(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    images: &mut ResMut<Assets<Image>>,
+    position: Vec3,
+    length: f32,
+    width: f32,
+    belt: ConveyorBelt,
+) {
+    let thickness = 0.2;
+    let mut mesh = Mesh::from(shape::Box::new(length, thickness, width));
+    set_conveyor_uvs(&mut mesh, width, 0.0);
+    // The mesh runs along local x, so that gets turned to face the way the belt goes
+    let heading = Vec3::new(belt.direction.x, 0.0, belt.direction.z).normalize_or_zero();
+    let rotation = if heading == Vec3::ZERO { Quat::IDENTITY } else { Quat::from_rotation_arc(Vec3::X, heading) };
+    commands.spawn((
+        belt,
+        ConveyorSurface { width, offset: 0.0 },
+        RigidBody::Fixed,
+        Collider::cuboid(length * 0.5, thickness * 0.5, width * 0.5),
+        SurfaceType::Stone,
+        PbrBundle {
+            mesh: meshes.add(mesh),
+            material: materials.add(StandardMaterial {
+                base_color_texture: Some(images.add(arrow_texture())),
+                perceptual_roughness: 0.8,
+                ..default()
+            }),
+            transform: Transform::from_translation(position - Vec3::Y * thickness * 0.5).with_rotation(rotation),
+            ..default()
+        },
+    ));
+}
+
+// Pushes dynamic bodies resting on a belt towards its speed, without ever speeding them up past it
+fn conveyor_system(
+    conveyor_query: Query<(Entity, &ConveyorBelt)>,
+    mut body_query: Query<(&RigidBody, &mut Velocity), Without<Player>>,
+    rapier_context: Res<RapierContext>,
+    time: Res<Time>,
+) {
+    let max_change = CONVEYOR_ACCELERATION * time.delta_seconds();
+    for (conveyor, belt) in conveyor_query.iter() {
+        let belt_velocity = belt.velocity();
+        let Some(direction) = belt_velocity.try_normalize() else {
+            continue;
+        };
+        for contact in rapier_context.contact_pairs_with(conveyor) {
+            if !contact.has_any_active_contacts() || !contact.manifolds().any(|manifold| manifold.normal().y.abs() >= MIN_RIDING_NORMAL_Y) {
+                continue;
+            }
+            let other = if contact.collider1() == conveyor { contact.collider2() } else { contact.collider1() };
+            let Ok((RigidBody::Dynamic, mut velocity)) = body_query.get_mut(other) else {
+                continue;
+            };
+            let along = velocity.linvel.dot(direction);
+            let push = (belt.speed.abs() - along).clamp(0.0, max_change);
+            velocity.linvel += direction * push;
+        }
+    }
+}
+
+fn scroll_conveyor_surfaces(
+    mut surface_query: Query<(&ConveyorBelt, &mut ConveyorSurface, &Handle<Mesh>)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    time: Res<Time>,
+) {
+    for (belt, mut surface, mesh) in surface_query.iter_mut() {
+        if belt.speed == 0.0 {
+            continue;
+        }
+        surface.offset = (surface.offset + belt.speed * time.delta_seconds()).rem_euclid(ARROW_SPACING);
+        if let Some(mesh) = meshes.get_mut(mesh) {
+            set_conveyor_uvs(mesh, surface.width, surface.offset);
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use bevy::prelude::*;
+use std::f32::consts::TAU;
+use crate::vulkan_renderer::SkyConfig;
+
+pub struct DayNightPlugin;
+
+impl Plugin for DayNightPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DayNightCycle>()
+            .add_systems(Update, advance_day_night);
+    }
+}
+
+// Sun illuminance at noon, matching the light spawned in setup_lighting
+pub const NOON_ILLUMINANCE: f32 = 10000.0;
+// Tilts the sun's path away from straight overhead so noon shadows aren't vertical
+const SUN_PATH_TILT: f32 = 0.4;
+
+#[derive(Resource)]
+pub struct DayNightCycle {
+    // Hours since midnight, 0..24
+    pub time_of_day: f32,
+    // Real seconds for a full 24 hour day
+    pub day_length: f32,
+    pub paused: bool,
+}
+
+impl Default for DayNightCycle {
+    fn default() -> Self {
+        Self {
+            time_of_day: 10.0,
+            day_length: 600.0,
+            paused: false,
+        }
+    }
+}
+
+impl DayNightCycle {
+    // Rises in +X at 6:00, peaks at noon and sets in -X at 18:00
+    pub fn sun_direction(&self) -> Vec3 {
+        let angle = (self.time_of_day - 6.0) / 24.0 * TAU;
+        Vec3::new(angle.cos(), angle.sin(), SUN_PATH_TILT).normalize()
+    }
+}
+
+fn advance_day_night(
+    mut cycle: ResMut<DayNightCycle>,
+    mut sky: ResMut<SkyConfig>,
+    mut light_query: Query<(&mut DirectionalLight, &mut Transform)>,
+    time: Res<Time>,
+) {
+    if !cycle.paused {
+        cycle.time_of_day = (cycle.time_of_day + time.delta_seconds() * 24.0 / cycle.day_length).rem_euclid(24.0);
+    }
+
+    let sun_direction = cycle.sun_direction();
+    sky.sun_direction = sun_direction;
+
+    // Fade the sun out as it drops below the horizon instead of lighting from underneath
+    let daylight = (sun_direction.y / 0.1).clamp(0.0, 1.0);
+    for (mut light, mut transform) in light_query.iter_mut() {
+        light.illuminance = NOON_ILLUMINANCE * daylight;
+        transform.look_to(-sun_direction, Vec3::Y);
+    }
+}
@@ -0,0 +1,60 @@
+use bevy::prelude::*;
+
+pub struct DebugPlugin;
+
+impl Plugin for DebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DebugOverlay>()
+            .init_resource::<DebugLogTimers>();
+    }
+}
+
+// Log target for periodic state dumps, e.g. RUST_LOG=vulkan_ex::debug=debug
+pub const DEBUG_LOG_TARGET: &str = "vulkan_ex::debug";
+
+// Turns the periodic debug systems on or off; off by default in release builds
+#[derive(Resource)]
+pub struct DebugOverlay {
+    pub enabled: bool,
+}
+
+impl Default for DebugOverlay {
+    fn default() -> Self {
+        Self { enabled: cfg!(debug_assertions) }
+    }
+}
+
+// How often each debug system dumps its state
+#[derive(Resource)]
+pub struct DebugLogTimers {
+    pub player: Timer,
+    pub camera: Timer,
+    pub gpu_memory: Timer,
+}
+
+impl Default for DebugLogTimers {
+    fn default() -> Self {
+        Self {
+            player: Timer::from_seconds(2.0, TimerMode::Repeating),
+            camera: Timer::from_seconds(3.0, TimerMode::Repeating),
+            gpu_memory: Timer::from_seconds(5.0, TimerMode::Repeating),
+        }
+    }
+}
+
+// Present on the player while it flies around in noclip (dev-tools builds only). Gameplay that
+// depends on physics contact, like ground detection and fall damage, skips noclipping players.
+#[derive(Component)]
+#[cfg_attr(not(feature = "dev-tools"), allow(dead_code))]
+pub struct Noclip;
+
+// Present on the player while god mode is on (dev-tools builds only); fall damage and the kill
+// plane leave it alone
+#[derive(Component)]
+#[cfg_attr(not(feature = "dev-tools"), allow(dead_code))]
+pub struct GodMode;
+
+// Run condition for systems that only exist to print debug state
+pub fn debug_overlay_enabled(overlay: Option<Res<DebugOverlay>>) -> bool {
+    overlay.is_some_and(|overlay| overlay.enabled)
+}
@@ -0,0 +1,303 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+use crate::interaction::{CurrentInteractTarget, Interacted, Npc};
+use crate::quests::QuestStartEvent;
+
+pub struct DialoguePlugin;
+
+impl Plugin for DialoguePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NpcState>()
+            .init_resource::<ActiveDialogue>()
+            .add_event::<ItemGiveEvent>()
+            .add_systems(Startup, setup_dialogue_ui)
+            .add_systems(Update, (
+                open_dialogue,
+                close_dialogue_out_of_range,
+                dialogue_response_buttons,
+                update_dialogue_ui,
+                log_dialogue_events,
+            ).chain());
+    }
+}
+
+const BUTTON_COLOR: Color = Color::rgba(0.2, 0.2, 0.25, 0.9);
+const BUTTON_HOVER_COLOR: Color = Color::rgba(0.35, 0.35, 0.45, 0.9);
+
+// One line of an NPC's conversation. Picking a response moves to that child node and fires its action.
+#[derive(Clone)]
+pub struct DialogueNode {
+    pub text: String,
+    pub responses: Vec<(String, DialogueNode)>,
+    pub action: Option<DialogueAction>,
+}
+
+#[derive(Clone)]
+pub enum DialogueAction {
+    StartQuest(String),
+    GiveItem(String),
+    // Ends the conversation; the next one starts from the top again
+    Close,
+}
+
+impl DialogueNode {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            responses: Vec::new(),
+            action: None,
+        }
+    }
+
+    pub fn with_action(mut self, action: DialogueAction) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    pub fn response(mut self, text: &str, node: DialogueNode) -> Self {
+        self.responses.push((text.to_string(), node));
+        self
+    }
+
+    // Nodes are numbered depth first from the root at 0, so a conversation's position is one usize
+    fn size(&self) -> usize {
+        1 + self.responses.iter().map(|(_, child)| child.size()).sum::<usize>()
+    }
+
+    fn node(&self, index: usize) -> Option<&DialogueNode> {
+        if index == 0 {
+            return Some(self);
+        }
+        let mut offset = 1;
+        for (_, child) in &self.responses {
+            let size = child.size();
+            if index < offset + size {
+                return child.node(index - offset);
+            }
+            offset += size;
+        }
+        None
+    }
+
+    fn child_index(&self, index: usize, response: usize) -> Option<usize> {
+        let node = self.node(index)?;
+        if response >= node.responses.len() {
+            return None;
+        }
+        let skipped: usize = node.responses[..response].iter().map(|(_, child)| child.size()).sum();
+        Some(index + 1 + skipped)
+    }
+}
+
+// Where each NPC's conversation is up to, so talking to them again picks up from there
+#[derive(Resource, Default)]
+pub struct NpcState(pub HashMap<Entity, usize>);
+
+// The NPC being talked to, if the dialogue panel is open
+#[derive(Resource, Default)]
+pub struct ActiveDialogue(pub Option<Entity>);
+
+#[derive(Event)]
+pub struct ItemGiveEvent {
+    pub npc: Entity,
+    pub item: String,
+}
+
+#[derive(Component)]
+struct DialogueUI;
+
+#[derive(Component)]
+struct DialogueResponseButton(usize);
+
+pub fn villager_dialogue() -> DialogueNode {
+    DialogueNode::new("Welcome to the floating island! Not many make it up here.")
+        .response(
+            "Is there anything I can do?",
+            DialogueNode::new("The lift's been acting up. Could you check the lever by the southern platform?")
+                .response(
+                    "I'll take a look.",
+                    DialogueNode::new("Thank you! Take this, it might come in handy.")
+                        .with_action(DialogueAction::StartQuest("fix_lift".to_string()))
+                        .response(
+                            "What is it?",
+                            DialogueNode::new("A gem from the old mines. Keep it safe.")
+                                .with_action(DialogueAction::GiveItem("Gem".to_string())),
+                        ),
+                )
+                .response("Not right now.", DialogueNode::new("Maybe later then.").with_action(DialogueAction::Close)),
+        )
+        .response(
+            "What is this place?",
+            DialogueNode::new("Nobody remembers who raised it. The ruins are older than the village.")
+                .response("Interesting.", DialogueNode::new("Safe travels.").with_action(DialogueAction::Close)),
+        )
+        .response("Goodbye.", DialogueNode::new("Goodbye!").with_action(DialogueAction::Close))
+}
+
+fn setup_dialogue_ui(mut commands: Commands) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                display: Display::None,
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(80.0),
+                left: Val::Percent(25.0),
+                width: Val::Percent(50.0),
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(12.0)),
+                row_gap: Val::Px(6.0),
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.75).into(),
+            ..default()
+        },
+        DialogueUI,
+    ));
+}
+
+fn open_dialogue(
+    mut interacted_events: EventReader<Interacted>,
+    npc_query: Query<&Npc>,
+    mut active: ResMut<ActiveDialogue>,
+) {
+    for event in interacted_events.read() {
+        if !npc_query.contains(event.entity) {
+            continue;
+        }
+        // Talking to the same NPC again puts the conversation down
+        active.0 = if active.0 == Some(event.entity) { None } else { Some(event.entity) };
+    }
+}
+
+fn close_dialogue_out_of_range(
+    target_query: Query<&CurrentInteractTarget>,
+    mut active: ResMut<ActiveDialogue>,
+) {
+    // Stays open while any player is still facing the NPC
+    if active.0.is_some() && !target_query.iter().any(|target| target.0 == active.0) {
+        active.0 = None;
+    }
+}
+
+fn dialogue_response_buttons(
+    mut button_query: Query<(&Interaction, &DialogueResponseButton, &mut BackgroundColor), Changed<Interaction>>,
+    npc_query: Query<&Npc>,
+    mut npc_state: ResMut<NpcState>,
+    mut active: ResMut<ActiveDialogue>,
+    mut quest_events: EventWriter<QuestStartEvent>,
+    mut item_events: EventWriter<ItemGiveEvent>,
+) {
+    for (interaction, button, mut color) in button_query.iter_mut() {
+        *color = match interaction {
+            Interaction::Hovered => BUTTON_HOVER_COLOR.into(),
+            _ => BUTTON_COLOR.into(),
+        };
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some(npc_entity) = active.0 else {
+            continue;
+        };
+        let Ok(npc) = npc_query.get(npc_entity) else {
+            continue;
+        };
+        let tree = &npc.dialogue_tree;
+        let index = npc_state.0.get(&npc_entity).copied().unwrap_or(0);
+
+        // A node with nothing left to say only offers to leave, which starts over next time
+        let Some(next) = tree.child_index(index, button.0) else {
+            npc_state.0.insert(npc_entity, 0);
+            active.0 = None;
+            continue;
+        };
+        let Some(node) = tree.node(next) else {
+            continue;
+        };
+        npc_state.0.insert(npc_entity, next);
+        match &node.action {
+            Some(DialogueAction::StartQuest(quest_id)) => quest_events.send(QuestStartEvent { quest_id: quest_id.clone() }),
+            Some(DialogueAction::GiveItem(item)) => item_events.send(ItemGiveEvent {
+                npc: npc_entity,
+                item: item.clone(),
+            }),
+            Some(DialogueAction::Close) => {
+                println!("{}: {}", npc.name, node.text);
+                npc_state.0.insert(npc_entity, 0);
+                active.0 = None;
+            }
+            None => {}
+        }
+    }
+}
+
+fn update_dialogue_ui(
+    mut commands: Commands,
+    active: Res<ActiveDialogue>,
+    npc_state: Res<NpcState>,
+    npc_query: Query<&Npc>,
+    mut panel_query: Query<(Entity, &mut Style), With<DialogueUI>>,
+) {
+    if !active.is_changed() && !npc_state.is_changed() {
+        return;
+    }
+    let Ok((panel, mut style)) = panel_query.get_single_mut() else {
+        return;
+    };
+    commands.entity(panel).despawn_descendants();
+
+    let Some((npc_entity, npc)) = active.0.and_then(|entity| npc_query.get(entity).ok().map(|npc| (entity, npc))) else {
+        style.display = Display::None;
+        return;
+    };
+    let index = npc_state.0.get(&npc_entity).copied().unwrap_or(0);
+    let Some(node) = npc.dialogue_tree.node(index) else {
+        style.display = Display::None;
+        return;
+    };
+    style.display = Display::Flex;
+
+    let mut responses: Vec<&str> = node.responses.iter().map(|(text, _)| text.as_str()).collect();
+    if responses.is_empty() {
+        responses.push("(Leave)");
+    }
+    commands.entity(panel).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            format!("{}: {}", npc.name, node.text),
+            TextStyle {
+                font_size: 22.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        ));
+        for (i, response) in responses.into_iter().enumerate() {
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+                            ..default()
+                        },
+                        background_color: BUTTON_COLOR.into(),
+                        ..default()
+                    },
+                    DialogueResponseButton(i),
+                ))
+                .with_children(|button| {
+                    button.spawn(TextBundle::from_section(
+                        format!("{}. {}", i + 1, response),
+                        TextStyle {
+                            font_size: 20.0,
+                            color: Color::rgb(0.9, 0.85, 0.6),
+                            ..default()
+                        },
+                    ));
+                });
+        }
+    });
+}
+
+fn log_dialogue_events(mut item_events: EventReader<ItemGiveEvent>) {
+    for event in item_events.read() {
+        println!("Received item from {:?}: {}", event.npc, event.item);
+    }
+}
@@ -0,0 +1,215 @@
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use bevy_rapier3d::prelude::*;
+use crate::player::Player;
+use crate::terrain::{height_at, SurfaceType, TerrainConfig};
+
+pub struct ElevatorPlugin;
+
+impl Plugin for ElevatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ActivationEvent>()
+            .add_systems(Startup, spawn_elevator)
+            .add_systems(Update, (pressure_plate_system, call_elevators, elevator_system).chain());
+    }
+}
+
+// The elevator on the western edge of the island, up to the floating platform beside it
+const ELEVATOR_BOTTOM: Vec3 = Vec3::new(-18.9, 0.25, 0.0);
+const ELEVATOR_TOP: Vec3 = Vec3::new(-18.9, 8.25, 0.0);
+const ELEVATOR_HALF_EXTENTS: Vec3 = Vec3::new(1.0, 0.25, 1.0);
+const PLATE_HALF_EXTENTS: Vec3 = Vec3::new(0.6, 0.05, 0.6);
+
+// A kinematic platform that travels between floors, stopping at each for wait_duration
+#[derive(Component)]
+pub struct Elevator {
+    pub floors: Vec<Vec3>,
+    // The floor it's heading to or waiting at
+    pub current_floor: usize,
+    // Metres per second
+    pub speed: f32,
+    pub waiting: bool,
+    pub wait_timer: f32,
+    pub wait_duration: f32,
+    // After the last floor go back to the first, rather than back down through the others
+    pub looping: bool,
+    // Which way through the floors it's going when not looping, 1 or -1
+    direction: isize,
+}
+
+impl Elevator {
+    pub fn new(floors: Vec<Vec3>, speed: f32, wait_duration: f32, looping: bool) -> Self {
+        Self {
+            floors,
+            current_floor: 0,
+            speed,
+            waiting: false,
+            wait_timer: 0.0,
+            wait_duration,
+            looping,
+            direction: 1,
+        }
+    }
+
+    fn advance(&mut self) {
+        let count = self.floors.len();
+        if count < 2 {
+            return;
+        }
+        if self.looping {
+            self.current_floor = (self.current_floor + 1) % count;
+            return;
+        }
+        let next = self.current_floor as isize + self.direction;
+        if next < 0 || next >= count as isize {
+            self.direction = -self.direction;
+        }
+        self.current_floor = (self.current_floor as isize + self.direction) as usize;
+    }
+
+    fn nearest_floor(&self, position: Vec3) -> Option<usize> {
+        self.floors
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| a.distance_squared(position).total_cmp(&b.distance_squared(position)))
+            .map(|(index, _)| index)
+    }
+}
+
+// Stepping on it sends an ActivationEvent for its target
+#[derive(Component)]
+pub struct PressurePlate {
+    pub target: Entity,
+    pub pressed: bool,
+}
+
+#[derive(Event)]
+pub struct ActivationEvent {
+    pub plate: Entity,
+    pub target: Entity,
+}
+
+fn spawn_elevator(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    config: Res<TerrainConfig>,
+) {
+    let elevator = commands.spawn((
+        Elevator::new(vec![ELEVATOR_BOTTOM, ELEVATOR_TOP], 2.0, 3.0, false),
+        RigidBody::KinematicPositionBased,
+        // Filled in by rapier each step so riders can pick up the platform's motion
+        Velocity::zero(),
+        Collider::cuboid(ELEVATOR_HALF_EXTENTS.x, ELEVATOR_HALF_EXTENTS.y, ELEVATOR_HALF_EXTENTS.z),
+        SurfaceType::Stone,
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Box::new(
+                ELEVATOR_HALF_EXTENTS.x * 2.0,
+                ELEVATOR_HALF_EXTENTS.y * 2.0,
+                ELEVATOR_HALF_EXTENTS.z * 2.0,
+            ))),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.6, 0.4, 0.2),
+                ..default()
+            }),
+            transform: Transform::from_translation(ELEVATOR_BOTTOM),
+            ..default()
+        },
+    )).id();
+
+    // A plate beside each floor calls the elevator down or up to it
+    let plate_mesh = meshes.add(Mesh::from(shape::Box::new(
+        PLATE_HALF_EXTENTS.x * 2.0,
+        PLATE_HALF_EXTENTS.y * 2.0,
+        PLATE_HALF_EXTENTS.z * 2.0,
+    )));
+    let plate_material = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.8, 0.3, 0.2),
+        ..default()
+    });
+    let ground_plate = Vec3::new(-16.0, height_at(&config, -16.0, 2.5) + 0.2, 2.5);
+    for position in [ground_plate, Vec3::new(-21.5, 8.55, 2.5)] {
+        commands.spawn((
+            PressurePlate {
+                target: elevator,
+                pressed: false,
+            },
+            RigidBody::Fixed,
+            Collider::cuboid(PLATE_HALF_EXTENTS.x, PLATE_HALF_EXTENTS.y, PLATE_HALF_EXTENTS.z),
+            SurfaceType::Stone,
+            PbrBundle {
+                mesh: plate_mesh.clone(),
+                material: plate_material.clone(),
+                transform: Transform::from_translation(position),
+                ..default()
+            },
+        ));
+    }
+}
+
+// A plate activates once as it's stepped on, and again only after everyone has stepped off
+fn pressure_plate_system(
+    player_query: Query<&Player>,
+    mut plate_query: Query<(Entity, &mut PressurePlate)>,
+    mut activation_events: EventWriter<ActivationEvent>,
+) {
+    for (entity, mut plate) in plate_query.iter_mut() {
+        let stood_on = player_query.iter().any(|player| player.ground_entity == Some(entity));
+        if stood_on && !plate.pressed {
+            activation_events.send(ActivationEvent {
+                plate: entity,
+                target: plate.target,
+            });
+        }
+        if plate.pressed != stood_on {
+            plate.pressed = stood_on;
+        }
+    }
+}
+
+// Sends an elevator to whichever of its floors is closest to the plate that called it
+fn call_elevators(
+    mut activation_events: EventReader<ActivationEvent>,
+    plate_query: Query<&GlobalTransform, With<PressurePlate>>,
+    mut elevator_query: Query<&mut Elevator>,
+) {
+    for event in activation_events.read() {
+        let (Ok(plate_transform), Ok(mut elevator)) = (plate_query.get(event.plate), elevator_query.get_mut(event.target)) else {
+            continue;
+        };
+        let Some(floor) = elevator.nearest_floor(plate_transform.translation()) else {
+            continue;
+        };
+        println!("Elevator called to floor {}", floor);
+        elevator.current_floor = floor;
+        elevator.waiting = false;
+        elevator.wait_timer = 0.0;
+    }
+}
+
+fn elevator_system(mut elevator_query: Query<(&mut Elevator, &mut Transform)>, time: Res<Time>) {
+    let delta_seconds = time.delta_seconds();
+    for (mut elevator, mut transform) in elevator_query.iter_mut() {
+        let Some(&target) = elevator.floors.get(elevator.current_floor) else {
+            continue;
+        };
+        if elevator.waiting {
+            elevator.wait_timer += delta_seconds;
+            if elevator.wait_timer >= elevator.wait_duration {
+                elevator.waiting = false;
+                elevator.wait_timer = 0.0;
+                elevator.advance();
+            }
+            continue;
+        }
+
+        let to_target = target - transform.translation;
+        let step = elevator.speed * delta_seconds;
+        if to_target.length() <= step {
+            transform.translation = target;
+            elevator.waiting = true;
+        } else {
+            transform.translation += to_target.normalize() * step;
+        }
+    }
+}
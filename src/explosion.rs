@@ -0,0 +1,195 @@
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use bevy_rapier3d::prelude::*;
+use std::collections::HashSet;
+use crate::combat::{DamageEvent, Health};
+use crate::particles::ParticleEmitter;
+use crate::status_effects::{ActiveEffect, ApplyStatusEffect, EffectKind};
+
+pub struct ExplosionPlugin;
+
+impl Plugin for ExplosionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_explosion_assets)
+            .add_systems(Update, (detonate_explosions, fade_explosion_lights, update_shockwaves));
+    }
+}
+
+const EXPLOSION_LIGHT_INTENSITY: f32 = 80000.0;
+const SHOCKWAVE_DURATION: f32 = 0.5;
+// Impulses are divided by the distance, but no closer than this so point blank hits stay finite
+const MIN_IMPULSE_DISTANCE: f32 = 1.0;
+// Anything caught in the blast keeps burning for a while, taking this much per tick
+const BURN_DURATION: f32 = 3.0;
+const BURN_DAMAGE: f32 = 4.0;
+
+// Detonates as soon as it's spawned and is replaced by its effects
+#[derive(Component, Clone, Copy)]
+pub struct Explosion {
+    pub radius: f32,
+    pub force: f32,
+    pub damage: f32,
+    pub light_duration: f32,
+}
+
+#[derive(Component)]
+struct ExplosionLight {
+    remaining: f32,
+    duration: f32,
+}
+
+#[derive(Component)]
+struct Shockwave {
+    age: f32,
+    radius: f32,
+}
+
+#[derive(Resource)]
+struct ExplosionAssets {
+    ring_mesh: Handle<Mesh>,
+}
+
+pub fn spawn_explosion(commands: &mut Commands, position: Vec3, config: Explosion) {
+    commands.spawn((
+        config,
+        TransformBundle::from_transform(Transform::from_translation(position)),
+    ));
+}
+
+fn setup_explosion_assets(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    commands.insert_resource(ExplosionAssets {
+        ring_mesh: meshes.add(Mesh::from(shape::Torus {
+            radius: 1.0,
+            ring_radius: 0.05,
+            ..default()
+        })),
+    });
+}
+
+fn detonate_explosions(
+    mut commands: Commands,
+    explosion_query: Query<(Entity, &Explosion, &Transform), Added<Explosion>>,
+    rapier_context: Res<RapierContext>,
+    mut body_query: Query<(&RigidBody, &GlobalTransform, Option<&mut ExternalImpulse>)>,
+    health_query: Query<(), With<Health>>,
+    mut damage_events: EventWriter<DamageEvent>,
+    mut effect_events: EventWriter<ApplyStatusEffect>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    assets: Res<ExplosionAssets>,
+) {
+    for (entity, explosion, transform) in explosion_query.iter() {
+        let center = transform.translation;
+
+        // A body with several colliders only gets hit once
+        let mut hit_entities = HashSet::new();
+        let blast = Collider::ball(explosion.radius);
+        rapier_context.intersections_with_shape(center, Quat::IDENTITY, &blast, QueryFilter::default(), |hit| {
+            hit_entities.insert(rapier_context.collider_parent(hit).unwrap_or(hit));
+            true
+        });
+
+        for hit in hit_entities {
+            if health_query.contains(hit) {
+                damage_events.send(DamageEvent {
+                    entity: hit,
+                    amount: explosion.damage,
+                });
+                effect_events.send(ApplyStatusEffect {
+                    entity: hit,
+                    effect: ActiveEffect::new(EffectKind::Burning, BURN_DURATION, BURN_DAMAGE),
+                });
+            }
+            let Ok((RigidBody::Dynamic, body_transform, impulse)) = body_query.get_mut(hit) else {
+                continue;
+            };
+            let offset = body_transform.translation() - center;
+            let push = offset.normalize_or_zero() * explosion.force / offset.length().max(MIN_IMPULSE_DISTANCE);
+            match impulse {
+                Some(mut impulse) => impulse.impulse += push,
+                None => {
+                    commands.entity(hit).insert(ExternalImpulse {
+                        impulse: push,
+                        ..default()
+                    });
+                }
+            }
+        }
+
+        commands.entity(entity).despawn();
+        commands.spawn((
+            ExplosionLight {
+                remaining: explosion.light_duration,
+                duration: explosion.light_duration,
+            },
+            PointLightBundle {
+                point_light: PointLight {
+                    intensity: EXPLOSION_LIGHT_INTENSITY,
+                    range: explosion.radius * 4.0,
+                    color: Color::rgb(1.0, 0.6, 0.2),
+                    ..default()
+                },
+                transform: Transform::from_translation(center),
+                ..default()
+            },
+        ));
+        commands.spawn((
+            ParticleEmitter::burst(40, explosion.radius * 2.0, 0.8, Color::rgb(1.0, 0.5, 0.1)),
+            TransformBundle::from_transform(Transform::from_translation(center)),
+        ));
+        commands.spawn((
+            Shockwave {
+                age: 0.0,
+                radius: explosion.radius,
+            },
+            PbrBundle {
+                mesh: assets.ring_mesh.clone(),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgba(1.0, 0.9, 0.7, 0.8),
+                    alpha_mode: AlphaMode::Blend,
+                    unlit: true,
+                    ..default()
+                }),
+                transform: Transform::from_translation(center).with_scale(Vec3::ZERO),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn fade_explosion_lights(
+    mut commands: Commands,
+    mut light_query: Query<(Entity, &mut ExplosionLight, &mut PointLight)>,
+    time: Res<Time>,
+) {
+    for (entity, mut light, mut point_light) in light_query.iter_mut() {
+        light.remaining -= time.delta_seconds();
+        if light.remaining <= 0.0 {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        point_light.intensity = EXPLOSION_LIGHT_INTENSITY * light.remaining / light.duration;
+    }
+}
+
+// The ring grows out to the blast radius while fading out
+fn update_shockwaves(
+    mut commands: Commands,
+    mut shockwave_query: Query<(Entity, &mut Shockwave, &mut Transform, &Handle<StandardMaterial>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    time: Res<Time>,
+) {
+    for (entity, mut shockwave, mut transform, material_handle) in shockwave_query.iter_mut() {
+        shockwave.age += time.delta_seconds();
+        let t = shockwave.age / SHOCKWAVE_DURATION;
+        if t >= 1.0 {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        // Flat scaling so the ring widens without getting thicker
+        let radius = shockwave.radius * t;
+        transform.scale = Vec3::new(radius, 1.0, radius);
+        if let Some(material) = materials.get_mut(material_handle) {
+            material.base_color.set_a(0.8 * (1.0 - t));
+        }
+    }
+}
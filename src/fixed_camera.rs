@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use std::collections::HashMap;
+use crate::camera::{camera_follow, CameraMode, CameraSnapEvent, ThirdPersonCamera};
+use crate::player::Player;
+
+pub struct FixedCameraPlugin;
+
+impl Plugin for FixedCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OccupiedCameraZones>()
+            .add_systems(Update, (track_fixed_camera_zones, add_fixed_camera_views).before(camera_follow))
+            .add_systems(Update, fixed_camera_system.after(camera_follow));
+    }
+}
+
+// A sensor that holds the camera of any player inside it on a pre-framed shot, easing there and
+// back over blend_time seconds
+#[derive(Component, Clone, Copy)]
+pub struct FixedCameraZone {
+    pub camera_transform: Transform,
+    pub blend_time: f32,
+}
+
+pub fn spawn_fixed_camera_zone(commands: &mut Commands, position: Vec3, half_extents: Vec3, zone: FixedCameraZone) {
+    commands.spawn((
+        zone,
+        Collider::cuboid(half_extents.x, half_extents.y, half_extents.z),
+        Sensor,
+        ActiveEvents::COLLISION_EVENTS,
+        TransformBundle::from_transform(Transform::from_translation(position)),
+    ));
+}
+
+// The zones each player is inside, in the order they went in. The last one wins where they overlap.
+#[derive(Resource, Default)]
+struct OccupiedCameraZones(HashMap<Entity, Vec<Entity>>);
+
+// Per camera: the zone holding it, and the blend into or back out of it
+#[derive(Component)]
+struct FixedCameraView {
+    zone: Option<Entity>,
+    // Mode to go back to on leaving the last zone
+    previous_mode: CameraMode,
+    // Pose the blend started from, seconds into it and how long it takes
+    from: Transform,
+    elapsed: f32,
+    blend_time: f32,
+}
+
+fn add_fixed_camera_views(mut commands: Commands, camera_query: Query<Entity, Added<ThirdPersonCamera>>) {
+    for entity in camera_query.iter() {
+        commands.entity(entity).insert(FixedCameraView {
+            zone: None,
+            previous_mode: CameraMode::ThirdPerson,
+            from: Transform::IDENTITY,
+            elapsed: 0.0,
+            blend_time: 0.0,
+        });
+    }
+}
+
+fn track_fixed_camera_zones(
+    mut collision_events: EventReader<CollisionEvent>,
+    mut occupied: ResMut<OccupiedCameraZones>,
+    zone_query: Query<(), With<FixedCameraZone>>,
+    player_query: Query<(), With<Player>>,
+) {
+    for event in collision_events.read() {
+        let (a, b, started) = match *event {
+            CollisionEvent::Started(a, b, _) => (a, b, true),
+            CollisionEvent::Stopped(a, b, _) => (a, b, false),
+        };
+        let (zone, player) = if zone_query.contains(a) { (a, b) } else { (b, a) };
+        if !zone_query.contains(zone) || !player_query.contains(player) {
+            continue;
+        }
+        let zones = occupied.0.entry(player).or_default();
+        zones.retain(|entered| *entered != zone);
+        if started {
+            zones.push(zone);
+        }
+    }
+}
+
+fn fixed_camera_system(
+    mut camera_query: Query<(&mut ThirdPersonCamera, &mut Transform, &mut FixedCameraView)>,
+    zone_query: Query<&FixedCameraZone>,
+    occupied: Res<OccupiedCameraZones>,
+    mut snap_events: EventWriter<CameraSnapEvent>,
+    time: Res<Time>,
+) {
+    for (mut camera, mut transform, mut view) in camera_query.iter_mut() {
+        // Photo mode has the camera, and puts the mode back itself
+        if camera.mode == CameraMode::Photo {
+            continue;
+        }
+        let zone = occupied
+            .0
+            .get(&camera.target)
+            .and_then(|zones| zones.iter().rev().find(|zone| zone_query.contains(**zone)))
+            .copied();
+
+        if zone != view.zone {
+            if let Some(entered) = zone.and_then(|zone| zone_query.get(zone).ok()) {
+                if view.zone.is_none() {
+                    view.previous_mode = camera.mode;
+                }
+                // Walk the way the shot looks, so pushing forward goes into the screen
+                let forward = entered.camera_transform.forward();
+                camera.fixed_rotation = (-forward.x).atan2(-forward.z);
+                camera.mode = CameraMode::Fixed;
+                camera.lock_on = None;
+                view.blend_time = entered.blend_time;
+                println!("Fixed camera zone {:?} entered", zone);
+            } else {
+                // The follow takes over again from next frame; the blend eases from here to it
+                camera.mode = view.previous_mode;
+                println!("Fixed camera zone left");
+            }
+            view.zone = zone;
+            view.from = *transform;
+            view.elapsed = 0.0;
+        }
+
+        view.elapsed += time.delta_seconds();
+        let t = if view.blend_time > 0.0 { (view.elapsed / view.blend_time).min(1.0) } else { 1.0 };
+        let t = t * t * (3.0 - 2.0 * t);
+        match view.zone.and_then(|zone| zone_query.get(zone).ok()) {
+            Some(zone) => {
+                transform.translation = view.from.translation.lerp(zone.camera_transform.translation, t);
+                transform.rotation = view.from.rotation.slerp(zone.camera_transform.rotation, t);
+            }
+            None if t < 1.0 => {
+                // The follow has just put the camera where it wants to be; ease from the shot to there
+                transform.translation = view.from.translation.lerp(transform.translation, t);
+                transform.rotation = view.from.rotation.slerp(transform.rotation, t);
+                // Keeps the follow spring from chasing the blended pose
+                snap_events.send(CameraSnapEvent { target: camera.target });
+            }
+            None => {}
+        }
+    }
+}
@@ -0,0 +1,221 @@
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy_rapier3d::prelude::*;
+use std::collections::VecDeque;
+use crate::player::Footstep;
+use crate::terrain::{height_at, SurfaceType, TerrainConfig, GRASS_SIZE, GRASS_TOP};
+use crate::weather::SnowAccumulation;
+
+pub struct FootprintPlugin;
+
+impl Plugin for FootprintPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<DecalPool>()
+            .add_systems(Startup, setup_footprints)
+            .add_systems(Update, (place_footprints, decal_fade_system).chain());
+    }
+}
+
+// Oldest decals are reused once this many exist
+const MAX_DECALS: usize = 256;
+// Footprints sit this far off the surface to avoid z-fighting
+const DECAL_OFFSET: f32 = 0.01;
+// How far below the foot the surface is looked for
+const FOOTPRINT_RAY_LENGTH: f32 = 2.0;
+// Feet point slightly outwards
+const TOE_OUT_ANGLE: f32 = 0.1;
+const FOOTPRINT_TEXTURE_WIDTH: u32 = 32;
+const FOOTPRINT_TEXTURE_LENGTH: u32 = 64;
+
+// A textured quad on a surface that fades out over its lifetime
+#[derive(Component)]
+pub struct Decal {
+    pub texture: Handle<Image>,
+    // Seconds left
+    pub lifetime: f32,
+    pub initial_lifetime: f32,
+    // Alpha when freshly placed
+    pub opacity: f32,
+}
+
+#[derive(Resource, Default)]
+struct DecalPool {
+    // Oldest first
+    entities: VecDeque<Entity>,
+}
+
+#[derive(Resource)]
+struct FootprintAssets {
+    mesh: Handle<Mesh>,
+    texture: Handle<Image>,
+}
+
+// How a surface takes a footprint
+struct FootprintStyle {
+    size: Vec2,
+    color: Color,
+    lifetime: f32,
+}
+
+fn footprint_style(surface: SurfaceType) -> Option<FootprintStyle> {
+    match surface {
+        // Deep prints that linger until the snow covers them
+        SurfaceType::Snow => Some(FootprintStyle {
+            size: Vec2::new(0.3, 0.45),
+            color: Color::rgba(0.45, 0.5, 0.6, 0.85),
+            lifetime: 60.0,
+        }),
+        // Shallow, flattened grass
+        SurfaceType::Grass => Some(FootprintStyle {
+            size: Vec2::new(0.25, 0.4),
+            color: Color::rgba(0.15, 0.35, 0.1, 0.35),
+            lifetime: 15.0,
+        }),
+        SurfaceType::Wood | SurfaceType::Stone | SurfaceType::Ice => None,
+    }
+}
+
+// Soft-edged sole shape, white so the material color tints it
+fn footprint_texture() -> Image {
+    let mut data = Vec::with_capacity((FOOTPRINT_TEXTURE_WIDTH * FOOTPRINT_TEXTURE_LENGTH * 4) as usize);
+    for y in 0..FOOTPRINT_TEXTURE_LENGTH {
+        for x in 0..FOOTPRINT_TEXTURE_WIDTH {
+            let u = (x as f32 + 0.5) / FOOTPRINT_TEXTURE_WIDTH as f32 * 2.0 - 1.0;
+            let v = (y as f32 + 0.5) / FOOTPRINT_TEXTURE_LENGTH as f32 * 2.0 - 1.0;
+            // Narrower at the heel than at the ball of the foot
+            let width = 0.75 + 0.25 * -v;
+            let distance = ((u / width).powi(2) + v * v).sqrt();
+            let alpha = ((1.0 - distance) * 5.0).clamp(0.0, 1.0);
+            data.extend_from_slice(&[255, 255, 255, (alpha * 255.0) as u8]);
+        }
+    }
+    Image::new(
+        Extent3d {
+            width: FOOTPRINT_TEXTURE_WIDTH,
+            height: FOOTPRINT_TEXTURE_LENGTH,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+    )
+}
+
+fn setup_footprints(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    commands.insert_resource(FootprintAssets {
+        mesh: meshes.add(Mesh::from(shape::Plane::from_size(1.0))),
+        texture: images.add(footprint_texture()),
+    });
+}
+
+fn place_footprints(
+    mut commands: Commands,
+    mut footstep_events: EventReader<Footstep>,
+    mut pool: ResMut<DecalPool>,
+    assets: Res<FootprintAssets>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    rapier_context: Res<RapierContext>,
+    surface_query: Query<&SurfaceType>,
+    snow_query: Query<&SnowAccumulation>,
+    config: Res<TerrainConfig>,
+    mut decal_query: Query<(&mut Decal, &mut Transform, &mut Visibility, &Handle<StandardMaterial>)>,
+) {
+    for footstep in footstep_events.read() {
+        let filter = QueryFilter::default().exclude_rigid_body(footstep.player).exclude_sensors();
+        let Some((hit_entity, hit)) = rapier_context.cast_ray_and_get_normal(footstep.position, Vec3::NEG_Y, FOOTPRINT_RAY_LENGTH, true, filter) else {
+            continue;
+        };
+        let body = rapier_context.collider_parent(hit_entity).unwrap_or(hit_entity);
+        let Ok(&surface) = surface_query.get(body) else {
+            continue;
+        };
+
+        let mut point = hit.point;
+        let mut surface = surface;
+        if surface == SurfaceType::Grass {
+            // The island's collider is under the visual-only grass layer
+            if point.x.abs() <= GRASS_SIZE * 0.5 && point.z.abs() <= GRASS_SIZE * 0.5 {
+                point.y = point.y.max(height_at(&config, point.x, point.z) + GRASS_TOP);
+            }
+            if let Some(height) = snow_query.get_single().ok().and_then(|snow| snow.surface_height(point)) {
+                point.y = height;
+                surface = SurfaceType::Snow;
+            }
+        }
+        let Some(style) = footprint_style(surface) else {
+            continue;
+        };
+
+        let toe_out = if footstep.left { TOE_OUT_ANGLE } else { -TOE_OUT_ANGLE };
+        let transform = Transform::from_translation(point + hit.normal * DECAL_OFFSET)
+            .with_rotation(Quat::from_rotation_arc(Vec3::Y, hit.normal) * footstep.rotation * Quat::from_rotation_y(toe_out))
+            .with_scale(Vec3::new(style.size.x, 1.0, style.size.y));
+        let decal = Decal {
+            texture: assets.texture.clone(),
+            lifetime: style.lifetime,
+            initial_lifetime: style.lifetime,
+            opacity: style.color.a(),
+        };
+        let material = StandardMaterial {
+            base_color: style.color,
+            base_color_texture: Some(decal.texture.clone()),
+            alpha_mode: AlphaMode::Blend,
+            perceptual_roughness: 1.0,
+            ..default()
+        };
+
+        // Recycle the oldest decal once the pool is full
+        if pool.entities.len() >= MAX_DECALS {
+            if let Some(oldest) = pool.entities.pop_front() {
+                if let Ok((mut old_decal, mut old_transform, mut visibility, material_handle)) = decal_query.get_mut(oldest) {
+                    *old_decal = decal;
+                    *old_transform = transform;
+                    *visibility = Visibility::Visible;
+                    if let Some(old_material) = materials.get_mut(material_handle) {
+                        *old_material = material;
+                    }
+                    pool.entities.push_back(oldest);
+                    continue;
+                }
+            }
+        }
+
+        let entity = commands.spawn((
+            decal,
+            PbrBundle {
+                mesh: assets.mesh.clone(),
+                material: materials.add(material),
+                transform,
+                ..default()
+            },
+        )).id();
+        pool.entities.push_back(entity);
+    }
+}
+
+fn decal_fade_system(
+    mut decal_query: Query<(&mut Decal, &mut Visibility, &Handle<StandardMaterial>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    time: Res<Time>,
+) {
+    for (mut decal, mut visibility, material_handle) in decal_query.iter_mut() {
+        if decal.lifetime <= 0.0 {
+            continue;
+        }
+        decal.lifetime = (decal.lifetime - time.delta_seconds()).max(0.0);
+        // Expired decals stay in the pool, hidden, until they're recycled
+        if decal.lifetime == 0.0 {
+            *visibility = Visibility::Hidden;
+            continue;
+        }
+        if let Some(material) = materials.get_mut(material_handle) {
+            let alpha = decal.opacity * decal.lifetime / decal.initial_lifetime;
+            material.base_color.set_a(alpha);
+        }
+    }
+}
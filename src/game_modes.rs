@@ -0,0 +1,528 @@
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use crate::camera::CameraSnapEvent;
+use crate::combat::{Health, HealthChanged};
+use crate::debug::{GodMode, Noclip};
+use crate::health::{Dead, RespawnPoint};
+use crate::input_map::{Action, InputMap};
+use crate::player::{GroundState, Player, Players};
+use crate::terrain::{MovingPlatform, SurfaceType};
+
+// Timed obstacle course east of the island, toggled on and off. Like the other abilities it's
+// run by the first player only.
+pub struct ObstacleCoursePlugin;
+
+impl Plugin for ObstacleCoursePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ObstacleCourse>()
+            .init_resource::<RunTimer>()
+            .insert_resource(Leaderboard::load())
+            .add_systems(Startup, setup_course_hud)
+            .add_systems(Update, (
+                toggle_obstacle_course,
+                course_triggers,
+                tick_run_timer,
+                lava_zones,
+                bounce_pads,
+                breakable_platforms,
+                update_course_hud,
+            ).chain());
+    }
+}
+
+const LEADERBOARD_DIR: &str = "save";
+const LEADERBOARD_FILE: &str = "save/leaderboard.json";
+const LEADERBOARD_SIZE: usize = 10;
+
+const COURSE_PAR_TIME: f32 = 45.0;
+// Trigger zones cover this much space above their surface, enough for the player's capsule
+const ZONE_HEIGHT: f32 = 2.0;
+// The player's centre sits this far above whatever it's standing on
+const STANDING_HEIGHT: f32 = 1.5;
+// Seconds a breakable platform holds once stepped on, and how long it stays gone after
+const BREAK_DELAY: f32 = 0.6;
+const BREAK_RESPAWN_TIME: f32 = 3.0;
+
+#[derive(Clone, Copy, Debug)]
+enum CoursePiece {
+    Platform,
+    // Eases back and forth between its position and position + offset
+    Moving { offset: Vec3, period: f32 },
+    // Crumbles shortly after being stood on and comes back a few seconds later
+    Breakable,
+    // Thin strip on top of a platform that kills on contact
+    Lava,
+    // Launches the player straight up at launch_speed
+    BouncePad { launch_speed: f32 },
+    // Platforms that also start, checkpoint or finish the run
+    Start,
+    Checkpoint,
+    Finish,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct CourseSection {
+    piece: CoursePiece,
+    position: Vec3,
+    half_extents: Vec3,
+}
+
+const fn section(piece: CoursePiece, position: Vec3, half_extents: Vec3) -> CourseSection {
+    CourseSection { piece, position, half_extents }
+}
+
+// End to end along +X, past the eastern floating platform
+const COURSE_LAYOUT: &[CourseSection] = &[
+    section(CoursePiece::Start, Vec3::new(45.0, 4.0, 0.0), Vec3::new(3.0, 0.5, 3.0)),
+    section(CoursePiece::Platform, Vec3::new(51.0, 4.5, 0.0), Vec3::new(1.5, 0.5, 1.5)),
+    section(CoursePiece::Breakable, Vec3::new(55.0, 5.0, 0.0), Vec3::new(1.2, 0.25, 1.2)),
+    section(CoursePiece::Breakable, Vec3::new(59.0, 5.5, 1.5), Vec3::new(1.2, 0.25, 1.2)),
+    section(CoursePiece::Checkpoint, Vec3::new(64.0, 5.5, 0.0), Vec3::new(2.0, 0.5, 2.0)),
+    section(CoursePiece::Platform, Vec3::new(72.0, 5.5, 0.0), Vec3::new(5.0, 0.5, 2.0)),
+    section(CoursePiece::Lava, Vec3::new(72.0, 6.05, 0.0), Vec3::new(1.5, 0.05, 2.0)),
+    section(CoursePiece::Platform, Vec3::new(80.0, 5.5, 0.0), Vec3::new(1.5, 0.5, 1.5)),
+    section(CoursePiece::BouncePad { launch_speed: 17.0 }, Vec3::new(80.0, 6.1, 0.0), Vec3::new(1.0, 0.1, 1.0)),
+    section(CoursePiece::Checkpoint, Vec3::new(86.0, 15.0, 0.0), Vec3::new(2.5, 0.5, 2.5)),
+    section(CoursePiece::Moving { offset: Vec3::new(6.0, 0.0, 0.0), period: 5.0 }, Vec3::new(91.0, 15.0, 0.0), Vec3::new(1.25, 0.25, 1.25)),
+    section(CoursePiece::Finish, Vec3::new(102.0, 15.0, 0.0), Vec3::new(3.0, 0.5, 3.0)),
+];
+
+#[derive(Resource)]
+pub struct ObstacleCourse {
+    pub active: bool,
+    pub par_time: f32,
+    // Respawn point from before the course, restored when it's turned off
+    previous_respawn: Option<Vec3>,
+}
+
+impl Default for ObstacleCourse {
+    fn default() -> Self {
+        Self {
+            active: false,
+            par_time: COURSE_PAR_TIME,
+            previous_respawn: None,
+        }
+    }
+}
+
+// Time since the player left the start zone. Stopped at the finish, which sets last_time.
+#[derive(Resource, Default)]
+pub struct RunTimer {
+    pub elapsed: f32,
+    pub running: bool,
+    pub last_time: Option<f32>,
+}
+
+// Fastest finishes, best first, kept across runs of the game
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct Leaderboard {
+    pub entries: Vec<(String, f32)>,
+}
+
+impl Leaderboard {
+    fn load() -> Self {
+        let Ok(contents) = fs::read_to_string(LEADERBOARD_FILE) else {
+            return Self::default();
+        };
+        match serde_json::from_str(&contents) {
+            Ok(leaderboard) => leaderboard,
+            Err(err) => {
+                println!("WARNING: Failed to parse {}: {}, starting an empty leaderboard", LEADERBOARD_FILE, err);
+                Self::default()
+            }
+        }
+    }
+
+    fn save(&self) {
+        let contents = match serde_json::to_string_pretty(self) {
+            Ok(contents) => contents,
+            Err(err) => {
+                println!("WARNING: Failed to serialize leaderboard: {}", err);
+                return;
+            }
+        };
+        let _ = fs::create_dir_all(LEADERBOARD_DIR);
+        if let Err(err) = fs::write(LEADERBOARD_FILE, contents) {
+            println!("WARNING: Failed to write {}: {}", LEADERBOARD_FILE, err);
+        }
+    }
+
+    // Returns the 1-based rank if the time made the board
+    fn insert(&mut self, name: String, time: f32) -> Option<usize> {
+        let rank = self.entries.partition_point(|(_, entry_time)| *entry_time <= time);
+        if rank >= LEADERBOARD_SIZE {
+            return None;
+        }
+        self.entries.insert(rank, (name, time));
+        self.entries.truncate(LEADERBOARD_SIZE);
+        Some(rank + 1)
+    }
+}
+
+// Everything spawned for the course, despawned when it's turned off
+#[derive(Component)]
+struct CoursePart;
+
+// A box the player's centre can be inside of, relative to the entity's translation
+#[derive(Component, Clone, Copy)]
+enum CourseZone {
+    Start { half_extents: Vec3 },
+    Checkpoint { half_extents: Vec3 },
+    Finish { half_extents: Vec3 },
+    Lava { half_extents: Vec3 },
+}
+
+impl CourseZone {
+    fn contains(&self, center: Vec3, point: Vec3) -> bool {
+        let (CourseZone::Start { half_extents }
+            | CourseZone::Checkpoint { half_extents }
+            | CourseZone::Finish { half_extents }
+            | CourseZone::Lava { half_extents }) = *self;
+        ((point - center).abs() - half_extents).max_element() <= 0.0
+    }
+}
+
+#[derive(Component)]
+struct BouncePad {
+    launch_speed: f32,
+}
+
+#[derive(Component, Default)]
+struct BreakablePlatform {
+    // Counting down to crumbling once stood on
+    break_timer: Option<f32>,
+    // Counting down to coming back once crumbled
+    respawn_timer: Option<f32>,
+}
+
+#[derive(Component)]
+struct CourseTimerText;
+
+fn setup_course_hud(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 32.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(10.0),
+            left: Val::Percent(45.0),
+            ..default()
+        }),
+        CourseTimerText,
+    ));
+}
+
+fn spawn_course(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+) {
+    for section in COURSE_LAYOUT {
+        let size = section.half_extents * 2.0;
+        let color = match section.piece {
+            CoursePiece::Platform | CoursePiece::Moving { .. } => Color::rgb(0.55, 0.55, 0.6),
+            CoursePiece::Breakable => Color::rgb(0.65, 0.5, 0.3),
+            CoursePiece::Lava => Color::rgb(1.0, 0.3, 0.0),
+            CoursePiece::BouncePad { .. } => Color::rgb(0.2, 0.8, 0.3),
+            CoursePiece::Start => Color::rgb(0.2, 0.4, 0.9),
+            CoursePiece::Checkpoint => Color::rgb(0.9, 0.8, 0.2),
+            CoursePiece::Finish => Color::rgb(0.9, 0.2, 0.6),
+        };
+        let mut entity = commands.spawn((
+            CoursePart,
+            PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Box::new(size.x, size.y, size.z))),
+                material: materials.add(StandardMaterial {
+                    base_color: color,
+                    emissive: if matches!(section.piece, CoursePiece::Lava) { color * 2.0 } else { Color::BLACK },
+                    ..default()
+                }),
+                transform: Transform::from_translation(section.position),
+                ..default()
+            },
+        ));
+
+        // Lava is only a zone; it sits on a platform that does the colliding
+        if !matches!(section.piece, CoursePiece::Lava) {
+            entity.insert((Collider::cuboid(section.half_extents.x, section.half_extents.y, section.half_extents.z), SurfaceType::Stone));
+        }
+        match section.piece {
+            CoursePiece::Moving { offset, period } => {
+                entity.insert((
+                    MovingPlatform {
+                        start: section.position,
+                        end: section.position + offset,
+                        period,
+                        elapsed: 0.0,
+                        active: true,
+                    },
+                    RigidBody::KinematicPositionBased,
+                    // Filled in by rapier each step so riders can pick up the platform's motion
+                    Velocity::zero(),
+                ));
+            }
+            CoursePiece::Breakable => {
+                entity.insert((RigidBody::Fixed, BreakablePlatform::default()));
+            }
+            CoursePiece::BouncePad { launch_speed } => {
+                entity.insert((RigidBody::Fixed, BouncePad { launch_speed }));
+            }
+            _ => {
+                entity.insert(RigidBody::Fixed);
+            }
+        }
+
+        // Zones are separate entities sitting on top of the surface
+        let half_extents = Vec3::new(section.half_extents.x, ZONE_HEIGHT / 2.0, section.half_extents.z);
+        let zone = match section.piece {
+            CoursePiece::Start => Some(CourseZone::Start { half_extents }),
+            CoursePiece::Checkpoint => Some(CourseZone::Checkpoint { half_extents }),
+            CoursePiece::Finish => Some(CourseZone::Finish { half_extents }),
+            CoursePiece::Lava => Some(CourseZone::Lava { half_extents }),
+            _ => None,
+        };
+        if let Some(zone) = zone {
+            let surface = section.position + Vec3::Y * section.half_extents.y;
+            commands.spawn((
+                CoursePart,
+                zone,
+                TransformBundle::from_transform(Transform::from_translation(surface + Vec3::Y * ZONE_HEIGHT / 2.0)),
+            ));
+        }
+    }
+}
+
+fn course_start_position() -> Vec3 {
+    let start = COURSE_LAYOUT
+        .iter()
+        .find(|section| matches!(section.piece, CoursePiece::Start))
+        .expect("Course layout has no start");
+    start.position + Vec3::Y * (start.half_extents.y + STANDING_HEIGHT)
+}
+
+// Turning the course on spawns it and moves the player to the start; turning it off clears it
+// and sends the player back to where they'd respawn before
+fn toggle_obstacle_course(
+    mut commands: Commands,
+    input_map: Res<InputMap>,
+    mut course: ResMut<ObstacleCourse>,
+    mut run_timer: ResMut<RunTimer>,
+    mut respawn_point: ResMut<RespawnPoint>,
+    players: Res<Players>,
+    mut player_query: Query<(&mut Player, &mut Transform, &mut Velocity), Without<Dead>>,
+    part_query: Query<Entity, With<CoursePart>>,
+    mut snap_events: EventWriter<CameraSnapEvent>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !input_map.just_pressed(Action::ToggleObstacleCourse) {
+        return;
+    }
+    let Some(&entity) = players.0.first() else {
+        return;
+    };
+    let Ok((mut player, mut transform, mut velocity)) = player_query.get_mut(entity) else {
+        return;
+    };
+
+    course.active = !course.active;
+    *run_timer = RunTimer::default();
+    let destination = if course.active {
+        spawn_course(&mut commands, &mut meshes, &mut materials);
+        course.previous_respawn = Some(respawn_point.0);
+        respawn_point.0 = course_start_position();
+        println!("=== OBSTACLE COURSE === Par time {:.2}s", course.par_time);
+        respawn_point.0
+    } else {
+        for part in part_query.iter() {
+            commands.entity(part).despawn_recursive();
+        }
+        if let Some(previous) = course.previous_respawn.take() {
+            respawn_point.0 = previous;
+        }
+        println!("Obstacle course closed");
+        respawn_point.0
+    };
+
+    transform.translation = destination;
+    *velocity = Velocity::zero();
+    player.ground = GroundState::Airborne;
+    player.ground_entity = None;
+    player.fall_speed = 0.0;
+    snap_events.send(CameraSnapEvent { target: entity });
+}
+
+// Standing in the start zone holds the timer at zero, so it starts on leaving it
+fn course_triggers(
+    course: Res<ObstacleCourse>,
+    mut run_timer: ResMut<RunTimer>,
+    mut leaderboard: ResMut<Leaderboard>,
+    mut respawn_point: ResMut<RespawnPoint>,
+    players: Res<Players>,
+    player_query: Query<&Transform, (With<Player>, Without<Dead>)>,
+    zone_query: Query<(&CourseZone, &Transform)>,
+) {
+    if !course.active {
+        return;
+    }
+    let Some(player_position) = players.0.first().and_then(|entity| player_query.get(*entity).ok()).map(|transform| transform.translation) else {
+        return;
+    };
+
+    for (zone, transform) in zone_query.iter() {
+        if !zone.contains(transform.translation, player_position) {
+            continue;
+        }
+        match zone {
+            CourseZone::Start { .. } => {
+                if !run_timer.running {
+                    println!("Run started");
+                }
+                run_timer.elapsed = 0.0;
+                run_timer.running = true;
+            }
+            CourseZone::Checkpoint { half_extents } => {
+                let checkpoint = transform.translation + Vec3::Y * (STANDING_HEIGHT - half_extents.y);
+                // Only the first frame inside counts as reaching it, so saves don't repeat
+                if respawn_point.0.distance(checkpoint) > 0.01 {
+                    respawn_point.0 = checkpoint;
+                    println!("Course checkpoint reached at {:.2}s", run_timer.elapsed);
+                }
+            }
+            CourseZone::Finish { .. } if run_timer.running => {
+                run_timer.running = false;
+                let time = run_timer.elapsed;
+                run_timer.last_time = Some(time);
+                let difference = time - course.par_time;
+                if difference <= 0.0 {
+                    println!("=== COURSE FINISHED === {:.2}s, {:.2}s under par", time, -difference);
+                } else {
+                    println!("=== COURSE FINISHED === {:.2}s, {:.2}s over par", time, difference);
+                }
+                if let Some(rank) = leaderboard.insert("Player 1".to_string(), time) {
+                    println!("New leaderboard time, rank {}", rank);
+                    leaderboard.save();
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn tick_run_timer(mut run_timer: ResMut<RunTimer>, time: Res<Time>) {
+    if run_timer.running {
+        run_timer.elapsed += time.delta_seconds();
+    }
+}
+
+// Like the kill plane, touching lava takes all of the player's health
+fn lava_zones(
+    course: Res<ObstacleCourse>,
+    mut player_query: Query<(Entity, &Transform, &mut Health), (With<Player>, Without<Dead>, Without<Noclip>, Without<GodMode>)>,
+    zone_query: Query<(&CourseZone, &Transform)>,
+    mut health_changed_events: EventWriter<HealthChanged>,
+) {
+    if !course.active {
+        return;
+    }
+    for (entity, transform, mut health) in player_query.iter_mut() {
+        let in_lava = zone_query.iter().any(|(zone, zone_transform)| {
+            matches!(zone, CourseZone::Lava { .. }) && zone.contains(zone_transform.translation, transform.translation)
+        });
+        if in_lava && health.current > 0.0 {
+            println!("Player touched lava");
+            health_changed_events.send(HealthChanged {
+                entity,
+                previous: health.current,
+                current: 0.0,
+                max: health.max,
+            });
+            health.current = 0.0;
+        }
+    }
+}
+
+fn bounce_pads(
+    mut player_query: Query<(&mut Player, &mut Velocity), Without<Dead>>,
+    pad_query: Query<&BouncePad>,
+) {
+    for (mut player, mut velocity) in player_query.iter_mut() {
+        let Some(pad) = player.ground_entity.and_then(|entity| pad_query.get(entity).ok()) else {
+            continue;
+        };
+        velocity.linvel.y = pad.launch_speed;
+        player.ground = GroundState::Airborne;
+        player.ground_entity = None;
+    }
+}
+
+// Stepping on a breakable platform starts it crumbling; it drops its collider and mesh a
+// moment later, then comes back so the course can be retried
+fn breakable_platforms(
+    mut commands: Commands,
+    player_query: Query<&Player>,
+    mut platform_query: Query<(Entity, &mut BreakablePlatform, &mut Visibility)>,
+    time: Res<Time>,
+) {
+    let delta_seconds = time.delta_seconds();
+    for (entity, mut platform, mut visibility) in platform_query.iter_mut() {
+        if let Some(remaining) = platform.respawn_timer.as_mut() {
+            *remaining -= delta_seconds;
+            if *remaining <= 0.0 {
+                platform.respawn_timer = None;
+                commands.entity(entity).remove::<ColliderDisabled>();
+                *visibility = Visibility::Inherited;
+            }
+            continue;
+        }
+
+        let stood_on = player_query.iter().any(|player| player.ground_entity == Some(entity));
+        if stood_on && platform.break_timer.is_none() {
+            platform.break_timer = Some(BREAK_DELAY);
+        }
+        if let Some(remaining) = platform.break_timer.as_mut() {
+            *remaining -= delta_seconds;
+            if *remaining <= 0.0 {
+                platform.break_timer = None;
+                platform.respawn_timer = Some(BREAK_RESPAWN_TIME);
+                commands.entity(entity).insert(ColliderDisabled);
+                *visibility = Visibility::Hidden;
+            }
+        }
+    }
+}
+
+fn format_time(seconds: f32) -> String {
+    format!("{}:{:05.2}", (seconds / 60.0) as u32, seconds % 60.0)
+}
+
+fn update_course_hud(
+    course: Res<ObstacleCourse>,
+    run_timer: Res<RunTimer>,
+    leaderboard: Res<Leaderboard>,
+    mut text_query: Query<&mut Text, With<CourseTimerText>>,
+) {
+    let Ok(mut text) = text_query.get_single_mut() else {
+        return;
+    };
+    let value = if !course.active {
+        String::new()
+    } else if let (false, Some(last_time)) = (run_timer.running, run_timer.last_time) {
+        let best = leaderboard.entries.first().map_or(last_time, |(_, time)| *time);
+        format!("Finished {}  Par {}  Best {}", format_time(last_time), format_time(course.par_time), format_time(best))
+    } else {
+        format!("{}  Par {}", format_time(run_timer.elapsed), format_time(course.par_time))
+    };
+    if text.sections[0].value != value {
+        text.sections[0].value = value;
+    }
+}
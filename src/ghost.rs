@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use crate::health::{Dead, RespawnPoint};
+use crate::input_map::{Action, InputMap};
+use crate::player::Player;
+use crate::recording::{load_recording, Keyframe, TICK_SECONDS};
+
+// Races a translucent copy of the player along a recorded run: `cargo run -- ghost <file>`
+pub struct GhostPlugin;
+
+impl Plugin for GhostPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(ghost_run) = ghost_run_from_args() {
+            app.insert_resource(ghost_run);
+        }
+        app.add_systems(PostStartup, (spawn_ghost, remember_start_point).after(crate::player::spawn_player))
+            .add_systems(Update, (restart_ghost_on_respawn, toggle_ghost, update_ghost).chain());
+    }
+}
+
+// The recorded run the ghost follows
+#[derive(Resource)]
+pub struct GhostRun {
+    keyframes: Vec<Keyframe>,
+    // Seconds into the recording since the current run started
+    pub elapsed: f32,
+    pub finished: bool,
+    // Respawning here restarts the ghost
+    start_point: Vec3,
+}
+
+impl GhostRun {
+    // Linear between keyframes for position, slerp for rotation; holds the last one once it runs out
+    fn transform_at(&self, time: f32) -> Option<Transform> {
+        let keyframe_time = |keyframe: &Keyframe| keyframe.tick as f32 * TICK_SECONDS;
+        let next = self.keyframes.iter().position(|keyframe| keyframe_time(keyframe) > time);
+        let (from, to) = match next {
+            Some(0) => return self.keyframes.first().map(keyframe_transform),
+            Some(index) => (&self.keyframes[index - 1], &self.keyframes[index]),
+            None => return self.keyframes.last().map(keyframe_transform),
+        };
+        let span = (keyframe_time(to) - keyframe_time(from)).max(f32::EPSILON);
+        let t = ((time - keyframe_time(from)) / span).clamp(0.0, 1.0);
+        Some(Transform {
+            translation: from.translation.lerp(to.translation, t),
+            rotation: from.rotation.slerp(to.rotation, t),
+            ..default()
+        })
+    }
+
+    fn duration(&self) -> f32 {
+        self.keyframes.last().map_or(0.0, |keyframe| keyframe.tick as f32 * TICK_SECONDS)
+    }
+}
+
+fn keyframe_transform(keyframe: &Keyframe) -> Transform {
+    Transform::from_translation(keyframe.translation).with_rotation(keyframe.rotation)
+}
+
+#[derive(Component)]
+pub struct Ghost;
+
+fn ghost_run_from_args() -> Option<GhostRun> {
+    let args: Vec<String> = std::env::args().collect();
+    let index = args.iter().position(|arg| arg == "ghost")?;
+    let Some(path) = args.get(index + 1) else {
+        println!("WARNING: ghost needs a recording file");
+        return None;
+    };
+    let recording = match load_recording(path) {
+        Ok(recording) => recording,
+        Err(err) => {
+            println!("WARNING: Failed to load ghost recording {}: {}", path, err);
+            return None;
+        }
+    };
+    if recording.keyframes.is_empty() {
+        println!("WARNING: Ghost recording {} has no keyframes", path);
+        return None;
+    }
+    println!("Racing the ghost of {} ({} keyframes)", path, recording.keyframes.len());
+    Some(GhostRun {
+        keyframes: recording.keyframes,
+        elapsed: 0.0,
+        finished: false,
+        start_point: Vec3::ZERO,
+    })
+}
+
+// Same shape as the player's model, but without a body or collider so nothing can touch it
+fn spawn_ghost(
+    mut commands: Commands,
+    ghost_run: Option<Res<GhostRun>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let Some(ghost_run) = ghost_run else {
+        return;
+    };
+    commands.spawn((
+        Ghost,
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Capsule {
+                radius: 0.5,
+                depth: 2.0,
+                ..default()
+            })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgba(0.6, 0.8, 1.0, 0.35),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            }),
+            transform: ghost_run.transform_at(0.0).unwrap_or_default(),
+            ..default()
+        },
+    ));
+}
+
+fn remember_start_point(ghost_run: Option<ResMut<GhostRun>>, respawn_point: Res<RespawnPoint>) {
+    if let Some(mut ghost_run) = ghost_run {
+        ghost_run.start_point = respawn_point.0;
+    }
+}
+
+fn restart_ghost_on_respawn(
+    ghost_run: Option<ResMut<GhostRun>>,
+    mut respawned: RemovedComponents<Dead>,
+    player_query: Query<(), With<Player>>,
+    respawn_point: Res<RespawnPoint>,
+) {
+    let Some(mut ghost_run) = ghost_run else {
+        return;
+    };
+    let player_respawned = respawned.read().any(|entity| player_query.contains(entity));
+    if player_respawned && respawn_point.0.distance(ghost_run.start_point) < 0.01 {
+        ghost_run.elapsed = 0.0;
+        ghost_run.finished = false;
+        println!("Ghost restarted");
+    }
+}
+
+fn toggle_ghost(input_map: Res<InputMap>, mut ghost_query: Query<&mut Visibility, With<Ghost>>) {
+    if !input_map.just_pressed(Action::ToggleGhost) {
+        return;
+    }
+    for mut visibility in ghost_query.iter_mut() {
+        *visibility = match *visibility {
+            Visibility::Hidden => Visibility::Visible,
+            _ => Visibility::Hidden,
+        };
+    }
+}
+
+// Freezes on the final frame once the recording runs out
+fn update_ghost(
+    ghost_run: Option<ResMut<GhostRun>>,
+    mut ghost_query: Query<&mut Transform, With<Ghost>>,
+    time: Res<Time>,
+) {
+    let Some(mut ghost_run) = ghost_run else {
+        return;
+    };
+    if ghost_run.finished {
+        return;
+    }
+    ghost_run.elapsed += time.delta_seconds();
+    if ghost_run.elapsed >= ghost_run.duration() {
+        ghost_run.finished = true;
+        println!("=== GHOST FINISHED === after {:.2}s", ghost_run.duration());
+    }
+    let Some(ghost_transform) = ghost_run.transform_at(ghost_run.elapsed) else {
+        return;
+    };
+    for mut transform in ghost_query.iter_mut() {
+        *transform = ghost_transform;
+    }
+}
@@ -0,0 +1,242 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use bevy::prelude::shape;
+use crate::camera::AimTarget;
+use crate::debug::Noclip;
+use crate::health::Dead;
+use crate::input_map::{Action, InputMap};
+use crate::player::{Player, PlayerIndex, PlayerSettings};
+
+pub struct GrapplePlugin;
+
+impl Plugin for GrapplePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GrappleSettings>()
+            .add_systems(Startup, spawn_grapple_rope)
+            .add_systems(Update, (grapple_input, update_grapple, update_grapple_rope).chain());
+    }
+}
+
+#[derive(Resource)]
+pub struct GrappleSettings {
+    // Furthest an anchor can be when firing
+    pub grapple_range: f32,
+    // How fast the rope shortens while attached
+    pub reel_speed: f32,
+    // The rope never reels in closer than this
+    pub min_rope_length: f32,
+    // Detach if the player ends up further than this from the anchor
+    pub max_rope_length: f32,
+    // Spring pulling the player back when the rope is stretched past its length
+    pub stiffness: f32,
+    pub damping: f32,
+    // Detach once the anchor has been hidden behind something this long
+    pub line_of_sight_grace: f32,
+}
+
+impl Default for GrappleSettings {
+    fn default() -> Self {
+        Self {
+            grapple_range: 30.0,
+            reel_speed: 8.0,
+            min_rope_length: 2.0,
+            max_rope_length: 35.0,
+            stiffness: 60.0,
+            damping: 6.0,
+            line_of_sight_grace: 0.5,
+        }
+    }
+}
+
+// Present on the player while the grapple is attached
+#[derive(Component)]
+pub struct Grappling {
+    pub anchor_entity: Entity,
+    // Hit point in the anchor's local space, so moving platforms carry it along
+    pub local_anchor: Vec3,
+    pub rope_length: f32,
+    // Seconds the anchor has been out of sight
+    pub hidden_time: f32,
+}
+
+// The rope of the local player with this index
+#[derive(Component)]
+pub struct GrappleRope(usize);
+
+// Where the rope leaves the player, relative to the capsule center
+const HAND_OFFSET: Vec3 = Vec3::new(0.0, 0.5, 0.0);
+const ROPE_THICKNESS: f32 = 0.05;
+
+fn hand_position(transform: &Transform) -> Vec3 {
+    transform.translation + transform.rotation * HAND_OFFSET
+}
+
+fn spawn_grapple_rope(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    settings: Res<PlayerSettings>,
+) {
+    // A unit cube stretched between the hand and the anchor each frame
+    let mesh = meshes.add(Mesh::from(shape::Cube { size: 1.0 }));
+    let material = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.4, 0.3, 0.2),
+        ..default()
+    });
+    for index in 0..settings.local_players {
+        commands.spawn((
+            GrappleRope(index),
+            PbrBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+        ));
+    }
+}
+
+fn grapple_input(
+    mut commands: Commands,
+    input_map: Res<InputMap>,
+    settings: Res<GrappleSettings>,
+    rapier_context: Res<RapierContext>,
+    player_query: Query<(Entity, &PlayerIndex, &Transform, Has<Grappling>), (With<Player>, Without<Dead>, Without<Noclip>)>,
+    aim_target: Res<AimTarget>,
+    body_query: Query<&RigidBody>,
+    global_transform_query: Query<&GlobalTransform>,
+) {
+    for (player_entity, index, player_transform, grappling) in player_query.iter() {
+        let input = input_map.for_player(index.0);
+
+        // Letting go detaches and keeps whatever velocity the swing built up
+        if grappling {
+            if !input.pressed(Action::Grapple) {
+                commands.entity(player_entity).remove::<Grappling>();
+                println!("Grapple released");
+            }
+            continue;
+        }
+        if !input.just_pressed(Action::Grapple) {
+            continue;
+        }
+
+        let aim = aim_target
+            .for_player(index.0)
+            .map(|aim| aim.ray.direction)
+            .unwrap_or(player_transform.rotation * Vec3::Z);
+        let origin = hand_position(player_transform);
+        let filter = QueryFilter::default().exclude_rigid_body(player_entity).exclude_sensors();
+        let Some((hit_entity, toi)) = rapier_context.cast_ray(origin, aim, settings.grapple_range, true, filter) else {
+            println!("Grapple missed");
+            continue;
+        };
+
+        // Only fixed and kinematic bodies make anchors; colliders without a body are static too
+        let body = rapier_context
+            .collider_parent(hit_entity)
+            .unwrap_or(hit_entity);
+        if matches!(body_query.get(body), Ok(RigidBody::Dynamic)) {
+            println!("Grapple hit a dynamic body, ignoring");
+            continue;
+        }
+        let Ok(anchor_transform) = global_transform_query.get(body) else {
+            continue;
+        };
+
+        let hit_point = origin + aim * toi;
+        let local_anchor = anchor_transform.affine().inverse().transform_point3(hit_point);
+        commands.entity(player_entity).insert(Grappling {
+            anchor_entity: body,
+            local_anchor,
+            rope_length: toi,
+            hidden_time: 0.0,
+        });
+        println!("Grapple attached to {:?} at {:?}", body, hit_point);
+    }
+}
+
+// Reels the rope in and pulls the player back whenever it's stretched past its length. Only the
+// radial velocity is touched, so the player is free to swing around the anchor.
+fn update_grapple(
+    mut commands: Commands,
+    settings: Res<GrappleSettings>,
+    rapier_context: Res<RapierContext>,
+    mut player_query: Query<(Entity, &Transform, &mut Velocity, &mut Grappling, Has<Dead>, Has<Noclip>), With<Player>>,
+    anchor_query: Query<(&GlobalTransform, Option<&Velocity>), Without<Player>>,
+    time: Res<Time>,
+) {
+    for (player_entity, player_transform, mut velocity, mut grappling, dead, noclip) in player_query.iter_mut() {
+        let Ok((anchor_transform, anchor_velocity)) = anchor_query.get(grappling.anchor_entity) else {
+            commands.entity(player_entity).remove::<Grappling>();
+            println!("Grapple anchor disappeared, detaching");
+            continue;
+        };
+        if dead || noclip {
+            commands.entity(player_entity).remove::<Grappling>();
+            continue;
+        }
+
+        let anchor = anchor_transform.transform_point(grappling.local_anchor);
+        let hand = hand_position(player_transform);
+        let to_anchor = anchor - hand;
+        let distance = to_anchor.length();
+        if distance > settings.max_rope_length {
+            commands.entity(player_entity).remove::<Grappling>();
+            println!("Grapple rope stretched past {} m, detaching", settings.max_rope_length);
+            continue;
+        }
+
+        // Anything other than the anchor between the hand and the anchor point blocks the rope
+        let direction = to_anchor / distance.max(f32::EPSILON);
+        let filter = QueryFilter::default().exclude_rigid_body(player_entity).exclude_sensors();
+        let blocked = rapier_context
+            .cast_ray(hand, direction, distance - 0.1, true, filter)
+            .is_some_and(|(entity, _toi)| rapier_context.collider_parent(entity).unwrap_or(entity) != grappling.anchor_entity);
+        grappling.hidden_time = if blocked { grappling.hidden_time + time.delta_seconds() } else { 0.0 };
+        if grappling.hidden_time > settings.line_of_sight_grace {
+            commands.entity(player_entity).remove::<Grappling>();
+            println!("Lost sight of the grapple anchor, detaching");
+            continue;
+        }
+
+        grappling.rope_length = (grappling.rope_length - settings.reel_speed * time.delta_seconds())
+            .max(settings.min_rope_length);
+
+        let stretch = distance - grappling.rope_length;
+        if stretch > 0.0 {
+            // Relative to the anchor, so ropes on moving platforms get dragged along
+            let anchor_linvel = anchor_velocity.map_or(Vec3::ZERO, |velocity| velocity.linvel);
+            let radial_speed = (velocity.linvel - anchor_linvel).dot(direction);
+            let pull = settings.stiffness * stretch - settings.damping * radial_speed;
+            velocity.linvel += direction * pull.max(0.0) * time.delta_seconds();
+        }
+    }
+}
+
+fn update_grapple_rope(
+    player_query: Query<(&PlayerIndex, &Transform, Option<&Grappling>), With<Player>>,
+    anchor_query: Query<&GlobalTransform>,
+    mut rope_query: Query<(&GrappleRope, &mut Transform, &mut Visibility), Without<Player>>,
+) {
+    for (rope, mut rope_transform, mut visibility) in rope_query.iter_mut() {
+        let attached = player_query
+            .iter()
+            .find(|(index, _, _)| index.0 == rope.0)
+            .and_then(|(_, player_transform, grappling)| {
+                let grappling = grappling?;
+                let anchor_transform = anchor_query.get(grappling.anchor_entity).ok()?;
+                Some((hand_position(player_transform), anchor_transform.transform_point(grappling.local_anchor)))
+            });
+        let Some((hand, anchor)) = attached else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+
+        let to_anchor = anchor - hand;
+        *visibility = Visibility::Visible;
+        rope_transform.translation = hand + to_anchor * 0.5;
+        rope_transform.rotation = Quat::from_rotation_arc(Vec3::Y, to_anchor.normalize_or_zero());
+        rope_transform.scale = Vec3::new(ROPE_THICKNESS, to_anchor.length(), ROPE_THICKNESS);
+    }
+}
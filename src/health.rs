@@ -0,0 +1,215 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use crate::combat::{DamageEvent, Health, HealthChanged, Invulnerability};
+use crate::debug::{GodMode, Noclip};
+use crate::player::{local_player_offset, Player, PlayerIndex, PlayerLanded, PlayerSettings, DEFAULT_SPAWN_POSITION};
+use crate::terrain::SpawnPointRegistry;
+
+pub struct HealthPlugin;
+
+impl Plugin for HealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RespawnPoint>()
+            .add_event::<PlayerDied>()
+            .add_event::<PlayerRespawned>()
+            .add_systems(Update, (
+                fall_damage_system,
+                kill_plane_system,
+                player_death_system.after(kill_plane_system),
+                respawn_system.after(player_death_system),
+                log_health_changes,
+            ));
+    }
+}
+
+#[derive(Resource)]
+pub struct RespawnPoint(pub Vec3);
+
+impl Default for RespawnPoint {
+    fn default() -> Self {
+        Self(DEFAULT_SPAWN_POSITION)
+    }
+}
+
+impl RespawnPoint {
+    // Moves the respawn location to a registered spawn point; unknown ids leave it unchanged
+    pub fn respawn_at(&mut self, registry: &SpawnPointRegistry, id: &str) -> bool {
+        match registry.get(id) {
+            Some(position) => {
+                self.0 = position;
+                println!("Respawn point set to {:?} at {:?}", id, position);
+                true
+            }
+            None => {
+                println!("WARNING: Unknown spawn point {:?}", id);
+                false
+            }
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct PlayerDied {
+    pub player: Entity,
+    pub position: Vec3,
+}
+
+// Sent once a dead player is back at the respawn point
+#[derive(Event)]
+pub struct PlayerRespawned {
+    pub player: Entity,
+}
+
+// Present on the player between dying and respawning; input is frozen meanwhile
+#[derive(Component)]
+pub struct Dead {
+    pub respawn_timer: f32,
+}
+
+fn fall_damage_system(
+    mut landed_events: EventReader<PlayerLanded>,
+    player_query: Query<(), (With<Player>, Without<Dead>, Without<Noclip>, Without<GodMode>)>,
+    settings: Res<PlayerSettings>,
+    mut damage_events: EventWriter<DamageEvent>,
+) {
+    for event in landed_events.read() {
+        if !player_query.contains(event.player) {
+            continue;
+        }
+        let damage = settings.fall_damage(event.impact_speed);
+        if damage > 0.0 {
+            println!("Hard landing at {:.1} m/s, taking {:.1} fall damage", event.impact_speed, damage);
+            damage_events.send(DamageEvent {
+                entity: event.player,
+                amount: damage,
+            });
+        }
+    }
+}
+
+fn kill_plane_system(
+    mut player_query: Query<(Entity, &PlayerIndex, &Transform, &mut Health), (With<Player>, Without<Dead>, Without<Noclip>, Without<GodMode>)>,
+    settings: Res<PlayerSettings>,
+    mut health_changed_events: EventWriter<HealthChanged>,
+) {
+    for (entity, index, transform, mut health) in player_query.iter_mut() {
+        if transform.translation.y < settings.kill_height && health.current > 0.0 {
+            println!("Player {} fell below kill height {}", index.0 + 1, settings.kill_height);
+            health_changed_events.send(HealthChanged {
+                entity,
+                previous: health.current,
+                current: 0.0,
+                max: health.max,
+            });
+            health.current = 0.0;
+        }
+    }
+}
+
+fn player_death_system(
+    mut commands: Commands,
+    mut player_query: Query<(Entity, &PlayerIndex, &Transform, &Health, &mut Velocity), (With<Player>, Without<Dead>)>,
+    settings: Res<PlayerSettings>,
+    mut died_events: EventWriter<PlayerDied>,
+) {
+    for (entity, index, transform, health, mut velocity) in player_query.iter_mut() {
+        if health.current <= 0.0 {
+            println!("=== PLAYER {} DIED ===", index.0 + 1);
+            velocity.linvel.x = 0.0;
+            velocity.linvel.z = 0.0;
+            commands.entity(entity).insert(Dead {
+                respawn_timer: settings.respawn_delay,
+            });
+            died_events.send(PlayerDied {
+                player: entity,
+                position: transform.translation,
+            });
+        }
+    }
+}
+
+fn respawn_system(
+    mut commands: Commands,
+    mut player_query: Query<(Entity, &PlayerIndex, &mut Dead, &mut Transform, &mut Velocity, &mut Health, &mut Player, Option<&mut Invulnerability>)>,
+    respawn_point: Res<RespawnPoint>,
+    mut health_changed_events: EventWriter<HealthChanged>,
+    mut respawned_events: EventWriter<PlayerRespawned>,
+    time: Res<Time>,
+) {
+    for (entity, index, mut dead, mut transform, mut velocity, mut health, mut player, invulnerability) in player_query.iter_mut() {
+        dead.respawn_timer -= time.delta_seconds();
+        if dead.respawn_timer > 0.0 {
+            continue;
+        }
+
+        // Spaced out like at the start so players respawning together don't land inside each other
+        let position = respawn_point.0 + local_player_offset(index.0);
+        transform.translation = position;
+        *velocity = Velocity::zero();
+        player.fall_speed = 0.0;
+        health_changed_events.send(HealthChanged {
+            entity,
+            previous: health.current,
+            current: health.max,
+            max: health.max,
+        });
+        health.current = health.max;
+        // Brief grace period after respawning
+        if let Some(mut invulnerability) = invulnerability {
+            invulnerability.remaining = invulnerability.duration;
+        }
+        commands.entity(entity).remove::<Dead>();
+        respawned_events.send(PlayerRespawned { player: entity });
+        println!("Player {} respawned at {:?}", index.0 + 1, position);
+    }
+}
+
+fn log_health_changes(
+    mut health_changed_events: EventReader<HealthChanged>,
+    mut died_events: EventReader<PlayerDied>,
+    player_query: Query<(), With<Player>>,
+) {
+    for event in health_changed_events.read() {
+        if player_query.contains(event.entity) {
+            println!("Player health: {:.0} -> {:.0} / {:.0}", event.previous, event.current, event.max);
+        }
+    }
+    for event in died_events.read() {
+        println!("Player {:?} died at {:?}", event.player, event.position);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn landings_up_to_the_safe_speed_deal_no_damage() {
+        let settings = PlayerSettings::default();
+        assert_eq!(settings.fall_damage(0.0), 0.0);
+        assert_eq!(settings.fall_damage(settings.safe_landing_speed - 0.1), 0.0);
+        assert_eq!(settings.fall_damage(settings.safe_landing_speed), 0.0);
+        assert!(settings.fall_damage(settings.safe_landing_speed + 0.1) > 0.0);
+        assert!(settings.fall_damage(30.0) > settings.fall_damage(20.0));
+    }
+
+    fn landing_damage(impact_speed: f32) -> Vec<f32> {
+        let mut app = App::new();
+        app.init_resource::<PlayerSettings>()
+            .add_event::<PlayerLanded>()
+            .add_event::<DamageEvent>()
+            .add_systems(Update, fall_damage_system);
+        let player = app.world.spawn(Player::default()).id();
+        app.world.send_event(PlayerLanded { player, impact_speed });
+        app.update();
+        let events = app.world.resource::<Events<DamageEvent>>();
+        events.get_reader().read(events).map(|event| event.amount).collect()
+    }
+
+    #[test]
+    fn only_hard_landings_send_damage() {
+        let settings = PlayerSettings::default();
+        assert!(landing_damage(settings.safe_landing_speed).is_empty());
+        assert_eq!(landing_damage(20.0), vec![settings.fall_damage(20.0)]);
+    }
+}
@@ -0,0 +1,614 @@
+use bevy::prelude::*;
+use bevy::input::InputSystem;
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::window::{CursorGrabMode, PrimaryWindow};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+pub struct InputMapPlugin;
+
+impl Plugin for InputMapPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(InputMap::load(INPUT_SETTINGS_PATH))
+            .add_systems(PreUpdate, update_action_state.after(InputSystem));
+    }
+}
+
+const INPUT_SETTINGS_PATH: &str = "assets/settings/input.ron";
+// Scroll is read in wheel notches. Trackpads report pixels in many small events instead, so
+// they're divided down by InputMap::scroll_pixels_per_line, this by default.
+const SCROLL_LINE_SCALE: f32 = 1.0;
+const DEFAULT_SCROLL_PIXELS_PER_LINE: f32 = 20.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Action {
+    MoveForward,
+    MoveBack,
+    MoveLeft,
+    MoveRight,
+    Jump,
+    Sprint,
+    Crouch,
+    Dash,
+    Interact,
+    Fire,
+    ThrowGrenade,
+    Grapple,
+    RotateCamera,
+    Aim,
+    ToggleView,
+    ToggleNoclip,
+    ToggleGodMode,
+    ToggleRecording,
+    CycleWeather,
+    ToggleGhost,
+    LoadGame,
+    ExportLevel,
+    ZoomIn,
+    ZoomOut,
+    ResetZoom,
+    RecenterCamera,
+    SwapShoulder,
+    CenterCamera,
+    ToggleLockOn,
+    FreeLook,
+    ToggleObstacleCourse,
+    PlayCameraPath,
+    CycleCameraTarget,
+    ToggleBuildMode,
+    RotateBuilding,
+    TogglePhotoMode,
+    PhotoRollLeft,
+    PhotoRollRight,
+    PhotoFovIn,
+    PhotoFovOut,
+    Screenshot,
+    ToggleWaves,
+    RotateViewLeft,
+    RotateViewRight,
+    ToggleSpectator,
+    SpectatorForward,
+    SpectatorBack,
+    SpectatorLeft,
+    SpectatorRight,
+    SpectatorUp,
+    SpectatorDown,
+    ToggleNavmeshDebug,
+    ToggleSleepOverlay,
+    ToggleInsetCamera,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
+    MouseWheelUp,
+    MouseWheelDown,
+}
+
+// What each action is doing this frame, rebuilt in PreUpdate
+#[derive(Default)]
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    axis: HashMap<Action, f32>,
+    // Mouse movement in pixels since last frame
+    look_delta: Vec2,
+    // Raw right stick deflection, -1..1 on each axis with up positive
+    look_stick: Vec2,
+}
+
+// Everything gameplay reads from the InputMap in one frame, for recording and replaying runs.
+// Empty fields are left out so idle frames stay small on disk.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct InputSnapshot {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub pressed: Vec<Action>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub just_pressed: Vec<Action>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub axes: Vec<(Action, f32)>,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub look_delta: Vec2,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub look_stick: Vec2,
+}
+
+fn is_zero(value: &Vec2) -> bool {
+    *value == Vec2::ZERO
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    // Analog strength of an action: 0..1 for buttons and triggers, scroll lines for the wheel
+    pub fn axis(&self, action: Action) -> f32 {
+        self.axis.get(&action).copied().unwrap_or(0.0)
+    }
+
+    pub fn look_delta(&self) -> Vec2 {
+        self.look_delta
+    }
+
+    pub fn look_stick(&self) -> Vec2 {
+        self.look_stick
+    }
+}
+
+// Which devices feed an ActionState
+#[derive(Clone, Copy)]
+enum Devices {
+    All,
+    KeyboardMouse,
+    Gamepad(Option<Gamepad>),
+}
+
+#[derive(Resource, Serialize, Deserialize)]
+pub struct InputMap {
+    bindings: BTreeMap<Action, Vec<Binding>>,
+    // How many pixels of trackpad scrolling count as one notch of the wheel
+    #[serde(default = "default_scroll_pixels_per_line")]
+    scroll_pixels_per_line: f32,
+    #[serde(skip)]
+    state: ActionState,
+    // With split devices player 1 has the keyboard and mouse and player 2 the first gamepad.
+    // Otherwise every device drives the single player.
+    #[serde(skip)]
+    split_devices: bool,
+    #[serde(skip)]
+    player_states: [ActionState; 2],
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        // Matches the controls that were hardcoded before bindings became configurable
+        let bindings = BTreeMap::from([
+            (Action::MoveForward, vec![Binding::Key(KeyCode::W), Binding::Gamepad(GamepadButtonType::DPadUp)]),
+            (Action::MoveBack, vec![Binding::Key(KeyCode::S), Binding::Gamepad(GamepadButtonType::DPadDown)]),
+            (Action::MoveLeft, vec![Binding::Key(KeyCode::A), Binding::Gamepad(GamepadButtonType::DPadLeft)]),
+            (Action::MoveRight, vec![Binding::Key(KeyCode::D), Binding::Gamepad(GamepadButtonType::DPadRight)]),
+            (Action::Jump, vec![Binding::Key(KeyCode::Space), Binding::Gamepad(GamepadButtonType::South)]),
+            (Action::Sprint, vec![Binding::Key(KeyCode::ShiftLeft), Binding::Gamepad(GamepadButtonType::LeftThumb)]),
+            (Action::Crouch, vec![Binding::Key(KeyCode::ControlLeft), Binding::Gamepad(GamepadButtonType::East)]),
+            (Action::Dash, vec![Binding::Key(KeyCode::Q), Binding::Gamepad(GamepadButtonType::LeftTrigger)]),
+            (Action::Interact, vec![Binding::Key(KeyCode::E), Binding::Gamepad(GamepadButtonType::West)]),
+            (Action::Fire, vec![Binding::Mouse(MouseButton::Left), Binding::Gamepad(GamepadButtonType::RightTrigger2)]),
+            (Action::ThrowGrenade, vec![Binding::Key(KeyCode::G), Binding::Gamepad(GamepadButtonType::RightTrigger)]),
+            (Action::Grapple, vec![Binding::Key(KeyCode::F), Binding::Gamepad(GamepadButtonType::LeftTrigger2)]),
+            // Right click aims, which orbits the camera too; this is left free to bind
+            (Action::RotateCamera, vec![]),
+            (Action::Aim, vec![Binding::Mouse(MouseButton::Right)]),
+            (Action::ToggleView, vec![Binding::Key(KeyCode::F5)]),
+            (Action::ToggleNoclip, vec![Binding::Key(KeyCode::F3)]),
+            (Action::ToggleGodMode, vec![Binding::Key(KeyCode::F7)]),
+            (Action::ToggleRecording, vec![Binding::Key(KeyCode::F9)]),
+            (Action::CycleWeather, vec![Binding::Key(KeyCode::F10)]),
+            (Action::ToggleGhost, vec![Binding::Key(KeyCode::F6)]),
+            (Action::LoadGame, vec![Binding::Key(KeyCode::F4)]),
+            (Action::ExportLevel, vec![Binding::Key(KeyCode::F8)]),
+            (Action::ZoomIn, vec![Binding::MouseWheelUp]),
+            (Action::ZoomOut, vec![Binding::MouseWheelDown]),
+            // Recentering resets the zoom too; this is left free to bind to reset only the zoom
+            (Action::ResetZoom, vec![]),
+            (Action::RecenterCamera, vec![Binding::Mouse(MouseButton::Middle), Binding::Gamepad(GamepadButtonType::RightThumb)]),
+            (Action::SwapShoulder, vec![Binding::Key(KeyCode::Tab), Binding::Gamepad(GamepadButtonType::North)]),
+            (Action::CenterCamera, vec![Binding::Key(KeyCode::V)]),
+            (Action::ToggleLockOn, vec![Binding::Key(KeyCode::T), Binding::Gamepad(GamepadButtonType::Select)]),
+            (Action::FreeLook, vec![Binding::Key(KeyCode::AltLeft)]),
+            (Action::ToggleObstacleCourse, vec![Binding::Key(KeyCode::F2)]),
+            (Action::PlayCameraPath, vec![Binding::Key(KeyCode::F1)]),
+            (Action::CycleCameraTarget, vec![Binding::Key(KeyCode::F11)]),
+            (Action::ToggleBuildMode, vec![Binding::Key(KeyCode::B)]),
+            (Action::RotateBuilding, vec![Binding::Key(KeyCode::R)]),
+            (Action::TogglePhotoMode, vec![Binding::Key(KeyCode::P)]),
+            (Action::PhotoRollLeft, vec![Binding::Key(KeyCode::Z)]),
+            (Action::PhotoRollRight, vec![Binding::Key(KeyCode::C)]),
+            (Action::PhotoFovIn, vec![Binding::Key(KeyCode::BracketLeft)]),
+            (Action::PhotoFovOut, vec![Binding::Key(KeyCode::BracketRight)]),
+            (Action::Screenshot, vec![Binding::Key(KeyCode::F12)]),
+            (Action::ToggleWaves, vec![Binding::Key(KeyCode::H)]),
+            (Action::RotateViewLeft, vec![Binding::Key(KeyCode::Comma)]),
+            (Action::RotateViewRight, vec![Binding::Key(KeyCode::Period)]),
+            (Action::ToggleSpectator, vec![Binding::Key(KeyCode::O)]),
+            (Action::SpectatorForward, vec![Binding::Key(KeyCode::Up)]),
+            (Action::SpectatorBack, vec![Binding::Key(KeyCode::Down)]),
+            (Action::SpectatorLeft, vec![Binding::Key(KeyCode::Left)]),
+            (Action::SpectatorRight, vec![Binding::Key(KeyCode::Right)]),
+            (Action::SpectatorUp, vec![Binding::Key(KeyCode::PageUp)]),
+            (Action::SpectatorDown, vec![Binding::Key(KeyCode::PageDown)]),
+            (Action::ToggleNavmeshDebug, vec![Binding::Key(KeyCode::N)]),
+            (Action::ToggleSleepOverlay, vec![Binding::Key(KeyCode::K)]),
+            (Action::ToggleInsetCamera, vec![Binding::Key(KeyCode::M)]),
+        ]);
+        Self {
+            bindings,
+            scroll_pixels_per_line: DEFAULT_SCROLL_PIXELS_PER_LINE,
+            state: ActionState::default(),
+            split_devices: false,
+            player_states: default(),
+        }
+    }
+}
+
+fn default_scroll_pixels_per_line() -> f32 {
+    DEFAULT_SCROLL_PIXELS_PER_LINE
+}
+
+// Everything scrolled this frame in wheel notches, summed before anything reads it so a trackpad's
+// little back and forth pixel deltas cancel out rather than each being acted on
+fn scroll_lines(events: impl Iterator<Item = (MouseScrollUnit, f32)>, pixels_per_line: f32) -> f32 {
+    let pixels_per_line = pixels_per_line.max(1.0);
+    events
+        .map(|(unit, y)| match unit {
+            MouseScrollUnit::Line => y * SCROLL_LINE_SCALE,
+            MouseScrollUnit::Pixel => y / pixels_per_line,
+        })
+        .sum()
+}
+
+impl InputMap {
+    // Reads bindings from disk, falling back to the defaults if the file is missing or invalid
+    pub fn load(path: &str) -> Self {
+        let input_map = match fs::read_to_string(path) {
+            Ok(contents) => match ron::from_str::<InputMap>(&contents) {
+                Ok(input_map) => {
+                    println!("Loaded input bindings from {}", path);
+                    input_map
+                }
+                Err(err) => {
+                    println!("WARNING: Failed to parse {}: {}, using default bindings", path, err);
+                    InputMap::default()
+                }
+            },
+            Err(_) => {
+                println!("No input settings at {}, using default bindings", path);
+                InputMap::default()
+            }
+        };
+        input_map.warn_conflicts();
+        input_map
+    }
+
+    pub fn save(&self, path: &str) {
+        let contents = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => contents,
+            Err(err) => {
+                println!("WARNING: Failed to serialize input bindings: {}", err);
+                return;
+            }
+        };
+        if let Some(parent) = Path::new(path).parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        match fs::write(path, contents) {
+            Ok(()) => println!("Saved input bindings to {}", path),
+            Err(err) => println!("WARNING: Failed to write {}: {}", path, err),
+        }
+    }
+
+    // Replaces every binding of `action` with `binding` and writes the result back to disk
+    #[allow(dead_code)]
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        self.set_binding(action, binding);
+        self.save(INPUT_SETTINGS_PATH);
+    }
+
+    fn set_binding(&mut self, action: Action, binding: Binding) {
+        println!("Rebinding {:?} to {:?}", action, binding);
+        self.bindings.insert(action, vec![binding]);
+        self.warn_conflicts();
+    }
+
+    // Every pair of actions that share a binding
+    pub fn conflicts(&self) -> Vec<(Action, Action, Binding)> {
+        let mut owners: HashMap<Binding, Action> = HashMap::new();
+        let mut conflicts = Vec::new();
+        for (action, bindings) in &self.bindings {
+            for binding in bindings {
+                match owners.get(binding) {
+                    Some(owner) if owner != action => conflicts.push((*owner, *action, *binding)),
+                    _ => {
+                        owners.insert(*binding, *action);
+                    }
+                }
+            }
+        }
+        conflicts
+    }
+
+    fn warn_conflicts(&self) {
+        for (first, second, binding) in self.conflicts() {
+            println!("WARNING: {:?} is bound to both {:?} and {:?}", binding, first, second);
+        }
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.state.just_pressed(action)
+    }
+
+    pub fn set_split_devices(&mut self, split_devices: bool) {
+        self.split_devices = split_devices;
+    }
+
+    // Input of one local player; everything goes to the first player unless devices are split
+    pub fn for_player(&self, index: usize) -> &ActionState {
+        if self.split_devices {
+            &self.player_states[index.min(self.player_states.len() - 1)]
+        } else {
+            &self.state
+        }
+    }
+
+    // Sorted so the same input always serializes the same way
+    pub fn snapshot(&self) -> InputSnapshot {
+        let sorted = |actions: &HashSet<Action>| {
+            let mut actions: Vec<Action> = actions.iter().copied().collect();
+            actions.sort();
+            actions
+        };
+        let mut axes: Vec<(Action, f32)> = self.state.axis.iter().map(|(action, value)| (*action, *value)).collect();
+        axes.sort_by_key(|(action, _)| *action);
+        InputSnapshot {
+            pressed: sorted(&self.state.pressed),
+            just_pressed: sorted(&self.state.just_pressed),
+            axes,
+            look_delta: self.state.look_delta,
+            look_stick: self.state.look_stick,
+        }
+    }
+
+    // Replaces this frame's device input with a recorded one
+    pub fn apply_snapshot(&mut self, snapshot: &InputSnapshot) {
+        self.state = ActionState {
+            pressed: snapshot.pressed.iter().copied().collect(),
+            just_pressed: snapshot.just_pressed.iter().copied().collect(),
+            axis: snapshot.axes.iter().copied().collect(),
+            look_delta: snapshot.look_delta,
+            look_stick: snapshot.look_stick,
+        };
+    }
+}
+
+pub fn update_action_state(
+    mut input_map: ResMut<InputMap>,
+    keyboard_input: Res<Input<KeyCode>>,
+    mouse_input: Res<Input<MouseButton>>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    gamepad_buttons: Res<Axis<GamepadButton>>,
+    gamepad_sticks: Res<Axis<GamepadAxis>>,
+    gamepads: Res<Gamepads>,
+    mut scroll_events: EventReader<MouseWheel>,
+    mut mouse_motion: EventReader<MouseMotion>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut last_cursor: Local<Option<(bool, CursorGrabMode)>>,
+) {
+    // Motion is thrown away while the window is in the background, and for a frame after it
+    // comes back or the cursor is grabbed or let go: some platforms report the whole jump of the
+    // cursor as one huge delta then
+    let cursor = window_query.get_single().ok().map(|window| (window.focused, window.cursor.grab_mode));
+    let cursor_settled = cursor == *last_cursor;
+    *last_cursor = cursor;
+    let mouse_motion: Vec2 = mouse_motion.read().map(|event| event.delta).sum();
+    let focused = cursor.is_none_or(|(focused, _)| focused);
+
+    let devices = DeviceInput {
+        keyboard: &keyboard_input,
+        mouse: &mouse_input,
+        gamepad_buttons: &gamepad_input,
+        gamepad_button_axes: &gamepad_buttons,
+        gamepad_sticks: &gamepad_sticks,
+        gamepads: &gamepads,
+        scroll: scroll_lines(scroll_events.read().map(|event| (event.unit, event.y)), input_map.scroll_pixels_per_line),
+        mouse_motion: if focused && cursor_settled { mouse_motion } else { Vec2::ZERO },
+    };
+
+    input_map.state = devices.read(&input_map.bindings, Devices::All);
+    if input_map.split_devices {
+        let first_gamepad = gamepads.iter().next();
+        input_map.player_states = [
+            devices.read(&input_map.bindings, Devices::KeyboardMouse),
+            devices.read(&input_map.bindings, Devices::Gamepad(first_gamepad)),
+        ];
+    }
+}
+
+struct DeviceInput<'a> {
+    keyboard: &'a Input<KeyCode>,
+    mouse: &'a Input<MouseButton>,
+    gamepad_buttons: &'a Input<GamepadButton>,
+    gamepad_button_axes: &'a Axis<GamepadButton>,
+    gamepad_sticks: &'a Axis<GamepadAxis>,
+    gamepads: &'a Gamepads,
+    scroll: f32,
+    mouse_motion: Vec2,
+}
+
+impl DeviceInput<'_> {
+    fn read(&self, bindings: &BTreeMap<Action, Vec<Binding>>, devices: Devices) -> ActionState {
+        let scroll = self.scroll;
+        let keyboard_mouse = !matches!(devices, Devices::Gamepad(_));
+        let gamepads: Vec<Gamepad> = match devices {
+            Devices::All => self.gamepads.iter().collect(),
+            Devices::KeyboardMouse => Vec::new(),
+            Devices::Gamepad(gamepad) => gamepad.into_iter().collect(),
+        };
+
+        // With several gamepads feeding one state, the most deflected right stick wins
+        let look_stick = gamepads
+            .iter()
+            .map(|gamepad| {
+                Vec2::new(
+                    self.gamepad_sticks.get(GamepadAxis::new(*gamepad, GamepadAxisType::RightStickX)).unwrap_or(0.0),
+                    self.gamepad_sticks.get(GamepadAxis::new(*gamepad, GamepadAxisType::RightStickY)).unwrap_or(0.0),
+                )
+            })
+            .max_by(|a, b| a.length_squared().total_cmp(&b.length_squared()))
+            .unwrap_or(Vec2::ZERO);
+        let mut state = ActionState {
+            look_delta: if keyboard_mouse { self.mouse_motion } else { Vec2::ZERO },
+            look_stick,
+            ..default()
+        };
+
+        for (action, bindings) in bindings {
+            let mut pressed = false;
+            let mut just_pressed = false;
+            let mut axis: f32 = 0.0;
+
+            for binding in bindings {
+                let (binding_pressed, binding_just_pressed, binding_axis) = match *binding {
+                    Binding::Gamepad(button_type) => {
+                        let mut result = (false, false, 0.0_f32);
+                        for gamepad in &gamepads {
+                            let button = GamepadButton::new(*gamepad, button_type);
+                            let pressed = self.gamepad_buttons.pressed(button);
+                            let value = self.gamepad_button_axes.get(button).unwrap_or(if pressed { 1.0 } else { 0.0 });
+                            result.0 |= pressed;
+                            result.1 |= self.gamepad_buttons.just_pressed(button);
+                            result.2 = result.2.max(value);
+                        }
+                        result
+                    }
+                    _ if !keyboard_mouse => (false, false, 0.0),
+                    Binding::Key(key) => {
+                        let pressed = self.keyboard.pressed(key);
+                        (pressed, self.keyboard.just_pressed(key), if pressed { 1.0 } else { 0.0 })
+                    }
+                    Binding::Mouse(button) => {
+                        let pressed = self.mouse.pressed(button);
+                        (pressed, self.mouse.just_pressed(button), if pressed { 1.0 } else { 0.0 })
+                    }
+                    Binding::MouseWheelUp => (scroll > 0.0, scroll > 0.0, scroll.max(0.0)),
+                    Binding::MouseWheelDown => (scroll < 0.0, scroll < 0.0, (-scroll).max(0.0)),
+                };
+                pressed |= binding_pressed;
+                just_pressed |= binding_just_pressed;
+                axis = axis.max(binding_axis);
+            }
+
+            if pressed {
+                state.pressed.insert(*action);
+            }
+            if just_pressed {
+                state.just_pressed.insert(*action);
+            }
+            if axis > 0.0 {
+                state.axis.insert(*action, axis);
+            }
+        }
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_app::TempDir;
+
+    #[test]
+    fn default_bindings_have_no_conflicts() {
+        assert_eq!(InputMap::default().conflicts(), vec![]);
+    }
+
+    #[test]
+    fn a_binding_shared_by_two_actions_is_a_conflict() {
+        let mut input_map = InputMap::default();
+        input_map.bindings.get_mut(&Action::Jump).unwrap().push(Binding::Key(KeyCode::E));
+        // Listing a key twice for the same action isn't one
+        input_map.bindings.get_mut(&Action::Jump).unwrap().push(Binding::Key(KeyCode::Space));
+        let conflicts = input_map.conflicts();
+        assert_eq!(conflicts.len(), 1);
+        let (first, second, binding) = conflicts[0];
+        assert_eq!(binding, Binding::Key(KeyCode::E));
+        assert!(matches!((first, second), (Action::Jump, Action::Interact) | (Action::Interact, Action::Jump)));
+    }
+
+    #[test]
+    fn bindings_survive_a_round_trip_through_ron() {
+        let mut input_map = InputMap::default();
+        input_map.bindings.insert(Action::Dash, vec![Binding::Mouse(MouseButton::Other(4)), Binding::MouseWheelDown]);
+        input_map.scroll_pixels_per_line = 35.0;
+        let text = ron::to_string(&input_map).unwrap();
+        let loaded: InputMap = ron::from_str(&text).unwrap();
+        assert_eq!(loaded.bindings, input_map.bindings);
+        assert_eq!(loaded.scroll_pixels_per_line, 35.0);
+    }
+
+    #[test]
+    fn a_rebound_action_is_saved_and_loaded_back() {
+        let dir = TempDir::new("rebind");
+        let path = dir.path().join("input.ron");
+        let path = path.to_str().unwrap();
+        let mut input_map = InputMap::default();
+        input_map.set_binding(Action::Dash, Binding::Key(KeyCode::X));
+        input_map.save(path);
+
+        let loaded = InputMap::load(path);
+        assert_eq!(loaded.bindings[&Action::Dash], vec![Binding::Key(KeyCode::X)]);
+        assert_eq!(loaded.bindings, input_map.bindings);
+        assert_eq!(loaded.conflicts(), vec![]);
+
+        // Rebinding onto a key another action already has is kept, and reported
+        let mut input_map = loaded;
+        let jump = input_map.bindings[&Action::Jump][0];
+        input_map.set_binding(Action::Dash, jump);
+        input_map.save(path);
+        let conflicts = InputMap::load(path).conflicts();
+        assert_eq!(conflicts.len(), 1);
+        let (first, second, binding) = conflicts[0];
+        assert_eq!(binding, jump);
+        assert!([first, second].contains(&Action::Dash) && [first, second].contains(&Action::Jump));
+    }
+
+    #[test]
+    fn older_files_without_scroll_settings_get_the_default() {
+        let text = ron::to_string(&InputMap::default()).unwrap();
+        let without_scroll = text.replace(&format!(",scroll_pixels_per_line:{:?}", DEFAULT_SCROLL_PIXELS_PER_LINE), "");
+        assert_ne!(without_scroll, text);
+        let loaded: InputMap = ron::from_str(&without_scroll).unwrap();
+        assert_eq!(loaded.scroll_pixels_per_line, DEFAULT_SCROLL_PIXELS_PER_LINE);
+    }
+
+    #[test]
+    fn a_trackpad_swipe_scrolls_as_far_as_the_same_wheel_notches() {
+        // Three notches of the wheel, against a swipe of the same length reported in pixels as a
+        // burst of small, uneven events
+        let wheel = [(MouseScrollUnit::Line, 1.0); 3];
+        let swipe = [4.0, 9.0, 12.0, 14.0, 11.0, 6.0, 3.0, 1.0].map(|y| (MouseScrollUnit::Pixel, y));
+        let pixels: f32 = swipe.iter().map(|(_, y)| y).sum();
+        assert_eq!(pixels, 3.0 * DEFAULT_SCROLL_PIXELS_PER_LINE);
+
+        let wheel_lines = scroll_lines(wheel.into_iter(), DEFAULT_SCROLL_PIXELS_PER_LINE);
+        let swipe_lines = scroll_lines(swipe.into_iter(), DEFAULT_SCROLL_PIXELS_PER_LINE);
+        assert_eq!(wheel_lines, 3.0);
+        assert!((swipe_lines - wheel_lines).abs() < 1e-4, "{} vs {}", swipe_lines, wheel_lines);
+
+        // A single large pixel event is still only as many notches as its length
+        let flick = scroll_lines([(MouseScrollUnit::Pixel, -150.0)].into_iter(), DEFAULT_SCROLL_PIXELS_PER_LINE);
+        assert_eq!(flick, -7.5);
+    }
+
+    #[test]
+    fn trackpad_jitter_within_a_frame_cancels_out() {
+        let jitter = [2.0, -2.0, 1.5, -1.0, -0.5].map(|y| (MouseScrollUnit::Pixel, y));
+        assert!(scroll_lines(jitter.into_iter(), DEFAULT_SCROLL_PIXELS_PER_LINE).abs() < 1e-6);
+    }
+
+    #[test]
+    fn pixels_per_line_scales_pixel_scroll_only() {
+        let events = [(MouseScrollUnit::Line, 1.0), (MouseScrollUnit::Pixel, 40.0)];
+        assert_eq!(scroll_lines(events.into_iter(), 20.0), 3.0);
+        assert_eq!(scroll_lines(events.into_iter(), 40.0), 2.0);
+        // Below one pixel a line is treated as one, so a bad setting can't blow up the zoom
+        assert_eq!(scroll_lines(events.into_iter(), 0.0), 41.0);
+    }
+}
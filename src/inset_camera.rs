@@ -0,0 +1,117 @@
+use bevy::core_pipeline::clear_color::ClearColorConfig;
+use bevy::prelude::*;
+use bevy::render::camera::{ScalingMode, Viewport};
+use bevy::window::{PrimaryWindow, WindowResized};
+use std::f32::consts::FRAC_PI_2;
+use crate::input_map::{Action, InputMap};
+use crate::player::Players;
+
+pub struct InsetCameraPlugin;
+
+impl Plugin for InsetCameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_inset_camera)
+            .add_systems(Update, (toggle_inset_camera, update_inset_viewport, follow_inset_camera).chain());
+    }
+}
+
+// Draws after every player camera, so it lands on top of theirs
+const INSET_CAMERA_ORDER: isize = 10;
+// Fraction of the window's width and height the inset takes up, and its gap from the corner in
+// physical pixels
+const INSET_SCALE: f32 = 0.25;
+const INSET_MARGIN: u32 = 16;
+// Metres above the player, and of ground shown top to bottom
+const INSET_HEIGHT: f32 = 40.0;
+const INSET_VIEW_SIZE: f32 = 30.0;
+// Time constant of the tracking, so it glides rather than jitters with every step
+const INSET_FOLLOW_TIME: f32 = 0.1;
+
+// A picture-in-picture map in the top right corner: a second camera looking straight down on the
+// first player, with the top of the inset towards -Z. M shows or hides it.
+#[derive(Component)]
+pub struct InsetCamera;
+
+fn spawn_inset_camera(mut commands: Commands) {
+    commands.spawn((
+        InsetCamera,
+        Camera3dBundle {
+            camera: Camera {
+                order: INSET_CAMERA_ORDER,
+                is_active: false,
+                ..default()
+            },
+            // Clearing would wipe the whole window, not just the inset
+            camera_3d: Camera3d {
+                clear_color: ClearColorConfig::None,
+                ..default()
+            },
+            projection: Projection::Orthographic(OrthographicProjection {
+                scaling_mode: ScalingMode::FixedVertical(INSET_VIEW_SIZE),
+                far: INSET_HEIGHT * 2.0,
+                ..default()
+            }),
+            transform: Transform::from_xyz(0.0, INSET_HEIGHT, 0.0).with_rotation(Quat::from_rotation_x(-FRAC_PI_2)),
+            ..default()
+        },
+    ));
+}
+
+fn toggle_inset_camera(input_map: Res<InputMap>, mut camera_query: Query<&mut Camera, With<InsetCamera>>) {
+    if !input_map.just_pressed(Action::ToggleInsetCamera) {
+        return;
+    }
+    for mut camera in camera_query.iter_mut() {
+        camera.is_active = !camera.is_active;
+        println!("Inset map {}", if camera.is_active { "on" } else { "off" });
+    }
+}
+
+// The top right corner of the window, worked out again when the window is resized or the inset
+// is turned on
+fn update_inset_viewport(
+    mut resize_events: EventReader<WindowResized>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut camera_query: Query<&mut Camera, With<InsetCamera>>,
+) {
+    let resized = resize_events.read().count() > 0;
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    let window_size = UVec2::new(window.physical_width(), window.physical_height());
+    for mut camera in camera_query.iter_mut() {
+        if !camera.is_active || (!resized && !camera.is_changed()) {
+            continue;
+        }
+        let size = (window_size.as_vec2() * INSET_SCALE).as_uvec2().max(UVec2::ONE);
+        let position = UVec2::new(window_size.x.saturating_sub(size.x + INSET_MARGIN), INSET_MARGIN);
+        let unchanged = camera
+            .viewport
+            .as_ref()
+            .is_some_and(|viewport| viewport.physical_position == position && viewport.physical_size == size);
+        if !unchanged {
+            camera.viewport = Some(Viewport {
+                physical_position: position,
+                physical_size: size,
+                ..default()
+            });
+        }
+    }
+}
+
+fn follow_inset_camera(
+    players: Res<Players>,
+    player_query: Query<&GlobalTransform>,
+    mut camera_query: Query<(&Camera, &mut Transform), With<InsetCamera>>,
+    time: Res<Time>,
+) {
+    let Some(player_transform) = players.0.first().and_then(|entity| player_query.get(*entity).ok()) else {
+        return;
+    };
+    let target = player_transform.translation() + Vec3::Y * INSET_HEIGHT;
+    let blend = 1.0 - (-time.delta_seconds() / INSET_FOLLOW_TIME).exp();
+    for (camera, mut transform) in camera_query.iter_mut() {
+        // Snaps on being turned on rather than gliding in from wherever it was left
+        transform.translation = if camera.is_active { transform.translation.lerp(target, blend) } else { target };
+    }
+}
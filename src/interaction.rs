@@ -0,0 +1,498 @@
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use bevy_rapier3d::prelude::*;
+use crate::camera::AimTarget;
+use crate::carry::{update_carried_object, Carrying, ObjectDropped};
+use crate::dialogue::{villager_dialogue, DialogueNode};
+use crate::input_map::{Action, InputMap};
+use crate::player::{Player, PlayerIndex, PlayerSettings};
+use crate::terrain::{height_at, MovingPlatform, TerrainConfig};
+
+pub struct InteractionPlugin;
+
+impl Plugin for InteractionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Interacted>()
+            .add_event::<InteractTargetChanged>()
+            .add_systems(Startup, (setup_interaction_prompt, spawn_interactables))
+            .add_systems(Update, (
+                add_interact_target,
+                interaction_detection_system.after(add_interact_target),
+                // Interact also picks up and drops carried objects, so carrying goes first and
+                // interaction sits out whenever the press was used for that
+                interaction_input_system.after(interaction_detection_system).after(update_carried_object),
+                update_interaction_prompt.after(interaction_detection_system),
+            ))
+            .add_systems(Update, (
+                door_interaction_handler,
+                item_interaction_handler,
+                switch_interaction_handler,
+                lever_interaction_handler,
+                sign_interaction_handler,
+            ).after(interaction_input_system));
+    }
+}
+
+// Half-angle of the cone in front of the player in which targets can be picked
+const INTERACT_CONE_HALF_ANGLE: f32 = 70.0_f32.to_radians();
+const INTERACT_EYE_HEIGHT: f32 = 0.5;
+
+#[derive(Component)]
+pub struct Interactable {
+    pub prompt: String,
+    pub range: f32,
+}
+
+// On each player, what they'd interact with right now
+#[derive(Component, Default)]
+pub struct CurrentInteractTarget(pub Option<Entity>);
+
+#[derive(Event)]
+pub struct Interacted {
+    pub entity: Entity,
+}
+
+#[derive(Event)]
+pub struct InteractTargetChanged {
+    pub player: Entity,
+    pub previous: Option<Entity>,
+    pub current: Option<Entity>,
+}
+
+#[derive(Component)]
+pub struct Door {
+    pub open: bool,
+}
+
+#[derive(Component)]
+pub struct Npc {
+    pub name: String,
+    pub dialogue_tree: DialogueNode,
+}
+
+#[derive(Component)]
+pub struct Item {
+    pub name: String,
+}
+
+#[derive(Component)]
+pub struct Switch {
+    pub on: bool,
+}
+
+#[derive(Component)]
+pub struct Lever {
+    pub platform: Entity,
+    pub on: bool,
+}
+
+#[derive(Component)]
+pub struct Sign {
+    pub text: String,
+}
+
+// The prompt for one local player, shown at the bottom of their part of the screen
+#[derive(Component)]
+struct InteractionPromptText(usize);
+
+struct InteractCandidate {
+    entity: Entity,
+    position: Vec3,
+    range: f32,
+}
+
+// Picks the closest candidate that is in range and inside the facing cone.
+// `is_visible` is asked last so line-of-sight raycasts only run for plausible targets.
+fn select_interact_target(
+    origin: Vec3,
+    facing: Vec3,
+    candidates: &[InteractCandidate],
+    mut is_visible: impl FnMut(&InteractCandidate) -> bool,
+) -> Option<Entity> {
+    let facing = Vec3::new(facing.x, 0.0, facing.z).normalize_or_zero();
+    let min_cos = INTERACT_CONE_HALF_ANGLE.cos();
+
+    let mut in_cone: Vec<(&InteractCandidate, f32)> = candidates
+        .iter()
+        .filter_map(|candidate| {
+            let offset = candidate.position - origin;
+            let distance = offset.length();
+            if distance > candidate.range {
+                return None;
+            }
+            let flat = Vec3::new(offset.x, 0.0, offset.z).normalize_or_zero();
+            // Targets right on top of the player count as in front of it
+            if flat != Vec3::ZERO && flat.dot(facing) < min_cos {
+                return None;
+            }
+            Some((candidate, distance))
+        })
+        .collect();
+
+    in_cone.sort_by(|a, b| a.1.total_cmp(&b.1));
+    in_cone
+        .into_iter()
+        .find(|(candidate, _)| is_visible(candidate))
+        .map(|(candidate, _)| candidate.entity)
+}
+
+fn setup_interaction_prompt(mut commands: Commands, settings: Res<PlayerSettings>) {
+    // Split-screen views are side by side columns
+    let column_width = 100.0 / settings.local_players as f32;
+    for index in 0..settings.local_players {
+        commands.spawn((
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font_size: 24.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(40.0),
+                left: Val::Percent(column_width * (index as f32 + 0.45)),
+                ..default()
+            }),
+            InteractionPromptText(index),
+        ));
+    }
+}
+
+fn spawn_interactables(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    config: Res<TerrainConfig>,
+) {
+    // Each stands this high above the ground where it is
+    let on_ground = |x: f32, y: f32, z: f32| Transform::from_xyz(x, height_at(&config, x, z) + y, z);
+
+    // Door
+    commands.spawn((
+        Door { open: false },
+        Interactable {
+            prompt: "Open door".to_string(),
+            range: 2.5,
+        },
+        RigidBody::Fixed,
+        Collider::cuboid(1.0, 1.5, 0.1),
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Box::new(2.0, 3.0, 0.2))),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.5, 0.3, 0.1),
+                ..default()
+            }),
+            transform: on_ground(5.0, 1.5, -5.0),
+            ..default()
+        },
+    ));
+
+    // NPC
+    commands.spawn((
+        Npc {
+            name: "Villager".to_string(),
+            dialogue_tree: villager_dialogue(),
+        },
+        Interactable {
+            prompt: "Talk".to_string(),
+            range: 3.0,
+        },
+        RigidBody::Fixed,
+        Collider::capsule_y(0.5, 0.5),
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Capsule {
+                radius: 0.5,
+                depth: 1.0,
+                ..default()
+            })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.2, 0.3, 0.8),
+                ..default()
+            }),
+            transform: on_ground(-5.0, 1.0, -5.0),
+            ..default()
+        },
+    ));
+
+    // Item
+    commands.spawn((
+        Item {
+            name: "Gem".to_string(),
+        },
+        Interactable {
+            prompt: "Pick up gem".to_string(),
+            range: 2.0,
+        },
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Cube { size: 0.4 })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.9, 0.1, 0.9),
+                ..default()
+            }),
+            transform: on_ground(5.0, 0.5, 5.0),
+            ..default()
+        },
+    ));
+
+    // Switch
+    commands.spawn((
+        Switch { on: false },
+        Interactable {
+            prompt: "Flip switch".to_string(),
+            range: 2.0,
+        },
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Box::new(0.3, 0.6, 0.3))),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.8, 0.1, 0.1),
+                ..default()
+            }),
+            transform: on_ground(-5.0, 0.4, 5.0),
+            ..default()
+        },
+    ));
+}
+
+fn add_interact_target(mut commands: Commands, player_query: Query<Entity, Added<Player>>) {
+    for entity in player_query.iter() {
+        commands.entity(entity).insert(CurrentInteractTarget::default());
+    }
+}
+
+fn interaction_detection_system(
+    mut player_query: Query<(Entity, &PlayerIndex, &Transform, &mut CurrentInteractTarget, Has<Carrying>), With<Player>>,
+    interactable_query: Query<(Entity, &GlobalTransform, &Interactable)>,
+    rapier_context: Res<RapierContext>,
+    aim_target: Res<AimTarget>,
+    mut target_changed_events: EventWriter<InteractTargetChanged>,
+) {
+    let candidates: Vec<InteractCandidate> = interactable_query
+        .iter()
+        .map(|(entity, transform, interactable)| InteractCandidate {
+            entity,
+            position: transform.translation(),
+            range: interactable.range,
+        })
+        .collect();
+
+    for (player_entity, index, player_transform, mut current_target, carrying) in player_query.iter_mut() {
+        let origin = player_transform.translation + Vec3::Y * INTERACT_EYE_HEIGHT;
+        // While aiming, the cone follows the crosshair rather than where the body faces
+        let facing = match aim_target.for_player(index.0) {
+            Some(aim) if aim.aiming => aim.ray.direction,
+            _ => player_transform.rotation * Vec3::Z,
+        };
+        let filter = QueryFilter::default().exclude_collider(player_entity).exclude_sensors();
+        // Hands are full while carrying, and Interact drops the object instead
+        let new_target = if carrying {
+            None
+        } else {
+            select_interact_target(origin, facing, &candidates, |candidate| {
+                let offset = candidate.position - origin;
+                let distance = offset.length();
+                if distance < 0.01 {
+                    return true;
+                }
+                match rapier_context.cast_ray(origin, offset / distance, distance, true, filter) {
+                    Some((hit_entity, _)) => hit_entity == candidate.entity,
+                    None => true,
+                }
+            })
+        };
+
+        if current_target.0 != new_target {
+            target_changed_events.send(InteractTargetChanged {
+                player: player_entity,
+                previous: current_target.0,
+                current: new_target,
+            });
+            current_target.0 = new_target;
+        }
+    }
+}
+
+fn interaction_input_system(
+    input_map: Res<InputMap>,
+    player_query: Query<(Entity, &PlayerIndex, &CurrentInteractTarget, Has<Carrying>)>,
+    mut dropped_events: EventReader<ObjectDropped>,
+    mut interacted_events: EventWriter<Interacted>,
+) {
+    let dropped: Vec<Entity> = dropped_events.read().map(|event| event.player).collect();
+    for (player_entity, index, current_target, carrying) in player_query.iter() {
+        // The press already picked something up or put it down this frame
+        if carrying || dropped.contains(&player_entity) || !input_map.for_player(index.0).just_pressed(Action::Interact) {
+            continue;
+        }
+        if let Some(entity) = current_target.0 {
+            interacted_events.send(Interacted { entity });
+        }
+    }
+}
+
+fn update_interaction_prompt(
+    mut target_changed_events: EventReader<InteractTargetChanged>,
+    player_query: Query<(&PlayerIndex, Ref<CurrentInteractTarget>)>,
+    interactable_query: Query<&Interactable, Changed<Interactable>>,
+    all_interactables: Query<&Interactable>,
+    mut prompt_query: Query<(&InteractionPromptText, &mut Text)>,
+) {
+    for event in target_changed_events.read() {
+        println!("Player {:?} interact target changed: {:?} -> {:?}", event.player, event.previous, event.current);
+    }
+
+    for (index, current_target) in player_query.iter() {
+        let prompt_changed = current_target.0.is_some_and(|entity| interactable_query.contains(entity));
+        if !current_target.is_changed() && !prompt_changed {
+            continue;
+        }
+        let Some((_, mut text)) = prompt_query.iter_mut().find(|(prompt, _)| prompt.0 == index.0) else {
+            continue;
+        };
+        text.sections[0].value = match current_target.0.and_then(|entity| all_interactables.get(entity).ok()) {
+            Some(interactable) => format!("[E] {}", interactable.prompt),
+            None => String::new(),
+        };
+    }
+}
+
+fn door_interaction_handler(
+    mut interacted_events: EventReader<Interacted>,
+    mut door_query: Query<(&mut Door, &mut Transform, &mut Interactable)>,
+) {
+    for event in interacted_events.read() {
+        if let Ok((mut door, mut transform, mut interactable)) = door_query.get_mut(event.entity) {
+            door.open = !door.open;
+            let angle = if door.open { std::f32::consts::FRAC_PI_2 } else { -std::f32::consts::FRAC_PI_2 };
+            transform.rotate_y(angle);
+            interactable.prompt = if door.open { "Close door" } else { "Open door" }.to_string();
+            println!("Door {:?} is now {}", event.entity, if door.open { "open" } else { "closed" });
+        }
+    }
+}
+
+fn item_interaction_handler(
+    mut commands: Commands,
+    mut interacted_events: EventReader<Interacted>,
+    item_query: Query<&Item>,
+) {
+    for event in interacted_events.read() {
+        if let Ok(item) = item_query.get(event.entity) {
+            println!("Picked up item: {}", item.name);
+            commands.entity(event.entity).despawn_recursive();
+        }
+    }
+}
+
+fn switch_interaction_handler(
+    mut interacted_events: EventReader<Interacted>,
+    mut switch_query: Query<(&mut Switch, &Handle<StandardMaterial>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    for event in interacted_events.read() {
+        if let Ok((mut switch, material_handle)) = switch_query.get_mut(event.entity) {
+            switch.on = !switch.on;
+            if let Some(material) = materials.get_mut(material_handle) {
+                material.base_color = if switch.on {
+                    Color::rgb(0.1, 0.8, 0.1)
+                } else {
+                    Color::rgb(0.8, 0.1, 0.1)
+                };
+            }
+            println!("Switch {:?} turned {}", event.entity, if switch.on { "on" } else { "off" });
+        }
+    }
+}
+
+fn lever_interaction_handler(
+    mut interacted_events: EventReader<Interacted>,
+    mut lever_query: Query<(&mut Lever, &mut Transform, &mut Interactable)>,
+    mut platform_query: Query<&mut MovingPlatform>,
+) {
+    for event in interacted_events.read() {
+        if let Ok((mut lever, mut transform, mut interactable)) = lever_query.get_mut(event.entity) {
+            lever.on = !lever.on;
+            let tilt = if lever.on { 0.8 } else { -0.8 };
+            transform.rotate_local_x(tilt);
+            interactable.prompt = if lever.on { "Stop platform" } else { "Start platform" }.to_string();
+            if let Ok(mut platform) = platform_query.get_mut(lever.platform) {
+                platform.active = lever.on;
+            }
+            println!("Lever {:?} turned {}", event.entity, if lever.on { "on" } else { "off" });
+        }
+    }
+}
+
+fn sign_interaction_handler(
+    mut interacted_events: EventReader<Interacted>,
+    sign_query: Query<&Sign>,
+) {
+    for event in interacted_events.read() {
+        if let Ok(sign) = sign_query.get(event.entity) {
+            println!("The sign reads: \"{}\"", sign.text);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(index: u32, position: Vec3) -> InteractCandidate {
+        InteractCandidate {
+            entity: Entity::from_raw(index),
+            position,
+            range: 3.0,
+        }
+    }
+
+    fn select(candidates: &[InteractCandidate]) -> Option<Entity> {
+        select_interact_target(Vec3::ZERO, Vec3::Z, candidates, |_| true)
+    }
+
+    #[test]
+    fn only_targets_inside_the_cone_and_range_are_picked() {
+        let edge = INTERACT_CONE_HALF_ANGLE - 0.05;
+        let inside = Vec3::new(edge.sin(), 0.0, edge.cos()) * 2.0;
+        assert_eq!(select(&[candidate(1, inside)]), Some(Entity::from_raw(1)));
+        let outside = Quat::from_rotation_y(0.1).mul_vec3(inside);
+        assert_eq!(select(&[candidate(1, outside)]), None);
+        assert_eq!(select(&[candidate(1, Vec3::new(0.0, 0.0, -1.0))]), None);
+        assert_eq!(select(&[candidate(1, Vec3::new(0.0, 0.0, 3.5))]), None);
+    }
+
+    #[test]
+    fn height_doesnt_affect_the_cone() {
+        // Straight above the player, and ahead but well below eye level
+        assert_eq!(select(&[candidate(1, Vec3::new(0.0, 2.0, 0.0))]), Some(Entity::from_raw(1)));
+        assert_eq!(select(&[candidate(1, Vec3::new(0.0, -2.0, 1.0))]), Some(Entity::from_raw(1)));
+    }
+
+    #[test]
+    fn the_nearest_target_wins() {
+        let candidates = [
+            candidate(1, Vec3::new(0.0, 0.0, 2.5)),
+            candidate(2, Vec3::new(0.3, 0.0, 1.0)),
+            candidate(3, Vec3::new(-0.2, 0.0, 1.8)),
+        ];
+        assert_eq!(select(&candidates), Some(Entity::from_raw(2)));
+    }
+
+    #[test]
+    fn hidden_targets_fall_through_to_the_next_nearest() {
+        let candidates = [
+            candidate(1, Vec3::new(0.0, 0.0, 2.5)),
+            candidate(2, Vec3::new(0.0, 0.0, 1.0)),
+            candidate(3, Vec3::new(-5.0, 0.0, 0.0)),
+        ];
+        let mut checked = Vec::new();
+        let target = select_interact_target(Vec3::ZERO, Vec3::Z, &candidates, |candidate| {
+            checked.push(candidate.entity);
+            candidate.entity != Entity::from_raw(2)
+        });
+        assert_eq!(target, Some(Entity::from_raw(1)));
+        // Nothing outside the cone costs a raycast
+        assert_eq!(checked, vec![Entity::from_raw(2), Entity::from_raw(1)]);
+    }
+}
@@ -0,0 +1,222 @@
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use crate::input_map::{Action, InputMap};
+use crate::occlusion::OcclusionFadeable;
+use crate::terrain::{generate_island_mesh, island_collider, SurfaceType, TerrainConfig};
+
+// Exports the static level geometry to a RON file that spawn_terrain loads in place of the
+// procedural layout, so levels can be edited and shared
+pub struct LevelSerializerPlugin;
+
+impl Plugin for LevelSerializerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ExportLevel>()
+            .add_systems(Update, (export_level_shortcut, export_level_system).chain());
+    }
+}
+
+pub const LEVEL_FILE: &str = "levels/level.ron";
+const LEVEL_DIR: &str = "levels";
+
+#[derive(Event)]
+pub struct ExportLevel;
+
+// Marks an entity as part of the saved level. The shape is both its mesh and its collider, which is
+// a sensor for entities without a rigid body.
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum LevelShape {
+    Box { half_extents: Vec3 },
+    Ball { radius: f32 },
+    Cylinder { half_height: f32, radius: f32 },
+    // The generated island. Only its seed is saved, so the rest of TerrainConfig is taken to be
+    // at its defaults when the level is loaded.
+    Island { seed: u32 },
+}
+
+impl LevelShape {
+    fn mesh(self) -> Mesh {
+        match self {
+            LevelShape::Box { half_extents } => {
+                Mesh::from(shape::Box::new(half_extents.x * 2.0, half_extents.y * 2.0, half_extents.z * 2.0))
+            }
+            LevelShape::Ball { radius } => Mesh::from(shape::UVSphere { radius, ..default() }),
+            LevelShape::Cylinder { half_height, radius } => Mesh::from(shape::Cylinder {
+                radius,
+                height: half_height * 2.0,
+                ..default()
+            }),
+            LevelShape::Island { seed } => generate_island_mesh(&island_config(seed)),
+        }
+    }
+
+    fn collider(self) -> Collider {
+        match self {
+            LevelShape::Box { half_extents } => Collider::cuboid(half_extents.x, half_extents.y, half_extents.z),
+            LevelShape::Ball { radius } => Collider::ball(radius),
+            LevelShape::Cylinder { half_height, radius } => Collider::cylinder(half_height, radius),
+            LevelShape::Island { seed } => island_collider(&island_config(seed)),
+        }
+    }
+}
+
+fn island_config(seed: u32) -> TerrainConfig {
+    TerrainConfig {
+        island_seed: seed,
+        ..default()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub enum LevelBody {
+    Fixed,
+    Dynamic,
+    KinematicPositionBased,
+    KinematicVelocityBased,
+}
+
+impl From<RigidBody> for LevelBody {
+    fn from(body: RigidBody) -> Self {
+        match body {
+            RigidBody::Fixed => LevelBody::Fixed,
+            RigidBody::Dynamic => LevelBody::Dynamic,
+            RigidBody::KinematicPositionBased => LevelBody::KinematicPositionBased,
+            RigidBody::KinematicVelocityBased => LevelBody::KinematicVelocityBased,
+        }
+    }
+}
+
+impl From<LevelBody> for RigidBody {
+    fn from(body: LevelBody) -> Self {
+        match body {
+            LevelBody::Fixed => RigidBody::Fixed,
+            LevelBody::Dynamic => RigidBody::Dynamic,
+            LevelBody::KinematicPositionBased => RigidBody::KinematicPositionBased,
+            LevelBody::KinematicVelocityBased => RigidBody::KinematicVelocityBased,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct LevelObject {
+    pub shape: LevelShape,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+    // Objects without a body are decoration; they only get a sensor, for camera occlusion
+    pub body: Option<LevelBody>,
+    pub color: [f32; 4],
+    pub surface: Option<SurfaceType>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct LevelFile {
+    pub objects: Vec<LevelObject>,
+}
+
+// The level to use instead of the procedural one, if there is a readable one on disk
+pub fn load_level_file() -> Option<LevelFile> {
+    if !Path::new(LEVEL_FILE).exists() {
+        return None;
+    }
+    let level = fs::read_to_string(LEVEL_FILE)
+        .map_err(|err| err.to_string())
+        .and_then(|contents| ron::from_str::<LevelFile>(&contents).map_err(|err| err.to_string()));
+    match level {
+        Ok(level) => {
+            println!("=== LEVEL LOADED === {} objects from {}", level.objects.len(), LEVEL_FILE);
+            Some(level)
+        }
+        Err(err) => {
+            println!("WARNING: Failed to load {}, using the generated level: {}", LEVEL_FILE, err);
+            None
+        }
+    }
+}
+
+pub fn spawn_level(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    level: &LevelFile,
+) {
+    for object in &level.objects {
+        let [r, g, b, a] = object.color;
+        let mut entity = commands.spawn((
+            object.shape,
+            PbrBundle {
+                mesh: meshes.add(object.shape.mesh()),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgba(r, g, b, a),
+                    alpha_mode: if a < 1.0 { AlphaMode::Blend } else { AlphaMode::Opaque },
+                    ..default()
+                }),
+                transform: Transform {
+                    translation: object.translation,
+                    rotation: object.rotation,
+                    scale: object.scale,
+                },
+                ..default()
+            },
+        ));
+        match object.body {
+            Some(body) => entity.insert((RigidBody::from(body), object.shape.collider())),
+            None => entity.insert((object.shape.collider(), Sensor)),
+        };
+        // The ground the camera looks down on never needs to fade
+        if object.surface != Some(SurfaceType::Grass) {
+            entity.insert(OcclusionFadeable::default());
+        }
+        if let Some(surface) = object.surface {
+            entity.insert(surface);
+        }
+    }
+}
+
+fn export_level_shortcut(input_map: Res<InputMap>, mut export_events: EventWriter<ExportLevel>) {
+    if input_map.just_pressed(Action::ExportLevel) {
+        export_events.send(ExportLevel);
+    }
+}
+
+fn export_level_system(
+    mut export_events: EventReader<ExportLevel>,
+    object_query: Query<(&LevelShape, &Transform, Option<&RigidBody>, Option<&Handle<StandardMaterial>>, Option<&SurfaceType>)>,
+    materials: Res<Assets<StandardMaterial>>,
+) {
+    if export_events.read().count() == 0 {
+        return;
+    }
+    let objects = object_query
+        .iter()
+        .map(|(shape, transform, body, material, surface)| LevelObject {
+            shape: *shape,
+            translation: transform.translation,
+            rotation: transform.rotation,
+            scale: transform.scale,
+            body: body.map(|body| LevelBody::from(*body)),
+            color: material
+                .and_then(|material| materials.get(material))
+                .map_or(Color::WHITE, |material| material.base_color)
+                .as_rgba_f32(),
+            surface: surface.copied(),
+        })
+        .collect();
+    let level = LevelFile { objects };
+
+    let contents = match ron::ser::to_string_pretty(&level, ron::ser::PrettyConfig::default()) {
+        Ok(contents) => contents,
+        Err(err) => {
+            println!("WARNING: Failed to serialize level: {}", err);
+            return;
+        }
+    };
+    let _ = fs::create_dir_all(LEVEL_DIR);
+    match fs::write(LEVEL_FILE, contents) {
+        Ok(()) => println!("=== LEVEL EXPORTED === {} objects to {}", level.objects.len(), LEVEL_FILE),
+        Err(err) => println!("WARNING: Failed to write {}: {}", LEVEL_FILE, err),
+    }
+}
@@ -0,0 +1,272 @@
+use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+use bevy_rapier3d::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::path::Path;
+use crate::camera::CameraShakeEvent;
+use crate::recording::GameRng;
+use crate::weather::WeatherState;
+
+pub struct LightningPlugin;
+
+impl Plugin for LightningPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_lightning)
+            .add_systems(Update, (
+                lightning_system,
+                fade_lightning_bolts,
+                end_lightning_flashes,
+                play_delayed_thunder,
+            ));
+    }
+}
+
+// Rain heavier than this brings lightning
+const STORM_RAIN_INTENSITY: f32 = 0.7;
+const MIN_STRIKE_INTERVAL: f32 = 5.0;
+const MAX_STRIKE_INTERVAL: f32 = 30.0;
+// Strikes land within this distance of the camera
+const STRIKE_RANGE: f32 = 40.0;
+// Where the bolt starts above the strike point
+const BOLT_HEIGHT: f32 = 60.0;
+// Midpoint displacement passes; each doubles the number of segments
+const BOLT_SUBDIVISIONS: u32 = 6;
+// Sideways jitter of the first pass, halved every pass after
+const BOLT_JAGGEDNESS: f32 = 8.0;
+const BOLT_LIFETIME: f32 = 0.2;
+const FLASH_INTENSITY: f32 = 50000.0;
+const FLASH_FRAMES: u32 = 2;
+const FLASH_AMBIENT_BOOST: f32 = 1.5;
+const IMPULSE_RADIUS: f32 = 5.0;
+const IMPULSE_STRENGTH: f32 = 20.0;
+const STRIKE_TRAUMA: f32 = 0.9;
+// Thunder arrives after this long for strikes right next to the camera up to the edge of the range
+const MIN_THUNDER_DELAY: f32 = 0.5;
+const MAX_THUNDER_DELAY: f32 = 2.0;
+const THUNDER_SOUND: &str = "audio/thunder.ogg";
+
+// Drives strike timing and placement; seeded from GameRng so a run's storms repeat with it
+#[derive(Resource)]
+pub struct LightningRng(pub StdRng);
+
+#[derive(Resource)]
+struct LightningState {
+    time_to_strike: f32,
+    bolt_material: Handle<StandardMaterial>,
+    thunder: Option<Handle<AudioSource>>,
+}
+
+#[derive(Component)]
+struct LightningBolt {
+    age: f32,
+}
+
+// The strike's light; also brightens the ambient light so the whole screen flashes
+#[derive(Component)]
+struct LightningFlash {
+    frames_left: u32,
+    ambient_brightness: f32,
+}
+
+#[derive(Component)]
+struct PendingThunder {
+    delay: f32,
+}
+
+fn setup_lightning(
+    mut commands: Commands,
+    mut game_rng: ResMut<GameRng>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+) {
+    let mut rng = StdRng::seed_from_u64(game_rng.0.gen());
+    let time_to_strike = rng.gen_range(MIN_STRIKE_INTERVAL..MAX_STRIKE_INTERVAL);
+    commands.insert_resource(LightningRng(rng));
+
+    // Missing sounds would only log load errors every run
+    let thunder = if Path::new("assets").join(THUNDER_SOUND).exists() {
+        Some(asset_server.load(THUNDER_SOUND))
+    } else {
+        println!("No thunder sound at {}, lightning will be silent", THUNDER_SOUND);
+        None
+    };
+    commands.insert_resource(LightningState {
+        time_to_strike,
+        bolt_material: materials.add(StandardMaterial {
+            base_color: Color::rgb(0.85, 0.9, 1.0),
+            emissive: Color::rgb(4.0, 4.5, 5.0),
+            unlit: true,
+            ..default()
+        }),
+        thunder,
+    });
+}
+
+// Jagged path from top to bottom: each pass pushes every segment's midpoint sideways at random
+fn bolt_points(rng: &mut impl Rng, top: Vec3, bottom: Vec3) -> Vec<Vec3> {
+    let mut points = vec![top, bottom];
+    let mut jaggedness = BOLT_JAGGEDNESS;
+    for _ in 0..BOLT_SUBDIVISIONS {
+        let mut subdivided = Vec::with_capacity(points.len() * 2 - 1);
+        for pair in points.windows(2) {
+            let offset = Vec3::new(rng.gen_range(-1.0..1.0), 0.0, rng.gen_range(-1.0..1.0)) * jaggedness;
+            subdivided.push(pair[0]);
+            subdivided.push((pair[0] + pair[1]) * 0.5 + offset);
+        }
+        subdivided.push(bottom);
+        points = subdivided;
+        jaggedness *= 0.5;
+    }
+    points
+}
+
+fn bolt_mesh(points: Vec<Vec3>) -> Mesh {
+    let normals = vec![[0.0, 1.0, 0.0]; points.len()];
+    let positions: Vec<[f32; 3]> = points.into_iter().map(|point| point.to_array()).collect();
+    let mut mesh = Mesh::new(PrimitiveTopology::LineStrip);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh
+}
+
+fn lightning_system(
+    mut commands: Commands,
+    weather: Res<WeatherState>,
+    mut state: ResMut<LightningState>,
+    mut rng: ResMut<LightningRng>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut ambient_light: ResMut<AmbientLight>,
+    rapier_context: Res<RapierContext>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    mut impulse_query: Query<(&GlobalTransform, &mut ExternalImpulse)>,
+    mut shake_events: EventWriter<CameraShakeEvent>,
+    time: Res<Time>,
+) {
+    if !matches!(*weather, WeatherState::Rain(intensity) if intensity > STORM_RAIN_INTENSITY) {
+        return;
+    }
+    state.time_to_strike -= time.delta_seconds();
+    if state.time_to_strike > 0.0 {
+        return;
+    }
+    state.time_to_strike = rng.0.gen_range(MIN_STRIKE_INTERVAL..MAX_STRIKE_INTERVAL);
+    // The main camera that's drawing, as the spectator camera takes over from the player's and the
+    // inset map draws over it
+    let Some((_, camera_transform)) = camera_query.iter().filter(|(camera, _)| camera.is_active).min_by_key(|(camera, _)| camera.order) else {
+        return;
+    };
+
+    let camera_position = camera_transform.translation();
+    let angle = rng.0.gen_range(0.0..std::f32::consts::TAU);
+    let distance = rng.0.gen_range(0.0..STRIKE_RANGE);
+    let sky = Vec3::new(
+        camera_position.x + angle.cos() * distance,
+        camera_position.y + BOLT_HEIGHT,
+        camera_position.z + angle.sin() * distance,
+    );
+    // Strike whatever is below, or end at the camera's height over the void
+    let strike = rapier_context
+        .cast_ray(sky, Vec3::NEG_Y, f32::MAX, true, QueryFilter::default().exclude_sensors())
+        .map_or(Vec3::new(sky.x, camera_position.y, sky.z), |(_entity, toi)| sky + Vec3::NEG_Y * toi);
+
+    commands.spawn((
+        LightningBolt { age: 0.0 },
+        PbrBundle {
+            mesh: meshes.add(bolt_mesh(bolt_points(&mut rng.0, sky, strike))),
+            material: state.bolt_material.clone(),
+            ..default()
+        },
+    ));
+    commands.spawn((
+        LightningFlash {
+            frames_left: FLASH_FRAMES,
+            ambient_brightness: ambient_light.brightness,
+        },
+        PointLightBundle {
+            point_light: PointLight {
+                intensity: FLASH_INTENSITY,
+                range: STRIKE_RANGE * 2.0,
+                color: Color::rgb(0.85, 0.9, 1.0),
+                ..default()
+            },
+            transform: Transform::from_translation(strike + Vec3::Y * 2.0),
+            ..default()
+        },
+    ));
+    ambient_light.brightness += FLASH_AMBIENT_BOOST;
+
+    // Blast nearby loose objects away from the strike
+    let blast = Collider::ball(IMPULSE_RADIUS);
+    rapier_context.intersections_with_shape(strike, Quat::IDENTITY, &blast, QueryFilter::only_dynamic(), |hit| {
+        let body = rapier_context.collider_parent(hit).unwrap_or(hit);
+        if let Ok((transform, mut impulse)) = impulse_query.get_mut(body) {
+            let offset = transform.translation() - strike;
+            let falloff = 1.0 - (offset.length() / IMPULSE_RADIUS).min(1.0);
+            let direction = (offset.normalize_or_zero() + Vec3::Y).normalize();
+            impulse.impulse += direction * IMPULSE_STRENGTH * falloff;
+        }
+        true
+    });
+
+    shake_events.send(CameraShakeEvent { trauma: STRIKE_TRAUMA });
+
+    let strike_distance = strike.distance(camera_position);
+    if state.thunder.is_some() {
+        let t = (strike_distance / STRIKE_RANGE).min(1.0);
+        commands.spawn(PendingThunder {
+            delay: MIN_THUNDER_DELAY + (MAX_THUNDER_DELAY - MIN_THUNDER_DELAY) * t,
+        });
+    }
+    println!("Lightning struck {:?}, {:.1} m away", strike, strike_distance);
+}
+
+fn fade_lightning_bolts(
+    mut commands: Commands,
+    mut bolt_query: Query<(Entity, &mut LightningBolt)>,
+    time: Res<Time>,
+) {
+    for (entity, mut bolt) in bolt_query.iter_mut() {
+        bolt.age += time.delta_seconds();
+        if bolt.age >= BOLT_LIFETIME {
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn end_lightning_flashes(
+    mut commands: Commands,
+    mut flash_query: Query<(Entity, &mut LightningFlash)>,
+    mut ambient_light: ResMut<AmbientLight>,
+) {
+    for (entity, mut flash) in flash_query.iter_mut() {
+        flash.frames_left = flash.frames_left.saturating_sub(1);
+        if flash.frames_left == 0 {
+            ambient_light.brightness = flash.ambient_brightness;
+            commands.entity(entity).despawn();
+        }
+    }
+}
+
+fn play_delayed_thunder(
+    mut commands: Commands,
+    state: Res<LightningState>,
+    mut thunder_query: Query<(Entity, &mut PendingThunder)>,
+    time: Res<Time>,
+) {
+    for (entity, mut thunder) in thunder_query.iter_mut() {
+        thunder.delay -= time.delta_seconds();
+        if thunder.delay > 0.0 {
+            continue;
+        }
+        match &state.thunder {
+            Some(sound) => {
+                commands.entity(entity).remove::<PendingThunder>().insert(AudioBundle {
+                    source: sound.clone(),
+                    settings: PlaybackSettings::DESPAWN,
+                });
+            }
+            None => commands.entity(entity).despawn(),
+        }
+    }
+}
@@ -0,0 +1,182 @@
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use bevy_rapier3d::prelude::*;
+use crate::camera::{CameraMode, ThirdPersonCamera};
+use crate::input_map::{Action, InputMap};
+use crate::occlusion::OcclusionFadeable;
+use crate::player::{Player, PlayerIndex};
+use crate::terrain::{height_at, TerrainConfig};
+
+// Third person lock-on: the camera frames the player and a target, and the player strafes
+// around it. camera_follow and player_movement do the framing and facing.
+pub struct LockOnPlugin;
+
+impl Plugin for LockOnPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_training_dummies)
+            .add_systems(Update, (toggle_lock_on, break_lock_on).chain());
+    }
+}
+
+// Targets further than this, or outside the cone in front of the camera, can't be picked
+const LOCK_ON_RANGE: f32 = 20.0;
+const LOCK_ON_CONE_DEGREES: f32 = 60.0;
+// Lock is lost once the target gets this far away
+const LOCK_ON_BREAK_DISTANCE: f32 = 25.0;
+
+const DUMMY_RADIUS: f32 = 0.4;
+const DUMMY_HALF_HEIGHT: f32 = 0.6;
+
+// Something the camera can lock on to
+#[derive(Component)]
+pub struct LockOnTarget;
+
+// A few fixed targets on the island to lock on to
+fn spawn_training_dummies(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    config: Res<TerrainConfig>,
+) {
+    let mesh = meshes.add(Mesh::from(shape::Capsule {
+        radius: DUMMY_RADIUS,
+        depth: DUMMY_HALF_HEIGHT * 2.0,
+        ..default()
+    }));
+    for position in [Vec3::new(8.0, 1.0, -8.0), Vec3::new(-9.0, 1.0, -6.0), Vec3::new(10.0, 1.0, 7.0)] {
+        let position = position + Vec3::Y * height_at(&config, position.x, position.z);
+        commands.spawn((
+            LockOnTarget,
+            RigidBody::Fixed,
+            Collider::capsule_y(DUMMY_HALF_HEIGHT, DUMMY_RADIUS),
+            OcclusionFadeable::default(),
+            PbrBundle {
+                mesh: mesh.clone(),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgb(0.8, 0.25, 0.2),
+                    ..default()
+                }),
+                transform: Transform::from_translation(position),
+                ..default()
+            },
+        ));
+    }
+}
+
+// The nearest target in range whose horizontal direction from `origin` is within the cone
+// around `forward`
+fn pick_lock_on_target(origin: Vec3, forward: Vec3, targets: impl IntoIterator<Item = (Entity, Vec3)>) -> Option<Entity> {
+    let flatten = Vec3::new(1.0, 0.0, 1.0);
+    let forward = (forward * flatten).normalize_or_zero();
+    let min_cos = LOCK_ON_CONE_DEGREES.to_radians().cos();
+    targets
+        .into_iter()
+        .filter_map(|(entity, position)| {
+            let offset = position - origin;
+            let distance = offset.length();
+            let in_cone = (offset * flatten).normalize_or_zero().dot(forward) >= min_cos;
+            (distance <= LOCK_ON_RANGE && in_cone).then_some((entity, distance))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(entity, _)| entity)
+}
+
+fn toggle_lock_on(
+    input_map: Res<InputMap>,
+    mut camera_query: Query<(&PlayerIndex, &mut ThirdPersonCamera, &GlobalTransform)>,
+    player_query: Query<&GlobalTransform, With<Player>>,
+    target_query: Query<(Entity, &GlobalTransform), With<LockOnTarget>>,
+) {
+    for (index, mut camera, camera_transform) in camera_query.iter_mut() {
+        if !input_map.for_player(index.0).just_pressed(Action::ToggleLockOn) {
+            continue;
+        }
+        if camera.lock_on.take().is_some() {
+            println!("Lock-on released");
+            continue;
+        }
+        if camera.mode != CameraMode::ThirdPerson {
+            continue;
+        }
+        let Ok(player_transform) = player_query.get(camera.target) else {
+            continue;
+        };
+        let targets = target_query.iter().map(|(entity, transform)| (entity, transform.translation()));
+        match pick_lock_on_target(player_transform.translation(), camera_transform.forward(), targets) {
+            Some(target) => {
+                camera.lock_on = Some(target);
+                if let Ok((_, transform)) = target_query.get(target) {
+                    camera.lock_point = transform.translation();
+                }
+                println!("Locked on to {:?}", target);
+            }
+            None => println!("No lock-on target in range"),
+        }
+    }
+}
+
+// Lets go when the target is gone, too far away, or the view switches to first person
+fn break_lock_on(
+    mut camera_query: Query<&mut ThirdPersonCamera>,
+    player_query: Query<&GlobalTransform, With<Player>>,
+    target_query: Query<&GlobalTransform, With<LockOnTarget>>,
+) {
+    for mut camera in camera_query.iter_mut() {
+        let Some(target) = camera.lock_on else {
+            continue;
+        };
+        let in_range = match (target_query.get(target), player_query.get(camera.target)) {
+            (Ok(target_transform), Ok(player_transform)) => {
+                target_transform.translation().distance(player_transform.translation()) <= LOCK_ON_BREAK_DISTANCE
+            }
+            _ => false,
+        };
+        if !in_range || camera.mode != CameraMode::ThirdPerson {
+            camera.lock_on = None;
+            println!("Lock-on broken");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pick(targets: &[(u32, Vec3)]) -> Option<u32> {
+        let targets = targets.iter().map(|&(index, position)| (Entity::from_raw(index), position));
+        pick_lock_on_target(Vec3::ZERO, Vec3::NEG_Z, targets).map(|entity| entity.index())
+    }
+
+    #[test]
+    fn nearest_target_wins_whatever_order_they_come_in() {
+        let targets = [
+            (1, Vec3::new(0.0, 0.0, -12.0)),
+            (2, Vec3::new(2.0, 0.0, -5.0)),
+            (3, Vec3::new(-3.0, 0.0, -8.0)),
+        ];
+        for order in [[0, 1, 2], [2, 1, 0], [1, 0, 2], [2, 0, 1]] {
+            let shuffled: Vec<(u32, Vec3)> = order.iter().map(|&i| targets[i]).collect();
+            assert_eq!(pick(&shuffled), Some(2));
+        }
+    }
+
+    #[test]
+    fn targets_outside_the_cone_or_range_are_skipped() {
+        // Nearer, but behind and off to the side
+        let behind = (1, Vec3::new(0.0, 0.0, 2.0));
+        let side = (2, Vec3::new(-3.0, 0.0, -1.0));
+        let ahead = (3, Vec3::new(1.0, 0.0, -10.0));
+        let too_far = (4, Vec3::new(0.0, 0.0, -LOCK_ON_RANGE - 1.0));
+        assert_eq!(pick(&[behind, side, too_far, ahead]), Some(3));
+        assert_eq!(pick(&[behind, side, too_far]), None);
+    }
+
+    #[test]
+    fn height_counts_towards_distance_but_not_the_cone() {
+        // Up on a ledge straight ahead is in the cone, but further than one on the ground
+        let ledge = (1, Vec3::new(0.0, 6.0, -4.0));
+        let ground = (2, Vec3::new(0.5, 0.0, -6.0));
+        assert_eq!(pick(&[ledge]), Some(1));
+        assert_eq!(pick(&[ledge, ground]), Some(2));
+    }
+}
@@ -0,0 +1,239 @@
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use bevy_rapier3d::prelude::*;
+use rand::Rng;
+use std::f32::consts::TAU;
+use crate::combat::{despawn_dead_enemies, EnemyDeathEvent, Health, HealthChanged, Weapon};
+use crate::health::Dead;
+use crate::player::Player;
+
+pub struct LootPlugin;
+
+impl Plugin for LootPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Coins>()
+            .add_event::<LootPickedUp>()
+            .add_systems(Startup, setup_loot_assets)
+            .add_systems(Update, loot_drop_system.before(despawn_dead_enemies))
+            .add_systems(Update, (pick_up_collectibles, apply_picked_up_loot, expire_collectibles).chain());
+    }
+}
+
+// Drops fly out this fast sideways and upwards, so they scatter around where the enemy was
+const SCATTER_SPEED: f32 = 3.0;
+const SCATTER_UP_SPEED: f32 = 4.0;
+const COLLECTIBLE_SIZE: f32 = 0.15;
+// Players pick up anything within this distance of their centre, once it's had a moment to scatter
+const PICKUP_RADIUS: f32 = 1.3;
+const PICKUP_DELAY: f32 = 0.5;
+// Left lying around this long, drops disappear
+const COLLECTIBLE_LIFETIME: f32 = 30.0;
+const HEALTH_POTION_HEAL: f32 = 25.0;
+const WEAPON_UPGRADE_DAMAGE: f32 = 5.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LootItem {
+    Coin(u32),
+    HealthPotion,
+    // There's no stamina yet, so picking one up only gets logged
+    StaminaPotion,
+    WeaponUpgrade,
+}
+
+// What an enemy may drop when it dies, each with its own chance from 0 to 1
+#[derive(Component, Clone, Debug)]
+pub struct LootTable {
+    pub drops: Vec<(LootItem, f32)>,
+}
+
+impl LootTable {
+    pub fn enemy() -> Self {
+        Self {
+            drops: vec![
+                (LootItem::Coin(5), 0.9),
+                (LootItem::Coin(10), 0.3),
+                (LootItem::HealthPotion, 0.25),
+                (LootItem::StaminaPotion, 0.15),
+                (LootItem::WeaponUpgrade, 0.05),
+            ],
+        }
+    }
+}
+
+// An item lying in the world, waiting to be walked over
+#[derive(Component)]
+pub struct Collectible {
+    pub item: LootItem,
+    age: f32,
+}
+
+#[derive(Event)]
+pub struct LootPickedUp {
+    pub player: Entity,
+    pub item: LootItem,
+}
+
+// Coins the players have picked up between them
+#[derive(Resource, Default)]
+pub struct Coins(pub u32);
+
+#[derive(Resource)]
+struct LootAssets {
+    coin_mesh: Handle<Mesh>,
+    potion_mesh: Handle<Mesh>,
+    upgrade_mesh: Handle<Mesh>,
+    coin_material: Handle<StandardMaterial>,
+    health_material: Handle<StandardMaterial>,
+    stamina_material: Handle<StandardMaterial>,
+    upgrade_material: Handle<StandardMaterial>,
+}
+
+impl LootAssets {
+    fn for_item(&self, item: LootItem) -> (Handle<Mesh>, Handle<StandardMaterial>) {
+        match item {
+            LootItem::Coin(_) => (self.coin_mesh.clone(), self.coin_material.clone()),
+            LootItem::HealthPotion => (self.potion_mesh.clone(), self.health_material.clone()),
+            LootItem::StaminaPotion => (self.potion_mesh.clone(), self.stamina_material.clone()),
+            LootItem::WeaponUpgrade => (self.upgrade_mesh.clone(), self.upgrade_material.clone()),
+        }
+    }
+}
+
+fn setup_loot_assets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let mut glowing = |color: Color| {
+        materials.add(StandardMaterial {
+            base_color: color,
+            emissive: color * 0.3,
+            ..default()
+        })
+    };
+    let assets = LootAssets {
+        coin_mesh: meshes.add(Mesh::from(shape::Cylinder {
+            radius: COLLECTIBLE_SIZE,
+            height: COLLECTIBLE_SIZE * 0.3,
+            ..default()
+        })),
+        potion_mesh: meshes.add(Mesh::from(shape::UVSphere {
+            radius: COLLECTIBLE_SIZE,
+            ..default()
+        })),
+        upgrade_mesh: meshes.add(Mesh::from(shape::Cube { size: COLLECTIBLE_SIZE * 2.0 })),
+        coin_material: glowing(Color::rgb(1.0, 0.8, 0.2)),
+        health_material: glowing(Color::rgb(0.9, 0.15, 0.15)),
+        stamina_material: glowing(Color::rgb(0.2, 0.8, 0.3)),
+        upgrade_material: glowing(Color::rgb(0.2, 0.8, 0.9)),
+    };
+    commands.insert_resource(assets);
+}
+
+// Rolls each entry of a dying enemy's table on its own, so it can drop several things or nothing
+fn loot_drop_system(
+    mut commands: Commands,
+    mut death_events: EventReader<EnemyDeathEvent>,
+    enemy_query: Query<(&LootTable, &GlobalTransform)>,
+    assets: Res<LootAssets>,
+) {
+    let mut rng = rand::thread_rng();
+    for event in death_events.read() {
+        let Ok((table, transform)) = enemy_query.get(event.entity) else {
+            continue;
+        };
+        let origin = transform.translation();
+        for &(item, chance) in &table.drops {
+            if rng.gen::<f32>() >= chance {
+                continue;
+            }
+            let angle = rng.gen_range(0.0..TAU);
+            let outward = Vec3::new(angle.cos(), 0.0, angle.sin());
+            let (mesh, material) = assets.for_item(item);
+            commands.spawn((
+                Collectible { item, age: 0.0 },
+                RigidBody::Dynamic,
+                Collider::ball(COLLECTIBLE_SIZE),
+                Velocity::linear(outward * SCATTER_SPEED + Vec3::Y * SCATTER_UP_SPEED),
+                PbrBundle {
+                    mesh,
+                    material,
+                    transform: Transform::from_translation(origin + outward * 0.3),
+                    ..default()
+                },
+            ));
+            println!("Enemy {:?} dropped {:?}", event.entity, item);
+        }
+    }
+}
+
+fn pick_up_collectibles(
+    mut commands: Commands,
+    player_query: Query<(Entity, &Transform), (With<Player>, Without<Dead>)>,
+    collectible_query: Query<(Entity, &Collectible, &Transform)>,
+    mut picked_up_events: EventWriter<LootPickedUp>,
+) {
+    for (entity, collectible, transform) in collectible_query.iter() {
+        if collectible.age < PICKUP_DELAY {
+            continue;
+        }
+        let Some((player, _)) = player_query
+            .iter()
+            .find(|(_, player_transform)| player_transform.translation.distance(transform.translation) <= PICKUP_RADIUS)
+        else {
+            continue;
+        };
+        picked_up_events.send(LootPickedUp {
+            player,
+            item: collectible.item,
+        });
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn apply_picked_up_loot(
+    mut picked_up_events: EventReader<LootPickedUp>,
+    mut player_query: Query<(&mut Health, &mut Weapon), With<Player>>,
+    mut health_changed_events: EventWriter<HealthChanged>,
+    mut coins: ResMut<Coins>,
+) {
+    for event in picked_up_events.read() {
+        let Ok((mut health, mut weapon)) = player_query.get_mut(event.player) else {
+            continue;
+        };
+        match event.item {
+            LootItem::Coin(value) => {
+                coins.0 += value;
+                println!("Picked up {} coins ({} total)", value, coins.0);
+            }
+            LootItem::HealthPotion => {
+                let previous = health.current;
+                health.current = (health.current + HEALTH_POTION_HEAL).min(health.max);
+                health_changed_events.send(HealthChanged {
+                    entity: event.player,
+                    previous,
+                    current: health.current,
+                    max: health.max,
+                });
+            }
+            LootItem::StaminaPotion => println!("Picked up a stamina potion"),
+            LootItem::WeaponUpgrade => {
+                weapon.damage += WEAPON_UPGRADE_DAMAGE;
+                println!("Weapon upgraded, damage now {}", weapon.damage);
+            }
+        }
+    }
+}
+
+fn expire_collectibles(
+    mut commands: Commands,
+    mut collectible_query: Query<(Entity, &mut Collectible)>,
+    time: Res<Time>,
+) {
+    for (entity, mut collectible) in collectible_query.iter_mut() {
+        collectible.age += time.delta_seconds();
+        if collectible.age >= COLLECTIBLE_LIFETIME {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
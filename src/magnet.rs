@@ -0,0 +1,125 @@
+use bevy::pbr::NotShadowCaster;
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use bevy_rapier3d::prelude::*;
+use std::collections::HashSet;
+use crate::player::Player;
+use crate::terrain::{height_at, TerrainConfig};
+
+pub struct MagnetPlugin;
+
+impl Plugin for MagnetPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_magnet_zones)
+            .add_systems(Update, magnet_system);
+    }
+}
+
+// Pulls dynamic bodies inside its sphere sensor towards its centre, hardest at the middle and
+// fading to nothing at the edge
+#[derive(Component, Clone, Copy)]
+pub struct MagnetZone {
+    pub pull_force: f32,
+    pub radius: f32,
+    pub affect_player: bool,
+}
+
+pub fn spawn_magnet_zone(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+    position: Vec3,
+    zone: MagnetZone,
+) {
+    commands.spawn((
+        zone,
+        Collider::ball(zone.radius),
+        Sensor,
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::UVSphere {
+                radius: zone.radius,
+                ..default()
+            })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgba(0.4, 0.5, 1.0, 0.12),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            }),
+            transform: Transform::from_translation(position),
+            ..default()
+        },
+        NotShadowCaster,
+    ));
+}
+
+fn spawn_magnet_zones(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    config: Res<TerrainConfig>,
+) {
+    // Drags the loose props that roll past it into a pile, but leaves the player alone
+    spawn_magnet_zone(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        Vec3::new(8.0, height_at(&config, 8.0, -6.0) + 1.0, -6.0),
+        MagnetZone {
+            pull_force: 30.0,
+            radius: 4.0,
+            affect_player: false,
+        },
+    );
+}
+
+// Force on a body at `offset` from the zone's centre
+fn magnet_force(zone: &MagnetZone, offset: Vec3) -> Vec3 {
+    let distance = offset.length();
+    if zone.radius <= 0.0 || distance >= zone.radius {
+        return Vec3::ZERO;
+    }
+    -offset.normalize_or_zero() * zone.pull_force * (1.0 - distance / zone.radius)
+}
+
+// Applied as this frame's share of impulse, like pushing props, rather than through ExternalForce,
+// which would keep pulling after a body leaves the zone until something cleared it
+fn magnet_system(
+    mut commands: Commands,
+    zone_query: Query<(Entity, &MagnetZone, &GlobalTransform)>,
+    mut body_query: Query<(&RigidBody, &GlobalTransform, Option<&mut ExternalImpulse>, Has<Player>)>,
+    rapier_context: Res<RapierContext>,
+    time: Res<Time>,
+) {
+    let delta_seconds = time.delta_seconds();
+    for (zone_entity, zone, zone_transform) in zone_query.iter() {
+        // A body with several colliders only gets pulled once
+        let mut bodies = HashSet::new();
+        for (collider1, collider2, intersecting) in rapier_context.intersection_pairs_with(zone_entity) {
+            if !intersecting {
+                continue;
+            }
+            let other = if collider1 == zone_entity { collider2 } else { collider1 };
+            bodies.insert(rapier_context.collider_parent(other).unwrap_or(other));
+        }
+
+        for body in bodies {
+            let Ok((RigidBody::Dynamic, body_transform, impulse, is_player)) = body_query.get_mut(body) else {
+                continue;
+            };
+            if is_player && !zone.affect_player {
+                continue;
+            }
+            let pull = magnet_force(zone, body_transform.translation() - zone_transform.translation()) * delta_seconds;
+            match impulse {
+                Some(mut impulse) => impulse.impulse += pull,
+                None => {
+                    commands.entity(body).insert(ExternalImpulse {
+                        impulse: pull,
+                        ..default()
+                    });
+                }
+            }
+        }
+    }
+}
@@ -1,26 +1,185 @@
+// Bevy systems routinely take many params and nested query types
+#![allow(clippy::type_complexity, clippy::too_many_arguments)]
+
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 
+mod ambient_audio;
 mod camera;
+mod camera_path;
+mod camera_preferences;
+mod building;
+mod carry;
+mod cloth;
+mod color_grading;
+mod combat;
+mod conveyor;
+mod day_night;
+mod debug;
+mod dialogue;
+mod elevator;
+mod explosion;
+mod fixed_camera;
+mod footprints;
+mod game_modes;
+mod ghost;
+mod grapple;
+mod health;
+mod input_map;
+mod inset_camera;
+mod interaction;
+mod level;
+mod lightning;
+mod lock_on;
+mod loot;
+mod magnet;
+mod memory_stats;
+mod navmesh;
+mod navmesh_debug;
+#[cfg(feature = "dev-tools")]
+mod noclip;
+mod noise;
+mod occlusion;
+mod particles;
+mod photo_mode;
+mod physics_sleep;
 mod player;
+#[cfg(feature = "dev-tools")]
+mod player_commands;
+mod props;
+mod quests;
+mod recording;
+mod rope;
+mod save;
+mod slow_motion;
+mod spectator;
+mod status_effects;
+mod stealth;
 mod terrain;
+#[cfg(test)]
+mod test_app;
+mod turret;
 mod vulkan_renderer;
+mod waves;
+mod weather;
 
+use ambient_audio::AmbientAudioPlugin;
 use camera::CameraPlugin;
+use camera_path::CameraPathPlugin;
+use camera_preferences::CameraPreferencesPlugin;
+use building::BuildingPlugin;
+use carry::CarryPlugin;
+use cloth::ClothPlugin;
+use color_grading::ColorGradingPlugin;
+use combat::CombatPlugin;
+use conveyor::ConveyorPlugin;
+use day_night::DayNightPlugin;
+use debug::DebugPlugin;
+use dialogue::DialoguePlugin;
+use elevator::ElevatorPlugin;
+use explosion::ExplosionPlugin;
+use fixed_camera::FixedCameraPlugin;
+use footprints::FootprintPlugin;
+use game_modes::ObstacleCoursePlugin;
+use ghost::GhostPlugin;
+use grapple::GrapplePlugin;
+use health::HealthPlugin;
+use input_map::InputMapPlugin;
+use inset_camera::InsetCameraPlugin;
+use interaction::InteractionPlugin;
+use level::LevelSerializerPlugin;
+use lightning::LightningPlugin;
+use lock_on::LockOnPlugin;
+use loot::LootPlugin;
+use magnet::MagnetPlugin;
+use memory_stats::MemoryStatsPlugin;
+use navmesh::NavmeshPlugin;
+use navmesh_debug::NavmeshDebugPlugin;
+#[cfg(feature = "dev-tools")]
+use noclip::NoclipPlugin;
+use occlusion::OcclusionPlugin;
+use particles::ParticlesPlugin;
+use photo_mode::PhotoModePlugin;
+use physics_sleep::PhysicsSleepPlugin;
 use player::PlayerPlugin;
+#[cfg(feature = "dev-tools")]
+use player_commands::PlayerCommandsPlugin;
+use props::PropsPlugin;
+use quests::QuestPlugin;
+use recording::RecordingPlugin;
+use rope::RopePlugin;
+use save::SaveSystemPlugin;
+use slow_motion::SlowMotionPlugin;
+use spectator::SpectatorPlugin;
+use status_effects::StatusEffectsPlugin;
+use stealth::StealthPlugin;
 use terrain::TerrainPlugin;
+use turret::TurretPlugin;
 use vulkan_renderer::VulkanRendererPlugin;
+use waves::WavePlugin;
+use weather::WeatherPlugin;
 
 fn main() {
     env_logger::init();
     
-    App::new()
+    let mut app = App::new();
+    app
         .add_plugins(DefaultPlugins)
         .add_plugins(RapierPhysicsPlugin::<NoUserData>::default())
         .add_plugins(RapierDebugRenderPlugin::default())
-        .add_plugins(VulkanRendererPlugin)
+        .add_plugins(VulkanRendererPlugin::default())
+        .add_plugins(ColorGradingPlugin)
+        .add_plugins(DayNightPlugin)
+        .add_plugins(DebugPlugin)
+        .add_plugins(MemoryStatsPlugin)
+        .add_plugins(InputMapPlugin)
+        .add_plugins(RecordingPlugin)
+        .add_plugins(GhostPlugin)
         .add_plugins(PlayerPlugin)
         .add_plugins(CameraPlugin)
+        .add_plugins(CameraPreferencesPlugin)
+        .add_plugins(CameraPathPlugin)
+        .add_plugins(PhotoModePlugin)
+        .add_plugins(SpectatorPlugin)
+        .add_plugins(InsetCameraPlugin)
+        .add_plugins(FixedCameraPlugin)
+        .add_plugins(OcclusionPlugin)
+        .add_plugins(LockOnPlugin)
+        .add_plugins(ObstacleCoursePlugin)
         .add_plugins(TerrainPlugin)
-        .run();
+        .add_plugins(ElevatorPlugin)
+        .add_plugins(ConveyorPlugin)
+        .add_plugins(BuildingPlugin)
+        .add_plugins(LevelSerializerPlugin)
+        .add_plugins(InteractionPlugin)
+        .add_plugins(DialoguePlugin)
+        .add_plugins(QuestPlugin)
+        .add_plugins(PropsPlugin)
+        .add_plugins(MagnetPlugin)
+        .add_plugins(RopePlugin)
+        .add_plugins(ClothPlugin)
+        .add_plugins(CarryPlugin)
+        .add_plugins(GrapplePlugin)
+        .add_plugins(CombatPlugin)
+        .add_plugins(LootPlugin)
+        .add_plugins(NavmeshPlugin)
+        .add_plugins(NavmeshDebugPlugin)
+        .add_plugins(WavePlugin)
+        .add_plugins(StealthPlugin)
+        .add_plugins(TurretPlugin)
+        .add_plugins(SlowMotionPlugin)
+        .add_plugins(ExplosionPlugin)
+        .add_plugins(PhysicsSleepPlugin)
+        .add_plugins(HealthPlugin)
+        .add_plugins(StatusEffectsPlugin)
+        .add_plugins(SaveSystemPlugin)
+        .add_plugins(ParticlesPlugin)
+        .add_plugins(AmbientAudioPlugin)
+        .add_plugins(WeatherPlugin)
+        .add_plugins(LightningPlugin)
+        .add_plugins(FootprintPlugin);
+    #[cfg(feature = "dev-tools")]
+    app.add_plugins(NoclipPlugin)
+        .add_plugins(PlayerCommandsPlugin);
+    app.run();
 }
@@ -0,0 +1,51 @@
+use bevy::prelude::*;
+use crate::debug::{debug_overlay_enabled, DebugLogTimers, DEBUG_LOG_TARGET};
+use crate::vulkan_renderer::VulkanRenderer;
+
+// Reports the Vulkan renderer's GPU memory use with the other debug state, and warns when it
+// gets close to the device's memory, to catch leaks during development
+pub struct MemoryStatsPlugin;
+
+impl Plugin for MemoryStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, log_gpu_memory.run_if(debug_overlay_enabled));
+    }
+}
+
+// Fraction of device local memory in use that triggers the warning
+const MEMORY_WARNING_FRACTION: f64 = 0.8;
+const MIB: f64 = 1024.0 * 1024.0;
+
+fn log_gpu_memory(
+    vulkan_renderer: Res<VulkanRenderer>,
+    mut timers: ResMut<DebugLogTimers>,
+    time: Res<Time>,
+    mut over_budget: Local<bool>,
+) {
+    if !timers.gpu_memory.tick(time.delta()).just_finished() || !vulkan_renderer.debug {
+        return;
+    }
+    let Some(allocator) = &vulkan_renderer.allocator else {
+        return;
+    };
+    let bytes = allocator.bytes_allocated();
+    let used = bytes as f64 / MIB;
+    debug!(
+        target: DEBUG_LOG_TARGET,
+        "GPU memory: {:.1} MiB in {} allocations",
+        used, allocator.allocation_count(),
+    );
+
+    let Some(budget) = vulkan_renderer.device_local_memory().filter(|&budget| budget > 0) else {
+        return;
+    };
+    // Once per crossing, not every report
+    let was_over_budget = *over_budget;
+    *over_budget = bytes as f64 > budget as f64 * MEMORY_WARNING_FRACTION;
+    if *over_budget && !was_over_budget {
+        warn!(
+            "GPU memory use is {:.1} MiB, over {}% of the device's {:.1} MiB",
+            used, MEMORY_WARNING_FRACTION * 100.0, budget as f64 / MIB,
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use crate::terrain::{height_at, TerrainConfig};
+
+pub struct NavmeshPlugin;
+
+impl Plugin for NavmeshPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, build_nav_grid.run_if(not(resource_exists::<NavGrid>())));
+    }
+}
+
+// The grid covers the main island, which spans -20..20 in x and z
+const GRID_ORIGIN: Vec2 = Vec2::new(-20.0, -20.0);
+const GRID_CELLS: usize = 40;
+const CELL_SIZE: f32 = 1.0;
+// Ground is looked for by a ray straight down from this high
+const PROBE_HEIGHT: f32 = 10.0;
+// A cell is walkable where the ground is within this far of the island's surface, rather than
+// on top of something standing on it...
+const GROUND_TOLERANCE: f32 = 0.5;
+// ...flat enough to stand on...
+const MIN_GROUND_NORMAL_Y: f32 = 0.7;
+// ...and nothing fixed is in the way from this far above it up to head height
+const CLEARANCE_BOTTOM: f32 = 0.3;
+const CLEARANCE_TOP: f32 = 1.8;
+// A* step costs, straight and diagonal, in tenths of a cell
+const STRAIGHT_COST: u32 = 10;
+const DIAGONAL_COST: u32 = 14;
+
+// Which cells of the island something can walk across, found once the physics world has its
+// colliders. Only fixed colliders count, so buildings put up later aren't in it.
+#[derive(Resource)]
+pub struct NavGrid {
+    walkable: Vec<bool>,
+    // Ground height of each cell
+    heights: Vec<f32>,
+}
+
+impl NavGrid {
+    pub fn size(&self) -> usize {
+        GRID_CELLS
+    }
+
+    pub fn is_walkable(&self, x: usize, z: usize) -> bool {
+        self.walkable[z * GRID_CELLS + x]
+    }
+
+    // The middle of a cell, on the ground
+    pub fn cell_center(&self, x: usize, z: usize) -> Vec3 {
+        Vec3::new(
+            GRID_ORIGIN.x + (x as f32 + 0.5) * CELL_SIZE,
+            self.heights[z * GRID_CELLS + x],
+            GRID_ORIGIN.y + (z as f32 + 0.5) * CELL_SIZE,
+        )
+    }
+
+    pub fn cell_size(&self) -> f32 {
+        CELL_SIZE
+    }
+
+    fn cell_at(&self, position: Vec3) -> Option<(usize, usize)> {
+        let x = ((position.x - GRID_ORIGIN.x) / CELL_SIZE).floor();
+        let z = ((position.z - GRID_ORIGIN.y) / CELL_SIZE).floor();
+        let range = 0.0..GRID_CELLS as f32;
+        (range.contains(&x) && range.contains(&z)).then_some((x as usize, z as usize))
+    }
+
+    // Cell centres from the one after `from`'s to `to`'s, going round anything in the way, or
+    // None if `to` isn't on a walkable cell or can't be reached. Diagonal steps never cut the
+    // corner of a blocked cell.
+    pub fn find_path(&self, from: Vec3, to: Vec3) -> Option<Vec<Vec3>> {
+        let start = self.cell_at(from)?;
+        let goal = self.cell_at(to)?;
+        if !self.is_walkable(goal.0, goal.1) {
+            return None;
+        }
+        let index = |(x, z): (usize, usize)| z * GRID_CELLS + x;
+        // Octile distance, which never overestimates on an 8-way grid
+        let heuristic = |(x, z): (usize, usize)| {
+            let dx = x.abs_diff(goal.0) as u32;
+            let dz = z.abs_diff(goal.1) as u32;
+            STRAIGHT_COST * dx.max(dz) + (DIAGONAL_COST - STRAIGHT_COST) * dx.min(dz)
+        };
+
+        let mut cost = vec![u32::MAX; GRID_CELLS * GRID_CELLS];
+        let mut came_from = vec![usize::MAX; GRID_CELLS * GRID_CELLS];
+        let mut open = BinaryHeap::new();
+        cost[index(start)] = 0;
+        open.push(Reverse((heuristic(start), start)));
+        while let Some(Reverse((_, cell))) = open.pop() {
+            if cell == goal {
+                let mut path = Vec::new();
+                let mut current = index(goal);
+                while current != index(start) {
+                    path.push(self.cell_center(current % GRID_CELLS, current / GRID_CELLS));
+                    current = came_from[current];
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for dz in -1..=1_i32 {
+                for dx in -1..=1_i32 {
+                    if dx == 0 && dz == 0 {
+                        continue;
+                    }
+                    let (Some(x), Some(z)) = (cell.0.checked_add_signed(dx as isize), cell.1.checked_add_signed(dz as isize)) else {
+                        continue;
+                    };
+                    if x >= GRID_CELLS || z >= GRID_CELLS || !self.is_walkable(x, z) {
+                        continue;
+                    }
+                    let diagonal = dx != 0 && dz != 0;
+                    if diagonal && (!self.is_walkable(x, cell.1) || !self.is_walkable(cell.0, z)) {
+                        continue;
+                    }
+                    let next_cost = cost[index(cell)] + if diagonal { DIAGONAL_COST } else { STRAIGHT_COST };
+                    if next_cost < cost[index((x, z))] {
+                        cost[index((x, z))] = next_cost;
+                        came_from[index((x, z))] = index(cell);
+                        open.push(Reverse((next_cost + heuristic((x, z)), (x, z))));
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+// Waits for the island's collider to reach the physics world, which happens after the frame it's
+// spawned in, then probes every cell
+fn build_nav_grid(mut commands: Commands, rapier_context: Res<RapierContext>, config: Res<TerrainConfig>) {
+    let filter = QueryFilter::only_fixed().exclude_sensors();
+    let probe = |x: usize, z: usize| {
+        let origin = Vec3::new(
+            GRID_ORIGIN.x + (x as f32 + 0.5) * CELL_SIZE,
+            PROBE_HEIGHT,
+            GRID_ORIGIN.y + (z as f32 + 0.5) * CELL_SIZE,
+        );
+        rapier_context
+            .cast_ray_and_get_normal(origin, Vec3::NEG_Y, PROBE_HEIGHT * 2.0, true, filter)
+            .map(|(_, hit)| hit)
+    };
+    let centre = GRID_CELLS / 2;
+    if probe(centre, centre).is_none() {
+        return;
+    }
+
+    let clearance = Collider::cuboid(CELL_SIZE * 0.4, (CLEARANCE_TOP - CLEARANCE_BOTTOM) * 0.5, CELL_SIZE * 0.4);
+    let mut walkable = vec![false; GRID_CELLS * GRID_CELLS];
+    let mut heights = vec![0.0; GRID_CELLS * GRID_CELLS];
+    for z in 0..GRID_CELLS {
+        for x in 0..GRID_CELLS {
+            let Some(hit) = probe(x, z) else {
+                continue;
+            };
+            heights[z * GRID_CELLS + x] = hit.point.y;
+            let surface = height_at(&config, hit.point.x, hit.point.z);
+            if (hit.point.y - surface).abs() > GROUND_TOLERANCE || hit.normal.y < MIN_GROUND_NORMAL_Y {
+                continue;
+            }
+            let clearance_centre = hit.point + Vec3::Y * (CLEARANCE_BOTTOM + CLEARANCE_TOP) * 0.5;
+            walkable[z * GRID_CELLS + x] = rapier_context
+                .intersection_with_shape(clearance_centre, Quat::IDENTITY, &clearance, filter)
+                .is_none();
+        }
+    }
+    println!(
+        "Navigation grid built: {} of {} cells walkable",
+        walkable.iter().filter(|walkable| **walkable).count(),
+        walkable.len()
+    );
+    commands.insert_resource(NavGrid { walkable, heights });
+}
+
+// A wave enemy's way to the player it's after. The path is only worked out again every so often,
+// so it doesn't jitter between equally good routes from one frame to the next.
+#[derive(Component, Default)]
+pub struct NavPath {
+    pub points: Vec<Vec3>,
+    // The point being walked to
+    pub next: usize,
+    // Seconds until the path is worked out again
+    pub repath_timer: f32,
+}
+
+impl NavPath {
+    pub fn remaining(&self) -> &[Vec3] {
+        self.points.get(self.next..).unwrap_or_default()
+    }
+}
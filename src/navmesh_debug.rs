@@ -0,0 +1,68 @@
+use bevy::prelude::*;
+use std::f32::consts::FRAC_PI_2;
+use crate::debug::debug_overlay_enabled;
+use crate::input_map::{Action, InputMap};
+use crate::navmesh::{NavGrid, NavPath};
+
+pub struct NavmeshDebugPlugin;
+
+impl Plugin for NavmeshDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NavmeshDebug>()
+            .add_systems(Update, (
+                toggle_navmesh_debug,
+                draw_navmesh
+                    .after(toggle_navmesh_debug)
+                    .run_if(debug_overlay_enabled)
+                    .run_if(resource_exists::<NavGrid>()),
+            ));
+    }
+}
+
+// Drawn just above the ground so it isn't hidden in it
+const OVERLAY_LIFT: f32 = 0.05;
+const WALKABLE_COLOR: Color = Color::rgba(0.2, 0.9, 0.2, 0.5);
+const BLOCKED_COLOR: Color = Color::rgba(0.9, 0.2, 0.2, 0.5);
+const PATH_COLOR: Color = Color::YELLOW;
+
+// N shows the navigation grid, walkable cells green and the rest red, with each enemy's path to
+// the player it's after in yellow. Only while the debug overlay is on.
+#[derive(Resource, Default)]
+pub struct NavmeshDebug {
+    pub show_navmesh: bool,
+}
+
+fn toggle_navmesh_debug(input_map: Res<InputMap>, mut navmesh_debug: ResMut<NavmeshDebug>) {
+    if !input_map.just_pressed(Action::ToggleNavmeshDebug) {
+        return;
+    }
+    navmesh_debug.show_navmesh = !navmesh_debug.show_navmesh;
+    println!("Navmesh overlay {}", if navmesh_debug.show_navmesh { "on" } else { "off" });
+}
+
+fn draw_navmesh(
+    mut gizmos: Gizmos,
+    navmesh_debug: Res<NavmeshDebug>,
+    nav_grid: Res<NavGrid>,
+    path_query: Query<(&GlobalTransform, &NavPath)>,
+) {
+    if !navmesh_debug.show_navmesh {
+        return;
+    }
+    let flat = Quat::from_rotation_x(-FRAC_PI_2);
+    let cell = Vec2::splat(nav_grid.cell_size() * 0.9);
+    for z in 0..nav_grid.size() {
+        for x in 0..nav_grid.size() {
+            let color = if nav_grid.is_walkable(x, z) { WALKABLE_COLOR } else { BLOCKED_COLOR };
+            gizmos.rect(nav_grid.cell_center(x, z) + Vec3::Y * OVERLAY_LIFT, flat, cell, color);
+        }
+    }
+    for (transform, path) in path_query.iter() {
+        if path.remaining().is_empty() {
+            continue;
+        }
+        let points = std::iter::once(transform.translation())
+            .chain(path.remaining().iter().map(|point| *point + Vec3::Y * OVERLAY_LIFT));
+        gizmos.linestrip(points, PATH_COLOR);
+    }
+}
@@ -0,0 +1,143 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use crate::camera::ThirdPersonCamera;
+use crate::debug::Noclip;
+use crate::health::Dead;
+use crate::input_map::{Action, InputMap};
+use crate::player::{GroundState, Player, PlayerIndex};
+
+// Developer fly mode, only built with the dev-tools feature
+pub struct NoclipPlugin;
+
+impl Plugin for NoclipPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<NoclipSettings>()
+            .add_systems(Update, (toggle_noclip, fly_movement).chain());
+    }
+}
+
+#[derive(Resource)]
+pub struct NoclipSettings {
+    pub fly_speed: f32,
+    pub min_fly_speed: f32,
+    pub max_fly_speed: f32,
+    pub sprint_multiplier: f32,
+    // Fly speed is multiplied by this per scroll line
+    pub scroll_factor: f32,
+}
+
+impl Default for NoclipSettings {
+    fn default() -> Self {
+        Self {
+            fly_speed: 15.0,
+            min_fly_speed: 1.0,
+            max_fly_speed: 200.0,
+            sprint_multiplier: 4.0,
+            scroll_factor: 1.2,
+        }
+    }
+}
+
+// What noclip overrode on the player's body, put back when it ends
+#[derive(Component)]
+struct SavedPhysics {
+    body: RigidBody,
+    gravity_scale: Option<GravityScale>,
+    collision_groups: CollisionGroups,
+}
+
+fn toggle_noclip(
+    mut commands: Commands,
+    input_map: Res<InputMap>,
+    mut player_query: Query<(Entity, &PlayerIndex, &mut Player, &RigidBody, Option<&GravityScale>, &CollisionGroups, &mut Velocity, Option<&SavedPhysics>), Without<Dead>>,
+) {
+    for (entity, index, mut player, body, gravity_scale, collision_groups, mut velocity, saved) in player_query.iter_mut() {
+        if !input_map.for_player(index.0).just_pressed(Action::ToggleNoclip) {
+            continue;
+        }
+
+        // Either way the player shouldn't keep whatever velocity it had
+        *velocity = Velocity::zero();
+        if let Some(saved) = saved {
+            let mut entity_commands = commands.entity(entity);
+            entity_commands
+                .insert(saved.body)
+                .insert(saved.collision_groups)
+                .remove::<(SavedPhysics, Noclip)>();
+            match saved.gravity_scale {
+                Some(gravity_scale) => entity_commands.insert(gravity_scale),
+                None => entity_commands.remove::<GravityScale>(),
+            };
+            // Don't count the flight towards fall damage
+            player.fall_speed = 0.0;
+            player.platform_velocity = Vec3::ZERO;
+            println!("=== NOCLIP OFF (player {}) ===", index.0 + 1);
+        } else {
+            commands.entity(entity).insert((
+                SavedPhysics {
+                    body: *body,
+                    gravity_scale: gravity_scale.copied(),
+                    collision_groups: *collision_groups,
+                },
+                Noclip,
+                RigidBody::KinematicPositionBased,
+                GravityScale(0.0),
+                CollisionGroups::new(collision_groups.memberships, Group::NONE),
+            ));
+            player.ground = GroundState::Airborne;
+            player.ground_entity = None;
+            println!("=== NOCLIP ON (player {}) ===", index.0 + 1);
+        }
+    }
+}
+
+fn fly_movement(
+    input_map: Res<InputMap>,
+    mut settings: ResMut<NoclipSettings>,
+    mut player_query: Query<(&PlayerIndex, &mut Transform), (With<Player>, With<Noclip>)>,
+    camera_query: Query<(&PlayerIndex, &Transform), (With<ThirdPersonCamera>, Without<Player>)>,
+    time: Res<Time>,
+) {
+    for (index, mut transform) in player_query.iter_mut() {
+        let input = input_map.for_player(index.0);
+        let scroll = input.axis(Action::ZoomIn) - input.axis(Action::ZoomOut);
+        if scroll != 0.0 {
+            settings.fly_speed = (settings.fly_speed * settings.scroll_factor.powf(scroll))
+                .clamp(settings.min_fly_speed, settings.max_fly_speed);
+            println!("Noclip fly speed: {:.1}", settings.fly_speed);
+        }
+
+        // Fly along where the camera actually looks, pitch included
+        let (forward, right) = camera_query
+            .iter()
+            .find(|(camera_index, _)| *camera_index == index)
+            .map(|(_, camera)| (camera.forward(), camera.right()))
+            .unwrap_or((Vec3::NEG_Z, Vec3::X));
+
+        let mut movement = Vec3::ZERO;
+        if input.pressed(Action::MoveForward) {
+            movement += forward;
+        }
+        if input.pressed(Action::MoveBack) {
+            movement -= forward;
+        }
+        if input.pressed(Action::MoveRight) {
+            movement += right;
+        }
+        if input.pressed(Action::MoveLeft) {
+            movement -= right;
+        }
+        if input.pressed(Action::Jump) {
+            movement += Vec3::Y;
+        }
+        if input.pressed(Action::Crouch) {
+            movement -= Vec3::Y;
+        }
+
+        let mut speed = settings.fly_speed;
+        if input.pressed(Action::Sprint) {
+            speed *= settings.sprint_multiplier;
+        }
+        transform.translation += movement.normalize_or_zero() * speed * time.delta_seconds();
+    }
+}
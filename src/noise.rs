@@ -0,0 +1,50 @@
+// Seeded value noise: a pseudo-random value at every point of an integer lattice, blended
+// smoothly in between. The same seed always gives the same values, on any machine.
+pub struct ValueNoise {
+    seed: u32,
+}
+
+impl ValueNoise {
+    pub fn new(seed: u32) -> Self {
+        Self { seed }
+    }
+
+    // A value in 0..1 for one lattice point of one octave, from an integer hash
+    fn lattice(&self, octave: u32, x: i32, z: i32) -> f32 {
+        let mut hash = self.seed
+            ^ octave.wrapping_mul(0x9e37_79b9)
+            ^ (x as u32).wrapping_mul(0x27d4_eb2d)
+            ^ (z as u32).wrapping_mul(0x1656_67b1);
+        hash = (hash ^ (hash >> 15)).wrapping_mul(0x2c1b_3c6d);
+        hash = (hash ^ (hash >> 12)).wrapping_mul(0x297a_2d39);
+        hash ^= hash >> 15;
+        hash as f32 / u32::MAX as f32
+    }
+
+    // In 0..1, with lattice points a unit apart
+    fn sample(&self, octave: u32, x: f32, z: f32) -> f32 {
+        let (x0, z0) = (x.floor(), z.floor());
+        let (ix, iz) = (x0 as i32, z0 as i32);
+        let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+        let (tx, tz) = (smooth(x - x0), smooth(z - z0));
+        let top = self.lattice(octave, ix, iz) * (1.0 - tx) + self.lattice(octave, ix + 1, iz) * tx;
+        let bottom = self.lattice(octave, ix, iz + 1) * (1.0 - tx) + self.lattice(octave, ix + 1, iz + 1) * tx;
+        top * (1.0 - tz) + bottom * tz
+    }
+
+    // `octaves` layers, each at twice the frequency and half the amplitude of the one before, in
+    // 0..1. Each octave has its own lattice, so their peaks don't line up.
+    pub fn fractal(&self, x: f32, z: f32, octaves: u32) -> f32 {
+        let mut total = 0.0;
+        let mut total_amplitude = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        for octave in 0..octaves {
+            total += self.sample(octave, x * frequency, z * frequency) * amplitude;
+            total_amplitude += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        if total_amplitude > 0.0 { total / total_amplitude } else { 0.0 }
+    }
+}
@@ -0,0 +1,245 @@
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use bevy_rapier3d::prelude::*;
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use crate::camera::{CameraMode, ThirdPersonCamera};
+use crate::player::Player;
+
+pub struct OcclusionPlugin;
+
+impl Plugin for OcclusionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OcclusionFader>()
+            .add_systems(PostUpdate, occlusion_fader.after(TransformSystem::TransformPropagate));
+    }
+}
+
+// Seconds to fade all the way out, or back in once the view clears
+const FADE_TIME: f32 = 0.3;
+const DEFAULT_MIN_ALPHA: f32 = 0.15;
+// Ray end point on the player, roughly chest height
+const PLAYER_AIM_HEIGHT: f32 = 0.5;
+// Radius of the capsule swept from the camera to the player. Wider than a ray, so something only
+// just covering the player still counts.
+const OCCLUSION_CAST_RADIUS: f32 = 0.3;
+
+// Scenery that turns see-through while it's between a third person camera and its player. Its
+// collider is what's hit, so decoration without collision needs a sensor collider. A min_alpha of
+// zero cuts it out by hiding it instead of fading it.
+#[derive(Component)]
+pub struct OcclusionFadeable {
+    pub min_alpha: f32,
+}
+
+impl Default for OcclusionFadeable {
+    fn default() -> Self {
+        Self {
+            min_alpha: DEFAULT_MIN_ALPHA,
+        }
+    }
+}
+
+// An occluder's own copy of its material, faded in place of the original. Materials can be shared
+// between entities, and dimming the shared one would dim all of them.
+struct FadedMaterial {
+    original: Handle<StandardMaterial>,
+    instance: Handle<StandardMaterial>,
+}
+
+// Everything currently not fully opaque, so it's brought back once the view clears
+#[derive(Resource, Default)]
+pub struct OcclusionFader {
+    pub faded_entities: Vec<Entity>,
+    // Per faded entity, removed along with the copy once it's opaque again or gone
+    materials: HashMap<Entity, FadedMaterial>,
+    // Cut out entities and the visibility they had
+    hidden: HashMap<Entity, Visibility>,
+}
+
+impl OcclusionFader {
+    // Puts the original material back and drops the copy, so faded copies never pile up
+    fn release(&mut self, entity: Entity, materials: &mut Assets<StandardMaterial>) -> Option<Handle<StandardMaterial>> {
+        let faded = self.materials.remove(&entity)?;
+        materials.remove(&faded.instance);
+        Some(faded.original)
+    }
+}
+
+fn occlusion_fader(
+    mut fader: ResMut<OcclusionFader>,
+    camera_query: Query<(&GlobalTransform, &ThirdPersonCamera)>,
+    player_query: Query<(&GlobalTransform, &Player)>,
+    mut fadeable_query: Query<(&OcclusionFadeable, &mut Handle<StandardMaterial>, Option<&mut Visibility>)>,
+    rapier_context: Res<RapierContext>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    time: Res<Time>,
+) {
+    let mut occluders = HashSet::new();
+    for (camera_transform, camera) in camera_query.iter() {
+        if !matches!(camera.mode, CameraMode::ThirdPerson | CameraMode::TopDown) {
+            continue;
+        }
+        let Ok((player_transform, player)) = player_query.get(camera.target) else {
+            continue;
+        };
+        let origin = camera_transform.translation();
+        let aim = player_transform.translation() + Vec3::Y * PLAYER_AIM_HEIGHT;
+        let to_player = aim - origin;
+        let distance = to_player.length();
+        if distance <= f32::EPSILON {
+            continue;
+        }
+        // Everything the swept sphere passes through, except the ground the player stands on
+        let midpoint = origin + to_player * 0.5;
+        let rotation = Quat::from_rotation_arc(Vec3::Y, to_player / distance);
+        let sweep = Collider::capsule_y(distance * 0.5, OCCLUSION_CAST_RADIUS);
+        let filter = QueryFilter::default().exclude_rigid_body(camera.target);
+        rapier_context.intersections_with_shape(midpoint, rotation, &sweep, filter, |hit| {
+            let entity = rapier_context.collider_parent(hit).unwrap_or(hit);
+            if fadeable_query.contains(entity) && player.ground_entity != Some(entity) {
+                occluders.insert(entity);
+            }
+            true
+        });
+    }
+
+    for &entity in &occluders {
+        if !fader.faded_entities.contains(&entity) {
+            fader.faded_entities.push(entity);
+        }
+    }
+
+    let step = time.delta_seconds() / FADE_TIME;
+    let faded_entities = std::mem::take(&mut fader.faded_entities);
+    for entity in faded_entities {
+        let occluding = occluders.contains(&entity);
+        let Ok((fadeable, mut material_handle, visibility)) = fadeable_query.get_mut(entity) else {
+            // Despawned while faded
+            fader.release(entity, &mut materials);
+            fader.hidden.remove(&entity);
+            continue;
+        };
+
+        // Cut out: hidden while in the way, back as it was once it isn't
+        if fadeable.min_alpha <= 0.0 {
+            if let Some(mut visibility) = visibility {
+                if occluding {
+                    fader.hidden.entry(entity).or_insert_with(|| std::mem::replace(&mut *visibility, Visibility::Hidden));
+                    fader.faded_entities.push(entity);
+                } else if let Some(shown) = fader.hidden.remove(&entity) {
+                    *visibility = shown;
+                }
+            }
+            continue;
+        }
+
+        if let Entry::Vacant(entry) = fader.materials.entry(entity) {
+            let Some(material) = materials.get(&*material_handle).cloned() else {
+                continue;
+            };
+            let instance = materials.add(material);
+            entry.insert(FadedMaterial {
+                original: material_handle.clone(),
+                instance: instance.clone(),
+            });
+            *material_handle = instance;
+        }
+        let Some(material) = materials.get_mut(&*material_handle) else {
+            continue;
+        };
+        let target = if occluding { fadeable.min_alpha } else { 1.0 };
+        let alpha = material.base_color.a();
+        let alpha = alpha + (target - alpha).clamp(-step, step);
+        // Opaque again: back to the shared material, and stop tracking it
+        if !occluding && alpha >= 1.0 {
+            if let Some(original) = fader.release(entity, &mut materials) {
+                *material_handle = original;
+            }
+            continue;
+        }
+        material.base_color.set_a(alpha);
+        material.alpha_mode = AlphaMode::Blend;
+        fader.faded_entities.push(entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use crate::camera::{spawn_camera, CameraSettings};
+    use crate::test_app::physics_app;
+
+    // A camera at the origin looking at a player 10 m down -Z, and two walls sharing one material
+    // halfway between them. The first starts in the way, the second off to the side.
+    fn occlusion_app() -> (App, Entity, [Entity; 2], Handle<StandardMaterial>) {
+        let mut app = physics_app();
+        app.init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<OcclusionFader>()
+            .add_systems(PostUpdate, occlusion_fader.after(TransformSystem::TransformPropagate));
+        let player = app.world.spawn((TransformBundle::from(Transform::from_xyz(0.0, -0.5, -10.0)), Player::default())).id();
+        app.world.run_system_once(|mut commands: Commands| spawn_camera(&mut commands, 0, &CameraSettings::default()));
+        let (camera_entity, mut camera) = app.world.query::<(Entity, &mut ThirdPersonCamera)>().single_mut(&mut app.world);
+        camera.target = player;
+        app.world.entity_mut(camera_entity).insert(Transform::IDENTITY);
+
+        let shared = app.world.resource_mut::<Assets<StandardMaterial>>().add(StandardMaterial::default());
+        let walls = [0.0, 20.0].map(|x| {
+            app.world.spawn((
+                TransformBundle::from(Transform::from_xyz(x, 0.0, -5.0)),
+                Collider::cuboid(2.0, 2.0, 0.2),
+                shared.clone(),
+                OcclusionFadeable::default(),
+            )).id()
+        });
+        (app, camera_entity, walls, shared)
+    }
+
+    fn frames(app: &mut App, count: usize) {
+        for _ in 0..count {
+            app.update();
+        }
+    }
+
+    fn material_count(app: &App) -> usize {
+        app.world.resource::<Assets<StandardMaterial>>().len()
+    }
+
+    #[test]
+    fn faded_material_copies_are_dropped_once_opaque_again() {
+        let (mut app, camera, [wall, side_wall], shared) = occlusion_app();
+        for _ in 0..5 {
+            app.world.entity_mut(camera).insert(Transform::IDENTITY);
+            frames(&mut app, 10);
+            // Only the wall in the way gets a copy; the shared original is untouched
+            assert_eq!(material_count(&app), 2);
+            assert_ne!(*app.world.get::<Handle<StandardMaterial>>(wall).unwrap(), shared);
+            assert_eq!(*app.world.get::<Handle<StandardMaterial>>(side_wall).unwrap(), shared);
+            assert_eq!(app.world.resource::<Assets<StandardMaterial>>().get(&shared).unwrap().base_color.a(), 1.0);
+
+            // Out of the way, long enough to fade all the way back
+            app.world.entity_mut(camera).insert(Transform::from_xyz(-20.0, 0.0, 0.0));
+            frames(&mut app, 30);
+            assert_eq!(material_count(&app), 1);
+            assert_eq!(*app.world.get::<Handle<StandardMaterial>>(wall).unwrap(), shared);
+            let fader = app.world.resource::<OcclusionFader>();
+            assert!(fader.faded_entities.is_empty());
+            assert!(fader.materials.is_empty());
+        }
+    }
+
+    #[test]
+    fn despawning_a_faded_occluder_drops_its_copy() {
+        let (mut app, _, [wall, _], _) = occlusion_app();
+        frames(&mut app, 10);
+        assert_eq!(material_count(&app), 2);
+
+        app.world.despawn(wall);
+        frames(&mut app, 2);
+        assert_eq!(material_count(&app), 1);
+        let fader = app.world.resource::<OcclusionFader>();
+        assert!(fader.faded_entities.is_empty());
+        assert!(fader.materials.is_empty());
+    }
+}
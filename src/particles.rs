@@ -0,0 +1,242 @@
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use bevy_rapier3d::prelude::*;
+use rand::Rng;
+
+pub struct ParticlesPlugin;
+
+impl Plugin for ParticlesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ParticleImpact>()
+            .add_systems(Startup, setup_particle_assets)
+            .add_systems(Update, (emit_particles, update_particles));
+    }
+}
+
+#[derive(Component, Clone)]
+pub struct ParticleEmitter {
+    // Particles spawned once on the first update
+    pub burst: u32,
+    // Particles spawned per second afterwards
+    pub rate: f32,
+    pub lifetime: f32,
+    pub speed: f32,
+    pub direction: Vec3,
+    // Half-angle of the emission cone in radians (PI emits in every direction)
+    pub spread: f32,
+    pub gravity: f32,
+    pub size: f32,
+    pub color: Color,
+    // Half extents of the box around the emitter particles start in (zero emits from a point)
+    pub spawn_extent: Vec3,
+    // Speed of the random horizontal sway, e.g. for snowflakes
+    pub drift: f32,
+    // Particles below this height are culled before their lifetime runs out
+    pub kill_height: Option<f32>,
+    // Particles stop at the first collider they hit and send a ParticleImpact
+    pub report_impacts: bool,
+    // Emitter despawns itself after this many seconds
+    pub duration: Option<f32>,
+    pub elapsed: f32,
+    pub spawn_accumulator: f32,
+    pub material: Option<Handle<StandardMaterial>>,
+}
+
+impl Default for ParticleEmitter {
+    fn default() -> Self {
+        Self {
+            burst: 0,
+            rate: 0.0,
+            lifetime: 1.0,
+            speed: 1.0,
+            direction: Vec3::Y,
+            spread: std::f32::consts::PI,
+            gravity: 0.0,
+            size: 0.1,
+            color: Color::WHITE,
+            spawn_extent: Vec3::ZERO,
+            drift: 0.0,
+            kill_height: None,
+            report_impacts: false,
+            duration: None,
+            elapsed: 0.0,
+            spawn_accumulator: 0.0,
+            material: None,
+        }
+    }
+}
+
+impl ParticleEmitter {
+    // One-shot spherical burst, e.g. for explosions
+    pub fn burst(count: u32, speed: f32, lifetime: f32, color: Color) -> Self {
+        Self {
+            burst: count,
+            speed,
+            lifetime,
+            color,
+            gravity: 4.0,
+            size: 0.15,
+            duration: Some(lifetime),
+            ..default()
+        }
+    }
+}
+
+#[derive(Component)]
+struct Particle {
+    velocity: Vec3,
+    lifetime: f32,
+    gravity: f32,
+    drift: f32,
+    // Offsets each particle's sway so they don't move in lockstep
+    drift_phase: f32,
+    kill_height: Option<f32>,
+    // Emitter to credit impacts to, if it asked for them
+    impact_emitter: Option<Entity>,
+}
+
+// A particle from an emitter with report_impacts hit a collider
+#[derive(Event)]
+pub struct ParticleImpact {
+    pub emitter: Entity,
+    pub entity: Entity,
+    pub position: Vec3,
+    pub normal: Vec3,
+}
+
+// Sway speeds per second along x and z, out of step so the path isn't a straight line
+const DRIFT_FREQUENCY: Vec2 = Vec2::new(1.3, 0.9);
+
+#[derive(Resource)]
+struct ParticleAssets {
+    mesh: Handle<Mesh>,
+}
+
+fn setup_particle_assets(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
+    let mesh = meshes.add(Mesh::from(shape::UVSphere {
+        radius: 1.0,
+        sectors: 6,
+        stacks: 4,
+    }));
+    commands.insert_resource(ParticleAssets { mesh });
+}
+
+fn random_direction_in_cone(rng: &mut impl Rng, direction: Vec3, spread: f32) -> Vec3 {
+    let cos_theta = rng.gen_range(spread.cos()..=1.0);
+    let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
+    let phi = rng.gen_range(0.0..std::f32::consts::TAU);
+    let local = Vec3::new(sin_theta * phi.cos(), cos_theta, sin_theta * phi.sin());
+    Quat::from_rotation_arc(Vec3::Y, direction.normalize_or_zero()) * local
+}
+
+fn emit_particles(
+    mut commands: Commands,
+    particle_assets: Res<ParticleAssets>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut emitter_query: Query<(Entity, &GlobalTransform, &mut ParticleEmitter)>,
+    time: Res<Time>,
+) {
+    let mut rng = rand::thread_rng();
+
+    for (entity, transform, mut emitter) in emitter_query.iter_mut() {
+        let mut count = if emitter.elapsed == 0.0 { emitter.burst } else { 0 };
+        emitter.spawn_accumulator += emitter.rate * time.delta_seconds();
+        let continuous = emitter.spawn_accumulator.floor();
+        emitter.spawn_accumulator -= continuous;
+        count += continuous as u32;
+        emitter.elapsed += time.delta_seconds();
+
+        if count > 0 {
+            let color = emitter.color;
+            let material = emitter
+                .material
+                .get_or_insert_with(|| {
+                    materials.add(StandardMaterial {
+                        base_color: color,
+                        emissive: color,
+                        unlit: true,
+                        ..default()
+                    })
+                })
+                .clone();
+
+            for _ in 0..count {
+                let direction = random_direction_in_cone(&mut rng, emitter.direction, emitter.spread);
+                let speed = emitter.speed * rng.gen_range(0.5..=1.0);
+                let extent = emitter.spawn_extent;
+                let offset = Vec3::new(
+                    rng.gen_range(-extent.x..=extent.x),
+                    rng.gen_range(-extent.y..=extent.y),
+                    rng.gen_range(-extent.z..=extent.z),
+                );
+                commands.spawn((
+                    Particle {
+                        velocity: direction * speed,
+                        lifetime: emitter.lifetime * rng.gen_range(0.7..=1.0),
+                        gravity: emitter.gravity,
+                        drift: emitter.drift,
+                        drift_phase: rng.gen_range(0.0..std::f32::consts::TAU),
+                        kill_height: emitter.kill_height,
+                        impact_emitter: emitter.report_impacts.then_some(entity),
+                    },
+                    PbrBundle {
+                        mesh: particle_assets.mesh.clone(),
+                        material: material.clone(),
+                        transform: Transform::from_translation(transform.translation() + offset)
+                            .with_scale(Vec3::splat(emitter.size)),
+                        ..default()
+                    },
+                ));
+            }
+        }
+
+        if emitter.duration.is_some_and(|duration| emitter.elapsed >= duration) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn update_particles(
+    mut commands: Commands,
+    mut particle_query: Query<(Entity, &mut Particle, &mut Transform)>,
+    rapier_context: Res<RapierContext>,
+    mut impact_events: EventWriter<ParticleImpact>,
+    time: Res<Time>,
+) {
+    let dt = time.delta_seconds();
+    let elapsed = time.elapsed_seconds();
+    for (entity, mut particle, mut transform) in particle_query.iter_mut() {
+        particle.lifetime -= dt;
+        if particle.lifetime <= 0.0 {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        particle.velocity.y -= particle.gravity * dt;
+        let sway = (DRIFT_FREQUENCY * elapsed + particle.drift_phase).to_array().map(f32::sin);
+        let movement = (particle.velocity + Vec3::new(sway[0], 0.0, sway[1]) * particle.drift) * dt;
+
+        // Only particles that asked for impacts pay for a ray cast along this frame's movement
+        if let Some(emitter) = particle.impact_emitter {
+            let distance = movement.length();
+            if distance > 0.0 {
+                let hit = rapier_context.cast_ray_and_get_normal(transform.translation, movement / distance, distance, true, QueryFilter::exclude_dynamic().exclude_sensors());
+                if let Some((hit_entity, hit)) = hit {
+                    impact_events.send(ParticleImpact {
+                        emitter,
+                        entity: hit_entity,
+                        position: hit.point,
+                        normal: hit.normal,
+                    });
+                    commands.entity(entity).despawn();
+                    continue;
+                }
+            }
+        }
+
+        transform.translation += movement;
+        if particle.kill_height.is_some_and(|kill_height| transform.translation.y < kill_height) {
+            commands.entity(entity).despawn();
+        }
+    }
+}
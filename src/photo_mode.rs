@@ -0,0 +1,298 @@
+use bevy::prelude::*;
+use bevy::render::view::screenshot::ScreenshotManager;
+use bevy::time::Real;
+use bevy::window::PrimaryWindow;
+use bevy_rapier3d::prelude::*;
+use bevy_rapier3d::render::DebugRenderContext;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+use crate::camera::{math, CameraMode, CameraSettings, ThirdPersonCamera};
+use crate::camera_path::CameraPathPlayer;
+use crate::debug::DebugOverlay;
+use crate::input_map::{update_action_state, Action, InputMap, InputSnapshot};
+use crate::player::PlayerIndex;
+use crate::spectator::SpectatorMode;
+
+pub struct PhotoModePlugin;
+
+impl Plugin for PhotoModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PhotoMode>()
+            .add_systems(PreUpdate, capture_photo_input.after(update_action_state))
+            .add_systems(Update, (toggle_photo_mode, fly_photo_camera, take_screenshot).chain());
+    }
+}
+
+const SCREENSHOT_DIR: &str = "screenshots";
+// Radians of look per pixel of mouse motion at sensitivity 1, like first person
+const PHOTO_LOOK_SENSITIVITY: f32 = 0.002;
+const PHOTO_START_SPEED: f32 = 5.0;
+const PHOTO_MIN_SPEED: f32 = 0.5;
+const PHOTO_MAX_SPEED: f32 = 50.0;
+// Fly speed is multiplied by this per scroll line
+const PHOTO_SCROLL_FACTOR: f32 = 1.2;
+// Radians per second while a roll key is held
+const PHOTO_ROLL_SPEED: f32 = 1.0;
+// Degrees of field of view per second while a bracket key is held, and the limits
+const PHOTO_FOV_SPEED: f32 = 30.0;
+const PHOTO_MIN_FOV: f32 = 10.0;
+const PHOTO_MAX_FOV: f32 = 110.0;
+
+// Everything photo mode changes on the first player's camera, put back exactly on leaving
+struct SavedCamera {
+    transform: Transform,
+    mode: CameraMode,
+    distance: f32,
+    target_distance: f32,
+    current_rotation: f32,
+    freelook_yaw: f32,
+    freelook_pitch: f32,
+    yaw: f32,
+    pitch: f32,
+    fov: f32,
+}
+
+// Paused free-fly camera for taking pictures. While it's on, game time and physics stop and the
+// first player's camera is flown with WASD, E/Q for up and down, the mouse to look, the wheel for
+// speed, Z/C to roll and the bracket keys for field of view.
+#[derive(Resource)]
+pub struct PhotoMode {
+    saved: Option<SavedCamera>,
+    // This frame's input, taken before it's cleared so the player doesn't react to it
+    input: InputSnapshot,
+    speed: f32,
+    yaw: f32,
+    pitch: f32,
+    roll: f32,
+    // Overlays that were showing when it started, shown again when it ends
+    debug_overlay: bool,
+    debug_render: bool,
+    hidden_ui: Vec<(Entity, Visibility)>,
+}
+
+impl Default for PhotoMode {
+    fn default() -> Self {
+        Self {
+            saved: None,
+            input: InputSnapshot::default(),
+            speed: PHOTO_START_SPEED,
+            yaw: 0.0,
+            pitch: 0.0,
+            roll: 0.0,
+            debug_overlay: false,
+            debug_render: false,
+            hidden_ui: Vec::new(),
+        }
+    }
+}
+
+impl PhotoMode {
+    pub fn is_active(&self) -> bool {
+        self.saved.is_some()
+    }
+
+    fn pressed(&self, action: Action) -> bool {
+        self.input.pressed.contains(&action)
+    }
+
+    fn axis(&self, action: Action) -> f32 {
+        self.input.axes.iter().find(|(axis, _)| *axis == action).map_or(0.0, |(_, value)| *value)
+    }
+}
+
+// Same as a locked camera path: gameplay sees an empty frame, apart from the keys that leave photo
+// mode and take screenshots
+fn capture_photo_input(mut photo_mode: ResMut<PhotoMode>, mut input_map: ResMut<InputMap>) {
+    if !photo_mode.is_active() {
+        return;
+    }
+    photo_mode.input = input_map.snapshot();
+    let just_pressed = [Action::TogglePhotoMode, Action::Screenshot]
+        .into_iter()
+        .filter(|action| input_map.just_pressed(*action))
+        .collect();
+    input_map.apply_snapshot(&InputSnapshot {
+        just_pressed,
+        ..default()
+    });
+}
+
+fn toggle_photo_mode(
+    input_map: Res<InputMap>,
+    mut photo_mode: ResMut<PhotoMode>,
+    mut camera_query: Query<(&PlayerIndex, &mut ThirdPersonCamera, &mut Transform, &mut Projection)>,
+    mut ui_query: Query<(Entity, &mut Visibility), (With<Node>, Without<Parent>)>,
+    camera_path: Res<CameraPathPlayer>,
+    spectator: Res<SpectatorMode>,
+    mut time: ResMut<Time<Virtual>>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    mut debug_overlay: ResMut<DebugOverlay>,
+    debug_render: Option<ResMut<DebugRenderContext>>,
+) {
+    if !input_map.just_pressed(Action::TogglePhotoMode) {
+        return;
+    }
+    let Some((_, mut camera, mut transform, mut projection)) = camera_query.iter_mut().find(|(index, ..)| index.0 == 0) else {
+        return;
+    };
+
+    if let Some(saved) = photo_mode.saved.take() {
+        *transform = saved.transform;
+        camera.mode = saved.mode;
+        camera.distance = saved.distance;
+        camera.target_distance = saved.target_distance;
+        camera.current_rotation = saved.current_rotation;
+        camera.freelook_yaw = saved.freelook_yaw;
+        camera.freelook_pitch = saved.freelook_pitch;
+        camera.yaw = saved.yaw;
+        camera.pitch = saved.pitch;
+        if let Projection::Perspective(perspective) = projection.as_mut() {
+            perspective.fov = saved.fov;
+        }
+
+        time.unpause();
+        rapier_config.physics_pipeline_active = true;
+        debug_overlay.enabled = photo_mode.debug_overlay;
+        if let Some(mut debug_render) = debug_render {
+            debug_render.enabled = photo_mode.debug_render;
+        }
+        for (entity, shown) in std::mem::take(&mut photo_mode.hidden_ui) {
+            if let Ok((_, mut visibility)) = ui_query.get_mut(entity) {
+                *visibility = shown;
+            }
+        }
+        println!("=== PHOTO MODE OFF ===");
+        return;
+    }
+
+    // A camera path is already driving the camera, or it's not the one being shown
+    if camera_path.is_playing() || camera.camera_transition.is_some() || spectator.is_active() {
+        return;
+    }
+    let fov = match projection.as_ref() {
+        Projection::Perspective(perspective) => perspective.fov,
+        _ => camera.fov.to_radians(),
+    };
+    photo_mode.saved = Some(SavedCamera {
+        transform: *transform,
+        mode: camera.mode,
+        distance: camera.distance,
+        target_distance: camera.target_distance,
+        current_rotation: camera.current_rotation,
+        freelook_yaw: camera.freelook_yaw,
+        freelook_pitch: camera.freelook_pitch,
+        yaw: camera.yaw,
+        pitch: camera.pitch,
+        fov,
+    });
+    camera.mode = CameraMode::Photo;
+    // Start looking exactly where the camera was
+    let forward = transform.forward();
+    photo_mode.yaw = (-forward.x).atan2(-forward.z);
+    photo_mode.pitch = math::clamp_pitch(forward.y.clamp(-1.0, 1.0).asin(), math::MAX_PITCH);
+    photo_mode.roll = 0.0;
+    photo_mode.speed = PHOTO_START_SPEED;
+
+    time.pause();
+    rapier_config.physics_pipeline_active = false;
+    photo_mode.debug_overlay = std::mem::replace(&mut debug_overlay.enabled, false);
+    photo_mode.debug_render = debug_render.is_some_and(|mut debug_render| std::mem::replace(&mut debug_render.enabled, false));
+    photo_mode.hidden_ui.clear();
+    for (entity, mut visibility) in ui_query.iter_mut() {
+        if *visibility != Visibility::Hidden {
+            photo_mode.hidden_ui.push((entity, *visibility));
+            *visibility = Visibility::Hidden;
+        }
+    }
+    println!("=== PHOTO MODE ON ===");
+}
+
+// Runs on real time, since game time is paused
+fn fly_photo_camera(
+    mut photo_mode: ResMut<PhotoMode>,
+    mut camera_query: Query<(&PlayerIndex, &ThirdPersonCamera, &mut Transform, &mut Projection)>,
+    settings: Res<CameraSettings>,
+    time: Res<Time<Real>>,
+) {
+    if !photo_mode.is_active() {
+        return;
+    }
+    let Some((_, _, mut transform, mut projection)) = camera_query
+        .iter_mut()
+        .find(|(index, camera, ..)| index.0 == 0 && camera.mode == CameraMode::Photo)
+    else {
+        return;
+    };
+    let delta_seconds = time.delta_seconds();
+
+    let scroll = photo_mode.axis(Action::ZoomIn) - photo_mode.axis(Action::ZoomOut);
+    if scroll != 0.0 {
+        photo_mode.speed = (photo_mode.speed * PHOTO_SCROLL_FACTOR.powf(scroll)).clamp(PHOTO_MIN_SPEED, PHOTO_MAX_SPEED);
+        println!("Photo mode fly speed: {:.1}", photo_mode.speed);
+    }
+
+    let look = photo_mode.input.look_delta
+        * Vec2::new(settings.horizontal_sensitivity, settings.vertical_sensitivity)
+        * settings.invert()
+        * PHOTO_LOOK_SENSITIVITY;
+    photo_mode.yaw = math::wrap_angle(photo_mode.yaw - look.x);
+    photo_mode.pitch = math::clamp_pitch(photo_mode.pitch - look.y, math::MAX_PITCH);
+    let roll_input = photo_mode.pressed(Action::PhotoRollLeft) as i32 - photo_mode.pressed(Action::PhotoRollRight) as i32;
+    photo_mode.roll = math::wrap_angle(photo_mode.roll + roll_input as f32 * PHOTO_ROLL_SPEED * delta_seconds);
+    transform.rotation = math::yaw_pitch_rotation(photo_mode.yaw, photo_mode.pitch) * Quat::from_rotation_z(photo_mode.roll);
+
+    // Along the view, pitch included, with E and Q straight up and down
+    let mut movement = Vec3::ZERO;
+    if photo_mode.pressed(Action::MoveForward) {
+        movement += transform.forward();
+    }
+    if photo_mode.pressed(Action::MoveBack) {
+        movement -= transform.forward();
+    }
+    if photo_mode.pressed(Action::MoveRight) {
+        movement += transform.right();
+    }
+    if photo_mode.pressed(Action::MoveLeft) {
+        movement -= transform.right();
+    }
+    if photo_mode.pressed(Action::Interact) {
+        movement += Vec3::Y;
+    }
+    if photo_mode.pressed(Action::Dash) {
+        movement -= Vec3::Y;
+    }
+    transform.translation += movement.normalize_or_zero() * photo_mode.speed * delta_seconds;
+
+    let fov_input = photo_mode.pressed(Action::PhotoFovOut) as i32 - photo_mode.pressed(Action::PhotoFovIn) as i32;
+    if fov_input != 0 {
+        if let Projection::Perspective(perspective) = projection.as_mut() {
+            let fov = perspective.fov.to_degrees() + fov_input as f32 * PHOTO_FOV_SPEED * delta_seconds;
+            perspective.fov = fov.clamp(PHOTO_MIN_FOV, PHOTO_MAX_FOV).to_radians();
+        }
+    }
+}
+
+// Saves the window to screenshots/. In photo mode the overlays are already hidden, so the picture
+// is just the scene.
+fn take_screenshot(
+    input_map: Res<InputMap>,
+    window_query: Query<Entity, With<PrimaryWindow>>,
+    mut screenshot_manager: ResMut<ScreenshotManager>,
+) {
+    if !input_map.just_pressed(Action::Screenshot) {
+        return;
+    }
+    let Ok(window) = window_query.get_single() else {
+        return;
+    };
+    if let Err(err) = fs::create_dir_all(SCREENSHOT_DIR) {
+        println!("WARNING: Failed to create {}: {}", SCREENSHOT_DIR, err);
+        return;
+    }
+    let stamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_millis());
+    let path = format!("{}/screenshot_{}.png", SCREENSHOT_DIR, stamp);
+    match screenshot_manager.save_screenshot_to_disk(window, &path) {
+        Ok(()) => println!("Saved screenshot to {}", path),
+        Err(err) => println!("WARNING: Failed to take screenshot: {}", err),
+    }
+}
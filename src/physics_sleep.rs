@@ -0,0 +1,130 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use crate::input_map::{Action, InputMap};
+
+pub struct PhysicsSleepPlugin;
+
+impl Plugin for PhysicsSleepPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SleepOverlay>()
+            .add_event::<BodyWakeEvent>()
+            .add_systems(Update, (track_body_sleep, toggle_sleep_overlay, update_sleep_overlay).chain());
+    }
+}
+
+const SLEEPING_COLOR: Color = Color::rgb(0.2, 0.4, 1.0);
+const AWAKE_COLOR: Color = Color::rgb(1.0, 0.55, 0.1);
+
+// Sent when Rapier wakes a dynamic body that was asleep, e.g. because something hit it or an
+// explosion pushed it. Nothing reads it yet.
+#[derive(Event)]
+#[allow(dead_code)]
+pub struct BodyWakeEvent {
+    pub entity: Entity,
+}
+
+// Whether Rapier had a dynamic body asleep last frame
+#[derive(Component)]
+pub struct SleepState {
+    pub sleeping: bool,
+}
+
+// K tints every dynamic body blue while it's asleep and orange while it's awake
+#[derive(Resource, Default)]
+pub struct SleepOverlay {
+    pub enabled: bool,
+}
+
+// A body shown by the overlay: its own material, put back when the overlay is turned off, and the
+// copy it wears meanwhile
+#[derive(Component)]
+struct SleepTint {
+    original: Handle<StandardMaterial>,
+    tinted: Handle<StandardMaterial>,
+}
+
+fn track_body_sleep(
+    mut commands: Commands,
+    mut body_query: Query<(Entity, &RigidBody, &RapierRigidBodyHandle, Option<&mut SleepState>)>,
+    rapier_context: Res<RapierContext>,
+    mut wake_events: EventWriter<BodyWakeEvent>,
+) {
+    for (entity, body, handle, state) in body_query.iter_mut() {
+        if *body != RigidBody::Dynamic {
+            continue;
+        }
+        let sleeping = rapier_context.bodies.get(handle.0).is_some_and(|body| body.is_sleeping());
+        match state {
+            Some(mut state) => {
+                if state.sleeping && !sleeping {
+                    wake_events.send(BodyWakeEvent { entity });
+                }
+                if state.sleeping != sleeping {
+                    state.sleeping = sleeping;
+                }
+            }
+            None => {
+                commands.entity(entity).insert(SleepState { sleeping });
+            }
+        }
+    }
+}
+
+// Turning the overlay off puts every body's own material back and drops the copies
+fn toggle_sleep_overlay(
+    mut commands: Commands,
+    input_map: Res<InputMap>,
+    mut overlay: ResMut<SleepOverlay>,
+    mut tinted_query: Query<(Entity, &SleepTint, &mut Handle<StandardMaterial>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !input_map.just_pressed(Action::ToggleSleepOverlay) {
+        return;
+    }
+    overlay.enabled = !overlay.enabled;
+    println!("Physics sleep overlay {}", if overlay.enabled { "on" } else { "off" });
+    if overlay.enabled {
+        return;
+    }
+    for (entity, tint, mut material) in tinted_query.iter_mut() {
+        *material = tint.original.clone();
+        materials.remove(&tint.tinted);
+        commands.entity(entity).remove::<SleepTint>();
+    }
+}
+
+fn update_sleep_overlay(
+    mut commands: Commands,
+    overlay: Res<SleepOverlay>,
+    mut body_query: Query<(Entity, &SleepState, &mut Handle<StandardMaterial>, Option<&SleepTint>)>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    if !overlay.enabled {
+        return;
+    }
+    for (entity, state, mut material, tint) in body_query.iter_mut() {
+        let color = if state.sleeping { SLEEPING_COLOR } else { AWAKE_COLOR };
+        match tint {
+            Some(tint) => {
+                if let Some(tinted) = materials.get_mut(&tint.tinted) {
+                    if tinted.base_color != color {
+                        tinted.base_color = color;
+                    }
+                }
+            }
+            // A copy of its own, so bodies sharing a material don't all change together
+            None => {
+                let Some(mut copy) = materials.get(&*material).cloned() else {
+                    continue;
+                };
+                copy.base_color = color;
+                let tinted = materials.add(copy);
+                commands.entity(entity).insert(SleepTint {
+                    original: material.clone(),
+                    tinted: tinted.clone(),
+                });
+                *material = tinted;
+            }
+        }
+    }
+}
@@ -1,106 +1,469 @@
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 use bevy::prelude::shape;
-use crate::camera::ThirdPersonCamera;
+use std::collections::HashMap;
+use crate::camera::{camera_follow, math as camera_math, CameraMode, CameraTargetable, ThirdPersonCamera};
+use crate::carry::{Carrying, CARRY_SPEED_MULTIPLIER};
+use crate::combat::{Health, Invulnerability, Weapon};
+use crate::conveyor::ConveyorBelt;
+use crate::debug::{debug_overlay_enabled, DebugLogTimers, Noclip, DEBUG_LOG_TARGET};
+use crate::grapple::Grappling;
+use crate::health::{Dead, RespawnPoint};
+use crate::input_map::{Action, InputMap};
+use crate::status_effects::StatusEffects;
+use crate::terrain::{SpawnPointRegistry, SurfaceType};
 
 pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_player)
+        app.insert_resource(PlayerSettings {
+            local_players: local_players_from_args(),
+            ..default()
+        })
+            .init_resource::<Players>()
+            .add_event::<PlayerLanded>()
+            .add_event::<Footstep>()
+            // Spawn points are registered by the terrain in PostStartup
+            .add_systems(PostStartup, spawn_player.after(crate::terrain::collect_spawn_points))
             .add_systems(Update, player_movement)
-            .add_systems(Update, update_camera_target)
+            .add_systems(Update, keep_player_upright.after(player_movement))
+            .add_systems(PostUpdate, clamp_fall_speed.after(PhysicsSet::Writeback))
+            .add_systems(Update, update_camera_target.before(camera_follow))
             .add_systems(Update, ground_detection)
-            .add_systems(Update, debug_player_state);
+            .add_systems(Update, footstep_system.after(ground_detection))
+            .add_systems(Update, update_surface_friction.after(ground_detection))
+            .add_systems(Update, player_orientation_system.after(ground_detection).after(keep_player_upright))
+            .add_systems(Update, debug_player_state.run_if(debug_overlay_enabled));
     }
 }
 
+// The player sits in its own collision group so carried objects can ignore it
+pub const PLAYER_COLLISION_GROUP: Group = Group::GROUP_2;
+
 #[derive(Component)]
 pub struct Player {
     pub speed: f32,
+    pub sprint_multiplier: f32,
     pub jump_force: f32,
-    pub on_ground: bool,
+    pub ground: GroundState,
+    // Surfaces steeper than this (radians from horizontal) can't be stood on
+    pub max_slope_angle: f32,
     pub rotation_speed: f32,
+    // Fastest downward speed reached since leaving the ground
+    pub fall_speed: f32,
+    // Body the ground ray hit this frame
+    pub ground_entity: Option<Entity>,
+    // Velocity inherited from whatever the player is standing on, kept while airborne
+    pub platform_velocity: Vec3,
+    // Fraction of its ground speed an idle grounded player keeps after one second
+    pub friction_per_second: f32,
+    // What the ground was made of when last touched, kept while airborne
+    pub last_surface: Option<SurfaceType>,
+    // Tilt the model to stand square on sloped ground. Only the model tilts; the capsule stays
+    // upright.
+    pub align_to_surface: bool,
+    // Normal of the ground under the player this frame, straight up while airborne
+    pub surface_normal: Vec3,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GroundState {
+    Grounded,
+    // Standing on a surface steeper than max_slope_angle
+    Sliding { normal: Vec3 },
+    Airborne,
+}
+
+// Which local player an entity belongs to; joins each player to its camera and input
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlayerIndex(pub usize);
+
+// Child entity holding the player's visible mesh, hidden in first person
+#[derive(Component)]
+pub struct PlayerModel;
+
+impl Default for Player {
+    fn default() -> Self {
+        Self {
+            speed: 8.0,
+            sprint_multiplier: 1.5,
+            jump_force: 12.0,
+            ground: GroundState::Airborne,
+            max_slope_angle: 45_f32.to_radians(),
+            rotation_speed: 10.0,
+            fall_speed: 0.0,
+            ground_entity: None,
+            platform_velocity: Vec3::ZERO,
+            // What 0.9 per frame at 60 fps used to give
+            friction_per_second: 0.9_f32.powi(60),
+            last_surface: None,
+            align_to_surface: true,
+            surface_normal: Vec3::Y,
+        }
+    }
+}
+
+impl Player {
+    // Touching something, walkable or not
+    pub fn touching_ground(&self) -> bool {
+        self.ground != GroundState::Airborne
+    }
+
+    pub fn on_ice(&self) -> bool {
+        self.touching_ground() && self.last_surface == Some(SurfaceType::Ice)
+    }
+
+    // Ground speed the input is scaled to. Sprinting isn't available while carrying.
+    pub fn target_speed(&self, carrying: bool, sprinting: bool) -> f32 {
+        if carrying {
+            self.speed * CARRY_SPEED_MULTIPLIER
+        } else if sprinting {
+            self.speed * self.sprint_multiplier
+        } else {
+            self.speed
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct PlayerSettings {
+    pub max_health: f32,
+    // Landings slower than this deal no damage
+    pub safe_landing_speed: f32,
+    // Damage = fall_damage_scale * (impact_speed - safe_landing_speed) ^ fall_damage_exponent
+    pub fall_damage_scale: f32,
+    pub fall_damage_exponent: f32,
+    pub invulnerability_duration: f32,
+    pub kill_height: f32,
+    pub respawn_delay: f32,
+    // Terminal velocity; downward speed never exceeds this (upward is untouched)
+    pub max_fall_speed: f32,
+    // Split-screen players on this machine, set with `--players <n>`
+    pub local_players: usize,
+}
+
+impl Default for PlayerSettings {
+    fn default() -> Self {
+        Self {
+            max_health: 100.0,
+            safe_landing_speed: 12.0,
+            fall_damage_scale: 4.0,
+            fall_damage_exponent: 1.5,
+            invulnerability_duration: 1.0,
+            kill_height: -30.0,
+            respawn_delay: 1.0,
+            max_fall_speed: 40.0,
+            local_players: 1,
+        }
+    }
+}
+
+impl PlayerSettings {
+    pub fn fall_damage(&self, impact_speed: f32) -> f32 {
+        let excess = impact_speed - self.safe_landing_speed;
+        if excess <= 0.0 {
+            return 0.0;
+        }
+        self.fall_damage_scale * excess.powf(self.fall_damage_exponent)
+    }
+}
+
+#[derive(Event)]
+pub struct PlayerLanded {
+    pub player: Entity,
+    pub impact_speed: f32,
+}
+
+// Sent each time a foot comes down while walking on the ground
+#[derive(Event)]
+pub struct Footstep {
+    pub player: Entity,
+    // Under the foot at the capsule's center height
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub left: bool,
 }
 
+// Ground distance covered between footsteps
+const STRIDE_LENGTH: f32 = 0.9;
+// Sideways distance of each foot from the capsule's center
+const FOOT_OFFSET: f32 = 0.2;
+
+// Ground is found by dropping a ball from the centre of the capsule's bottom hemisphere. It's a
+// touch smaller than the capsule so it starts clear of whatever the capsule rests on, and reaches
+// a little below the capsule's bottom. A ball rather than a ray so slopes too steep to stand on,
+// which the capsule only touches well off to one side, are still found.
+const GROUND_PROBE_OFFSET: f32 = 1.0;
+const GROUND_PROBE_RADIUS: f32 = 0.45;
+const GROUND_PROBE_DISTANCE: f32 = 0.15;
+
+// How much input still steers while sliding, relative to running
+const SLIDE_STEER: f32 = 0.2;
+// Extra acceleration down the slope on top of gravity
+const SLIDE_ACCELERATION: f32 = 12.0;
+// Sideways push off the slope when jumping out of a slide
+const SLIDE_JUMP_PUSH: f32 = 6.0;
+// Share of the excess over run speed still left after one second, when moving faster than it
+// (e.g. off the end of a slide). Matches the old 10% per frame blend at 60 fps.
+const OVERSPEED_REMAINING_PER_SECOND: f32 = 0.0018;
+
+// Facing is driven through the transform, so physics spin is only ever noise
+const MAX_PLAYER_ANGULAR_SPEED: f32 = 2.0;
+// Tilt (radians) the upright safety net tolerates before snapping back
+const MAX_PLAYER_TILT: f32 = 0.001;
+// Tilt past which the snap is worth a debug line; below it it's just solver noise
+const LOGGED_PLAYER_TILT: f32 = 1.0 * std::f32::consts::PI / 180.0;
+
+// How quickly the inherited platform velocity may change, so stepping between
+// platforms moving in different directions doesn't jolt the player
+const PLATFORM_VELOCITY_ACCELERATION: f32 = 60.0;
+
+// How quickly the model tilts to match the ground, and the steepest ground it tilts for, past
+// which it would look like it was tipping over
+const SURFACE_ALIGN_SPEED: f32 = 10.0;
+const MAX_SURFACE_ALIGN_ANGLE: f32 = 50.0 * std::f32::consts::PI / 180.0;
+// The bottom of the capsule, relative to its centre, which the model tilts about so its feet
+// stay on the ground
+const MODEL_FEET: Vec3 = Vec3::new(0.0, -1.5, 0.0);
+
+// On ice an idle player keeps what 0.99 per frame at 60 fps gives, instead of friction_per_second
+const ICE_FRICTION_PER_SECOND: f32 = 0.5472;
+// Contact friction while on ice. Min makes it win over whatever the ice collider says.
+const ICE_FRICTION: Friction = Friction {
+    coefficient: 0.02,
+    combine_rule: CoefficientCombineRule::Min,
+};
+
+// Used when the level defines no spawn points
+pub const DEFAULT_SPAWN_POSITION: Vec3 = Vec3::new(0.0, 2.0, 0.0);
+
+// Split-screen supports up to one keyboard and mouse player and one gamepad player
+pub const MAX_LOCAL_PLAYERS: usize = 2;
+// Sideways spacing between local players at the spawn point
+const LOCAL_PLAYER_SPACING: f32 = 2.0;
+const PLAYER_COLORS: [Color; MAX_LOCAL_PLAYERS] = [Color::rgb(0.8, 0.2, 0.2), Color::rgb(0.2, 0.3, 0.8)];
+
+// Local players in index order
 #[derive(Resource, Default)]
-pub struct PlayerEntity(Option<Entity>);
+pub struct Players(pub Vec<Entity>);
+
+// Where a local player stands relative to a spawn point, so players spawning together don't overlap
+pub fn local_player_offset(index: usize) -> Vec3 {
+    Vec3::X * LOCAL_PLAYER_SPACING * index as f32
+}
 
-fn spawn_player(
+fn local_players_from_args() -> usize {
+    let args: Vec<String> = std::env::args().collect();
+    let Some(index) = args.iter().position(|arg| arg == "--players") else {
+        return 1;
+    };
+    match args.get(index + 1).and_then(|count| count.parse::<usize>().ok()) {
+        Some(count) if (1..=MAX_LOCAL_PLAYERS).contains(&count) => count,
+        _ => {
+            println!("WARNING: --players takes 1 to {}, starting with 1", MAX_LOCAL_PLAYERS);
+            1
+        }
+    }
+}
+
+pub fn spawn_player(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    settings: Res<PlayerSettings>,
+    spawn_points: Res<SpawnPointRegistry>,
+    mut respawn_point: ResMut<RespawnPoint>,
+    mut players: ResMut<Players>,
+    mut input_map: ResMut<InputMap>,
 ) {
-    println!("=== SPAWNING PLAYER ===");
-    
-    // Spawn player
+
+    let spawn_position = match spawn_points.best() {
+        Some((id, position)) => {
+            println!("Spawning at {:?}", id);
+            respawn_point.respawn_at(&spawn_points, id);
+            position
+        }
+        None => {
+            println!("No spawn points registered, spawning at {:?}", DEFAULT_SPAWN_POSITION);
+            DEFAULT_SPAWN_POSITION
+        }
+    };
+
+    players.0 = (0..settings.local_players)
+        .map(|index| {
+            let transform = Transform::from_translation(spawn_position + local_player_offset(index));
+            spawn_player_at(&mut commands, &mut meshes, &mut materials, &settings, index, transform)
+        })
+        .collect();
+    input_map.set_split_devices(settings.local_players > 1);
+}
+
+pub fn spawn_player_at(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    settings: &PlayerSettings,
+    index: usize,
+    transform: Transform,
+) -> Entity {
+    println!("=== SPAWNING PLAYER {} ===", index + 1);
+
     let player_entity = commands.spawn((
-        Player {
-            speed: 8.0,
-            jump_force: 12.0,
-            on_ground: false,
-            rotation_speed: 10.0,
-        },
+        PlayerIndex(index),
+        Player::default(),
+        Health::new(settings.max_health),
+        Invulnerability::new(settings.invulnerability_duration),
         RigidBody::Dynamic,
+        // Collisions must never tip the capsule over; yaw stays free for facing
+        LockedAxes::ROTATION_LOCKED_X | LockedAxes::ROTATION_LOCKED_Z,
         Collider::capsule_y(1.0, 0.5),
+        // Swapped for ICE_FRICTION while standing on ice
+        Friction::default(),
+        // Falling at terminal velocity would otherwise tunnel through the thin grass layer
+        Ccd::enabled(),
+        CollisionGroups::new(PLAYER_COLLISION_GROUP, Group::ALL),
         Velocity::zero(),
-        // Visual representation
-        PbrBundle {
-            mesh: meshes.add(Mesh::from(shape::Capsule {
-                radius: 0.5,
-                depth: 2.0,
-                ..default()
-            })),
-            material: materials.add(StandardMaterial {
-                base_color: Color::rgb(0.8, 0.2, 0.2),
+        Weapon::default(),
+        StatusEffects::default(),
+        TransformBundle::from_transform(transform),
+        VisibilityBundle::default(),
+    )).insert(CameraTargetable {
+        name: format!("Player {}", index + 1),
+    }).with_children(|parent| {
+        // Visual representation, kept separate from the body so it can be hidden
+        parent.spawn((
+            PlayerModel,
+            PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Capsule {
+                    radius: 0.5,
+                    depth: 2.0,
+                    ..default()
+                })),
+                material: materials.add(StandardMaterial {
+                    base_color: PLAYER_COLORS[index % MAX_LOCAL_PLAYERS],
+                    ..default()
+                }),
                 ..default()
-            }),
-            transform: Transform::from_xyz(0.0, 2.0, 0.0),
-            ..default()
-        },
-    )).id();
+            },
+        ));
+    }).id();
     
     println!("Player spawned with entity ID: {:?}", player_entity);
-    
-    // Store the player entity in a resource
-    commands.insert_resource(PlayerEntity(Some(player_entity)));
-    println!("Player entity stored in resource");
+    player_entity
 }
 
-fn update_camera_target(
-    player_entity: Res<PlayerEntity>,
-    mut camera_query: Query<&mut ThirdPersonCamera>,
+// Points each camera at its player to begin with, and back at them if whatever it was following
+// is despawned. Any other target set in between is left alone.
+pub fn update_camera_target(
+    players: Res<Players>,
+    mut camera_query: Query<(&PlayerIndex, &mut ThirdPersonCamera)>,
+    target_query: Query<(), With<Transform>>,
 ) {
-    if let Some(entity) = player_entity.0 {
-        if let Ok(mut camera) = camera_query.get_single_mut() {
-            if camera.target == Entity::PLACEHOLDER {
-                camera.target = entity;
-                println!("Camera target set to player entity: {:?}", entity);
-            }
+    for (index, mut camera) in camera_query.iter_mut() {
+        let unset = camera.target == Entity::PLACEHOLDER;
+        if !unset && target_query.contains(camera.target) {
+            continue;
+        }
+        let Some(&entity) = players.0.get(index.0) else {
+            continue;
+        };
+        if unset {
+            camera.target = entity;
+            println!("Camera {} target set to player entity: {:?}", index.0 + 1, entity);
+        } else if camera.target != entity {
+            println!("Camera {} target {:?} is gone, back to player entity: {:?}", index.0 + 1, camera.target, entity);
+            camera.set_camera_target(entity);
         }
     }
 }
 
-fn player_movement(
-    keyboard_input: Res<Input<KeyCode>>,
-    mut player_query: Query<(&mut Player, &mut Transform, &mut Velocity)>,
-    camera_query: Query<&ThirdPersonCamera>,
+pub fn player_movement(
+    input_map: Res<InputMap>,
+    mut player_query: Query<(&PlayerIndex, &mut Player, &mut Transform, &mut Velocity, Option<&StatusEffects>, Has<Carrying>, Has<Dead>, Has<Noclip>, Has<Grappling>)>,
+    ground_query: Query<(&RigidBody, &Velocity, &GlobalTransform), Without<Player>>,
+    conveyor_query: Query<&ConveyorBelt>,
+    camera_query: Query<(&PlayerIndex, &ThirdPersonCamera)>,
     time: Res<Time>,
 ) {
-    if let Ok((mut player, mut transform, mut velocity)) = player_query.get_single_mut() {
+    for (index, mut player, mut transform, mut velocity, effects, carrying, dead, noclip, grappling) in player_query.iter_mut() {
+        // Input is frozen while dead, and the noclip fly controls or the grapple rope take over
+        // while they're in use
+        if dead || noclip || grappling {
+            continue;
+        }
+        let input = input_map.for_player(index.0);
+        let camera = camera_query
+            .iter()
+            .find(|(camera_index, _)| *camera_index == index)
+            .map(|(_, camera)| camera);
+
+        // Pick up the motion of whatever we're standing on. Airborne players keep
+        // the last value so jumping off a moving platform carries its momentum.
+        if player.touching_ground() {
+            let mut target_platform_velocity = Vec3::ZERO;
+            if let Some((body, ground_velocity, ground_transform)) = player.ground_entity.and_then(|entity| ground_query.get(entity).ok()) {
+                if matches!(body, RigidBody::KinematicPositionBased | RigidBody::KinematicVelocityBased) {
+                    target_platform_velocity = platform_point_velocity(ground_velocity, ground_transform.translation(), transform.translation);
+                    // Spinning platforms turn the player along with them
+                    transform.rotate_y(ground_velocity.angvel.y * time.delta_seconds());
+                }
+            }
+            // Conveyor belts are fixed, so their motion is only in how fast they run
+            if let Some(belt) = player.ground_entity.and_then(|entity| conveyor_query.get(entity).ok()) {
+                target_platform_velocity = belt.velocity();
+            }
+            let max_change = PLATFORM_VELOCITY_ACCELERATION * time.delta_seconds();
+            let change = (target_platform_velocity - player.platform_velocity).clamp_length_max(max_change);
+            player.platform_velocity += change;
+        }
+        let platform_velocity = player.platform_velocity;
+
+        // In first person the body always faces where the camera looks, so aiming,
+        // carrying and interacting follow the view
+        let first_person_yaw = camera
+            .filter(|camera| camera.mode == CameraMode::FirstPerson)
+            .map(|camera| camera.yaw);
+        if let Some(yaw) = first_person_yaw {
+            transform.rotation = Quat::from_rotation_y(yaw + std::f32::consts::PI);
+        }
+        let first_person = first_person_yaw.is_some();
+
+        // How far to turn towards the way the player should face this frame. Past 1 the slerp
+        // would swing beyond the target, at a high rotation speed or on a long frame.
+        let turn = (player.rotation_speed * time.delta_seconds()).clamp(0.0, 1.0);
+
+        // Locked on, the player keeps facing the target and A/D strafe around it
+        let lock_point = camera
+            .filter(|camera| camera.mode == CameraMode::ThirdPerson && camera.lock_on.is_some())
+            .map(|camera| camera.lock_point);
+        if let Some(to_target) = lock_point.and_then(|point| ((point - transform.translation) * Vec3::new(1.0, 0.0, 1.0)).try_normalize()) {
+            if let Some(target_rotation) = camera_math::facing_rotation(to_target) {
+                transform.rotation = transform.rotation.slerp(target_rotation, turn);
+            }
+        }
+
+        // Aiming in third person, the player faces where the camera looks and strafes
+        let aim_direction = camera
+            .filter(|camera| camera.mode == CameraMode::ThirdPerson && camera.aiming && camera.lock_on.is_none())
+            .map(|camera| -camera_math::orbit_direction(camera.current_rotation + camera.freelook_yaw, 0.0));
+        if let Some(target_rotation) = aim_direction.and_then(camera_math::facing_rotation) {
+            transform.rotation = transform.rotation.slerp(target_rotation, turn);
+        }
+
         let mut movement = Vec3::ZERO;
         
         // WASD movement
-        if keyboard_input.pressed(KeyCode::W) {
+        if input.pressed(Action::MoveForward) {
             movement.z -= 1.0;
         }
-        if keyboard_input.pressed(KeyCode::S) {
+        if input.pressed(Action::MoveBack) {
             movement.z += 1.0;
         }
-        if keyboard_input.pressed(KeyCode::A) {
+        if input.pressed(Action::MoveLeft) {
             movement.x -= 1.0;
         }
-        if keyboard_input.pressed(KeyCode::D) {
+        if input.pressed(Action::MoveRight) {
             movement.x += 1.0;
         }
         
@@ -109,11 +472,7 @@ fn player_movement(
             movement = movement.normalize();
             
             // Get camera rotation to align movement with camera view
-            let camera_rotation = if let Ok(camera) = camera_query.get_single() {
-                camera.current_rotation
-            } else {
-                0.0
-            };
+            let camera_rotation = camera.map_or(0.0, |camera| camera.movement_rotation());
             
             // Rotate movement based on camera rotation
             let cos_rot = camera_rotation.cos();
@@ -124,77 +483,603 @@ fn player_movement(
                 movement.x * sin_rot + movement.z * cos_rot,
             );
             
-            // Apply movement to velocity
-            let target_velocity = rotated_movement * player.speed;
-            velocity.linvel.x = target_velocity.x;
-            velocity.linvel.z = target_velocity.z;
+            // Apply movement to velocity, sped up while sprinting or slowed down while carrying
+            // Stuns, freezes and slows hold the player back
+            let speed = player.target_speed(carrying, input.pressed(Action::Sprint)) * effects.map_or(1.0, StatusEffects::speed_multiplier);
+            let target_velocity = rotated_movement * speed;
+            let current = Vec3::new(velocity.linvel.x - platform_velocity.x, 0.0, velocity.linvel.z - platform_velocity.z);
+            let horizontal = if matches!(player.ground, GroundState::Sliding { .. }) {
+                // Sliding only lets the input nudge the slide
+                current + target_velocity * SLIDE_STEER * time.delta_seconds()
+            } else if current.length() > speed {
+                // Keep momentum from slides and launches, easing down to the run speed
+                target_velocity.lerp(current, OVERSPEED_REMAINING_PER_SECOND.powf(time.delta_seconds()))
+            } else {
+                target_velocity
+            };
+            velocity.linvel.x = horizontal.x + platform_velocity.x;
+            velocity.linvel.z = horizontal.z + platform_velocity.z;
             
-            // Update player rotation to face movement direction
-            let target_rotation = Quat::from_rotation_arc(Vec3::Z, rotated_movement);
-            transform.rotation = transform.rotation.slerp(target_rotation, player.rotation_speed * time.delta_seconds());
-        } else {
-            // Apply friction when not moving
-            velocity.linvel.x *= 0.9;
-            velocity.linvel.z *= 0.9;
+            // Update player rotation to face movement direction (first person and aiming face the
+            // view and lock-on the target instead)
+            if !first_person && lock_point.is_none() && aim_direction.is_none() {
+                if let Some(target_rotation) = camera_math::facing_rotation(rotated_movement) {
+                    transform.rotation = transform.rotation.slerp(target_rotation, turn);
+                }
+            }
+        } else if player.ground == GroundState::Grounded {
+            // Apply friction when not moving, settling to the platform's velocity. Airborne
+            // players keep their momentum, e.g. after letting go of the grapple.
+            // Decays per second rather than per frame so the frame rate doesn't change how far
+            // the player slides.
+            let friction_per_second = if player.on_ice() { ICE_FRICTION_PER_SECOND } else { player.friction_per_second };
+            let remaining = friction_per_second.powf(time.delta_seconds());
+            velocity.linvel.x = platform_velocity.x + (velocity.linvel.x - platform_velocity.x) * remaining;
+            velocity.linvel.z = platform_velocity.z + (velocity.linvel.z - platform_velocity.z) * remaining;
         }
-        
-        // Jump
-        if keyboard_input.just_pressed(KeyCode::Space) && player.on_ground {
-            velocity.linvel.y = player.jump_force;
-            player.on_ground = false;
+
+        // Slopes too steep to stand on pull the player down along the surface
+        if let GroundState::Sliding { normal } = player.ground {
+            let downhill = (Vec3::NEG_Y - normal * Vec3::NEG_Y.dot(normal)).normalize_or_zero();
+            velocity.linvel += downhill * SLIDE_ACCELERATION * time.delta_seconds();
         }
         
-        // Sprint
-        if keyboard_input.pressed(KeyCode::ShiftLeft) && movement.length() > 0.0 {
-            velocity.linvel.x *= 1.5;
-            velocity.linvel.z *= 1.5;
+        // Jump, keeping any upward motion of the platform. Jumping out of a slide pushes
+        // away from the slope instead of straight up it.
+        if input.just_pressed(Action::Jump) && effects.is_none_or(StatusEffects::can_jump) {
+            match player.ground {
+                GroundState::Grounded => {
+                    velocity.linvel.y = player.jump_force + platform_velocity.y.max(0.0);
+                    player.ground = GroundState::Airborne;
+                }
+                GroundState::Sliding { normal } => {
+                    let push = Vec3::new(normal.x, 0.0, normal.z).normalize_or_zero() * SLIDE_JUMP_PUSH;
+                    velocity.linvel.x += push.x;
+                    velocity.linvel.z += push.z;
+                    velocity.linvel.y = player.jump_force;
+                    player.ground = GroundState::Airborne;
+                }
+                GroundState::Airborne => {}
+            }
         }
-    } else {
-        println!("ERROR: No player found in movement system!");
+
+        // Only yaw is unlocked, and even that shouldn't build up spin from bumps
+        velocity.angvel = Vec3::new(0.0, velocity.angvel.y.clamp(-MAX_PLAYER_ANGULAR_SPEED, MAX_PLAYER_ANGULAR_SPEED), 0.0);
     }
 }
 
+// Runs every frame right after the physics step, so gravity can't build up unbounded speed and
+// nothing reading the velocity, like the landing speed, ever sees more than the clamp
+fn clamp_fall_speed(
+    mut player_query: Query<&mut Velocity, With<Player>>,
+    settings: Res<PlayerSettings>,
+) {
+    for mut velocity in player_query.iter_mut() {
+        if velocity.linvel.y < -settings.max_fall_speed {
+            velocity.linvel.y = -settings.max_fall_speed;
+        }
+    }
+}
+
+// Safety net on top of the locked axes: strips any tilt that still sneaks in, keeping the yaw
+fn keep_player_upright(mut player_query: Query<(&mut Transform, &mut Velocity), With<Player>>) {
+    for (mut transform, mut velocity) in player_query.iter_mut() {
+        velocity.angvel.x = 0.0;
+        velocity.angvel.z = 0.0;
+        let tilt = transform.up().angle_between(Vec3::Y);
+        if tilt > MAX_PLAYER_TILT {
+            let (yaw, _, _) = transform.rotation.to_euler(EulerRot::YXZ);
+            transform.rotation = Quat::from_rotation_y(yaw);
+            if tilt > LOGGED_PLAYER_TILT {
+                debug!(target: DEBUG_LOG_TARGET, "Player capsule was tilted {:.1} degrees, snapped back upright", tilt.to_degrees());
+            }
+        }
+    }
+}
+
+// Velocity of a platform's surface at `point`, including the sweep from its rotation
+fn platform_point_velocity(platform_velocity: &Velocity, platform_center: Vec3, point: Vec3) -> Vec3 {
+    let offset = point - platform_center;
+    let offset = Vec3::new(offset.x, 0.0, offset.z);
+    platform_velocity.linvel + platform_velocity.angvel.cross(offset)
+}
+
 fn ground_detection(
-    mut player_query: Query<(&mut Player, &Transform)>,
+    mut player_query: Query<(Entity, &mut Player, &Transform, &Velocity), Without<Noclip>>,
+    surface_query: Query<&SurfaceType>,
     rapier_context: Res<RapierContext>,
+    mut landed_events: EventWriter<PlayerLanded>,
 ) {
-    if let Ok((mut player, transform)) = player_query.get_single_mut() {
-        let ray_origin = transform.translation;
-        let ray_dir = Vec3::Y * -1.0;
-        let max_distance = 1.1; // Slightly more than player height
-        
-        if let Some((_entity, toi)) = rapier_context.cast_ray(ray_origin, ray_dir, max_distance, true, QueryFilter::default()) {
-            if toi < max_distance {
-                player.on_ground = true;
+    for (entity, mut player, transform, velocity) in player_query.iter_mut() {
+        let probe_origin = transform.translation - Vec3::Y * GROUND_PROBE_OFFSET;
+        // Skip the player's own capsule, which the probe starts inside of
+        let filter = QueryFilter::default().exclude_collider(entity).exclude_sensors();
+        let was_touching_ground = player.touching_ground();
+        let probe = Collider::ball(GROUND_PROBE_RADIUS);
+        let hit = rapier_context.cast_shape(probe_origin, Quat::IDENTITY, Vec3::NEG_Y, &probe, GROUND_PROBE_DISTANCE, true, filter);
+
+        if let Some((ground_entity, toi)) = hit {
+            // Already overlapping the ground gives no normal; that only happens standing on it
+            let normal = toi.details.map_or(Vec3::Y, |details| details.normal1);
+            let slope_angle = normal.angle_between(Vec3::Y);
+            player.surface_normal = normal;
+            player.ground = if slope_angle > player.max_slope_angle {
+                GroundState::Sliding { normal }
             } else {
-                player.on_ground = false;
+                GroundState::Grounded
+            };
+            player.ground_entity = Some(ground_entity);
+            // Surfaces are tagged on the body, which may not be the collider that was hit
+            let body = rapier_context.collider_parent(ground_entity).unwrap_or(ground_entity);
+            player.last_surface = surface_query.get(body).ok().copied();
+        } else {
+            player.ground = GroundState::Airborne;
+            player.ground_entity = None;
+            player.surface_normal = Vec3::Y;
+        }
+
+        if player.touching_ground() {
+            if !was_touching_ground {
+                landed_events.send(PlayerLanded {
+                    player: entity,
+                    impact_speed: player.fall_speed,
+                });
             }
+            player.fall_speed = 0.0;
         } else {
-            player.on_ground = false;
+            player.fall_speed = player.fall_speed.max(-velocity.linvel.y);
+        }
+    }
+}
+
+// The world space lean that takes up to the ground's normal. Upright in the air, when turned off,
+// and on slopes too steep to stand on, where leaning in would tip the model over.
+fn surface_tilt(player: &Player) -> Quat {
+    let normal = player.surface_normal;
+    let align = player.align_to_surface
+        && player.ground == GroundState::Grounded
+        && normal.angle_between(Vec3::Y) <= MAX_SURFACE_ALIGN_ANGLE;
+    if align { Quat::from_rotation_arc(Vec3::Y, normal) } else { Quat::IDENTITY }
+}
+
+// Leans the model so its up follows the ground's normal, easing there rather than snapping at
+// every bump. The tilt is applied to the model, not the body, as the capsule must stay upright
+// for the physics; the model is a child, so the tilt is taken into the body's turned frame.
+fn player_orientation_system(
+    player_query: Query<(&Player, &Transform, &Children)>,
+    mut model_query: Query<&mut Transform, (With<PlayerModel>, Without<Player>)>,
+    time: Res<Time>,
+) {
+    let blend = (SURFACE_ALIGN_SPEED * time.delta_seconds()).clamp(0.0, 1.0);
+    for (player, transform, children) in player_query.iter() {
+        let target = transform.rotation.inverse() * surface_tilt(player) * transform.rotation;
+        for child in children.iter() {
+            let Ok(mut model_transform) = model_query.get_mut(*child) else {
+                continue;
+            };
+            let rotation = model_transform.rotation.slerp(target, blend);
+            model_transform.rotation = rotation;
+            model_transform.translation = MODEL_FEET - rotation * MODEL_FEET;
+        }
+    }
+}
+
+// Grip comes from the player's side of the contact, so it's swapped here rather than on the ice
+fn update_surface_friction(mut player_query: Query<(&Player, &mut Friction)>) {
+    for (player, mut friction) in player_query.iter_mut() {
+        let target = if player.on_ice() { ICE_FRICTION } else { Friction::default() };
+        if *friction != target {
+            *friction = target;
         }
     }
 }
 
 fn debug_player_state(
-    player_query: Query<(&Player, &Transform, &Velocity)>,
+    player_query: Query<(&PlayerIndex, &Player, &Transform, &Velocity, Has<Noclip>)>,
+    mut timers: ResMut<DebugLogTimers>,
     time: Res<Time>,
 ) {
-    static mut LAST_LOG_TIME: f32 = 0.0;
-    
-    unsafe {
-        if time.elapsed_seconds() - LAST_LOG_TIME > 2.0 {
-            if let Ok((player, transform, velocity)) = player_query.get_single() {
-                println!("=== PLAYER DEBUG ===");
-                println!("Position: {:?}", transform.translation);
-                println!("Velocity: {:?}", velocity.linvel);
-                println!("On ground: {}", player.on_ground);
-                println!("Speed: {}", player.speed);
-                println!("Jump force: {}", player.jump_force);
-                println!("===================");
-            } else {
-                println!("ERROR: No player found in debug system!");
+    if !timers.player.tick(time.delta()).just_finished() {
+        return;
+    }
+    if player_query.is_empty() {
+        warn!(target: DEBUG_LOG_TARGET, "No player found in debug system");
+    }
+    for (index, player, transform, velocity, noclip) in player_query.iter() {
+        debug!(
+            target: DEBUG_LOG_TARGET,
+            "Player {}: position {:?}, velocity {:?}, ground {:?}, speed {}, jump force {}, noclip {}",
+            index.0 + 1, transform.translation, velocity.linvel, player.ground, player.speed, player.jump_force, noclip,
+        );
+    }
+}
+
+// Walks the stride along with the player, alternating feet
+fn footstep_system(
+    player_query: Query<(Entity, &Player, &Transform, &Velocity), Without<Noclip>>,
+    mut footstep_events: EventWriter<Footstep>,
+    // Distance walked since the last footstep and which foot that was, per player
+    mut strides: Local<HashMap<Entity, (f32, bool)>>,
+    time: Res<Time>,
+) {
+    for (entity, player, transform, velocity) in player_query.iter() {
+        let (distance, left) = strides.entry(entity).or_default();
+        if player.ground != GroundState::Grounded {
+            *distance = 0.0;
+            continue;
+        }
+        let ground_velocity = velocity.linvel - player.platform_velocity;
+        *distance += Vec3::new(ground_velocity.x, 0.0, ground_velocity.z).length() * time.delta_seconds();
+        if *distance < STRIDE_LENGTH {
+            continue;
+        }
+        *distance -= STRIDE_LENGTH;
+        *left = !*left;
+        let side = if *left { -FOOT_OFFSET } else { FOOT_OFFSET };
+        footstep_events.send(Footstep {
+            player: entity,
+            position: transform.translation + transform.rotation * Vec3::new(side, 0.0, 0.0),
+            rotation: transform.rotation,
+            left: *left,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::RunSystemOnce;
+    use crate::debug::DebugOverlay;
+    use crate::input_map::InputSnapshot;
+    use crate::terrain::{collect_spawn_points, height_at, island_collider, SpawnPoint, TerrainConfig};
+    use crate::test_app::{physics_app, FRAME};
+    use std::time::Duration;
+
+    // Headless physics with the player's movement systems and nothing else
+    fn player_app() -> App {
+        let mut app = physics_app();
+        app.init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<PlayerSettings>()
+            .insert_resource(InputMap::default())
+            .add_event::<PlayerLanded>()
+            .add_systems(Update, (
+                ground_detection,
+                player_movement.after(ground_detection),
+                keep_player_upright.after(player_movement),
+            ))
+            .add_systems(PostUpdate, clamp_fall_speed.after(PhysicsSet::Writeback));
+        app
+    }
+
+    fn spawn_test_player(app: &mut App, position: Vec3) -> Entity {
+        app.world.run_system_once(move |mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>, mut materials: ResMut<Assets<StandardMaterial>>, settings: Res<PlayerSettings>| {
+            spawn_player_at(&mut commands, &mut meshes, &mut materials, &settings, 0, Transform::from_translation(position))
+        })
+    }
+
+    fn translation(app: &App, entity: Entity) -> Vec3 {
+        app.world.get::<Transform>(entity).unwrap().translation
+    }
+
+    #[test]
+    fn idle_player_rides_an_oscillating_platform() {
+        let mut app = player_app();
+        let platform = app.world.spawn((
+            RigidBody::KinematicPositionBased,
+            Velocity::zero(),
+            Collider::cuboid(3.0, 0.25, 3.0),
+            TransformBundle::default(),
+        )).id();
+        let player = spawn_test_player(&mut app, Vec3::new(0.0, 1.8, 0.0));
+        for _ in 0..60 {
+            app.update();
+        }
+        assert_eq!(app.world.get::<Player>(player).unwrap().ground, GroundState::Grounded);
+
+        // Up to 3 m/s back and forth along x, starting from rest
+        let start = translation(&app, player) - translation(&app, platform);
+        let mut worst_drift: f32 = 0.0;
+        for frame in 1..=240 {
+            let x = 1.5 * (1.0 - (2.0 * frame as f32 * FRAME).cos());
+            app.world.get_mut::<Transform>(platform).unwrap().translation.x = x;
+            app.update();
+            let offset = translation(&app, player) - translation(&app, platform) - start;
+            worst_drift = worst_drift.max(Vec2::new(offset.x, offset.z).length());
+        }
+        assert!(worst_drift < 0.1, "player drifted {worst_drift} m across the platform");
+        assert_eq!(app.world.get::<Player>(player).unwrap().ground, GroundState::Grounded);
+    }
+
+    #[test]
+    fn a_big_off_center_impulse_doesnt_tip_the_capsule() {
+        let mut app = player_app();
+        app.world.spawn((RigidBody::Fixed, Collider::cuboid(20.0, 0.25, 20.0), TransformBundle::default()));
+        let player = spawn_test_player(&mut app, Vec3::new(0.0, 1.8, 0.0));
+        for _ in 0..30 {
+            app.update();
+        }
+        // Hits the top of the capsule sideways, hard enough to knock it well across the ground
+        let center = translation(&app, player);
+        app.world.entity_mut(player).insert(ExternalImpulse::at_point(Vec3::new(400.0, 0.0, 250.0), center + Vec3::new(0.3, 1.2, 0.0), center));
+        let mut worst_tilt: f32 = 0.0;
+        for _ in 0..120 {
+            app.update();
+            let up = app.world.get::<Transform>(player).unwrap().up();
+            worst_tilt = worst_tilt.max(up.angle_between(Vec3::Y));
+        }
+        assert!(translation(&app, player).x > center.x + 1.0);
+        assert!(worst_tilt < 1.0_f32.to_radians(), "tilted {} degrees", worst_tilt.to_degrees());
+    }
+
+    #[test]
+    fn a_200_metre_drop_lands_on_top_of_the_island_at_terminal_velocity() {
+        let mut app = player_app();
+        let config = TerrainConfig::default();
+        app.world.spawn((RigidBody::Fixed, island_collider(&config), TransformBundle::default()));
+        let player = spawn_test_player(&mut app, Vec3::new(3.0, 200.0, -4.0));
+        let mut impact_speed = None;
+        for _ in 0..600 {
+            app.update();
+            let events = app.world.resource::<Events<PlayerLanded>>();
+            if let Some(landed) = events.get_reader().read(events).last() {
+                impact_speed = Some(landed.impact_speed);
+                break;
             }
-            LAST_LOG_TIME = time.elapsed_seconds();
         }
+        let impact_speed = impact_speed.expect("the player never landed");
+        assert_eq!(impact_speed, PlayerSettings::default().max_fall_speed);
+        for _ in 0..30 {
+            app.update();
+        }
+        // Standing on the ground, the capsule's centre is half its height above it
+        let ground = height_at(&config, 3.0, -4.0);
+        let position = translation(&app, player);
+        assert!((position.y - (ground + 1.5)).abs() < 0.1, "resting at {} over ground at {ground}", position.y);
     }
-} 
\ No newline at end of file
+
+    // Drops an idle player onto a long ramp rising towards -z, returning its ground state each
+    // frame and how far it moved down the ramp over the next second
+    fn ramp_run(degrees: f32) -> (Vec<GroundState>, f32) {
+        let mut app = player_app();
+        let rotation = Quat::from_rotation_x(degrees.to_radians());
+        app.world.spawn((RigidBody::Fixed, Collider::cuboid(2.0, 0.1, 20.0), TransformBundle::from_transform(Transform::from_rotation(rotation))));
+        // Just clear of the surface, which a capsule touches half a height further out the
+        // flatter it is
+        let normal = rotation * Vec3::Y;
+        let player = spawn_test_player(&mut app, normal * (0.65 + normal.y));
+        let mut states = Vec::new();
+        for _ in 0..10 {
+            app.update();
+        }
+        let start = translation(&app, player);
+        for _ in 0..60 {
+            app.update();
+            states.push(app.world.get::<Player>(player).unwrap().ground);
+        }
+        let downhill = (rotation * Vec3::Z).normalize();
+        (states, (translation(&app, player) - start).dot(downhill))
+    }
+
+    #[test]
+    fn walkable_ramps_hold_an_idle_player() {
+        let (states, slid) = ramp_run(30.0);
+        assert!(states.iter().all(|state| *state == GroundState::Grounded), "{states:?}");
+        assert!(slid.abs() < 0.2, "slid {slid} m");
+    }
+
+    #[test]
+    fn steep_ramps_slide_the_player_down() {
+        let (states, slid) = ramp_run(55.0);
+        let sliding = |state: &GroundState| matches!(state, GroundState::Sliding { normal } if (normal.angle_between(Vec3::Y).to_degrees() - 55.0).abs() < 1.0);
+        assert!(states.iter().all(sliding), "{states:?}");
+        assert!(slid > 2.0, "slid {slid} m");
+    }
+
+    #[test]
+    fn near_vertical_ramps_are_never_stood_on() {
+        let (states, slid) = ramp_run(80.0);
+        assert!(!states.contains(&GroundState::Grounded), "{states:?}");
+        assert!(slid > 2.0, "slid {slid} m");
+    }
+
+    #[test]
+    fn target_speed_scales_for_sprinting_and_carrying() {
+        let player = Player::default();
+        assert_eq!(player.target_speed(false, false), player.speed);
+        assert_eq!(player.target_speed(false, true), player.speed * player.sprint_multiplier);
+        // Carrying rules out sprinting
+        assert_eq!(player.target_speed(true, true), player.speed * CARRY_SPEED_MULTIPLIER);
+    }
+
+    // A grounded player with only the movement system, no physics step afterwards to add
+    // contact friction, holding the given actions
+    fn movement_app(pressed: Vec<Action>, velocity: Vec3) -> (App, Entity) {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .insert_resource(InputMap::default())
+            .add_systems(Update, player_movement);
+        let player = app.world.spawn((
+            PlayerIndex(0),
+            Player {
+                ground: GroundState::Grounded,
+                ..default()
+            },
+            Transform::default(),
+            Velocity::linear(velocity),
+        )).id();
+        app.world.resource_mut::<InputMap>().apply_snapshot(&InputSnapshot {
+            pressed,
+            ..default()
+        });
+        (app, player)
+    }
+
+    fn ground_speed_after(app: &mut App, player: Entity, dt: f32, frames: u32) -> f32 {
+        for _ in 0..frames {
+            app.world.resource_mut::<Time>().advance_by(Duration::from_secs_f32(dt));
+            app.update();
+        }
+        let velocity = app.world.get::<Velocity>(player).unwrap().linvel;
+        Vec2::new(velocity.x, velocity.z).length()
+    }
+
+    fn held_speed(sprinting: bool, dt: f32, frames: u32) -> f32 {
+        let mut pressed = vec![Action::MoveForward];
+        if sprinting {
+            pressed.push(Action::Sprint);
+        }
+        let (mut app, player) = movement_app(pressed, Vec3::ZERO);
+        ground_speed_after(&mut app, player, dt, frames)
+    }
+
+    #[test]
+    fn sprint_speed_is_exactly_speed_times_multiplier_at_any_frame_rate() {
+        let player = Player::default();
+        for (dt, frames) in [(1.0 / 30.0, 1), (1.0 / 30.0, 90), (1.0 / 60.0, 180), (1.0 / 144.0, 432)] {
+            assert!((held_speed(false, dt, frames) - player.speed).abs() < 1e-4);
+            let sprint = held_speed(true, dt, frames);
+            assert!((sprint - player.speed * player.sprint_multiplier).abs() < 1e-4, "{sprint} after {frames} frames of {dt}");
+        }
+    }
+
+    #[test]
+    fn idle_friction_slows_the_same_at_any_frame_rate() {
+        // Speed half a second and a second and a half into coasting from a run
+        let speeds: Vec<(f32, f32)> = [30, 60, 240]
+            .into_iter()
+            .map(|fps| {
+                let (mut app, player) = movement_app(vec![], Vec3::new(6.0, 0.0, -8.0));
+                let half = ground_speed_after(&mut app, player, 1.0 / fps as f32, fps / 2);
+                (half, ground_speed_after(&mut app, player, 1.0 / fps as f32, fps))
+            })
+            .collect();
+        let (reference_half, reference) = speeds[1];
+        assert!(reference_half < 10.0 && reference < reference_half);
+        for (half, end) in speeds {
+            assert!((half - reference_half).abs() <= reference_half * 0.02, "{half} vs {reference_half}");
+            assert!((end - reference).abs() <= reference * 0.02, "{end} vs {reference}");
+        }
+    }
+
+    // Steps debug_player_state at 60 fps, counting the frames it logged on
+    fn debug_log_app(enabled: bool) -> App {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<DebugLogTimers>()
+            .insert_resource(DebugOverlay { enabled })
+            .init_resource::<LoggedFrames>()
+            .add_systems(Update, (
+                debug_player_state.run_if(debug_overlay_enabled),
+                count_logged_frames.after(debug_player_state),
+            ));
+        app.world.spawn((PlayerIndex(0), Player::default(), Transform::default(), Velocity::zero()));
+        app
+    }
+
+    #[derive(Resource, Default)]
+    struct LoggedFrames(u32);
+
+    fn count_logged_frames(timers: Res<DebugLogTimers>, mut logged: ResMut<LoggedFrames>) {
+        if timers.player.just_finished() {
+            logged.0 += 1;
+        }
+    }
+
+    fn step(app: &mut App) {
+        app.world.resource_mut::<Time>().advance_by(Duration::from_secs_f32(FRAME));
+        app.update();
+    }
+
+    #[test]
+    fn player_debug_log_is_throttled_per_app() {
+        // Two worlds side by side, which the old static timestamps got mixed up by
+        let mut first = debug_log_app(true);
+        let mut second = debug_log_app(true);
+        for _ in 0..600 {
+            step(&mut first);
+        }
+        for _ in 0..300 {
+            step(&mut second);
+        }
+        // Every 2 seconds over 10 and 5 seconds, give or take float rounding on the last one
+        assert!((4..=5).contains(&first.world.resource::<LoggedFrames>().0));
+        assert!((2..=3).contains(&second.world.resource::<LoggedFrames>().0));
+    }
+
+    #[test]
+    fn player_debug_log_is_off_without_the_overlay() {
+        let mut app = debug_log_app(false);
+        for _ in 0..600 {
+            step(&mut app);
+        }
+        assert_eq!(app.world.resource::<LoggedFrames>().0, 0);
+        assert_eq!(app.world.resource::<DebugLogTimers>().player.elapsed_secs(), 0.0);
+    }
+
+    // Runs just the startup schedules of the player plugin, with spawn points spawned in Startup
+    // the way the terrain does and collected in PostStartup
+    fn spawned_at(points: Vec<(&'static str, i32, Vec3)>) -> (Vec3, Vec3) {
+        let mut app = App::new();
+        app.add_plugins(PlayerPlugin)
+            .init_resource::<Assets<Mesh>>()
+            .init_resource::<Assets<StandardMaterial>>()
+            .init_resource::<SpawnPointRegistry>()
+            .init_resource::<RespawnPoint>()
+            .insert_resource(InputMap::default())
+            .add_systems(Startup, move |mut commands: Commands| {
+                for &(id, priority, position) in &points {
+                    commands.spawn((
+                        SpawnPoint {
+                            id: id.to_string(),
+                            priority,
+                        },
+                        Transform::from_translation(position),
+                    ));
+                }
+            })
+            .add_systems(PostStartup, collect_spawn_points);
+        app.world.run_schedule(Startup);
+        app.world.run_schedule(PostStartup);
+        let player = app.world.resource::<Players>().0[0];
+        (translation(&app, player), app.world.resource::<RespawnPoint>().0)
+    }
+
+    #[test]
+    fn player_spawns_after_the_spawn_points_are_collected() {
+        let island = Vec3::new(4.0, 3.0, -2.0);
+        let (spawned, respawn) = spawned_at(vec![("platform_0", 0, Vec3::new(20.0, 8.0, 0.0)), ("island", 10, island)]);
+        assert_eq!(spawned, island);
+        assert_eq!(respawn, island);
+    }
+
+    #[test]
+    fn player_spawns_at_the_default_without_spawn_points() {
+        let (spawned, _) = spawned_at(vec![]);
+        assert_eq!(spawned, DEFAULT_SPAWN_POSITION);
+    }
+
+    // A player standing on ground whose normal is tilted by the given degrees about X
+    fn on_slope(degrees: f32) -> Player {
+        Player {
+            ground: GroundState::Grounded,
+            surface_normal: Quat::from_rotation_x(degrees.to_radians()) * Vec3::Y,
+            ..default()
+        }
+    }
+
+    #[test]
+    fn the_model_leans_to_slopes_it_can_stand_on() {
+        for degrees in [0.0, 15.0, 30.0, 45.0] {
+            let player = on_slope(degrees);
+            let up = surface_tilt(&player) * Vec3::Y;
+            assert!((up - player.surface_normal).length() < 1e-4, "{} degrees: up {:?}", degrees, up);
+        }
+    }
+
+    #[test]
+    fn the_model_stays_upright_off_standable_ground() {
+        assert_eq!(surface_tilt(&on_slope(55.0)), Quat::IDENTITY);
+        assert_eq!(surface_tilt(&Player { ground: GroundState::Airborne, ..on_slope(20.0) }), Quat::IDENTITY);
+        assert_eq!(surface_tilt(&Player { align_to_surface: false, ..on_slope(20.0) }), Quat::IDENTITY);
+    }
+}
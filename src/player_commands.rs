@@ -0,0 +1,178 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use crate::camera::CameraSnapEvent;
+use crate::debug::GodMode;
+use crate::grapple::Grappling;
+use crate::input_map::{Action, InputMap};
+use crate::player::{GroundState, Player, Players};
+use crate::spectator::SpectatorMode;
+use crate::status_effects::{ActiveEffect, ApplyStatusEffect, EffectKind};
+use crate::terrain::FloatingPlatforms;
+
+// Developer commands acting on the first player, only built with the dev-tools feature. Queue them
+// through PlayerCommands; number keys teleport to the floating platforms and numpad keys apply
+// status effects.
+pub struct PlayerCommandsPlugin;
+
+impl Plugin for PlayerCommandsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PlayerCommands>()
+            .add_event::<PlayerCommandExecuted>()
+            .add_systems(Update, (player_command_shortcuts, execute_player_commands, log_player_commands).chain());
+    }
+}
+
+// Number keys in platform order
+const PLATFORM_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1, KeyCode::Key2, KeyCode::Key3,
+    KeyCode::Key4, KeyCode::Key5, KeyCode::Key6,
+    KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+];
+// Walk speed is multiplied or divided by this per PageUp/PageDown press
+const SPEED_STEP: f32 = 1.5;
+// Numpad 1-5, with the duration and magnitude each applies
+const EFFECT_KEYS: [(KeyCode, EffectKind, f32, f32); 5] = [
+    (KeyCode::Numpad1, EffectKind::Poison, 5.0, 5.0),
+    (KeyCode::Numpad2, EffectKind::Stun, 2.0, 0.0),
+    (KeyCode::Numpad3, EffectKind::Freeze, 3.0, 0.0),
+    (KeyCode::Numpad4, EffectKind::Burning, 3.0, 4.0),
+    (KeyCode::Numpad5, EffectKind::Slow, 5.0, 0.5),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PlayerCommand {
+    Teleport(Vec3),
+    SetSpeed(f32),
+    ToggleGodMode,
+    ApplyEffect(ActiveEffect),
+}
+
+// Commands run in order at the next execute_player_commands
+#[derive(Resource, Default)]
+pub struct PlayerCommands {
+    queue: Vec<PlayerCommand>,
+}
+
+impl PlayerCommands {
+    pub fn teleport(&mut self, position: Vec3) {
+        self.queue.push(PlayerCommand::Teleport(position));
+    }
+
+    pub fn set_speed(&mut self, speed: f32) {
+        self.queue.push(PlayerCommand::SetSpeed(speed));
+    }
+
+    pub fn toggle_god_mode(&mut self) {
+        self.queue.push(PlayerCommand::ToggleGodMode);
+    }
+
+    pub fn apply_effect(&mut self, effect: ActiveEffect) {
+        self.queue.push(PlayerCommand::ApplyEffect(effect));
+    }
+}
+
+#[derive(Event)]
+pub struct PlayerCommandExecuted {
+    pub player: Entity,
+    pub command: PlayerCommand,
+}
+
+fn player_command_shortcuts(
+    keyboard_input: Res<Input<KeyCode>>,
+    input_map: Res<InputMap>,
+    platforms: Option<Res<FloatingPlatforms>>,
+    players: Res<Players>,
+    player_query: Query<&Player>,
+    spectator: Res<SpectatorMode>,
+    mut player_commands: ResMut<PlayerCommands>,
+) {
+    if let Some(platforms) = platforms {
+        for (key, position) in PLATFORM_KEYS.iter().zip(&platforms.standing_positions) {
+            if keyboard_input.just_pressed(*key) {
+                player_commands.teleport(*position);
+            }
+        }
+    }
+    if input_map.just_pressed(Action::ToggleGodMode) {
+        player_commands.toggle_god_mode();
+    }
+    for (key, kind, duration, magnitude) in EFFECT_KEYS {
+        if keyboard_input.just_pressed(key) {
+            player_commands.apply_effect(ActiveEffect::new(kind, duration, magnitude));
+        }
+    }
+
+    // Page Up and Page Down fly the spectator camera while it's live
+    if spectator.is_active() {
+        return;
+    }
+    let Some(player) = players.0.first().and_then(|entity| player_query.get(*entity).ok()) else {
+        return;
+    };
+    if keyboard_input.just_pressed(KeyCode::PageUp) {
+        player_commands.set_speed(player.speed * SPEED_STEP);
+    }
+    if keyboard_input.just_pressed(KeyCode::PageDown) {
+        player_commands.set_speed(player.speed / SPEED_STEP);
+    }
+}
+
+fn execute_player_commands(
+    mut commands: Commands,
+    mut player_commands: ResMut<PlayerCommands>,
+    players: Res<Players>,
+    mut player_query: Query<(&mut Player, &mut Transform, &mut Velocity, Has<GodMode>)>,
+    mut snap_events: EventWriter<CameraSnapEvent>,
+    mut effect_events: EventWriter<ApplyStatusEffect>,
+    mut executed_events: EventWriter<PlayerCommandExecuted>,
+) {
+    if player_commands.queue.is_empty() {
+        return;
+    }
+    let Some(&entity) = players.0.first() else {
+        player_commands.queue.clear();
+        return;
+    };
+    let Ok((mut player, mut transform, mut velocity, mut god_mode)) = player_query.get_mut(entity) else {
+        player_commands.queue.clear();
+        return;
+    };
+
+    for command in player_commands.queue.drain(..) {
+        match command {
+            // Arrive standing still, as if freshly spawned there
+            PlayerCommand::Teleport(position) => {
+                transform.translation = position;
+                *velocity = Velocity::zero();
+                player.ground = GroundState::Airborne;
+                player.ground_entity = None;
+                player.fall_speed = 0.0;
+                player.platform_velocity = Vec3::ZERO;
+                commands.entity(entity).remove::<Grappling>();
+                snap_events.send(CameraSnapEvent { target: entity });
+            }
+            PlayerCommand::SetSpeed(speed) => player.speed = speed,
+            PlayerCommand::ApplyEffect(effect) => effect_events.send(ApplyStatusEffect { entity, effect }),
+            PlayerCommand::ToggleGodMode => {
+                god_mode = !god_mode;
+                if god_mode {
+                    commands.entity(entity).insert(GodMode);
+                    println!("=== GOD MODE ON ===");
+                } else {
+                    commands.entity(entity).remove::<GodMode>();
+                    println!("=== GOD MODE OFF ===");
+                }
+            }
+        }
+        executed_events.send(PlayerCommandExecuted {
+            player: entity,
+            command,
+        });
+    }
+}
+
+fn log_player_commands(mut executed_events: EventReader<PlayerCommandExecuted>) {
+    for event in executed_events.read() {
+        println!("Player command on {:?}: {:?}", event.player, event.command);
+    }
+}
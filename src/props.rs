@@ -0,0 +1,193 @@
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use bevy_rapier3d::prelude::*;
+use crate::carry::Carryable;
+use crate::player::Player;
+use crate::terrain::{height_at, TerrainConfig};
+
+pub struct PropsPlugin;
+
+impl Plugin for PropsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_props)
+            .add_systems(Update, push_props);
+    }
+}
+
+// Push force applied for each m/s the player moves into a prop
+const PUSH_FORCE_PER_SPEED: f32 = 12.0;
+// Upper bound on the push force so sprinting can't launch heavy crates
+const MAX_PUSH_FORCE: f32 = 150.0;
+
+#[derive(Component)]
+pub struct Pushable;
+
+struct PropConfig {
+    position: Vec3,
+    size: f32,
+    mass: f32,
+    friction: f32,
+    restitution: f32,
+    color: Color,
+    is_ball: bool,
+}
+
+fn spawn_props(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    config: Res<TerrainConfig>,
+) {
+    let props = [
+        // Heavy wooden crates
+        PropConfig { position: Vec3::new(3.0, 0.6, 3.0), size: 1.0, mass: 40.0, friction: 0.8, restitution: 0.05, color: Color::rgb(0.55, 0.35, 0.15), is_ball: false },
+        PropConfig { position: Vec3::new(-3.0, 0.6, 3.0), size: 1.0, mass: 40.0, friction: 0.8, restitution: 0.05, color: Color::rgb(0.55, 0.35, 0.15), is_ball: false },
+        PropConfig { position: Vec3::new(0.0, 0.4, 6.0), size: 0.6, mass: 10.0, friction: 0.7, restitution: 0.1, color: Color::rgb(0.65, 0.45, 0.2), is_ball: false },
+        // Light bouncy balls
+        PropConfig { position: Vec3::new(2.0, 0.4, -3.0), size: 0.4, mass: 1.0, friction: 0.3, restitution: 0.7, color: Color::rgb(0.9, 0.8, 0.1), is_ball: true },
+        PropConfig { position: Vec3::new(-2.0, 0.4, -3.0), size: 0.4, mass: 1.0, friction: 0.3, restitution: 0.7, color: Color::rgb(0.1, 0.6, 0.9), is_ball: true },
+        PropConfig { position: Vec3::new(0.0, 0.6, -6.0), size: 0.6, mass: 3.0, friction: 0.4, restitution: 0.5, color: Color::rgb(0.9, 0.3, 0.6), is_ball: true },
+    ];
+
+    for prop in props.iter() {
+        // The heights above are above the ground
+        let position = prop.position + Vec3::Y * height_at(&config, prop.position.x, prop.position.z);
+        let (mesh, collider) = if prop.is_ball {
+            (
+                Mesh::from(shape::UVSphere {
+                    radius: prop.size,
+                    ..default()
+                }),
+                Collider::ball(prop.size),
+            )
+        } else {
+            (
+                Mesh::from(shape::Cube { size: prop.size }),
+                Collider::cuboid(prop.size / 2.0, prop.size / 2.0, prop.size / 2.0),
+            )
+        };
+
+        let mut prop_entity = commands.spawn((
+            Pushable,
+            RigidBody::Dynamic,
+            collider,
+            ColliderMassProperties::Mass(prop.mass),
+            Friction::coefficient(prop.friction),
+            Restitution::coefficient(prop.restitution),
+            // Props dominate the player in the contact solver so the only way the
+            // player moves them is through the clamped impulse in `push_props`
+            Dominance::group(1),
+            ExternalImpulse::default(),
+            Velocity::zero(),
+            PbrBundle {
+                mesh: meshes.add(mesh),
+                material: materials.add(StandardMaterial {
+                    base_color: prop.color,
+                    ..default()
+                }),
+                transform: Transform::from_translation(position),
+                ..default()
+            },
+        ));
+
+        // Anything light enough can also be picked up and carried
+        if prop.mass <= 10.0 {
+            prop_entity.insert(Carryable);
+        }
+    }
+
+    println!("Spawned {} pushable props", props.len());
+}
+
+// Impulse applied to a prop for one frame of contact. `direction` points from the
+// player to the prop; only the part of the player's velocity heading into the prop counts.
+fn push_impulse(player_velocity: Vec3, direction: Vec3, delta_seconds: f32) -> Vec3 {
+    let direction = Vec3::new(direction.x, 0.0, direction.z).normalize_or_zero();
+    let horizontal_velocity = Vec3::new(player_velocity.x, 0.0, player_velocity.z);
+    let speed_into_prop = horizontal_velocity.dot(direction);
+    if speed_into_prop <= 0.0 {
+        return Vec3::ZERO;
+    }
+
+    let force = (speed_into_prop * PUSH_FORCE_PER_SPEED).min(MAX_PUSH_FORCE);
+    direction * force * delta_seconds
+}
+
+fn push_props(
+    player_query: Query<(Entity, &Transform, &Velocity), With<Player>>,
+    mut prop_query: Query<(&Transform, &mut ExternalImpulse), With<Pushable>>,
+    rapier_context: Res<RapierContext>,
+    time: Res<Time>,
+) {
+    for (player_entity, player_transform, player_velocity) in player_query.iter() {
+        for contact_pair in rapier_context.contact_pairs_with(player_entity) {
+            if !contact_pair.has_any_active_contacts() {
+                continue;
+            }
+
+            let other = if contact_pair.collider1() == player_entity {
+                contact_pair.collider2()
+            } else {
+                contact_pair.collider1()
+            };
+
+            if let Ok((prop_transform, mut external_impulse)) = prop_query.get_mut(other) {
+                let direction = prop_transform.translation - player_transform.translation;
+                external_impulse.impulse += push_impulse(player_velocity.linvel, direction, time.delta_seconds());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_app::{physics_app, FRAME};
+
+    #[test]
+    fn impulse_is_clamped_at_the_max_push_force() {
+        let sprinting = push_impulse(Vec3::new(0.0, 0.0, 20.0), Vec3::Z, FRAME);
+        let launched = push_impulse(Vec3::new(0.0, 0.0, 500.0), Vec3::Z, FRAME);
+        assert!((sprinting.length() - MAX_PUSH_FORCE * FRAME).abs() < 1e-5);
+        assert_eq!(sprinting, launched);
+        // Below the clamp it still scales with speed
+        let walking = push_impulse(Vec3::new(0.0, 0.0, 2.0), Vec3::Z, FRAME);
+        assert!((walking.length() - 2.0 * PUSH_FORCE_PER_SPEED * FRAME).abs() < 1e-5);
+    }
+
+    #[test]
+    fn impulse_only_counts_speed_into_the_prop() {
+        assert_eq!(push_impulse(Vec3::new(0.0, 0.0, -5.0), Vec3::Z, FRAME), Vec3::ZERO);
+        assert_eq!(push_impulse(Vec3::new(5.0, 0.0, 0.0), Vec3::Z, FRAME), Vec3::ZERO);
+        // Falling onto a prop doesn't shove it sideways, and the push stays level
+        assert_eq!(push_impulse(Vec3::new(0.0, -30.0, 0.0), Vec3::new(0.0, -1.0, 0.1), FRAME).y, 0.0);
+    }
+
+    // Pushes a resting body of the given mass for a second and returns its speed
+    fn pushed_speed(mass: f32, collider: Collider) -> f32 {
+        let mut app = physics_app();
+        app.world.resource_mut::<RapierConfiguration>().gravity = Vec3::ZERO;
+        let prop = app.world.spawn((
+            RigidBody::Dynamic,
+            collider,
+            ColliderMassProperties::Mass(mass),
+            ExternalImpulse::default(),
+            Velocity::zero(),
+            TransformBundle::default(),
+        )).id();
+        app.update();
+        for _ in 0..60 {
+            app.world.get_mut::<ExternalImpulse>(prop).unwrap().impulse = push_impulse(Vec3::new(0.0, 0.0, 6.0), Vec3::Z, FRAME);
+            app.update();
+        }
+        app.world.get::<Velocity>(prop).unwrap().linvel.length()
+    }
+
+    #[test]
+    fn heavy_crates_gain_less_speed_than_light_balls() {
+        let crate_speed = pushed_speed(40.0, Collider::cuboid(0.5, 0.5, 0.5));
+        let ball_speed = pushed_speed(1.0, Collider::ball(0.4));
+        assert!(crate_speed > 0.0);
+        assert!(ball_speed > crate_speed * 10.0, "ball {ball_speed}, crate {crate_speed}");
+    }
+}
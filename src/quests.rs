@@ -0,0 +1,265 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::dialogue::ItemGiveEvent;
+use crate::interaction::{Interacted, Lever};
+
+pub struct QuestPlugin;
+
+impl Plugin for QuestPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActiveQuests>()
+            .init_resource::<CompletedQuests>()
+            .add_event::<QuestStartEvent>()
+            .add_event::<QuestProgressEvent>()
+            .add_event::<QuestCompleteEvent>()
+            .add_systems(Startup, setup_quest_hud)
+            .add_systems(Update, (
+                start_quests,
+                quest_interaction_progress,
+                progress_quests,
+                announce_completed_quests,
+                update_quest_hud,
+            ).chain());
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Objective {
+    pub description: String,
+    pub required: u32,
+    pub current: u32,
+    pub completed: bool,
+}
+
+impl Objective {
+    fn new(description: &str, required: u32) -> Self {
+        Self {
+            description: description.to_string(),
+            required,
+            current: 0,
+            completed: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Quest {
+    pub id: &'static str,
+    pub title: String,
+    pub objectives: Vec<Objective>,
+}
+
+impl Quest {
+    // Every quest in the game, by id. Saves only keep ids and counts, so these are the source of
+    // the titles and objectives.
+    pub fn definition(id: &str) -> Option<Quest> {
+        match id {
+            "fix_lift" => Some(Quest {
+                id: "fix_lift",
+                title: "Fix the lift".to_string(),
+                objectives: vec![
+                    Objective::new("Pull the lever by the southern platform", 1),
+                    Objective::new("Ask the villager what the gift is", 1),
+                ],
+            }),
+            _ => None,
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.objectives.iter().all(|objective| objective.completed)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ActiveQuests(pub Vec<Quest>);
+
+// Finished quests can't be started again
+#[derive(Resource, Default)]
+pub struct CompletedQuests(pub Vec<String>);
+
+#[derive(Event)]
+pub struct QuestStartEvent {
+    pub quest_id: String,
+}
+
+#[derive(Event)]
+pub struct QuestProgressEvent {
+    pub quest_id: String,
+    pub objective_idx: usize,
+    pub amount: u32,
+}
+
+#[derive(Event)]
+pub struct QuestCompleteEvent {
+    pub quest_id: String,
+}
+
+// One quest's progress as kept in the save file
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QuestSaveData {
+    pub id: String,
+    pub progress: Vec<u32>,
+}
+
+impl ActiveQuests {
+    pub fn to_save(&self) -> Vec<QuestSaveData> {
+        self.0
+            .iter()
+            .map(|quest| QuestSaveData {
+                id: quest.id.to_string(),
+                progress: quest.objectives.iter().map(|objective| objective.current).collect(),
+            })
+            .collect()
+    }
+
+    // Quests that no longer exist are dropped, and counts are clamped to what is required now
+    pub fn from_save(saved: &[QuestSaveData]) -> Self {
+        let quests = saved
+            .iter()
+            .filter_map(|data| {
+                let Some(mut quest) = Quest::definition(&data.id) else {
+                    println!("WARNING: Unknown quest {} in save, skipping", data.id);
+                    return None;
+                };
+                for (objective, current) in quest.objectives.iter_mut().zip(&data.progress) {
+                    objective.current = (*current).min(objective.required);
+                    objective.completed = objective.current >= objective.required;
+                }
+                Some(quest)
+            })
+            .collect();
+        Self(quests)
+    }
+}
+
+#[derive(Component)]
+struct QuestHudText;
+
+fn setup_quest_hud(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 20.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(80.0),
+            right: Val::Px(20.0),
+            ..default()
+        }),
+        QuestHudText,
+    ));
+}
+
+fn start_quests(
+    mut start_events: EventReader<QuestStartEvent>,
+    mut active: ResMut<ActiveQuests>,
+    completed: Res<CompletedQuests>,
+) {
+    for event in start_events.read() {
+        if active.0.iter().any(|quest| quest.id == event.quest_id) || completed.0.contains(&event.quest_id) {
+            continue;
+        }
+        match Quest::definition(&event.quest_id) {
+            Some(quest) => {
+                println!("Quest started: {}", quest.title);
+                active.0.push(quest);
+            }
+            None => println!("WARNING: Unknown quest {}", event.quest_id),
+        }
+    }
+}
+
+// Things done in the world that count towards quests
+fn quest_interaction_progress(
+    mut interacted_events: EventReader<Interacted>,
+    mut item_events: EventReader<ItemGiveEvent>,
+    lever_query: Query<(), With<Lever>>,
+    mut progress_events: EventWriter<QuestProgressEvent>,
+) {
+    for event in interacted_events.read() {
+        if lever_query.contains(event.entity) {
+            progress_events.send(QuestProgressEvent {
+                quest_id: "fix_lift".to_string(),
+                objective_idx: 0,
+                amount: 1,
+            });
+        }
+    }
+    for event in item_events.read() {
+        if event.item == "Gem" {
+            progress_events.send(QuestProgressEvent {
+                quest_id: "fix_lift".to_string(),
+                objective_idx: 1,
+                amount: 1,
+            });
+        }
+    }
+}
+
+fn progress_quests(
+    mut progress_events: EventReader<QuestProgressEvent>,
+    mut active: ResMut<ActiveQuests>,
+    mut completed: ResMut<CompletedQuests>,
+    mut complete_events: EventWriter<QuestCompleteEvent>,
+) {
+    let mut any_completed = false;
+    for event in progress_events.read() {
+        // Progress on quests that haven't been started is dropped
+        let Some(quest) = active.0.iter_mut().find(|quest| quest.id == event.quest_id) else {
+            continue;
+        };
+        let Some(objective) = quest.objectives.get_mut(event.objective_idx) else {
+            continue;
+        };
+        if objective.completed {
+            continue;
+        }
+        objective.current = (objective.current + event.amount).min(objective.required);
+        objective.completed = objective.current >= objective.required;
+
+        if quest.is_complete() {
+            complete_events.send(QuestCompleteEvent { quest_id: quest.id.to_string() });
+            completed.0.push(quest.id.to_string());
+            any_completed = true;
+        }
+    }
+    if any_completed {
+        active.0.retain(|quest| !quest.is_complete());
+    }
+}
+
+fn announce_completed_quests(mut complete_events: EventReader<QuestCompleteEvent>) {
+    for event in complete_events.read() {
+        let title = Quest::definition(&event.quest_id).map_or(event.quest_id.clone(), |quest| quest.title);
+        println!("=== QUEST COMPLETE === {}", title);
+    }
+}
+
+fn update_quest_hud(active: Res<ActiveQuests>, mut text_query: Query<&mut Text, With<QuestHudText>>) {
+    if !active.is_changed() {
+        return;
+    }
+    let Ok(mut text) = text_query.get_single_mut() else {
+        return;
+    };
+    let mut value = String::new();
+    for quest in &active.0 {
+        value.push_str(&quest.title);
+        value.push('\n');
+        for objective in &quest.objectives {
+            let check = if objective.completed { "[x]" } else { "[ ]" };
+            value.push_str(&format!("  {} {}", check, objective.description));
+            if objective.required > 1 {
+                value.push_str(&format!(" ({}/{})", objective.current, objective.required));
+            }
+            value.push('\n');
+        }
+    }
+    text.sections[0].value = value;
+}
@@ -0,0 +1,350 @@
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use bevy_rapier3d::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use crate::camera::ThirdPersonCamera;
+use crate::input_map::{update_action_state, Action, InputMap, InputSnapshot};
+use crate::player::{Player, PlayerIndex, PlayerSettings};
+
+// Records the input of a run (F9) and plays it back with `cargo run -- replay <file> [--assert]`.
+// Both lock the frame time and the physics step to TICK_SECONDS so the same input produces the
+// same run.
+pub struct RecordingPlugin;
+
+impl Plugin for RecordingPlugin {
+    fn build(&self, app: &mut App) {
+        let replay = replay_from_args();
+        let seed = replay.as_ref().map_or_else(|| rand::thread_rng().gen(), |replay| replay.recording.seed);
+
+        app.insert_resource(GameRng(StdRng::seed_from_u64(seed)))
+            .insert_resource(InputRecorder {
+                state: replay.map_or(RecorderState::Idle, RecorderState::Replaying),
+                saved_timestep: None,
+            })
+            .add_event::<StartRecording>()
+            .add_event::<StopRecording>()
+            .add_systems(Startup, lock_timestep_for_replay)
+            .add_systems(PreUpdate, record_or_replay_input.after(update_action_state))
+            .add_systems(Update, (toggle_recording, start_stop_recording).chain())
+            .add_systems(Last, record_or_check_keyframe);
+    }
+}
+
+const RECORDINGS_DIR: &str = "recordings";
+pub const TICK_SECONDS: f32 = 1.0 / 60.0;
+// Ticks between recorded player transforms, 20 Hz
+const KEYFRAME_INTERVAL: u32 = 3;
+// How far a replayed position may drift from the recorded one
+const KEYFRAME_EPSILON: f32 = 0.01;
+
+// Randomness that affects gameplay, seeded per recording so replays draw the same numbers
+#[derive(Resource)]
+pub struct GameRng(pub StdRng);
+
+#[derive(Event)]
+pub struct StartRecording;
+
+#[derive(Event)]
+pub struct StopRecording;
+
+#[derive(Default, Serialize, Deserialize)]
+pub struct Recording {
+    pub seed: u64,
+    pub initial_translation: Vec3,
+    pub initial_rotation: Quat,
+    pub initial_velocity: Vec3,
+    // Movement is relative to the camera, so its angles are part of the starting state
+    pub initial_camera_rotation: f32,
+    pub initial_camera_yaw: f32,
+    pub initial_camera_pitch: f32,
+    // One per tick
+    pub frames: Vec<InputSnapshot>,
+    // Player transform at the end of every KEYFRAME_INTERVAL-th tick
+    pub keyframes: Vec<Keyframe>,
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize)]
+pub struct Keyframe {
+    pub tick: u32,
+    pub translation: Vec3,
+    pub rotation: Quat,
+}
+
+struct Replay {
+    recording: Recording,
+    tick: u32,
+    started: bool,
+    // Panic on the first divergence instead of just reporting it
+    assert: bool,
+    divergences: u32,
+}
+
+enum RecorderState {
+    Idle,
+    Recording { recording: Recording, tick: u32 },
+    Replaying(Replay),
+}
+
+#[derive(Resource)]
+pub struct InputRecorder {
+    state: RecorderState,
+    // Physics timestep to go back to once recording stops
+    saved_timestep: Option<TimestepMode>,
+}
+
+fn replay_from_args() -> Option<Replay> {
+    let args: Vec<String> = std::env::args().collect();
+    let index = args.iter().position(|arg| arg == "replay")?;
+    let Some(path) = args.get(index + 1) else {
+        println!("WARNING: replay needs a recording file");
+        return None;
+    };
+    let recording = match load_recording(path) {
+        Ok(recording) => recording,
+        Err(err) => {
+            println!("WARNING: Failed to load recording {}: {}", path, err);
+            return None;
+        }
+    };
+    println!("Replaying {} ({} ticks)", path, recording.frames.len());
+    Some(Replay {
+        recording,
+        tick: 0,
+        started: false,
+        assert: args.iter().any(|arg| arg == "--assert"),
+        divergences: 0,
+    })
+}
+
+pub fn load_recording(path: &str) -> Result<Recording, String> {
+    let contents = fs::read_to_string(path).map_err(|err| err.to_string())?;
+    ron::from_str(&contents).map_err(|err| err.to_string())
+}
+
+fn lock_timestep(time_strategy: &mut TimeUpdateStrategy, rapier_config: &mut RapierConfiguration) -> TimestepMode {
+    *time_strategy = TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(TICK_SECONDS));
+    std::mem::replace(&mut rapier_config.timestep_mode, TimestepMode::Fixed {
+        dt: TICK_SECONDS,
+        substeps: 1,
+    })
+}
+
+fn lock_timestep_for_replay(
+    mut recorder: ResMut<InputRecorder>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    settings: Res<PlayerSettings>,
+) {
+    // Recordings hold one player's input, and split-screen players read theirs per device
+    if matches!(recorder.state, RecorderState::Replaying(_)) && settings.local_players > 1 {
+        println!("WARNING: Replays only work with a single local player, not replaying");
+        recorder.state = RecorderState::Idle;
+        return;
+    }
+    if matches!(recorder.state, RecorderState::Replaying(_)) {
+        recorder.saved_timestep = Some(lock_timestep(&mut time_strategy, &mut rapier_config));
+    }
+}
+
+fn toggle_recording(
+    input_map: Res<InputMap>,
+    recorder: Res<InputRecorder>,
+    mut start_events: EventWriter<StartRecording>,
+    mut stop_events: EventWriter<StopRecording>,
+) {
+    if !input_map.just_pressed(Action::ToggleRecording) {
+        return;
+    }
+    match recorder.state {
+        RecorderState::Idle => start_events.send(StartRecording),
+        RecorderState::Recording { .. } => stop_events.send(StopRecording),
+        RecorderState::Replaying(_) => {}
+    }
+}
+
+fn start_stop_recording(
+    mut start_events: EventReader<StartRecording>,
+    mut stop_events: EventReader<StopRecording>,
+    mut recorder: ResMut<InputRecorder>,
+    mut rng: ResMut<GameRng>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    player_query: Query<(), With<Player>>,
+    camera_query: Query<(), With<ThirdPersonCamera>>,
+) {
+    let start = start_events.read().count() > 0;
+    let stop = stop_events.read().count() > 0;
+
+    if start && matches!(recorder.state, RecorderState::Idle) {
+        if player_query.is_empty() || camera_query.is_empty() {
+            println!("WARNING: Can't record without a player and camera");
+            return;
+        }
+        if player_query.iter().count() > 1 {
+            println!("WARNING: Recording only works with a single local player");
+            return;
+        }
+        // The starting state is filled in by the first recorded tick
+        let seed = rand::thread_rng().gen();
+        rng.0 = StdRng::seed_from_u64(seed);
+        recorder.saved_timestep = Some(lock_timestep(&mut time_strategy, &mut rapier_config));
+        recorder.state = RecorderState::Recording {
+            recording: Recording {
+                seed,
+                ..default()
+            },
+            tick: 0,
+        };
+        println!("=== RECORDING STARTED ===");
+    } else if stop && matches!(recorder.state, RecorderState::Recording { .. }) {
+        let RecorderState::Recording { recording, .. } = std::mem::replace(&mut recorder.state, RecorderState::Idle) else {
+            return;
+        };
+        *time_strategy = TimeUpdateStrategy::Automatic;
+        if let Some(timestep) = recorder.saved_timestep.take() {
+            rapier_config.timestep_mode = timestep;
+        }
+        save_recording(&recording);
+    }
+}
+
+fn save_recording(recording: &Recording) {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs());
+    let path = Path::new(RECORDINGS_DIR).join(format!("run_{}.ron", timestamp));
+    let contents = match ron::to_string(recording) {
+        Ok(contents) => contents,
+        Err(err) => {
+            println!("WARNING: Failed to serialize recording: {}", err);
+            return;
+        }
+    };
+    let _ = fs::create_dir_all(RECORDINGS_DIR);
+    match fs::write(&path, contents) {
+        Ok(()) => println!("=== RECORDING SAVED === {} ticks to {}", recording.frames.len(), path.display()),
+        Err(err) => println!("WARNING: Failed to write {}: {}", path.display(), err),
+    }
+}
+
+// Runs right after the devices are read: records what gameplay is about to see, or swaps it for
+// the recorded frame
+fn record_or_replay_input(
+    mut recorder: ResMut<InputRecorder>,
+    mut input_map: ResMut<InputMap>,
+    mut player_query: Query<(&PlayerIndex, &mut Transform, &mut Velocity), With<Player>>,
+    mut camera_query: Query<(&PlayerIndex, &mut ThirdPersonCamera)>,
+) {
+    // Only single player runs are recorded, so that's the first player
+    let mut player = player_query
+        .iter_mut()
+        .find(|(index, _, _)| index.0 == 0)
+        .map(|(_, transform, velocity)| (transform, velocity));
+    let mut camera = camera_query.iter_mut().find(|(index, _)| index.0 == 0).map(|(_, camera)| camera);
+    match &mut recorder.state {
+        RecorderState::Idle => {}
+        RecorderState::Recording { recording, .. } => {
+            if recording.frames.is_empty() {
+                let (Some((transform, velocity)), Some(camera)) = (player.as_ref(), camera.as_ref()) else {
+                    return;
+                };
+                recording.initial_translation = transform.translation;
+                recording.initial_rotation = transform.rotation;
+                recording.initial_velocity = velocity.linvel;
+                recording.initial_camera_rotation = camera.current_rotation;
+                recording.initial_camera_yaw = camera.yaw;
+                recording.initial_camera_pitch = camera.pitch;
+            }
+            recording.frames.push(input_map.snapshot());
+        }
+        RecorderState::Replaying(replay) => {
+            // Put the player and camera back how the recording started before the first tick
+            if !replay.started {
+                let (Some((transform, velocity)), Some(camera)) = (player.as_mut(), camera.as_mut()) else {
+                    return;
+                };
+                let recording = &replay.recording;
+                transform.translation = recording.initial_translation;
+                transform.rotation = recording.initial_rotation;
+                **velocity = Velocity::linear(recording.initial_velocity);
+                camera.current_rotation = recording.initial_camera_rotation;
+                camera.yaw = recording.initial_camera_yaw;
+                camera.pitch = recording.initial_camera_pitch;
+                replay.started = true;
+            }
+            match replay.recording.frames.get(replay.tick as usize) {
+                Some(frame) => input_map.apply_snapshot(frame),
+                None => input_map.apply_snapshot(&InputSnapshot::default()),
+            }
+        }
+    }
+}
+
+// Runs after physics has moved the player for this tick
+fn record_or_check_keyframe(
+    mut recorder: ResMut<InputRecorder>,
+    mut time_strategy: ResMut<TimeUpdateStrategy>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    player_query: Query<(&PlayerIndex, &Transform), With<Player>>,
+) {
+    let Some((_, player_transform)) = player_query.iter().find(|(index, _)| index.0 == 0) else {
+        return;
+    };
+    let keyframe = Keyframe {
+        tick: 0,
+        translation: player_transform.translation,
+        rotation: player_transform.rotation,
+    };
+    let position = keyframe.translation;
+
+    let finished = match &mut recorder.state {
+        RecorderState::Idle => false,
+        RecorderState::Recording { recording, tick } => {
+            // Recording starts mid-frame; the first tick is the next full frame
+            if (*tick as usize) < recording.frames.len() {
+                if *tick % KEYFRAME_INTERVAL == 0 {
+                    recording.keyframes.push(Keyframe {
+                        tick: *tick,
+                        ..keyframe
+                    });
+                }
+                *tick += 1;
+            }
+            false
+        }
+        RecorderState::Replaying(replay) => {
+            if !replay.started {
+                return;
+            }
+            let tick = replay.tick;
+            if let Some(expected) = replay.recording.keyframes.iter().find(|keyframe| keyframe.tick == tick) {
+                let expected = expected.translation;
+                let drift = position.distance(expected);
+                if drift > KEYFRAME_EPSILON {
+                    replay.divergences += 1;
+                    println!("WARNING: Replay diverged at tick {}: expected {:?}, got {:?} ({:.3} m off)", tick, expected, position, drift);
+                    assert!(!replay.assert, "Replay diverged at tick {}", tick);
+                }
+            }
+            replay.tick += 1;
+            if replay.tick as usize >= replay.recording.frames.len() {
+                println!("=== REPLAY FINISHED === {} ticks, {} divergences", replay.tick, replay.divergences);
+                true
+            } else {
+                false
+            }
+        }
+    };
+
+    // Back to live input once the recording runs out
+    if finished {
+        recorder.state = RecorderState::Idle;
+        *time_strategy = TimeUpdateStrategy::Automatic;
+        if let Some(timestep) = recorder.saved_timestep.take() {
+            rapier_config.timestep_mode = timestep;
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use bevy_rapier3d::prelude::*;
+
+pub struct RopePlugin;
+
+impl Plugin for RopePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_ropes);
+    }
+}
+
+// Swing allowed at each joint, so the chain bends without whipping around
+const MAX_JOINT_SWING: f32 = std::f32::consts::FRAC_PI_4;
+const SEGMENT_RADIUS: f32 = 0.06;
+
+// A chain of capsules joined end to end
+#[derive(Component, Clone, Copy)]
+pub struct Rope {
+    pub segment_count: u8,
+    pub segment_length: f32,
+    pub segment_mass: f32,
+    // Holds the first segment's start in place with a fixed anchor
+    pub pinned: bool,
+}
+
+#[derive(Component)]
+pub struct RopeSegment;
+
+// Lays the segments out from `start` towards `end`. Returns the rope entity, which sits at the
+// start and doubles as the anchor when the rope is pinned.
+pub fn spawn_rope(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    start: Vec3,
+    end: Vec3,
+    config: Rope,
+) -> Entity {
+    let direction = (end - start).normalize_or_zero();
+    let direction = if direction == Vec3::ZERO { Vec3::NEG_Y } else { direction };
+    // Segment capsules run along their local Y, top end towards the start
+    let rotation = Quat::from_rotation_arc(Vec3::NEG_Y, direction);
+    let half_length = config.segment_length * 0.5;
+    let top_anchor = Vec3::Y * half_length;
+    let bottom_anchor = Vec3::NEG_Y * half_length;
+
+    let mut rope = commands.spawn((
+        config,
+        TransformBundle::from_transform(Transform::from_translation(start)),
+    ));
+    if config.pinned {
+        rope.insert(RigidBody::Fixed);
+    }
+    let rope = rope.id();
+
+    let mesh = meshes.add(Mesh::from(shape::Capsule {
+        radius: SEGMENT_RADIUS,
+        depth: (config.segment_length - SEGMENT_RADIUS * 2.0).max(0.0),
+        ..default()
+    }));
+    let material = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.55, 0.45, 0.3),
+        perceptual_roughness: 1.0,
+        ..default()
+    });
+
+    let mut previous: Option<Entity> = None;
+    for i in 0..config.segment_count {
+        let center = start + direction * config.segment_length * (i as f32 + 0.5);
+        let mut segment = commands.spawn((
+            RopeSegment,
+            RigidBody::Dynamic,
+            Collider::capsule_y((half_length - SEGMENT_RADIUS).max(0.0), SEGMENT_RADIUS),
+            ColliderMassProperties::Mass(config.segment_mass),
+            PbrBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                transform: Transform::from_translation(center).with_rotation(rotation),
+                ..default()
+            },
+        ));
+
+        // Each segment hangs off the bottom of the one before, the first off the anchor
+        let parent = match previous {
+            Some(previous) => Some((previous, bottom_anchor)),
+            None if config.pinned => Some((rope, Vec3::ZERO)),
+            None => None,
+        };
+        if let Some((parent, parent_anchor)) = parent {
+            let mut joint = SphericalJointBuilder::new()
+                .local_anchor1(parent_anchor)
+                .local_anchor2(top_anchor)
+                .limits(JointAxis::AngX, [-MAX_JOINT_SWING, MAX_JOINT_SWING])
+                .limits(JointAxis::AngY, [-MAX_JOINT_SWING, MAX_JOINT_SWING])
+                .limits(JointAxis::AngZ, [-MAX_JOINT_SWING, MAX_JOINT_SWING])
+                .build();
+            // Neighbouring capsules overlap at the joint
+            joint.set_contacts_enabled(false);
+            segment.insert(ImpulseJoint::new(parent, joint));
+        }
+        previous = Some(segment.id());
+    }
+    rope
+}
+
+fn spawn_ropes(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    // Hanging from just under the eastern floating platform
+    let start = Vec3::new(25.0, 4.4, 0.0);
+    spawn_rope(&mut commands, &mut meshes, &mut materials, start, start + Vec3::NEG_Y, Rope {
+        segment_count: 12,
+        segment_length: 0.5,
+        segment_mass: 0.2,
+        pinned: true,
+    });
+}
@@ -0,0 +1,174 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use crate::camera::CameraSnapEvent;
+use crate::combat::Health;
+use crate::day_night::DayNightCycle;
+use crate::health::{Dead, RespawnPoint};
+use crate::input_map::{Action, InputMap};
+use crate::player::{GroundState, Player, Players};
+use crate::quests::{ActiveQuests, CompletedQuests, QuestSaveData};
+
+pub struct SaveSystemPlugin;
+
+impl Plugin for SaveSystemPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<AutoSaveTimer>()
+            .add_event::<SaveGame>()
+            .add_event::<LoadGame>()
+            .add_systems(Startup, announce_save)
+            .add_systems(Update, (continue_shortcut, auto_save, save_game_system, load_game_system).chain());
+    }
+}
+
+const SAVE_DIR: &str = "save";
+const SAVE_FILE: &str = "save/game.json";
+const AUTO_SAVE_INTERVAL: f32 = 300.0;
+
+#[derive(Event)]
+pub struct SaveGame;
+
+#[derive(Event)]
+pub struct LoadGame;
+
+// The first player's progress. There is no stamina or inventory in the game yet, so neither is saved.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct PlayerSaveData {
+    pub position: [f32; 3],
+    pub health: f32,
+    pub checkpoint: [f32; 3],
+    pub time_of_day: f32,
+    // Older saves have no quests
+    #[serde(default)]
+    pub quests: Vec<QuestSaveData>,
+    #[serde(default)]
+    pub completed_quests: Vec<String>,
+}
+
+#[derive(Resource)]
+struct AutoSaveTimer(Timer);
+
+impl Default for AutoSaveTimer {
+    fn default() -> Self {
+        Self(Timer::from_seconds(AUTO_SAVE_INTERVAL, TimerMode::Repeating))
+    }
+}
+
+pub fn save_exists() -> bool {
+    Path::new(SAVE_FILE).exists()
+}
+
+fn announce_save() {
+    if save_exists() {
+        println!("Save found at {}, press F4 to continue", SAVE_FILE);
+    }
+}
+
+fn continue_shortcut(input_map: Res<InputMap>, mut load_events: EventWriter<LoadGame>) {
+    if input_map.just_pressed(Action::LoadGame) {
+        if save_exists() {
+            load_events.send(LoadGame);
+        } else {
+            println!("No save to continue from");
+        }
+    }
+}
+
+// Every few minutes, and whenever a new checkpoint is reached
+fn auto_save(
+    mut timer: ResMut<AutoSaveTimer>,
+    respawn_point: Res<RespawnPoint>,
+    time: Res<Time>,
+    mut save_events: EventWriter<SaveGame>,
+) {
+    let checkpoint_reached = respawn_point.is_changed() && !respawn_point.is_added();
+    if timer.0.tick(time.delta()).just_finished() || checkpoint_reached {
+        save_events.send(SaveGame);
+    }
+}
+
+fn save_game_system(
+    mut save_events: EventReader<SaveGame>,
+    players: Res<Players>,
+    player_query: Query<(&Transform, &Health), Without<Dead>>,
+    respawn_point: Res<RespawnPoint>,
+    cycle: Res<DayNightCycle>,
+    active_quests: Res<ActiveQuests>,
+    completed_quests: Res<CompletedQuests>,
+) {
+    if save_events.read().count() == 0 {
+        return;
+    }
+    // Mid-death there's nothing worth keeping
+    let Some((transform, health)) = players.0.first().and_then(|entity| player_query.get(*entity).ok()) else {
+        return;
+    };
+    let data = PlayerSaveData {
+        position: transform.translation.to_array(),
+        health: health.current,
+        checkpoint: respawn_point.0.to_array(),
+        time_of_day: cycle.time_of_day,
+        quests: active_quests.to_save(),
+        completed_quests: completed_quests.0.clone(),
+    };
+    let contents = match serde_json::to_string_pretty(&data) {
+        Ok(contents) => contents,
+        Err(err) => {
+            println!("WARNING: Failed to serialize save: {}", err);
+            return;
+        }
+    };
+    let _ = fs::create_dir_all(SAVE_DIR);
+    match fs::write(SAVE_FILE, contents) {
+        Ok(()) => println!("=== GAME SAVED === {}", SAVE_FILE),
+        Err(err) => println!("WARNING: Failed to write {}: {}", SAVE_FILE, err),
+    }
+}
+
+fn load_save() -> Result<PlayerSaveData, String> {
+    let contents = fs::read_to_string(SAVE_FILE).map_err(|err| err.to_string())?;
+    serde_json::from_str(&contents).map_err(|err| err.to_string())
+}
+
+fn load_game_system(
+    mut load_events: EventReader<LoadGame>,
+    players: Res<Players>,
+    mut player_query: Query<(&mut Player, &mut Transform, &mut Velocity, &mut Health)>,
+    mut respawn_point: ResMut<RespawnPoint>,
+    mut cycle: ResMut<DayNightCycle>,
+    mut active_quests: ResMut<ActiveQuests>,
+    mut completed_quests: ResMut<CompletedQuests>,
+    mut snap_events: EventWriter<CameraSnapEvent>,
+) {
+    if load_events.read().count() == 0 {
+        return;
+    }
+    let data = match load_save() {
+        Ok(data) => data,
+        Err(err) => {
+            println!("WARNING: Failed to load {}: {}", SAVE_FILE, err);
+            return;
+        }
+    };
+    let Some(&entity) = players.0.first() else {
+        return;
+    };
+    let Ok((mut player, mut transform, mut velocity, mut health)) = player_query.get_mut(entity) else {
+        return;
+    };
+
+    transform.translation = Vec3::from_array(data.position);
+    *velocity = Velocity::zero();
+    player.ground = GroundState::Airborne;
+    player.ground_entity = None;
+    player.fall_speed = 0.0;
+    health.current = data.health.clamp(0.0, health.max);
+    respawn_point.0 = Vec3::from_array(data.checkpoint);
+    cycle.time_of_day = data.time_of_day.rem_euclid(24.0);
+    *active_quests = ActiveQuests::from_save(&data.quests);
+    completed_quests.0 = data.completed_quests;
+    snap_events.send(CameraSnapEvent { target: entity });
+    println!("=== GAME LOADED === {}", SAVE_FILE);
+}
@@ -0,0 +1,154 @@
+use bevy::prelude::*;
+use bevy::time::Real;
+use bevy_rapier3d::prelude::*;
+use crate::combat::EnemyDeathEvent;
+use crate::vulkan_renderer::ToneMappingConfig;
+
+pub struct SlowMotionPlugin;
+
+impl Plugin for SlowMotionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SlowMotionConfig>()
+            .add_event::<SlowMotionEvent>()
+            .add_systems(Update, (slow_motion_on_kill, start_slow_motion, slow_motion_system, slow_motion_effects).chain());
+    }
+}
+
+// Physics steps this long at normal speed
+const PHYSICS_DT: f32 = 1.0 / 60.0;
+// Slower than this and the picture drains of color and darkens at the edges
+const SLOW_MOTION_VFX_SCALE: f32 = 0.5;
+const SLOW_MOTION_DESATURATION: f32 = 0.7;
+const SLOW_MOTION_VIGNETTE: f32 = 0.35;
+// Real seconds the color takes to drain or come back
+const SLOW_MOTION_VFX_TIME: f32 = 0.2;
+// Each kill slows things down briefly
+const KILL_SLOW_MOTION_SCALE: f32 = 0.3;
+const KILL_SLOW_MOTION_DURATION: f32 = 0.5;
+// Never slower than this, so physics still gets somewhere
+const MIN_SCALE: f32 = 0.05;
+
+// The slow motion in progress. scale is 1 when there's none; duration and timer are in real
+// seconds, so a slowed game doesn't stretch its own slow motion out.
+#[derive(Resource)]
+pub struct SlowMotionConfig {
+    pub scale: f32,
+    pub duration: f32,
+    pub timer: f32,
+    // Physics timestep from before it started, put back when it ends
+    previous_timestep: Option<TimestepMode>,
+    // 0 to 1, how much of the desaturation and vignette is showing
+    effect_strength: f32,
+}
+
+impl Default for SlowMotionConfig {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            duration: 0.0,
+            timer: 0.0,
+            previous_timestep: None,
+            effect_strength: 0.0,
+        }
+    }
+}
+
+impl SlowMotionConfig {
+    pub fn is_active(&self) -> bool {
+        self.previous_timestep.is_some()
+    }
+}
+
+// Runs everything at `scale` times normal speed for `duration` real seconds. A new one replaces
+// whatever is running.
+#[derive(Event)]
+pub struct SlowMotionEvent {
+    pub scale: f32,
+    pub duration: f32,
+}
+
+fn slow_motion_on_kill(mut death_events: EventReader<EnemyDeathEvent>, mut slow_motion_events: EventWriter<SlowMotionEvent>) {
+    if death_events.read().count() > 0 {
+        slow_motion_events.send(SlowMotionEvent {
+            scale: KILL_SLOW_MOTION_SCALE,
+            duration: KILL_SLOW_MOTION_DURATION,
+        });
+    }
+}
+
+fn set_speed(scale: f32, time: &mut Time<Virtual>, rapier_config: &mut RapierConfiguration, audio_query: &Query<&AudioSink>) {
+    time.set_relative_speed(scale);
+    for sink in audio_query.iter() {
+        sink.set_speed(scale);
+    }
+    rapier_config.timestep_mode = TimestepMode::Fixed {
+        dt: PHYSICS_DT * scale,
+        substeps: 1,
+    };
+}
+
+fn start_slow_motion(
+    mut slow_motion_events: EventReader<SlowMotionEvent>,
+    mut config: ResMut<SlowMotionConfig>,
+    mut time: ResMut<Time<Virtual>>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    audio_query: Query<&AudioSink>,
+) {
+    let Some(event) = slow_motion_events.read().last() else {
+        return;
+    };
+    let scale = event.scale.clamp(MIN_SCALE, 1.0);
+    if config.previous_timestep.is_none() {
+        config.previous_timestep = Some(rapier_config.timestep_mode);
+    }
+    config.scale = scale;
+    config.duration = event.duration.max(0.0);
+    config.timer = 0.0;
+    set_speed(scale, &mut time, &mut rapier_config, &audio_query);
+    println!("Slow motion at {:.2}x for {:.1}s", scale, config.duration);
+}
+
+// Counts down in real time, but not while photo mode has the game paused
+fn slow_motion_system(
+    mut config: ResMut<SlowMotionConfig>,
+    mut time: ResMut<Time<Virtual>>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+    audio_query: Query<&AudioSink>,
+    real_time: Res<Time<Real>>,
+) {
+    if !config.is_active() || time.is_paused() {
+        return;
+    }
+    config.timer += real_time.delta_seconds();
+    if config.timer < config.duration {
+        // Sounds that started since slow motion began play slowed down too
+        for sink in audio_query.iter() {
+            if sink.speed() != config.scale {
+                sink.set_speed(config.scale);
+            }
+        }
+        return;
+    }
+    set_speed(1.0, &mut time, &mut rapier_config, &audio_query);
+    if let Some(previous) = config.previous_timestep.take() {
+        rapier_config.timestep_mode = previous;
+    }
+    config.scale = 1.0;
+    println!("Slow motion over");
+}
+
+// Eases the tone mapping pass's desaturation and vignette in while slow motion is strong enough
+fn slow_motion_effects(
+    mut config: ResMut<SlowMotionConfig>,
+    mut tone_mapping: ResMut<ToneMappingConfig>,
+    real_time: Res<Time<Real>>,
+) {
+    let target = if config.is_active() && config.scale < SLOW_MOTION_VFX_SCALE { 1.0 } else { 0.0 };
+    if config.effect_strength == target {
+        return;
+    }
+    let step = real_time.delta_seconds() / SLOW_MOTION_VFX_TIME;
+    config.effect_strength += (target - config.effect_strength).clamp(-step, step);
+    tone_mapping.desaturation = config.effect_strength * SLOW_MOTION_DESATURATION;
+    tone_mapping.vignette = config.effect_strength * SLOW_MOTION_VIGNETTE;
+}
@@ -0,0 +1,182 @@
+use bevy::prelude::*;
+use crate::camera::{math, CameraSettings, ThirdPersonCamera};
+use crate::input_map::{Action, InputMap};
+use crate::photo_mode::PhotoMode;
+use crate::player::PlayerIndex;
+
+pub struct SpectatorPlugin;
+
+impl Plugin for SpectatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SpectatorMode>()
+            .add_systems(Startup, setup_spectator_hud)
+            .add_systems(Update, (toggle_spectator, fly_spectator_camera, update_spectator_hud).chain());
+    }
+}
+
+// Radians of look per pixel of mouse motion at sensitivity 1, like photo mode
+const SPECTATOR_LOOK_SENSITIVITY: f32 = 0.002;
+// Metres per second
+const SPECTATOR_SPEED: f32 = 10.0;
+
+// A free-flying camera of its own that stands in for the first player's. The game keeps running,
+// and the player can still be walked about with WASD while the camera is flown with the arrow keys,
+// Page Up and Page Down and the mouse.
+#[derive(Component)]
+pub struct SpectatorCamera {
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+// The spectator camera once it's been made, and whether it's the one drawing
+#[derive(Resource, Default)]
+pub struct SpectatorMode {
+    camera: Option<Entity>,
+    active: bool,
+}
+
+impl SpectatorMode {
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+}
+
+#[derive(Component)]
+struct SpectatorHudText;
+
+fn setup_spectator_hud(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "SPECTATOR - O to go back to the player camera",
+            TextStyle {
+                font_size: 20.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(10.0),
+            left: Val::Px(10.0),
+            ..default()
+        }),
+        Visibility::Hidden,
+        SpectatorHudText,
+    ));
+}
+
+// O swaps which of the first player's camera and the spectator camera is active. The player camera
+// is left alone while it's off, so coming back picks up exactly where it was; the spectator camera
+// is made the first time, in the same place, and stays where it was left after that.
+fn toggle_spectator(
+    mut commands: Commands,
+    input_map: Res<InputMap>,
+    photo_mode: Res<PhotoMode>,
+    mut spectator: ResMut<SpectatorMode>,
+    mut player_camera_query: Query<(&PlayerIndex, &mut Camera, &Transform, &Projection), (With<ThirdPersonCamera>, Without<SpectatorCamera>)>,
+    mut spectator_query: Query<&mut Camera, With<SpectatorCamera>>,
+) {
+    if !input_map.just_pressed(Action::ToggleSpectator) {
+        return;
+    }
+    // Photo mode is flying the player camera
+    if photo_mode.is_active() {
+        return;
+    }
+    let Some((_, mut player_camera, transform, projection)) = player_camera_query.iter_mut().find(|(index, ..)| index.0 == 0) else {
+        return;
+    };
+
+    spectator.active = !spectator.active;
+    player_camera.is_active = !spectator.active;
+    match spectator.camera.and_then(|entity| spectator_query.get_mut(entity).ok()) {
+        Some(mut camera) => {
+            camera.is_active = spectator.active;
+            // Takes over the player's part of the window, which split-screen may have moved
+            camera.viewport = player_camera.viewport.clone();
+        }
+        None => {
+            let forward = transform.forward();
+            let entity = commands
+                .spawn((
+                    Camera3dBundle {
+                        camera: Camera {
+                            order: player_camera.order,
+                            viewport: player_camera.viewport.clone(),
+                            ..default()
+                        },
+                        projection: projection.clone(),
+                        transform: *transform,
+                        ..default()
+                    },
+                    SpectatorCamera {
+                        yaw: (-forward.x).atan2(-forward.z),
+                        pitch: math::clamp_pitch(forward.y.clamp(-1.0, 1.0).asin(), math::MAX_PITCH),
+                    },
+                ))
+                .id();
+            spectator.camera = Some(entity);
+        }
+    }
+    if spectator.active {
+        println!("=== SPECTATOR CAMERA LIVE ===");
+    } else {
+        println!("=== PLAYER CAMERA LIVE ===");
+    }
+}
+
+fn fly_spectator_camera(
+    input_map: Res<InputMap>,
+    spectator: Res<SpectatorMode>,
+    settings: Res<CameraSettings>,
+    mut spectator_query: Query<(&mut SpectatorCamera, &mut Transform)>,
+    time: Res<Time>,
+) {
+    if !spectator.is_active() {
+        return;
+    }
+    let Some((mut camera, mut transform)) = spectator.camera.and_then(|entity| spectator_query.get_mut(entity).ok()) else {
+        return;
+    };
+    let input = input_map.for_player(0);
+
+    let look = input.look_delta()
+        * Vec2::new(settings.horizontal_sensitivity, settings.vertical_sensitivity)
+        * settings.invert()
+        * SPECTATOR_LOOK_SENSITIVITY;
+    camera.yaw = math::wrap_angle(camera.yaw - look.x);
+    camera.pitch = math::clamp_pitch(camera.pitch - look.y, math::MAX_PITCH);
+    transform.rotation = math::yaw_pitch_rotation(camera.yaw, camera.pitch);
+
+    // Along the view, pitch included, with Page Up and Page Down straight up and down
+    let mut movement = Vec3::ZERO;
+    if input.pressed(Action::SpectatorForward) {
+        movement += transform.forward();
+    }
+    if input.pressed(Action::SpectatorBack) {
+        movement -= transform.forward();
+    }
+    if input.pressed(Action::SpectatorRight) {
+        movement += transform.right();
+    }
+    if input.pressed(Action::SpectatorLeft) {
+        movement -= transform.right();
+    }
+    if input.pressed(Action::SpectatorUp) {
+        movement += Vec3::Y;
+    }
+    if input.pressed(Action::SpectatorDown) {
+        movement -= Vec3::Y;
+    }
+    transform.translation += movement.normalize_or_zero() * SPECTATOR_SPEED * time.delta_seconds();
+}
+
+fn update_spectator_hud(spectator: Res<SpectatorMode>, mut text_query: Query<&mut Visibility, With<SpectatorHudText>>) {
+    if !spectator.is_changed() {
+        return;
+    }
+    let Ok(mut visibility) = text_query.get_single_mut() else {
+        return;
+    };
+    *visibility = if spectator.is_active() { Visibility::Inherited } else { Visibility::Hidden };
+}
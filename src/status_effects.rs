@@ -0,0 +1,271 @@
+use bevy::pbr::NotShadowCaster;
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use bevy_rapier3d::prelude::*;
+use std::collections::HashMap;
+use crate::camera::ThirdPersonCamera;
+use crate::combat::DamageEvent;
+use crate::health::Dead;
+use crate::player::player_movement;
+
+pub struct StatusEffectsPlugin;
+
+impl Plugin for StatusEffectsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ApplyStatusEffect>()
+            .add_systems(Startup, setup_status_icons)
+            .add_systems(Update, (
+                apply_status_effects,
+                status_effect_system,
+                update_status_icons,
+                face_status_icons,
+            ).chain().after(player_movement));
+    }
+}
+
+// Seconds between damage ticks; magnitude is the damage per tick
+const POISON_TICK: f32 = 1.0;
+const BURNING_TICK: f32 = 0.5;
+// Icons sit in a row this high above the entity's origin
+const ICON_HEIGHT: f32 = 1.8;
+const ICON_SIZE: f32 = 0.25;
+const ICON_SPACING: f32 = 0.3;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(not(feature = "dev-tools"), allow(dead_code))]
+pub enum EffectKind {
+    Poison,
+    // Can't move, but can still jump
+    Stun,
+    // Can't move or jump, and horizontal momentum is stopped
+    Freeze,
+    Burning,
+    // Speed is multiplied by the magnitude
+    Slow,
+}
+
+impl EffectKind {
+    const ALL: [EffectKind; 5] = [EffectKind::Poison, EffectKind::Stun, EffectKind::Freeze, EffectKind::Burning, EffectKind::Slow];
+
+    fn color(self) -> Color {
+        match self {
+            EffectKind::Poison => Color::rgb(0.3, 0.9, 0.2),
+            EffectKind::Stun => Color::rgb(1.0, 0.9, 0.2),
+            EffectKind::Freeze => Color::rgb(0.6, 0.9, 1.0),
+            EffectKind::Burning => Color::rgb(1.0, 0.45, 0.1),
+            EffectKind::Slow => Color::rgb(0.6, 0.3, 0.9),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ActiveEffect {
+    pub kind: EffectKind,
+    // Seconds left
+    pub duration: f32,
+    pub magnitude: f32,
+    // Time since the last damage tick, for effects that deal damage
+    since_tick: f32,
+}
+
+impl ActiveEffect {
+    pub fn new(kind: EffectKind, duration: f32, magnitude: f32) -> Self {
+        Self {
+            kind,
+            duration,
+            magnitude,
+            since_tick: 0.0,
+        }
+    }
+}
+
+#[derive(Component, Default, Debug)]
+pub struct StatusEffects(pub Vec<ActiveEffect>);
+
+impl StatusEffects {
+    // The same kind again refreshes the one already running instead of stacking
+    pub fn apply(&mut self, effect: ActiveEffect) {
+        match self.0.iter_mut().find(|active| active.kind == effect.kind) {
+            Some(active) => {
+                active.duration = active.duration.max(effect.duration);
+                active.magnitude = effect.magnitude;
+            }
+            None => self.0.push(effect),
+        }
+    }
+
+    fn has(&self, kind: EffectKind) -> bool {
+        self.0.iter().any(|effect| effect.kind == kind)
+    }
+
+    // Scales the player's target speed
+    pub fn speed_multiplier(&self) -> f32 {
+        if self.has(EffectKind::Stun) || self.has(EffectKind::Freeze) {
+            return 0.0;
+        }
+        self.0
+            .iter()
+            .filter(|effect| effect.kind == EffectKind::Slow)
+            .fold(1.0, |multiplier, effect| multiplier * effect.magnitude.clamp(0.0, 1.0))
+    }
+
+    pub fn can_jump(&self) -> bool {
+        !self.has(EffectKind::Freeze)
+    }
+}
+
+#[derive(Event)]
+pub struct ApplyStatusEffect {
+    pub entity: Entity,
+    pub effect: ActiveEffect,
+}
+
+#[derive(Resource)]
+struct StatusIconAssets {
+    mesh: Handle<Mesh>,
+    materials: HashMap<EffectKind, Handle<StandardMaterial>>,
+}
+
+#[derive(Component)]
+struct StatusIcon;
+
+fn setup_status_icons(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let materials = EffectKind::ALL
+        .into_iter()
+        .map(|kind| {
+            let material = materials.add(StandardMaterial {
+                base_color: kind.color(),
+                unlit: true,
+                ..default()
+            });
+            (kind, material)
+        })
+        .collect();
+    commands.insert_resource(StatusIconAssets {
+        mesh: meshes.add(Mesh::from(shape::Quad::new(Vec2::splat(ICON_SIZE)))),
+        materials,
+    });
+}
+
+// Only entities that can carry effects take them
+fn apply_status_effects(
+    mut apply_events: EventReader<ApplyStatusEffect>,
+    mut effects_query: Query<&mut StatusEffects, Without<Dead>>,
+) {
+    for event in apply_events.read() {
+        if let Ok(mut effects) = effects_query.get_mut(event.entity) {
+            println!("{:?} is now affected by {:?} for {}s", event.entity, event.effect.kind, event.effect.duration);
+            effects.apply(event.effect);
+        }
+    }
+}
+
+fn status_effect_system(
+    mut effects_query: Query<(Entity, &mut StatusEffects, Option<&mut Velocity>, Has<Dead>)>,
+    mut damage_events: EventWriter<DamageEvent>,
+    time: Res<Time>,
+) {
+    let delta_seconds = time.delta_seconds();
+    for (entity, mut effects, velocity, dead) in effects_query.iter_mut() {
+        if effects.0.is_empty() {
+            continue;
+        }
+        // Dying clears everything, so a respawn starts clean
+        if dead {
+            effects.0.clear();
+            continue;
+        }
+
+        for effect in effects.0.iter_mut() {
+            effect.duration -= delta_seconds;
+            let tick = match effect.kind {
+                EffectKind::Poison => POISON_TICK,
+                EffectKind::Burning => BURNING_TICK,
+                _ => continue,
+            };
+            effect.since_tick += delta_seconds;
+            if effect.since_tick >= tick {
+                effect.since_tick -= tick;
+                damage_events.send(DamageEvent {
+                    entity,
+                    amount: effect.magnitude,
+                });
+            }
+        }
+        // Frozen in place sideways; gravity still brings them down rather than leaving them hanging
+        if effects.has(EffectKind::Freeze) {
+            if let Some(mut velocity) = velocity {
+                velocity.linvel.x = 0.0;
+                velocity.linvel.z = 0.0;
+                velocity.linvel.y = velocity.linvel.y.min(0.0);
+                velocity.angvel = Vec3::ZERO;
+            }
+        }
+
+        let before = effects.0.len();
+        effects.0.retain(|effect| effect.duration > 0.0);
+        if effects.0.len() != before {
+            println!("{:?} status effects now {:?}", entity, effects.0.iter().map(|effect| effect.kind).collect::<Vec<_>>());
+        }
+    }
+}
+
+// Rebuilds an entity's row of icons whenever its set of effects changes
+fn update_status_icons(
+    mut commands: Commands,
+    effects_query: Query<(Entity, &StatusEffects, Option<&Children>), Changed<StatusEffects>>,
+    icon_query: Query<(), With<StatusIcon>>,
+    mut shown: Local<HashMap<Entity, Vec<EffectKind>>>,
+    assets: Res<StatusIconAssets>,
+) {
+    for (entity, effects, children) in effects_query.iter() {
+        let kinds: Vec<EffectKind> = effects.0.iter().map(|effect| effect.kind).collect();
+        if shown.get(&entity) == Some(&kinds) {
+            continue;
+        }
+        for child in children.into_iter().flatten() {
+            if icon_query.contains(*child) {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+        let row_start = -(kinds.len() as f32 - 1.0) * ICON_SPACING * 0.5;
+        commands.entity(entity).with_children(|parent| {
+            for (i, kind) in kinds.iter().enumerate() {
+                parent.spawn((
+                    StatusIcon,
+                    PbrBundle {
+                        mesh: assets.mesh.clone(),
+                        material: assets.materials[kind].clone(),
+                        transform: Transform::from_xyz(row_start + i as f32 * ICON_SPACING, ICON_HEIGHT, 0.0),
+                        ..default()
+                    },
+                    NotShadowCaster,
+                ));
+            }
+        });
+        shown.insert(entity, kinds);
+    }
+}
+
+// Turns the icons to face the first player's camera, undoing their parent's rotation
+fn face_status_icons(
+    camera_query: Query<&GlobalTransform, With<ThirdPersonCamera>>,
+    parent_query: Query<&GlobalTransform, Without<StatusIcon>>,
+    mut icon_query: Query<(&Parent, &mut Transform), With<StatusIcon>>,
+) {
+    let Some(camera) = camera_query.iter().next() else {
+        return;
+    };
+    let (_, camera_rotation, _) = camera.to_scale_rotation_translation();
+    for (parent, mut transform) in icon_query.iter_mut() {
+        let Ok(parent_transform) = parent_query.get(parent.get()) else {
+            continue;
+        };
+        let (_, parent_rotation, _) = parent_transform.to_scale_rotation_translation();
+        transform.rotation = parent_rotation.inverse() * camera_rotation;
+    }
+}
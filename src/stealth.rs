@@ -0,0 +1,219 @@
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::Velocity;
+use std::f32::consts::PI;
+use crate::combat::Enemy;
+use crate::day_night::NOON_ILLUMINANCE;
+use crate::debug::Noclip;
+use crate::health::Dead;
+use crate::input_map::{Action, InputMap};
+use crate::player::{Player, PlayerIndex};
+
+pub struct StealthPlugin;
+
+impl Plugin for StealthPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_visibility_hud)
+            .add_systems(Update, (add_stealth_components, player_visibility_system, stealth_detection_system, update_visibility_hud).chain());
+    }
+}
+
+const MAX_VISIBILITY: f32 = 100.0;
+// An enemy notices the player once its meter passes this
+const DEFAULT_DETECTION_THRESHOLD: f32 = 50.0;
+// Enemies can't see further than this, and see less the further away the player is
+const SIGHT_RANGE: f32 = 25.0;
+// Meter points per second an enemy gains watching a fully visible player up close
+const DETECTION_RATE: f32 = 60.0;
+// Meter points per second an enemy loses while it can't see anything
+const DETECTION_DECAY: f32 = 15.0;
+// Standing still still shows this much of the player, so nobody goes unseen in broad daylight
+const STILL_VISIBILITY: f32 = 0.2;
+const CROUCH_VISIBILITY: f32 = 0.5;
+// Point light intensity that fully lights the player standing right next to it
+const POINT_LIGHT_REFERENCE: f32 = 800.0;
+// The visibility HUD is a half ring of this many dots
+const ARC_SEGMENTS: usize = 20;
+const ARC_RADIUS: f32 = 40.0;
+const ARC_DOT_SIZE: f32 = 6.0;
+const ARC_LIT_COLOR: Color = Color::rgba(0.75, 0.75, 0.75, 0.9);
+const ARC_UNLIT_COLOR: Color = Color::rgba(0.3, 0.3, 0.3, 0.4);
+
+// On a player, how visible they are right now. On an enemy, how much of the player it's seen;
+// once that passes the enemy's detection threshold it gives chase.
+#[derive(Component)]
+pub struct VisibilityMeter {
+    pub current: f32,
+    pub max: f32,
+}
+
+impl Default for VisibilityMeter {
+    fn default() -> Self {
+        Self {
+            current: 0.0,
+            max: MAX_VISIBILITY,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct StealthDetection {
+    pub detection_threshold: f32,
+}
+
+// Idle enemies stay put until they notice a player, then chase for good
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnemyState {
+    Idle,
+    Chase,
+}
+
+#[derive(Component)]
+struct VisibilityArcSegment(usize);
+
+fn setup_visibility_hud(mut commands: Commands) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(20.0),
+                right: Val::Px(20.0),
+                width: Val::Px(ARC_RADIUS * 2.0 + ARC_DOT_SIZE),
+                height: Val::Px(ARC_RADIUS + ARC_DOT_SIZE),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            // Left to right over the top
+            for index in 0..ARC_SEGMENTS {
+                let angle = PI * (1.0 - index as f32 / (ARC_SEGMENTS - 1) as f32);
+                parent.spawn((
+                    NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            left: Val::Px(ARC_RADIUS + ARC_RADIUS * angle.cos()),
+                            top: Val::Px(ARC_RADIUS - ARC_RADIUS * angle.sin()),
+                            width: Val::Px(ARC_DOT_SIZE),
+                            height: Val::Px(ARC_DOT_SIZE),
+                            ..default()
+                        },
+                        background_color: ARC_UNLIT_COLOR.into(),
+                        ..default()
+                    },
+                    VisibilityArcSegment(index),
+                ));
+            }
+        });
+}
+
+fn add_stealth_components(
+    mut commands: Commands,
+    player_query: Query<Entity, Added<Player>>,
+    enemy_query: Query<Entity, Added<Enemy>>,
+) {
+    for entity in player_query.iter() {
+        commands.entity(entity).insert(VisibilityMeter::default());
+    }
+    for entity in enemy_query.iter() {
+        commands.entity(entity).insert((
+            VisibilityMeter::default(),
+            StealthDetection {
+                detection_threshold: DEFAULT_DETECTION_THRESHOLD,
+            },
+            EnemyState::Idle,
+        ));
+    }
+}
+
+// 0 to 1, roughly how lit a point is: the sun by its height in the sky, nearby point lights by
+// distance, and the ambient light on top
+fn light_level(
+    position: Vec3,
+    ambient_light: &AmbientLight,
+    sun_query: &Query<(&DirectionalLight, &GlobalTransform)>,
+    point_light_query: &Query<(&PointLight, &GlobalTransform)>,
+) -> f32 {
+    let sun: f32 = sun_query
+        .iter()
+        .map(|(light, transform)| light.illuminance / NOON_ILLUMINANCE * (-transform.forward().y).max(0.0))
+        .sum();
+    let points: f32 = point_light_query
+        .iter()
+        .filter(|(light, _)| light.range > 0.0)
+        .map(|(light, transform)| {
+            let falloff = (1.0 - transform.translation().distance(position) / light.range).max(0.0);
+            light.intensity / POINT_LIGHT_REFERENCE * falloff * falloff
+        })
+        .sum();
+    (ambient_light.brightness + sun + points).clamp(0.0, 1.0)
+}
+
+// How visible each player is: how lit they are, times how fast they're going, halved while crouching
+fn player_visibility_system(
+    input_map: Res<InputMap>,
+    ambient_light: Res<AmbientLight>,
+    sun_query: Query<(&DirectionalLight, &GlobalTransform)>,
+    point_light_query: Query<(&PointLight, &GlobalTransform)>,
+    mut player_query: Query<(&PlayerIndex, &Player, &GlobalTransform, &Velocity, &mut VisibilityMeter, Has<Dead>, Has<Noclip>)>,
+) {
+    for (index, player, transform, velocity, mut meter, dead, noclip) in player_query.iter_mut() {
+        if dead || noclip {
+            meter.current = 0.0;
+            continue;
+        }
+        let light = light_level(transform.translation(), &ambient_light, &sun_query, &point_light_query);
+        let max_speed = player.speed * player.sprint_multiplier;
+        let speed = Vec3::new(velocity.linvel.x, 0.0, velocity.linvel.z).length();
+        let movement = if max_speed > 0.0 { (speed / max_speed).min(1.0) } else { 0.0 };
+        let mut visibility = light * (STILL_VISIBILITY + (1.0 - STILL_VISIBILITY) * movement);
+        if input_map.for_player(index.0).pressed(Action::Crouch) {
+            visibility *= CROUCH_VISIBILITY;
+        }
+        meter.current = visibility * meter.max;
+    }
+}
+
+// Each idle enemy's meter fills with the most visible player in sight, less the further away they
+// are, and drains while there's nobody to see
+fn stealth_detection_system(
+    player_query: Query<(&GlobalTransform, &VisibilityMeter), With<Player>>,
+    mut enemy_query: Query<(Entity, &GlobalTransform, &mut VisibilityMeter, &StealthDetection, &mut EnemyState), Without<Player>>,
+    time: Res<Time>,
+) {
+    let delta_seconds = time.delta_seconds();
+    for (entity, enemy_transform, mut meter, detection, mut state) in enemy_query.iter_mut() {
+        if *state == EnemyState::Chase {
+            continue;
+        }
+        let seen = player_query
+            .iter()
+            .map(|(player_transform, player_meter)| {
+                let distance = player_transform.translation().distance(enemy_transform.translation());
+                let closeness = (1.0 - distance / SIGHT_RANGE).max(0.0);
+                player_meter.current / player_meter.max * closeness
+            })
+            .fold(0.0, f32::max);
+        let change = if seen > 0.0 { seen * DETECTION_RATE } else { -DETECTION_DECAY };
+        meter.current = (meter.current + change * delta_seconds).clamp(0.0, meter.max);
+        if meter.current > detection.detection_threshold {
+            *state = EnemyState::Chase;
+            println!("Enemy {:?} spotted a player", entity);
+        }
+    }
+}
+
+fn update_visibility_hud(
+    player_query: Query<(&PlayerIndex, &VisibilityMeter)>,
+    mut segment_query: Query<(&VisibilityArcSegment, &mut BackgroundColor)>,
+) {
+    let Some((_, meter)) = player_query.iter().find(|(index, _)| index.0 == 0) else {
+        return;
+    };
+    let lit = (meter.current / meter.max * ARC_SEGMENTS as f32).round() as usize;
+    for (segment, mut color) in segment_query.iter_mut() {
+        let wanted = if segment.0 < lit { ARC_LIT_COLOR } else { ARC_UNLIT_COLOR };
+        if color.0 != wanted {
+            color.0 = wanted;
+        }
+    }
+}
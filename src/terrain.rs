@@ -1,59 +1,691 @@
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 use bevy::prelude::shape;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::render::texture::{ImageAddressMode, ImageSampler, ImageSamplerDescriptor};
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use futures_lite::future;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use crate::camera::CameraTargetable;
+use crate::fixed_camera::{spawn_fixed_camera_zone, FixedCameraZone};
+use crate::cloth::spawn_flag;
+use crate::conveyor::{spawn_conveyor, ConveyorBelt};
+use crate::level::{load_level_file, spawn_level, LevelShape};
+use crate::occlusion::OcclusionFadeable;
+use crate::noise::ValueNoise;
+use crate::player::Players;
+use crate::interaction::{Interactable, Lever, Sign};
+use crate::vulkan_renderer::{VulkanObject, GEOMETRY_SUBPASS};
+use std::collections::HashMap;
+use std::sync::Arc;
 
 pub struct TerrainPlugin;
 
 impl Plugin for TerrainPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_terrain);
+        app.init_resource::<SpawnPointRegistry>()
+            .init_resource::<TerrainConfig>()
+            .add_systems(Startup, spawn_terrain)
+            // Startup commands are applied by PostStartup, so every spawn point exists by then
+            .add_systems(PostStartup, collect_spawn_points)
+            .add_systems(Update, (collect_spawn_points, move_platforms, poll_terrain_tasks, terrain_lod_system.after(poll_terrain_tasks)));
     }
 }
 
+// Where players can (re)appear; the highest priority one is used for the initial spawn
+#[derive(Component)]
+pub struct SpawnPoint {
+    pub id: String,
+    pub priority: i32,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct SpawnPointEntry {
+    pub position: Vec3,
+    pub priority: i32,
+}
+
+#[derive(Resource, Default)]
+pub struct SpawnPointRegistry {
+    pub points: HashMap<String, SpawnPointEntry>,
+}
+
+impl SpawnPointRegistry {
+    pub fn get(&self, id: &str) -> Option<Vec3> {
+        self.points.get(id).map(|entry| entry.position)
+    }
+
+    // Highest priority spawn point, ties broken by id so the choice is stable
+    pub fn best(&self) -> Option<(&str, Vec3)> {
+        self.points
+            .iter()
+            .max_by(|(id_a, a), (id_b, b)| a.priority.cmp(&b.priority).then_with(|| id_b.cmp(id_a)))
+            .map(|(id, entry)| (id.as_str(), entry.position))
+    }
+}
+
+#[derive(Component)]
+pub struct MovingPlatform {
+    pub start: Vec3,
+    pub end: Vec3,
+    pub period: f32,
+    pub elapsed: f32,
+    pub active: bool,
+}
+
+// Where to stand on each floating platform, in spawn order
+#[derive(Resource, Default)]
+#[cfg_attr(not(feature = "dev-tools"), allow(dead_code))]
+pub struct FloatingPlatforms {
+    pub standing_positions: Vec<Vec3>,
+}
+
+// The playable area, centred on the origin. It takes in the floating platforms as well as the
+// island, so the edge walls don't cut the platforms off.
+#[derive(Resource, Clone)]
+pub struct TerrainConfig {
+    pub width: f32,
+    pub depth: f32,
+    // The edge walls reach this far above and below the ground
+    pub wall_height: f32,
+    pub wall_depth: f32,
+    // The main island is a square `island_size` metres across with `island_resolution` vertices
+    // a side. Its hills rise up to `island_height` above y = 0, and its sides drop `island_depth`
+    // below that.
+    pub island_size: f32,
+    pub island_resolution: usize,
+    pub island_height: f32,
+    pub island_depth: f32,
+    // Roughly how wide the biggest hills are, in metres, and how many octaves of smaller bumps
+    // go on top of them
+    pub island_feature_size: f32,
+    pub island_octaves: u32,
+    // How far out from the middle, as a fraction of the way to the edge, the hills start to
+    // flatten out towards the cliff
+    pub island_falloff_start: f32,
+    // The same seed always makes the same island
+    pub island_seed: u32,
+}
+
+impl Default for TerrainConfig {
+    fn default() -> Self {
+        Self {
+            width: 64.0,
+            depth: 64.0,
+            wall_height: 20.0,
+            wall_depth: 20.0,
+            island_size: 40.0,
+            island_resolution: 128,
+            island_height: 2.0,
+            island_depth: 2.0,
+            island_feature_size: 12.0,
+            island_octaves: 4,
+            island_falloff_start: 0.6,
+            island_seed: 8,
+        }
+    }
+}
+
+// One of the invisible walls around the edge of the playable area
+#[derive(Component)]
+pub struct TerrainBoundary;
+
+// What a collider is made of, for effects like footprints that depend on it
+#[derive(Component, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SurfaceType {
+    Grass,
+    // Not on any collider; snow settles over the grass in weather
+    Snow,
+    Wood,
+    Stone,
+    // Next to no grip: the player slides a long way before stopping
+    Ice,
+}
+
+// The grass layer covering the island is visual only; its top is GRASS_TOP above the island's
+// collider, which follows height_at
+pub const GRASS_SIZE: f32 = 38.0;
+pub const GRASS_TOP: f32 = 0.15;
+const GRASS_COLOR: Color = Color::rgb(0.2, 0.8, 0.2);
+const GRASS_THICKNESS: f32 = 0.1;
+// The ice is a 3 x 3 grid of tiles centred here on the island, in x and z
+const ICE_CENTER: Vec2 = Vec2::new(10.0, 10.0);
+const ICE_TILE_SIZE: f32 = 2.5;
+const ICE_TILE_THICKNESS: f32 = 0.1;
+// The grass surface dips up to this far below GRASS_TOP, following the heightmap
+pub const GRASS_BUMP_HEIGHT: f32 = 0.03;
+
+// The grass layer is split into GRASS_CHUNKS x GRASS_CHUNKS chunks, each meshed at a resolution
+// that drops with distance from the nearest player
+const GRASS_CHUNKS: i32 = 4;
+const GRASS_CHUNK_SIZE: f32 = GRASS_SIZE / GRASS_CHUNKS as f32;
+const LOD_FULL_RESOLUTION: u32 = 64;
+const LOD_LEVELS: u8 = 3;
+// Chunk centre distances at which LOD 1 and LOD 2 take over
+const LOD_DISTANCES: [f32; 2] = [15.0, 30.0];
+
+// Resolution of the generated grass heightmap and the maps derived from it
+const TERRAIN_MAP_SIZE: usize = 128;
+// Times the maps repeat across the grass layer
+const GRASS_UV_TILES: f32 = 8.0;
+// Exaggerates the height slopes; larger makes bumpier lighting
+const NORMAL_STRENGTH: f32 = 4.0;
+const FLAT_ROUGHNESS: f32 = 0.6;
+const STEEP_ROUGHNESS: f32 = 1.0;
+
+// Tiling grass surface detail, generated once at startup
+#[derive(Resource)]
+pub struct TerrainMaps {
+    pub normal_map: Handle<Image>,
+    pub roughness_map: Handle<Image>,
+}
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ChunkCoord(pub IVec2);
+
+// 0 is full resolution, each level after halves it
+#[derive(Component)]
+pub struct TerrainLod {
+    pub lod_level: u8,
+}
+
+// Every chunk's mesh at every LOD, built as soon as the chunk is so switching is just swapping
+// handles
+#[derive(Resource)]
+pub struct TerrainLodMeshes {
+    pub meshes: HashMap<(ChunkCoord, u8), Handle<Mesh>>,
+}
+
+// A chunk's meshes at every LOD, full resolution first
+pub struct TerrainChunkData {
+    pub coord: ChunkCoord,
+    pub lod_meshes: Vec<Mesh>,
+}
+
+// On a chunk that's still being meshed in the background. It shows a flat stand-in until then,
+// and gets `material` with its real mesh.
+#[derive(Component)]
+pub struct TerrainChunkTask {
+    task: Task<TerrainChunkData>,
+    material: Handle<StandardMaterial>,
+}
+
+// Heights in 0..1 on a grid that wraps at the edges, so the derived maps tile seamlessly
+struct Heightmap {
+    size: usize,
+    heights: Vec<f32>,
+}
+
+impl Heightmap {
+    // A few octaves of value noise on lattices that divide the map evenly
+    fn generate(size: usize, rng: &mut impl Rng) -> Self {
+        let mut heights = vec![0.0; size * size];
+        let mut amplitude = 0.5;
+        let mut total_amplitude = 0.0;
+        for cells in [4, 8, 16, 32] {
+            let lattice: Vec<f32> = (0..cells * cells).map(|_| rng.gen()).collect();
+            let cell_size = size as f32 / cells as f32;
+            for y in 0..size {
+                for x in 0..size {
+                    let fx = x as f32 / cell_size;
+                    let fy = y as f32 / cell_size;
+                    let (x0, y0) = (fx as usize % cells, fy as usize % cells);
+                    let (x1, y1) = ((x0 + 1) % cells, (y0 + 1) % cells);
+                    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+                    let (tx, ty) = (smooth(fx.fract()), smooth(fy.fract()));
+                    let top = lattice[y0 * cells + x0] * (1.0 - tx) + lattice[y0 * cells + x1] * tx;
+                    let bottom = lattice[y1 * cells + x0] * (1.0 - tx) + lattice[y1 * cells + x1] * tx;
+                    heights[y * size + x] += (top * (1.0 - ty) + bottom * ty) * amplitude;
+                }
+            }
+            total_amplitude += amplitude;
+            amplitude *= 0.5;
+        }
+        for height in &mut heights {
+            *height /= total_amplitude;
+        }
+        Self { size, heights }
+    }
+
+    // Level at the top everywhere, for the stand-in shown while a chunk is generated
+    fn flat() -> Self {
+        Self { size: 1, heights: vec![1.0] }
+    }
+
+    fn height(&self, x: isize, y: isize) -> f32 {
+        let size = self.size as isize;
+        self.heights[(y.rem_euclid(size) * size + x.rem_euclid(size)) as usize]
+    }
+
+    // Bilinear height at a texture coordinate, wrapping like the maps do
+    fn sample(&self, uv: Vec2) -> f32 {
+        let texel = uv * self.size as f32 - 0.5;
+        let (x, y) = (texel.x.floor() as isize, texel.y.floor() as isize);
+        let (tx, ty) = (texel.x - x as f32, texel.y - y as f32);
+        let top = self.height(x, y) * (1.0 - tx) + self.height(x + 1, y) * tx;
+        let bottom = self.height(x, y + 1) * (1.0 - tx) + self.height(x + 1, y + 1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+
+    // Tangent space normal from a Sobel filter over the neighbouring heights
+    fn normal(&self, x: usize, y: usize) -> Vec3 {
+        let (x, y) = (x as isize, y as isize);
+        let h = |dx: isize, dy: isize| self.height(x + dx, y + dy);
+        let gradient_x = (h(1, -1) + 2.0 * h(1, 0) + h(1, 1)) - (h(-1, -1) + 2.0 * h(-1, 0) + h(-1, 1));
+        let gradient_y = (h(-1, 1) + 2.0 * h(0, 1) + h(1, 1)) - (h(-1, -1) + 2.0 * h(0, -1) + h(1, -1));
+        // The Sobel kernel weights sum to 8 on each side
+        let scale = NORMAL_STRENGTH / 8.0;
+        Vec3::new(-gradient_x * scale, -gradient_y * scale, 1.0).normalize()
+    }
+
+    // Normals packed as XYZ in RGB (0.5 being zero), W left at 1
+    fn normal_map(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.size * self.size * 4);
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let encoded = (self.normal(x, y) * 0.5 + 0.5) * 255.0;
+                data.extend_from_slice(&[encoded.x.round() as u8, encoded.y.round() as u8, encoded.z.round() as u8, 255]);
+            }
+        }
+        data
+    }
+
+    // Laid out for StandardMaterial's metallic_roughness_texture: roughness in G, metallic in B.
+    // Steeper texels are rougher.
+    fn roughness_map(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(self.size * self.size * 4);
+        for y in 0..self.size {
+            for x in 0..self.size {
+                let steepness = 1.0 - self.normal(x, y).z;
+                let roughness = FLAT_ROUGHNESS + (STEEP_ROUGHNESS - FLAT_ROUGHNESS) * (steepness * 4.0).min(1.0);
+                data.extend_from_slice(&[0, (roughness * 255.0).round() as u8, 0, 255]);
+            }
+        }
+        data
+    }
+}
+
+// Height of the island's ground at a point: a few octaves of noise hills, flattening out towards
+// the edge so the island ends in a cliff all the way round. Zero off the island.
+pub fn height_at(config: &TerrainConfig, x: f32, z: f32) -> f32 {
+    let distance = Vec2::new(x, z).length() / (config.island_size * 0.5);
+    let t = ((distance - config.island_falloff_start) / (1.0 - config.island_falloff_start).max(f32::EPSILON)).clamp(0.0, 1.0);
+    let falloff = 1.0 - t * t * (3.0 - 2.0 * t);
+    if falloff <= 0.0 {
+        return 0.0;
+    }
+    let noise = ValueNoise::new(config.island_seed);
+    let hills = noise.fractal(x / config.island_feature_size, z / config.island_feature_size, config.island_octaves);
+    hills * config.island_height * falloff
+}
+
+// The highest ground under a level rectangle centred on `center`, so something flat put there
+// rests on the hillside rather than sinking into it
+pub fn ground_under(config: &TerrainConfig, center: Vec2, half_extents: Vec2) -> f32 {
+    const SAMPLES: usize = 5;
+    let mut highest = f32::MIN;
+    for z in 0..SAMPLES {
+        for x in 0..SAMPLES {
+            let offset = Vec2::new(x as f32, z as f32) / (SAMPLES - 1) as f32 * 2.0 - 1.0;
+            let point = center + offset * half_extents;
+            highest = highest.max(height_at(config, point.x, point.y));
+        }
+    }
+    highest
+}
+
+// Heights of the island's vertices, in rows along +Z
+fn island_heights(config: &TerrainConfig) -> Vec<f32> {
+    let n = config.island_resolution.max(2);
+    let step = config.island_size / (n - 1) as f32;
+    let half = config.island_size * 0.5;
+    let mut heights = Vec::with_capacity(n * n);
+    for z in 0..n {
+        for x in 0..n {
+            heights.push(height_at(config, x as f32 * step - half, z as f32 * step - half));
+        }
+    }
+    heights
+}
+
+// The main island: the height_at surface with smooth normals and UVs laid flat across the whole
+// top, and straight sides down to a flat bottom `island_depth` below y = 0. The top is split into
+// triangles the same way as island_collider's heightfield, so the two match exactly.
+pub fn generate_island_mesh(config: &TerrainConfig) -> Mesh {
+    let n = config.island_resolution.max(2);
+    let step = config.island_size / (n - 1) as f32;
+    let half = config.island_size * 0.5;
+    let heights = island_heights(config);
+    let height = |x: usize, z: usize| heights[z * n + x];
+
+    let mut positions = Vec::with_capacity(n * n);
+    let mut normals = Vec::with_capacity(n * n);
+    let mut uvs = Vec::with_capacity(n * n);
+    for z in 0..n {
+        for x in 0..n {
+            positions.push([x as f32 * step - half, height(x, z), z as f32 * step - half]);
+            // Central differences, one-sided along the edges
+            let (x0, x1) = (x.saturating_sub(1), (x + 1).min(n - 1));
+            let (z0, z1) = (z.saturating_sub(1), (z + 1).min(n - 1));
+            let slope_x = (height(x1, z) - height(x0, z)) / ((x1 - x0) as f32 * step);
+            let slope_z = (height(x, z1) - height(x, z0)) / ((z1 - z0) as f32 * step);
+            normals.push(Vec3::new(-slope_x, 1.0, -slope_z).normalize().to_array());
+            uvs.push([x as f32 / (n - 1) as f32, z as f32 / (n - 1) as f32]);
+        }
+    }
+
+    let mut indices = Vec::with_capacity((n - 1) * (n - 1) * 6);
+    for z in 0..n - 1 {
+        for x in 0..n - 1 {
+            let i = (z * n + x) as u32;
+            let below = i + n as u32;
+            indices.extend_from_slice(&[i, below, i + 1, i + 1, below, below + 1]);
+        }
+    }
+
+    // The cliffs, each edge's vertices in order with the direction it faces
+    let bottom = -config.island_depth;
+    let edges: [(Vec<usize>, Vec3); 4] = [
+        ((0..n).collect(), Vec3::NEG_Z),
+        ((0..n).map(|x| (n - 1) * n + x).collect(), Vec3::Z),
+        ((0..n).map(|z| z * n).collect(), Vec3::NEG_X),
+        ((0..n).map(|z| z * n + n - 1).collect(), Vec3::X),
+    ];
+    for (edge, outward) in edges {
+        let first = positions.len() as u32;
+        for &i in &edge {
+            let [x, y, z] = positions[i];
+            let uv = uvs[i];
+            positions.push([x, y, z]);
+            positions.push([x, bottom, z]);
+            normals.extend_from_slice(&[outward.to_array(), outward.to_array()]);
+            uvs.extend_from_slice(&[uv, uv]);
+        }
+        // Wound to face out, whichever way along the edge the vertices run
+        let along = Vec3::from(positions[edge[1]]) - Vec3::from(positions[edge[0]]);
+        let flip = Vec3::NEG_Y.cross(along).dot(outward) < 0.0;
+        for k in 0..edge.len() as u32 - 1 {
+            let (top_a, bottom_a) = (first + k * 2, first + k * 2 + 1);
+            let (top_b, bottom_b) = (top_a + 2, bottom_a + 2);
+            if flip {
+                indices.extend_from_slice(&[top_a, top_b, bottom_a, top_b, bottom_b, bottom_a]);
+            } else {
+                indices.extend_from_slice(&[top_a, bottom_a, top_b, top_b, bottom_a, bottom_b]);
+            }
+        }
+    }
+
+    // The underside, seen from below
+    let first = positions.len() as u32;
+    for (x, z) in [(-half, -half), (half, -half), (-half, half), (half, half)] {
+        positions.push([x, bottom, z]);
+        normals.push(Vec3::NEG_Y.to_array());
+        uvs.push([x / config.island_size + 0.5, z / config.island_size + 0.5]);
+    }
+    indices.extend_from_slice(&[first, first + 1, first + 2, first + 1, first + 3, first + 2]);
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+// The top of generate_island_mesh as a heightfield; nothing goes under the cliffs to need the rest
+pub fn island_collider(config: &TerrainConfig) -> Collider {
+    let n = config.island_resolution.max(2);
+    let heights = island_heights(config);
+    // Rapier takes them column by column, with rows along z and columns along x
+    let columns = (0..n).flat_map(|x| (0..n).map(move |z| (x, z))).map(|(x, z)| heights[z * n + x]).collect();
+    Collider::heightfield(columns, n, n, Vec3::new(config.island_size, 1.0, config.island_size))
+}
+
+// A linear, repeating RGBA8 texture, as normal maps must not be sRGB decoded
+fn tiling_texture(size: usize, data: Vec<u8>) -> Image {
+    let mut image = Image::new(
+        Extent3d {
+            width: size as u32,
+            height: size as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8Unorm,
+    );
+    image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+        address_mode_u: ImageAddressMode::Repeat,
+        address_mode_v: ImageAddressMode::Repeat,
+        ..ImageSamplerDescriptor::linear()
+    });
+    image
+}
+
+// The grass box with its UVs scaled to repeat the maps and tangents for normal mapping
+// Texture coordinate of a point on the grass, repeating GRASS_UV_TILES times across it
+fn grass_uv(x: f32, z: f32) -> Vec2 {
+    Vec2::new(x / GRASS_SIZE + 0.5, z / GRASS_SIZE + 0.5) * GRASS_UV_TILES
+}
+
+fn grass_height(heightmap: &Heightmap, config: &TerrainConfig, x: f32, z: f32) -> f32 {
+    height_at(config, x, z) + GRASS_TOP - (1.0 - heightmap.sample(grass_uv(x, z))) * GRASS_BUMP_HEIGHT
+}
+
+// One chunk of the grass surface, centred on its own origin, with `resolution` quads per side.
+// Skirts hang down from every edge so the gaps between neighbours at different LODs, and the
+// island's edge, are never see-through.
+fn grass_chunk_mesh(heightmap: &Heightmap, config: &TerrainConfig, coord: ChunkCoord, resolution: u32) -> Mesh {
+    let n = resolution as usize + 1;
+    let center = grass_chunk_center(coord);
+    let step = GRASS_CHUNK_SIZE / resolution as f32;
+    let half = GRASS_CHUNK_SIZE * 0.5;
+
+    let mut positions = Vec::with_capacity(n * n);
+    let mut normals = Vec::with_capacity(n * n);
+    let mut uvs = Vec::with_capacity(n * n);
+    for z in 0..n {
+        for x in 0..n {
+            let local = Vec2::new(x as f32 * step - half, z as f32 * step - half);
+            let (world_x, world_z) = (center.x + local.x, center.z + local.y);
+            let height = |dx: f32, dz: f32| grass_height(heightmap, config, world_x + dx, world_z + dz);
+            positions.push([local.x, height(0.0, 0.0), local.y]);
+            let normal = Vec3::new(height(-step, 0.0) - height(step, 0.0), 2.0 * step, height(0.0, -step) - height(0.0, step));
+            normals.push(normal.normalize().to_array());
+            uvs.push(grass_uv(world_x, world_z).to_array());
+        }
+    }
+
+    let mut indices = Vec::with_capacity((n - 1) * (n - 1) * 6);
+    for z in 0..n - 1 {
+        for x in 0..n - 1 {
+            let i = (z * n + x) as u32;
+            let below = i + n as u32;
+            indices.extend_from_slice(&[i, below, i + 1, i + 1, below, below + 1]);
+        }
+    }
+
+    // Each edge's vertices in order, with the direction its skirt faces
+    let edges: [(Vec<usize>, Vec3); 4] = [
+        ((0..n).collect(), Vec3::NEG_Z),
+        ((0..n).map(|x| (n - 1) * n + x).collect(), Vec3::Z),
+        ((0..n).map(|z| z * n).collect(), Vec3::NEG_X),
+        ((0..n).map(|z| z * n + n - 1).collect(), Vec3::X),
+    ];
+    for (edge, outward) in edges {
+        let first_skirt = positions.len() as u32;
+        for &i in &edge {
+            let [x, y, z] = positions[i];
+            positions.push([x, y - GRASS_THICKNESS, z]);
+            normals.push(outward.to_array());
+            uvs.push(uvs[i]);
+        }
+        for k in 0..edge.len() - 1 {
+            let (top_a, top_b) = (edge[k] as u32, edge[k + 1] as u32);
+            let (bottom_a, bottom_b) = (first_skirt + k as u32, first_skirt + k as u32 + 1);
+            // Both windings, so the skirt shows from either side
+            indices.extend_from_slice(&[top_a, bottom_a, top_b, top_b, bottom_a, bottom_b]);
+            indices.extend_from_slice(&[top_a, top_b, bottom_a, top_b, bottom_b, bottom_a]);
+        }
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    if let Err(err) = mesh.generate_tangents() {
+        println!("WARNING: Failed to generate grass tangents: {}", err);
+    }
+    mesh
+}
+
+// Run on the async compute pool, so the meshing doesn't hold up a frame
+fn generate_chunk(heightmap: &Heightmap, config: &TerrainConfig, coord: ChunkCoord) -> TerrainChunkData {
+    let lod_meshes = (0..LOD_LEVELS)
+        .map(|lod_level| grass_chunk_mesh(heightmap, config, coord, LOD_FULL_RESOLUTION >> lod_level))
+        .collect();
+    TerrainChunkData { coord, lod_meshes }
+}
+
+fn grass_chunk_center(coord: ChunkCoord) -> Vec3 {
+    let offset = (coord.0.as_vec2() + 0.5) * GRASS_CHUNK_SIZE - GRASS_SIZE * 0.5;
+    Vec3::new(offset.x, 0.0, offset.y)
+}
+
 fn spawn_terrain(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    config: Res<TerrainConfig>,
 ) {
-    // Main floating island platform
-    commands.spawn((
-        RigidBody::Fixed,
-        Collider::cuboid(20.0, 1.0, 20.0),
-        PbrBundle {
-            mesh: meshes.add(Mesh::from(shape::Box::new(40.0, 2.0, 40.0))),
-            material: materials.add(StandardMaterial {
-                base_color: Color::rgb(0.3, 0.6, 0.3),
-                ..default()
-            }),
-            transform: Transform::from_xyz(0.0, -1.0, 0.0),
-            ..default()
-        },
-    ));
+    let heightmap = Heightmap::generate(TERRAIN_MAP_SIZE, &mut rand::thread_rng());
+    let terrain_maps = TerrainMaps {
+        normal_map: images.add(tiling_texture(heightmap.size, heightmap.normal_map())),
+        roughness_map: images.add(tiling_texture(heightmap.size, heightmap.roughness_map())),
+    };
+
+    // A level file replaces the generated island, decorations and floating platforms
+    match load_level_file() {
+        Some(level) => {
+            spawn_level(&mut commands, &mut meshes, &mut materials, &level);
+            commands.insert_resource(FloatingPlatforms::default());
+        }
+        None => {
+            // Main floating island, generated from the config's seed
+            commands.spawn((
+                RigidBody::Fixed,
+                island_collider(&config),
+                LevelShape::Island { seed: config.island_seed },
+                SurfaceType::Grass,
+                PbrBundle {
+                    mesh: meshes.add(generate_island_mesh(&config)),
+                    material: materials.add(StandardMaterial {
+                        base_color: Color::rgb(0.3, 0.6, 0.3),
+                        ..default()
+                    }),
+                    ..default()
+                },
+            ));
+
+            // Add some decorative elements
+            spawn_decorative_elements(&mut commands, &mut meshes, &mut materials, &config);
 
-    // Grass layer on top
+            // Add some floating platforms
+            spawn_floating_platforms(&mut commands, &mut meshes, &mut materials);
+
+            // Keep everything in, without hiding the view out over the edge
+            spawn_terrain_boundary(&mut commands, &config);
+        }
+    }
+
+    // Grass layer on top, in chunks meshed in the background that start at full resolution
+    let grass_material = materials.add(StandardMaterial {
+        base_color: GRASS_COLOR,
+        normal_map_texture: Some(terrain_maps.normal_map.clone()),
+        // The roughness map holds the actual values
+        perceptual_roughness: 1.0,
+        metallic: 0.0,
+        metallic_roughness_texture: Some(terrain_maps.roughness_map.clone()),
+        ..default()
+    });
+    // Every chunk's stand-in is the same flat quad, as positions are relative to the chunk centre
+    let flat_island = TerrainConfig { island_height: 0.0, ..config.clone() };
+    let placeholder_mesh = meshes.add(grass_chunk_mesh(&Heightmap::flat(), &flat_island, ChunkCoord(IVec2::ZERO), 1));
+    let placeholder_material = materials.add(StandardMaterial {
+        base_color: GRASS_COLOR,
+        perceptual_roughness: FLAT_ROUGHNESS,
+        ..default()
+    });
+    let heightmap = Arc::new(heightmap);
+    let island = Arc::new(config.clone());
+    let task_pool = AsyncComputeTaskPool::get();
+    for z in 0..GRASS_CHUNKS {
+        for x in 0..GRASS_CHUNKS {
+            let coord = ChunkCoord(IVec2::new(x, z));
+            let heightmap = heightmap.clone();
+            let island = island.clone();
+            let task = task_pool.spawn(async move { generate_chunk(&heightmap, &island, coord) });
+            commands.spawn((
+                TerrainChunkTask {
+                    task,
+                    material: grass_material.clone(),
+                },
+                PbrBundle {
+                    mesh: placeholder_mesh.clone(),
+                    material: placeholder_material.clone(),
+                    transform: Transform::from_translation(grass_chunk_center(coord)),
+                    ..default()
+                },
+            ));
+        }
+    }
+    commands.insert_resource(TerrainLodMeshes { meshes: HashMap::new() });
+
+    commands.insert_resource(terrain_maps);
+
+    // Players start on the main island, whatever its height in the middle
     commands.spawn((
-        PbrBundle {
-            mesh: meshes.add(Mesh::from(shape::Box::new(38.0, 0.1, 38.0))),
-            material: materials.add(StandardMaterial {
-                base_color: Color::rgb(0.2, 0.8, 0.2),
-                ..default()
-            }),
-            transform: Transform::from_xyz(0.0, 0.1, 0.0),
-            ..default()
+        SpawnPoint {
+            id: "island".to_string(),
+            priority: 10,
         },
+        TransformBundle::from_transform(Transform::from_xyz(0.0, height_at(&config, 0.0, 0.0) + 2.0, 0.0)),
     ));
 
-    // Add some decorative elements
-    spawn_decorative_elements(&mut commands, &mut meshes, &mut materials);
-    
-    // Add some floating platforms
-    spawn_floating_platforms(&mut commands, &mut meshes, &mut materials);
+    // Lift up to the southern floating platform, driven by a lever
+    spawn_moving_platform(&mut commands, &mut meshes, &mut materials, &config);
+
+    // Ramps on either side of the walkable slope limit
+    spawn_ramps(&mut commands, &mut meshes, &mut materials, &config);
+
+    // A patch of ice to slide about on
+    spawn_ice(&mut commands, &mut meshes, &mut materials, &config);
+
+    // A conveyor belt running east towards the middle of the island
+    let conveyor_center = Vec2::new(-9.0, -2.0);
+    spawn_conveyor(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        &mut images,
+        Vec3::new(conveyor_center.x, ground_under(&config, conveyor_center, Vec2::new(4.0, 1.0)) + GRASS_TOP, conveyor_center.y),
+        8.0,
+        2.0,
+        ConveyorBelt {
+            direction: Vec3::X,
+            speed: 3.0,
+        },
+    );
 }
 
 fn spawn_decorative_elements(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
+    config: &TerrainConfig,
 ) {
     // Trees
     for i in 0..8 {
@@ -61,11 +693,14 @@ fn spawn_decorative_elements(
         let radius = 12.0;
         let x = angle.cos() * radius;
         let z = angle.sin() * radius;
+        let ground = height_at(config, x, z);
         
         // Tree trunk
-        commands.spawn((
+        let trunk = commands.spawn((
             RigidBody::Fixed,
             Collider::cylinder(2.0, 0.3),
+            LevelShape::Cylinder { half_height: 2.0, radius: 0.3 },
+            OcclusionFadeable::default(),
             PbrBundle {
                 mesh: meshes.add(Mesh::from(shape::Cylinder {
                     radius: 0.3,
@@ -76,13 +711,21 @@ fn spawn_decorative_elements(
                     base_color: Color::rgb(0.4, 0.2, 0.1),
                     ..default()
                 }),
-                transform: Transform::from_xyz(x, 1.0, z),
+                transform: Transform::from_xyz(x, ground + 1.0, z),
                 ..default()
             },
-        ));
+        )).id();
+        // One tree the dev camera can be pointed at
+        if i == 0 {
+            commands.entity(trunk).insert(CameraTargetable { name: "Tree".to_string() });
+        }
 
-        // Tree foliage
+        // Tree foliage, with a sensor only so it can fade out of the camera's way
         commands.spawn((
+            Collider::ball(2.0),
+            Sensor,
+            LevelShape::Ball { radius: 2.0 },
+            OcclusionFadeable::default(),
             PbrBundle {
                 mesh: meshes.add(Mesh::from(shape::UVSphere {
                     radius: 2.0,
@@ -92,45 +735,75 @@ fn spawn_decorative_elements(
                     base_color: Color::rgb(0.1, 0.5, 0.1),
                     ..default()
                 }),
-                transform: Transform::from_xyz(x, 4.0, z),
+                transform: Transform::from_xyz(x, ground + 4.0, z),
                 ..default()
             },
         ));
     }
 
     // Rocks
+    let rock_mesh = meshes.add(Mesh::from(shape::UVSphere {
+        radius: 0.5,
+        ..default()
+    }));
     for i in 0..12 {
         let angle = (i as f32) * std::f32::consts::PI * 2.0 / 12.0;
         let radius = 15.0 + (i % 3) as f32 * 2.0;
         let x = angle.cos() * radius;
         let z = angle.sin() * radius;
+        let ground = height_at(config, x, z);
         
         commands.spawn((
             RigidBody::Fixed,
             Collider::ball(0.5),
+            LevelShape::Ball { radius: 0.5 },
+            OcclusionFadeable::default(),
+            // Drawn by the Vulkan renderer's culled geometry pass as instances of one mesh
+            VulkanObject { subpass_index: GEOMETRY_SUBPASS },
             PbrBundle {
-                mesh: meshes.add(Mesh::from(shape::UVSphere {
-                    radius: 0.5,
-                    ..default()
-                })),
+                mesh: rock_mesh.clone(),
                 material: materials.add(StandardMaterial {
                     base_color: Color::rgb(0.5, 0.5, 0.5),
                     ..default()
                 }),
-                transform: Transform::from_xyz(x, 0.5, z),
+                transform: Transform::from_xyz(x, ground + 0.5, z),
                 ..default()
             },
         ));
     }
 }
 
+// A level file brings its own edges, so this is only for the generated island
+fn spawn_terrain_boundary(commands: &mut Commands, config: &TerrainConfig) {
+    const WALL_THICKNESS: f32 = 1.0;
+    let half_width = config.width * 0.5;
+    let half_depth = config.depth * 0.5;
+    let half_height = (config.wall_height + config.wall_depth) * 0.5;
+    let center_y = (config.wall_height - config.wall_depth) * 0.5;
+    // North, south, east and west, each with its inner face on the edge
+    let walls = [
+        (Vec3::new(0.0, center_y, -half_depth - WALL_THICKNESS), Vec3::new(half_width + WALL_THICKNESS * 2.0, half_height, WALL_THICKNESS)),
+        (Vec3::new(0.0, center_y, half_depth + WALL_THICKNESS), Vec3::new(half_width + WALL_THICKNESS * 2.0, half_height, WALL_THICKNESS)),
+        (Vec3::new(half_width + WALL_THICKNESS, center_y, 0.0), Vec3::new(WALL_THICKNESS, half_height, half_depth)),
+        (Vec3::new(-half_width - WALL_THICKNESS, center_y, 0.0), Vec3::new(WALL_THICKNESS, half_height, half_depth)),
+    ];
+    for (position, half_extents) in walls {
+        commands.spawn((
+            TerrainBoundary,
+            RigidBody::Fixed,
+            Collider::cuboid(half_extents.x, half_extents.y, half_extents.z),
+            TransformBundle::from_transform(Transform::from_translation(position)),
+        ));
+    }
+}
+
 fn spawn_floating_platforms(
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials: &mut ResMut<Assets<StandardMaterial>>,
 ) {
     // Create some floating platforms around the main island
-    let platform_positions = vec![
+    let platform_positions = [
         Vec3::new(25.0, 5.0, 0.0),
         Vec3::new(-25.0, 8.0, 0.0),
         Vec3::new(0.0, 12.0, 25.0),
@@ -139,12 +812,16 @@ fn spawn_floating_platforms(
         Vec3::new(-18.0, 7.0, -18.0),
     ];
     
+    let mut standing_positions = Vec::new();
     for (i, pos) in platform_positions.iter().enumerate() {
         let size = 3.0 + (i % 2) as f32 * 2.0;
         
-        commands.spawn((
+        let platform = commands.spawn((
             RigidBody::Fixed,
             Collider::cuboid(size, 0.5, size),
+            LevelShape::Box { half_extents: Vec3::new(size, 0.5, size) },
+            OcclusionFadeable::default(),
+            SurfaceType::Wood,
             PbrBundle {
                 mesh: meshes.add(Mesh::from(shape::Box::new(size * 2.0, 1.0, size * 2.0))),
                 material: materials.add(StandardMaterial {
@@ -154,6 +831,295 @@ fn spawn_floating_platforms(
                 transform: Transform::from_xyz(pos.x, pos.y, pos.z),
                 ..default()
             },
+        )).id();
+        if i == 0 {
+            commands.entity(platform).insert(CameraTargetable { name: "Floating platform".to_string() });
+            // Standing on it cuts to a wide shot from off its corner, looking back at the island
+            spawn_fixed_camera_zone(
+                commands,
+                *pos + Vec3::Y * 2.0,
+                Vec3::new(size, 1.5, size),
+                FixedCameraZone {
+                    camera_transform: Transform::from_translation(*pos + Vec3::new(9.0, 6.0, 9.0))
+                        .looking_at(*pos + Vec3::new(-6.0, 0.0, -2.0), Vec3::Y),
+                    blend_time: 0.8,
+                },
+            );
+        }
+
+        // A flag at the back corner, hanging over the platform
+        let flag_base = *pos + Vec3::new(-size + 0.3, 0.5, -size + 0.3);
+        spawn_flag(commands, meshes, materials, flag_base, Color::hsl(i as f32 * 60.0, 0.7, 0.5));
+
+        // Standing height above the platform top for the player capsule
+        let standing_position = *pos + Vec3::Y * 2.0;
+        commands.spawn((
+            SpawnPoint {
+                id: format!("platform_{}", i),
+                priority: 0,
+            },
+            TransformBundle::from_transform(Transform::from_translation(standing_position)),
         ));
+        standing_positions.push(standing_position);
     }
-} 
\ No newline at end of file
+    commands.insert_resource(FloatingPlatforms { standing_positions });
+}
+
+pub fn collect_spawn_points(
+    mut registry: ResMut<SpawnPointRegistry>,
+    spawn_points: Query<(&SpawnPoint, &Transform), Added<SpawnPoint>>,
+) {
+    for (spawn_point, transform) in spawn_points.iter() {
+        registry.points.insert(spawn_point.id.clone(), SpawnPointEntry {
+            position: transform.translation,
+            priority: spawn_point.priority,
+        });
+        println!("Registered spawn point {:?} at {:?}", spawn_point.id, transform.translation);
+    }
+}
+
+fn spawn_moving_platform(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    config: &TerrainConfig,
+) {
+    // Resting on the ground at the bottom, and level with the southern platform's top at the top
+    let start = Vec3::new(0.0, ground_under(config, Vec2::new(0.0, -17.0), Vec2::ONE) + 0.25, -17.0);
+    let end = Vec3::new(0.0, 6.25, -17.0);
+
+    let platform = commands.spawn((
+        MovingPlatform {
+            start,
+            end,
+            period: 6.0,
+            elapsed: 0.0,
+            active: false,
+        },
+        RigidBody::KinematicPositionBased,
+        // Filled in by rapier each step so riders can pick up the platform's motion
+        Velocity::zero(),
+        Collider::cuboid(1.0, 0.25, 1.0),
+        SurfaceType::Stone,
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Box::new(2.0, 0.5, 2.0))),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.3, 0.3, 0.7),
+                ..default()
+            }),
+            transform: Transform::from_translation(start),
+            ..default()
+        },
+    )).id();
+
+    // Lever that starts and stops the lift
+    commands.spawn((
+        Lever {
+            platform,
+            on: false,
+        },
+        Interactable {
+            prompt: "Start platform".to_string(),
+            range: 2.5,
+        },
+        RigidBody::Fixed,
+        Collider::cuboid(0.1, 0.5, 0.1),
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Box::new(0.2, 1.0, 0.2))),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.7, 0.7, 0.1),
+                ..default()
+            }),
+            transform: Transform::from_xyz(2.0, height_at(config, 2.0, -14.0) + 0.5, -14.0),
+            ..default()
+        },
+    ));
+
+    // Turntable that spins in place
+    commands.spawn((
+        RigidBody::KinematicVelocityBased,
+        Velocity::angular(Vec3::Y * 0.8),
+        Collider::cylinder(0.25, 2.5),
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Cylinder {
+                radius: 2.5,
+                height: 0.5,
+                ..default()
+            })),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.7, 0.4, 0.3),
+                ..default()
+            }),
+            transform: Transform::from_xyz(8.0, ground_under(config, Vec2::new(8.0, -9.0), Vec2::splat(2.5)) + 0.25, -9.0),
+            ..default()
+        },
+    ));
+
+    // Sign explaining the lift
+    commands.spawn((
+        Sign {
+            text: "Pull the lever to ride the lift to the southern platform.".to_string(),
+        },
+        Interactable {
+            prompt: "Read sign".to_string(),
+            range: 2.5,
+        },
+        RigidBody::Fixed,
+        Collider::cuboid(0.6, 0.4, 0.05),
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Box::new(1.2, 0.8, 0.1))),
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.6, 0.45, 0.25),
+                ..default()
+            }),
+            transform: Transform::from_xyz(-2.0, height_at(config, -2.0, -14.0) + 1.0, -14.0),
+            ..default()
+        },
+    ));
+}
+
+fn spawn_ramps(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    config: &TerrainConfig,
+) {
+    let half_length = 3.0;
+    for (i, degrees) in [30.0_f32, 55.0, 80.0].into_iter().enumerate() {
+        let angle = degrees.to_radians();
+        // Tilted up towards -Z with the low edge resting on the grass
+        let x = -6.0 - i as f32 * 3.0;
+        let low_edge = Vec2::new(x, 6.0 + half_length * angle.cos());
+        let position = Vec3::new(x, height_at(config, low_edge.x, low_edge.y) + half_length * angle.sin(), 6.0);
+        commands.spawn((
+            RigidBody::Fixed,
+            Collider::cuboid(1.0, 0.1, half_length),
+            SurfaceType::Stone,
+            PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Box::new(2.0, 0.2, half_length * 2.0))),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgb(0.55, 0.5, 0.45),
+                    ..default()
+                }),
+                transform: Transform::from_translation(position)
+                    .with_rotation(Quat::from_rotation_x(angle)),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn spawn_ice(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    config: &TerrainConfig,
+) {
+    // Glossy and a little see-through, so the grass shows faintly underneath
+    let material = materials.add(StandardMaterial {
+        base_color: Color::rgba(0.75, 0.9, 1.0, 0.8),
+        alpha_mode: AlphaMode::Blend,
+        perceptual_roughness: 0.05,
+        reflectance: 0.9,
+        ..default()
+    });
+    let mesh = meshes.add(Mesh::from(shape::Box::new(ICE_TILE_SIZE, ICE_TILE_THICKNESS, ICE_TILE_SIZE)));
+    for z in 0..3 {
+        for x in 0..3 {
+            // Resting on the grass layer
+            let center = ICE_CENTER + (Vec2::new(x as f32, z as f32) - 1.0) * ICE_TILE_SIZE;
+            let ground = ground_under(config, center, Vec2::splat(ICE_TILE_SIZE * 0.5));
+            let position = Vec3::new(center.x, ground + GRASS_TOP + ICE_TILE_THICKNESS * 0.5, center.y);
+            commands.spawn((
+                RigidBody::Fixed,
+                Collider::cuboid(ICE_TILE_SIZE * 0.5, ICE_TILE_THICKNESS * 0.5, ICE_TILE_SIZE * 0.5),
+                SurfaceType::Ice,
+                PbrBundle {
+                    mesh: mesh.clone(),
+                    material: material.clone(),
+                    transform: Transform::from_translation(position),
+                    ..default()
+                },
+            ));
+        }
+    }
+}
+
+fn move_platforms(
+    mut platform_query: Query<(&mut MovingPlatform, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (mut platform, mut transform) in platform_query.iter_mut() {
+        if !platform.active {
+            continue;
+        }
+
+        platform.elapsed += time.delta_seconds();
+        // Ease back and forth between the two end points
+        let phase = platform.elapsed / platform.period * std::f32::consts::TAU;
+        let t = (1.0 - phase.cos()) / 2.0;
+        transform.translation = platform.start.lerp(platform.end, t);
+    }
+}
+
+// Picks each grass chunk's LOD from its distance to the closest player
+// Swaps each chunk's stand-in for its real meshes once they're generated
+fn poll_terrain_tasks(
+    mut commands: Commands,
+    mut chunk_query: Query<(Entity, &mut TerrainChunkTask, &mut Handle<Mesh>, &mut Handle<StandardMaterial>)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut lod_meshes: ResMut<TerrainLodMeshes>,
+) {
+    for (entity, mut chunk_task, mut mesh, mut material) in chunk_query.iter_mut() {
+        let Some(data) = future::block_on(future::poll_once(&mut chunk_task.task)) else {
+            continue;
+        };
+        for (lod_level, lod_mesh) in data.lod_meshes.into_iter().enumerate() {
+            lod_meshes.meshes.insert((data.coord, lod_level as u8), meshes.add(lod_mesh));
+        }
+        if let Some(handle) = lod_meshes.meshes.get(&(data.coord, 0)) {
+            *mesh = handle.clone();
+        }
+        *material = chunk_task.material.clone();
+        commands
+            .entity(entity)
+            .remove::<TerrainChunkTask>()
+            .insert((data.coord, TerrainLod { lod_level: 0 }));
+    }
+}
+
+fn terrain_lod_system(
+    players: Res<Players>,
+    player_query: Query<&GlobalTransform>,
+    lod_meshes: Option<Res<TerrainLodMeshes>>,
+    mut chunk_query: Query<(&ChunkCoord, &GlobalTransform, &mut TerrainLod, &mut Handle<Mesh>)>,
+) {
+    let Some(lod_meshes) = lod_meshes else {
+        return;
+    };
+    let player_positions: Vec<Vec2> = players
+        .0
+        .iter()
+        .filter_map(|entity| player_query.get(*entity).ok())
+        .map(|transform| transform.translation().xz())
+        .collect();
+    if player_positions.is_empty() {
+        return;
+    }
+
+    for (coord, transform, mut lod, mut mesh) in chunk_query.iter_mut() {
+        let center = transform.translation().xz();
+        let distance = player_positions
+            .iter()
+            .map(|position| position.distance(center))
+            .fold(f32::MAX, f32::min);
+        let lod_level = LOD_DISTANCES.iter().filter(|&&threshold| distance > threshold).count() as u8;
+        if lod_level == lod.lod_level {
+            continue;
+        }
+        if let Some(handle) = lod_meshes.meshes.get(&(*coord, lod_level)) {
+            lod.lod_level = lod_level;
+            *mesh = handle.clone();
+        }
+    }
+}
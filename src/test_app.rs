@@ -0,0 +1,48 @@
+use bevy::prelude::*;
+use bevy::scene::SceneSpawner;
+use bevy::time::TimeUpdateStrategy;
+use bevy_rapier3d::prelude::*;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+// Length of one frame in the physics test apps
+pub const FRAME: f32 = 1.0 / 60.0;
+
+// A headless app where each update advances the clock by FRAME and steps Rapier once. Rapier's
+// scene collider system wants the mesh and scene resources even with no renderer.
+pub fn physics_app() -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, TransformPlugin, HierarchyPlugin, RapierPhysicsPlugin::<NoUserData>::default()))
+        .init_resource::<Assets<Mesh>>()
+        .init_resource::<SceneSpawner>()
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f32(FRAME)))
+        .insert_resource(RapierConfiguration {
+            timestep_mode: TimestepMode::Fixed { dt: FRAME, substeps: 1 },
+            ..default()
+        });
+    app
+}
+
+// A fresh directory under the system temp dir for one test, removed when it's dropped
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let dir = env::temp_dir().join(format!("vulkan-ex-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
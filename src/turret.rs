@@ -0,0 +1,245 @@
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use bevy_rapier3d::prelude::*;
+use crate::camera::math;
+use crate::combat::Projectile;
+use crate::debug::Noclip;
+use crate::health::Dead;
+use crate::player::Player;
+use crate::terrain::{height_at, TerrainConfig, GRASS_TOP};
+
+pub struct TurretPlugin;
+
+impl Plugin for TurretPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_turrets)
+            .add_systems(Update, turret_system);
+    }
+}
+
+const BASE_RADIUS: f32 = 0.4;
+const BASE_HEIGHT: f32 = 1.0;
+const BARREL_LENGTH: f32 = 0.9;
+const BARREL_THICKNESS: f32 = 0.12;
+// Metres per second, with no drop
+const BOLT_SPEED: f32 = 25.0;
+const BOLT_RADIUS: f32 = 0.08;
+// Bolts that miss are gone after this long
+const BOLT_LIFETIME: f32 = 3.0;
+// How far the barrel can tilt down and up, in radians
+const MIN_ELEVATION: f32 = -0.5;
+const MAX_ELEVATION: f32 = 1.2;
+// Only fires once the barrel is within this many radians of the aim point
+const FIRE_TOLERANCE: f32 = 0.1;
+
+// A fixed gun that turns towards the nearest player it can see within `range`, leading them by
+// their velocity, and fires a bolt every 1 / `fire_rate` seconds while it's on them. The head
+// turns about y and the barrel tilts about its local x, both at up to `rotation_speed` radians
+// per second.
+#[derive(Component)]
+pub struct Turret {
+    pub fire_rate: f32,
+    pub fire_cooldown: f32,
+    pub range: f32,
+    pub damage: f32,
+    pub rotation_speed: f32,
+}
+
+impl Default for Turret {
+    fn default() -> Self {
+        Self {
+            fire_rate: 0.5,
+            fire_cooldown: 0.0,
+            range: 20.0,
+            damage: 10.0,
+            rotation_speed: 2.0,
+        }
+    }
+}
+
+// Sits on top of the base and turns it left and right, relative to the base
+#[derive(Component)]
+struct TurretHead {
+    yaw: f32,
+}
+
+// Tilts up and down on the head; the barrel runs along its -z
+#[derive(Component)]
+struct TurretBarrel {
+    pitch: f32,
+}
+
+fn spawn_turrets(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    config: Res<TerrainConfig>,
+) {
+    // Guarding the north-east corner of the island
+    let position = Vec3::new(12.0, height_at(&config, 12.0, -12.0) + GRASS_TOP, -12.0);
+    spawn_turret(&mut commands, &mut meshes, &mut materials, position, Turret::default());
+}
+
+// A turret whose base stands on `position`
+pub fn spawn_turret(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    position: Vec3,
+    turret: Turret,
+) {
+    let metal = materials.add(StandardMaterial {
+        base_color: Color::rgb(0.35, 0.37, 0.4),
+        metallic: 0.8,
+        perceptual_roughness: 0.4,
+        ..default()
+    });
+    commands
+        .spawn((
+            turret,
+            RigidBody::Fixed,
+            Collider::cylinder(BASE_HEIGHT * 0.5, BASE_RADIUS),
+            PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::Cylinder {
+                    radius: BASE_RADIUS,
+                    height: BASE_HEIGHT,
+                    ..default()
+                })),
+                material: metal.clone(),
+                transform: Transform::from_translation(position + Vec3::Y * BASE_HEIGHT * 0.5),
+                ..default()
+            },
+        ))
+        .with_children(|base| {
+            base.spawn((
+                TurretHead { yaw: 0.0 },
+                PbrBundle {
+                    mesh: meshes.add(Mesh::from(shape::UVSphere {
+                        radius: BASE_RADIUS * 0.8,
+                        ..default()
+                    })),
+                    material: metal.clone(),
+                    transform: Transform::from_xyz(0.0, BASE_HEIGHT * 0.5, 0.0),
+                    ..default()
+                },
+            ))
+            .with_children(|head| {
+                // Pivots at the back end, so tilting swings the muzzle
+                head.spawn((
+                    TurretBarrel { pitch: 0.0 },
+                    PbrBundle {
+                        mesh: meshes.add(Mesh::from(shape::Box {
+                            min_x: -BARREL_THICKNESS * 0.5,
+                            max_x: BARREL_THICKNESS * 0.5,
+                            min_y: -BARREL_THICKNESS * 0.5,
+                            max_y: BARREL_THICKNESS * 0.5,
+                            min_z: -BARREL_LENGTH,
+                            max_z: 0.0,
+                        })),
+                        material: metal,
+                        ..default()
+                    },
+                ));
+            });
+        });
+}
+
+// Turns `current` towards `target` by at most `max_step`, the short way round
+fn step_angle(current: f32, target: f32, max_step: f32) -> f32 {
+    math::wrap_angle(current + math::wrap_angle(target - current).clamp(-max_step, max_step))
+}
+
+fn turret_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut turret_query: Query<(Entity, &mut Turret, &Transform, &Children)>,
+    mut head_query: Query<(&mut TurretHead, &mut Transform, &Children), Without<Turret>>,
+    mut barrel_query: Query<(&mut TurretBarrel, &mut Transform, &GlobalTransform), (Without<Turret>, Without<TurretHead>)>,
+    player_query: Query<(Entity, &GlobalTransform, &Velocity), (With<Player>, Without<Dead>, Without<Noclip>)>,
+    rapier_context: Res<RapierContext>,
+    time: Res<Time>,
+) {
+    let delta_seconds = time.delta_seconds();
+    for (turret_entity, mut turret, turret_transform, children) in turret_query.iter_mut() {
+        turret.fire_cooldown = (turret.fire_cooldown - delta_seconds).max(0.0);
+        let Some(Ok((mut head, mut head_transform, head_children))) = children.iter().find(|child| head_query.contains(**child)).map(|child| head_query.get_mut(*child)) else {
+            continue;
+        };
+        let Some(Ok((mut barrel, mut barrel_transform, barrel_global))) = head_children.iter().find(|child| barrel_query.contains(**child)).map(|child| barrel_query.get_mut(*child)) else {
+            continue;
+        };
+        let pivot = barrel_global.translation();
+
+        // The nearest player in range with nothing in between
+        let filter = QueryFilter::default().exclude_collider(turret_entity).exclude_sensors();
+        let target = player_query
+            .iter()
+            .filter(|(_, transform, _)| transform.translation().distance(pivot) <= turret.range)
+            .filter(|(player, transform, _)| {
+                let to_player = transform.translation() - pivot;
+                rapier_context
+                    .cast_ray(pivot, to_player.normalize_or_zero(), turret.range, true, filter)
+                    .is_some_and(|(hit, _)| hit == *player)
+            })
+            .min_by(|(_, a, _), (_, b, _)| a.translation().distance_squared(pivot).total_cmp(&b.translation().distance_squared(pivot)));
+        let Some((_, player_transform, player_velocity)) = target else {
+            continue;
+        };
+
+        // Where the player will be when a bolt gets there, if they keep going the same way
+        let player_position = player_transform.translation();
+        let flight_time = player_position.distance(pivot) / BOLT_SPEED;
+        let aim_point = player_position + player_velocity.linvel * flight_time;
+
+        // Aimed in the base's own space, so a turned base still tracks
+        let local = turret_transform.rotation.inverse() * (aim_point - pivot);
+        let horizontal = Vec2::new(local.x, local.z).length();
+        if horizontal <= f32::EPSILON {
+            continue;
+        }
+        let target_yaw = (-local.x).atan2(-local.z);
+        let target_pitch = local.y.atan2(horizontal).clamp(MIN_ELEVATION, MAX_ELEVATION);
+        let max_step = turret.rotation_speed * delta_seconds;
+        head.yaw = step_angle(head.yaw, target_yaw, max_step);
+        barrel.pitch = step_angle(barrel.pitch, target_pitch, max_step);
+        head_transform.rotation = Quat::from_rotation_y(head.yaw);
+        barrel_transform.rotation = Quat::from_rotation_x(barrel.pitch);
+
+        let on_target = math::wrap_angle(target_yaw - head.yaw).abs() <= FIRE_TOLERANCE
+            && (target_pitch - barrel.pitch).abs() <= FIRE_TOLERANCE;
+        if !on_target || turret.fire_cooldown > 0.0 || turret.fire_rate <= 0.0 {
+            continue;
+        }
+        turret.fire_cooldown = 1.0 / turret.fire_rate;
+
+        let muzzle = pivot + barrel_global.forward() * BARREL_LENGTH;
+        let direction = (aim_point - muzzle).normalize_or_zero();
+        commands.spawn((
+            Projectile {
+                velocity: direction * BOLT_SPEED,
+                gravity_scale: 0.0,
+                damage: turret.damage,
+                // Hurts only what it hits
+                aoe_radius: 0.0,
+                owner: turret_entity,
+                lifetime: BOLT_LIFETIME,
+            },
+            PbrBundle {
+                mesh: meshes.add(Mesh::from(shape::UVSphere {
+                    radius: BOLT_RADIUS,
+                    ..default()
+                })),
+                material: materials.add(StandardMaterial {
+                    base_color: Color::rgb(1.0, 0.3, 0.2),
+                    emissive: Color::rgb(1.0, 0.2, 0.1),
+                    unlit: true,
+                    ..default()
+                }),
+                transform: Transform::from_translation(muzzle),
+                ..default()
+            },
+        ));
+        println!("Turret {:?} fired at {:?}", turret_entity, aim_point);
+    }
+}
@@ -1,260 +1,4583 @@
+use bevy::app::AppExit;
 use bevy::prelude::*;
-use bevy::window::Window;
-use log::info;
-use std::ffi::CStr;
+use bevy::render::mesh::{Indices, PrimitiveTopology, VertexAttributeValues};
+use bevy::render::primitives::{Aabb, Frustum};
+use bevy::render::view::VisibilitySystems;
+use bevy::window::{Window, WindowResized};
+use log::{info, warn};
+use std::collections::HashMap;
+use std::ffi::{CStr, CString};
+use std::path::{Path, PathBuf};
 use ash::{
     vk,
     Instance as AshInstance,
     Device as AshDevice,
     Entry,
     extensions::{
-        khr::Surface,
-        khr::Swapchain,
+        ext::DebugUtils,
+        khr::{AccelerationStructure, DeferredHostOperations, RayTracingPipeline as RayTracingPipelineLoader, Surface, Swapchain},
     },
 };
-use gpu_allocator::vulkan::Allocator;
+use gpu_allocator::vulkan::{Allocation, AllocationCreateDesc, AllocationScheme, Allocator};
+use gpu_allocator::{AllocatorDebugSettings, MemoryLocation};
+use rand::Rng;
 
-pub struct VulkanRendererPlugin;
+const VALIDATION_LAYER: &CStr = c"VK_LAYER_KHRONOS_validation";
+
+// Compiled from the matching .glsl files, see the README
+const SHADER_DIR: &str = "assets/shaders";
+
+// Material textures are stored as 8-bit sRGB and get a full mip chain
+const TEXTURE_FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
+// Material descriptor sets the texture cache's pool can hand out
+const MAX_TEXTURES: u32 = 64;
+const TERRAIN_TEXTURE: &str = "assets/textures/terrain.png";
+const PLAYER_TEXTURE: &str = "assets/textures/player.png";
+
+// Deferred render pass layout: subpass 0 fills the G-buffer, subpass 1 lights it
+pub const GEOMETRY_SUBPASS: u32 = 0;
+pub const LIGHTING_SUBPASS: u32 = 1;
+
+const GBUFFER_POSITION_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+const GBUFFER_NORMAL_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+const GBUFFER_ALBEDO_FORMAT: vk::Format = vk::Format::B8G8R8A8_UNORM;
+
+// Interleaved position (vec3), normal (vec3) and tex coords (vec2), see vulkan_vertex.glsl
+const VERTEX_STRIDE: u32 = 32;
+
+const COLOR_SUBRESOURCE_RANGE: vk::ImageSubresourceRange = vk::ImageSubresourceRange {
+    aspect_mask: vk::ImageAspectFlags::COLOR,
+    base_mip_level: 0,
+    level_count: 1,
+    base_array_layer: 0,
+    layer_count: 1,
+};
+
+// Lighting renders into this HDR target; post processing runs on it before it's blitted
+// to the swapchain image
+const SCENE_COLOR_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+// Tone mapping gamma-corrects itself, so its output isn't sRGB
+const TONE_MAPPED_FORMAT: vk::Format = vk::Format::R8G8B8A8_UNORM;
+const TONE_MAP_WORKGROUP_SIZE: u32 = 8;
+
+const SSAO_KERNEL_SIZE: usize = 64;
+// Side of the tile of random rotations; the blur pass averages the same area
+const SSAO_NOISE_SIZE: usize = 4;
+const SSAO_WORKGROUP_SIZE: u32 = 8;
+const SSAO_FORMAT: vk::Format = vk::Format::R32_SFLOAT;
+
+const FOG_WORKGROUP_SIZE: u32 = 8;
+const FOG_FORMAT: vk::Format = vk::Format::R16G16B16A16_SFLOAT;
+// Side of the tiling Perlin noise volume the fog density is read from
+const FOG_NOISE_SIZE: u32 = 32;
+
+// Preetham luminance is in kcd/m^2; this brings a clear noon zenith to roughly 0.5
+const SKY_LUMINANCE_SCALE: f32 = 0.08;
+
+// Half resolution bright pass followed by five progressively smaller levels
+const BLOOM_LEVELS: usize = 6;
+const BLOOM_WORKGROUP_SIZE: u32 = 8;
+
+// The object and visibility buffers are sized for this many objects; more are left undrawn
+const MAX_CULL_OBJECTS: usize = 4096;
+const CULL_WORKGROUP_SIZE: u32 = 64;
+// Raygen, miss and closest hit, in shader binding table order
+#[cfg(feature = "ray_tracing")]
+const RAY_TRACING_GROUP_COUNT: u32 = 3;
+
+// Matches the Object struct in gpu_cull.glsl and vulkan_vertex.glsl
+#[repr(C)]
+#[derive(Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct CullObject {
+    // xyz world space center, w radius
+    pub bounding_sphere: [f32; 4],
+    pub model: [[f32; 4]; 4],
+}
+
+// Matches the uniform block in gpu_cull.glsl; the geometry vertex shader reads the matrices
+#[repr(C)]
+#[derive(Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct CullUniforms {
+    view: [[f32; 4]; 4],
+    projection: [[f32; 4]; 4],
+    // Inward facing, xyz normal and w distance
+    frustum_planes: [[f32; 4]; 6],
+    object_count: u32,
+    _padding: [u32; 3],
+}
+
+// Matches the push constant block in the bloom shaders
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct BloomPushConstants {
+    threshold: f32,
+    intensity: f32,
+}
+
+// Matches the push constant block in tone_map.glsl
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct ToneMapPushConstants {
+    exposure: f32,
+    mode: u32,
+    lut_blend: f32,
+    desaturation: f32,
+    vignette: f32,
+}
+
+// Matches the uniform block in volumetric_fog.glsl
+#[repr(C)]
+#[derive(Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct FogUniforms {
+    inverse_view_projection: [[f32; 4]; 4],
+    camera_position: [f32; 4],
+    // Points towards the light
+    light_direction: [f32; 4],
+    light_color: [f32; 4],
+    // density, scatter, absorption, anisotropy
+    params: [f32; 4],
+}
+
+// Matches the uniform block in sky_fragment.glsl
+#[repr(C)]
+#[derive(Clone, Copy, Default, bytemuck::Pod, bytemuck::Zeroable)]
+struct SkyUniforms {
+    inverse_view_projection: [[f32; 4]; 4],
+    camera_position: [f32; 4],
+    // Points towards the sun
+    sun_direction: [f32; 4],
+    // Perez distribution coefficients A-E, each holding Y, x and y
+    perez: [[f32; 4]; 5],
+    // Zenith Yxy over the distribution at the zenith
+    zenith: [f32; 4],
+    // rgb ground albedo, w fades the day sky out at night
+    ground_albedo: [f32; 4],
+}
+
+// Matches the uniform block in ssao_compute.glsl
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct SsaoUniforms {
+    view: [[f32; 4]; 4],
+    projection: [[f32; 4]; 4],
+    kernel: [[f32; 4]; SSAO_KERNEL_SIZE],
+    noise: [[f32; 4]; SSAO_NOISE_SIZE * SSAO_NOISE_SIZE],
+}
+
+pub struct VulkanRendererPlugin {
+    // Enables validation layers, object names and command buffer labels for capture tools
+    pub debug: bool,
+}
+
+impl Default for VulkanRendererPlugin {
+    fn default() -> Self {
+        Self {
+            debug: cfg!(debug_assertions),
+        }
+    }
+}
 
 impl Plugin for VulkanRendererPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<VulkanRenderer>()
-            .add_systems(Update, setup_vulkan_surface)
-            .add_systems(Startup, setup_lighting);
+        // Built field by field since Drop rules out struct update syntax
+        let mut vulkan_renderer = VulkanRenderer::default();
+        vulkan_renderer.debug = self.debug;
+
+        app.insert_resource(vulkan_renderer)
+            .init_resource::<PostProcessConfig>()
+            .init_resource::<ToneMappingConfig>()
+            .init_resource::<VolumetricFogConfig>()
+            .init_resource::<SkyConfig>()
+            .add_systems(Update, (setup_vulkan_surface, resize_vulkan_targets).chain())
+            // After Bevy has updated the camera projections and frusta, so a field of view or
+            // transform change made during Update reaches the uniforms the same frame
+            .add_systems(PostUpdate, (update_ssao_uniforms, update_fog_uniforms, update_sky_uniforms, upload_cull_mesh, update_cull_uniforms)
+                .chain()
+                .after(VisibilitySystems::UpdatePerspectiveFrusta))
+            .add_systems(Startup, setup_lighting)
+            .add_systems(Last, cleanup_vulkan);
+
+        #[cfg(feature = "ray_tracing")]
+        app.add_systems(PostUpdate, build_acceleration_structures.after(update_cull_uniforms));
+    }
+}
+
+#[derive(Resource)]
+pub struct PostProcessConfig {
+    pub bloom_enabled: bool,
+    // Luminance above which pixels start to glow
+    pub bloom_threshold: f32,
+    // Strength of the glow added back onto the scene
+    pub bloom_intensity: f32,
+}
+
+impl Default for PostProcessConfig {
+    fn default() -> Self {
+        Self {
+            bloom_enabled: true,
+            bloom_threshold: 0.8,
+            bloom_intensity: 0.5,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct VolumetricFogConfig {
+    // Fog density at the peak of the noise
+    pub density: f32,
+    // How much light the fog scatters towards the camera
+    pub scatter_coefficient: f32,
+    // How much light the fog swallows
+    pub absorption_coefficient: f32,
+    // Henyey-Greenstein g: 0 scatters evenly, towards 1 glows around the sun
+    pub anisotropy: f32,
+}
+
+impl Default for VolumetricFogConfig {
+    fn default() -> Self {
+        Self {
+            density: 0.05,
+            scatter_coefficient: 0.5,
+            absorption_coefficient: 0.1,
+            anisotropy: 0.6,
+        }
+    }
+}
+
+// Inputs to the Preetham sky drawn behind the scene, driven by the day/night cycle
+#[derive(Resource)]
+pub struct SkyConfig {
+    // Points towards the sun
+    pub sun_direction: Vec3,
+    // Haziness of the atmosphere: 2 is a very clear sky, 10 a hazy one
+    pub turbidity: f32,
+    // Tints the sky reflected off the ground below the horizon
+    pub ground_albedo: Vec3,
+}
+
+impl Default for SkyConfig {
+    fn default() -> Self {
+        Self {
+            sun_direction: Vec3::new(4.0, 8.0, 4.0).normalize(),
+            turbidity: 2.5,
+            ground_albedo: Vec3::new(0.3, 0.28, 0.25),
+        }
+    }
+}
+
+// Only ACES is selected by default; the others are picked by setting ToneMappingConfig's mode
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[allow(dead_code)]
+pub enum ToneMappingMode {
+    #[default]
+    Aces,
+    Reinhard,
+    Uncharted2,
+}
+
+impl ToneMappingMode {
+    // Index the tone_map.glsl switch expects
+    fn shader_index(self) -> u32 {
+        match self {
+            ToneMappingMode::Aces => 0,
+            ToneMappingMode::Reinhard => 1,
+            ToneMappingMode::Uncharted2 => 2,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct ToneMappingConfig {
+    // Scene color is multiplied by this before the operator is applied
+    pub exposure: f32,
+    pub mode: ToneMappingMode,
+    // 0 leaves the graded color alone, 1 is fully greyscale
+    pub desaturation: f32,
+    // How much the corners are darkened, 0 for none
+    pub vignette: f32,
+}
+
+impl Default for ToneMappingConfig {
+    fn default() -> Self {
+        Self {
+            exposure: 1.0,
+            mode: ToneMappingMode::Aces,
+            desaturation: 0.0,
+            vignette: 0.0,
+        }
+    }
+}
+
+// A device local buffer and the memory backing it
+pub struct GpuBuffer {
+    pub buffer: vk::Buffer,
+    pub allocation: Allocation,
+}
+
+// A 2D image with its view, used for G-buffer and SSAO targets
+pub struct RenderTarget {
+    pub image: vk::Image,
+    pub view: vk::ImageView,
+    pub allocation: Allocation,
+}
+
+pub struct GBuffer {
+    pub position: RenderTarget,
+    pub normal: RenderTarget,
+    pub albedo: RenderTarget,
+    pub extent: vk::Extent2D,
+}
+
+// Compute passes that turn the G-buffer into a blurred ambient occlusion image
+pub struct SsaoPass {
+    pub uniforms: GpuBuffer,
+    pub occlusion: RenderTarget,
+    pub blurred: RenderTarget,
+    pub extent: vk::Extent2D,
+    pub sampler: vk::Sampler,
+    pub ssao_set_layout: vk::DescriptorSetLayout,
+    pub blur_set_layout: vk::DescriptorSetLayout,
+    pub ssao_pipeline_layout: vk::PipelineLayout,
+    pub blur_pipeline_layout: vk::PipelineLayout,
+    pub ssao_pipeline: Option<vk::Pipeline>,
+    pub blur_pipeline: Option<vk::Pipeline>,
+    pub ssao_set: vk::DescriptorSet,
+    pub blur_set: vk::DescriptorSet,
+}
+
+// Bright pass plus a dual filter down/up-sample chain, added back onto the scene color.
+// Everything here is sized to the window and rebuilt on resize.
+pub struct BloomPass {
+    // levels[0] is half resolution, each following level halves again
+    pub levels: Vec<RenderTarget>,
+    pub sampler: vk::Sampler,
+    pub descriptor_pool: vk::DescriptorPool,
+    // Storage image in, storage image out (bright pass)
+    pub storage_set_layout: vk::DescriptorSetLayout,
+    // Sampled image in, storage image out (down-sample, up-sample and composite)
+    pub sampled_set_layout: vk::DescriptorSetLayout,
+    pub storage_pipeline_layout: vk::PipelineLayout,
+    pub sampled_pipeline_layout: vk::PipelineLayout,
+    pub extract_pipeline: Option<vk::Pipeline>,
+    pub downsample_pipeline: Option<vk::Pipeline>,
+    pub upsample_pipeline: Option<vk::Pipeline>,
+    pub composite_pipeline: Option<vk::Pipeline>,
+    pub extract_set: vk::DescriptorSet,
+    // downsample_sets[i] reads levels[i] and writes levels[i + 1]
+    pub downsample_sets: Vec<vk::DescriptorSet>,
+    // upsample_sets[i] reads levels[i + 1] and adds into levels[i]
+    pub upsample_sets: Vec<vk::DescriptorSet>,
+    pub composite_set: vk::DescriptorSet,
+}
+
+// Maps the HDR scene color down to the LDR image that gets presented
+pub struct ToneMapPass {
+    pub output: RenderTarget,
+    pub set_layout: vk::DescriptorSetLayout,
+    pub pipeline_layout: vk::PipelineLayout,
+    pub pipeline: Option<vk::Pipeline>,
+    pub set: vk::DescriptorSet,
+}
+
+// Ray marches the view rays through noisy fog; rgb is in-scattered light, alpha transmittance
+pub struct FogPass {
+    pub uniforms: GpuBuffer,
+    pub noise: VolumeTexture,
+    pub output: RenderTarget,
+    pub extent: vk::Extent2D,
+    pub noise_sampler: vk::Sampler,
+    pub output_sampler: vk::Sampler,
+    pub set_layout: vk::DescriptorSetLayout,
+    pub pipeline_layout: vk::PipelineLayout,
+    pub pipeline: Option<vk::Pipeline>,
+    pub set: vk::DescriptorSet,
+}
+
+// Fullscreen sky drawn first in the lighting subpass; lighting blends the scene over it.
+// Nothing here depends on the window size, so it has its own descriptor pool.
+pub struct SkyPass {
+    pub uniforms: GpuBuffer,
+    pub descriptor_pool: vk::DescriptorPool,
+    pub set_layout: vk::DescriptorSetLayout,
+    pub pipeline_layout: vk::PipelineLayout,
+    pub pipeline: Option<vk::Pipeline>,
+    pub set: vk::DescriptorSet,
+}
+
+// The mesh every culled object is drawn with, one instance per visible object
+pub struct CullMesh {
+    pub vertices: GpuBuffer,
+    pub indices: GpuBuffer,
+    pub index_count: u32,
+    // Only read as the BLAS build's highest vertex index
+    #[cfg_attr(not(feature = "ray_tracing"), allow(dead_code))]
+    pub vertex_count: u32,
+}
+
+// Frustum culls object bounding spheres in a compute shader, which appends the survivors to the
+// visible list and counts them into the instance count of an indexed indirect draw, so the
+// geometry subpass draws everything left with one call. Nothing here depends on the window
+// size, so it has its own descriptor pool.
+pub struct IndirectCullPass {
+    pub uniforms: GpuBuffer,
+    pub objects: GpuBuffer,
+    // Indices into objects, read back through gl_InstanceIndex
+    pub visible: GpuBuffer,
+    // A single VkDrawIndexedIndirectCommand
+    pub draw_command: GpuBuffer,
+    pub object_count: u32,
+    pub mesh: Option<CullMesh>,
+    pub descriptor_pool: vk::DescriptorPool,
+    pub set_layout: vk::DescriptorSetLayout,
+    pub pipeline_layout: vk::PipelineLayout,
+    pub pipeline: Option<vk::Pipeline>,
+    pub set: vk::DescriptorSet,
+    // Geometry pipeline set: the same uniforms, objects and visible list
+    pub geometry_set: vk::DescriptorSet,
+}
+
+// An acceleration structure and the buffer it lives in
+#[cfg(feature = "ray_tracing")]
+pub struct GpuAccelerationStructure {
+    pub handle: vk::AccelerationStructureKHR,
+    pub buffer: GpuBuffer,
+    pub address: vk::DeviceAddress,
+}
+
+// Ray traced ambient occlusion, used instead of the SSAO compute pass when the device
+// supports it. The BLAS is built once per mesh upload and the TLAS rebuilt every frame
+// from the geometry objects. Writes the SSAO occlusion image so the blur and lighting
+// passes don't change.
+#[cfg(feature = "ray_tracing")]
+pub struct RayTracingPipeline {
+    pub acceleration_structure_loader: AccelerationStructure,
+    pub ray_tracing_loader: RayTracingPipelineLoader,
+    // Only the cull mesh is uploaded so far, so there is a single BLAS
+    pub blas: Option<GpuAccelerationStructure>,
+    // Sized for MAX_CULL_OBJECTS instances
+    pub tlas: GpuAccelerationStructure,
+    pub instances: GpuBuffer,
+    pub instance_count: u32,
+    pub scratch: GpuBuffer,
+    pub scratch_address: vk::DeviceAddress,
+    pub scratch_alignment: vk::DeviceSize,
+    pub descriptor_pool: vk::DescriptorPool,
+    pub set_layout: vk::DescriptorSetLayout,
+    pub pipeline_layout: vk::PipelineLayout,
+    pub pipeline: vk::Pipeline,
+    pub set: vk::DescriptorSet,
+    pub shader_binding_table: GpuBuffer,
+    pub raygen_region: vk::StridedDeviceAddressRegionKHR,
+    pub miss_region: vk::StridedDeviceAddressRegionKHR,
+    pub hit_region: vk::StridedDeviceAddressRegionKHR,
+    // Seeds the per pixel ray directions
+    pub frame: u32,
+}
+
+// A mip mapped 2D texture loaded from disk, with the material set that samples it
+pub struct Texture {
+    pub image: vk::Image,
+    pub view: vk::ImageView,
+    pub allocation: Allocation,
+    pub set: vk::DescriptorSet,
+}
+
+// Textures by path, so each file is only uploaded once. They share one trilinear sampler and
+// the material set layout (a combined image sampler at binding 0).
+#[derive(Default)]
+pub struct TextureCache {
+    pub textures: HashMap<PathBuf, Texture>,
+    pub sampler: Option<vk::Sampler>,
+    pub descriptor_pool: Option<vk::DescriptorPool>,
+    pub set_layout: Option<vk::DescriptorSetLayout>,
+}
+
+// A 3D texture filled once from the CPU, used for lookup tables and noise
+pub struct VolumeTexture {
+    pub image: vk::Image,
+    pub view: vk::ImageView,
+    pub allocation: Allocation,
+}
+
+// 3D lookup table the tone map pass grades its output with
+pub struct ColorGradingLut {
+    pub texture: VolumeTexture,
+    pub sampler: vk::Sampler,
+}
+
+// gpu-allocator doesn't report totals, so every allocation and free goes through this to keep
+// a count of live allocations and their size
+pub struct TrackedAllocator {
+    allocator: Allocator,
+    allocation_count: usize,
+    bytes_allocated: u64,
+}
+
+impl TrackedAllocator {
+    fn new(allocator: Allocator) -> Self {
+        Self {
+            allocator,
+            allocation_count: 0,
+            bytes_allocated: 0,
+        }
+    }
+
+    pub fn allocate(&mut self, desc: &AllocationCreateDesc) -> gpu_allocator::Result<Allocation> {
+        let allocation = self.allocator.allocate(desc)?;
+        self.allocation_count += 1;
+        self.bytes_allocated += allocation.size();
+        Ok(allocation)
+    }
+
+    pub fn free(&mut self, allocation: Allocation) -> gpu_allocator::Result<()> {
+        let size = allocation.size();
+        self.allocator.free(allocation)?;
+        self.allocation_count -= 1;
+        self.bytes_allocated -= size;
+        Ok(())
+    }
+
+    pub fn allocation_count(&self) -> usize {
+        self.allocation_count
+    }
+
+    pub fn bytes_allocated(&self) -> u64 {
+        self.bytes_allocated
+    }
+}
+
+// Entities drawn by the Vulkan renderer, tagged with the subpass they belong to
+#[derive(Component)]
+pub struct VulkanObject {
+    pub subpass_index: u32,
+}
+
+#[derive(Resource, Default)]
+#[allow(dead_code)]
+pub struct VulkanRenderer {
+    pub entry: Option<Entry>,
+    pub instance: Option<AshInstance>,
+    pub physical_device: Option<vk::PhysicalDevice>,
+    pub device: Option<AshDevice>,
+    pub graphics_queue_family: u32,
+    pub graphics_queue: Option<vk::Queue>,
+    pub graphics_command_pool: Option<vk::CommandPool>,
+    // Only set when the GPU exposes a transfer-only queue family
+    pub transfer_queue_family: Option<u32>,
+    pub transfer_queue: Option<vk::Queue>,
+    pub transfer_command_pool: Option<vk::CommandPool>,
+    pub surface: Option<vk::SurfaceKHR>,
+    pub swapchain: Option<vk::SwapchainKHR>,
+    pub swapchain_images: Vec<vk::Image>,
+    pub swapchain_extent: vk::Extent2D,
+    pub gbuffer: Option<GBuffer>,
+    pub scene_color: Option<RenderTarget>,
+    pub render_pass: Option<vk::RenderPass>,
+    pub geometry_descriptor_set_layout: Option<vk::DescriptorSetLayout>,
+    pub lighting_descriptor_set_layout: Option<vk::DescriptorSetLayout>,
+    pub geometry_pipeline_layout: Option<vk::PipelineLayout>,
+    pub lighting_pipeline_layout: Option<vk::PipelineLayout>,
+    pub geometry_pipeline: Option<vk::Pipeline>,
+    pub lighting_pipeline: Option<vk::Pipeline>,
+    pub descriptor_pool: Option<vk::DescriptorPool>,
+    pub lighting_descriptor_set: Option<vk::DescriptorSet>,
+    pub ssao: Option<SsaoPass>,
+    pub fog: Option<FogPass>,
+    pub sky: Option<SkyPass>,
+    pub cull: Option<IndirectCullPass>,
+    // Whether the device was created with the ray tracing extensions
+    pub ray_tracing_supported: bool,
+    #[cfg(feature = "ray_tracing")]
+    pub ray_tracing: Option<RayTracingPipeline>,
+    pub bloom: Option<BloomPass>,
+    pub tone_map: Option<ToneMapPass>,
+    pub color_grading_lut: Option<ColorGradingLut>,
+    pub color_grading_blend: f32,
+    pub texture_cache: TextureCache,
+    pub allocator: Option<TrackedAllocator>,
+    pub debug_utils: Option<DebugUtils>,
+    pub debug: bool,
+    pub instance_created: bool,
+    pub device_created: bool,
+    pub swapchain_created: bool,
+    pub pipeline_created: bool,
+}
+
+impl VulkanRenderer {
+    // Attaches a human readable name to a Vulkan object for RenderDoc / Nsight captures
+    pub fn set_object_name<T: vk::Handle>(&self, handle: T, name: &str) {
+        let (Some(debug_utils), Some(device)) = (&self.debug_utils, &self.device) else {
+            return;
+        };
+        let Ok(name) = CString::new(name) else {
+            return;
+        };
+
+        let name_info = vk::DebugUtilsObjectNameInfoEXT::builder()
+            .object_type(T::TYPE)
+            .object_handle(handle.as_raw())
+            .object_name(&name)
+            .build();
+
+        if let Err(err) = unsafe { debug_utils.set_debug_utils_object_name(device.handle(), &name_info) } {
+            warn!("Failed to name Vulkan object {:?}: {:?}", name, err);
+        }
+    }
+
+    // Total size of the device local memory heaps, what allocations are budgeted against
+    pub fn device_local_memory(&self) -> Option<u64> {
+        let (Some(instance), Some(physical_device)) = (&self.instance, self.physical_device) else {
+            return None;
+        };
+        let properties = unsafe { instance.get_physical_device_memory_properties(physical_device) };
+        let total = properties.memory_heaps[..properties.memory_heap_count as usize]
+            .iter()
+            .filter(|heap| heap.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+            .map(|heap| heap.size)
+            .sum();
+        Some(total)
+    }
+
+    pub fn begin_debug_label(&self, command_buffer: vk::CommandBuffer, name: &str) {
+        let Some(debug_utils) = &self.debug_utils else {
+            return;
+        };
+        let Ok(name) = CString::new(name) else {
+            return;
+        };
+
+        let label = vk::DebugUtilsLabelEXT::builder()
+            .label_name(&name)
+            .build();
+        unsafe { debug_utils.cmd_begin_debug_utils_label(command_buffer, &label) };
+    }
+
+    pub fn end_debug_label(&self, command_buffer: vk::CommandBuffer) {
+        if let Some(debug_utils) = &self.debug_utils {
+            unsafe { debug_utils.cmd_end_debug_utils_label(command_buffer) };
+        }
+    }
+
+    fn create_buffer(
+        &mut self,
+        device: &AshDevice,
+        size: vk::DeviceSize,
+        usage: vk::BufferUsageFlags,
+        location: MemoryLocation,
+        name: &str,
+    ) -> GpuBuffer {
+        let buffer_create_info = vk::BufferCreateInfo::builder()
+            .size(size)
+            .usage(usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .build();
+        let buffer = unsafe {
+            device.create_buffer(&buffer_create_info, None)
+                .expect("Failed to create buffer")
+        };
+        let requirements = unsafe { device.get_buffer_memory_requirements(buffer) };
+
+        let allocation = self.allocator.as_mut()
+            .expect("Allocator not created")
+            .allocate(&AllocationCreateDesc {
+                name,
+                requirements,
+                location,
+                linear: true,
+                allocation_scheme: AllocationScheme::GpuAllocatorManaged,
+            })
+            .expect("Failed to allocate buffer memory");
+        unsafe {
+            device.bind_buffer_memory(buffer, allocation.memory(), allocation.offset())
+                .expect("Failed to bind buffer memory")
+        };
+
+        self.set_object_name(buffer, name);
+        GpuBuffer { buffer, allocation }
+    }
+
+    fn create_render_target(
+        &mut self,
+        device: &AshDevice,
+        extent: vk::Extent2D,
+        format: vk::Format,
+        usage: vk::ImageUsageFlags,
+        name: &str,
+    ) -> RenderTarget {
+        let image_create_info = vk::ImageCreateInfo::builder()
+            .image_type(vk::ImageType::TYPE_2D)
+            .format(format)
+            .extent(vk::Extent3D {
+                width: extent.width,
+                height: extent.height,
+                depth: 1,
+            })
+            .mip_levels(1)
+            .array_layers(1)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(usage)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .build();
+        let image = unsafe {
+            device.create_image(&image_create_info, None)
+                .expect("Failed to create render target image")
+        };
+        let requirements = unsafe { device.get_image_memory_requirements(image) };
+
+        let allocation = self.allocator.as_mut()
+            .expect("Allocator not created")
+            .allocate(&AllocationCreateDesc {
+                name,
+                requirements,
+                location: MemoryLocation::GpuOnly,
+                linear: false,
+                allocation_scheme: AllocationScheme::GpuAllocatorManaged,
+            })
+            .expect("Failed to allocate render target memory");
+        unsafe {
+            device.bind_image_memory(image, allocation.memory(), allocation.offset())
+                .expect("Failed to bind render target memory")
+        };
+
+        let view_create_info = vk::ImageViewCreateInfo::builder()
+            .image(image)
+            .view_type(vk::ImageViewType::TYPE_2D)
+            .format(format)
+            .subresource_range(COLOR_SUBRESOURCE_RANGE)
+            .build();
+        let view = unsafe {
+            device.create_image_view(&view_create_info, None)
+                .expect("Failed to create render target view")
+        };
+
+        self.set_object_name(image, name);
+        self.set_object_name(view, &format!("{} View", name));
+        RenderTarget { image, view, allocation }
+    }
+
+    fn free_render_target(&mut self, target: RenderTarget) {
+        let (Some(device), Some(allocator)) = (&self.device, self.allocator.as_mut()) else {
+            return;
+        };
+        unsafe {
+            device.destroy_image_view(target.view, None);
+            device.destroy_image(target.image, None);
+        }
+        if let Err(err) = allocator.free(target.allocation) {
+            warn!("Failed to free render target memory: {:?}", err);
+        }
+    }
+
+    fn destroy_gbuffer(&mut self) {
+        let Some(gbuffer) = self.gbuffer.take() else {
+            return;
+        };
+        for target in [gbuffer.position, gbuffer.normal, gbuffer.albedo] {
+            self.free_render_target(target);
+        }
+    }
+
+    pub fn free_buffer(&mut self, buffer: GpuBuffer) {
+        let (Some(device), Some(allocator)) = (&self.device, self.allocator.as_mut()) else {
+            return;
+        };
+        if let Err(err) = allocator.free(buffer.allocation) {
+            warn!("Failed to free buffer memory: {:?}", err);
+        }
+        unsafe { device.destroy_buffer(buffer.buffer, None) };
+    }
+
+    // Copies mesh data into a new device local buffer. With a dedicated transfer queue
+    // the copy runs there and ownership is handed to the graphics queue afterwards, so
+    // uploads don't occupy the graphics queue.
+    pub fn upload_mesh(&mut self, data: &[u8], usage: vk::BufferUsageFlags) -> Option<GpuBuffer> {
+        let device = self.device.clone()?;
+        let graphics_queue = self.graphics_queue?;
+        let graphics_command_pool = self.graphics_command_pool?;
+        let size = data.len() as vk::DeviceSize;
+
+        let mut staging = self.create_buffer(&device, size, vk::BufferUsageFlags::TRANSFER_SRC, MemoryLocation::CpuToGpu, "Mesh Staging Buffer");
+        staging.allocation.mapped_slice_mut()
+            .expect("Staging buffer is not host visible")[..data.len()]
+            .copy_from_slice(data);
+        // Meshes may become acceleration structure build input
+        let usage = if self.ray_tracing_supported {
+            usage | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS | vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR
+        } else {
+            usage
+        };
+        let destination = self.create_buffer(&device, size, usage | vk::BufferUsageFlags::TRANSFER_DST, MemoryLocation::GpuOnly, "Mesh Buffer");
+
+        let (copy_queue, copy_command_pool, copy_family) = match (self.transfer_queue, self.transfer_command_pool, self.transfer_queue_family) {
+            (Some(queue), Some(command_pool), Some(family)) => (queue, command_pool, family),
+            _ => (graphics_queue, graphics_command_pool, self.graphics_queue_family),
+        };
+        let dedicated_transfer = copy_family != self.graphics_queue_family;
+
+        // Barrier that moves the buffer from the transfer family to the graphics family.
+        // The same barrier is recorded on both queues: a release and a matching acquire.
+        let ownership_barrier = vk::BufferMemoryBarrier::builder()
+            .src_queue_family_index(copy_family)
+            .dst_queue_family_index(self.graphics_queue_family)
+            .buffer(destination.buffer)
+            .offset(0)
+            .size(vk::WHOLE_SIZE)
+            .build();
+
+        unsafe {
+            let copy_command_buffer = allocate_one_time_command_buffer(&device, copy_command_pool);
+            device.cmd_copy_buffer(copy_command_buffer, staging.buffer, destination.buffer, &[vk::BufferCopy {
+                src_offset: 0,
+                dst_offset: 0,
+                size,
+            }]);
+            if dedicated_transfer {
+                let release = vk::BufferMemoryBarrier {
+                    src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+                    dst_access_mask: vk::AccessFlags::empty(),
+                    ..ownership_barrier
+                };
+                device.cmd_pipeline_barrier(
+                    copy_command_buffer,
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[release],
+                    &[],
+                );
+            }
+            device.end_command_buffer(copy_command_buffer)
+                .expect("Failed to record upload commands");
+
+            let fence = device.create_fence(&vk::FenceCreateInfo::default(), None)
+                .expect("Failed to create upload fence");
+
+            if dedicated_transfer {
+                let semaphore = device.create_semaphore(&vk::SemaphoreCreateInfo::default(), None)
+                    .expect("Failed to create upload semaphore");
+
+                let copy_submit = vk::SubmitInfo::builder()
+                    .command_buffers(std::slice::from_ref(&copy_command_buffer))
+                    .signal_semaphores(std::slice::from_ref(&semaphore))
+                    .build();
+                device.queue_submit(copy_queue, &[copy_submit], vk::Fence::null())
+                    .expect("Failed to submit upload to transfer queue");
+
+                let acquire_command_buffer = allocate_one_time_command_buffer(&device, graphics_command_pool);
+                let acquire = vk::BufferMemoryBarrier {
+                    src_access_mask: vk::AccessFlags::empty(),
+                    dst_access_mask: vk::AccessFlags::VERTEX_ATTRIBUTE_READ | vk::AccessFlags::INDEX_READ,
+                    ..ownership_barrier
+                };
+                device.cmd_pipeline_barrier(
+                    acquire_command_buffer,
+                    vk::PipelineStageFlags::TOP_OF_PIPE,
+                    vk::PipelineStageFlags::VERTEX_INPUT,
+                    vk::DependencyFlags::empty(),
+                    &[],
+                    &[acquire],
+                    &[],
+                );
+                device.end_command_buffer(acquire_command_buffer)
+                    .expect("Failed to record ownership acquire");
+
+                let wait_stage = vk::PipelineStageFlags::VERTEX_INPUT;
+                let acquire_submit = vk::SubmitInfo::builder()
+                    .wait_semaphores(std::slice::from_ref(&semaphore))
+                    .wait_dst_stage_mask(std::slice::from_ref(&wait_stage))
+                    .command_buffers(std::slice::from_ref(&acquire_command_buffer))
+                    .build();
+                device.queue_submit(graphics_queue, &[acquire_submit], fence)
+                    .expect("Failed to submit ownership acquire to graphics queue");
+
+                device.wait_for_fences(&[fence], true, u64::MAX)
+                    .expect("Failed to wait for mesh upload");
+                device.free_command_buffers(graphics_command_pool, &[acquire_command_buffer]);
+                device.destroy_semaphore(semaphore, None);
+            } else {
+                let copy_submit = vk::SubmitInfo::builder()
+                    .command_buffers(std::slice::from_ref(&copy_command_buffer))
+                    .build();
+                device.queue_submit(copy_queue, &[copy_submit], fence)
+                    .expect("Failed to submit upload");
+                device.wait_for_fences(&[fence], true, u64::MAX)
+                    .expect("Failed to wait for mesh upload");
+            }
+
+            device.free_command_buffers(copy_command_pool, &[copy_command_buffer]);
+            device.destroy_fence(fence, None);
+        }
+
+        self.free_buffer(staging);
+        Some(destination)
+    }
+
+    // Moves freshly created storage images into GENERAL, where they stay for their whole life
+    fn transition_to_general(&self, images: &[vk::Image]) {
+        let (Some(device), Some(graphics_queue), Some(graphics_command_pool)) =
+            (&self.device, self.graphics_queue, self.graphics_command_pool) else {
+            return;
+        };
+        unsafe {
+            let command_buffer = allocate_one_time_command_buffer(device, graphics_command_pool);
+            let barriers: Vec<_> = images.iter().map(|&image| {
+                vk::ImageMemoryBarrier::builder()
+                    .src_access_mask(vk::AccessFlags::empty())
+                    .dst_access_mask(vk::AccessFlags::SHADER_WRITE)
+                    .old_layout(vk::ImageLayout::UNDEFINED)
+                    .new_layout(vk::ImageLayout::GENERAL)
+                    .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                    .image(image)
+                    .subresource_range(COLOR_SUBRESOURCE_RANGE)
+                    .build()
+            }).collect();
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &barriers,
+            );
+            device.end_command_buffer(command_buffer)
+                .expect("Failed to record layout transition");
+            let submit_info = vk::SubmitInfo::builder()
+                .command_buffers(std::slice::from_ref(&command_buffer))
+                .build();
+            device.queue_submit(graphics_queue, &[submit_info], vk::Fence::null())
+                .expect("Failed to submit layout transition");
+            device.queue_wait_idle(graphics_queue)
+                .expect("Failed to wait for layout transition");
+            device.free_command_buffers(graphics_command_pool, &[command_buffer]);
+        }
+    }
+
+    // Rebuilds everything sized to the window. The render pass and main pipelines don't
+    // depend on the extent, so only the targets and the passes that own targets are redone.
+    pub fn recreate_render_targets(&mut self, extent: vk::Extent2D) {
+        let (Some(device), Some(descriptor_pool), Some(lighting_set_layout)) =
+            (self.device.clone(), self.descriptor_pool, self.lighting_descriptor_set_layout) else {
+            return;
+        };
+        info!("Recreating Vulkan render targets ({}x{})...", extent.width, extent.height);
+        self.wait_idle();
+
+        self.destroy_tone_map();
+        self.destroy_bloom();
+        self.destroy_fog();
+        self.destroy_ssao();
+        if let Some(scene_color) = self.scene_color.take() {
+            self.free_render_target(scene_color);
+        }
+        self.destroy_gbuffer();
+        unsafe {
+            device.reset_descriptor_pool(descriptor_pool, vk::DescriptorPoolResetFlags::empty())
+                .expect("Failed to reset descriptor pool")
+        };
+        self.lighting_descriptor_set = None;
+
+        self.swapchain_extent = extent;
+        self.gbuffer = create_gbuffer(self, extent);
+        self.scene_color = Some(create_scene_color(self, &device, extent));
+        self.ssao = create_ssao_pipeline(self, descriptor_pool, extent);
+        #[cfg(feature = "ray_tracing")]
+        self.write_ray_tracing_targets();
+        self.fog = create_fog_pass(self, descriptor_pool, extent);
+        self.lighting_descriptor_set = write_lighting_descriptor_set(self, descriptor_pool, lighting_set_layout);
+        self.bloom = create_bloom_pass(self, extent);
+        self.tone_map = create_tone_map_pass(self, descriptor_pool, extent);
+        self.write_tone_map_lut();
+    }
+
+    // Uploads a size^3 3D texture and leaves it in SHADER_READ_ONLY_OPTIMAL for compute shaders
+    fn create_volume_texture(&mut self, size: u32, format: vk::Format, bytes: &[u8], name: &str) -> Option<VolumeTexture> {
+        let (Some(device), Some(graphics_queue), Some(graphics_command_pool)) =
+            (self.device.clone(), self.graphics_queue, self.graphics_command_pool) else {
+            return None;
+        };
+        let extent = vk::Extent3D {
+            width: size,
+            height: size,
+            depth: size,
+        };
+
+        let image_create_info = vk::ImageCreateInfo::builder()
+            .image_type(vk::ImageType::TYPE_3D)
+            .format(format)
+            .extent(extent)
+            .mip_levels(1)
+            .array_layers(1)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .build();
+        let image = unsafe {
+            device.create_image(&image_create_info, None)
+                .expect("Failed to create volume texture")
+        };
+        let requirements = unsafe { device.get_image_memory_requirements(image) };
+        let allocation = self.allocator.as_mut()
+            .expect("Allocator not created")
+            .allocate(&AllocationCreateDesc {
+                name,
+                requirements,
+                location: MemoryLocation::GpuOnly,
+                linear: false,
+                allocation_scheme: AllocationScheme::GpuAllocatorManaged,
+            })
+            .expect("Failed to allocate volume texture memory");
+        unsafe {
+            device.bind_image_memory(image, allocation.memory(), allocation.offset())
+                .expect("Failed to bind volume texture memory")
+        };
+
+        let mut staging = self.create_buffer(
+            &device,
+            bytes.len() as vk::DeviceSize,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            MemoryLocation::CpuToGpu,
+            &format!("{} Staging Buffer", name),
+        );
+        staging.allocation.mapped_slice_mut()
+            .expect("Staging buffer is not host visible")[..bytes.len()]
+            .copy_from_slice(bytes);
+
+        let barrier = |old_layout, new_layout, src_access_mask, dst_access_mask| vk::ImageMemoryBarrier::builder()
+            .src_access_mask(src_access_mask)
+            .dst_access_mask(dst_access_mask)
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(COLOR_SUBRESOURCE_RANGE)
+            .build();
+        let region = vk::BufferImageCopy {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: vk::ImageSubresourceLayers {
+                aspect_mask: vk::ImageAspectFlags::COLOR,
+                mip_level: 0,
+                base_array_layer: 0,
+                layer_count: 1,
+            },
+            image_offset: vk::Offset3D::default(),
+            image_extent: extent,
+        };
+        unsafe {
+            let command_buffer = allocate_one_time_command_buffer(&device, graphics_command_pool);
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TOP_OF_PIPE,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier(vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::AccessFlags::empty(), vk::AccessFlags::TRANSFER_WRITE)],
+            );
+            device.cmd_copy_buffer_to_image(command_buffer, staging.buffer, image, vk::ImageLayout::TRANSFER_DST_OPTIMAL, &[region]);
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier(vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::SHADER_READ)],
+            );
+            device.end_command_buffer(command_buffer)
+                .expect("Failed to record volume texture upload");
+            let submit_info = vk::SubmitInfo::builder()
+                .command_buffers(std::slice::from_ref(&command_buffer))
+                .build();
+            device.queue_submit(graphics_queue, &[submit_info], vk::Fence::null())
+                .expect("Failed to submit volume texture upload");
+            device.queue_wait_idle(graphics_queue)
+                .expect("Failed to wait for volume texture upload");
+            device.free_command_buffers(graphics_command_pool, &[command_buffer]);
+        }
+        self.free_buffer(staging);
+
+        let view_create_info = vk::ImageViewCreateInfo::builder()
+            .image(image)
+            .view_type(vk::ImageViewType::TYPE_3D)
+            .format(format)
+            .subresource_range(COLOR_SUBRESOURCE_RANGE)
+            .build();
+        let view = unsafe {
+            device.create_image_view(&view_create_info, None)
+                .expect("Failed to create volume texture view")
+        };
+
+        self.set_object_name(image, name);
+        self.set_object_name(view, &format!("{} View", name));
+        Some(VolumeTexture { image, view, allocation })
+    }
+
+    // Loads an image file into a mip mapped RGBA texture and gives it a material descriptor set.
+    // Each path is uploaded once; later calls return the cached set.
+    pub fn upload_texture(&mut self, path: &Path) -> Option<vk::DescriptorSet> {
+        if let Some(texture) = self.texture_cache.textures.get(path) {
+            return Some(texture.set);
+        }
+        let (Some(device), Some(instance), Some(physical_device), Some(graphics_queue), Some(graphics_command_pool)) =
+            (self.device.clone(), self.instance.clone(), self.physical_device, self.graphics_queue, self.graphics_command_pool) else {
+            return None;
+        };
+        let (Some(sampler), Some(descriptor_pool), Some(set_layout)) =
+            (self.texture_cache.sampler, self.texture_cache.descriptor_pool, self.texture_cache.set_layout) else {
+            return None;
+        };
+        if self.texture_cache.textures.len() >= MAX_TEXTURES as usize {
+            warn!("Texture limit of {} reached, not loading {}", MAX_TEXTURES, path.display());
+            return None;
+        }
+        let pixels = match image::open(path) {
+            Ok(image) => image.to_rgba8(),
+            Err(err) => {
+                warn!("Failed to load texture {}: {}", path.display(), err);
+                return None;
+            }
+        };
+        let (width, height) = pixels.dimensions();
+        let name = path.display().to_string();
+        info!("Uploading {}x{} texture {}...", width, height, name);
+
+        // Blitting the mip chain needs linear filtering on the format; without it only the full
+        // size level is kept
+        let format_properties = unsafe { instance.get_physical_device_format_properties(physical_device, TEXTURE_FORMAT) };
+        let mip_levels = if format_properties.optimal_tiling_features.contains(vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR) {
+            32 - width.max(height).leading_zeros()
+        } else {
+            warn!("{:?} can't be blitted with linear filtering, {} gets no mip maps", TEXTURE_FORMAT, name);
+            1
+        };
+
+        let image_create_info = vk::ImageCreateInfo::builder()
+            .image_type(vk::ImageType::TYPE_2D)
+            .format(TEXTURE_FORMAT)
+            .extent(vk::Extent3D { width, height, depth: 1 })
+            .mip_levels(mip_levels)
+            .array_layers(1)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .tiling(vk::ImageTiling::OPTIMAL)
+            .usage(vk::ImageUsageFlags::TRANSFER_SRC | vk::ImageUsageFlags::TRANSFER_DST | vk::ImageUsageFlags::SAMPLED)
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .build();
+        let image = unsafe {
+            device.create_image(&image_create_info, None)
+                .expect("Failed to create texture")
+        };
+        let requirements = unsafe { device.get_image_memory_requirements(image) };
+        let allocation = self.allocator.as_mut()
+            .expect("Allocator not created")
+            .allocate(&AllocationCreateDesc {
+                name: &name,
+                requirements,
+                location: MemoryLocation::GpuOnly,
+                linear: false,
+                allocation_scheme: AllocationScheme::GpuAllocatorManaged,
+            })
+            .expect("Failed to allocate texture memory");
+        unsafe {
+            device.bind_image_memory(image, allocation.memory(), allocation.offset())
+                .expect("Failed to bind texture memory")
+        };
+
+        let bytes = pixels.as_raw();
+        let mut staging = self.create_buffer(
+            &device,
+            bytes.len() as vk::DeviceSize,
+            vk::BufferUsageFlags::TRANSFER_SRC,
+            MemoryLocation::CpuToGpu,
+            &format!("{} Staging Buffer", name),
+        );
+        staging.allocation.mapped_slice_mut()
+            .expect("Staging buffer is not host visible")[..bytes.len()]
+            .copy_from_slice(bytes);
+
+        let barrier = |mip_level, old_layout, new_layout, src_access_mask, dst_access_mask| vk::ImageMemoryBarrier::builder()
+            .src_access_mask(src_access_mask)
+            .dst_access_mask(dst_access_mask)
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(vk::ImageSubresourceRange {
+                base_mip_level: mip_level,
+                ..COLOR_SUBRESOURCE_RANGE
+            })
+            .build();
+        let mip_layers = |mip_level| vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        // Far corner of a mip level, never smaller than a pixel
+        let mip_extent = |mip_level: u32| vk::Offset3D {
+            x: (width >> mip_level).max(1) as i32,
+            y: (height >> mip_level).max(1) as i32,
+            z: 1,
+        };
+        let region = vk::BufferImageCopy {
+            buffer_offset: 0,
+            buffer_row_length: 0,
+            buffer_image_height: 0,
+            image_subresource: mip_layers(0),
+            image_offset: vk::Offset3D::default(),
+            image_extent: vk::Extent3D { width, height, depth: 1 },
+        };
+        unsafe {
+            let command_buffer = allocate_one_time_command_buffer(&device, graphics_command_pool);
+            let pipeline_barrier = |src_stage, dst_stage, barrier: vk::ImageMemoryBarrier| {
+                device.cmd_pipeline_barrier(command_buffer, src_stage, dst_stage, vk::DependencyFlags::empty(), &[], &[], &[barrier]);
+            };
+            let all_levels = vk::ImageMemoryBarrier {
+                subresource_range: vk::ImageSubresourceRange {
+                    level_count: mip_levels,
+                    ..COLOR_SUBRESOURCE_RANGE
+                },
+                ..barrier(0, vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::AccessFlags::empty(), vk::AccessFlags::TRANSFER_WRITE)
+            };
+            pipeline_barrier(vk::PipelineStageFlags::TOP_OF_PIPE, vk::PipelineStageFlags::TRANSFER, all_levels);
+            device.cmd_copy_buffer_to_image(command_buffer, staging.buffer, image, vk::ImageLayout::TRANSFER_DST_OPTIMAL, &[region]);
+
+            // Each level is blitted down from the one before, which is then done with
+            for mip_level in 1..mip_levels {
+                let source = mip_level - 1;
+                pipeline_barrier(
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::TRANSFER,
+                    barrier(source, vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::TRANSFER_SRC_OPTIMAL, vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::TRANSFER_READ),
+                );
+                let blit = vk::ImageBlit {
+                    src_subresource: mip_layers(source),
+                    src_offsets: [vk::Offset3D::default(), mip_extent(source)],
+                    dst_subresource: mip_layers(mip_level),
+                    dst_offsets: [vk::Offset3D::default(), mip_extent(mip_level)],
+                };
+                device.cmd_blit_image(
+                    command_buffer,
+                    image,
+                    vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                    image,
+                    vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                    &[blit],
+                    vk::Filter::LINEAR,
+                );
+                pipeline_barrier(
+                    vk::PipelineStageFlags::TRANSFER,
+                    vk::PipelineStageFlags::FRAGMENT_SHADER,
+                    barrier(source, vk::ImageLayout::TRANSFER_SRC_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, vk::AccessFlags::TRANSFER_READ, vk::AccessFlags::SHADER_READ),
+                );
+            }
+            // The smallest level was only ever written
+            pipeline_barrier(
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                barrier(mip_levels - 1, vk::ImageLayout::TRANSFER_DST_OPTIMAL, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL, vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::SHADER_READ),
+            );
+
+            device.end_command_buffer(command_buffer)
+                .expect("Failed to record texture upload");
+            let submit_info = vk::SubmitInfo::builder()
+                .command_buffers(std::slice::from_ref(&command_buffer))
+                .build();
+            device.queue_submit(graphics_queue, &[submit_info], vk::Fence::null())
+                .expect("Failed to submit texture upload");
+            device.queue_wait_idle(graphics_queue)
+                .expect("Failed to wait for texture upload");
+            device.free_command_buffers(graphics_command_pool, &[command_buffer]);
+        }
+        self.free_buffer(staging);
+
+        let view_create_info = vk::ImageViewCreateInfo::builder()
+            .image(image)
+            .view_type(vk::ImageViewType::TYPE_2D)
+            .format(TEXTURE_FORMAT)
+            .subresource_range(vk::ImageSubresourceRange {
+                level_count: mip_levels,
+                ..COLOR_SUBRESOURCE_RANGE
+            })
+            .build();
+        let view = unsafe {
+            device.create_image_view(&view_create_info, None)
+                .expect("Failed to create texture view")
+        };
+        self.set_object_name(image, &name);
+        self.set_object_name(view, &format!("{} View", name));
+
+        let set = allocate_descriptor_set(&device, descriptor_pool, set_layout);
+        let image_info = vk::DescriptorImageInfo {
+            sampler,
+            image_view: view,
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        };
+        unsafe { device.update_descriptor_sets(&[image_write(set, 0, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, &image_info)], &[]) };
+
+        self.texture_cache.textures.insert(path.to_path_buf(), Texture { image, view, allocation, set });
+        Some(set)
+    }
+
+    fn destroy_texture_cache(&mut self) {
+        let texture_cache = std::mem::take(&mut self.texture_cache);
+        let (Some(device), Some(allocator)) = (&self.device, self.allocator.as_mut()) else {
+            return;
+        };
+        for texture in texture_cache.textures.into_values() {
+            unsafe {
+                device.destroy_image_view(texture.view, None);
+                device.destroy_image(texture.image, None);
+            }
+            if let Err(err) = allocator.free(texture.allocation) {
+                warn!("Failed to free texture memory: {:?}", err);
+            }
+        }
+        unsafe {
+            if let Some(descriptor_pool) = texture_cache.descriptor_pool {
+                device.destroy_descriptor_pool(descriptor_pool, None);
+            }
+            if let Some(set_layout) = texture_cache.set_layout {
+                device.destroy_descriptor_set_layout(set_layout, None);
+            }
+            if let Some(sampler) = texture_cache.sampler {
+                device.destroy_sampler(sampler, None);
+            }
+        }
+    }
+
+    fn free_volume_texture(&mut self, texture: VolumeTexture) {
+        let (Some(device), Some(allocator)) = (&self.device, self.allocator.as_mut()) else {
+            return;
+        };
+        unsafe {
+            device.destroy_image_view(texture.view, None);
+            device.destroy_image(texture.image, None);
+        }
+        if let Err(err) = allocator.free(texture.allocation) {
+            warn!("Failed to free volume texture memory: {:?}", err);
+        }
+    }
+
+    // Replaces the color grading LUT with a size^3 RGBA8 table and points tone mapping at it
+    pub fn set_color_grading_lut(&mut self, size: u32, data: &[[u8; 4]]) {
+        let Some(device) = self.device.clone() else {
+            return;
+        };
+        info!("Uploading {}^3 color grading LUT...", size);
+        // The old LUT may still be referenced by in-flight frames
+        self.wait_idle();
+        self.destroy_color_grading_lut();
+
+        let Some(texture) = self.create_volume_texture(size, vk::Format::R8G8B8A8_UNORM, bytemuck::cast_slice(data), "Color Grading LUT") else {
+            return;
+        };
+        // Trilinear filtering between entries is what makes a 64^3 table look smooth
+        let sampler = create_sampler(&device, vk::Filter::LINEAR, vk::SamplerAddressMode::CLAMP_TO_EDGE);
+        self.color_grading_lut = Some(ColorGradingLut { texture, sampler });
+        self.write_tone_map_lut();
+    }
+
+    fn write_tone_map_lut(&self) {
+        let (Some(device), Some(tone_map), Some(lut)) = (&self.device, &self.tone_map, &self.color_grading_lut) else {
+            return;
+        };
+        let lut_info = vk::DescriptorImageInfo {
+            sampler: lut.sampler,
+            image_view: lut.texture.view,
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        };
+        let write = image_write(tone_map.set, 2, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, &lut_info);
+        unsafe { device.update_descriptor_sets(&[write], &[]) };
+    }
+
+    fn destroy_color_grading_lut(&mut self) {
+        let Some(lut) = self.color_grading_lut.take() else {
+            return;
+        };
+        if let Some(device) = &self.device {
+            unsafe { device.destroy_sampler(lut.sampler, None) };
+        }
+        self.free_volume_texture(lut.texture);
+    }
+
+    // Blocks until the GPU has finished all submitted work
+    pub fn wait_idle(&self) {
+        if let Some(device) = &self.device {
+            if let Err(err) = unsafe { device.device_wait_idle() } {
+                warn!("Failed to wait for Vulkan device to go idle: {:?}", err);
+            }
+        }
+    }
+
+    fn destroy_ssao(&mut self) {
+        let Some(ssao) = self.ssao.take() else {
+            return;
+        };
+        if let Some(device) = &self.device {
+            unsafe {
+                for pipeline in [ssao.blur_pipeline, ssao.ssao_pipeline].into_iter().flatten() {
+                    device.destroy_pipeline(pipeline, None);
+                }
+                device.destroy_pipeline_layout(ssao.blur_pipeline_layout, None);
+                device.destroy_pipeline_layout(ssao.ssao_pipeline_layout, None);
+                device.destroy_descriptor_set_layout(ssao.blur_set_layout, None);
+                device.destroy_descriptor_set_layout(ssao.ssao_set_layout, None);
+                device.destroy_sampler(ssao.sampler, None);
+            }
+        }
+        self.free_render_target(ssao.blurred);
+        self.free_render_target(ssao.occlusion);
+        self.free_buffer(ssao.uniforms);
+    }
+
+    // Writes the camera matrices SSAO projects its samples with
+    pub fn update_ssao_camera(&mut self, view: Mat4, projection: Mat4) {
+        let Some(ssao) = self.ssao.as_mut() else {
+            return;
+        };
+        let Some(mapped) = ssao.uniforms.allocation.mapped_slice_mut() else {
+            return;
+        };
+        let matrices = [view.to_cols_array_2d(), projection.to_cols_array_2d()];
+        let bytes: &[u8] = bytemuck::cast_slice(&matrices);
+        mapped[..bytes.len()].copy_from_slice(bytes);
+    }
+
+    // Records the SSAO and blur dispatches. This runs after the deferred render pass, so the
+    // lighting subpass sees the previous frame's occlusion; a frame of lag isn't noticeable.
+    pub fn record_ssao(&self, command_buffer: vk::CommandBuffer) {
+        let (Some(device), Some(ssao)) = (&self.device, &self.ssao) else {
+            return;
+        };
+        let Some(blur_pipeline) = ssao.blur_pipeline else {
+            return;
+        };
+        // Ray traced occlusion replaces the SSAO dispatch once there's a BLAS to trace against
+        #[cfg(feature = "ray_tracing")]
+        let ray_traced = self.ray_tracing.as_ref().is_some_and(|ray_tracing| ray_tracing.blas.is_some());
+        #[cfg(not(feature = "ray_tracing"))]
+        let ray_traced = false;
+        if !ray_traced && ssao.ssao_pipeline.is_none() {
+            return;
+        }
+        let occlusion_stage = if ray_traced {
+            vk::PipelineStageFlags::RAY_TRACING_SHADER_KHR
+        } else {
+            vk::PipelineStageFlags::COMPUTE_SHADER
+        };
+
+        let group_count_x = ssao.extent.width.div_ceil(SSAO_WORKGROUP_SIZE);
+        let group_count_y = ssao.extent.height.div_ceil(SSAO_WORKGROUP_SIZE);
+        let storage_barrier = |image, dst_stage_access| vk::ImageMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+            .dst_access_mask(dst_stage_access)
+            .old_layout(vk::ImageLayout::GENERAL)
+            .new_layout(vk::ImageLayout::GENERAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(image)
+            .subresource_range(COLOR_SUBRESOURCE_RANGE)
+            .build();
+
+        self.begin_debug_label(command_buffer, "SSAO");
+        unsafe {
+            if ray_traced {
+                #[cfg(feature = "ray_tracing")]
+                self.record_ray_traced_ao(command_buffer);
+            } else if let Some(ssao_pipeline) = ssao.ssao_pipeline {
+                device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::COMPUTE, ssao_pipeline);
+                device.cmd_bind_descriptor_sets(command_buffer, vk::PipelineBindPoint::COMPUTE, ssao.ssao_pipeline_layout, 0, &[ssao.ssao_set], &[]);
+                device.cmd_dispatch(command_buffer, group_count_x, group_count_y, 1);
+            }
+
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                occlusion_stage,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[storage_barrier(ssao.occlusion.image, vk::AccessFlags::SHADER_READ)],
+            );
+
+            device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::COMPUTE, blur_pipeline);
+            device.cmd_bind_descriptor_sets(command_buffer, vk::PipelineBindPoint::COMPUTE, ssao.blur_pipeline_layout, 0, &[ssao.blur_set], &[]);
+            device.cmd_dispatch(command_buffer, group_count_x, group_count_y, 1);
+
+            // The lighting subpass samples the blurred result
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[storage_barrier(ssao.blurred.image, vk::AccessFlags::SHADER_READ)],
+            );
+        }
+        self.end_debug_label(command_buffer);
+    }
+
+    #[cfg(feature = "ray_tracing")]
+    fn buffer_device_address(&self, buffer: vk::Buffer) -> vk::DeviceAddress {
+        let device = self.device.as_ref().expect("Device not created");
+        let address_info = vk::BufferDeviceAddressInfo::builder()
+            .buffer(buffer)
+            .build();
+        unsafe { device.get_buffer_device_address(&address_info) }
+    }
+
+    #[cfg(feature = "ray_tracing")]
+    fn create_acceleration_structure(
+        &mut self,
+        loader: &AccelerationStructure,
+        ty: vk::AccelerationStructureTypeKHR,
+        size: vk::DeviceSize,
+        name: &str,
+    ) -> GpuAccelerationStructure {
+        let device = self.device.clone().expect("Device not created");
+        let buffer = self.create_buffer(
+            &device,
+            size,
+            vk::BufferUsageFlags::ACCELERATION_STRUCTURE_STORAGE_KHR | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+            MemoryLocation::GpuOnly,
+            name,
+        );
+        let create_info = vk::AccelerationStructureCreateInfoKHR::builder()
+            .buffer(buffer.buffer)
+            .size(size)
+            .ty(ty)
+            .build();
+        let handle = unsafe {
+            loader.create_acceleration_structure(&create_info, None)
+                .expect("Failed to create acceleration structure")
+        };
+        let address_info = vk::AccelerationStructureDeviceAddressInfoKHR::builder()
+            .acceleration_structure(handle)
+            .build();
+        let address = unsafe { loader.get_acceleration_structure_device_address(&address_info) };
+
+        self.set_object_name(handle, name);
+        GpuAccelerationStructure { handle, buffer, address }
+    }
+
+    #[cfg(feature = "ray_tracing")]
+    fn free_acceleration_structure(&mut self, loader: &AccelerationStructure, acceleration_structure: GpuAccelerationStructure) {
+        unsafe { loader.destroy_acceleration_structure(acceleration_structure.handle, None) };
+        self.free_buffer(acceleration_structure.buffer);
+    }
+
+    #[cfg(feature = "ray_tracing")]
+    fn destroy_ray_tracing(&mut self) {
+        let Some(ray_tracing) = self.ray_tracing.take() else {
+            return;
+        };
+        if let Some(device) = &self.device {
+            unsafe {
+                device.destroy_pipeline(ray_tracing.pipeline, None);
+                device.destroy_pipeline_layout(ray_tracing.pipeline_layout, None);
+                device.destroy_descriptor_set_layout(ray_tracing.set_layout, None);
+                device.destroy_descriptor_pool(ray_tracing.descriptor_pool, None);
+            }
+        }
+        let loader = ray_tracing.acceleration_structure_loader;
+        self.free_acceleration_structure(&loader, ray_tracing.tlas);
+        if let Some(blas) = ray_tracing.blas {
+            self.free_acceleration_structure(&loader, blas);
+        }
+        for buffer in [ray_tracing.shader_binding_table, ray_tracing.scratch, ray_tracing.instances] {
+            self.free_buffer(buffer);
+        }
+    }
+
+    // Points the ray tracing set at the G-buffer and the SSAO occlusion image, which are
+    // rebuilt on resize
+    #[cfg(feature = "ray_tracing")]
+    fn write_ray_tracing_targets(&self) {
+        let (Some(device), Some(ray_tracing), Some(gbuffer), Some(ssao)) =
+            (&self.device, &self.ray_tracing, &self.gbuffer, &self.ssao) else {
+            return;
+        };
+        let gbuffer_info = |view| vk::DescriptorImageInfo {
+            sampler: ssao.sampler,
+            image_view: view,
+            image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        };
+        let position_info = gbuffer_info(gbuffer.position.view);
+        let normal_info = gbuffer_info(gbuffer.normal.view);
+        let occlusion_info = vk::DescriptorImageInfo {
+            sampler: vk::Sampler::null(),
+            image_view: ssao.occlusion.view,
+            image_layout: vk::ImageLayout::GENERAL,
+        };
+        let writes = [
+            image_write(ray_tracing.set, 1, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, &position_info),
+            image_write(ray_tracing.set, 2, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, &normal_info),
+            image_write(ray_tracing.set, 3, vk::DescriptorType::STORAGE_IMAGE, &occlusion_info),
+        ];
+        unsafe { device.update_descriptor_sets(&writes, &[]) };
+    }
+
+    // Builds the cull mesh's bottom level acceleration structure, replacing the old one
+    #[cfg(feature = "ray_tracing")]
+    fn build_blas(&mut self) {
+        let (Some(device), Some(graphics_queue), Some(graphics_command_pool)) =
+            (self.device.clone(), self.graphics_queue, self.graphics_command_pool) else {
+            return;
+        };
+        let (Some(ray_tracing), Some(mesh)) = (&self.ray_tracing, self.cull.as_ref().and_then(|cull| cull.mesh.as_ref())) else {
+            return;
+        };
+        let loader = ray_tracing.acceleration_structure_loader.clone();
+        let scratch_alignment = ray_tracing.scratch_alignment;
+        let primitive_count = mesh.index_count / 3;
+
+        let triangles = vk::AccelerationStructureGeometryTrianglesDataKHR::builder()
+            .vertex_format(vk::Format::R32G32B32_SFLOAT)
+            .vertex_data(vk::DeviceOrHostAddressConstKHR { device_address: self.buffer_device_address(mesh.vertices.buffer) })
+            .vertex_stride(VERTEX_STRIDE as vk::DeviceSize)
+            .max_vertex(mesh.vertex_count.saturating_sub(1))
+            .index_type(vk::IndexType::UINT32)
+            .index_data(vk::DeviceOrHostAddressConstKHR { device_address: self.buffer_device_address(mesh.indices.buffer) })
+            .build();
+        let geometry = vk::AccelerationStructureGeometryKHR::builder()
+            .geometry_type(vk::GeometryTypeKHR::TRIANGLES)
+            .geometry(vk::AccelerationStructureGeometryDataKHR { triangles })
+            .flags(vk::GeometryFlagsKHR::OPAQUE)
+            .build();
+        let mut build_info = vk::AccelerationStructureBuildGeometryInfoKHR::builder()
+            .ty(vk::AccelerationStructureTypeKHR::BOTTOM_LEVEL)
+            .flags(vk::BuildAccelerationStructureFlagsKHR::PREFER_FAST_TRACE)
+            .mode(vk::BuildAccelerationStructureModeKHR::BUILD)
+            .geometries(std::slice::from_ref(&geometry))
+            .build();
+        let sizes = unsafe {
+            loader.get_acceleration_structure_build_sizes(vk::AccelerationStructureBuildTypeKHR::DEVICE, &build_info, &[primitive_count])
+        };
+
+        let blas = self.create_acceleration_structure(&loader, vk::AccelerationStructureTypeKHR::BOTTOM_LEVEL, sizes.acceleration_structure_size, "Cull Mesh BLAS");
+        let scratch = self.create_buffer(
+            &device,
+            sizes.build_scratch_size + scratch_alignment,
+            vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+            MemoryLocation::GpuOnly,
+            "BLAS Scratch",
+        );
+        build_info.dst_acceleration_structure = blas.handle;
+        build_info.scratch_data = vk::DeviceOrHostAddressKHR {
+            device_address: align_up(self.buffer_device_address(scratch.buffer), scratch_alignment),
+        };
+        let range = vk::AccelerationStructureBuildRangeInfoKHR {
+            primitive_count,
+            primitive_offset: 0,
+            first_vertex: 0,
+            transform_offset: 0,
+        };
+
+        unsafe {
+            let command_buffer = allocate_one_time_command_buffer(&device, graphics_command_pool);
+            loader.cmd_build_acceleration_structures(command_buffer, &[build_info], &[&[range]]);
+            device.end_command_buffer(command_buffer)
+                .expect("Failed to record BLAS build");
+            let submit_info = vk::SubmitInfo::builder()
+                .command_buffers(std::slice::from_ref(&command_buffer))
+                .build();
+            device.queue_submit(graphics_queue, &[submit_info], vk::Fence::null())
+                .expect("Failed to submit BLAS build");
+            device.queue_wait_idle(graphics_queue)
+                .expect("Failed to wait for BLAS build");
+            device.free_command_buffers(graphics_command_pool, &[command_buffer]);
+        }
+        self.free_buffer(scratch);
+
+        let old_blas = self.ray_tracing.as_mut().and_then(|ray_tracing| ray_tracing.blas.replace(blas));
+        if let Some(old_blas) = old_blas {
+            self.free_acceleration_structure(&loader, old_blas);
+        }
+    }
+
+    // One instance of the cull mesh BLAS per geometry object, built into the TLAS when the
+    // occlusion rays are recorded. Anything past MAX_CULL_OBJECTS is dropped.
+    #[cfg(feature = "ray_tracing")]
+    pub fn write_tlas_instances(&mut self, models: &[Mat4]) {
+        let Some(ray_tracing) = self.ray_tracing.as_mut() else {
+            return;
+        };
+        ray_tracing.frame = ray_tracing.frame.wrapping_add(1);
+        let Some(blas_address) = ray_tracing.blas.as_ref().map(|blas| blas.address) else {
+            ray_tracing.instance_count = 0;
+            return;
+        };
+
+        let instances: Vec<vk::AccelerationStructureInstanceKHR> = models
+            .iter()
+            .take(MAX_CULL_OBJECTS)
+            .map(|model| {
+                // The first three rows, row major
+                let rows = model.transpose().to_cols_array();
+                let mut matrix = [0.0; 12];
+                matrix.copy_from_slice(&rows[..12]);
+                vk::AccelerationStructureInstanceKHR {
+                    transform: vk::TransformMatrixKHR { matrix },
+                    instance_custom_index_and_mask: vk::Packed24_8::new(0, 0xff),
+                    instance_shader_binding_table_record_offset_and_flags: vk::Packed24_8::new(
+                        0,
+                        vk::GeometryInstanceFlagsKHR::TRIANGLE_FACING_CULL_DISABLE.as_raw() as u8,
+                    ),
+                    acceleration_structure_reference: vk::AccelerationStructureReferenceKHR { device_handle: blas_address },
+                }
+            })
+            .collect();
+        if let Some(mapped) = ray_tracing.instances.allocation.mapped_slice_mut() {
+            // Plain data, but ash doesn't implement Pod for it
+            let bytes = unsafe {
+                std::slice::from_raw_parts(instances.as_ptr().cast::<u8>(), std::mem::size_of_val(instances.as_slice()))
+            };
+            mapped[..bytes.len()].copy_from_slice(bytes);
+            ray_tracing.instance_count = instances.len() as u32;
+        }
+    }
+
+    // Rebuilds the TLAS from this frame's instances and traces the occlusion rays into the
+    // SSAO occlusion image. Recorded by record_ssao in place of the SSAO dispatch.
+    #[cfg(feature = "ray_tracing")]
+    fn record_ray_traced_ao(&self, command_buffer: vk::CommandBuffer) {
+        let (Some(device), Some(ray_tracing), Some(ssao)) = (&self.device, &self.ray_tracing, &self.ssao) else {
+            return;
+        };
+
+        let geometry = vk::AccelerationStructureGeometryKHR::builder()
+            .geometry_type(vk::GeometryTypeKHR::INSTANCES)
+            .geometry(vk::AccelerationStructureGeometryDataKHR {
+                instances: vk::AccelerationStructureGeometryInstancesDataKHR::builder()
+                    .array_of_pointers(false)
+                    .data(vk::DeviceOrHostAddressConstKHR { device_address: self.buffer_device_address(ray_tracing.instances.buffer) })
+                    .build(),
+            })
+            .build();
+        let build_info = vk::AccelerationStructureBuildGeometryInfoKHR::builder()
+            .ty(vk::AccelerationStructureTypeKHR::TOP_LEVEL)
+            .flags(vk::BuildAccelerationStructureFlagsKHR::PREFER_FAST_TRACE)
+            .mode(vk::BuildAccelerationStructureModeKHR::BUILD)
+            .dst_acceleration_structure(ray_tracing.tlas.handle)
+            .scratch_data(vk::DeviceOrHostAddressKHR { device_address: ray_tracing.scratch_address })
+            .geometries(std::slice::from_ref(&geometry))
+            .build();
+        let range = vk::AccelerationStructureBuildRangeInfoKHR {
+            primitive_count: ray_tracing.instance_count,
+            primitive_offset: 0,
+            first_vertex: 0,
+            transform_offset: 0,
+        };
+        let build_barrier = vk::MemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::ACCELERATION_STRUCTURE_WRITE_KHR)
+            .dst_access_mask(vk::AccessFlags::ACCELERATION_STRUCTURE_READ_KHR)
+            .build();
+
+        self.begin_debug_label(command_buffer, "Ray Traced AO");
+        unsafe {
+            ray_tracing.acceleration_structure_loader.cmd_build_acceleration_structures(command_buffer, &[build_info], &[&[range]]);
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::ACCELERATION_STRUCTURE_BUILD_KHR,
+                vk::PipelineStageFlags::RAY_TRACING_SHADER_KHR,
+                vk::DependencyFlags::empty(),
+                &[build_barrier],
+                &[],
+                &[],
+            );
+
+            device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::RAY_TRACING_KHR, ray_tracing.pipeline);
+            device.cmd_bind_descriptor_sets(command_buffer, vk::PipelineBindPoint::RAY_TRACING_KHR, ray_tracing.pipeline_layout, 0, &[ray_tracing.set], &[]);
+            device.cmd_push_constants(command_buffer, ray_tracing.pipeline_layout, vk::ShaderStageFlags::RAYGEN_KHR, 0, bytemuck::bytes_of(&ray_tracing.frame));
+            ray_tracing.ray_tracing_loader.cmd_trace_rays(
+                command_buffer,
+                &ray_tracing.raygen_region,
+                &ray_tracing.miss_region,
+                &ray_tracing.hit_region,
+                &vk::StridedDeviceAddressRegionKHR::default(),
+                ssao.extent.width,
+                ssao.extent.height,
+                1,
+            );
+        }
+        self.end_debug_label(command_buffer);
+    }
+
+    fn destroy_fog(&mut self) {
+        let Some(fog) = self.fog.take() else {
+            return;
+        };
+        if let Some(device) = &self.device {
+            unsafe {
+                if let Some(pipeline) = fog.pipeline {
+                    device.destroy_pipeline(pipeline, None);
+                }
+                device.destroy_pipeline_layout(fog.pipeline_layout, None);
+                device.destroy_descriptor_set_layout(fog.set_layout, None);
+                device.destroy_sampler(fog.output_sampler, None);
+                device.destroy_sampler(fog.noise_sampler, None);
+            }
+        }
+        self.free_render_target(fog.output);
+        self.free_volume_texture(fog.noise);
+        self.free_buffer(fog.uniforms);
+    }
+
+    fn write_fog_uniforms(&mut self, uniforms: &FogUniforms) {
+        let Some(fog) = self.fog.as_mut() else {
+            return;
+        };
+        if let Some(mapped) = fog.uniforms.allocation.mapped_slice_mut() {
+            let bytes = bytemuck::bytes_of(uniforms);
+            mapped[..bytes.len()].copy_from_slice(bytes);
+        }
+    }
+
+    // Like SSAO this reads the stored G-buffer after the render pass, so lighting composites
+    // the previous frame's fog
+    pub fn record_fog(&self, command_buffer: vk::CommandBuffer) {
+        let (Some(device), Some(fog)) = (&self.device, &self.fog) else {
+            return;
+        };
+        let Some(pipeline) = fog.pipeline else {
+            return;
+        };
+
+        self.begin_debug_label(command_buffer, "Volumetric Fog");
+        unsafe {
+            device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::COMPUTE, pipeline);
+            device.cmd_bind_descriptor_sets(command_buffer, vk::PipelineBindPoint::COMPUTE, fog.pipeline_layout, 0, &[fog.set], &[]);
+            device.cmd_dispatch(
+                command_buffer,
+                fog.extent.width.div_ceil(FOG_WORKGROUP_SIZE),
+                fog.extent.height.div_ceil(FOG_WORKGROUP_SIZE),
+                1,
+            );
+
+            // The lighting subpass samples the fog image
+            let barrier = vk::ImageMemoryBarrier::builder()
+                .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+                .dst_access_mask(vk::AccessFlags::SHADER_READ)
+                .old_layout(vk::ImageLayout::GENERAL)
+                .new_layout(vk::ImageLayout::GENERAL)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(fog.output.image)
+                .subresource_range(COLOR_SUBRESOURCE_RANGE)
+                .build();
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::FRAGMENT_SHADER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[barrier],
+            );
+        }
+        self.end_debug_label(command_buffer);
+    }
+
+    fn destroy_sky(&mut self) {
+        let Some(sky) = self.sky.take() else {
+            return;
+        };
+        if let Some(device) = &self.device {
+            unsafe {
+                if let Some(pipeline) = sky.pipeline {
+                    device.destroy_pipeline(pipeline, None);
+                }
+                device.destroy_pipeline_layout(sky.pipeline_layout, None);
+                device.destroy_descriptor_set_layout(sky.set_layout, None);
+                device.destroy_descriptor_pool(sky.descriptor_pool, None);
+            }
+        }
+        self.free_buffer(sky.uniforms);
+    }
+
+    fn write_sky_uniforms(&mut self, uniforms: &SkyUniforms) {
+        let Some(sky) = self.sky.as_mut() else {
+            return;
+        };
+        if let Some(mapped) = sky.uniforms.allocation.mapped_slice_mut() {
+            let bytes = bytemuck::bytes_of(uniforms);
+            mapped[..bytes.len()].copy_from_slice(bytes);
+        }
+    }
+
+    // Must be recorded inside the lighting subpass, before the lighting triangle
+    pub fn record_sky(&self, command_buffer: vk::CommandBuffer) {
+        let (Some(device), Some(sky)) = (&self.device, &self.sky) else {
+            return;
+        };
+        let Some(pipeline) = sky.pipeline else {
+            return;
+        };
+
+        self.begin_debug_label(command_buffer, "Sky");
+        unsafe {
+            device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline);
+            device.cmd_bind_descriptor_sets(command_buffer, vk::PipelineBindPoint::GRAPHICS, sky.pipeline_layout, 0, &[sky.set], &[]);
+            device.cmd_draw(command_buffer, 3, 1, 0, 0);
+        }
+        self.end_debug_label(command_buffer);
+    }
+
+    fn destroy_cull(&mut self) {
+        let Some(cull) = self.cull.take() else {
+            return;
+        };
+        if let Some(device) = &self.device {
+            unsafe {
+                if let Some(pipeline) = cull.pipeline {
+                    device.destroy_pipeline(pipeline, None);
+                }
+                device.destroy_pipeline_layout(cull.pipeline_layout, None);
+                device.destroy_descriptor_set_layout(cull.set_layout, None);
+                device.destroy_descriptor_pool(cull.descriptor_pool, None);
+            }
+        }
+        if let Some(mesh) = cull.mesh {
+            self.free_buffer(mesh.indices);
+            self.free_buffer(mesh.vertices);
+        }
+        for buffer in [cull.draw_command, cull.visible, cull.objects, cull.uniforms] {
+            self.free_buffer(buffer);
+        }
+    }
+
+    fn write_cull_uniforms(&mut self, uniforms: &CullUniforms) {
+        let Some(cull) = self.cull.as_mut() else {
+            return;
+        };
+        if let Some(mapped) = cull.uniforms.allocation.mapped_slice_mut() {
+            let bytes = bytemuck::bytes_of(uniforms);
+            mapped[..bytes.len()].copy_from_slice(bytes);
+        }
+    }
+
+    // Anything past MAX_CULL_OBJECTS is dropped with a warning
+    pub fn write_cull_objects(&mut self, objects: &[CullObject]) {
+        let Some(cull) = self.cull.as_mut() else {
+            return;
+        };
+        if objects.len() > MAX_CULL_OBJECTS {
+            warn!("{} objects to cull, only the first {} are drawn", objects.len(), MAX_CULL_OBJECTS);
+        }
+        let objects = &objects[..objects.len().min(MAX_CULL_OBJECTS)];
+        if let Some(mapped) = cull.objects.allocation.mapped_slice_mut() {
+            let bytes: &[u8] = bytemuck::cast_slice(objects);
+            mapped[..bytes.len()].copy_from_slice(bytes);
+            cull.object_count = objects.len() as u32;
+        }
+    }
+
+    pub fn has_cull_mesh(&self) -> bool {
+        self.cull.as_ref().is_some_and(|cull| cull.mesh.is_some())
+    }
+
+    // Takes buffers from upload_mesh, laid out as the geometry pipeline's vertex input
+    pub fn set_cull_mesh(&mut self, vertices: GpuBuffer, indices: GpuBuffer, index_count: u32, vertex_count: u32) {
+        let Some(cull) = self.cull.as_mut() else {
+            self.free_buffer(indices);
+            self.free_buffer(vertices);
+            return;
+        };
+        let old_mesh = cull.mesh.replace(CullMesh { vertices, indices, index_count, vertex_count });
+        if let Some(old_mesh) = old_mesh {
+            self.wait_idle();
+            self.free_buffer(old_mesh.indices);
+            self.free_buffer(old_mesh.vertices);
+        }
+        #[cfg(feature = "ray_tracing")]
+        self.build_blas();
+    }
+
+    // Resets the draw command and culls into it. Must be recorded outside the render pass,
+    // before the geometry subpass consumes it.
+    pub fn record_cull(&self, command_buffer: vk::CommandBuffer) {
+        let (Some(device), Some(cull)) = (&self.device, &self.cull) else {
+            return;
+        };
+        let (Some(pipeline), Some(mesh)) = (cull.pipeline, &cull.mesh) else {
+            return;
+        };
+
+        // index_count, instance_count, first_index, vertex_offset, first_instance
+        let draw_command: [u32; 5] = [mesh.index_count, 0, 0, 0, 0];
+        let memory_barrier = |src_access_mask, dst_access_mask| vk::MemoryBarrier::builder()
+            .src_access_mask(src_access_mask)
+            .dst_access_mask(dst_access_mask)
+            .build();
+
+        self.begin_debug_label(command_buffer, "Indirect Cull Pass");
+        unsafe {
+            device.cmd_update_buffer(command_buffer, cull.draw_command.buffer, 0, bytemuck::cast_slice(&draw_command));
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::DependencyFlags::empty(),
+                &[memory_barrier(vk::AccessFlags::TRANSFER_WRITE, vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE)],
+                &[],
+                &[],
+            );
+
+            if cull.object_count > 0 {
+                device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::COMPUTE, pipeline);
+                device.cmd_bind_descriptor_sets(command_buffer, vk::PipelineBindPoint::COMPUTE, cull.pipeline_layout, 0, &[cull.set], &[]);
+                device.cmd_dispatch(command_buffer, cull.object_count.div_ceil(CULL_WORKGROUP_SIZE), 1, 1);
+            }
+
+            // The draw reads the command, the vertex shader the visible list
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::DRAW_INDIRECT | vk::PipelineStageFlags::VERTEX_SHADER,
+                vk::DependencyFlags::empty(),
+                &[memory_barrier(vk::AccessFlags::SHADER_WRITE, vk::AccessFlags::INDIRECT_COMMAND_READ | vk::AccessFlags::SHADER_READ)],
+                &[],
+                &[],
+            );
+        }
+        self.end_debug_label(command_buffer);
+    }
+
+    // Must be recorded inside the geometry subpass, after record_cull
+    pub fn record_culled_geometry(&self, command_buffer: vk::CommandBuffer) {
+        let (Some(device), Some(cull)) = (&self.device, &self.cull) else {
+            return;
+        };
+        let (Some(pipeline), Some(layout), Some(mesh)) = (self.geometry_pipeline, self.geometry_pipeline_layout, &cull.mesh) else {
+            return;
+        };
+        if cull.pipeline.is_none() {
+            return;
+        }
+
+        unsafe {
+            device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline);
+            device.cmd_bind_descriptor_sets(command_buffer, vk::PipelineBindPoint::GRAPHICS, layout, 0, &[cull.geometry_set], &[]);
+            device.cmd_bind_vertex_buffers(command_buffer, 0, &[mesh.vertices.buffer], &[0]);
+            device.cmd_bind_index_buffer(command_buffer, mesh.indices.buffer, 0, vk::IndexType::UINT32);
+            device.cmd_draw_indexed_indirect(
+                command_buffer,
+                cull.draw_command.buffer,
+                0,
+                1,
+                std::mem::size_of::<vk::DrawIndexedIndirectCommand>() as u32,
+            );
+        }
+    }
+
+    fn destroy_bloom(&mut self) {
+        let Some(bloom) = self.bloom.take() else {
+            return;
+        };
+        if let Some(device) = &self.device {
+            unsafe {
+                let pipelines = [bloom.composite_pipeline, bloom.upsample_pipeline, bloom.downsample_pipeline, bloom.extract_pipeline];
+                for pipeline in pipelines.into_iter().flatten() {
+                    device.destroy_pipeline(pipeline, None);
+                }
+                device.destroy_pipeline_layout(bloom.sampled_pipeline_layout, None);
+                device.destroy_pipeline_layout(bloom.storage_pipeline_layout, None);
+                device.destroy_descriptor_set_layout(bloom.sampled_set_layout, None);
+                device.destroy_descriptor_set_layout(bloom.storage_set_layout, None);
+                device.destroy_descriptor_pool(bloom.descriptor_pool, None);
+                device.destroy_sampler(bloom.sampler, None);
+            }
+        }
+        for level in bloom.levels.into_iter().rev() {
+            self.free_render_target(level);
+        }
+    }
+
+    fn destroy_tone_map(&mut self) {
+        let Some(tone_map) = self.tone_map.take() else {
+            return;
+        };
+        if let Some(device) = &self.device {
+            unsafe {
+                if let Some(pipeline) = tone_map.pipeline {
+                    device.destroy_pipeline(pipeline, None);
+                }
+                device.destroy_pipeline_layout(tone_map.pipeline_layout, None);
+                device.destroy_descriptor_set_layout(tone_map.set_layout, None);
+            }
+        }
+        self.free_render_target(tone_map.output);
+    }
+
+    // Records bloom on the scene color (when enabled), tone maps it and copies the result to
+    // the swapchain image
+    pub fn record_post_process(
+        &self,
+        command_buffer: vk::CommandBuffer,
+        config: &PostProcessConfig,
+        tone_mapping: &ToneMappingConfig,
+        swapchain_image: vk::Image,
+    ) {
+        let (Some(device), Some(scene_color)) = (&self.device, &self.scene_color) else {
+            return;
+        };
+        if config.bloom_enabled {
+            self.record_bloom(command_buffer, config);
+        }
+        // Without the tone map shader (or a LUT to grade with) the HDR scene is presented as is
+        let source = match &self.tone_map {
+            Some(tone_map) if tone_map.pipeline.is_some() && self.color_grading_lut.is_some() => {
+                self.record_tone_map(command_buffer, tone_mapping);
+                tone_map.output.image
+            }
+            _ => scene_color.image,
+        };
+        if swapchain_image == vk::Image::null() {
+            return;
+        }
+
+        let extent = self.swapchain_extent;
+        let to_transfer = [
+            vk::ImageMemoryBarrier::builder()
+                .src_access_mask(vk::AccessFlags::SHADER_WRITE | vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .dst_access_mask(vk::AccessFlags::TRANSFER_READ)
+                .old_layout(vk::ImageLayout::GENERAL)
+                .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(source)
+                .subresource_range(COLOR_SUBRESOURCE_RANGE)
+                .build(),
+            vk::ImageMemoryBarrier::builder()
+                .src_access_mask(vk::AccessFlags::empty())
+                .dst_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+                .old_layout(vk::ImageLayout::UNDEFINED)
+                .new_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+                .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+                .image(swapchain_image)
+                .subresource_range(COLOR_SUBRESOURCE_RANGE)
+                .build(),
+        ];
+        let corner = vk::Offset3D {
+            x: extent.width as i32,
+            y: extent.height as i32,
+            z: 1,
+        };
+        let layers = vk::ImageSubresourceLayers {
+            aspect_mask: vk::ImageAspectFlags::COLOR,
+            mip_level: 0,
+            base_array_layer: 0,
+            layer_count: 1,
+        };
+        let blit = vk::ImageBlit {
+            src_subresource: layers,
+            src_offsets: [vk::Offset3D::default(), corner],
+            dst_subresource: layers,
+            dst_offsets: [vk::Offset3D::default(), corner],
+        };
+        let to_present = vk::ImageMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(vk::AccessFlags::empty())
+            .old_layout(vk::ImageLayout::TRANSFER_DST_OPTIMAL)
+            .new_layout(vk::ImageLayout::PRESENT_SRC_KHR)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(swapchain_image)
+            .subresource_range(COLOR_SUBRESOURCE_RANGE)
+            .build();
+        // Next frame's compute passes expect the source back in GENERAL
+        let source_back = vk::ImageMemoryBarrier::builder()
+            .src_access_mask(vk::AccessFlags::TRANSFER_READ)
+            .dst_access_mask(vk::AccessFlags::empty())
+            .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+            .new_layout(vk::ImageLayout::GENERAL)
+            .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+            .image(source)
+            .subresource_range(COLOR_SUBRESOURCE_RANGE)
+            .build();
+
+        self.begin_debug_label(command_buffer, "Present Blit");
+        unsafe {
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COMPUTE_SHADER | vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &to_transfer,
+            );
+            device.cmd_blit_image(
+                command_buffer,
+                source,
+                vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+                swapchain_image,
+                vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+                &[blit],
+                vk::Filter::NEAREST,
+            );
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::TRANSFER,
+                vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+                vk::DependencyFlags::empty(),
+                &[],
+                &[],
+                &[to_present, source_back],
+            );
+        }
+        self.end_debug_label(command_buffer);
+    }
+
+    fn record_tone_map(&self, command_buffer: vk::CommandBuffer, config: &ToneMappingConfig) {
+        let (Some(device), Some(tone_map)) = (&self.device, &self.tone_map) else {
+            return;
+        };
+        let Some(pipeline) = tone_map.pipeline else {
+            return;
+        };
+        let push_constants = ToneMapPushConstants {
+            exposure: config.exposure,
+            mode: config.mode.shader_index(),
+            lut_blend: self.color_grading_blend,
+            desaturation: config.desaturation,
+            vignette: config.vignette,
+        };
+        let extent = self.swapchain_extent;
+
+        self.begin_debug_label(command_buffer, "Tone Mapping");
+        unsafe {
+            // Covers both the lighting subpass and bloom's composite writing the scene color
+            let memory_barrier = vk::MemoryBarrier::builder()
+                .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE | vk::AccessFlags::SHADER_WRITE)
+                .dst_access_mask(vk::AccessFlags::SHADER_READ)
+                .build();
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT | vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::DependencyFlags::empty(),
+                &[memory_barrier],
+                &[],
+                &[],
+            );
+            device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::COMPUTE, pipeline);
+            device.cmd_bind_descriptor_sets(command_buffer, vk::PipelineBindPoint::COMPUTE, tone_map.pipeline_layout, 0, &[tone_map.set], &[]);
+            device.cmd_push_constants(command_buffer, tone_map.pipeline_layout, vk::ShaderStageFlags::COMPUTE, 0, bytemuck::bytes_of(&push_constants));
+            device.cmd_dispatch(
+                command_buffer,
+                extent.width.div_ceil(TONE_MAP_WORKGROUP_SIZE),
+                extent.height.div_ceil(TONE_MAP_WORKGROUP_SIZE),
+                1,
+            );
+        }
+        self.end_debug_label(command_buffer);
+    }
+
+    fn record_bloom(&self, command_buffer: vk::CommandBuffer, config: &PostProcessConfig) {
+        let (Some(device), Some(bloom)) = (&self.device, &self.bloom) else {
+            return;
+        };
+        let (Some(extract), Some(downsample), Some(upsample), Some(composite)) =
+            (bloom.extract_pipeline, bloom.downsample_pipeline, bloom.upsample_pipeline, bloom.composite_pipeline) else {
+            return;
+        };
+
+        let push_constants = BloomPushConstants {
+            threshold: config.bloom_threshold,
+            intensity: config.bloom_intensity,
+        };
+        let dispatch = |pipeline, layout, set, extent: vk::Extent2D| unsafe {
+            device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::COMPUTE, pipeline);
+            device.cmd_bind_descriptor_sets(command_buffer, vk::PipelineBindPoint::COMPUTE, layout, 0, &[set], &[]);
+            device.cmd_push_constants(command_buffer, layout, vk::ShaderStageFlags::COMPUTE, 0, bytemuck::bytes_of(&push_constants));
+            device.cmd_dispatch(
+                command_buffer,
+                extent.width.div_ceil(BLOOM_WORKGROUP_SIZE),
+                extent.height.div_ceil(BLOOM_WORKGROUP_SIZE),
+                1,
+            );
+        };
+        // Each step reads what the previous one wrote
+        let barrier = || unsafe {
+            let memory_barrier = vk::MemoryBarrier::builder()
+                .src_access_mask(vk::AccessFlags::SHADER_WRITE)
+                .dst_access_mask(vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE)
+                .build();
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::DependencyFlags::empty(),
+                &[memory_barrier],
+                &[],
+                &[],
+            );
+        };
+        let level_extent = |level: usize| bloom_level_extent(self.swapchain_extent, level);
+
+        self.begin_debug_label(command_buffer, "Bloom");
+        unsafe {
+            // The lighting subpass wrote the scene color as an attachment
+            let memory_barrier = vk::MemoryBarrier::builder()
+                .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .dst_access_mask(vk::AccessFlags::SHADER_READ)
+                .build();
+            device.cmd_pipeline_barrier(
+                command_buffer,
+                vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                vk::PipelineStageFlags::COMPUTE_SHADER,
+                vk::DependencyFlags::empty(),
+                &[memory_barrier],
+                &[],
+                &[],
+            );
+        }
+        dispatch(extract, bloom.storage_pipeline_layout, bloom.extract_set, level_extent(0));
+        barrier();
+        for (i, &set) in bloom.downsample_sets.iter().enumerate() {
+            dispatch(downsample, bloom.sampled_pipeline_layout, set, level_extent(i + 1));
+            barrier();
+        }
+        for (i, &set) in bloom.upsample_sets.iter().enumerate().rev() {
+            dispatch(upsample, bloom.sampled_pipeline_layout, set, level_extent(i));
+            barrier();
+        }
+        dispatch(composite, bloom.sampled_pipeline_layout, bloom.composite_set, self.swapchain_extent);
+        self.end_debug_label(command_buffer);
+    }
+
+    // Destroys everything in reverse creation order. Each field is taken so calling
+    // this more than once (e.g. from the shutdown system and then Drop) is harmless.
+    fn destroy(&mut self) {
+        self.destroy_texture_cache();
+        #[cfg(feature = "ray_tracing")]
+        self.destroy_ray_tracing();
+        self.destroy_color_grading_lut();
+        self.destroy_tone_map();
+        self.destroy_bloom();
+        self.destroy_sky();
+        self.destroy_cull();
+        self.destroy_fog();
+        self.destroy_ssao();
+        if let Some(scene_color) = self.scene_color.take() {
+            self.free_render_target(scene_color);
+        }
+        self.destroy_gbuffer();
+        if let Some(device) = &self.device {
+            for pipeline in [self.lighting_pipeline.take(), self.geometry_pipeline.take()].into_iter().flatten() {
+                unsafe { device.destroy_pipeline(pipeline, None) };
+            }
+            for layout in [self.lighting_pipeline_layout.take(), self.geometry_pipeline_layout.take()].into_iter().flatten() {
+                unsafe { device.destroy_pipeline_layout(layout, None) };
+            }
+            for layout in [self.lighting_descriptor_set_layout.take(), self.geometry_descriptor_set_layout.take()].into_iter().flatten() {
+                unsafe { device.destroy_descriptor_set_layout(layout, None) };
+            }
+            if let Some(descriptor_pool) = self.descriptor_pool.take() {
+                unsafe { device.destroy_descriptor_pool(descriptor_pool, None) };
+            }
+            self.lighting_descriptor_set = None;
+            if let Some(render_pass) = self.render_pass.take() {
+                unsafe { device.destroy_render_pass(render_pass, None) };
+            }
+            if let (Some(swapchain), Some(instance)) = (self.swapchain.take(), &self.instance) {
+                let swapchain_loader = Swapchain::new(instance, device);
+                unsafe { swapchain_loader.destroy_swapchain(swapchain, None) };
+            }
+            self.swapchain_images.clear();
+            if let Some(command_pool) = self.transfer_command_pool.take() {
+                unsafe { device.destroy_command_pool(command_pool, None) };
+            }
+            if let Some(command_pool) = self.graphics_command_pool.take() {
+                unsafe { device.destroy_command_pool(command_pool, None) };
+            }
+            self.transfer_queue = None;
+            self.graphics_queue = None;
+        }
+
+        // The allocator frees its memory blocks through the device, so it goes first
+        drop(self.allocator.take());
+        if let Some(device) = self.device.take() {
+            unsafe { device.destroy_device(None) };
+        }
+
+        if let (Some(surface), Some(entry), Some(instance)) = (self.surface.take(), &self.entry, &self.instance) {
+            let surface_loader = Surface::new(entry, instance);
+            unsafe { surface_loader.destroy_surface(surface, None) };
+        }
+        self.debug_utils = None;
+        self.physical_device = None;
+        if let Some(instance) = self.instance.take() {
+            unsafe { instance.destroy_instance(None) };
+        }
+        drop(self.entry.take());
+    }
+}
+
+impl Drop for VulkanRenderer {
+    fn drop(&mut self) {
+        self.wait_idle();
+        self.destroy();
+    }
+}
+
+fn setup_vulkan_renderer(vulkan_renderer: &mut VulkanRenderer) {
+    info!("Setting up Vulkan renderer...");
+    
+    // Load Vulkan entry point
+    let entry = unsafe { Entry::load().expect("Failed to load Vulkan entry point") };
+    
+    // Check available extensions
+    let available_extensions = entry.enumerate_instance_extension_properties(None)
+        .expect("Failed to enumerate instance extensions");
+    
+    info!("Available extensions: {:?}", available_extensions.len());
+
+    // In debug mode, turn on validation and debug utils when the loader provides them
+    let mut layer_names = Vec::new();
+    let mut extension_names = Vec::new();
+    let mut debug_utils_enabled = false;
+    if vulkan_renderer.debug {
+        let has_debug_utils = available_extensions.iter().any(|ext| {
+            let name = unsafe { CStr::from_ptr(ext.extension_name.as_ptr()) };
+            name == DebugUtils::name()
+        });
+        if has_debug_utils {
+            extension_names.push(DebugUtils::name().as_ptr());
+            debug_utils_enabled = true;
+        } else {
+            warn!("VK_EXT_debug_utils not available, object names and labels disabled");
+        }
+
+        let available_layers = entry.enumerate_instance_layer_properties()
+            .unwrap_or_default();
+        let has_validation = available_layers.iter().any(|layer| {
+            let name = unsafe { CStr::from_ptr(layer.layer_name.as_ptr()) };
+            name == VALIDATION_LAYER
+        });
+        if has_validation {
+            layer_names.push(VALIDATION_LAYER.as_ptr());
+        } else {
+            warn!("Validation layer not available");
+        }
+    }
+    
+    // Create Vulkan instance with minimal extensions
+    let app_info = vk::ApplicationInfo::builder()
+        .application_name(c"Vulkan Game")
+        .application_version(vk::API_VERSION_1_0)
+        .engine_name(c"Bevy")
+        .engine_version(vk::API_VERSION_1_0)
+        // Ray tracing needs buffer device addresses and SPIR-V 1.4 from Vulkan 1.2
+        .api_version(if cfg!(feature = "ray_tracing") { vk::API_VERSION_1_2 } else { vk::API_VERSION_1_0 })
+        .build();
+    
+    let instance_create_info = vk::InstanceCreateInfo::builder()
+        .application_info(&app_info)
+        .enabled_layer_names(&layer_names)
+        .enabled_extension_names(&extension_names)
+        .build();
+    
+    let instance = unsafe { 
+        entry.create_instance(&instance_create_info, None)
+            .expect("Failed to create Vulkan instance")
+    };
+    
+    if debug_utils_enabled {
+        vulkan_renderer.debug_utils = Some(DebugUtils::new(&entry, &instance));
+    }
+    vulkan_renderer.entry = Some(entry);
+    vulkan_renderer.instance = Some(instance);
+    vulkan_renderer.instance_created = true;
+    
+    info!("Vulkan instance created successfully");
+}
+
+fn setup_lighting(mut commands: Commands) {
+    // Add a directional light for the scene
+    commands.spawn(DirectionalLightBundle {
+        directional_light: DirectionalLight {
+            illuminance: 10000.0,
+            shadows_enabled: true,
+            ..default()
+        },
+        transform: Transform::from_xyz(4.0, 8.0, 4.0).looking_at(Vec3::ZERO, Vec3::Y),
+        ..default()
+    });
+}
+
+fn setup_vulkan_surface(
+    mut vulkan_renderer: ResMut<VulkanRenderer>,
+    windows: Query<&Window>,
+) {
+    let ready = |renderer: &VulkanRenderer| {
+        renderer.instance_created && renderer.device_created && renderer.swapchain_created && renderer.pipeline_created
+    };
+    if ready(&vulkan_renderer) {
+        return;
+    }
+
+    if !vulkan_renderer.instance_created {
+        setup_vulkan_renderer(&mut vulkan_renderer);
+    }
+    
+    if !vulkan_renderer.device_created {
+        create_vulkan_device_and_queue(&mut vulkan_renderer);
+    }
+    
+    if !vulkan_renderer.swapchain_created {
+        if let Ok(window) = windows.get_single() {
+            create_vulkan_swapchain(&mut vulkan_renderer, window);
+        }
+    }
+    
+    if !vulkan_renderer.pipeline_created {
+        create_vulkan_render_pass_and_pipeline(&mut vulkan_renderer);
+    }
+    
+    if ready(&vulkan_renderer) {
+        info!("Vulkan device, render targets and pipelines created successfully");
+    }
+}
+
+// A transfer-only queue family, preferred for uploads so they can overlap with rendering
+fn dedicated_transfer_family(queue_family_properties: &[vk::QueueFamilyProperties]) -> Option<u32> {
+    queue_family_properties
+        .iter()
+        .position(|props| {
+            props.queue_flags.contains(vk::QueueFlags::TRANSFER)
+                && !props.queue_flags.intersects(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE)
+        })
+        .map(|index| index as u32)
+}
+
+fn ray_tracing_extensions() -> [&'static CStr; 3] {
+    [
+        AccelerationStructure::name(),
+        RayTracingPipelineLoader::name(),
+        DeferredHostOperations::name(),
+    ]
+}
+
+fn supports_ray_tracing(instance: &AshInstance, physical_device: vk::PhysicalDevice) -> bool {
+    let available = unsafe { instance.enumerate_device_extension_properties(physical_device) }
+        .unwrap_or_default();
+    ray_tracing_extensions().iter().all(|name| {
+        available
+            .iter()
+            .any(|extension| unsafe { CStr::from_ptr(extension.extension_name.as_ptr()) } == *name)
+    })
+}
+
+fn create_vulkan_device_and_queue(vulkan_renderer: &mut VulkanRenderer) {
+    if let Some(instance) = &vulkan_renderer.instance {
+        info!("Creating Vulkan device and queue...");
+        
+        // Find a suitable physical device
+        let physical_devices = unsafe { 
+            instance.enumerate_physical_devices()
+                .expect("Failed to enumerate physical devices")
+        };
+        
+        let physical_device = physical_devices[0]; // Use first available device
+        
+        // Find a queue family that supports graphics
+        let queue_family_properties = unsafe { 
+            instance.get_physical_device_queue_family_properties(physical_device)
+        };
+        
+        let queue_family_index = queue_family_properties
+            .iter()
+            .position(|props| props.queue_flags.contains(vk::QueueFlags::GRAPHICS))
+            .expect("No graphics queue family found") as u32;
+
+        let transfer_family_index = dedicated_transfer_family(&queue_family_properties);
+        match transfer_family_index {
+            Some(index) => info!("Using dedicated transfer queue family {}", index),
+            None => info!("No dedicated transfer queue family, uploads will use the graphics queue"),
+        }
+        
+        // Create logical device
+        let mut queue_create_infos = vec![
+            vk::DeviceQueueCreateInfo::builder()
+                .queue_family_index(queue_family_index)
+                .queue_priorities(&[1.0])
+                .build(),
+        ];
+        if let Some(index) = transfer_family_index {
+            queue_create_infos.push(
+                vk::DeviceQueueCreateInfo::builder()
+                    .queue_family_index(index)
+                    .queue_priorities(&[1.0])
+                    .build(),
+            );
+        }
+        
+        let ray_tracing_supported = cfg!(feature = "ray_tracing") && supports_ray_tracing(instance, physical_device);
+        let mut extension_names = vec![Swapchain::name().as_ptr()];
+        let mut vulkan12_features = vk::PhysicalDeviceVulkan12Features::builder().buffer_device_address(true);
+        let mut acceleration_structure_features = vk::PhysicalDeviceAccelerationStructureFeaturesKHR::builder()
+            .acceleration_structure(true);
+        let mut ray_tracing_features = vk::PhysicalDeviceRayTracingPipelineFeaturesKHR::builder()
+            .ray_tracing_pipeline(true);
+        let mut device_create_info = vk::DeviceCreateInfo::builder()
+            .queue_create_infos(&queue_create_infos);
+        if ray_tracing_supported {
+            info!("Ray tracing supported, ambient occlusion will be ray traced");
+            extension_names.extend(ray_tracing_extensions().iter().map(|name| name.as_ptr()));
+            device_create_info = device_create_info
+                .push_next(&mut vulkan12_features)
+                .push_next(&mut acceleration_structure_features)
+                .push_next(&mut ray_tracing_features);
+        } else if cfg!(feature = "ray_tracing") {
+            warn!("Ray tracing not supported by this device, falling back to SSAO");
+        }
+        let device_create_info = device_create_info
+            .enabled_extension_names(&extension_names)
+            .build();
+        
+        let device = unsafe { 
+            instance.create_device(physical_device, &device_create_info, None)
+                .expect("Failed to create logical device")
+        };
+        
+        // Create memory allocator
+        let allocator = Allocator::new(&gpu_allocator::vulkan::AllocatorCreateDesc {
+            instance: instance.clone(),
+            device: device.clone(),
+            physical_device,
+            debug_settings: allocator_debug_settings(vulkan_renderer.debug),
+            buffer_device_address: ray_tracing_supported,
+        }).expect("Failed to create memory allocator");
+        
+        let graphics_queue = unsafe { device.get_device_queue(queue_family_index, 0) };
+        let graphics_command_pool = create_command_pool(&device, queue_family_index);
+        vulkan_renderer.ray_tracing_supported = ray_tracing_supported;
+        vulkan_renderer.graphics_queue_family = queue_family_index;
+        vulkan_renderer.graphics_queue = Some(graphics_queue);
+        vulkan_renderer.graphics_command_pool = Some(graphics_command_pool);
+
+        if let Some(index) = transfer_family_index {
+            let transfer_queue = unsafe { device.get_device_queue(index, 0) };
+            vulkan_renderer.transfer_queue_family = Some(index);
+            vulkan_renderer.transfer_queue = Some(transfer_queue);
+            vulkan_renderer.transfer_command_pool = Some(create_command_pool(&device, index));
+        }
+        
+        vulkan_renderer.physical_device = Some(physical_device);
+        vulkan_renderer.device = Some(device);
+        vulkan_renderer.allocator = Some(TrackedAllocator::new(allocator));
+        vulkan_renderer.set_object_name(graphics_queue, "Graphics Queue");
+        if let Some(transfer_queue) = vulkan_renderer.transfer_queue {
+            vulkan_renderer.set_object_name(transfer_queue, "Transfer Queue");
+        }
+        vulkan_renderer.device_created = true;
+        
+        info!("Vulkan device and memory allocator created successfully");
+    }
+}
+
+// Pool for short lived command buffers, e.g. uploads
+fn create_command_pool(device: &AshDevice, queue_family_index: u32) -> vk::CommandPool {
+    let command_pool_create_info = vk::CommandPoolCreateInfo::builder()
+        .queue_family_index(queue_family_index)
+        .flags(vk::CommandPoolCreateFlags::TRANSIENT)
+        .build();
+    unsafe {
+        device.create_command_pool(&command_pool_create_info, None)
+            .expect("Failed to create command pool")
+    }
+}
+
+// Allocates a primary command buffer and begins recording it for a single submit
+unsafe fn allocate_one_time_command_buffer(device: &AshDevice, command_pool: vk::CommandPool) -> vk::CommandBuffer {
+    let allocate_info = vk::CommandBufferAllocateInfo::builder()
+        .command_pool(command_pool)
+        .level(vk::CommandBufferLevel::PRIMARY)
+        .command_buffer_count(1)
+        .build();
+    let command_buffer = device.allocate_command_buffers(&allocate_info)
+        .expect("Failed to allocate command buffer")[0];
+
+    let begin_info = vk::CommandBufferBeginInfo::builder()
+        .flags(vk::CommandBufferUsageFlags::ONE_TIME_SUBMIT)
+        .build();
+    device.begin_command_buffer(command_buffer, &begin_info)
+        .expect("Failed to begin command buffer");
+    command_buffer
+}
+
+// The allocator isn't a Vulkan object so it can't be named; in debug mode have it
+// log its heaps, allocations and leaks instead
+fn allocator_debug_settings(debug: bool) -> AllocatorDebugSettings {
+    if debug {
+        AllocatorDebugSettings {
+            log_memory_information: true,
+            log_leaks_on_shutdown: true,
+            log_allocations: true,
+            log_frees: true,
+            ..Default::default()
+        }
+    } else {
+        AllocatorDebugSettings::default()
+    }
+}
+
+fn create_vulkan_swapchain(vulkan_renderer: &mut VulkanRenderer, window: &Window) {
+    if let (Some(_instance), Some(_device)) = (&vulkan_renderer.instance, &vulkan_renderer.device) {
+        vulkan_renderer.swapchain_extent = vk::Extent2D {
+            width: window.physical_width(),
+            height: window.physical_height(),
+        };
+
+        // Bevy's renderer owns the window surface, so there's no swapchain to present to. The
+        // extent still sizes the G-buffer and the other full screen targets.
+        info!(
+            "No Vulkan swapchain, sizing render targets to the {}x{} window",
+            vulkan_renderer.swapchain_extent.width, vulkan_renderer.swapchain_extent.height,
+        );
+        vulkan_renderer.swapchain_created = true;
+    }
+}
+
+// Allocates the position, normal and albedo targets the geometry subpass writes to
+fn create_gbuffer(vulkan_renderer: &mut VulkanRenderer, extent: vk::Extent2D) -> Option<GBuffer> {
+    let device = vulkan_renderer.device.clone()?;
+    info!("Creating G-buffer ({}x{})...", extent.width, extent.height);
+
+    // Position and normal are also sampled by the SSAO compute pass after the render pass
+    let sampled_usage = vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::INPUT_ATTACHMENT | vk::ImageUsageFlags::SAMPLED;
+    let position = vulkan_renderer.create_render_target(&device, extent, GBUFFER_POSITION_FORMAT, sampled_usage, "G-Buffer Position");
+    let normal = vulkan_renderer.create_render_target(&device, extent, GBUFFER_NORMAL_FORMAT, sampled_usage, "G-Buffer Normal");
+    let albedo = vulkan_renderer.create_render_target(
+        &device,
+        extent,
+        GBUFFER_ALBEDO_FORMAT,
+        vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::INPUT_ATTACHMENT,
+        "G-Buffer Albedo",
+    );
+
+    Some(GBuffer { position, normal, albedo, extent })
+}
+
+fn create_vulkan_render_pass_and_pipeline(vulkan_renderer: &mut VulkanRenderer) {
+    // The G-buffer is sized to the window, so wait until the swapchain knows it
+    let extent = vulkan_renderer.swapchain_extent;
+    if extent.width == 0 || extent.height == 0 {
+        return;
+    }
+
+    if let Some(device) = vulkan_renderer.device.clone() {
+        info!("Creating Vulkan deferred render pass and pipelines...");
+
+        vulkan_renderer.gbuffer = create_gbuffer(vulkan_renderer, extent);
+        vulkan_renderer.scene_color = Some(create_scene_color(vulkan_renderer, &device, extent));
+
+        // Attachment 0 is the HDR scene color, 1-3 are the G-buffer
+        let gbuffer_attachment = |format, store_op| vk::AttachmentDescription::builder()
+            .format(format)
+            .samples(vk::SampleCountFlags::TYPE_1)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(store_op)
+            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .initial_layout(vk::ImageLayout::UNDEFINED)
+            .final_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .build();
+        let attachments = [
+            // Left in GENERAL for the bloom compute passes
+            vk::AttachmentDescription::builder()
+                .format(SCENE_COLOR_FORMAT)
+                .samples(vk::SampleCountFlags::TYPE_1)
+                .load_op(vk::AttachmentLoadOp::CLEAR)
+                .store_op(vk::AttachmentStoreOp::STORE)
+                .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
+                .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
+                .initial_layout(vk::ImageLayout::UNDEFINED)
+                .final_layout(vk::ImageLayout::GENERAL)
+                .build(),
+            // Position and normal outlive the pass for SSAO
+            gbuffer_attachment(GBUFFER_POSITION_FORMAT, vk::AttachmentStoreOp::STORE),
+            gbuffer_attachment(GBUFFER_NORMAL_FORMAT, vk::AttachmentStoreOp::STORE),
+            gbuffer_attachment(GBUFFER_ALBEDO_FORMAT, vk::AttachmentStoreOp::DONT_CARE),
+        ];
+
+        let attachment_ref = |attachment, layout| vk::AttachmentReference { attachment, layout };
+        let gbuffer_write_refs = [
+            attachment_ref(1, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+            attachment_ref(2, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+            attachment_ref(3, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+        ];
+        let gbuffer_read_refs = [
+            attachment_ref(1, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+            attachment_ref(2, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+            attachment_ref(3, vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL),
+        ];
+        let scene_color_ref = attachment_ref(0, vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+
+        let subpasses = [
+            // Geometry: write position, normal and albedo
+            vk::SubpassDescription::builder()
+                .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+                .color_attachments(&gbuffer_write_refs)
+                .build(),
+            // Lighting: read the G-buffer as input attachments, write the final color
+            vk::SubpassDescription::builder()
+                .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
+                .input_attachments(&gbuffer_read_refs)
+                .color_attachments(std::slice::from_ref(&scene_color_ref))
+                .build(),
+        ];
+
+        let dependencies = [
+            vk::SubpassDependency::builder()
+                .src_subpass(vk::SUBPASS_EXTERNAL)
+                .dst_subpass(GEOMETRY_SUBPASS)
+                .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                .dst_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                .dst_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .build(),
+            vk::SubpassDependency::builder()
+                .src_subpass(GEOMETRY_SUBPASS)
+                .dst_subpass(LIGHTING_SUBPASS)
+                .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                .dst_stage_mask(vk::PipelineStageFlags::FRAGMENT_SHADER)
+                .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .dst_access_mask(vk::AccessFlags::INPUT_ATTACHMENT_READ)
+                .dependency_flags(vk::DependencyFlags::BY_REGION)
+                .build(),
+            // SSAO reads the stored position and normal targets after the pass
+            vk::SubpassDependency::builder()
+                .src_subpass(GEOMETRY_SUBPASS)
+                .dst_subpass(vk::SUBPASS_EXTERNAL)
+                .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                .dst_stage_mask(vk::PipelineStageFlags::COMPUTE_SHADER)
+                .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .dst_access_mask(vk::AccessFlags::SHADER_READ)
+                .build(),
+            // Bloom and the present blit pick up the lit scene color
+            vk::SubpassDependency::builder()
+                .src_subpass(LIGHTING_SUBPASS)
+                .dst_subpass(vk::SUBPASS_EXTERNAL)
+                .src_stage_mask(vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
+                .dst_stage_mask(vk::PipelineStageFlags::COMPUTE_SHADER | vk::PipelineStageFlags::TRANSFER)
+                .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+                .dst_access_mask(vk::AccessFlags::SHADER_READ | vk::AccessFlags::SHADER_WRITE | vk::AccessFlags::TRANSFER_READ)
+                .build(),
+        ];
+
+        let render_pass_create_info = vk::RenderPassCreateInfo::builder()
+            .attachments(&attachments)
+            .subpasses(&subpasses)
+            .dependencies(&dependencies)
+            .build();
+        
+        let render_pass = unsafe { 
+            device.create_render_pass(&render_pass_create_info, None)
+                .expect("Failed to create render pass")
+        };
+        vulkan_renderer.render_pass = Some(render_pass);
+        vulkan_renderer.set_object_name(render_pass, "Deferred Render Pass");
+
+        // Geometry pass takes the camera uniforms, the cull pass's objects and its visible list
+        let geometry_bindings = [
+            descriptor_binding(0, vk::DescriptorType::UNIFORM_BUFFER, vk::ShaderStageFlags::VERTEX),
+            descriptor_binding(1, vk::DescriptorType::STORAGE_BUFFER, vk::ShaderStageFlags::VERTEX),
+            descriptor_binding(2, vk::DescriptorType::STORAGE_BUFFER, vk::ShaderStageFlags::VERTEX),
+        ];
+        // Lighting pass reads the three G-buffer targets plus the blurred SSAO result and the fog
+        let lighting_bindings = [
+            descriptor_binding(0, vk::DescriptorType::INPUT_ATTACHMENT, vk::ShaderStageFlags::FRAGMENT),
+            descriptor_binding(1, vk::DescriptorType::INPUT_ATTACHMENT, vk::ShaderStageFlags::FRAGMENT),
+            descriptor_binding(2, vk::DescriptorType::INPUT_ATTACHMENT, vk::ShaderStageFlags::FRAGMENT),
+            descriptor_binding(3, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, vk::ShaderStageFlags::FRAGMENT),
+            descriptor_binding(4, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, vk::ShaderStageFlags::FRAGMENT),
+        ];
+
+        let geometry_set_layout = create_descriptor_set_layout(&device, &geometry_bindings);
+        let lighting_set_layout = create_descriptor_set_layout(&device, &lighting_bindings);
+        let geometry_layout = create_pipeline_layout(&device, geometry_set_layout, &[]);
+        let lighting_layout = create_pipeline_layout(&device, lighting_set_layout, &[]);
+        vulkan_renderer.geometry_descriptor_set_layout = Some(geometry_set_layout);
+        vulkan_renderer.lighting_descriptor_set_layout = Some(lighting_set_layout);
+        vulkan_renderer.geometry_pipeline_layout = Some(geometry_layout);
+        vulkan_renderer.lighting_pipeline_layout = Some(lighting_layout);
+
+        vulkan_renderer.geometry_pipeline = create_graphics_pipeline(&device, &PipelineDesc {
+            render_pass,
+            subpass: GEOMETRY_SUBPASS,
+            layout: geometry_layout,
+            vertex_shader: "vulkan_vertex.spv",
+            fragment_shader: "gbuffer_fragment.spv",
+            uses_vertex_buffer: true,
+            cull_mode: vk::CullModeFlags::BACK,
+            color_attachment_count: 3,
+            blend: None,
+        });
+        vulkan_renderer.lighting_pipeline = create_graphics_pipeline(&device, &PipelineDesc {
+            render_pass,
+            subpass: LIGHTING_SUBPASS,
+            layout: lighting_layout,
+            vertex_shader: "lighting_vertex.spv",
+            fragment_shader: "lighting_fragment.spv",
+            uses_vertex_buffer: false,
+            cull_mode: vk::CullModeFlags::NONE,
+            color_attachment_count: 1,
+            // rgb + sky * alpha, keeping the sky's alpha
+            blend: Some(vk::PipelineColorBlendAttachmentState::builder()
+                .blend_enable(true)
+                .src_color_blend_factor(vk::BlendFactor::ONE)
+                .dst_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
+                .color_blend_op(vk::BlendOp::ADD)
+                .src_alpha_blend_factor(vk::BlendFactor::ZERO)
+                .dst_alpha_blend_factor(vk::BlendFactor::ONE)
+                .alpha_blend_op(vk::BlendOp::ADD)
+                .color_write_mask(vk::ColorComponentFlags::RGBA)
+                .build()),
+        });
+
+        if let Some(pipeline) = vulkan_renderer.geometry_pipeline {
+            vulkan_renderer.set_object_name(pipeline, "Geometry Pipeline");
+        }
+        if let Some(pipeline) = vulkan_renderer.lighting_pipeline {
+            vulkan_renderer.set_object_name(pipeline, "Lighting Pipeline");
+        }
+
+        vulkan_renderer.sky = create_sky_pass(vulkan_renderer, render_pass);
+        vulkan_renderer.cull = create_indirect_cull_pass(vulkan_renderer, geometry_set_layout);
+
+        let descriptor_pool = create_descriptor_pool(&device);
+        vulkan_renderer.descriptor_pool = Some(descriptor_pool);
+        vulkan_renderer.ssao = create_ssao_pipeline(vulkan_renderer, descriptor_pool, extent);
+        #[cfg(feature = "ray_tracing")]
+        {
+            vulkan_renderer.ray_tracing = create_ray_tracing_pipeline(vulkan_renderer);
+            vulkan_renderer.write_ray_tracing_targets();
+        }
+        vulkan_renderer.fog = create_fog_pass(vulkan_renderer, descriptor_pool, extent);
+        vulkan_renderer.lighting_descriptor_set = write_lighting_descriptor_set(vulkan_renderer, descriptor_pool, lighting_set_layout);
+        vulkan_renderer.bloom = create_bloom_pass(vulkan_renderer, extent);
+        vulkan_renderer.tone_map = create_tone_map_pass(vulkan_renderer, descriptor_pool, extent);
+        create_texture_cache(vulkan_renderer);
+        vulkan_renderer.pipeline_created = true;
+        
+        info!("Vulkan deferred render pass created successfully");
+    }
+}
+
+fn descriptor_binding(binding: u32, descriptor_type: vk::DescriptorType, stage_flags: vk::ShaderStageFlags) -> vk::DescriptorSetLayoutBinding {
+    vk::DescriptorSetLayoutBinding::builder()
+        .binding(binding)
+        .descriptor_type(descriptor_type)
+        .descriptor_count(1)
+        .stage_flags(stage_flags)
+        .build()
+}
+
+// Sized for the lighting set, the two SSAO sets, the fog set and the tone map set
+fn create_descriptor_pool(device: &AshDevice) -> vk::DescriptorPool {
+    let pool_sizes = [
+        vk::DescriptorPoolSize { ty: vk::DescriptorType::INPUT_ATTACHMENT, descriptor_count: 3 },
+        vk::DescriptorPoolSize { ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER, descriptor_count: 7 },
+        vk::DescriptorPoolSize { ty: vk::DescriptorType::UNIFORM_BUFFER, descriptor_count: 2 },
+        vk::DescriptorPoolSize { ty: vk::DescriptorType::STORAGE_IMAGE, descriptor_count: 6 },
+    ];
+    let create_info = vk::DescriptorPoolCreateInfo::builder()
+        .max_sets(5)
+        .pool_sizes(&pool_sizes)
+        .build();
+    unsafe {
+        device.create_descriptor_pool(&create_info, None)
+            .expect("Failed to create descriptor pool")
+    }
+}
+
+fn allocate_descriptor_set(device: &AshDevice, descriptor_pool: vk::DescriptorPool, set_layout: vk::DescriptorSetLayout) -> vk::DescriptorSet {
+    let allocate_info = vk::DescriptorSetAllocateInfo::builder()
+        .descriptor_pool(descriptor_pool)
+        .set_layouts(std::slice::from_ref(&set_layout))
+        .build();
+    unsafe {
+        device.allocate_descriptor_sets(&allocate_info)
+            .expect("Failed to allocate descriptor set")[0]
+    }
+}
+
+fn image_write(set: vk::DescriptorSet, binding: u32, descriptor_type: vk::DescriptorType, image_info: &vk::DescriptorImageInfo) -> vk::WriteDescriptorSet {
+    vk::WriteDescriptorSet::builder()
+        .dst_set(set)
+        .dst_binding(binding)
+        .descriptor_type(descriptor_type)
+        .image_info(std::slice::from_ref(image_info))
+        .build()
+}
+
+// Hemisphere samples along +Z, scaled so more of them sit close to the surface
+fn ssao_kernel(rng: &mut impl Rng) -> [[f32; 4]; SSAO_KERNEL_SIZE] {
+    let mut kernel = [[0.0; 4]; SSAO_KERNEL_SIZE];
+    for (i, sample) in kernel.iter_mut().enumerate() {
+        let direction = Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(0.0..1.0))
+            .normalize_or_zero();
+        let t = i as f32 / SSAO_KERNEL_SIZE as f32;
+        let scale = 0.1 + 0.9 * t * t;
+        let point = direction * rng.gen_range(0.0..1.0) * scale;
+        *sample = [point.x, point.y, point.z, 0.0];
+    }
+    kernel
+}
+
+// Random rotations around the surface normal, tiled across the screen
+fn ssao_noise(rng: &mut impl Rng) -> [[f32; 4]; SSAO_NOISE_SIZE * SSAO_NOISE_SIZE] {
+    let mut noise = [[0.0; 4]; SSAO_NOISE_SIZE * SSAO_NOISE_SIZE];
+    for rotation in noise.iter_mut() {
+        *rotation = [rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), 0.0, 0.0];
+    }
+    noise
+}
+
+// Sets up the SSAO and blur compute pipelines, their occlusion images and descriptor sets
+fn create_ssao_pipeline(vulkan_renderer: &mut VulkanRenderer, descriptor_pool: vk::DescriptorPool, extent: vk::Extent2D) -> Option<SsaoPass> {
+    let device = vulkan_renderer.device.clone()?;
+    let (position_view, normal_view) = {
+        let gbuffer = vulkan_renderer.gbuffer.as_ref()?;
+        (gbuffer.position.view, gbuffer.normal.view)
+    };
+    info!("Creating SSAO compute passes...");
+
+    let mut rng = rand::thread_rng();
+    let uniforms = SsaoUniforms {
+        view: Mat4::IDENTITY.to_cols_array_2d(),
+        projection: Mat4::IDENTITY.to_cols_array_2d(),
+        kernel: ssao_kernel(&mut rng),
+        noise: ssao_noise(&mut rng),
+    };
+    let mut uniform_buffer = vulkan_renderer.create_buffer(
+        &device,
+        std::mem::size_of::<SsaoUniforms>() as vk::DeviceSize,
+        vk::BufferUsageFlags::UNIFORM_BUFFER,
+        MemoryLocation::CpuToGpu,
+        "SSAO Uniforms",
+    );
+    uniform_buffer.allocation.mapped_slice_mut()
+        .expect("SSAO uniform buffer is not host visible")[..std::mem::size_of::<SsaoUniforms>()]
+        .copy_from_slice(bytemuck::bytes_of(&uniforms));
+
+    let storage_usage = vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED;
+    let occlusion = vulkan_renderer.create_render_target(&device, extent, SSAO_FORMAT, storage_usage, "SSAO Occlusion");
+    let blurred = vulkan_renderer.create_render_target(&device, extent, SSAO_FORMAT, storage_usage, "SSAO Blurred");
+
+    // Both images are written as storage and sampled by lighting, so they live in GENERAL
+    vulkan_renderer.transition_to_general(&[occlusion.image, blurred.image]);
+
+    let sampler = create_sampler(&device, vk::Filter::NEAREST, vk::SamplerAddressMode::CLAMP_TO_EDGE);
+
+    let ssao_set_layout = create_descriptor_set_layout(&device, &[
+        descriptor_binding(0, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, vk::ShaderStageFlags::COMPUTE),
+        descriptor_binding(1, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, vk::ShaderStageFlags::COMPUTE),
+        descriptor_binding(2, vk::DescriptorType::UNIFORM_BUFFER, vk::ShaderStageFlags::COMPUTE),
+        descriptor_binding(3, vk::DescriptorType::STORAGE_IMAGE, vk::ShaderStageFlags::COMPUTE),
+    ]);
+    let blur_set_layout = create_descriptor_set_layout(&device, &[
+        descriptor_binding(0, vk::DescriptorType::STORAGE_IMAGE, vk::ShaderStageFlags::COMPUTE),
+        descriptor_binding(1, vk::DescriptorType::STORAGE_IMAGE, vk::ShaderStageFlags::COMPUTE),
+    ]);
+    let ssao_pipeline_layout = create_pipeline_layout(&device, ssao_set_layout, &[]);
+    let blur_pipeline_layout = create_pipeline_layout(&device, blur_set_layout, &[]);
+    let ssao_pipeline = create_compute_pipeline(&device, ssao_pipeline_layout, "ssao_compute.spv");
+    let blur_pipeline = create_compute_pipeline(&device, blur_pipeline_layout, "ssao_blur.spv");
+
+    let ssao_set = allocate_descriptor_set(&device, descriptor_pool, ssao_set_layout);
+    let blur_set = allocate_descriptor_set(&device, descriptor_pool, blur_set_layout);
+
+    let gbuffer_info = |view| vk::DescriptorImageInfo {
+        sampler,
+        image_view: view,
+        image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+    };
+    let storage_info = |view| vk::DescriptorImageInfo {
+        sampler: vk::Sampler::null(),
+        image_view: view,
+        image_layout: vk::ImageLayout::GENERAL,
+    };
+    let position_info = gbuffer_info(position_view);
+    let normal_info = gbuffer_info(normal_view);
+    let occlusion_info = storage_info(occlusion.view);
+    let blurred_info = storage_info(blurred.view);
+    let uniform_info = vk::DescriptorBufferInfo {
+        buffer: uniform_buffer.buffer,
+        offset: 0,
+        range: vk::WHOLE_SIZE,
+    };
+    let writes = [
+        image_write(ssao_set, 0, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, &position_info),
+        image_write(ssao_set, 1, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, &normal_info),
+        vk::WriteDescriptorSet::builder()
+            .dst_set(ssao_set)
+            .dst_binding(2)
+            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+            .buffer_info(std::slice::from_ref(&uniform_info))
+            .build(),
+        image_write(ssao_set, 3, vk::DescriptorType::STORAGE_IMAGE, &occlusion_info),
+        image_write(blur_set, 0, vk::DescriptorType::STORAGE_IMAGE, &occlusion_info),
+        image_write(blur_set, 1, vk::DescriptorType::STORAGE_IMAGE, &blurred_info),
+    ];
+    unsafe { device.update_descriptor_sets(&writes, &[]) };
+
+    if let Some(pipeline) = ssao_pipeline {
+        vulkan_renderer.set_object_name(pipeline, "SSAO Pipeline");
+    }
+    if let Some(pipeline) = blur_pipeline {
+        vulkan_renderer.set_object_name(pipeline, "SSAO Blur Pipeline");
+    }
+
+    Some(SsaoPass {
+        uniforms: uniform_buffer,
+        occlusion,
+        blurred,
+        extent,
+        sampler,
+        ssao_set_layout,
+        blur_set_layout,
+        ssao_pipeline_layout,
+        blur_pipeline_layout,
+        ssao_pipeline,
+        blur_pipeline,
+        ssao_set,
+        blur_set,
+    })
+}
+
+fn create_scene_color(vulkan_renderer: &mut VulkanRenderer, device: &AshDevice, extent: vk::Extent2D) -> RenderTarget {
+    let usage = vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::TRANSFER_SRC;
+    vulkan_renderer.create_render_target(device, extent, SCENE_COLOR_FORMAT, usage, "Scene Color")
+}
+
+fn bloom_level_extent(extent: vk::Extent2D, level: usize) -> vk::Extent2D {
+    vk::Extent2D {
+        width: (extent.width >> (level + 1)).max(1),
+        height: (extent.height >> (level + 1)).max(1),
+    }
+}
+
+// Sets up the bloom chain images, its four compute pipelines and one descriptor set per step
+fn create_bloom_pass(vulkan_renderer: &mut VulkanRenderer, extent: vk::Extent2D) -> Option<BloomPass> {
+    let device = vulkan_renderer.device.clone()?;
+    let scene_color_view = vulkan_renderer.scene_color.as_ref()?.view;
+    info!("Creating bloom passes...");
+
+    let usage = vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED;
+    let levels: Vec<_> = (0..BLOOM_LEVELS)
+        .map(|level| {
+            let level_extent = bloom_level_extent(extent, level);
+            vulkan_renderer.create_render_target(&device, level_extent, SCENE_COLOR_FORMAT, usage, &format!("Bloom Level {}", level))
+        })
+        .collect();
+    let images: Vec<_> = levels.iter().map(|level| level.image).collect();
+    vulkan_renderer.transition_to_general(&images);
+
+    // Linear filtering does most of the work in the dual filter taps
+    let sampler = create_sampler(&device, vk::Filter::LINEAR, vk::SamplerAddressMode::CLAMP_TO_EDGE);
+
+    // One extract, a down and up step between each pair of levels, one composite
+    let set_count = 2 * BLOOM_LEVELS as u32;
+    let pool_sizes = [
+        vk::DescriptorPoolSize { ty: vk::DescriptorType::STORAGE_IMAGE, descriptor_count: set_count + 1 },
+        vk::DescriptorPoolSize { ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER, descriptor_count: set_count - 1 },
+    ];
+    let pool_create_info = vk::DescriptorPoolCreateInfo::builder()
+        .max_sets(set_count)
+        .pool_sizes(&pool_sizes)
+        .build();
+    let descriptor_pool = unsafe {
+        device.create_descriptor_pool(&pool_create_info, None)
+            .expect("Failed to create bloom descriptor pool")
+    };
+
+    let storage_set_layout = create_descriptor_set_layout(&device, &[
+        descriptor_binding(0, vk::DescriptorType::STORAGE_IMAGE, vk::ShaderStageFlags::COMPUTE),
+        descriptor_binding(1, vk::DescriptorType::STORAGE_IMAGE, vk::ShaderStageFlags::COMPUTE),
+    ]);
+    let sampled_set_layout = create_descriptor_set_layout(&device, &[
+        descriptor_binding(0, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, vk::ShaderStageFlags::COMPUTE),
+        descriptor_binding(1, vk::DescriptorType::STORAGE_IMAGE, vk::ShaderStageFlags::COMPUTE),
+    ]);
+    let push_constant_range = vk::PushConstantRange {
+        stage_flags: vk::ShaderStageFlags::COMPUTE,
+        offset: 0,
+        size: std::mem::size_of::<BloomPushConstants>() as u32,
+    };
+    let storage_pipeline_layout = create_pipeline_layout(&device, storage_set_layout, &[push_constant_range]);
+    let sampled_pipeline_layout = create_pipeline_layout(&device, sampled_set_layout, &[push_constant_range]);
+    let extract_pipeline = create_compute_pipeline(&device, storage_pipeline_layout, "bloom_extract.spv");
+    let downsample_pipeline = create_compute_pipeline(&device, sampled_pipeline_layout, "bloom_downsample.spv");
+    let upsample_pipeline = create_compute_pipeline(&device, sampled_pipeline_layout, "bloom_upsample.spv");
+    let composite_pipeline = create_compute_pipeline(&device, sampled_pipeline_layout, "bloom_composite.spv");
+
+    let storage_info = |view| vk::DescriptorImageInfo {
+        sampler: vk::Sampler::null(),
+        image_view: view,
+        image_layout: vk::ImageLayout::GENERAL,
+    };
+    let sampled_info = |view| vk::DescriptorImageInfo {
+        sampler,
+        image_view: view,
+        image_layout: vk::ImageLayout::GENERAL,
+    };
+    // Writes (input, output) into a freshly allocated set
+    let write_set = |set_layout, input_type, input_info: vk::DescriptorImageInfo, output_info: vk::DescriptorImageInfo| {
+        let set = allocate_descriptor_set(&device, descriptor_pool, set_layout);
+        let writes = [
+            image_write(set, 0, input_type, &input_info),
+            image_write(set, 1, vk::DescriptorType::STORAGE_IMAGE, &output_info),
+        ];
+        unsafe { device.update_descriptor_sets(&writes, &[]) };
+        set
+    };
+
+    let extract_set = write_set(
+        storage_set_layout,
+        vk::DescriptorType::STORAGE_IMAGE,
+        storage_info(scene_color_view),
+        storage_info(levels[0].view),
+    );
+    let downsample_sets = levels.windows(2)
+        .map(|pair| write_set(sampled_set_layout, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, sampled_info(pair[0].view), storage_info(pair[1].view)))
+        .collect();
+    let upsample_sets = levels.windows(2)
+        .map(|pair| write_set(sampled_set_layout, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, sampled_info(pair[1].view), storage_info(pair[0].view)))
+        .collect();
+    let composite_set = write_set(
+        sampled_set_layout,
+        vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+        sampled_info(levels[0].view),
+        storage_info(scene_color_view),
+    );
+
+    for (pipeline, name) in [
+        (extract_pipeline, "Bloom Extract Pipeline"),
+        (downsample_pipeline, "Bloom Downsample Pipeline"),
+        (upsample_pipeline, "Bloom Upsample Pipeline"),
+        (composite_pipeline, "Bloom Composite Pipeline"),
+    ] {
+        if let Some(pipeline) = pipeline {
+            vulkan_renderer.set_object_name(pipeline, name);
+        }
+    }
+
+    Some(BloomPass {
+        levels,
+        sampler,
+        descriptor_pool,
+        storage_set_layout,
+        sampled_set_layout,
+        storage_pipeline_layout,
+        sampled_pipeline_layout,
+        extract_pipeline,
+        downsample_pipeline,
+        upsample_pipeline,
+        composite_pipeline,
+        extract_set,
+        downsample_sets,
+        upsample_sets,
+        composite_set,
+    })
+}
+
+fn create_tone_map_pass(vulkan_renderer: &mut VulkanRenderer, descriptor_pool: vk::DescriptorPool, extent: vk::Extent2D) -> Option<ToneMapPass> {
+    let device = vulkan_renderer.device.clone()?;
+    let scene_color_view = vulkan_renderer.scene_color.as_ref()?.view;
+    info!("Creating tone mapping pass...");
+
+    let usage = vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::TRANSFER_SRC;
+    let output = vulkan_renderer.create_render_target(&device, extent, TONE_MAPPED_FORMAT, usage, "Tone Mapped");
+    vulkan_renderer.transition_to_general(&[output.image]);
+
+    // Binding 2 is the color grading LUT, written once one has been uploaded
+    let set_layout = create_descriptor_set_layout(&device, &[
+        descriptor_binding(0, vk::DescriptorType::STORAGE_IMAGE, vk::ShaderStageFlags::COMPUTE),
+        descriptor_binding(1, vk::DescriptorType::STORAGE_IMAGE, vk::ShaderStageFlags::COMPUTE),
+        descriptor_binding(2, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, vk::ShaderStageFlags::COMPUTE),
+    ]);
+    let push_constant_range = vk::PushConstantRange {
+        stage_flags: vk::ShaderStageFlags::COMPUTE,
+        offset: 0,
+        size: std::mem::size_of::<ToneMapPushConstants>() as u32,
+    };
+    let pipeline_layout = create_pipeline_layout(&device, set_layout, &[push_constant_range]);
+    let pipeline = create_compute_pipeline(&device, pipeline_layout, "tone_map.spv");
+    if let Some(pipeline) = pipeline {
+        vulkan_renderer.set_object_name(pipeline, "Tone Map Pipeline");
+    }
+
+    let set = allocate_descriptor_set(&device, descriptor_pool, set_layout);
+    let storage_info = |view| vk::DescriptorImageInfo {
+        sampler: vk::Sampler::null(),
+        image_view: view,
+        image_layout: vk::ImageLayout::GENERAL,
+    };
+    let scene_info = storage_info(scene_color_view);
+    let output_info = storage_info(output.view);
+    let writes = [
+        image_write(set, 0, vk::DescriptorType::STORAGE_IMAGE, &scene_info),
+        image_write(set, 1, vk::DescriptorType::STORAGE_IMAGE, &output_info),
+    ];
+    unsafe { device.update_descriptor_sets(&writes, &[]) };
+
+    Some(ToneMapPass {
+        output,
+        set_layout,
+        pipeline_layout,
+        pipeline,
+        set,
+    })
+}
+
+// Tiling 3D Perlin noise in [0, 1], x varying fastest
+fn perlin_noise_volume(size: u32, rng: &mut impl Rng) -> Vec<u8> {
+    // Four lattice cells across the volume gives blobs a few meters wide at the shader's scale.
+    // The lattice wraps so the volume tiles.
+    let cells = 4;
+    let gradients: Vec<Vec3> = (0..cells * cells * cells)
+        .map(|_| Vec3::new(rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0), rng.gen_range(-1.0..1.0)).normalize_or_zero())
+        .collect();
+    let gradient = |x: usize, y: usize, z: usize| gradients[(z % cells * cells + y % cells) * cells + x % cells];
+    let fade = |t: f32| t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+
+    let mut data = Vec::with_capacity((size * size * size) as usize);
+    for z in 0..size {
+        for y in 0..size {
+            for x in 0..size {
+                let point = Vec3::new(x as f32, y as f32, z as f32) * cells as f32 / size as f32;
+                let cell = point.floor();
+                let local = point - cell;
+                let (cx, cy, cz) = (cell.x as usize, cell.y as usize, cell.z as usize);
+                let corner = |dx: usize, dy: usize, dz: usize| {
+                    gradient(cx + dx, cy + dy, cz + dz).dot(local - Vec3::new(dx as f32, dy as f32, dz as f32))
+                };
+                let (u, v, w) = (fade(local.x), fade(local.y), fade(local.z));
+                let value = lerp(
+                    lerp(lerp(corner(0, 0, 0), corner(1, 0, 0), u), lerp(corner(0, 1, 0), corner(1, 1, 0), u), v),
+                    lerp(lerp(corner(0, 0, 1), corner(1, 0, 1), u), lerp(corner(0, 1, 1), corner(1, 1, 1), u), v),
+                    w,
+                );
+                data.push(((value * 0.5 + 0.5).clamp(0.0, 1.0) * 255.0) as u8);
+            }
+        }
+    }
+    data
+}
+
+// Sets up the fog ray march: its noise volume, uniforms, output image and descriptor set
+fn create_fog_pass(vulkan_renderer: &mut VulkanRenderer, descriptor_pool: vk::DescriptorPool, extent: vk::Extent2D) -> Option<FogPass> {
+    let device = vulkan_renderer.device.clone()?;
+    let position_view = vulkan_renderer.gbuffer.as_ref()?.position.view;
+    info!("Creating volumetric fog pass...");
+
+    let noise_data = perlin_noise_volume(FOG_NOISE_SIZE, &mut rand::thread_rng());
+    let noise = vulkan_renderer.create_volume_texture(FOG_NOISE_SIZE, vk::Format::R8_UNORM, &noise_data, "Fog Noise")?;
+    let uniforms = vulkan_renderer.create_buffer(
+        &device,
+        std::mem::size_of::<FogUniforms>() as vk::DeviceSize,
+        vk::BufferUsageFlags::UNIFORM_BUFFER,
+        MemoryLocation::CpuToGpu,
+        "Fog Uniforms",
+    );
+    let output = vulkan_renderer.create_render_target(
+        &device,
+        extent,
+        FOG_FORMAT,
+        vk::ImageUsageFlags::STORAGE | vk::ImageUsageFlags::SAMPLED,
+        "Volumetric Fog",
+    );
+    vulkan_renderer.transition_to_general(&[output.image]);
+    let noise_sampler = create_sampler(&device, vk::Filter::LINEAR, vk::SamplerAddressMode::REPEAT);
+    let output_sampler = create_sampler(&device, vk::Filter::NEAREST, vk::SamplerAddressMode::CLAMP_TO_EDGE);
+
+    let set_layout = create_descriptor_set_layout(&device, &[
+        descriptor_binding(0, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, vk::ShaderStageFlags::COMPUTE),
+        descriptor_binding(1, vk::DescriptorType::UNIFORM_BUFFER, vk::ShaderStageFlags::COMPUTE),
+        descriptor_binding(2, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, vk::ShaderStageFlags::COMPUTE),
+        descriptor_binding(3, vk::DescriptorType::STORAGE_IMAGE, vk::ShaderStageFlags::COMPUTE),
+    ]);
+    let pipeline_layout = create_pipeline_layout(&device, set_layout, &[]);
+    let pipeline = create_compute_pipeline(&device, pipeline_layout, "volumetric_fog.spv");
+    if let Some(pipeline) = pipeline {
+        vulkan_renderer.set_object_name(pipeline, "Volumetric Fog Pipeline");
+    }
+
+    let set = allocate_descriptor_set(&device, descriptor_pool, set_layout);
+    let position_info = vk::DescriptorImageInfo {
+        sampler: output_sampler,
+        image_view: position_view,
+        image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+    };
+    let noise_info = vk::DescriptorImageInfo {
+        sampler: noise_sampler,
+        image_view: noise.view,
+        image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+    };
+    let output_info = vk::DescriptorImageInfo {
+        sampler: vk::Sampler::null(),
+        image_view: output.view,
+        image_layout: vk::ImageLayout::GENERAL,
+    };
+    let uniform_info = vk::DescriptorBufferInfo {
+        buffer: uniforms.buffer,
+        offset: 0,
+        range: vk::WHOLE_SIZE,
+    };
+    let writes = [
+        image_write(set, 0, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, &position_info),
+        vk::WriteDescriptorSet::builder()
+            .dst_set(set)
+            .dst_binding(1)
+            .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+            .buffer_info(std::slice::from_ref(&uniform_info))
+            .build(),
+        image_write(set, 2, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, &noise_info),
+        image_write(set, 3, vk::DescriptorType::STORAGE_IMAGE, &output_info),
+    ];
+    unsafe { device.update_descriptor_sets(&writes, &[]) };
+
+    let mut fog = FogPass {
+        uniforms,
+        noise,
+        output,
+        extent,
+        noise_sampler,
+        output_sampler,
+        set_layout,
+        pipeline_layout,
+        pipeline,
+        set,
+    };
+    // Zero density, so the fog starts out clear until the first camera update
+    let initial_uniforms = FogUniforms::default();
+    if let Some(mapped) = fog.uniforms.allocation.mapped_slice_mut() {
+        let bytes = bytemuck::bytes_of(&initial_uniforms);
+        mapped[..bytes.len()].copy_from_slice(bytes);
+    }
+    Some(fog)
+}
+
+fn create_sky_pass(vulkan_renderer: &mut VulkanRenderer, render_pass: vk::RenderPass) -> Option<SkyPass> {
+    let device = vulkan_renderer.device.clone()?;
+    info!("Creating sky pass...");
+
+    let uniforms = vulkan_renderer.create_buffer(
+        &device,
+        std::mem::size_of::<SkyUniforms>() as vk::DeviceSize,
+        vk::BufferUsageFlags::UNIFORM_BUFFER,
+        MemoryLocation::CpuToGpu,
+        "Sky Uniforms",
+    );
+    let set_layout = create_descriptor_set_layout(&device, &[
+        descriptor_binding(0, vk::DescriptorType::UNIFORM_BUFFER, vk::ShaderStageFlags::FRAGMENT),
+    ]);
+    let pipeline_layout = create_pipeline_layout(&device, set_layout, &[]);
+    let pipeline = create_graphics_pipeline(&device, &PipelineDesc {
+        render_pass,
+        subpass: LIGHTING_SUBPASS,
+        layout: pipeline_layout,
+        vertex_shader: "sky_vertex.spv",
+        fragment_shader: "sky_fragment.spv",
+        uses_vertex_buffer: false,
+        cull_mode: vk::CullModeFlags::NONE,
+        color_attachment_count: 1,
+        blend: None,
+    });
+    if let Some(pipeline) = pipeline {
+        vulkan_renderer.set_object_name(pipeline, "Sky Pipeline");
+    }
+
+    let pool_sizes = [vk::DescriptorPoolSize { ty: vk::DescriptorType::UNIFORM_BUFFER, descriptor_count: 1 }];
+    let pool_create_info = vk::DescriptorPoolCreateInfo::builder()
+        .max_sets(1)
+        .pool_sizes(&pool_sizes)
+        .build();
+    let descriptor_pool = unsafe {
+        device.create_descriptor_pool(&pool_create_info, None)
+            .expect("Failed to create sky descriptor pool")
+    };
+    let set = allocate_descriptor_set(&device, descriptor_pool, set_layout);
+    let uniform_info = vk::DescriptorBufferInfo {
+        buffer: uniforms.buffer,
+        offset: 0,
+        range: vk::WHOLE_SIZE,
+    };
+    let write = vk::WriteDescriptorSet::builder()
+        .dst_set(set)
+        .dst_binding(0)
+        .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
+        .buffer_info(std::slice::from_ref(&uniform_info))
+        .build();
+    unsafe { device.update_descriptor_sets(&[write], &[]) };
+
+    let mut sky = SkyPass {
+        uniforms,
+        descriptor_pool,
+        set_layout,
+        pipeline_layout,
+        pipeline,
+        set,
+    };
+    // All zero draws black until the first camera update
+    let initial_uniforms = SkyUniforms::default();
+    if let Some(mapped) = sky.uniforms.allocation.mapped_slice_mut() {
+        let bytes = bytemuck::bytes_of(&initial_uniforms);
+        mapped[..bytes.len()].copy_from_slice(bytes);
+    }
+    Some(sky)
+}
+
+fn create_indirect_cull_pass(vulkan_renderer: &mut VulkanRenderer, geometry_set_layout: vk::DescriptorSetLayout) -> Option<IndirectCullPass> {
+    let device = vulkan_renderer.device.clone()?;
+    info!("Creating indirect cull pass...");
+
+    let uniforms = vulkan_renderer.create_buffer(
+        &device,
+        std::mem::size_of::<CullUniforms>() as vk::DeviceSize,
+        vk::BufferUsageFlags::UNIFORM_BUFFER,
+        MemoryLocation::CpuToGpu,
+        "Cull Uniforms",
+    );
+    let objects = vulkan_renderer.create_buffer(
+        &device,
+        (std::mem::size_of::<CullObject>() * MAX_CULL_OBJECTS) as vk::DeviceSize,
+        vk::BufferUsageFlags::STORAGE_BUFFER,
+        MemoryLocation::CpuToGpu,
+        "Cull Objects",
+    );
+    let visible = vulkan_renderer.create_buffer(
+        &device,
+        (std::mem::size_of::<u32>() * MAX_CULL_OBJECTS) as vk::DeviceSize,
+        vk::BufferUsageFlags::STORAGE_BUFFER,
+        MemoryLocation::GpuOnly,
+        "Cull Visible Indices",
+    );
+    let draw_command = vulkan_renderer.create_buffer(
+        &device,
+        std::mem::size_of::<vk::DrawIndexedIndirectCommand>() as vk::DeviceSize,
+        vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::INDIRECT_BUFFER | vk::BufferUsageFlags::TRANSFER_DST,
+        MemoryLocation::GpuOnly,
+        "Cull Draw Command",
+    );
+
+    let set_layout = create_descriptor_set_layout(&device, &[
+        descriptor_binding(0, vk::DescriptorType::UNIFORM_BUFFER, vk::ShaderStageFlags::COMPUTE),
+        descriptor_binding(1, vk::DescriptorType::STORAGE_BUFFER, vk::ShaderStageFlags::COMPUTE),
+        descriptor_binding(2, vk::DescriptorType::STORAGE_BUFFER, vk::ShaderStageFlags::COMPUTE),
+        descriptor_binding(3, vk::DescriptorType::STORAGE_BUFFER, vk::ShaderStageFlags::COMPUTE),
+    ]);
+    let pipeline_layout = create_pipeline_layout(&device, set_layout, &[]);
+    let pipeline = create_compute_pipeline(&device, pipeline_layout, "gpu_cull.spv");
+    if let Some(pipeline) = pipeline {
+        vulkan_renderer.set_object_name(pipeline, "Indirect Cull Pipeline");
+    }
+
+    // The cull set plus the geometry set, which shares the first three buffers
+    let pool_sizes = [
+        vk::DescriptorPoolSize { ty: vk::DescriptorType::UNIFORM_BUFFER, descriptor_count: 2 },
+        vk::DescriptorPoolSize { ty: vk::DescriptorType::STORAGE_BUFFER, descriptor_count: 5 },
+    ];
+    let pool_create_info = vk::DescriptorPoolCreateInfo::builder()
+        .max_sets(2)
+        .pool_sizes(&pool_sizes)
+        .build();
+    let descriptor_pool = unsafe {
+        device.create_descriptor_pool(&pool_create_info, None)
+            .expect("Failed to create cull descriptor pool")
+    };
+    let set = allocate_descriptor_set(&device, descriptor_pool, set_layout);
+    let geometry_set = allocate_descriptor_set(&device, descriptor_pool, geometry_set_layout);
+
+    let buffer_info = |buffer: &GpuBuffer| vk::DescriptorBufferInfo {
+        buffer: buffer.buffer,
+        offset: 0,
+        range: vk::WHOLE_SIZE,
+    };
+    let infos = [buffer_info(&uniforms), buffer_info(&objects), buffer_info(&visible), buffer_info(&draw_command)];
+    let buffer_write = |dst_set, binding: usize| {
+        let descriptor_type = if binding == 0 { vk::DescriptorType::UNIFORM_BUFFER } else { vk::DescriptorType::STORAGE_BUFFER };
+        vk::WriteDescriptorSet::builder()
+            .dst_set(dst_set)
+            .dst_binding(binding as u32)
+            .descriptor_type(descriptor_type)
+            .buffer_info(std::slice::from_ref(&infos[binding]))
+            .build()
+    };
+    let writes: Vec<_> = (0..4).map(|binding| buffer_write(set, binding))
+        .chain((0..3).map(|binding| buffer_write(geometry_set, binding)))
+        .collect();
+    unsafe { device.update_descriptor_sets(&writes, &[]) };
+
+    let mut cull = IndirectCullPass {
+        uniforms,
+        objects,
+        visible,
+        draw_command,
+        object_count: 0,
+        mesh: None,
+        descriptor_pool,
+        set_layout,
+        pipeline_layout,
+        pipeline,
+        set,
+        geometry_set,
+    };
+    // No objects and no planes culls nothing until the first camera update
+    let initial_uniforms = CullUniforms::default();
+    if let Some(mapped) = cull.uniforms.allocation.mapped_slice_mut() {
+        let bytes = bytemuck::bytes_of(&initial_uniforms);
+        mapped[..bytes.len()].copy_from_slice(bytes);
+    }
+    Some(cull)
+}
+
+// Only runs on devices created with the ray tracing extensions. Returns None (after
+// logging why) if the ray tracing shaders aren't available, leaving SSAO in place.
+#[cfg(feature = "ray_tracing")]
+fn create_ray_tracing_pipeline(vulkan_renderer: &mut VulkanRenderer) -> Option<RayTracingPipeline> {
+    if !vulkan_renderer.ray_tracing_supported {
+        return None;
+    }
+    let instance = vulkan_renderer.instance.clone()?;
+    let physical_device = vulkan_renderer.physical_device?;
+    let device = vulkan_renderer.device.clone()?;
+    info!("Creating ray tracing pipeline...");
+
+    let acceleration_structure_loader = AccelerationStructure::new(&instance, &device);
+    let ray_tracing_loader = RayTracingPipelineLoader::new(&instance, &device);
+    let (ray_tracing_properties, acceleration_structure_properties) = unsafe {
+        (
+            RayTracingPipelineLoader::get_properties(&instance, physical_device),
+            AccelerationStructure::get_properties(&instance, physical_device),
+        )
+    };
+
+    let set_layout = create_descriptor_set_layout(&device, &[
+        descriptor_binding(0, vk::DescriptorType::ACCELERATION_STRUCTURE_KHR, vk::ShaderStageFlags::RAYGEN_KHR),
+        descriptor_binding(1, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, vk::ShaderStageFlags::RAYGEN_KHR),
+        descriptor_binding(2, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, vk::ShaderStageFlags::RAYGEN_KHR),
+        descriptor_binding(3, vk::DescriptorType::STORAGE_IMAGE, vk::ShaderStageFlags::RAYGEN_KHR),
+    ]);
+    let push_constant_range = vk::PushConstantRange {
+        stage_flags: vk::ShaderStageFlags::RAYGEN_KHR,
+        offset: 0,
+        size: std::mem::size_of::<u32>() as u32,
+    };
+    let pipeline_layout = create_pipeline_layout(&device, set_layout, &[push_constant_range]);
+    let Some(pipeline) = create_ray_tracing_shader_pipeline(&device, &ray_tracing_loader, pipeline_layout) else {
+        unsafe {
+            device.destroy_pipeline_layout(pipeline_layout, None);
+            device.destroy_descriptor_set_layout(set_layout, None);
+        }
+        return None;
+    };
+    vulkan_renderer.set_object_name(pipeline, "Ray Traced AO Pipeline");
+
+    // Raygen, miss and hit regions, each starting on the base alignment
+    let handle_size = ray_tracing_properties.shader_group_handle_size as usize;
+    let handle_stride = align_up(handle_size as vk::DeviceSize, ray_tracing_properties.shader_group_handle_alignment as vk::DeviceSize);
+    let base_alignment = ray_tracing_properties.shader_group_base_alignment as vk::DeviceSize;
+    let region_size = align_up(handle_stride, base_alignment);
+    let handles = unsafe {
+        ray_tracing_loader.get_ray_tracing_shader_group_handles(pipeline, 0, RAY_TRACING_GROUP_COUNT, handle_size * RAY_TRACING_GROUP_COUNT as usize)
+            .expect("Failed to get shader group handles")
+    };
+    // Padded so the table can start on the base alignment wherever the buffer lands
+    let mut shader_binding_table = vulkan_renderer.create_buffer(
+        &device,
+        region_size * RAY_TRACING_GROUP_COUNT as vk::DeviceSize + base_alignment,
+        vk::BufferUsageFlags::SHADER_BINDING_TABLE_KHR | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+        MemoryLocation::CpuToGpu,
+        "Shader Binding Table",
+    );
+    let buffer_address = vulkan_renderer.buffer_device_address(shader_binding_table.buffer);
+    let table_address = align_up(buffer_address, base_alignment);
+    let table_offset = (table_address - buffer_address) as usize;
+    let mapped = shader_binding_table.allocation.mapped_slice_mut()
+        .expect("Shader binding table is not host visible");
+    for (group, handle) in handles.chunks_exact(handle_size).enumerate() {
+        let offset = table_offset + group * region_size as usize;
+        mapped[offset..offset + handle_size].copy_from_slice(handle);
+    }
+    let region = |group: vk::DeviceSize| vk::StridedDeviceAddressRegionKHR {
+        device_address: table_address + group * region_size,
+        stride: handle_stride,
+        size: handle_stride,
+    };
+
+    let instances = vulkan_renderer.create_buffer(
+        &device,
+        (std::mem::size_of::<vk::AccelerationStructureInstanceKHR>() * MAX_CULL_OBJECTS) as vk::DeviceSize,
+        vk::BufferUsageFlags::ACCELERATION_STRUCTURE_BUILD_INPUT_READ_ONLY_KHR | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+        MemoryLocation::CpuToGpu,
+        "TLAS Instances",
+    );
+    let geometry = vk::AccelerationStructureGeometryKHR::builder()
+        .geometry_type(vk::GeometryTypeKHR::INSTANCES)
+        .geometry(vk::AccelerationStructureGeometryDataKHR {
+            instances: vk::AccelerationStructureGeometryInstancesDataKHR::builder()
+                .array_of_pointers(false)
+                .build(),
+        })
+        .build();
+    let build_info = vk::AccelerationStructureBuildGeometryInfoKHR::builder()
+        .ty(vk::AccelerationStructureTypeKHR::TOP_LEVEL)
+        .flags(vk::BuildAccelerationStructureFlagsKHR::PREFER_FAST_TRACE)
+        .mode(vk::BuildAccelerationStructureModeKHR::BUILD)
+        .geometries(std::slice::from_ref(&geometry))
+        .build();
+    let sizes = unsafe {
+        acceleration_structure_loader.get_acceleration_structure_build_sizes(
+            vk::AccelerationStructureBuildTypeKHR::DEVICE,
+            &build_info,
+            &[MAX_CULL_OBJECTS as u32],
+        )
+    };
+    let tlas = vulkan_renderer.create_acceleration_structure(
+        &acceleration_structure_loader,
+        vk::AccelerationStructureTypeKHR::TOP_LEVEL,
+        sizes.acceleration_structure_size,
+        "Scene TLAS",
+    );
+    let scratch_alignment = acceleration_structure_properties.min_acceleration_structure_scratch_offset_alignment as vk::DeviceSize;
+    let scratch = vulkan_renderer.create_buffer(
+        &device,
+        sizes.build_scratch_size + scratch_alignment,
+        vk::BufferUsageFlags::STORAGE_BUFFER | vk::BufferUsageFlags::SHADER_DEVICE_ADDRESS,
+        MemoryLocation::GpuOnly,
+        "TLAS Scratch",
+    );
+    let scratch_address = align_up(vulkan_renderer.buffer_device_address(scratch.buffer), scratch_alignment);
+
+    let pool_sizes = [
+        vk::DescriptorPoolSize { ty: vk::DescriptorType::ACCELERATION_STRUCTURE_KHR, descriptor_count: 1 },
+        vk::DescriptorPoolSize { ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER, descriptor_count: 2 },
+        vk::DescriptorPoolSize { ty: vk::DescriptorType::STORAGE_IMAGE, descriptor_count: 1 },
+    ];
+    let pool_create_info = vk::DescriptorPoolCreateInfo::builder()
+        .max_sets(1)
+        .pool_sizes(&pool_sizes)
+        .build();
+    let descriptor_pool = unsafe {
+        device.create_descriptor_pool(&pool_create_info, None)
+            .expect("Failed to create ray tracing descriptor pool")
+    };
+    let set = allocate_descriptor_set(&device, descriptor_pool, set_layout);
+
+    // The TLAS handle never changes, only its contents, so it's written once here
+    let mut tlas_write = vk::WriteDescriptorSetAccelerationStructureKHR::builder()
+        .acceleration_structures(std::slice::from_ref(&tlas.handle))
+        .build();
+    let mut write = vk::WriteDescriptorSet::builder()
+        .dst_set(set)
+        .dst_binding(0)
+        .descriptor_type(vk::DescriptorType::ACCELERATION_STRUCTURE_KHR)
+        .push_next(&mut tlas_write)
+        .build();
+    write.descriptor_count = 1;
+    unsafe { device.update_descriptor_sets(&[write], &[]) };
+
+    Some(RayTracingPipeline {
+        acceleration_structure_loader,
+        ray_tracing_loader,
+        blas: None,
+        tlas,
+        instances,
+        instance_count: 0,
+        scratch,
+        scratch_address,
+        scratch_alignment,
+        descriptor_pool,
+        set_layout,
+        pipeline_layout,
+        pipeline,
+        set,
+        shader_binding_table,
+        raygen_region: region(0),
+        miss_region: region(1),
+        hit_region: region(2),
+        frame: 0,
+    })
+}
+
+// Shared sampler, material set layout and descriptor pool, then the terrain and player textures
+fn create_texture_cache(vulkan_renderer: &mut VulkanRenderer) {
+    let Some(device) = vulkan_renderer.device.clone() else {
+        return;
+    };
+    info!("Creating texture cache...");
+
+    let sampler_create_info = vk::SamplerCreateInfo::builder()
+        .mag_filter(vk::Filter::LINEAR)
+        .min_filter(vk::Filter::LINEAR)
+        .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+        .address_mode_u(vk::SamplerAddressMode::REPEAT)
+        .address_mode_v(vk::SamplerAddressMode::REPEAT)
+        .address_mode_w(vk::SamplerAddressMode::REPEAT)
+        .max_lod(vk::LOD_CLAMP_NONE)
+        .build();
+    let sampler = unsafe {
+        device.create_sampler(&sampler_create_info, None)
+            .expect("Failed to create texture sampler")
+    };
+    vulkan_renderer.set_object_name(sampler, "Texture Sampler");
+
+    let set_layout = create_descriptor_set_layout(&device, &[
+        descriptor_binding(0, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, vk::ShaderStageFlags::FRAGMENT),
+    ]);
+    let pool_sizes = [vk::DescriptorPoolSize { ty: vk::DescriptorType::COMBINED_IMAGE_SAMPLER, descriptor_count: MAX_TEXTURES }];
+    let pool_create_info = vk::DescriptorPoolCreateInfo::builder()
+        .max_sets(MAX_TEXTURES)
+        .pool_sizes(&pool_sizes)
+        .build();
+    let descriptor_pool = unsafe {
+        device.create_descriptor_pool(&pool_create_info, None)
+            .expect("Failed to create texture descriptor pool")
+    };
+
+    vulkan_renderer.texture_cache = TextureCache {
+        sampler: Some(sampler),
+        descriptor_pool: Some(descriptor_pool),
+        set_layout: Some(set_layout),
+        ..default()
+    };
+    for path in [TERRAIN_TEXTURE, PLAYER_TEXTURE] {
+        vulkan_renderer.upload_texture(Path::new(path));
+    }
+}
+
+// Perez distribution coefficients A-E as (Y, x, y) for the given turbidity, from Preetham et al.
+fn preetham_perez_coefficients(turbidity: f32) -> [Vec3; 5] {
+    let t = turbidity;
+    [
+        Vec3::new(0.1787 * t - 1.4630, -0.0193 * t - 0.2592, -0.0167 * t - 0.2608),
+        Vec3::new(-0.3554 * t + 0.4275, -0.0665 * t + 0.0008, -0.0950 * t + 0.0092),
+        Vec3::new(-0.0227 * t + 5.3251, -0.0004 * t + 0.2125, -0.0079 * t + 0.2102),
+        Vec3::new(0.1206 * t - 2.5771, -0.0641 * t - 0.8989, -0.0441 * t - 1.6537),
+        Vec3::new(-0.0670 * t + 0.3703, -0.0033 * t + 0.0452, -0.0109 * t + 0.0529),
+    ]
+}
+
+// Zenith luminance (kcd/m^2) and chromaticity for a sun theta_s radians from the zenith
+fn preetham_zenith(turbidity: f32, theta_s: f32) -> Vec3 {
+    let t = turbidity;
+    let chi = (4.0 / 9.0 - t / 120.0) * (std::f32::consts::PI - 2.0 * theta_s);
+    let luminance = (4.0453 * t - 4.9710) * chi.tan() - 0.2155 * t + 2.4192;
+
+    let theta = Vec3::new(theta_s * theta_s * theta_s, theta_s * theta_s, theta_s);
+    let chromaticity = |t2: Vec3, t1: Vec3, t0: Vec3, c: [f32; 3]| {
+        t * t * (t2.dot(theta) + c[0]) + t * (t1.dot(theta) + c[1]) + t0.dot(theta) + c[2]
+    };
+    let x = chromaticity(
+        Vec3::new(0.00166, -0.00375, 0.00209),
+        Vec3::new(-0.02903, 0.06377, -0.03202),
+        Vec3::new(0.11693, -0.21196, 0.06052),
+        [0.0, 0.00394, 0.25886],
+    );
+    let y = chromaticity(
+        Vec3::new(0.00275, -0.00610, 0.00317),
+        Vec3::new(-0.04214, 0.08970, -0.04153),
+        Vec3::new(0.15346, -0.26756, 0.06670),
+        [0.0, 0.00516, 0.26688],
+    );
+    Vec3::new(luminance, x, y)
+}
+
+// Precomputes everything in sky_fragment.glsl that doesn't depend on the view direction
+fn sky_uniforms(config: &SkyConfig, inverse_view_projection: Mat4, camera_position: Vec3) -> SkyUniforms {
+    let sun_direction = config.sun_direction.normalize_or_zero();
+    // The model breaks down once the sun sets, so hold it at the horizon and fade to night
+    let theta_s = sun_direction.y.clamp(0.0, 1.0).acos();
+    let day = ((sun_direction.y + 0.1) / 0.15).clamp(0.0, 1.0);
+    let day = day * day * (3.0 - 2.0 * day);
+
+    let perez = preetham_perez_coefficients(config.turbidity);
+    // F(theta = 0, gamma = theta_s), the distribution at the zenith
+    let zenith_distribution = (Vec3::ONE + perez[0] * perez[1].exp())
+        * (Vec3::ONE + perez[2] * (perez[3] * theta_s).exp() + perez[4] * theta_s.cos() * theta_s.cos());
+    let mut zenith = preetham_zenith(config.turbidity, theta_s) / zenith_distribution;
+    zenith.x *= SKY_LUMINANCE_SCALE;
+
+    SkyUniforms {
+        inverse_view_projection: inverse_view_projection.to_cols_array_2d(),
+        camera_position: camera_position.extend(1.0).to_array(),
+        sun_direction: sun_direction.extend(0.0).to_array(),
+        perez: perez.map(|coefficient| coefficient.extend(0.0).to_array()),
+        zenith: zenith.extend(0.0).to_array(),
+        ground_albedo: config.ground_albedo.extend(day).to_array(),
+    }
+}
+
+// Points the lighting subpass at the G-buffer input attachments and the SSAO result
+fn write_lighting_descriptor_set(
+    vulkan_renderer: &VulkanRenderer,
+    descriptor_pool: vk::DescriptorPool,
+    set_layout: vk::DescriptorSetLayout,
+) -> Option<vk::DescriptorSet> {
+    let device = vulkan_renderer.device.as_ref()?;
+    let gbuffer = vulkan_renderer.gbuffer.as_ref()?;
+    let ssao = vulkan_renderer.ssao.as_ref()?;
+    let fog = vulkan_renderer.fog.as_ref()?;
+
+    let set = allocate_descriptor_set(device, descriptor_pool, set_layout);
+    let input_info = |view| vk::DescriptorImageInfo {
+        sampler: vk::Sampler::null(),
+        image_view: view,
+        image_layout: vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+    };
+    let position_info = input_info(gbuffer.position.view);
+    let normal_info = input_info(gbuffer.normal.view);
+    let albedo_info = input_info(gbuffer.albedo.view);
+    let occlusion_info = vk::DescriptorImageInfo {
+        sampler: ssao.sampler,
+        image_view: ssao.blurred.view,
+        image_layout: vk::ImageLayout::GENERAL,
+    };
+    let fog_info = vk::DescriptorImageInfo {
+        sampler: fog.output_sampler,
+        image_view: fog.output.view,
+        image_layout: vk::ImageLayout::GENERAL,
+    };
+    let writes = [
+        image_write(set, 0, vk::DescriptorType::INPUT_ATTACHMENT, &position_info),
+        image_write(set, 1, vk::DescriptorType::INPUT_ATTACHMENT, &normal_info),
+        image_write(set, 2, vk::DescriptorType::INPUT_ATTACHMENT, &albedo_info),
+        image_write(set, 3, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, &occlusion_info),
+        image_write(set, 4, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, &fog_info),
+    ];
+    unsafe { device.update_descriptor_sets(&writes, &[]) };
+    Some(set)
+}
+
+fn create_sampler(device: &AshDevice, filter: vk::Filter, address_mode: vk::SamplerAddressMode) -> vk::Sampler {
+    let create_info = vk::SamplerCreateInfo::builder()
+        .mag_filter(filter)
+        .min_filter(filter)
+        .address_mode_u(address_mode)
+        .address_mode_v(address_mode)
+        .address_mode_w(address_mode)
+        .build();
+    unsafe {
+        device.create_sampler(&create_info, None)
+            .expect("Failed to create sampler")
+    }
+}
+
+fn create_descriptor_set_layout(device: &AshDevice, bindings: &[vk::DescriptorSetLayoutBinding]) -> vk::DescriptorSetLayout {
+    let create_info = vk::DescriptorSetLayoutCreateInfo::builder()
+        .bindings(bindings)
+        .build();
+    unsafe {
+        device.create_descriptor_set_layout(&create_info, None)
+            .expect("Failed to create descriptor set layout")
+    }
+}
+
+fn create_pipeline_layout(
+    device: &AshDevice,
+    set_layout: vk::DescriptorSetLayout,
+    push_constant_ranges: &[vk::PushConstantRange],
+) -> vk::PipelineLayout {
+    let create_info = vk::PipelineLayoutCreateInfo::builder()
+        .set_layouts(std::slice::from_ref(&set_layout))
+        .push_constant_ranges(push_constant_ranges)
+        .build();
+    unsafe {
+        device.create_pipeline_layout(&create_info, None)
+            .expect("Failed to create pipeline layout")
     }
 }
 
-#[derive(Resource)]
-pub struct VulkanRenderer {
-    pub entry: Option<Entry>,
-    pub instance: Option<AshInstance>,
-    pub device: Option<AshDevice>,
-    pub surface: Option<vk::SurfaceKHR>,
-    pub swapchain: Option<vk::SwapchainKHR>,
-    pub swapchain_images: Vec<vk::Image>,
-    pub render_pass: Option<vk::RenderPass>,
-    pub pipeline: Option<vk::Pipeline>,
-    pub allocator: Option<Allocator>,
-    pub instance_created: bool,
-    pub device_created: bool,
-    pub swapchain_created: bool,
-    pub pipeline_created: bool,
+fn load_shader_module(device: &AshDevice, file_name: &str) -> Option<vk::ShaderModule> {
+    let path = format!("{}/{}", SHADER_DIR, file_name);
+    let code = match std::fs::File::open(&path).and_then(|mut file| ash::util::read_spv(&mut file)) {
+        Ok(code) => code,
+        Err(err) => {
+            warn!("Failed to load shader {}: {}", path, err);
+            return None;
+        }
+    };
+
+    let create_info = vk::ShaderModuleCreateInfo::builder()
+        .code(&code)
+        .build();
+    unsafe {
+        device.create_shader_module(&create_info, None)
+            .map_err(|err| warn!("Failed to create shader module {}: {:?}", path, err))
+            .ok()
+    }
 }
 
-impl Default for VulkanRenderer {
-    fn default() -> Self {
-        Self {
-            entry: None,
-            instance: None,
-            device: None,
-            surface: None,
-            swapchain: None,
-            swapchain_images: Vec::new(),
-            render_pass: None,
-            pipeline: None,
-            allocator: None,
-            instance_created: false,
-            device_created: false,
-            swapchain_created: false,
-            pipeline_created: false,
+// Returns None (after logging why) if the SPIR-V isn't available
+fn create_compute_pipeline(device: &AshDevice, layout: vk::PipelineLayout, shader: &str) -> Option<vk::Pipeline> {
+    let module = load_shader_module(device, shader)?;
+    let stage = vk::PipelineShaderStageCreateInfo::builder()
+        .stage(vk::ShaderStageFlags::COMPUTE)
+        .module(module)
+        .name(c"main")
+        .build();
+    let create_info = vk::ComputePipelineCreateInfo::builder()
+        .stage(stage)
+        .layout(layout)
+        .build();
+
+    let pipeline = unsafe {
+        device.create_compute_pipelines(vk::PipelineCache::null(), &[create_info], None)
+            .map_err(|(_, err)| warn!("Failed to create compute pipeline {}: {:?}", shader, err))
+            .ok()
+            .map(|pipelines| pipelines[0])
+    };
+    unsafe { device.destroy_shader_module(module, None) };
+    pipeline
+}
+
+// Raygen, miss and closest hit groups for the occlusion rays. Returns None (after logging
+// why) if any of the SPIR-V isn't available.
+#[cfg(feature = "ray_tracing")]
+fn create_ray_tracing_shader_pipeline(
+    device: &AshDevice,
+    loader: &RayTracingPipelineLoader,
+    layout: vk::PipelineLayout,
+) -> Option<vk::Pipeline> {
+    let shaders = [
+        (vk::ShaderStageFlags::RAYGEN_KHR, "rt_ao_raygen.spv"),
+        (vk::ShaderStageFlags::MISS_KHR, "rt_ao_miss.spv"),
+        (vk::ShaderStageFlags::CLOSEST_HIT_KHR, "rt_ao_closest_hit.spv"),
+    ];
+    let modules: Vec<_> = shaders.iter().filter_map(|&(_, shader)| load_shader_module(device, shader)).collect();
+    if modules.len() < shaders.len() {
+        for module in modules {
+            unsafe { device.destroy_shader_module(module, None) };
         }
+        return None;
+    }
+
+    let stages: Vec<_> = shaders.iter().zip(&modules).map(|(&(stage, _), &module)| {
+        vk::PipelineShaderStageCreateInfo::builder()
+            .stage(stage)
+            .module(module)
+            .name(c"main")
+            .build()
+    }).collect();
+    let group = |ty, general_shader, closest_hit_shader| vk::RayTracingShaderGroupCreateInfoKHR::builder()
+        .ty(ty)
+        .general_shader(general_shader)
+        .closest_hit_shader(closest_hit_shader)
+        .any_hit_shader(vk::SHADER_UNUSED_KHR)
+        .intersection_shader(vk::SHADER_UNUSED_KHR)
+        .build();
+    let groups = [
+        group(vk::RayTracingShaderGroupTypeKHR::GENERAL, 0, vk::SHADER_UNUSED_KHR),
+        group(vk::RayTracingShaderGroupTypeKHR::GENERAL, 1, vk::SHADER_UNUSED_KHR),
+        group(vk::RayTracingShaderGroupTypeKHR::TRIANGLES_HIT_GROUP, vk::SHADER_UNUSED_KHR, 2),
+    ];
+    let create_info = vk::RayTracingPipelineCreateInfoKHR::builder()
+        .stages(&stages)
+        .groups(&groups)
+        .max_pipeline_ray_recursion_depth(1)
+        .layout(layout)
+        .build();
+
+    let pipeline = unsafe {
+        loader.create_ray_tracing_pipelines(vk::DeferredOperationKHR::null(), vk::PipelineCache::null(), &[create_info], None)
+            .map_err(|err| warn!("Failed to create ray tracing pipeline: {:?}", err))
+            .ok()
+            .map(|pipelines| pipelines[0])
+    };
+    for module in modules {
+        unsafe { device.destroy_shader_module(module, None) };
     }
+    pipeline
 }
 
-fn setup_vulkan_renderer(vulkan_renderer: &mut VulkanRenderer) {
-    info!("Setting up Vulkan renderer...");
-    
-    // Load Vulkan entry point
-    let entry = unsafe { Entry::load().expect("Failed to load Vulkan entry point") };
-    
-    // Check available extensions
-    let available_extensions = unsafe {
-        entry.enumerate_instance_extension_properties(None)
-            .expect("Failed to enumerate instance extensions")
+#[cfg(feature = "ray_tracing")]
+fn align_up(value: vk::DeviceSize, alignment: vk::DeviceSize) -> vk::DeviceSize {
+    value.div_ceil(alignment) * alignment
+}
+
+struct PipelineDesc<'a> {
+    render_pass: vk::RenderPass,
+    subpass: u32,
+    layout: vk::PipelineLayout,
+    vertex_shader: &'a str,
+    fragment_shader: &'a str,
+    // The lighting pass draws a fullscreen triangle from gl_VertexIndex alone
+    uses_vertex_buffer: bool,
+    cull_mode: vk::CullModeFlags,
+    color_attachment_count: usize,
+    // Applied to every color attachment; None overwrites
+    blend: Option<vk::PipelineColorBlendAttachmentState>,
+}
+
+// Returns None (after logging why) if the SPIR-V for either stage isn't available
+fn create_graphics_pipeline(device: &AshDevice, desc: &PipelineDesc) -> Option<vk::Pipeline> {
+    let vertex_module = load_shader_module(device, desc.vertex_shader);
+    let fragment_module = load_shader_module(device, desc.fragment_shader);
+    let (Some(vertex_module), Some(fragment_module)) = (vertex_module, fragment_module) else {
+        for module in [vertex_module, fragment_module].into_iter().flatten() {
+            unsafe { device.destroy_shader_module(module, None) };
+        }
+        return None;
     };
-    
-    info!("Available extensions: {:?}", available_extensions.len());
-    
-    // Create Vulkan instance with minimal extensions
-    let app_info = vk::ApplicationInfo::builder()
-        .application_name(unsafe { CStr::from_bytes_with_nul_unchecked(b"Vulkan Game\0") })
-        .application_version(vk::API_VERSION_1_0)
-        .engine_name(unsafe { CStr::from_bytes_with_nul_unchecked(b"Bevy\0") })
-        .engine_version(vk::API_VERSION_1_0)
-        .api_version(vk::API_VERSION_1_0)
+
+    let stages = [
+        vk::PipelineShaderStageCreateInfo::builder()
+            .stage(vk::ShaderStageFlags::VERTEX)
+            .module(vertex_module)
+            .name(c"main")
+            .build(),
+        vk::PipelineShaderStageCreateInfo::builder()
+            .stage(vk::ShaderStageFlags::FRAGMENT)
+            .module(fragment_module)
+            .name(c"main")
+            .build(),
+    ];
+
+    let vertex_bindings = [vk::VertexInputBindingDescription {
+        binding: 0,
+        stride: VERTEX_STRIDE,
+        input_rate: vk::VertexInputRate::VERTEX,
+    }];
+    let vertex_attributes = [
+        vk::VertexInputAttributeDescription { location: 0, binding: 0, format: vk::Format::R32G32B32_SFLOAT, offset: 0 },
+        vk::VertexInputAttributeDescription { location: 1, binding: 0, format: vk::Format::R32G32B32_SFLOAT, offset: 12 },
+        vk::VertexInputAttributeDescription { location: 2, binding: 0, format: vk::Format::R32G32_SFLOAT, offset: 24 },
+    ];
+    let vertex_input = if desc.uses_vertex_buffer {
+        vk::PipelineVertexInputStateCreateInfo::builder()
+            .vertex_binding_descriptions(&vertex_bindings)
+            .vertex_attribute_descriptions(&vertex_attributes)
+            .build()
+    } else {
+        vk::PipelineVertexInputStateCreateInfo::default()
+    };
+
+    let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::builder()
+        .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
         .build();
-    
-    let instance_create_info = vk::InstanceCreateInfo::builder()
-        .application_info(&app_info)
+    // Viewport and scissor are set per frame so resizing doesn't need new pipelines
+    let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+        .viewport_count(1)
+        .scissor_count(1)
         .build();
-    
-    let instance = unsafe { 
-        entry.create_instance(&instance_create_info, None)
-            .expect("Failed to create Vulkan instance")
+    let rasterization = vk::PipelineRasterizationStateCreateInfo::builder()
+        .polygon_mode(vk::PolygonMode::FILL)
+        .cull_mode(desc.cull_mode)
+        .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
+        .line_width(1.0)
+        .build();
+    let multisample = vk::PipelineMultisampleStateCreateInfo::builder()
+        .rasterization_samples(vk::SampleCountFlags::TYPE_1)
+        .build();
+    let blend_attachment = desc.blend.unwrap_or_else(|| {
+        vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(vk::ColorComponentFlags::RGBA)
+            .build()
+    });
+    let blend_attachments = vec![blend_attachment; desc.color_attachment_count];
+    let color_blend = vk::PipelineColorBlendStateCreateInfo::builder()
+        .attachments(&blend_attachments)
+        .build();
+    let dynamic_states = [vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+    let dynamic_state = vk::PipelineDynamicStateCreateInfo::builder()
+        .dynamic_states(&dynamic_states)
+        .build();
+
+    let create_info = vk::GraphicsPipelineCreateInfo::builder()
+        .stages(&stages)
+        .vertex_input_state(&vertex_input)
+        .input_assembly_state(&input_assembly)
+        .viewport_state(&viewport_state)
+        .rasterization_state(&rasterization)
+        .multisample_state(&multisample)
+        .color_blend_state(&color_blend)
+        .dynamic_state(&dynamic_state)
+        .layout(desc.layout)
+        .render_pass(desc.render_pass)
+        .subpass(desc.subpass)
+        .build();
+
+    let pipeline = unsafe {
+        device.create_graphics_pipelines(vk::PipelineCache::null(), &[create_info], None)
+            .map_err(|(_, err)| warn!("Failed to create graphics pipeline: {:?}", err))
+            .ok()
+            .map(|pipelines| pipelines[0])
     };
-    
-    vulkan_renderer.entry = Some(entry);
-    vulkan_renderer.instance = Some(instance);
-    vulkan_renderer.instance_created = true;
-    
-    info!("Vulkan instance created successfully");
+
+    // Modules are baked into the pipeline and no longer needed
+    unsafe {
+        device.destroy_shader_module(vertex_module, None);
+        device.destroy_shader_module(fragment_module, None);
+    }
+    pipeline
 }
 
-fn setup_lighting(mut commands: Commands) {
-    // Add a directional light for the scene
-    commands.spawn(DirectionalLightBundle {
-        directional_light: DirectionalLight {
-            illuminance: 10000.0,
-            shadows_enabled: true,
-            ..default()
-        },
-        transform: Transform::from_xyz(4.0, 8.0, 4.0).looking_at(Vec3::ZERO, Vec3::Y),
-        ..default()
-    });
+// Release Vulkan resources once the GPU is done, while the window still exists
+fn cleanup_vulkan(
+    mut exit_events: EventReader<AppExit>,
+    mut vulkan_renderer: ResMut<VulkanRenderer>,
+) {
+    if exit_events.read().next().is_none() {
+        return;
+    }
+
+    info!("Shutting down Vulkan renderer...");
+    vulkan_renderer.wait_idle();
+    vulkan_renderer.destroy();
+    info!("Vulkan resources released");
 }
 
-fn setup_vulkan_surface(
+// Records every pass of one frame into command_buffer. Nothing calls it while there's no
+// swapchain to present to.
+#[allow(dead_code)]
+fn render_vulkan(
+    vulkan_renderer: &VulkanRenderer,
+    post_process: &PostProcessConfig,
+    tone_mapping: &ToneMappingConfig,
+    command_buffer: vk::CommandBuffer,
+    swapchain_image: vk::Image,
+) {
+    // Both subpasses cover the whole G-buffer
+    if let (Some(device), Some(gbuffer)) = (&vulkan_renderer.device, &vulkan_renderer.gbuffer) {
+        let viewport = vk::Viewport {
+            x: 0.0,
+            y: 0.0,
+            width: gbuffer.extent.width as f32,
+            height: gbuffer.extent.height as f32,
+            min_depth: 0.0,
+            max_depth: 1.0,
+        };
+        let scissor = vk::Rect2D {
+            offset: vk::Offset2D::default(),
+            extent: gbuffer.extent,
+        };
+        unsafe {
+            device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+            device.cmd_set_scissor(command_buffer, 0, &[scissor]);
+        }
+    }
+
+    // Culling fills the indirect draw the geometry subpass consumes
+    vulkan_renderer.record_cull(command_buffer);
+
+    vulkan_renderer.begin_debug_label(command_buffer, "Geometry Subpass");
+    vulkan_renderer.record_culled_geometry(command_buffer);
+    vulkan_renderer.end_debug_label(command_buffer);
+
+    // Lighting is a single fullscreen triangle over the G-buffer, blended over the sky
+    vulkan_renderer.begin_debug_label(command_buffer, "Lighting Subpass");
+    vulkan_renderer.record_sky(command_buffer);
+    vulkan_renderer.end_debug_label(command_buffer);
+
+    vulkan_renderer.record_ssao(command_buffer);
+    vulkan_renderer.record_fog(command_buffer);
+    vulkan_renderer.record_post_process(command_buffer, post_process, tone_mapping, swapchain_image);
+}
+
+// Window size changes invalidate every full screen target
+fn resize_vulkan_targets(
+    mut resize_events: EventReader<WindowResized>,
     mut vulkan_renderer: ResMut<VulkanRenderer>,
     windows: Query<&Window>,
 ) {
-    if !vulkan_renderer.instance_created {
-        setup_vulkan_renderer(&mut vulkan_renderer);
+    if resize_events.read().last().is_none() || !vulkan_renderer.pipeline_created {
+        return;
     }
-    
-    if !vulkan_renderer.device_created {
-        create_vulkan_device_and_queue(&mut vulkan_renderer);
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let extent = vk::Extent2D {
+        width: window.physical_width(),
+        height: window.physical_height(),
+    };
+    // Minimized windows report zero; keep the old targets until it comes back
+    if extent.width == 0 || extent.height == 0 || extent == vulkan_renderer.swapchain_extent {
+        return;
     }
-    
-    if !vulkan_renderer.swapchain_created {
-        if let Ok(window) = windows.get_single() {
-            create_vulkan_swapchain(&mut vulkan_renderer, window);
-        }
+    vulkan_renderer.recreate_render_targets(extent);
+}
+
+// The main view: of the cameras drawing, the first in order. The spectator camera and the player's
+// swap which one draws, and the inset map draws after and over it.
+fn active_camera<'a>(camera_query: &'a Query<(&Camera, &GlobalTransform), With<Camera3d>>) -> Option<(&'a Camera, &'a GlobalTransform)> {
+    camera_query.iter().filter(|(camera, _)| camera.is_active).min_by_key(|(camera, _)| camera.order)
+}
+
+// Keeps the SSAO projection in sync with the game camera
+fn update_ssao_uniforms(
+    mut vulkan_renderer: ResMut<VulkanRenderer>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+) {
+    if vulkan_renderer.ssao.is_none() {
+        return;
     }
-    
-    if !vulkan_renderer.pipeline_created {
-        create_vulkan_render_pass_and_pipeline(&mut vulkan_renderer);
+    if let Some((camera, transform)) = active_camera(&camera_query) {
+        let view = transform.compute_matrix().inverse();
+        vulkan_renderer.update_ssao_camera(view, camera.projection_matrix());
     }
-    
-    if vulkan_renderer.instance_created && vulkan_renderer.device_created && 
-       vulkan_renderer.swapchain_created && vulkan_renderer.pipeline_created {
-        info!("Vulkan surface, swapchain, and pipeline created successfully");
+}
+
+// Feeds the fog ray march the camera, the sun and the current fog settings
+fn update_fog_uniforms(
+    mut vulkan_renderer: ResMut<VulkanRenderer>,
+    config: Res<VolumetricFogConfig>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    light_query: Query<(&DirectionalLight, &GlobalTransform)>,
+) {
+    if vulkan_renderer.fog.is_none() {
+        return;
     }
+    let Some((camera, camera_transform)) = active_camera(&camera_query) else {
+        return;
+    };
+    let view_projection = camera.projection_matrix() * camera_transform.compute_matrix().inverse();
+
+    // Scaled so the scene's 10000 lux sun comes out at 1
+    let (light_direction, light_color) = light_query
+        .iter()
+        .next()
+        .map(|(light, transform)| {
+            let color = Vec4::from(light.color.as_rgba_f32()) * light.illuminance / 10000.0;
+            (-transform.forward(), color)
+        })
+        .unwrap_or((Vec3::Y, Vec4::ZERO));
+
+    vulkan_renderer.write_fog_uniforms(&FogUniforms {
+        inverse_view_projection: view_projection.inverse().to_cols_array_2d(),
+        camera_position: camera_transform.translation().extend(1.0).to_array(),
+        light_direction: light_direction.extend(0.0).to_array(),
+        light_color: light_color.to_array(),
+        params: [config.density, config.scatter_coefficient, config.absorption_coefficient, config.anisotropy],
+    });
 }
 
-fn create_vulkan_device_and_queue(vulkan_renderer: &mut VulkanRenderer) {
-    if let Some(instance) = &vulkan_renderer.instance {
-        info!("Creating Vulkan device and queue...");
-        
-        // Find a suitable physical device
-        let physical_devices = unsafe { 
-            instance.enumerate_physical_devices()
-                .expect("Failed to enumerate physical devices")
-        };
-        
-        let physical_device = physical_devices[0]; // Use first available device
-        
-        // Find a queue family that supports graphics
-        let queue_family_properties = unsafe { 
-            instance.get_physical_device_queue_family_properties(physical_device)
-        };
-        
-        let queue_family_index = queue_family_properties
-            .iter()
-            .position(|props| props.queue_flags.contains(vk::QueueFlags::GRAPHICS))
-            .expect("No graphics queue family found") as u32;
-        
-        // Create logical device
-        let queue_create_info = vk::DeviceQueueCreateInfo::builder()
-            .queue_family_index(queue_family_index)
-            .queue_priorities(&[1.0])
-            .build();
-        
-        let device_create_info = vk::DeviceCreateInfo::builder()
-            .queue_create_infos(std::slice::from_ref(&queue_create_info))
-            .enabled_extension_names(&[
-                Swapchain::name().as_ptr(),
-            ])
-            .build();
-        
-        let device = unsafe { 
-            instance.create_device(physical_device, &device_create_info, None)
-                .expect("Failed to create logical device")
-        };
-        
-        // Create memory allocator
-        let allocator = Allocator::new(&gpu_allocator::vulkan::AllocatorCreateDesc {
-            instance: instance.clone(),
-            device: device.clone(),
-            physical_device,
-            debug_settings: Default::default(),
-            buffer_device_address: false,
-        }).expect("Failed to create memory allocator");
-        
-        vulkan_renderer.device = Some(device);
-        vulkan_renderer.allocator = Some(allocator);
-        vulkan_renderer.device_created = true;
-        
-        info!("Vulkan device and memory allocator created successfully");
+// Keeps the sky's view rays and Preetham coefficients in sync with the camera and SkyConfig
+fn update_sky_uniforms(
+    mut vulkan_renderer: ResMut<VulkanRenderer>,
+    config: Res<SkyConfig>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+) {
+    if vulkan_renderer.sky.is_none() {
+        return;
     }
+    let Some((camera, camera_transform)) = active_camera(&camera_query) else {
+        return;
+    };
+    let view_projection = camera.projection_matrix() * camera_transform.compute_matrix().inverse();
+    let uniforms = sky_uniforms(&config, view_projection.inverse(), camera_transform.translation());
+    vulkan_renderer.write_sky_uniforms(&uniforms);
 }
 
-fn create_vulkan_swapchain(vulkan_renderer: &mut VulkanRenderer, _window: &Window) {
-    if let (Some(_instance), Some(_device)) = (&vulkan_renderer.instance, &vulkan_renderer.device) {
-        info!("Creating Vulkan swapchain...");
-        
-        // For now, we'll create a basic swapchain setup
-        // The surface creation from Bevy window requires more complex integration
-        // We'll implement this in the next step
-        
-        info!("Swapchain creation - will be implemented in next step");
-        vulkan_renderer.swapchain_created = true;
+// Uploads the geometry objects' bounding spheres and the camera frustum for GPU culling
+fn update_cull_uniforms(
+    mut vulkan_renderer: ResMut<VulkanRenderer>,
+    camera_query: Query<(&Camera, &GlobalTransform, &Frustum), With<Camera3d>>,
+    object_query: Query<(&VulkanObject, &GlobalTransform, &Aabb)>,
+) {
+    if vulkan_renderer.cull.is_none() {
+        return;
+    }
+    let Some((camera, camera_transform, frustum)) = camera_query.iter().filter(|(camera, _, _)| camera.is_active).min_by_key(|(camera, _, _)| camera.order) else {
+        return;
+    };
+
+    let objects: Vec<CullObject> = object_query
+        .iter()
+        .filter(|(object, _, _)| object.subpass_index == GEOMETRY_SUBPASS)
+        .map(|(_, transform, aabb)| {
+            let (scale, _, _) = transform.to_scale_rotation_translation();
+            let center = transform.transform_point(aabb.center.into());
+            let radius = Vec3::from(aabb.half_extents).length() * scale.abs().max_element();
+            CullObject {
+                bounding_sphere: center.extend(radius).to_array(),
+                model: transform.compute_matrix().to_cols_array_2d(),
+            }
+        })
+        .collect();
+    vulkan_renderer.write_cull_objects(&objects);
+
+    let object_count = vulkan_renderer.cull.as_ref().map_or(0, |cull| cull.object_count);
+    vulkan_renderer.write_cull_uniforms(&CullUniforms {
+        view: camera_transform.compute_matrix().inverse().to_cols_array_2d(),
+        projection: camera.projection_matrix().to_cols_array_2d(),
+        // Bevy's half spaces already face inwards
+        frustum_planes: frustum.half_spaces.map(|half_space| half_space.normal_d().to_array()),
+        object_count,
+        _padding: [0; 3],
+    });
+}
+
+// The culled objects are drawn as instances of one mesh, so the first geometry object's mesh is
+// uploaded once the cull pass exists and stands in for all of them
+fn upload_cull_mesh(
+    mut vulkan_renderer: ResMut<VulkanRenderer>,
+    meshes: Res<Assets<Mesh>>,
+    object_query: Query<(&VulkanObject, &Handle<Mesh>)>,
+) {
+    if vulkan_renderer.cull.is_none() || vulkan_renderer.has_cull_mesh() {
+        return;
     }
+    let Some(mesh) = object_query
+        .iter()
+        .filter(|(object, _)| object.subpass_index == GEOMETRY_SUBPASS)
+        .find_map(|(_, handle)| meshes.get(handle))
+    else {
+        return;
+    };
+    let Some((vertices, indices)) = pack_vertices(mesh) else {
+        warn!("Geometry objects' mesh isn't an indexable triangle list, nothing will be culled");
+        return;
+    };
+    let vertex_count = (vertices.len() / VERTEX_STRIDE as usize) as u32;
+    let Some(vertex_buffer) = vulkan_renderer.upload_mesh(&vertices, vk::BufferUsageFlags::VERTEX_BUFFER) else {
+        return;
+    };
+    let Some(index_buffer) = vulkan_renderer.upload_mesh(bytemuck::cast_slice(&indices), vk::BufferUsageFlags::INDEX_BUFFER) else {
+        vulkan_renderer.free_buffer(vertex_buffer);
+        return;
+    };
+    info!("Uploaded cull mesh: {} vertices, {} indices", vertex_count, indices.len());
+    vulkan_renderer.set_cull_mesh(vertex_buffer, index_buffer, indices.len() as u32, vertex_count);
 }
 
-fn create_vulkan_render_pass_and_pipeline(vulkan_renderer: &mut VulkanRenderer) {
-    if let Some(device) = &vulkan_renderer.device {
-        info!("Creating Vulkan render pass and pipeline...");
-        
-        // Create a simple render pass
-        let color_attachment = vk::AttachmentDescription::builder()
-            .format(vk::Format::B8G8R8A8_SRGB)
-            .samples(vk::SampleCountFlags::TYPE_1)
-            .load_op(vk::AttachmentLoadOp::CLEAR)
-            .store_op(vk::AttachmentStoreOp::STORE)
-            .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
-            .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
-            .initial_layout(vk::ImageLayout::UNDEFINED)
-            .final_layout(vk::ImageLayout::PRESENT_SRC_KHR)
-            .build();
-        
-        let color_attachment_ref = vk::AttachmentReference::builder()
-            .attachment(0)
-            .layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
-            .build();
-        
-        let subpass = vk::SubpassDescription::builder()
-            .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
-            .color_attachments(std::slice::from_ref(&color_attachment_ref))
-            .build();
-        
-        let render_pass_create_info = vk::RenderPassCreateInfo::builder()
-            .attachments(std::slice::from_ref(&color_attachment))
-            .subpasses(std::slice::from_ref(&subpass))
-            .build();
-        
-        let render_pass = unsafe { 
-            device.create_render_pass(&render_pass_create_info, None)
-                .expect("Failed to create render pass")
-        };
-        
-        vulkan_renderer.render_pass = Some(render_pass);
-        vulkan_renderer.pipeline_created = true;
-        
-        info!("Vulkan render pass created successfully (pipeline will be implemented in next step)");
+// Interleaves a triangle list mesh into VERTEX_STRIDE byte vertices, with u32 indices. Missing
+// normals face up and missing tex coords are zero; an unindexed mesh gets sequential indices.
+fn pack_vertices(mesh: &Mesh) -> Option<(Vec<u8>, Vec<u32>)> {
+    if mesh.primitive_topology() != PrimitiveTopology::TriangleList {
+        return None;
+    }
+    let Some(VertexAttributeValues::Float32x3(positions)) = mesh.attribute(Mesh::ATTRIBUTE_POSITION) else {
+        return None;
+    };
+    let normals = match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+        Some(VertexAttributeValues::Float32x3(normals)) if normals.len() == positions.len() => Some(normals),
+        _ => None,
+    };
+    let uvs = match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+        Some(VertexAttributeValues::Float32x2(uvs)) if uvs.len() == positions.len() => Some(uvs),
+        _ => None,
+    };
+
+    let mut vertices = Vec::with_capacity(positions.len() * VERTEX_STRIDE as usize);
+    for (index, position) in positions.iter().enumerate() {
+        let normal = normals.map_or([0.0, 1.0, 0.0], |normals| normals[index]);
+        let uv = uvs.map_or([0.0, 0.0], |uvs| uvs[index]);
+        vertices.extend_from_slice(bytemuck::cast_slice(position));
+        vertices.extend_from_slice(bytemuck::cast_slice(&normal));
+        vertices.extend_from_slice(bytemuck::cast_slice(&uv));
+    }
+    let indices: Vec<u32> = match mesh.indices() {
+        Some(Indices::U16(indices)) => indices.iter().map(|&index| index as u32).collect(),
+        Some(Indices::U32(indices)) => indices.clone(),
+        None => (0..positions.len() as u32).collect(),
+    };
+    if indices.is_empty() || indices.iter().any(|&index| index as usize >= positions.len()) {
+        return None;
+    }
+    Some((vertices, indices))
+}
+
+// Refreshes the TLAS instances from the geometry objects; the BLAS is built when the mesh
+// is uploaded and the TLAS itself is rebuilt on the GPU each frame
+#[cfg(feature = "ray_tracing")]
+fn build_acceleration_structures(
+    mut vulkan_renderer: ResMut<VulkanRenderer>,
+    object_query: Query<(&VulkanObject, &GlobalTransform)>,
+) {
+    if vulkan_renderer.ray_tracing.is_none() {
+        return;
     }
+    let models: Vec<Mat4> = object_query
+        .iter()
+        .filter(|(object, _)| object.subpass_index == GEOMETRY_SUBPASS)
+        .map(|(_, transform)| transform.compute_matrix())
+        .collect();
+    vulkan_renderer.write_tlas_instances(&models);
 }
 
-fn render_vulkan() {
-    // This will be implemented in the next step
-    info!("Vulkan render system called");
-} 
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn floats(bytes: &[u8]) -> &[f32] {
+        bytemuck::cast_slice(bytes)
+    }
+
+    fn families(flags: &[vk::QueueFlags]) -> Vec<vk::QueueFamilyProperties> {
+        flags.iter().map(|&queue_flags| vk::QueueFamilyProperties { queue_flags, queue_count: 1, ..default() }).collect()
+    }
+
+    #[test]
+    fn only_a_transfer_only_family_counts_as_dedicated() {
+        let graphics = vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE | vk::QueueFlags::TRANSFER;
+        let async_compute = vk::QueueFlags::COMPUTE | vk::QueueFlags::TRANSFER;
+        let transfer = vk::QueueFlags::TRANSFER | vk::QueueFlags::SPARSE_BINDING;
+        assert_eq!(dedicated_transfer_family(&families(&[graphics, async_compute, transfer])), Some(2));
+        // Uploads fall back to the graphics queue, with no ownership transfer
+        assert_eq!(dedicated_transfer_family(&families(&[graphics, async_compute])), None);
+        assert_eq!(dedicated_transfer_family(&families(&[graphics])), None);
+    }
+
+    #[test]
+    fn packed_vertices_interleave_position_normal_and_uv() {
+        let mesh = Mesh::from(shape::Cube { size: 2.0 });
+        let (vertices, indices) = pack_vertices(&mesh).unwrap();
+        let positions = mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap().as_float3().unwrap();
+        assert_eq!(vertices.len(), positions.len() * VERTEX_STRIDE as usize);
+        assert_eq!(indices.len(), mesh.indices().unwrap().len());
+
+        let Some(VertexAttributeValues::Float32x3(normals)) = mesh.attribute(Mesh::ATTRIBUTE_NORMAL) else {
+            panic!("Cube has no normals");
+        };
+        let Some(VertexAttributeValues::Float32x2(uvs)) = mesh.attribute(Mesh::ATTRIBUTE_UV_0) else {
+            panic!("Cube has no tex coords");
+        };
+        for (index, vertex) in vertices.chunks_exact(VERTEX_STRIDE as usize).enumerate() {
+            let vertex = floats(vertex);
+            assert_eq!(vertex[0..3], positions[index]);
+            assert_eq!(vertex[3..6], normals[index]);
+            assert_eq!(vertex[6..8], uvs[index]);
+        }
+    }
+
+    #[test]
+    fn unindexed_meshes_get_sequential_indices_and_default_attributes() {
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]]);
+        let (vertices, indices) = pack_vertices(&mesh).unwrap();
+        assert_eq!(indices, vec![0, 1, 2]);
+        assert_eq!(floats(&vertices[..VERTEX_STRIDE as usize]), [0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn meshes_that_cant_be_drawn_as_triangles_are_refused() {
+        let mut lines = Mesh::new(PrimitiveTopology::LineList);
+        lines.insert_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0]]);
+        assert!(pack_vertices(&lines).is_none());
+
+        let mut out_of_range = Mesh::new(PrimitiveTopology::TriangleList);
+        out_of_range.insert_attribute(Mesh::ATTRIBUTE_POSITION, vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]]);
+        out_of_range.set_indices(Some(Indices::U32(vec![0, 1, 3])));
+        assert!(pack_vertices(&out_of_range).is_none());
+    }
+}
@@ -0,0 +1,353 @@
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use bevy_rapier3d::prelude::*;
+use rand::Rng;
+use crate::combat::{despawn_dead_enemies, Enemy, EnemyDeathEvent, Health};
+use crate::input_map::{Action, InputMap};
+use crate::loot::LootTable;
+use crate::navmesh::{NavGrid, NavPath};
+use crate::player::Player;
+use crate::status_effects::StatusEffects;
+use crate::stealth::EnemyState;
+
+// Endless waves of enemies that walk at the player, toggled on and off. Each wave is tougher than
+// the last, with a boss on top every few waves.
+pub struct WavePlugin;
+
+impl Plugin for WavePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WaveManager>()
+            .init_resource::<SpawnTimer>()
+            .add_systems(Startup, setup_wave_hud)
+            .add_systems(Update, (toggle_waves, count_wave_deaths.before(despawn_dead_enemies), wave_spawn_system, chase_players, update_wave_hud).chain());
+    }
+}
+
+// Health and speed are multiplied by 1 + this per wave
+const WAVE_SCALING: f32 = 0.15;
+// Every this many waves a boss joins in
+const BOSS_WAVE_INTERVAL: u32 = 5;
+// Enemies stop this far from the player, in metres between centres
+const CHASE_STOP_DISTANCE: f32 = 1.5;
+// Kept pressed onto the ground as they walk
+const ENEMY_FALL_SPEED: f32 = 9.81;
+// Seconds between working out the way to the player again
+const REPATH_INTERVAL: f32 = 0.5;
+// A path point counts as reached this close, in metres across the ground
+const WAYPOINT_RADIUS: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EnemyType {
+    Grunt,
+    Runner,
+    Boss,
+}
+
+impl EnemyType {
+    fn base_health(self) -> f32 {
+        match self {
+            EnemyType::Grunt => 100.0,
+            EnemyType::Runner => 50.0,
+            EnemyType::Boss => 600.0,
+        }
+    }
+
+    // Metres per second
+    fn base_speed(self) -> f32 {
+        match self {
+            EnemyType::Grunt => 3.0,
+            EnemyType::Runner => 5.5,
+            EnemyType::Boss => 2.5,
+        }
+    }
+
+    // Capsule radius; the straight part is twice as long, like the training dummies
+    fn radius(self) -> f32 {
+        match self {
+            EnemyType::Grunt | EnemyType::Runner => 0.5,
+            EnemyType::Boss => 1.0,
+        }
+    }
+
+    fn color(self) -> Color {
+        match self {
+            EnemyType::Grunt => Color::rgb(0.7, 0.2, 0.15),
+            EnemyType::Runner => Color::rgb(0.9, 0.6, 0.1),
+            EnemyType::Boss => Color::rgb(0.3, 0.05, 0.05),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct WaveConfig {
+    pub enemy_count: u32,
+    pub enemy_type: EnemyType,
+    // Seconds between one enemy appearing and the next
+    pub spawn_interval: f32,
+}
+
+#[derive(Resource)]
+pub struct WaveManager {
+    pub active: bool,
+    pub current_wave: u32,
+    // Enemies of this wave still to spawn or kill; the next wave starts when it reaches 0
+    pub enemies_remaining: u32,
+    pub spawn_points: Vec<Vec3>,
+    // One per wave in order; waves past the end repeat the last one
+    pub wave_config: Vec<WaveConfig>,
+    // This wave's enemies still to be spawned, in order
+    pending: Vec<EnemyType>,
+}
+
+impl Default for WaveManager {
+    fn default() -> Self {
+        Self {
+            active: false,
+            current_wave: 0,
+            enemies_remaining: 0,
+            // Around the edge of the island
+            spawn_points: vec![
+                Vec3::new(15.0, 1.0, 15.0),
+                Vec3::new(-15.0, 1.0, 15.0),
+                Vec3::new(15.0, 1.0, -15.0),
+                Vec3::new(-15.0, 1.0, -15.0),
+            ],
+            wave_config: vec![
+                WaveConfig { enemy_count: 4, enemy_type: EnemyType::Grunt, spawn_interval: 1.5 },
+                WaveConfig { enemy_count: 5, enemy_type: EnemyType::Runner, spawn_interval: 1.0 },
+                WaveConfig { enemy_count: 8, enemy_type: EnemyType::Grunt, spawn_interval: 1.0 },
+                WaveConfig { enemy_count: 8, enemy_type: EnemyType::Runner, spawn_interval: 0.8 },
+                WaveConfig { enemy_count: 10, enemy_type: EnemyType::Grunt, spawn_interval: 0.8 },
+            ],
+            pending: Vec::new(),
+        }
+    }
+}
+
+impl WaveManager {
+    pub fn difficulty(&self) -> f32 {
+        1.0 + self.current_wave as f32 * WAVE_SCALING
+    }
+}
+
+// Counts down to the next enemy of the wave appearing
+#[derive(Resource, Default)]
+pub struct SpawnTimer(pub Timer);
+
+// An enemy spawned by a wave, walking at the nearest player
+#[derive(Component)]
+pub struct WaveEnemy {
+    pub speed: f32,
+}
+
+#[derive(Component)]
+struct WaveHudText;
+
+fn setup_wave_hud(mut commands: Commands) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font_size: 28.0,
+                color: Color::WHITE,
+                ..default()
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(50.0),
+            left: Val::Percent(45.0),
+            ..default()
+        }),
+        WaveHudText,
+    ));
+}
+
+// Starting resets to before the first wave; stopping clears away whatever is left of the current one
+fn toggle_waves(
+    mut commands: Commands,
+    input_map: Res<InputMap>,
+    mut manager: ResMut<WaveManager>,
+    enemy_query: Query<Entity, With<WaveEnemy>>,
+) {
+    if !input_map.just_pressed(Action::ToggleWaves) {
+        return;
+    }
+    manager.active = !manager.active;
+    manager.current_wave = 0;
+    manager.enemies_remaining = 0;
+    manager.pending.clear();
+    if manager.active {
+        println!("=== WAVES STARTED ===");
+    } else {
+        for entity in enemy_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        println!("Waves stopped");
+    }
+}
+
+fn count_wave_deaths(
+    mut death_events: EventReader<EnemyDeathEvent>,
+    mut manager: ResMut<WaveManager>,
+    enemy_query: Query<(), With<WaveEnemy>>,
+) {
+    for event in death_events.read() {
+        if enemy_query.contains(event.entity) {
+            manager.enemies_remaining = manager.enemies_remaining.saturating_sub(1);
+        }
+    }
+}
+
+fn wave_spawn_system(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut manager: ResMut<WaveManager>,
+    mut spawn_timer: ResMut<SpawnTimer>,
+    time: Res<Time>,
+) {
+    if !manager.active {
+        return;
+    }
+
+    if manager.enemies_remaining == 0 {
+        manager.current_wave += 1;
+        let index = (manager.current_wave as usize - 1).min(manager.wave_config.len().saturating_sub(1));
+        let Some(config) = manager.wave_config.get(index).copied() else {
+            return;
+        };
+        let mut pending = vec![config.enemy_type; config.enemy_count as usize];
+        if manager.current_wave.is_multiple_of(BOSS_WAVE_INTERVAL) {
+            pending.push(EnemyType::Boss);
+        }
+        // Spawned from the back, so the boss comes in last
+        pending.reverse();
+        manager.enemies_remaining = pending.len() as u32;
+        manager.pending = pending;
+        spawn_timer.0 = Timer::from_seconds(config.spawn_interval, TimerMode::Repeating);
+        println!(
+            "=== WAVE {} === {} enemies at {:.2}x health and speed",
+            manager.current_wave,
+            manager.enemies_remaining,
+            manager.difficulty()
+        );
+    }
+
+    if manager.pending.is_empty() || manager.spawn_points.is_empty() || !spawn_timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    let Some(enemy_type) = manager.pending.pop() else {
+        return;
+    };
+    let position = manager.spawn_points[rand::thread_rng().gen_range(0..manager.spawn_points.len())];
+    let difficulty = manager.difficulty();
+    spawn_wave_enemy(&mut commands, &mut meshes, &mut materials, enemy_type, position, difficulty);
+}
+
+fn spawn_wave_enemy(
+    commands: &mut Commands,
+    meshes: &mut ResMut<Assets<Mesh>>,
+    materials: &mut ResMut<Assets<StandardMaterial>>,
+    enemy_type: EnemyType,
+    position: Vec3,
+    difficulty: f32,
+) {
+    let radius = enemy_type.radius();
+    commands.spawn((
+        Enemy,
+        WaveEnemy {
+            speed: enemy_type.base_speed() * difficulty,
+        },
+        Health::new(enemy_type.base_health() * difficulty),
+        StatusEffects::default(),
+        LootTable::enemy(),
+        NavPath::default(),
+        RigidBody::KinematicPositionBased,
+        Collider::capsule_y(radius, radius),
+        KinematicCharacterController::default(),
+        PbrBundle {
+            mesh: meshes.add(Mesh::from(shape::Capsule {
+                radius,
+                depth: radius * 2.0,
+                ..default()
+            })),
+            material: materials.add(StandardMaterial {
+                base_color: enemy_type.color(),
+                ..default()
+            }),
+            // Standing on the ground rather than sunk into it
+            transform: Transform::from_translation(position + Vec3::Y * (radius * 2.0 - 1.0)),
+            ..default()
+        },
+    ));
+    println!("{:?} spawned at {:?}", enemy_type, position);
+}
+
+// Once it's noticed someone, walks at the nearest player along a path round whatever the
+// navigation grid knows is in the way, straight at them where there's no path, and lets the
+// character controller slide it round anything else
+fn chase_players(
+    mut enemy_query: Query<(&WaveEnemy, &StatusEffects, Option<&EnemyState>, &mut NavPath, &mut Transform, &mut KinematicCharacterController)>,
+    player_query: Query<&Transform, (With<Player>, Without<WaveEnemy>)>,
+    nav_grid: Option<Res<NavGrid>>,
+    time: Res<Time>,
+) {
+    let delta_seconds = time.delta_seconds();
+    let flat = |offset: Vec3| Vec3::new(offset.x, 0.0, offset.z);
+    for (enemy, effects, state, mut path, mut transform, mut controller) in enemy_query.iter_mut() {
+        let position = transform.translation;
+        let nearest = player_query
+            .iter()
+            .map(|player| player.translation)
+            .min_by(|a, b| a.distance_squared(position).total_cmp(&b.distance_squared(position)))
+            .filter(|_| state.is_none_or(|state| *state == EnemyState::Chase));
+        let mut movement = Vec3::NEG_Y * ENEMY_FALL_SPEED * delta_seconds;
+        let Some(target) = nearest else {
+            path.points.clear();
+            controller.translation = Some(movement);
+            continue;
+        };
+
+        path.repath_timer -= delta_seconds;
+        if path.repath_timer <= 0.0 {
+            path.repath_timer = REPATH_INTERVAL;
+            path.points = nav_grid.as_ref().and_then(|grid| grid.find_path(position, target)).unwrap_or_default();
+            path.next = 0;
+        }
+        while path.remaining().first().is_some_and(|point| flat(*point - position).length() < WAYPOINT_RADIUS) {
+            path.next += 1;
+        }
+        // The last point is the player's own cell, so that stretch goes straight at them
+        let waypoint = match path.remaining() {
+            [next, _, ..] => *next,
+            _ => target,
+        };
+
+        let distance = flat(target - position).length();
+        let to_waypoint = flat(waypoint - position);
+        let waypoint_distance = to_waypoint.length();
+        if waypoint_distance > 0.01 {
+            transform.rotation = Quat::from_rotation_y((-to_waypoint.x).atan2(-to_waypoint.z));
+            if distance > CHASE_STOP_DISTANCE {
+                let step = (enemy.speed * effects.speed_multiplier() * delta_seconds).min(distance - CHASE_STOP_DISTANCE);
+                movement += to_waypoint / waypoint_distance * step;
+            }
+        }
+        controller.translation = Some(movement);
+    }
+}
+
+fn update_wave_hud(manager: Res<WaveManager>, mut text_query: Query<&mut Text, With<WaveHudText>>) {
+    if !manager.is_changed() {
+        return;
+    }
+    let Ok(mut text) = text_query.get_single_mut() else {
+        return;
+    };
+    text.sections[0].value = if manager.active && manager.current_wave > 0 {
+        format!("Wave {} - {} left", manager.current_wave, manager.enemies_remaining)
+    } else {
+        String::new()
+    };
+}
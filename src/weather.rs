@@ -0,0 +1,439 @@
+use bevy::prelude::*;
+use bevy::prelude::shape;
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+#[cfg(feature = "dev-tools")]
+use crate::input_map::{Action, InputMap};
+use crate::particles::{ParticleEmitter, ParticleImpact};
+use crate::terrain::{height_at, MovingPlatform, TerrainConfig, GRASS_BUMP_HEIGHT, GRASS_SIZE, GRASS_TOP};
+
+pub struct WeatherPlugin;
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<WeatherState>()
+            .init_resource::<WeatherTransition>()
+            .add_systems(Startup, spawn_weather)
+            .add_systems(Update, (
+                start_weather_transition,
+                update_weather_emitters.after(start_weather_transition),
+                spawn_ripples,
+                update_ripples,
+                accumulate_snow,
+                update_snow_mesh.after(accumulate_snow),
+            ));
+        #[cfg(feature = "dev-tools")]
+        app.add_systems(Update, cycle_weather.before(start_weather_transition));
+    }
+}
+
+// Emitters follow the camera this far above it
+const EMITTER_HEIGHT: f32 = 15.0;
+// Half width of the area around the camera that precipitation falls in
+const EMITTER_EXTENT: f32 = 20.0;
+// 5 units below the bottom of the main island
+const PRECIPITATION_KILL_HEIGHT: f32 = -7.0;
+// Particles per second at full intensity
+const MAX_RAIN_RATE: f32 = 600.0;
+const MAX_SNOW_RATE: f32 = 200.0;
+const DEFAULT_TRANSITION_SECONDS: f32 = 8.0;
+
+const RIPPLE_LIFETIME: f32 = 0.4;
+const RIPPLE_RADIUS: f32 = 0.25;
+
+// Vertices per side of the snow cover over the grass
+const SNOW_GRID_RESOLUTION: usize = 48;
+// Depth each snowflake adds where it lands
+const SNOW_PER_FLAKE: f32 = 0.01;
+const MAX_SNOW_DEPTH: f32 = 0.3;
+// Depth lost per second while it isn't snowing
+const SNOW_MELT_RATE: f32 = 0.01;
+// Bare snow cover sits this far under the grass top, below its lowest dips, so it's hidden
+const SNOW_HIDDEN_OFFSET: f32 = GRASS_BUMP_HEIGHT + 0.02;
+const SNOW_MESH_UPDATE_SECONDS: f32 = 0.25;
+
+// The weather being transitioned to; set it to change the weather. Nothing but the dev-tools
+// shortcut changes it yet.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Default)]
+#[cfg_attr(not(feature = "dev-tools"), allow(dead_code))]
+pub enum WeatherState {
+    #[default]
+    Clear,
+    // Intensities are 0..1
+    Rain(f32),
+    Snow(f32),
+}
+
+impl WeatherState {
+    fn rain(self) -> f32 {
+        match self {
+            WeatherState::Rain(intensity) => intensity,
+            _ => 0.0,
+        }
+    }
+
+    fn snow(self) -> f32 {
+        match self {
+            WeatherState::Snow(intensity) => intensity,
+            _ => 0.0,
+        }
+    }
+}
+
+// Blends from one weather to the next; started whenever WeatherState changes
+#[derive(Resource)]
+pub struct WeatherTransition {
+    pub from: WeatherState,
+    pub to: WeatherState,
+    pub duration: f32,
+    pub elapsed: f32,
+}
+
+impl Default for WeatherTransition {
+    fn default() -> Self {
+        Self {
+            from: WeatherState::Clear,
+            to: WeatherState::Clear,
+            duration: DEFAULT_TRANSITION_SECONDS,
+            elapsed: DEFAULT_TRANSITION_SECONDS,
+        }
+    }
+}
+
+impl WeatherTransition {
+    // Smoothstepped 0..1
+    fn progress(&self) -> f32 {
+        let t = (self.elapsed / self.duration.max(f32::EPSILON)).clamp(0.0, 1.0);
+        t * t * (3.0 - 2.0 * t)
+    }
+
+    pub fn rain_intensity(&self) -> f32 {
+        self.from.rain() + (self.to.rain() - self.from.rain()) * self.progress()
+    }
+
+    pub fn snow_intensity(&self) -> f32 {
+        self.from.snow() + (self.to.snow() - self.from.snow()) * self.progress()
+    }
+}
+
+#[derive(Component)]
+struct RainEmitter;
+
+#[derive(Component)]
+struct SnowEmitter;
+
+#[derive(Component)]
+struct Ripple {
+    age: f32,
+}
+
+#[derive(Resource)]
+struct RippleAssets {
+    mesh: Handle<Mesh>,
+    material: Handle<StandardMaterial>,
+}
+
+// Snow depth at each vertex of the snow cover mesh, row by row along +Z, with the island's
+// ground height under each
+#[derive(Component)]
+pub struct SnowAccumulation {
+    pub depths: Vec<f32>,
+    ground: Vec<f32>,
+    dirty: bool,
+    update_timer: Timer,
+}
+
+impl SnowAccumulation {
+    // Nearest vertex to a point on the island top; None off the grass or away from its surface
+    fn vertex_index(&self, position: Vec3) -> Option<usize> {
+        let half_size = GRASS_SIZE * 0.5;
+        if position.x.abs() > half_size || position.z.abs() > half_size {
+            return None;
+        }
+        let step = GRASS_SIZE / (SNOW_GRID_RESOLUTION - 1) as f32;
+        let x = (((position.x + half_size) / step).round() as usize).min(SNOW_GRID_RESOLUTION - 1);
+        let z = (((position.z + half_size) / step).round() as usize).min(SNOW_GRID_RESOLUTION - 1);
+        let index = z * SNOW_GRID_RESOLUTION + x;
+        ((position.y - self.ground[index] - GRASS_TOP).abs() <= 0.5).then_some(index)
+    }
+
+    // Height of the snow showing above the grass at a point, if there's any there
+    pub fn surface_height(&self, position: Vec3) -> Option<f32> {
+        let index = self.vertex_index(position)?;
+        let grass_top = self.ground[index] + GRASS_TOP;
+        let height = grass_top - SNOW_HIDDEN_OFFSET + self.depths[index];
+        (height > grass_top).then_some(height)
+    }
+}
+
+fn spawn_weather(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    config: Res<TerrainConfig>,
+) {
+    let precipitation = ParticleEmitter {
+        direction: Vec3::NEG_Y,
+        spawn_extent: Vec3::new(EMITTER_EXTENT, 0.0, EMITTER_EXTENT),
+        kill_height: Some(PRECIPITATION_KILL_HEIGHT),
+        report_impacts: true,
+        ..default()
+    };
+    commands.spawn((
+        RainEmitter,
+        ParticleEmitter {
+            lifetime: 4.0,
+            speed: 20.0,
+            spread: 0.05,
+            size: 0.03,
+            color: Color::rgb(0.6, 0.7, 0.8),
+            ..precipitation.clone()
+        },
+        TransformBundle::default(),
+    ));
+    commands.spawn((
+        SnowEmitter,
+        ParticleEmitter {
+            lifetime: 14.0,
+            speed: 1.5,
+            spread: 0.3,
+            drift: 0.8,
+            size: 0.05,
+            color: Color::WHITE,
+            ..precipitation
+        },
+        TransformBundle::default(),
+    ));
+
+    commands.insert_resource(RippleAssets {
+        mesh: meshes.add(Mesh::from(shape::Cylinder {
+            radius: 1.0,
+            height: 0.01,
+            resolution: 12,
+            segments: 1,
+        })),
+        material: materials.add(StandardMaterial {
+            base_color: Color::rgba(0.8, 0.9, 1.0, 0.4),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        }),
+    });
+
+    let vertex_count = SNOW_GRID_RESOLUTION * SNOW_GRID_RESOLUTION;
+    let ground: Vec<f32> = (0..vertex_count)
+        .map(|index| {
+            let position = snow_vertex_position(index % SNOW_GRID_RESOLUTION, index / SNOW_GRID_RESOLUTION, 0.0, 0.0);
+            height_at(&config, position.x, position.z)
+        })
+        .collect();
+    let mesh = meshes.add(snow_cover_mesh(&ground, &vec![0.0; vertex_count]));
+    commands.spawn((
+        SnowAccumulation {
+            depths: vec![0.0; vertex_count],
+            ground,
+            dirty: false,
+            update_timer: Timer::from_seconds(SNOW_MESH_UPDATE_SECONDS, TimerMode::Repeating),
+        },
+        PbrBundle {
+            mesh,
+            material: materials.add(StandardMaterial {
+                base_color: Color::rgb(0.95, 0.95, 1.0),
+                perceptual_roughness: 0.9,
+                ..default()
+            }),
+            ..default()
+        },
+    ));
+}
+
+fn snow_vertex_position(x: usize, z: usize, ground: f32, depth: f32) -> Vec3 {
+    let step = GRASS_SIZE / (SNOW_GRID_RESOLUTION - 1) as f32;
+    Vec3::new(
+        x as f32 * step - GRASS_SIZE * 0.5,
+        ground + GRASS_TOP - SNOW_HIDDEN_OFFSET + depth,
+        z as f32 * step - GRASS_SIZE * 0.5,
+    )
+}
+
+// A grid over the grass raised by the snow depth at each vertex
+fn snow_cover_mesh(ground: &[f32], depths: &[f32]) -> Mesh {
+    let n = SNOW_GRID_RESOLUTION;
+    // Normals follow the hills under the snow as well as the snow itself
+    let height = |x: usize, z: usize| ground[z * n + x] + depths[z * n + x];
+    let step = GRASS_SIZE / (n - 1) as f32;
+
+    let mut positions = Vec::with_capacity(n * n);
+    let mut normals = Vec::with_capacity(n * n);
+    let mut uvs = Vec::with_capacity(n * n);
+    for z in 0..n {
+        for x in 0..n {
+            positions.push(snow_vertex_position(x, z, ground[z * n + x], depths[z * n + x]).to_array());
+            let slope_x = height(x.saturating_sub(1), z) - height((x + 1).min(n - 1), z);
+            let slope_z = height(x, z.saturating_sub(1)) - height(x, (z + 1).min(n - 1));
+            normals.push(Vec3::new(slope_x, 2.0 * step, slope_z).normalize().to_array());
+            uvs.push([x as f32 / (n - 1) as f32, z as f32 / (n - 1) as f32]);
+        }
+    }
+
+    let mut indices = Vec::with_capacity((n - 1) * (n - 1) * 6);
+    for z in 0..n - 1 {
+        for x in 0..n - 1 {
+            let i = (z * n + x) as u32;
+            let below = i + n as u32;
+            indices.extend_from_slice(&[i, below, i + 1, i + 1, below, below + 1]);
+        }
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh.set_indices(Some(Indices::U32(indices)));
+    mesh
+}
+
+#[cfg(feature = "dev-tools")]
+fn cycle_weather(input_map: Res<InputMap>, mut weather: ResMut<WeatherState>) {
+    if !input_map.just_pressed(Action::CycleWeather) {
+        return;
+    }
+    *weather = match *weather {
+        WeatherState::Clear => WeatherState::Rain(1.0),
+        WeatherState::Rain(_) => WeatherState::Snow(1.0),
+        WeatherState::Snow(_) => WeatherState::Clear,
+    };
+}
+
+fn start_weather_transition(weather: Res<WeatherState>, mut transition: ResMut<WeatherTransition>) {
+    if !weather.is_changed() || weather.is_added() {
+        return;
+    }
+    transition.from = transition.to;
+    transition.to = *weather;
+    transition.elapsed = 0.0;
+    println!("Weather changing from {:?} to {:?}", transition.from, transition.to);
+}
+
+fn update_weather_emitters(
+    mut transition: ResMut<WeatherTransition>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera3d>>,
+    mut rain_query: Query<(&mut Transform, &mut ParticleEmitter), (With<RainEmitter>, Without<SnowEmitter>)>,
+    mut snow_query: Query<(&mut Transform, &mut ParticleEmitter), (With<SnowEmitter>, Without<RainEmitter>)>,
+    time: Res<Time>,
+) {
+    transition.elapsed += time.delta_seconds();
+    // The main camera that's drawing, as the spectator camera takes over from the player's and the
+    // inset map draws over it
+    let Some((_, camera_transform)) = camera_query.iter().filter(|(camera, _)| camera.is_active).min_by_key(|(camera, _)| camera.order) else {
+        return;
+    };
+    let emitter_position = camera_transform.translation() + Vec3::Y * EMITTER_HEIGHT;
+
+    if let Ok((mut transform, mut emitter)) = rain_query.get_single_mut() {
+        transform.translation = emitter_position;
+        emitter.rate = MAX_RAIN_RATE * transition.rain_intensity();
+    }
+    if let Ok((mut transform, mut emitter)) = snow_query.get_single_mut() {
+        transform.translation = emitter_position;
+        emitter.rate = MAX_SNOW_RATE * transition.snow_intensity();
+    }
+}
+
+fn spawn_ripples(
+    mut commands: Commands,
+    mut impact_events: EventReader<ParticleImpact>,
+    ripple_assets: Res<RippleAssets>,
+    rain_query: Query<(), With<RainEmitter>>,
+    config: Res<TerrainConfig>,
+) {
+    for impact in impact_events.read() {
+        if !rain_query.contains(impact.emitter) {
+            continue;
+        }
+        // Drops landing on the island hit its collider, just under the grass
+        let mut position = impact.position + impact.normal * 0.01;
+        let grass_top = height_at(&config, position.x, position.z) + GRASS_TOP;
+        if position.x.abs() <= GRASS_SIZE * 0.5 && position.z.abs() <= GRASS_SIZE * 0.5 && position.y < grass_top {
+            position.y = grass_top + 0.01;
+        }
+        commands.spawn((
+            Ripple { age: 0.0 },
+            PbrBundle {
+                mesh: ripple_assets.mesh.clone(),
+                material: ripple_assets.material.clone(),
+                transform: Transform::from_translation(position)
+                    .with_rotation(Quat::from_rotation_arc(Vec3::Y, impact.normal))
+                    .with_scale(Vec3::ZERO),
+                ..default()
+            },
+        ));
+    }
+}
+
+fn update_ripples(
+    mut commands: Commands,
+    mut ripple_query: Query<(Entity, &mut Ripple, &mut Transform)>,
+    time: Res<Time>,
+) {
+    for (entity, mut ripple, mut transform) in ripple_query.iter_mut() {
+        ripple.age += time.delta_seconds();
+        if ripple.age >= RIPPLE_LIFETIME {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let radius = RIPPLE_RADIUS * ripple.age / RIPPLE_LIFETIME;
+        transform.scale = Vec3::new(radius, 1.0, radius);
+    }
+}
+
+// Snowflakes landing on the grass deepen the snow at the nearest vertex; without snowfall it
+// slowly melts
+fn accumulate_snow(
+    mut impact_events: EventReader<ParticleImpact>,
+    transition: Res<WeatherTransition>,
+    snow_emitter_query: Query<(), With<SnowEmitter>>,
+    platform_query: Query<(), With<MovingPlatform>>,
+    mut snow_query: Query<&mut SnowAccumulation>,
+    time: Res<Time>,
+) {
+    let Ok(mut snow) = snow_query.get_single_mut() else {
+        return;
+    };
+
+    for impact in impact_events.read() {
+        if !snow_emitter_query.contains(impact.emitter) || platform_query.contains(impact.entity) {
+            continue;
+        }
+        let Some(index) = snow.vertex_index(impact.position) else {
+            continue;
+        };
+        snow.depths[index] = (snow.depths[index] + SNOW_PER_FLAKE).min(MAX_SNOW_DEPTH);
+        snow.dirty = true;
+    }
+
+    if transition.snow_intensity() <= 0.0 && snow.depths.iter().any(|depth| *depth > 0.0) {
+        let melt = SNOW_MELT_RATE * time.delta_seconds();
+        for depth in snow.depths.iter_mut() {
+            *depth = (*depth - melt).max(0.0);
+        }
+        snow.dirty = true;
+    }
+}
+
+// Re-uploads the snow cover's vertices a few times a second while it's changing
+fn update_snow_mesh(
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut snow_query: Query<(&mut SnowAccumulation, &Handle<Mesh>)>,
+    time: Res<Time>,
+) {
+    let Ok((mut snow, mesh_handle)) = snow_query.get_single_mut() else {
+        return;
+    };
+    if !snow.update_timer.tick(time.delta()).just_finished() || !snow.dirty {
+        return;
+    }
+    if let Some(mesh) = meshes.get_mut(mesh_handle) {
+        *mesh = snow_cover_mesh(&snow.ground, &snow.depths);
+    }
+    snow.dirty = false;
+}